@@ -1,13 +1,44 @@
+use std::time::Duration;
+
 use crate::db::AuditAction;
+use crate::hardening;
+use crate::i18n;
 use crate::input::keymap::{parse_command, Action};
-use crate::ui::components::MessageType;
+use crate::input::InputMode;
+use crate::ui::components::backups::format_size;
+use crate::ui::components::palette::PaletteRun;
+use crate::ui::components::panes::Pane;
+use crate::ui::components::{MessageType, PaneLayout, SecretDisplay};
 use crate::ui::renderer::View;
+use crate::vault::{compact, cryptoinfo};
 
-use super::config::PendingAction;
-use super::App;
+use super::hooks::SecurityEvent;
+use super::pending;
+use super::{App, AppResult, Cmd};
 
 impl App {
-    pub fn execute_action(&mut self, action: Action) -> Result<bool, Box<dyn std::error::Error>> {
+    pub fn execute_action(&mut self, action: Action) -> AppResult<bool> {
+        if action.is_mutating() && self.vault.is_read_only() {
+            self.set_message("Vault is open read-only", MessageType::Error);
+            return Ok(false);
+        }
+        if !self.authorize(&action) {
+            return Ok(false);
+        }
+        self.run_action(action)
+    }
+
+    /// Run an action that has passed the read-only and re-authentication
+    /// checks
+    pub(super) fn run_action(&mut self, action: Action) -> AppResult<bool> {
+        if action.is_change() {
+            self.repeat_registers.insert(self.view, action.clone());
+        }
+        if self.scroll_detail(&action, 1) {
+            return Ok(false);
+        }
+        self.open_for(&action)?;
+
         match action {
             Action::MoveUp => self.move_list(|ls| ls.move_up())?,
             Action::MoveDown => self.move_list(|ls| ls.move_down())?,
@@ -21,39 +52,96 @@ impl App {
             Action::ShowHelp => self.show_help(),
             Action::ShowTags => self.show_tags()?,
             Action::ShowLogs => self.show_logs()?,
+            Action::ShowTimeline => self.show_timeline()?,
+            Action::ShowFinder => self.show_finder()?,
+            Action::ShowPalette => {
+                self.palette_state.open();
+                self.mode_state.to_palette();
+            }
+            Action::ShowBackups => self.show_backups()?,
+            Action::ShowDupes => self.show_dupes()?,
+            Action::CheckBreaches => self.check_breaches()?,
+            Action::ShowSecurityInfo => self.show_security_info(),
+            Action::ShowTips => self.show_tips(),
+            Action::ShowCryptoInfo => self.show_crypto_info()?,
+            Action::ShowVaultInfo => self.show_vault_info()?,
+            Action::Merge(name) => self.merge_with(&name)?,
             Action::ChangePassword => self.request_password_change(),
+            Action::KeySlotAdd(label) => self.request_key_slot(label),
+            Action::KeySlotRemove(label) => self.initiate_key_slot_removal(label),
+            Action::KeySlotList => self.list_key_slots()?,
 
             Action::Select => self.select_credential()?,
             Action::Back => self.go_back()?,
 
-            Action::CopyPassword => self.copy_secret()?,
+            Action::CopyPassword => self.copy_default()?,
             Action::CopyUsername => self.copy_username()?,
             Action::CopyTotp => self.copy_totp()?,
-            Action::TogglePasswordVisibility => self.toggle_password()?,
+            Action::CopyPublicKey => self.copy_public_key()?,
+            Action::CopyPrivateKey => self.copy_private_key()?,
+            Action::CopyConnectionString => self.open_connection_menu(),
+            Action::OpenUrl => self.initiate_open_url(),
+            Action::CopyField(number) => self.copy_field(number)?,
+            Action::TogglePasswordVisibility => self.toggle_password(SecretDisplay::Full)?,
+            Action::TogglePartialReveal => self.toggle_password(SecretDisplay::LastFour)?,
+
+            Action::ZoomPane => self.zoom_pane(),
+            Action::CyclePaneFocus => self.panes.cycle_focus(),
+            Action::GrowPane => self.resize_pane(|p| p.grow()),
+            Action::ShrinkPane => self.resize_pane(|p| p.shrink()),
+            Action::ResetPanes => self.resize_pane(|p| p.reset()),
 
             Action::Delete => self.initiate_delete(),
             Action::New => self.new_credential(),
             Action::Edit => self.edit_credential()?,
 
-            Action::EnterCommand => self.mode_state.to_command(),
-            Action::EnterSearch => self.mode_state.to_search(),
+            Action::EnterCommand => {
+                self.command_history.reset();
+                self.mode_state.to_command();
+            }
+            Action::EnterSearch => self.start_search(),
+            Action::NextMatch => self.jump_to_match(true)?,
+            Action::PrevMatch => self.jump_to_match(false)?,
+            Action::RepeatChange => return self.repeat_change(),
 
             Action::ExecuteCommand(cmd) => return self.execute_action(parse_command(&cmd)),
             Action::Search(query) => self.search_credentials(&query)?,
             Action::FilterByTag(tag) => self.filter_by_tag(&[tag])?,
+            Action::FilterUnused(days) => self.filter_unused(days)?,
+            Action::ClearFilters => self.clear_filters()?,
+            Action::Sort(order) => self.set_sort_order(order)?,
+            Action::TagAdd(tag) => self.edit_tag(&tag, true)?,
+            Action::TagRemove(tag) => self.edit_tag(&tag, false)?,
+            Action::Export(path) => self.export_vault(&path)?,
+            Action::ExportCsv { path, fields } => self.export_csv(&path, &fields)?,
+            Action::VaultMove(path) => self.move_vault(&path)?,
+            Action::Inspect(path) => self.toggle_inspect(path.as_deref())?,
+            Action::ImportSshKey(path) => self.import_ssh_key(&path)?,
+            Action::Alias(alias) => self.alias_credential(alias.as_deref())?,
+            Action::Unalias => self.unalias_credential()?,
+            Action::OtpImport(path) => self.import_otp_backup(&path)?,
+            Action::OtpExport { format, path } => self.export_otp_backup(format, &path)?,
+            Action::Rotate => self.rotate_credential(),
 
             Action::GeneratePassword => self.generate_and_copy_password()?,
+            Action::GeneratePassphrase(words) => self.generate_and_copy_passphrase(words)?,
 
             Action::Confirm => self.handle_confirm()?,
-            Action::Cancel => self.cancel_pending(),
+            Action::Cancel => self.cancel_pending()?,
 
             Action::Clear => self.set_message("", MessageType::Info),
             Action::Quit => return self.quit(),
             Action::ForceQuit => return Ok(true),
             Action::Lock => self.lock(),
+            Action::SetAutoLock(timeout) => self.set_auto_lock(timeout),
             Action::Refresh => self.refresh_data()?,
             Action::VerifyAudit => self.verify_and_report_audit(),
-            Action::Invalid(cmd) => self.set_message(&format!("Unknown command: {}", cmd), MessageType::Error),
+            Action::Fsck { repair } => self.run_fsck(repair),
+            Action::Compact => self.run_compact(),
+            Action::Privacy(on) => self.set_privacy(on.unwrap_or(!self.privacy))?,
+            Action::SyncPush { force } => self.sync_push(force),
+            Action::SyncPull { force } => self.sync_pull(force),
+            Action::Invalid(cmd) => self.set_message(&i18n::trf("Unknown command: {}", &[&cmd]), MessageType::Error),
 
             _ => {}
         }
@@ -61,12 +149,72 @@ impl App {
         Ok(false)
     }
 
-    fn move_list(&mut self, f: impl FnOnce(&mut crate::ui::components::ListViewState)) -> Result<(), Box<dyn std::error::Error>> {
+    /// Run a normal-mode action with its count. Motions move that many rows
+    /// or pages, `5G` and `5gg` go to the fifth row, match jumps and pane
+    /// resizes repeat, and anything else runs once.
+    pub(super) fn execute_counted(&mut self, action: Action, count: Option<usize>) -> AppResult<bool> {
+        let Some(count) = count else { return self.execute_action(action) };
+        if self.scroll_detail(&action, count) {
+            return Ok(false);
+        }
+        match action {
+            Action::MoveUp => self.move_list(|ls| ls.page_up(count))?,
+            Action::MoveDown => self.move_list(|ls| ls.page_down(count))?,
+            Action::MoveToTop | Action::MoveToBottom => self.move_list(|ls| ls.move_to(count - 1))?,
+            Action::PageUp => self.page_move(|ls, h| ls.page_up(h.saturating_sub(1) * count))?,
+            Action::PageDown => self.page_move(|ls, h| ls.page_down(h.saturating_sub(1) * count))?,
+            Action::HalfPageUp => self.page_move(|ls, h| ls.page_up(h / 2 * count))?,
+            Action::HalfPageDown => self.page_move(|ls, h| ls.page_down(h / 2 * count))?,
+            Action::NextMatch | Action::PrevMatch | Action::GrowPane | Action::ShrinkPane => {
+                for _ in 0..count {
+                    self.execute_action(action.clone())?;
+                }
+            }
+            action => return self.execute_action(action),
+        }
+        Ok(false)
+    }
+
+    /// `.` replays the last change made in the current view
+    fn repeat_change(&mut self) -> AppResult<bool> {
+        let Some(action) = self.repeat_registers.get(&self.view).cloned() else {
+            self.set_message("No change to repeat", MessageType::Info);
+            return Ok(false);
+        };
+        self.execute_action(action)
+    }
+
+    /// In the detail view with the detail pane focused, motions scroll the
+    /// panel rather than move through the list. Returns whether it did.
+    fn scroll_detail(&mut self, action: &Action, count: usize) -> bool {
+        if self.view != View::Detail || self.panes.focus != Pane::Detail {
+            return false;
+        }
+        let (visible, max) = match (self.screen_areas.detail, &self.selected_detail) {
+            (Some(area), Some(detail)) => (area.height.saturating_sub(2) as usize, detail.max_scroll(area)),
+            _ => (0, 0),
+        };
+        let scroll = &mut self.detail_scroll;
+        match action {
+            Action::MoveUp => scroll.scroll_up(count),
+            Action::MoveDown => scroll.scroll_down(count, max),
+            Action::MoveToTop => scroll.home(),
+            Action::MoveToBottom => scroll.end(max),
+            Action::PageUp => scroll.scroll_up(visible.saturating_sub(1) * count),
+            Action::PageDown => scroll.scroll_down(visible.saturating_sub(1) * count, max),
+            Action::HalfPageUp => scroll.scroll_up(visible / 2 * count),
+            Action::HalfPageDown => scroll.scroll_down(visible / 2 * count, max),
+            _ => return false,
+        }
+        true
+    }
+
+    fn move_list(&mut self, f: impl FnOnce(&mut crate::ui::components::ListViewState)) -> AppResult<()> {
         f(&mut self.list_state);
         self.update_selected_detail()
     }
 
-    fn page_move(&mut self, f: impl FnOnce(&mut crate::ui::components::ListViewState, usize)) -> Result<(), Box<dyn std::error::Error>> {
+    fn page_move(&mut self, f: impl FnOnce(&mut crate::ui::components::ListViewState, usize)) -> AppResult<()> {
         let visible = self.list_visible_height();
         f(&mut self.list_state, visible);
         self.update_selected_detail()
@@ -76,13 +224,64 @@ impl App {
         (self.terminal_size.height as usize).saturating_sub(4)
     }
 
+    fn zoom_pane(&mut self) {
+        match self.mode_state.mode {
+            InputMode::Logs => self.logs_state.zoomed = !self.logs_state.zoomed,
+            _ => self.panes.toggle_zoom(),
+        }
+    }
+
+    fn resize_pane(&mut self, f: impl FnOnce(&mut PaneLayout)) {
+        f(&mut self.panes);
+        if self.panes.list_width == self.config.list_pane_width {
+            return;
+        }
+
+        let width = self.panes.list_width;
+        self.config.list_pane_width = width;
+        if let Err(e) = self.config.save(|stored| stored.list_pane_width = width) {
+            self.set_message(&i18n::trf("Failed to save pane size: {}", &[&e]), MessageType::Warning);
+        }
+    }
+
+    /// Apply a new idle timeout now and keep it for later sessions
+    fn set_auto_lock(&mut self, timeout: Duration) {
+        self.vault.set_auto_lock_timeout(timeout);
+        self.config.auto_lock_timeout = timeout;
+        match self.config.save(|stored| stored.auto_lock_timeout = timeout) {
+            Ok(()) => self.set_message(&i18n::trf("Auto-lock after {}", &[&format_timeout(timeout)]), MessageType::Success),
+            Err(e) => self.set_message(&i18n::trf("Auto-lock changed, but failed to save it: {}", &[&e]), MessageType::Warning),
+        }
+    }
+
     fn show_help(&mut self) {
+        self.help_state.clear_query();
         self.help_state.home();
         self.help_state.scroll.pending_g = false;
         self.mode_state.to_help();
     }
 
-    fn show_tags(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn show_security_info(&mut self) {
+        self.security_report = hardening::report();
+        let clipboard = self.clipboard_mitigation();
+        self.security_report.push(clipboard);
+        self.mode_state.to_security();
+    }
+
+    fn show_crypto_info(&mut self) -> AppResult<()> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let info = cryptoinfo::crypto_info(self.vault.db()?.conn())?;
+        self.crypto_info_state.set_info(info);
+        self.crypto_info_state.home();
+        self.crypto_info_state.scroll.pending_g = false;
+        self.mode_state.to_crypto_info();
+        Ok(())
+    }
+
+    fn show_tags(&mut self) -> AppResult<()> {
         if !self.vault.is_unlocked() {
             self.set_message("Vault must be unlocked", MessageType::Error);
             return Ok(());
@@ -93,26 +292,114 @@ impl App {
         Ok(())
     }
 
-    fn show_logs(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn show_logs(&mut self) -> AppResult<()> {
         if !self.vault.is_unlocked() {
             self.set_message("Vault must be unlocked", MessageType::Error);
             return Ok(());
         }
+        self.logs_state.clear_query();
         self.load_audit_logs()?;
         self.logs_state.scroll.pending_g = false;
         self.mode_state.to_logs();
         Ok(())
     }
 
+    fn show_timeline(&mut self) -> AppResult<()> {
+        let Some(cred) = &self.selected_credential else {
+            self.set_message("No credential selected", MessageType::Error);
+            return Ok(());
+        };
+        let (id, name) = (cred.id.clone(), cred.name.clone());
+        self.load_timeline(&id, &name)?;
+        self.mode_state.to_timeline();
+        Ok(())
+    }
+
+    fn show_finder(&mut self) -> AppResult<()> {
+        let db = self.vault.db()?;
+        let all = crate::vault::credential::list_credentials(db.conn())?;
+        self.finder_state.open(&all);
+        self.mode_state.to_finder();
+        Ok(())
+    }
+
+    /// Jump from the finder to the chosen credential's detail view
+    pub(super) fn open_finder_selection(&mut self) -> AppResult<()> {
+        let Some(id) = self.finder_state.selected_id().map(str::to_string) else {
+            return Ok(());
+        };
+        self.finder_state.close();
+        self.mode_state.to_normal();
+
+        self.go_to_credential(&id)?;
+        self.select_credential()
+    }
+
+    /// Close the palette and run its chosen entry, or start typing the
+    /// command when it needs arguments
+    pub(super) fn run_palette_selection(&mut self) -> Option<Action> {
+        let run = self.palette_state.selected_entry()?.run.clone();
+        self.palette_state.close();
+        self.mode_state.to_normal();
+        match run {
+            PaletteRun::Action(action) => Some(action),
+            PaletteRun::Prefill(command) => {
+                self.command_history.reset();
+                self.mode_state.to_command();
+                self.mode_state.set_buffer(&command);
+                None
+            }
+        }
+    }
+
+    /// Clear any filter and move the list cursor to a credential
+    pub(super) fn go_to_credential(&mut self, id: &str) -> AppResult<()> {
+        self.refresh_data()?;
+        let index = self.list_position(id)?;
+        self.list_state.select(index);
+        self.update_selected_detail()
+    }
+
     fn request_password_change(&mut self) {
         if self.vault.is_unlocked() {
-            self.wants_password_change = true;
+            self.emit(Cmd::ChangePassword);
+        } else {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+        }
+    }
+
+    fn request_key_slot(&mut self, label: String) {
+        if self.vault.is_unlocked() {
+            self.emit(Cmd::AddKeySlot(label));
         } else {
             self.set_message("Vault must be unlocked", MessageType::Error);
         }
     }
 
-    fn select_credential(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn initiate_key_slot_removal(&mut self, label: String) {
+        self.request_confirm(pending::RemoveKeySlot(label));
+    }
+
+    pub(super) fn remove_key_slot(&mut self, label: &str) -> AppResult<()> {
+        self.backup_before("keyslot")?;
+        self.vault.remove_key_slot(label)?;
+        self.set_message(&i18n::trf("Key slot '{}' removed", &[&label]), MessageType::Success);
+        Ok(())
+    }
+
+    fn list_key_slots(&mut self) -> AppResult<()> {
+        let active = self.vault.active_slot().to_string();
+        let labels: Vec<String> = self
+            .vault
+            .key_slots()?
+            .into_iter()
+            .map(|l| if l == active { format!("{}*", l) } else { l })
+            .collect();
+        self.set_message(&i18n::trf("Key slots: {}", &[&labels.join(", ")]), MessageType::Info);
+        Ok(())
+    }
+
+    pub(super) fn select_credential(&mut self) -> AppResult<()> {
         if let Some(cred) = &self.selected_credential {
             let (id, name, username) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
             self.log_audit(AuditAction::Read, Some(&id), Some(&name), username.as_deref(), None)?;
@@ -121,69 +408,112 @@ impl App {
         Ok(())
     }
 
-    fn go_back(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn go_back(&mut self) -> AppResult<()> {
         if self.view == View::Detail {
             self.view = View::List;
         }
         self.search_credentials("")
     }
 
-    fn toggle_password(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.password_visible = !self.password_visible;
+    /// Privacy mode masks usernames and URLs, keeps secrets hidden and
+    /// leaves generated passwords and TOTP codes out of messages, for
+    /// sharing the screen
+    fn set_privacy(&mut self, on: bool) -> AppResult<()> {
+        self.privacy = on;
+        if on {
+            self.secret_display = SecretDisplay::Masked;
+            self.update_selected_detail()?;
+        }
+        let message = if on { "Privacy mode on" } else { "Privacy mode off" };
+        self.set_message(message, MessageType::Info);
+        Ok(())
+    }
+
+    /// Show the secret as `display`, or mask it again if it already is
+    fn toggle_password(&mut self, display: SecretDisplay) -> AppResult<()> {
+        if self.privacy {
+            self.set_message("Secrets stay hidden in privacy mode (:privacy off)", MessageType::Info);
+            return Ok(());
+        }
+        self.secret_display = if self.secret_display == display { SecretDisplay::Masked } else { display };
         self.update_selected_detail()?;
 
         if let Some(cred) = &self.selected_credential {
             let (id, name, username) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
-            self.log_audit(AuditAction::Read, Some(&id), Some(&name), username.as_deref(), Some("Toggle Password Visibility"))?;
+            let details = match display {
+                SecretDisplay::LastFour => "Toggle Partial Reveal",
+                _ => "Toggle Password Visibility",
+            };
+            self.log_audit(AuditAction::Read, Some(&id), Some(&name), username.as_deref(), Some(details))?;
         }
         Ok(())
     }
 
     fn initiate_delete(&mut self) {
         let Some(idx) = self.list_state.selected() else { return };
-        let Some(item) = self.credential_items.get(idx) else { return };
+        let Some(item) = self.credential_item_at(idx) else { return };
 
-        self.pending_action = Some(PendingAction::DeleteCredential(item.id.clone()));
-        self.mode_state.to_confirm();
+        let action = pending::DeleteCredential { id: item.id.clone(), name: item.name.clone() };
+        self.request_confirm(action);
     }
 
-    fn cancel_pending(&mut self) {
-        self.pending_action = None;
-        self.mode_state.to_normal();
-    }
-
-    fn handle_confirm(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let Some(action) = self.pending_action.take() else {
-            self.mode_state.to_normal();
-            return Ok(());
-        };
-
-        match action {
-            PendingAction::DeleteCredential(id) => self.delete_credential(&id)?,
-            PendingAction::LockVault => self.confirm_lock(),
-            PendingAction::Quit => self.should_quit = true,
-        }
-
-        self.mode_state.to_normal();
-        Ok(())
-    }
-
-    fn confirm_lock(&mut self) {
-        self.lock();
-        self.set_message("Vault locked", MessageType::Info);
-    }
-
-    fn quit(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+    fn quit(&mut self) -> AppResult<bool> {
+        self.save_session();
         self.should_quit = true;
         Ok(true)
     }
 
     fn verify_and_report_audit(&mut self) {
         let (msg, msg_type) = match self.verify_audit_logs() {
-            Ok((0, total)) => (format!("Audit OK: {} logs verified", total), MessageType::Success),
-            Ok((tampered, total)) => (format!("Warning: {} of {} logs may be tampered!", tampered, total), MessageType::Error),
+            Ok(report) if report.is_intact() => (format!("Audit OK: {} logs verified", report.total), MessageType::Success),
+            Ok(report) => {
+                let breaks: Vec<String> = report.breaks.iter().map(|b| b.to_string()).collect();
+                let msg = format!("Audit log tampered: {}", breaks.join("; "));
+                self.set_message(&msg, MessageType::Error);
+                self.fire_event(SecurityEvent::AuditTampered, &msg);
+                return;
+            }
             Err(e) => (format!("Audit check failed: {}", e), MessageType::Error),
         };
         self.set_message(&msg, msg_type);
     }
+
+    pub(super) fn run_compact(&mut self) {
+        let (msg, msg_type) = match self.vault.db().and_then(|db| compact::compact(db.conn())) {
+            Ok(report) if report.intact => {
+                (format!("Vault compacted, {} reclaimed", format_size(report.reclaimed())), MessageType::Success)
+            }
+            Ok(_) => ("Vault compacted, but the integrity check failed; run :fsck".to_string(), MessageType::Error),
+            Err(e) => (format!("Compact failed: {}", e), MessageType::Error),
+        };
+        self.set_message(&msg, msg_type);
+    }
+
+    fn run_fsck(&mut self, repair: bool) {
+        let report = match self.check_vault(repair) {
+            Ok(report) => report,
+            Err(e) => {
+                self.set_message(&i18n::trf("Integrity check failed: {}", &[&e]), MessageType::Error);
+                return;
+            }
+        };
+        let problems = report.problems();
+        let warnings = report.warnings();
+        if problems.is_empty() && !warnings.is_empty() {
+            self.set_message(&i18n::trf("Vault OK, but: {}", &[&warnings.join("; ")]), MessageType::Warning);
+        } else if problems.is_empty() {
+            self.set_message(&i18n::trf("Vault OK: {}", &[&report.summary()]), MessageType::Success);
+        } else {
+            self.set_message(&i18n::trf("Vault problems: {}", &[&problems.join("; ")]), MessageType::Error);
+        }
+    }
+}
+
+/// A timeout the way `:set autolock` takes it, e.g. `10m`
+pub(super) fn format_timeout(timeout: Duration) -> String {
+    match timeout.as_secs() {
+        secs if secs % 3600 == 0 => format!("{}h", secs / 3600),
+        secs if secs % 60 == 0 => format!("{}m", secs / 60),
+        secs => format!("{}s", secs),
+    }
 }
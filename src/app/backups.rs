@@ -0,0 +1,321 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use zeroize::Zeroizing;
+
+use crate::db::AuditAction;
+use crate::i18n;
+use crate::ui::components::MessageType;
+use crate::ui::renderer::View;
+use crate::vault::backup;
+use crate::vault::csv_export::{self, CsvField};
+use crate::vault::otp_backup::{self, OtpFormat};
+
+use super::hooks::SecurityEvent;
+use super::pending::RestoreBackup;
+use super::{App, AppResult};
+
+/// How often the scheduled backup checks whether one is due
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+impl App {
+    /// Write a backup and apply the retention policy. Called ahead of
+    /// anything that destroys data so the previous state can be restored.
+    pub(crate) fn backup_before(&self, reason: &str) -> AppResult<PathBuf> {
+        if self.vault.inspected().is_some() {
+            return Err("a backup is being inspected; go back with :inspect".into());
+        }
+        let dir = self.config.backup_dir();
+        let path = backup::create_backup(self.vault.db()?.conn(), self.vault.dek()?, &dir, reason)?;
+        backup::prune_backups(&dir, self.config.backup_retention.max(1))?;
+        Ok(path)
+    }
+
+    /// Take a scheduled backup when the newest one is older than the configured interval
+    pub(super) fn run_scheduled_backup(&mut self) {
+        if self.config.backup_interval.is_zero() || !self.vault.is_unlocked() || self.vault.inspected().is_some() {
+            return;
+        }
+        if self.last_backup_check.is_some_and(|t| t.elapsed() < SCHEDULE_CHECK_INTERVAL) {
+            return;
+        }
+        self.last_backup_check = Some(Instant::now());
+
+        if !self.backup_due() {
+            return;
+        }
+        if let Err(e) = self.backup_before("scheduled") {
+            self.set_message(&i18n::trf("Scheduled backup failed: {}", &[&e]), MessageType::Warning);
+        }
+    }
+
+    fn backup_due(&self) -> bool {
+        let Ok(entries) = backup::list_backups(&self.config.backup_dir()) else { return true };
+        let Some(newest) = entries.first() else { return true };
+        let age = Local::now().signed_duration_since(newest.created);
+        age.to_std().is_ok_and(|age| age >= self.config.backup_interval)
+    }
+
+    /// `:export PATH` writes an encrypted copy of the vault, in the same
+    /// format as backups
+    pub(super) fn export_vault(&mut self, path: &str) -> AppResult<()> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let path = expand_home(path);
+        backup::export_backup(self.vault.db()?.conn(), self.vault.dek()?, &path)?;
+
+        let details = path.display().to_string();
+        self.log_audit(AuditAction::Export, None, None, None, Some(&details))?;
+        self.set_message(&i18n::trf("Exported vault to {}", &[&details]), MessageType::Success);
+        self.fire_event(SecurityEvent::Export, &format!("Exported an encrypted copy of the vault to {}", details));
+        Ok(())
+    }
+
+    /// `:export csv PATH` writes the chosen fields of every credential as
+    /// CSV; the audit entry names the fields
+    pub(super) fn export_csv(&mut self, path: &str, fields: &[CsvField]) -> AppResult<()> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let path = expand_home(path);
+        let count = csv_export::export_csv(self.vault.db()?.conn(), self.vault.dek()?, fields, &path)?;
+
+        let labels: Vec<&str> = fields.iter().map(|f| f.label()).collect();
+        let details = format!("CSV {}: {}", path.display(), labels.join(","));
+        self.log_audit(AuditAction::Export, None, None, None, Some(&details))?;
+        let msg = i18n::trf("Exported {} credentials to {}", &[&count, &path.display()]);
+        let msg_type = if fields.iter().any(|f| f.is_secret()) { MessageType::Warning } else { MessageType::Success };
+        self.set_message(&msg, msg_type);
+        self.fire_event(SecurityEvent::Export, &format!("Exported {} credentials as {}", count, details));
+        Ok(())
+    }
+
+    /// `:otpimport PATH` adds the TOTP accounts in an Aegis or andOTP
+    /// backup, skipping keys the vault already has
+    pub(super) fn import_otp_backup(&mut self, path: &str) -> AppResult<()> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let path = expand_home(path);
+        let json = match std::fs::read_to_string(&path) {
+            Ok(text) => Zeroizing::new(text),
+            Err(e) => {
+                self.set_message(&i18n::trf("Cannot read {}: {}", &[&path.display(), &e]), MessageType::Error);
+                return Ok(());
+            }
+        };
+        let report = self.atomic(|app| {
+            let report = otp_backup::import(app.vault.db()?.conn(), app.vault.dek()?, &json)?;
+            let details = format!("{} {}: {} accounts", report.format.name(), path.display(), report.imported);
+            app.log_audit(AuditAction::Import, None, None, None, Some(&details))?;
+            Ok(report)
+        })?;
+        self.refresh_data()?;
+
+        let mut msg = i18n::trf("Imported {} TOTP accounts from {}", &[&report.imported, &report.format.name()]);
+        if report.duplicates > 0 {
+            msg.push_str(&i18n::trf(", {} already in the vault", &[&report.duplicates]));
+        }
+        if report.unsupported > 0 {
+            msg.push_str(&i18n::trf(", {} not TOTP", &[&report.unsupported]));
+        }
+        self.set_message(&msg, MessageType::Success);
+        Ok(())
+    }
+
+    /// `:otpexport FORMAT PATH` writes the TOTP credentials as a plain
+    /// backup the authenticator app can import
+    pub(super) fn export_otp_backup(&mut self, format: OtpFormat, path: &str) -> AppResult<()> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let path = expand_home(path);
+        let count = otp_backup::export_file(self.vault.db()?.conn(), self.vault.dek()?, format, &path)?;
+
+        let details = format!("{} {}", format.name(), path.display());
+        self.log_audit(AuditAction::Export, None, None, None, Some(&details))?;
+        let msg = i18n::trf("Exported {} TOTP accounts to {}; the file is not encrypted", &[&count, &path.display()]);
+        self.set_message(&msg, MessageType::Warning);
+        self.fire_event(SecurityEvent::Export, &format!("Exported {} TOTP accounts unencrypted as {}", count, details));
+        Ok(())
+    }
+
+    /// `:vaultmove PATH` moves the vault file and points the config at
+    /// the new one
+    pub(super) fn move_vault(&mut self, path: &str) -> AppResult<()> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let path = std::path::absolute(expand_home(path))?;
+        let old_path = self.config.vault_path.clone();
+        let old_backups = self.config.backup_dir();
+        let removed = self.vault.relocate(&path)?;
+
+        // Existing backups stay where they are rather than following the vault
+        if self.config.backup_dir.is_none() && old_backups.exists() {
+            self.config.backup_dir = Some(old_backups);
+        }
+        self.config.vault_path = path.clone();
+
+        let backup_dir = self.config.backup_dir.clone();
+        let saved = self.config.save(|stored| {
+            stored.vault_path = path.clone();
+            stored.backup_dir = backup_dir;
+        });
+        let (msg, msg_type) = match (saved, removed) {
+            (Err(e), _) => (
+                format!("Vault moved to {}, but the config could not be saved: {}", path.display(), e),
+                MessageType::Warning,
+            ),
+            (Ok(()), false) => (
+                format!("Vault moved to {}; delete the old file {} by hand", path.display(), old_path.display()),
+                MessageType::Warning,
+            ),
+            (Ok(()), true) => (format!("Vault moved to {}", path.display()), MessageType::Success),
+        };
+        self.set_message(&msg, msg_type);
+        Ok(())
+    }
+
+    pub(super) fn show_backups(&mut self) -> AppResult<()> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        self.load_backups()?;
+        self.backups_state.home();
+        self.backups_state.scroll.pending_g = false;
+        self.mode_state.to_backups();
+        Ok(())
+    }
+
+    fn load_backups(&mut self) -> AppResult<()> {
+        let entries = backup::list_backups(&self.config.backup_dir())?;
+        self.backups_state.set_entries(entries);
+        Ok(())
+    }
+
+    pub(super) fn backup_now(&mut self) {
+        let result = self.backup_before("manual").and_then(|_| self.load_backups());
+        match result {
+            Ok(()) => {
+                self.backups_state.home();
+                self.set_message("Backup created", MessageType::Success);
+            }
+            Err(e) => self.set_message(&i18n::trf("Backup failed: {}", &[&e]), MessageType::Error),
+        }
+    }
+
+    pub(super) fn request_restore(&mut self) {
+        if self.vault.is_read_only() {
+            self.set_message("Vault is open read-only", MessageType::Error);
+            return;
+        }
+        let Some(entry) = self.backups_state.selected_entry() else { return };
+        self.request_confirm(RestoreBackup(entry.path.clone()));
+    }
+
+    /// Browse the selected backup in place of the vault
+    pub(super) fn inspect_selected_backup(&mut self) {
+        let Some(entry) = self.backups_state.selected_entry() else { return };
+        let path = entry.path.clone();
+        if let Err(e) = self.inspect_backup(&path) {
+            self.set_message(&i18n::trf("Cannot open backup: {}", &[&e]), MessageType::Error);
+        }
+    }
+
+    /// `:inspect PATH` shows a backup or export read-only in place of the
+    /// vault, so old secrets can be copied out of it; `:inspect` alone
+    /// goes back to the vault
+    pub(super) fn toggle_inspect(&mut self, path: Option<&str>) -> AppResult<()> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        match path {
+            Some(path) => self.inspect_backup(&expand_home(path)),
+            None if self.vault.close_inspected() => {
+                self.show_current_database()?;
+                self.set_message("Back to the vault", MessageType::Info);
+                Ok(())
+            }
+            None => {
+                self.set_message("No backup is open; :inspect PATH opens one", MessageType::Info);
+                Ok(())
+            }
+        }
+    }
+
+    fn inspect_backup(&mut self, path: &Path) -> AppResult<()> {
+        // Decrypted next to the backups, which only the owner can read
+        let opened = backup::open_backup(self.vault.dek()?, path, &self.config.backup_dir())?;
+        self.vault.close_inspected();
+        self.vault.inspect(opened)?;
+        self.mode_state.to_normal();
+        self.show_current_database()?;
+        let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+        self.set_message(&i18n::trf("Inspecting {} read-only; :inspect to go back", &[&name]), MessageType::Info);
+        Ok(())
+    }
+
+    /// Reload everything shown after the database behind the vault changed
+    fn show_current_database(&mut self) -> AppResult<()> {
+        self.clear_credentials();
+        if self.view == View::Detail {
+            self.view = View::List;
+        }
+        self.list_state.select(Some(0));
+        self.refresh_data()?;
+        self.update_selected_detail()
+    }
+
+    /// Replace the vault with a backup and lock, since the restored file
+    /// may carry a different master password
+    pub(super) fn restore_backup(&mut self, path: &Path) -> AppResult<()> {
+        let snapshot = backup::read_backup(self.vault.dek()?, path)?;
+        self.backup_before("restore")?;
+
+        self.lock();
+        backup::replace_vault_file(&snapshot, &self.config.vault_path)?;
+        self.set_message("Backup restored; unlock with the password it was made under", MessageType::Success);
+        Ok(())
+    }
+}
+
+/// Expand a leading `~` to the home directory
+pub(super) fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ if path == "~" => dirs::home_dir().unwrap_or_else(|| PathBuf::from(path)),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::unlocked_app;
+    use crate::app::AppConfig;
+    use crate::input::keymap::Action;
+
+    #[test]
+    fn test_reads_from_inspected_backup_audited() {
+        let (_dir, mut app) = unlocked_app(AppConfig::default(), &["GitHub"]);
+        let backup = app.backup_before("manual").unwrap();
+        app.inspect_backup(&backup).unwrap();
+        app.execute_action(Action::Select).unwrap();
+
+        let logs = crate::vault::audit::get_all_logs(app.vault.live_db().unwrap().conn()).unwrap();
+        let read = logs.iter().find(|log| log.action == AuditAction::Read).unwrap();
+        assert_eq!(read.credential_name.as_deref(), Some("GitHub"));
+        assert!(read.details.as_deref().unwrap().contains(&backup.display().to_string()));
+        assert!(crate::vault::audit::get_all_logs(app.vault.db().unwrap().conn()).unwrap().is_empty());
+    }
+}
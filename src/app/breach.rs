@@ -0,0 +1,59 @@
+//! Offline Breach Check
+//!
+//! `:breaches` looks up every password in the Bloom filter built by
+//! `vault breach-index build`, without any network access. The first
+//! breached credential is selected and the rest are named in the message;
+//! the detail view marks them beside the strength too.
+
+use crate::i18n;
+use crate::ui::components::MessageType;
+use crate::vault::breach::{self, BreachIndex};
+use crate::vault::credential::DecryptedCredential;
+
+use super::hooks::SecurityEvent;
+use super::{App, AppResult};
+
+/// Breached credentials named in the message before the rest are counted
+const NAMED: usize = 3;
+
+impl App {
+    pub(super) fn check_breaches(&mut self) -> AppResult<()> {
+        let path = self.config.breach_index_path();
+        if !path.exists() {
+            let message = i18n::trf("No breach index at {}; build one with vault breach-index build", &[&path.display()]);
+            self.set_message(&message, MessageType::Info);
+            return Ok(());
+        }
+        let index = BreachIndex::open(&path)?;
+        let (breached, checked) = breach::check_vault(self.vault.db()?.conn(), self.vault.dek()?, &index)?;
+        let Some(first) = breached.first() else {
+            self.set_message(&i18n::trf("None of {} passwords are in the breach index", &[&checked]), MessageType::Success);
+            return Ok(());
+        };
+
+        let mut names: Vec<&str> = breached.iter().take(NAMED).map(|c| c.name.as_str()).collect();
+        let more = format!("{} more", breached.len().saturating_sub(NAMED));
+        if breached.len() > NAMED {
+            names.push(&more);
+        }
+        let message = i18n::trf("{} of {} passwords found in breaches: {}", &[&breached.len(), &checked, &names.join(", ")]);
+        self.go_to_credential(&first.id.clone())?;
+        self.set_message(&message, MessageType::Warning);
+        // Names stay out of what is handed to other programs
+        self.fire_event(SecurityEvent::Breach, &format!("{} of {} passwords found in breaches", breached.len(), checked));
+        Ok(())
+    }
+
+    /// Whether the credential's password is in the breach index, when
+    /// there is one to ask
+    pub(super) fn is_breached(&self, cred: &DecryptedCredential) -> bool {
+        if !breach::is_checked(cred.credential_type) {
+            return false;
+        }
+        let Some(secret) = cred.secret.as_ref().map(secrecy::ExposeSecret::expose_secret).filter(|s| !s.is_empty()) else {
+            return false;
+        };
+        let path = self.config.breach_index_path();
+        path.exists() && BreachIndex::open(&path).and_then(|index| index.is_breached(secret)).unwrap_or(false)
+    }
+}
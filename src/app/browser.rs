@@ -0,0 +1,72 @@
+//! Browser
+//!
+//! Opens credential URLs with the platform's default handler.
+
+use std::process::{Command, Stdio};
+
+/// The URL to hand to the browser: http(s) URLs as stored, bare hosts
+/// with `https://` prepended. Other schemes are refused so a stored URL
+/// cannot launch arbitrary handlers.
+pub fn launch_target(url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty() || url.chars().any(char::is_whitespace) {
+        return None;
+    }
+    match url.split_once("://") {
+        Some((scheme, rest)) if !rest.is_empty() && matches!(scheme.to_ascii_lowercase().as_str(), "http" | "https") => {
+            Some(url.to_string())
+        }
+        Some(_) => None,
+        // `host:port` is fine, `mailto:` and the like are not
+        None => match url.split_once(':') {
+            Some((_, rest)) if !rest.starts_with(|c: char| c.is_ascii_digit()) => None,
+            _ => Some(format!("https://{}", url)),
+        },
+    }
+}
+
+/// Launch the default browser without waiting for it
+pub fn open(url: &str) -> std::io::Result<()> {
+    let mut command = opener(url);
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    command.spawn().map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+fn opener(url: &str) -> Command {
+    let mut command = Command::new("open");
+    command.arg(url);
+    command
+}
+
+#[cfg(windows)]
+fn opener(url: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.args(["/C", "start", ""]).arg(url);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn opener(url: &str) -> Command {
+    let mut command = Command::new("xdg-open");
+    command.arg(url);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch_target() {
+        assert_eq!(launch_target("https://github.com/login").as_deref(), Some("https://github.com/login"));
+        assert_eq!(launch_target(" HTTP://example.com ").as_deref(), Some("HTTP://example.com"));
+        assert_eq!(launch_target("example.com/path").as_deref(), Some("https://example.com/path"));
+        assert_eq!(launch_target("localhost:8080").as_deref(), Some("https://localhost:8080"));
+        assert_eq!(launch_target("javascript:alert(1)"), None);
+        assert_eq!(launch_target("file:///etc/passwd"), None);
+        assert_eq!(launch_target("ssh://git@host"), None);
+        assert_eq!(launch_target("two words"), None);
+        assert_eq!(launch_target(""), None);
+    }
+}
@@ -1,57 +1,480 @@
+//! Clipboard
+//!
+//! Copies go to the clipboard, the primary selection, or are typed into
+//! the focused window, and are cleared after a timeout. Clipboard
+//! managers keep their own history of what is copied, so the ones
+//! running are looked for on the first copy and named in a warning;
+//! `clipboard_purge_history` has them drop it when the clipboard is
+//! cleared.
+
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::Duration;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
 use zeroize::Zeroize;
 
+use crate::hardening::Mitigation;
+use crate::i18n;
+use crate::ui::components::tips::Tip;
+use crate::ui::components::MessageType;
+
+use super::App;
+
 pub static CLIPBOARD_COPY_ID: AtomicU64 = AtomicU64::new(0);
 
-pub fn copy_with_timeout(text: &str, timeout: Duration) {
+/// Puts text on the clipboard for `App`; tests swap in one that records it
+pub type Copier = Box<dyn FnMut(&str, CopyOptions) -> Receiver<ClearOutcome>>;
+
+/// How often a copy thread checks on the clipboard while it waits
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Time to focus the window a secret is typed into
+pub const TYPE_DELAY: Duration = Duration::from_secs(3);
+
+/// Where a copy goes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardMode {
+    #[default]
+    Clipboard,
+    /// The X11/Wayland primary selection, pasted with a middle click; most
+    /// clipboard managers leave it alone. Other platforms use the clipboard.
+    Primary,
+    /// Typed into the focused window after `TYPE_DELAY`, with wtype or
+    /// xdotool; no clipboard is used
+    Type,
+}
+
+impl ClipboardMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Clipboard => "clipboard",
+            Self::Primary => "primary",
+            Self::Type => "type",
+        }
+    }
+}
+
+/// A clipboard manager, known by its process name
+#[derive(Debug, PartialEq, Eq)]
+pub struct Manager {
+    pub name: &'static str,
+    process: &'static str,
+    /// Command that drops the copied secret from its history, or the
+    /// whole history where that is all it offers
+    purge: Option<&'static [&'static str]>,
+}
+
+const MANAGERS: &[Manager] = &[
+    Manager {
+        name: "Klipper",
+        process: "klipper",
+        purge: Some(&["qdbus", "org.kde.klipper", "/klipper", "org.kde.klipper.klipper.clearClipboardHistory"]),
+    },
+    Manager { name: "CopyQ", process: "copyq", purge: Some(&["copyq", "remove", "0"]) },
+    Manager { name: "GPaste", process: "gpaste-daemon", purge: Some(&["gpaste-client", "empty"]) },
+    Manager { name: "cliphist", process: "cliphist", purge: Some(&["cliphist", "wipe"]) },
+    Manager { name: "clipman", process: "clipman", purge: Some(&["clipman", "clear", "--all"]) },
+    Manager { name: "Clipman", process: "xfce4-clipman", purge: None },
+    Manager { name: "Parcellite", process: "parcellite", purge: None },
+    Manager { name: "ClipIt", process: "clipit", purge: None },
+    Manager { name: "Diodon", process: "diodon", purge: None },
+    Manager { name: "wl-clip-persist", process: "wl-clip-persist", purge: None },
+];
+
+/// A `wl-paste --watch` feeding something not listed above
+const WATCHER: Manager = Manager { name: "wl-paste --watch", process: "wl-paste", purge: None };
+
+/// How a copy is made, from the config
+#[derive(Debug, Clone)]
+pub struct CopyOptions {
+    pub timeout: Duration,
+    pub paste_once: bool,
+    pub mode: ClipboardMode,
+    /// Managers to purge once the clipboard is cleared; empty unless
+    /// `clipboard_purge_history` is set
+    pub purge: Vec<&'static Manager>,
+}
+
+/// What became of a copy, sent once its thread is done. A copy replaced by
+/// a newer one from the app sends nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClearOutcome {
+    /// Cleared after the first paste
+    Pasted,
+    /// Cleared when the timeout ran out
+    Expired,
+    /// Something else was copied meanwhile, so it was left alone
+    Replaced,
+    /// Typed into the focused window
+    Typed,
+    Failed(String),
+}
+
+/// Copy `text` as `options` say, and clear it after the timeout, or after
+/// the first paste with `paste_once` where the platform can tell
+pub fn copy_with_timeout(text: &str, options: CopyOptions) -> Receiver<ClearOutcome> {
     let copy_id = CLIPBOARD_COPY_ID.fetch_add(1, Ordering::SeqCst) + 1;
     let mut text = text.to_string();
+    let (tx, rx) = mpsc::channel();
 
-    std::thread::spawn(move || copy_thread(&mut text, timeout, copy_id));
+    std::thread::spawn(move || {
+        let outcome = copy_thread(&mut text, &options, copy_id);
+        text.zeroize();
+        if let Some(outcome) = outcome {
+            let _ = tx.send(outcome);
+        }
+    });
+    rx
 }
 
+/// Managers among running processes, given each one's name and command
+/// line. Wayland ones run as `wl-paste --watch <manager> store`.
+fn find_managers(processes: impl IntoIterator<Item = (String, String)>) -> Vec<&'static Manager> {
+    let mut found: Vec<&'static Manager> = Vec::new();
+    for (process, cmdline) in processes {
+        let manager = if process == WATCHER.process {
+            if !cmdline.split_whitespace().any(|arg| arg == "--watch" || arg == "-w") {
+                continue;
+            }
+            let fed = cmdline.split_whitespace().find_map(|arg| {
+                let program = arg.rsplit('/').next().unwrap_or(arg);
+                MANAGERS.iter().find(|m| m.process == program)
+            });
+            fed.unwrap_or(&WATCHER)
+        } else {
+            match MANAGERS.iter().find(|m| m.process == process) {
+                Some(manager) => manager,
+                None => continue,
+            }
+        };
+        if !found.contains(&manager) {
+            found.push(manager);
+        }
+    }
+    found
+}
+
+/// Clipboard managers running now, from `/proc`
 #[cfg(target_os = "linux")]
-fn copy_thread(text: &mut String, timeout: Duration, copy_id: u64) {
+fn detect_managers() -> Vec<&'static Manager> {
+    let Ok(entries) = std::fs::read_dir("/proc") else { return Vec::new() };
+    let processes = entries.flatten().filter_map(|entry| {
+        entry.file_name().to_str().filter(|pid| pid.bytes().all(|b| b.is_ascii_digit()))?;
+        let process = std::fs::read_to_string(entry.path().join("comm")).ok()?;
+        let cmdline = std::fs::read(entry.path().join("cmdline")).unwrap_or_default();
+        Some((process.trim().to_string(), String::from_utf8_lossy(&cmdline).replace('\0', " ")))
+    });
+    find_managers(processes)
+}
+
+/// Managers elsewhere keep their history out of reach
+#[cfg(not(target_os = "linux"))]
+fn detect_managers() -> Vec<&'static Manager> {
+    Vec::new()
+}
+
+fn manager_names(managers: &[&Manager]) -> String {
+    managers.iter().map(|m| m.name).collect::<Vec<_>>().join(", ")
+}
+
+/// Advice for `:tips`, starting with what was found on this system
+pub fn security_tips(managers: &[&Manager], mode: ClipboardMode, purge: bool) -> Vec<Tip> {
+    let history = match managers.is_empty() {
+        true => "None found running. Klipper, CopyQ, GPaste, cliphist and the like keep a history of \
+                 everything copied, so a copied secret can outlive clipboard_timeout."
+            .to_string(),
+        false => format!(
+            "Running: {}. They keep a history of everything copied, so a copied secret can outlive \
+             clipboard_timeout. Exclude this terminal in their settings, or use one of the options below.",
+            manager_names(managers)
+        ),
+    };
+    vec![
+        Tip::new("Clipboard managers", history, !managers.is_empty() && mode == ClipboardMode::Clipboard && !purge),
+        Tip::new(
+            "Copy mode",
+            format!(
+                "clipboard_mode is \"{}\". \"primary\" copies to the primary selection only, pasted with a \
+                 middle click, which most managers ignore. \"type\" types the value into the window focused \
+                 {}s after copying, with wtype (Wayland) or xdotool (X11), and uses no clipboard at all.",
+                mode.as_str(),
+                TYPE_DELAY.as_secs()
+            ),
+            false,
+        ),
+        Tip::new(
+            "History purge",
+            format!(
+                "clipboard_purge_history is {}. When on, clearing the clipboard also removes the newest CopyQ \
+                 item, and empties the whole history of Klipper, GPaste, cliphist and clipman.",
+                if purge { "on" } else { "off" }
+            ),
+            false,
+        ),
+        Tip::new(
+            "Paste once",
+            "clipboard_paste_once clears a copy as soon as it has been pasted, on Wayland and X11.",
+            false,
+        ),
+        Tip::new("Privacy", ":privacy on hides secrets, TOTP codes and URLs from the screen and messages.", false),
+        Tip::new("Hardening", ":securityinfo shows which process hardening is in effect.", false),
+    ]
+}
+
+impl App {
+    /// Copy with the configured mode, timeout and paste-once setting
+    pub(super) fn copy_to_clipboard(&mut self, text: &str) {
+        let purge = match self.config.clipboard_purge_history {
+            true => self.clipboard_managers().iter().copied().filter(|m| m.purge.is_some()).collect(),
+            false => Vec::new(),
+        };
+        let options = CopyOptions {
+            timeout: self.config.clipboard_timeout,
+            paste_once: self.config.clipboard_paste_once,
+            mode: self.config.clipboard_mode,
+            purge,
+        };
+        let outcome = (self.copier)(text, options);
+        self.clipboard_outcome = Some(outcome);
+    }
+
+    /// Clipboard managers, looked for once per session
+    fn clipboard_managers(&mut self) -> &[&'static Manager] {
+        self.clipboard_managers.get_or_insert_with(detect_managers)
+    }
+
+    /// Say that `what` was copied, or will be typed, with `copied` as the
+    /// message for a copy. The first copy of a session that a clipboard
+    /// manager may keep says so.
+    pub(super) fn announce_copy(&mut self, what: &str, copied: String) {
+        let warn = !self.clipboard_warned
+            && self.config.clipboard_mode == ClipboardMode::Clipboard
+            && !self.config.clipboard_purge_history;
+        let managers = if warn { manager_names(self.clipboard_managers()) } else { String::new() };
+        let message = match self.config.clipboard_mode {
+            ClipboardMode::Type => i18n::trf("{} will be typed in {}s", &[&what, &TYPE_DELAY.as_secs()]),
+            _ => copied,
+        };
+        if managers.is_empty() {
+            self.set_message(&message, MessageType::Success);
+            return;
+        }
+        self.clipboard_warned = true;
+        let warning = i18n::trf("{}; {} may keep it after the timeout, see :tips", &[&message, &managers]);
+        self.set_message(&warning, MessageType::Warning);
+    }
+
+    /// `:tips`, with the clipboard managers looked for again
+    pub(super) fn show_tips(&mut self) {
+        let managers = detect_managers();
+        let tips = security_tips(&managers, self.config.clipboard_mode, self.config.clipboard_purge_history);
+        self.clipboard_managers = Some(managers);
+        self.tips_state.set_tips(tips);
+        self.mode_state.to_tips();
+    }
+
+    /// Row for `:securityinfo` on whether copies can outlive the timeout
+    pub(super) fn clipboard_mitigation(&mut self) -> Mitigation {
+        let mode = self.config.clipboard_mode;
+        let purge = self.config.clipboard_purge_history;
+        let managers = manager_names(self.clipboard_managers());
+        let (active, detail) = match (managers.is_empty(), mode) {
+            (true, _) => (true, format!("No clipboard manager found, copies go to the {}", mode.as_str())),
+            (false, ClipboardMode::Clipboard) if !purge => (false, format!("{} may keep copies, see :tips", managers)),
+            (false, ClipboardMode::Clipboard) => (true, format!("{} history purged when cleared", managers)),
+            (false, _) => (true, format!("{} running, copies go to the {}", managers, mode.as_str())),
+        };
+        Mitigation::new("Clipboard history", active, detail)
+    }
+
+    /// Say how the last copy was cleared, once it has been
+    pub(super) fn poll_clipboard(&mut self) {
+        let Some(outcome) = self.clipboard_outcome.as_ref().and_then(|rx| rx.try_recv().ok()) else { return };
+        self.clipboard_outcome = None;
+        match outcome {
+            ClearOutcome::Pasted => self.set_message("Clipboard cleared after paste", MessageType::Info),
+            ClearOutcome::Expired => self.set_message("Clipboard cleared", MessageType::Info),
+            ClearOutcome::Replaced => {}
+            ClearOutcome::Typed => self.set_message("Typed into the focused window", MessageType::Info),
+            ClearOutcome::Failed(e) => self.set_message(&e, MessageType::Error),
+        }
+    }
+}
+
+fn superseded(copy_id: u64) -> bool {
+    CLIPBOARD_COPY_ID.load(Ordering::SeqCst) != copy_id
+}
+
+#[cfg(target_os = "linux")]
+const NO_TOOL: &str = "Could not copy: install wl-clipboard (Wayland) or xclip (X11)";
+
+#[cfg(target_os = "linux")]
+const NO_TYPER: &str = "Could not type: install wtype (Wayland) or xdotool (X11)";
+
+#[cfg(target_os = "linux")]
+fn copy_thread(text: &mut String, options: &CopyOptions, copy_id: u64) -> Option<ClearOutcome> {
     let is_wayland = std::env::var("WAYLAND_DISPLAY").is_ok();
+    let primary = match options.mode {
+        ClipboardMode::Type => return type_text(text, is_wayland, copy_id),
+        mode => mode == ClipboardMode::Primary,
+    };
+    let outcome = match options.paste_once {
+        true => copy_until_pasted(text, options.timeout, is_wayland, primary, copy_id),
+        false => copy_then_clear(text, options.timeout, is_wayland, primary, copy_id),
+    };
+    match outcome {
+        Some(ClearOutcome::Pasted | ClearOutcome::Expired) => purge_history(&options.purge).or(outcome),
+        outcome => outcome,
+    }
+}
 
-    let ok = if is_wayland { set_wayland(text) } else { set_x11(text) };
-    if !ok {
-        return;
+#[cfg(target_os = "linux")]
+fn copy_then_clear(text: &mut String, timeout: Duration, is_wayland: bool, primary: bool, copy_id: u64) -> Option<ClearOutcome> {
+    if !set_selection(text, is_wayland, primary) {
+        return Some(ClearOutcome::Failed(NO_TOOL.to_string()));
     }
 
     std::thread::sleep(timeout);
+    if superseded(copy_id) {
+        return None;
+    }
+    // Leave a value copied from elsewhere in the meantime alone
+    let replaced = read_selection(is_wayland, primary).is_some_and(|current| *current != *text);
+    text.zeroize();
+    if replaced {
+        return Some(ClearOutcome::Replaced);
+    }
+
+    Some(match clear_selection(is_wayland, primary) {
+        true => ClearOutcome::Expired,
+        false => ClearOutcome::Failed("Could not clear the clipboard".to_string()),
+    })
+}
+
+/// Serve the text from a clipboard tool that quits after one paste, and
+/// stop it at the timeout if nothing was pasted by then. The tool also
+/// quits when something else is copied.
+#[cfg(target_os = "linux")]
+fn copy_until_pasted(
+    text: &mut String,
+    timeout: Duration,
+    is_wayland: bool,
+    primary: bool,
+    copy_id: u64,
+) -> Option<ClearOutcome> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let (program, once): (&str, &[&str]) = match is_wayland {
+        true => ("wl-copy", &["--paste-once", "--foreground"]),
+        false => ("xclip", &["-loops", "1", "-quiet"]),
+    };
+    let child = Command::new(program)
+        .args(selection_args(is_wayland, primary))
+        .args(once)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else { return Some(ClearOutcome::Failed(NO_TOOL.to_string())) };
+    let written = child.stdin.take().is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
     text.zeroize();
+    if !written {
+        let _ = child.kill();
+        return Some(ClearOutcome::Failed(NO_TOOL.to_string()));
+    }
 
-    if CLIPBOARD_COPY_ID.load(Ordering::SeqCst) != copy_id {
-        return;
+    let deadline = Instant::now() + timeout;
+    loop {
+        if superseded(copy_id) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Some(ClearOutcome::Pasted),
+            // Gone before anything was pasted, e.g. no display to copy to
+            Ok(Some(status)) => return Some(ClearOutcome::Failed(format!("Could not copy: {} failed ({})", program, status))),
+            Ok(None) if Instant::now() >= deadline => break,
+            Ok(None) => std::thread::sleep(POLL_INTERVAL),
+            Err(e) => return Some(ClearOutcome::Failed(e.to_string())),
+        }
     }
 
-    clear_clipboard(is_wayland);
+    let _ = child.kill();
+    let _ = child.wait();
+    Some(match clear_selection(is_wayland, primary) {
+        true => ClearOutcome::Expired,
+        false => ClearOutcome::Failed("Could not clear the clipboard".to_string()),
+    })
 }
 
+/// Type the text into whatever window has focus once `TYPE_DELAY` is up,
+/// unless another copy came first
 #[cfg(target_os = "linux")]
-fn set_wayland(text: &str) -> bool {
+fn type_text(text: &mut String, is_wayland: bool, copy_id: u64) -> Option<ClearOutcome> {
     use std::io::Write;
     use std::process::{Command, Stdio};
 
-    Command::new("wl-copy")
+    std::thread::sleep(TYPE_DELAY);
+    if superseded(copy_id) {
+        return None;
+    }
+    let (program, args): (&str, &[&str]) = match is_wayland {
+        true => ("wtype", &["-"]),
+        false => ("xdotool", &["type", "--clearmodifiers", "--file", "-"]),
+    };
+    let child = Command::new(program)
+        .args(args)
         .stdin(Stdio::piped())
         .stdout(Stdio::null())
         .stderr(Stdio::null())
-        .spawn()
-        .ok()
-        .and_then(|mut c| c.stdin.take()?.write_all(text.as_bytes()).ok())
-        .is_some()
+        .spawn();
+    let Ok(mut child) = child else { return Some(ClearOutcome::Failed(NO_TYPER.to_string())) };
+    // Dropping stdin once written lets the tool see the end of the text
+    let written = child.stdin.take().is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+    text.zeroize();
+    let typed = child.wait().is_ok_and(|status| status.success());
+    Some(match written && typed {
+        true => ClearOutcome::Typed,
+        false => ClearOutcome::Failed(NO_TYPER.to_string()),
+    })
+}
+
+/// Have each manager drop the cleared copy from its history; the first
+/// one that could not is reported
+#[cfg(target_os = "linux")]
+fn purge_history(managers: &[&Manager]) -> Option<ClearOutcome> {
+    use std::process::{Command, Stdio};
+
+    let failed = managers.iter().find(|manager| {
+        let Some((program, args)) = manager.purge.and_then(|purge| purge.split_first()) else { return false };
+        let status = Command::new(program).args(args).stdout(Stdio::null()).stderr(Stdio::null()).status();
+        !status.is_ok_and(|s| s.success())
+    })?;
+    Some(ClearOutcome::Failed(format!("Clipboard cleared, but not {}'s history", failed.name)))
 }
 
+/// wl-clipboard and xclip arguments naming the selection
 #[cfg(target_os = "linux")]
-fn set_x11(text: &str) -> bool {
+fn selection_args(is_wayland: bool, primary: bool) -> &'static [&'static str] {
+    match (is_wayland, primary) {
+        (true, false) => &[],
+        (true, true) => &["--primary"],
+        (false, false) => &["-selection", "clipboard"],
+        (false, true) => &["-selection", "primary"],
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_selection(text: &str, is_wayland: bool, primary: bool) -> bool {
     use std::io::Write;
     use std::process::{Command, Stdio};
 
-    Command::new("xclip")
-        .args(["-selection", "clipboard"])
+    Command::new(if is_wayland { "wl-copy" } else { "xclip" })
+        .args(selection_args(is_wayland, primary))
         .stdin(Stdio::piped())
         .stdout(Stdio::null())
         .stderr(Stdio::null())
@@ -61,32 +484,129 @@ fn set_x11(text: &str) -> bool {
         .is_some()
 }
 
+/// The selection's text, if the tool could read it
 #[cfg(target_os = "linux")]
-fn clear_clipboard(is_wayland: bool) {
+fn read_selection(is_wayland: bool, primary: bool) -> Option<zeroize::Zeroizing<String>> {
+    use std::process::Command;
+
+    let output = if is_wayland {
+        Command::new("wl-paste").arg("--no-newline").args(selection_args(is_wayland, primary)).output()
+    } else {
+        Command::new("xclip").args(selection_args(is_wayland, primary)).arg("-o").output()
+    };
+    let output = output.ok().filter(|o| o.status.success())?;
+    let text = zeroize::Zeroizing::new(output.stdout);
+    String::from_utf8(text.to_vec()).ok().map(zeroize::Zeroizing::new)
+}
+
+#[cfg(target_os = "linux")]
+fn clear_selection(is_wayland: bool, primary: bool) -> bool {
     use std::process::{Command, Stdio};
 
-    if is_wayland {
-        let _ = Command::new("wl-copy").arg("--clear").output();
+    let output = if is_wayland {
+        Command::new("wl-copy").arg("--clear").args(selection_args(is_wayland, primary)).output()
     } else {
-        let _ = Command::new("xclip")
-            .args(["-selection", "clipboard"])
+        Command::new("xclip")
+            .args(selection_args(is_wayland, primary))
             .stdin(Stdio::piped())
-            .output();
-    }
+            .output()
+    };
+    output.is_ok_and(|o| o.status.success())
 }
 
+/// Other platforms cannot tell when something is pasted, so `paste_once`
+/// falls back to the timeout, and have no primary selection, so that
+/// mode uses the clipboard. The clipboard is watched meanwhile and left
+/// alone once something else is copied.
 #[cfg(not(target_os = "linux"))]
-fn copy_thread(text: &mut String, timeout: Duration, copy_id: u64) {
-    let Ok(mut clipboard) = arboard::Clipboard::new() else { return };
+fn copy_thread(text: &mut String, options: &CopyOptions, copy_id: u64) -> Option<ClearOutcome> {
+    use zeroize::Zeroizing;
 
-    if clipboard.set_text(&*text).is_err() {
-        return;
+    if options.mode == ClipboardMode::Type {
+        return Some(ClearOutcome::Failed("Typing needs wtype or xdotool, on Linux".to_string()));
     }
+    let timeout = options.timeout;
 
-    std::thread::sleep(timeout);
-    text.zeroize();
+    let mut clipboard = match arboard::Clipboard::new() {
+        Ok(clipboard) => clipboard,
+        Err(e) => return Some(ClearOutcome::Failed(format!("Could not copy: {}", e))),
+    };
+    if let Err(e) = clipboard.set_text(&*text) {
+        return Some(ClearOutcome::Failed(format!("Could not copy: {}", e)));
+    }
+
+    let holds_text = |clipboard: &mut arboard::Clipboard, text: &str| {
+        Zeroizing::new(clipboard.get_text().unwrap_or_default()).as_str() == text
+    };
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        std::thread::sleep(POLL_INTERVAL);
+        if superseded(copy_id) {
+            return None;
+        }
+        if !holds_text(&mut clipboard, text) {
+            return Some(ClearOutcome::Replaced);
+        }
+    }
+
+    // Clearing can fail quietly, so check and overwrite if it did
+    let _ = clipboard.clear();
+    if holds_text(&mut clipboard, text) {
+        let _ = clipboard.set_text(String::new());
+    }
+    Some(match holds_text(&mut clipboard, text) {
+        true => ClearOutcome::Failed("Could not clear the clipboard".to_string()),
+        false => ClearOutcome::Expired,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::AppConfig;
+
+    #[test]
+    fn test_poll_clipboard_reports_once() {
+        let mut app = App::new(AppConfig::default());
+        let (tx, rx) = mpsc::channel();
+        app.clipboard_outcome = Some(rx);
+        app.poll_clipboard();
+        assert!(app.message.is_none());
+
+        tx.send(ClearOutcome::Failed("Could not clear the clipboard".to_string())).unwrap();
+        app.poll_clipboard();
+        assert!(matches!(&app.message, Some((m, MessageType::Error, _)) if m == "Could not clear the clipboard"));
+        assert!(app.clipboard_outcome.is_none());
+    }
+
+    #[test]
+    fn test_find_managers() {
+        let processes = [
+            ("bash", "bash"),
+            ("copyq", "/usr/bin/copyq"),
+            ("wl-paste", "wl-paste --type text --watch cliphist store"),
+            ("wl-paste", "wl-paste --no-newline"),
+            ("wl-paste", "wl-paste --watch notify-send copied"),
+            ("copyq", "copyq --start-server"),
+        ];
+        let found = find_managers(processes.iter().map(|(p, c)| (p.to_string(), c.to_string())));
+        let names: Vec<&str> = found.iter().map(|m| m.name).collect();
+        assert_eq!(names, ["CopyQ", "cliphist", "wl-paste --watch"]);
+        assert!(find_managers(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_announce_copy_warns_once() {
+        let mut app = App::new(AppConfig::default());
+        app.clipboard_managers = Some(vec![&MANAGERS[1]]);
+        app.announce_copy("Password", "Password copied (15s)".to_string());
+        assert!(matches!(&app.message, Some((m, MessageType::Warning, _)) if m.contains("CopyQ may keep it")));
+        app.announce_copy("Password", "Password copied (15s)".to_string());
+        assert!(matches!(&app.message, Some((m, MessageType::Success, _)) if m == "Password copied (15s)"));
 
-    if CLIPBOARD_COPY_ID.load(Ordering::SeqCst) == copy_id {
-        let _ = clipboard.clear();
+        app.config.clipboard_mode = ClipboardMode::Type;
+        app.announce_copy("Password", "Password copied (15s)".to_string());
+        assert!(matches!(&app.message, Some((m, _, _)) if m == "Password will be typed in 3s"));
+        assert!(app.clipboard_mitigation().active);
     }
 }
@@ -0,0 +1,57 @@
+use crate::db::SortOrder;
+use crate::i18n;
+use crate::input::commands::{complete, Completion};
+use crate::input::InputMode;
+use crate::ui::components::MessageType;
+
+use super::{App, AppResult};
+
+impl App {
+    /// Replace the command line with an older or newer history entry
+    pub(super) fn recall_history(&mut self, older: bool) {
+        if self.mode_state.mode != InputMode::Command {
+            return;
+        }
+        let current = self.mode_state.get_buffer().to_string();
+        let recalled = match older {
+            true => self.command_history.prev(&current),
+            false => self.command_history.next(),
+        };
+        if let Some(cmd) = recalled.map(str::to_string) {
+            self.mode_state.set_buffer(&cmd);
+        }
+    }
+
+    /// Complete the command line; pressing Tab again cycles through the
+    /// other candidates
+    pub(super) fn complete_command(&mut self) -> AppResult<()> {
+        if self.mode_state.mode != InputMode::Command {
+            return Ok(());
+        }
+        let buffer = self.mode_state.get_buffer().to_string();
+        if let Some(completion) = &mut self.completion
+            && completion.current() == buffer
+        {
+            let next = completion.advance().to_string();
+            self.mode_state.set_buffer(&next);
+            return Ok(());
+        }
+
+        let db = self.vault.db()?;
+        let names: Vec<String> = crate::vault::credential::list_credentials(db.conn())?.into_iter().map(|c| c.name).collect();
+        let tags: Vec<String> = crate::vault::search::get_tags_with_counts(db.conn())?.into_iter().map(|(t, _)| t).collect();
+        self.completion = Completion::new(complete(&buffer, &names, &tags));
+        if let Some(completion) = &self.completion {
+            let first = completion.current().to_string();
+            self.mode_state.set_buffer(&first);
+        }
+        Ok(())
+    }
+
+    pub(super) fn set_sort_order(&mut self, order: SortOrder) -> AppResult<()> {
+        self.sort_order = order;
+        self.reload_list()?;
+        self.set_message(&i18n::trf("Sorted by {}", &[&order.as_str()]), MessageType::Info);
+        Ok(())
+    }
+}
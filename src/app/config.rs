@@ -1,10 +1,122 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::strength::DEFAULT_MIN_ENTROPY;
+use crate::crypto::PasswordPolicy;
+use crate::db::models::CustomType;
+use crate::ui::components::list::ListColumn;
+use crate::ui::components::panes::DEFAULT_LIST_WIDTH;
+use crate::ui::theme::{IconSet, TagStyle};
+use crate::vault::search_index::SearchIndexMode;
+
+use super::clipboard::ClipboardMode;
+use super::hooks::EventHook;
+use super::session::StartupView;
+use super::AppResult;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppConfig {
     pub vault_path: PathBuf,
+    #[serde(with = "duration_secs")]
     pub auto_lock_timeout: Duration,
+    #[serde(with = "duration_secs")]
     pub clipboard_timeout: Duration,
+    /// Clear the clipboard after the first paste, where the platform can
+    /// tell; the timeout still applies
+    pub clipboard_paste_once: bool,
+    /// `clipboard`, `primary` for the primary selection only, or `type` to
+    /// type copies into the focused window
+    pub clipboard_mode: ClipboardMode,
+    /// Have running clipboard managers drop their history when the
+    /// clipboard is cleared
+    pub clipboard_purge_history: bool,
+    /// How long before auto-lock the status line starts counting down
+    #[serde(with = "duration_secs")]
+    pub lock_warning: Duration,
+    /// Idle time after which the open credential is wiped from memory and
+    /// the screen, short of locking; 0 disables
+    #[serde(with = "duration_secs")]
+    pub wipe_detail_after: Duration,
+    /// Lock as soon as the terminal reports that it lost focus
+    pub lock_on_focus_loss: bool,
+    /// Width of the list pane in the detail view, as a percentage
+    pub list_pane_width: u16,
+    /// Columns of the credential list, in order; narrow terminals drop the
+    /// least important ones
+    pub list_columns: Vec<ListColumn>,
+    /// Latest audit events shown in the detail view; 0 hides the section
+    pub detail_activity: usize,
+    /// Decrypt the credential under the cursor to show it beside the list;
+    /// off, it is only decrypted once opened, revealed or copied
+    pub preview_decryption: bool,
+    /// Log each credential decrypted for the preview as a `preview` event
+    pub audit_previews: bool,
+    /// Named generator policies, e.g. "banking" or "infra"
+    pub password_policies: BTreeMap<String, PasswordPolicy>,
+    /// Kinds of Custom credential with their own fields
+    pub custom_types: Vec<CustomType>,
+    /// Where encrypted backups go; defaults to `backups/` next to the vault
+    pub backup_dir: Option<PathBuf>,
+    /// How many backups to keep before the oldest are deleted
+    pub backup_retention: usize,
+    /// Minimum time between scheduled backups; 0 disables them
+    #[serde(with = "duration_secs")]
+    pub backup_interval: Duration,
+    /// Bloom filter built by `vault breach-index build`; defaults to
+    /// `breach-index.bloom` next to the vault
+    pub breach_index: Option<PathBuf>,
+    /// scp target for `:sync`, e.g. `me@desktop:.local/share/vault/vault.db`
+    pub sync_remote: Option<String>,
+    /// Open the vault without allowing any changes; also set by `--read-only`
+    pub read_only: bool,
+    /// Destroy the vault key after this many consecutive failed unlocks; 0 never
+    pub wipe_after_failures: u32,
+    /// Estimated entropy in bits a new master password needs
+    pub min_master_entropy: f64,
+    /// Unanswered destructive confirmations are cancelled after this; 0 never
+    #[serde(with = "duration_secs")]
+    pub confirm_timeout: Duration,
+    /// Show what an edit changes and ask before saving it
+    pub confirm_edits: bool,
+    /// `memory` keeps no search index in the vault file and searches one
+    /// built after unlock instead
+    pub search_index: SearchIndexMode,
+    /// Store usernames and URLs encrypted; implies `search_index: memory`
+    pub encrypt_metadata: bool,
+    /// Commands fetching secrets kept outside the vault, by the name a
+    /// credential's `backend` attribute gives; `{}` is the reference
+    pub secret_backends: BTreeMap<String, Vec<String>>,
+    /// Compact the vault after this many deletions; 0 only on `:compact`
+    pub compact_after_deletes: u32,
+    /// Start in privacy mode, as after `:privacy on`
+    pub privacy_mode: bool,
+    /// How long an entered master password covers sensitive actions like
+    /// export and revealing a secret; 0 asks every time
+    #[serde(with = "duration_secs")]
+    pub reauth_grace: Duration,
+    /// JSON file translating the UI, keyed by the English text
+    pub locale: Option<PathBuf>,
+    /// `nerd` or `ascii` icons; `auto` picks ASCII where Nerd Font glyphs
+    /// are unlikely to show
+    pub icons: IconSet,
+    /// Brighter secondary text, and selections and messages marked by more
+    /// than color
+    pub high_contrast: bool,
+    /// `last` to reopen the list as it was left, `all` for every
+    /// credential, or a search to start with, e.g. `tag:favorite`
+    pub startup_view: StartupView,
+    /// Colors and icons of particular tags, drawn as chips in the list,
+    /// the detail view and the tags popup
+    pub tag_styles: BTreeMap<String, TagStyle>,
+    /// Commands and desktop notifications fired on security events
+    pub event_hooks: Vec<EventHook>,
+    /// File this config was read from with `--config`; saved back there
+    #[serde(skip)]
+    pub config_file: Option<PathBuf>,
 }
 
 impl Default for AppConfig {
@@ -18,23 +130,146 @@ impl Default for AppConfig {
             vault_path,
             auto_lock_timeout: Duration::from_secs(300),
             clipboard_timeout: Duration::from_secs(15),
+            clipboard_paste_once: false,
+            clipboard_mode: ClipboardMode::default(),
+            clipboard_purge_history: false,
+            lock_warning: Duration::from_secs(30),
+            wipe_detail_after: Duration::ZERO,
+            lock_on_focus_loss: false,
+            list_pane_width: DEFAULT_LIST_WIDTH,
+            list_columns: ListColumn::ALL.to_vec(),
+            detail_activity: 5,
+            preview_decryption: true,
+            audit_previews: false,
+            password_policies: BTreeMap::new(),
+            custom_types: Vec::new(),
+            backup_dir: None,
+            backup_retention: 10,
+            backup_interval: Duration::from_secs(24 * 60 * 60),
+            breach_index: None,
+            sync_remote: None,
+            read_only: false,
+            wipe_after_failures: 0,
+            min_master_entropy: DEFAULT_MIN_ENTROPY,
+            confirm_timeout: Duration::from_secs(30),
+            confirm_edits: true,
+            search_index: SearchIndexMode::default(),
+            encrypt_metadata: false,
+            secret_backends: BTreeMap::new(),
+            compact_after_deletes: 0,
+            privacy_mode: false,
+            reauth_grace: Duration::from_secs(300),
+            locale: None,
+            icons: IconSet::default(),
+            high_contrast: false,
+            startup_view: StartupView::default(),
+            tag_styles: BTreeMap::new(),
+            event_hooks: Vec::new(),
+            config_file: None,
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub enum PendingAction {
-    DeleteCredential(String),
-    LockVault,
-    Quit,
-}
+impl AppConfig {
+    /// Load the config file, falling back to defaults when it does not exist
+    pub fn load() -> AppResult<Self> {
+        let path = config_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::read(&path)
+    }
+
+    /// Load a config file given with `--config`, which has to exist.
+    /// Settings changed in the app are saved back to it.
+    pub fn load_from(path: &Path) -> AppResult<Self> {
+        if !path.exists() {
+            return Err(format!("No config file at {}", path.display()).into());
+        }
+        let config = Self::read(path)?;
+        Ok(Self { config_file: Some(path.to_path_buf()), ..config })
+    }
+
+    fn read(path: &Path) -> AppResult<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e).into())
+    }
+
+    pub fn backup_dir(&self) -> PathBuf {
+        self.backup_dir.clone().unwrap_or_else(|| {
+            let parent = self.vault_path.parent().unwrap_or(std::path::Path::new("."));
+            parent.join("backups")
+        })
+    }
+
+    pub fn breach_index_path(&self) -> PathBuf {
+        self.breach_index.clone().unwrap_or_else(|| self.vault_path.with_file_name("breach-index.bloom"))
+    }
 
-impl PendingAction {
-    pub fn confirm_message(&self) -> &'static str {
-        match self {
-            Self::DeleteCredential(_) => "Delete this credential?",
-            Self::LockVault => "Lock the vault?",
-            Self::Quit => "Quit Vault?",
+    /// Write `change` to the config file. It is applied to what the file
+    /// holds rather than to this config, which also carries overrides from
+    /// the command line, like `--vault` or `--read-only`, that last only
+    /// for this run.
+    pub fn save(&self, change: impl FnOnce(&mut AppConfig)) -> AppResult<()> {
+        let path = self.config_file.clone().unwrap_or_else(config_path);
+        let mut stored = match path.exists() {
+            true => Self::read(&path)?,
+            false => Self::default(),
+        };
+        change(&mut stored);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
         }
+        std::fs::write(&path, serde_json::to_string_pretty(&stored)?)?;
+        Ok(())
+    }
+}
+
+/// Get config file path (<config dir>/vault/config.json)
+pub fn config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("vault")
+        .join("config.json")
+}
+
+mod duration_secs {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_secs)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_leaves_overrides_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, r#"{"vault_path": "/home/me/vault.db", "auto_lock_timeout": 600}"#).unwrap();
+
+        let mut config = AppConfig::load_from(&path).unwrap();
+        config.vault_path = PathBuf::from("/tmp/other.db");
+        config.read_only = true;
+        config.auto_lock_timeout = Duration::from_secs(60);
+        config.list_pane_width = 40;
+        config.save(|stored| stored.list_pane_width = 40).unwrap();
+
+        let saved = AppConfig::load_from(&path).unwrap();
+        assert_eq!(saved.list_pane_width, 40);
+        assert_eq!(saved.vault_path, PathBuf::from("/home/me/vault.db"));
+        assert!(!saved.read_only);
+        assert_eq!(saved.auto_lock_timeout, Duration::from_secs(600));
     }
 }
@@ -1,50 +1,299 @@
+use std::collections::BTreeMap;
+
+use chrono::Local;
 use secrecy::ExposeSecret;
+use zeroize::Zeroizing;
 
 use crate::crypto::totp::{self, TotpSecret};
-use crate::db::models::{Credential, CredentialType};
-use crate::db::AuditAction;
-use crate::ui::components::{CredentialDetail, CredentialForm, CredentialItem, MessageType};
+use crate::crypto::{Certificate, SshPublicKey};
+use crate::db::models::{Credential, CredentialType, CustomType, CUSTOM_TYPE, EXPIRES_AT};
+use crate::db::{AccessStats, AuditAction, AuditLog, SearchQuery};
+use crate::i18n;
+use crate::ui::components::detail::{ActivityEntry, DetailField};
+use crate::ui::components::{CredentialDetail, CredentialForm, CredentialItem, MessageType, SecretDisplay};
 use crate::ui::renderer::View;
-use crate::vault::credential::DecryptedCredential;
+use crate::ui::theme;
+use crate::vault::connection::{Connection, Scheme};
+use crate::vault::credential::{DecryptedCredential, NewCredential};
+use crate::vault::{audit, backend, expiry};
+use crate::vault::timeline::{changed_fields_details, SECRET_FIELD};
+
+use super::pending::{OpenUrl, SaveEdit};
+use super::{App, AppResult};
+
+/// Rows on each side of the cursor decrypted ahead of time
+const PREFETCH_RADIUS: usize = 32;
+
+/// Rows of the unfiltered list held in memory, centred on the cursor
+const WINDOW_SIZE: usize = 512;
 
-use super::App;
+/// How close the cursor may get to either end of the window before the
+/// rows around it are loaded again
+const WINDOW_MARGIN: usize = 128;
 
 impl App {
-    pub fn refresh_data(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn refresh_data(&mut self) -> AppResult<()> {
         let db = self.vault.db()?;
-        self.credentials = crate::db::get_all_credentials(db.conn())?;
-        self.credential_items = self.credentials.iter().map(|c| credential_to_item(c)).collect();
-        self.list_state.set_total(self.credential_items.len());
+        let total = crate::vault::search::count_credentials(db.conn())?;
+        self.list_state.set_total(total);
+        self.active_query = None;
+        self.load_window()
+    }
+
+    /// Load the page of the unfiltered list around the cursor
+    fn load_window(&mut self) -> AppResult<()> {
+        let db = self.vault.db()?;
+        let start = self.list_state.selected().unwrap_or(0).saturating_sub(WINDOW_SIZE / 2);
+        self.credentials = crate::vault::search::get_page(db.conn(), self.sort_order, start, WINDOW_SIZE)?;
+        self.credential_items = self.credentials.iter().map(|c| credential_to_item(c, &self.config.custom_types)).collect();
+        self.credentials_start = start;
         Ok(())
     }
 
+    /// Move the window along once the cursor nears one of its ends.
+    /// Filtered lists are loaded whole and never move.
+    pub(super) fn ensure_window(&mut self) -> AppResult<()> {
+        let Some(selected) = self.list_state.selected() else { return Ok(()) };
+        if self.active_query.is_some() {
+            return Ok(());
+        }
+        let end = self.credentials_start + self.credentials.len();
+        let near_start = self.credentials_start > 0 && selected < self.credentials_start + WINDOW_MARGIN;
+        let near_end = end < self.list_state.total && selected + WINDOW_MARGIN >= end;
+        match near_start || near_end {
+            true => self.load_window(),
+            false => Ok(()),
+        }
+    }
+
+    /// The credential on a list row, if it is loaded
+    pub fn credential_at(&self, index: usize) -> Option<&Credential> {
+        self.credentials.get(index.checked_sub(self.credentials_start)?)
+    }
+
+    pub(super) fn credential_item_at(&self, index: usize) -> Option<&CredentialItem> {
+        self.credential_items.get(index.checked_sub(self.credentials_start)?)
+    }
+
+    /// List row of a credential under the current filter and order
+    pub(super) fn list_position(&self, id: &str) -> AppResult<Option<usize>> {
+        if self.active_query.is_some() {
+            return Ok(self.credentials.iter().position(|c| c.id == id));
+        }
+        let db = self.vault.db()?;
+        Ok(crate::vault::search::position(db.conn(), self.sort_order, id)?)
+    }
+
     pub fn clear_credentials(&mut self) {
+        self.decrypt_cache.clear();
+        self.search_index.clear();
         self.credentials.clear();
         self.credential_items.clear();
+        self.credentials_start = 0;
         self.selected_credential = None;
         self.selected_detail = None;
+        self.opened = None;
+        self.detail_wiped = false;
+        self.timeline_state.clear();
     }
 
-    pub fn search_credentials(&mut self, query: &str) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn search_credentials(&mut self, query: &str) -> AppResult<()> {
+        let query = match SearchQuery::parse(query) {
+            Ok(q) => q,
+            Err(e) => {
+                self.set_message(&e, MessageType::Error);
+                return Ok(());
+            }
+        };
+
         if query.is_empty() {
             self.refresh_data()?;
             return self.update_selected_detail();
         }
 
+        self.apply_query(query)?;
+        self.update_selected_detail()
+    }
+
+    pub fn clear_filters(&mut self) -> AppResult<()> {
+        if self.active_query.is_none() {
+            return Ok(());
+        }
+        self.refresh_data()?;
+        self.set_message("Filters cleared", MessageType::Info);
+        self.update_selected_detail()
+    }
+
+    pub(super) fn apply_query(&mut self, query: SearchQuery) -> AppResult<()> {
         let db = self.vault.db()?;
-        let results = crate::db::search_credentials(db.conn(), query)?;
-        self.credential_items = results.iter().map(|c| credential_to_item(c)).collect();
+        // A leftover on-disk index would only hold sealed usernames and URLs
+        let mut results = match crate::db::has_fts(db.conn())? && !self.config.encrypt_metadata {
+            true => crate::vault::search::search_filtered(db.conn(), &query)?,
+            false => {
+                // Filter on everything but the text in SQL, then rank by the
+                // in-memory index
+                self.search_index.sync(db.conn(), self.vault.dek()?)?;
+                let filters = SearchQuery { text: String::new(), ..query.clone() };
+                let results = crate::vault::search::search_filtered(db.conn(), &filters)?;
+                self.search_index.search(&query.text, results)
+            }
+        };
+        self.sort_order.apply(&mut results);
+        self.credential_items = results.iter().map(|c| credential_to_item(c, &self.config.custom_types)).collect();
         self.credentials = results;
+        self.credentials_start = 0;
         self.list_state.set_total(self.credential_items.len());
+        self.active_query = Some(query);
+        Ok(())
+    }
+
+    /// Reload the list under the current filters, keeping the selected
+    /// credential selected if it is still listed
+    pub(super) fn reload_list(&mut self) -> AppResult<()> {
+        let selected = self.selected_credential.as_ref().map(|c| c.id.clone());
+        match self.active_query.clone() {
+            Some(query) => self.apply_query(query)?,
+            None => self.refresh_data()?,
+        }
+        let index = match selected {
+            Some(id) => self.list_position(&id)?,
+            None => None,
+        };
+        if index.is_some() {
+            self.list_state.select(index);
+        }
         self.update_selected_detail()
     }
 
-    pub fn filter_by_tag(&mut self, tags: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    /// `:tag add` and `:tag rm` on the selected credential
+    pub(super) fn edit_tag(&mut self, tag: &str, add: bool) -> AppResult<()> {
+        let Some(selected) = &self.selected_credential else {
+            self.set_message("No credential selected", MessageType::Error);
+            return Ok(());
+        };
+        let id = selected.id.clone();
+
         let db = self.vault.db()?;
-        let results = crate::db::get_credentials_by_tag(db.conn(), tags)?;
-        self.credential_items = results.iter().map(|c| credential_to_item(c)).collect();
-        self.credentials = results;
-        self.list_state.set_total(self.credential_items.len());
+        let mut cred = crate::vault::credential::get_credential(db.conn(), &id)?;
+        if cred.tags.iter().any(|t| t == tag) == add {
+            let msg = match add {
+                true => format!("'{}' is already tagged {}", cred.name, tag),
+                false => format!("'{}' is not tagged {}", cred.name, tag),
+            };
+            self.set_message(&msg, MessageType::Info);
+            return Ok(());
+        }
+
+        let mut tags = cred.tags.clone();
+        match add {
+            true => tags.push(tag.to_string()),
+            false => tags.retain(|t| t != tag),
+        }
+        self.atomic(|app| {
+            crate::vault::credential::set_tags(app.vault.db()?.conn(), &mut cred, tags)?;
+            let details = changed_fields_details(&["tags"]);
+            app.log_audit(AuditAction::Update, Some(&id), Some(&cred.name), cred.username.as_deref(), details.as_deref())
+        })?;
+        self.reload_list()?;
+        let msg = match add {
+            true => format!("Tagged '{}' {}", cred.name, tag),
+            false => format!("Removed tag {} from '{}'", tag, cred.name),
+        };
+        self.set_message(&msg, MessageType::Success);
+        Ok(())
+    }
+
+    /// `:sshkey PATH` loads the selected SSH key credential's secret from a
+    /// private key file, with its `.pub` file beside it when there is one
+    pub(super) fn import_ssh_key(&mut self, path: &str) -> AppResult<()> {
+        let Some(selected) = &self.selected_credential else {
+            self.set_message("No credential selected", MessageType::Error);
+            return Ok(());
+        };
+        if selected.credential_type != CredentialType::SshKey {
+            self.set_message("Only SSH key credentials can load a key file", MessageType::Error);
+            return Ok(());
+        }
+        let id = selected.id.clone();
+
+        let path = super::backups::expand_home(path);
+        let private_key = match std::fs::read_to_string(&path) {
+            Ok(text) => Zeroizing::new(text),
+            Err(e) => {
+                self.set_message(&i18n::trf("Cannot read {}: {}", &[&path.display(), &e]), MessageType::Error);
+                return Ok(());
+            }
+        };
+        let mut public_path = path.clone().into_os_string();
+        public_path.push(".pub");
+        let public_line = std::fs::read_to_string(public_path).ok();
+
+        let mut cred = crate::vault::credential::get_credential(self.vault.db()?.conn(), &id)?;
+        let key = self.atomic(|app| {
+            let key = crate::vault::credential::import_ssh_key(
+                app.vault.db()?.conn(),
+                app.vault.dek()?,
+                &mut cred,
+                &private_key,
+                public_line.as_deref(),
+            )?;
+            let details = changed_fields_details(&[SECRET_FIELD, "public key"]);
+            app.log_audit(AuditAction::Update, Some(&id), Some(&cred.name), cred.username.as_deref(), details.as_deref())?;
+            Ok(key)
+        })?;
+        self.reload_list()?;
+        self.set_message(&i18n::trf("Loaded {} key {}", &[&key.kind(), &key.fingerprint()]), MessageType::Success);
+        Ok(())
+    }
+
+    /// `:alias [NAME]` gives the selected credential a short name to look
+    /// it up by, a memorable word when none is given
+    pub(super) fn alias_credential(&mut self, alias: Option<&str>) -> AppResult<()> {
+        let alias = match alias {
+            Some(alias) => alias.to_string(),
+            None => crate::vault::alias::suggest(self.vault.db()?.conn())?,
+        };
+        self.change_alias(Some(alias))
+    }
+
+    /// `:unalias` removes the selected credential's alias
+    pub(super) fn unalias_credential(&mut self) -> AppResult<()> {
+        self.change_alias(None)
+    }
+
+    fn change_alias(&mut self, alias: Option<String>) -> AppResult<()> {
+        let Some(selected) = &self.selected_credential else {
+            self.set_message("No credential selected", MessageType::Error);
+            return Ok(());
+        };
+        let id = selected.id.clone();
+        let mut cred = crate::vault::credential::get_credential(self.vault.db()?.conn(), &id)?;
+        if alias == cred.alias {
+            return Ok(());
+        }
+        self.atomic(|app| {
+            crate::vault::alias::set_alias(app.vault.db()?.conn(), &mut cred, alias)?;
+            let details = changed_fields_details(&["alias"]);
+            app.log_audit(AuditAction::Update, Some(&id), Some(&cred.name), cred.username.as_deref(), details.as_deref())
+        })?;
+        self.reload_list()?;
+        let msg = match &cred.alias {
+            Some(alias) => i18n::trf("{} can now be found as {}", &[&cred.name, alias]),
+            None => i18n::trf("Removed the alias of {}", &[&cred.name]),
+        };
+        self.set_message(&msg, MessageType::Success);
+        Ok(())
+    }
+
+    pub fn filter_unused(&mut self, days: u32) -> AppResult<()> {
+        self.apply_query(SearchQuery::unused_for(days))?;
+        let msg = format!("{} credentials unused for {} days", self.credentials.len(), days);
+        self.set_message(&msg, MessageType::Info);
+        self.update_selected_detail()
+    }
+
+    pub fn filter_by_tag(&mut self, tags: &[String]) -> AppResult<()> {
+        self.apply_query(SearchQuery::with_tags(tags))?;
 
         let msg = match tags.len() {
             1 => format!("Filtered by tag: {}", tags[0]),
@@ -54,31 +303,82 @@ impl App {
         self.update_selected_detail()
     }
 
-    pub fn update_selected_detail(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn update_selected_detail(&mut self) -> AppResult<()> {
         let Some(idx) = self.list_state.selected() else {
             self.selected_detail = None;
             return Ok(());
         };
-        let Some(cred) = self.credentials.get(idx) else {
+        self.ensure_window()?;
+        // Borrows the field alone, leaving the cache free to update
+        let Some(cred) = idx.checked_sub(self.credentials_start).and_then(|i| self.credentials.get(i)) else {
             self.selected_detail = None;
             return Ok(());
         };
 
         let key = self.vault.dek()?;
         let db = self.vault.db()?;
-        let decrypted = crate::vault::credential::decrypt_credential(db.conn(), key, cred, false)?;
+        if self.opened.as_deref().is_some_and(|id| id != cred.id) {
+            self.opened = None;
+        }
+        let opened = self.opened.is_some();
+        let previews = self.config.preview_decryption;
+        self.decrypt_cache.receive();
+        let decrypted = if opened {
+            crate::vault::credential::decrypt_fields(key, cred)?
+        } else if !previews {
+            DecryptedCredential::from_credential(cred, None, None)
+        } else if let Some(cached) = self.decrypt_cache.get(cred) {
+            cached
+        } else {
+            let decrypted = crate::vault::credential::decrypt_preview(key, cred)?;
+            self.decrypt_cache.insert(decrypted.clone());
+            decrypted
+        };
+        let stats = crate::vault::stats::access_stats(db.conn(), &cred.id)?;
+        let activity = match self.config.detail_activity {
+            0 => Vec::new(),
+            limit => audit::get_credential_logs(db.conn(), &cred.id)?.into_iter().take(limit).collect(),
+        };
+        if self.selected_credential.as_ref().is_none_or(|c| c.id != cred.id) {
+            self.detail_scroll.reset();
+        }
 
-        self.selected_detail = Some(build_detail(&decrypted, self.password_visible));
+        let mut detail = build_detail(&decrypted, &stats, &activity, self.secret_display, &self.config.custom_types);
+        detail.breached = self.is_breached(&decrypted);
+        detail.encrypted = !opened;
+        if previews && !opened {
+            self.audit_preview(&decrypted)?;
+        }
+        self.selected_detail = Some(detail);
         self.selected_credential = Some(decrypted);
+        match previews {
+            true => self.prefetch_around(idx),
+            false => Ok(()),
+        }
+    }
+
+    /// Have the rows around the cursor decrypted in the background, so
+    /// the next moves find them cached
+    fn prefetch_around(&mut self, index: usize) -> AppResult<()> {
+        let index = index.saturating_sub(self.credentials_start);
+        let start = index.saturating_sub(PREFETCH_RADIUS);
+        let end = (index + PREFETCH_RADIUS + 1).min(self.credentials.len());
+        self.decrypt_cache.prefetch(self.vault.dek()?, &self.credentials[start..end]);
         Ok(())
     }
 
     pub fn new_credential(&mut self) {
-        self.credential_form = Some(CredentialForm::new());
+        let mut form = CredentialForm::new();
+        form.custom_types = self.config.custom_types.clone();
+        if let Some(name) = self.missed_search() {
+            form.fields[0].value.set(name);
+        }
+        self.credential_form = Some(form);
         self.view = View::Form;
     }
 
-    pub fn edit_credential(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn edit_credential(&mut self) -> AppResult<()> {
+        self.open_selected()?;
         if let Some(cred) = self.selected_credential.clone() {
             self.open_edit_form(&cred);
             return Ok(());
@@ -87,7 +387,7 @@ impl App {
         let Some(idx) = self.list_state.selected() else {
             return Ok(());
         };
-        let Some(cred) = self.credentials.get(idx) else {
+        let Some(cred) = self.credential_at(idx) else {
             return Ok(());
         };
 
@@ -98,30 +398,47 @@ impl App {
         Ok(())
     }
 
+    /// `:rotate` opens the edit form with the secret emptied, ready for
+    /// the new one; the old one is archived when the form is saved
+    pub(super) fn rotate_credential(&mut self) {
+        let Some(cred) = self.selected_credential.clone() else {
+            self.set_message("No credential selected", MessageType::Error);
+            return;
+        };
+        self.open_edit_form(&cred);
+        if let Some(form) = self.credential_form.as_mut() {
+            form.start_rotation(Local::now().date_naive());
+        }
+    }
+
     fn open_edit_form(&mut self, cred: &DecryptedCredential) {
-        let form = CredentialForm::for_edit(
+        let mut form = CredentialForm::for_edit(
             cred.id.clone(),
             cred.name.clone(),
             cred.credential_type,
             cred.username.clone(),
-            cred.secret.as_ref().map(|s| s.expose_secret().to_string()).unwrap_or_default(),
+            cred.secret.as_ref().map(|s| s.expose_secret()).unwrap_or_default(),
             cred.url.clone(),
             cred.tags.clone(),
-            cred.notes.as_ref().map(|s| s.expose_secret().to_string()),
+            cred.notes.as_ref().map(|s| s.expose_secret()),
             self.view.clone(),
         );
+        form.password_policy = cred.password_policy.clone();
+        form.custom_types = self.config.custom_types.clone();
+        form.set_env_var(cred.env_var.clone());
+        form.set_attributes(&cred.attributes);
         self.credential_form = Some(form);
         self.view = View::Form;
     }
 
-    pub fn save_credential_form(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn save_credential_form(&mut self) -> AppResult<()> {
         let form = self.credential_form.take().unwrap();
         let return_to = form.previous_view.clone();
         let editing_id = form.editing_id.clone();
 
         match editing_id {
-            Some(id) => self.do_update_credential(&form, &id)?,
-            None => self.do_create_credential(&form)?,
+            Some(id) => self.atomic(|app| app.do_update_credential(&form, &id))?,
+            None => self.atomic(|app| app.do_create_credential(&form))?,
         }
 
         self.view = return_to;
@@ -129,143 +446,519 @@ impl App {
         self.update_selected_detail()
     }
 
-    fn do_update_credential(&mut self, form: &CredentialForm, id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    fn do_update_credential(&mut self, form: &CredentialForm, id: &str) -> AppResult<()> {
         let db = self.vault.db()?;
         let key = self.vault.dek()?;
 
-        let mut cred = crate::db::get_credential(db.conn(), id)?;
+        let mut cred = crate::vault::credential::get_credential(db.conn(), id)?;
+        let before = crate::vault::credential::decrypt_credential(db.conn(), key, &cred, false)?;
+        let changes = changed_fields(&before, form);
+
         cred.name = form.get_name().to_string();
         cred.credential_type = form.credential_type;
         cred.username = form.get_username();
         cred.url = form.get_url();
         cred.tags = form.get_tags();
+        cred.password_policy = form.password_policy.clone();
+        cred.env_var = form.get_env_var();
+        cred.attributes = form.get_attributes();
+        if changes.contains(&SECRET_FIELD) {
+            cred.public_key = public_key_of(form.credential_type, form.get_secret());
+        }
+        let rotated = form.rotating && changes.contains(&SECRET_FIELD);
+        if rotated {
+            crate::vault::credential::archive_secret(db.conn(), key, &cred)?;
+        }
 
         crate::vault::credential::update_credential(
             db.conn(),
             key,
             &mut cred,
             Some(form.get_secret()),
-            form.get_notes().as_deref(),
+            form.get_notes(),
         )?;
 
-        self.log_audit(AuditAction::Update, Some(id), Some(&cred.name), cred.username.as_deref(), None)?;
-        self.set_message("Credential updated", MessageType::Success);
+        let details = changed_fields_details(&changes);
+        self.log_audit(AuditAction::Update, Some(id), Some(&cred.name), cred.username.as_deref(), details.as_deref())?;
+        if rotated {
+            let archived = crate::vault::credential::secret_history(db.conn(), id)?.len();
+            self.set_message(&i18n::trf("Secret rotated; {} previous kept in history", &[&archived]), MessageType::Success);
+        } else if form.rotating {
+            self.set_message("Secret unchanged, nothing archived", MessageType::Warning);
+        } else {
+            self.set_message("Credential updated", MessageType::Success);
+        }
         Ok(())
     }
 
-    fn do_create_credential(&mut self, form: &CredentialForm) -> Result<(), Box<dyn std::error::Error>> {
+    /// Ask before saving an edit, listing what it changes. Returns whether
+    /// the confirmation is now showing; edits that change nothing are
+    /// saved as before.
+    pub(super) fn confirm_edit(&mut self) -> AppResult<bool> {
+        let Some(form) = &self.credential_form else { return Ok(false) };
+        let Some(id) = form.editing_id.as_deref().filter(|_| self.config.confirm_edits) else { return Ok(false) };
+        let db = self.vault.db()?;
+        let cred = crate::vault::credential::get_credential(db.conn(), id)?;
+        let before = crate::vault::credential::decrypt_credential(db.conn(), self.vault.dek()?, &cred, false)?;
+        let changes = changed_fields(&before, form);
+        if changes.is_empty() {
+            return Ok(false);
+        }
+        let changes = edit_summary(&before, form, &changes, self.privacy);
+        self.request_confirm(SaveEdit { name: form.get_name().to_string(), changes });
+        Ok(true)
+    }
+
+    fn do_create_credential(&mut self, form: &CredentialForm) -> AppResult<()> {
         let db = self.vault.db()?;
         let key = self.vault.dek()?;
 
-        let cred = crate::vault::credential::create_credential(
-            db.conn(),
-            key,
-            form.get_name().to_string(),
-            form.credential_type,
-            form.get_secret(),
-            form.get_username(),
-            form.get_url(),
-            form.get_tags(),
-            form.get_notes().as_deref(),
-        )?;
+        let new = NewCredential {
+            username: form.get_username(),
+            url: form.get_url(),
+            tags: form.get_tags(),
+            notes: form.get_notes(),
+            password_policy: form.password_policy.clone(),
+            ..NewCredential::new(form.get_name().to_string(), form.credential_type, form.get_secret())
+        };
+        let mut cred = crate::vault::credential::create_credential(db.conn(), key, new)?;
+        if let Some(env_var) = form.get_env_var() {
+            crate::vault::credential::set_env_var(db.conn(), &mut cred, Some(env_var))?;
+        }
+        let attributes = form.get_attributes();
+        if !attributes.is_empty() {
+            crate::vault::credential::set_attributes(db.conn(), &mut cred, attributes)?;
+        }
+        if let Some(public_key) = public_key_of(form.credential_type, form.get_secret()) {
+            crate::vault::credential::set_public_key(db.conn(), &mut cred, Some(public_key))?;
+        }
 
         self.log_audit(AuditAction::Create, Some(&cred.id), Some(&cred.name), cred.username.as_deref(), None)?;
         self.set_message("Credential created", MessageType::Success);
         Ok(())
     }
 
-    pub fn delete_credential(&mut self, id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let db = self.vault.db()?;
-        let cred = crate::db::get_credential(db.conn(), id)?;
-        crate::db::delete_credential(db.conn(), id)?;
-        self.log_audit(AuditAction::Delete, Some(id), Some(&cred.name), cred.username.as_deref(), None)?;
+    pub fn delete_credential(&mut self, id: &str) -> AppResult<()> {
+        self.backup_before("delete")?;
+        self.atomic(|app| {
+            let db = app.vault.db()?;
+            let cred = crate::vault::credential::get_credential(db.conn(), id)?;
+            crate::vault::credential::delete_credential(db.conn(), id)?;
+            app.log_audit(AuditAction::Delete, Some(id), Some(&cred.name), cred.username.as_deref(), None)
+        })?;
+        self.decrypt_cache.remove(id);
         self.refresh_data()?;
         self.set_message("Credential deleted", MessageType::Success);
+
+        let deletes = crate::vault::compact::record_delete(self.vault.db()?.conn())?;
+        let threshold = self.config.compact_after_deletes;
+        if threshold > 0 && deletes >= threshold {
+            self.run_compact();
+        }
         Ok(())
     }
 
-    pub fn copy_secret(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    /// `yy` copies the secret, or the field a custom type names as its
+    /// default with `copy`
+    pub fn copy_default(&mut self) -> AppResult<()> {
         let Some(cred) = &self.selected_credential else { return Ok(()) };
-        let Some(secret) = &cred.secret else { return Ok(()) };
+        let custom = CustomType::find(&self.config.custom_types, &cred.attributes)
+            .filter(|_| cred.credential_type == CredentialType::Custom);
+        let Some(custom) = custom else { return self.copy_secret() };
+        let Some(key) = custom.copy.clone() else { return self.copy_secret() };
+        match key.as_str() {
+            "secret" | "password" => return self.copy_secret(),
+            "username" => return self.copy_username(),
+            _ => {}
+        }
 
-        let text = secret.expose_secret().to_string();
+        let label = custom.fields.iter().find(|f| f.key == key).map_or(key.clone(), |f| f.label.clone());
+        let Some(text) = cred.attributes.get(&key).cloned() else {
+            self.set_message(&i18n::trf("No {} to copy", &[&label]), MessageType::Info);
+            return Ok(());
+        };
         let (id, name, username) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
 
-        super::clipboard::copy_with_timeout(&text, self.config.clipboard_timeout);
-        self.log_audit(AuditAction::Copy, Some(&id), Some(&name), username.as_deref(), Some("Secret"))?;
-        self.set_message(&format!("Password copied ({}s)", self.config.clipboard_timeout.as_secs()), MessageType::Success);
+        self.copy_to_clipboard(&text);
+        self.log_audit(AuditAction::Copy, Some(&id), Some(&name), username.as_deref(), Some(&label))?;
+        let copied = i18n::trf("{} copied ({}s)", &[&label, &self.config.clipboard_timeout.as_secs()]);
+        self.announce_copy(&label, copied);
+        Ok(())
+    }
+
+    /// A secret kept in a backend is fetched now, and only for the copy
+    pub fn copy_secret(&mut self) -> AppResult<()> {
+        self.open_selected()?;
+        let Some(mut cred) = self.selected_credential.clone() else { return Ok(()) };
+        let fetched = self.backends.resolve(&mut cred)?;
+        let Some(secret) = &cred.secret else { return Ok(()) };
+
+        let text = Zeroizing::new(secret.expose_secret().to_string());
+        let details = backend::audit_details("Secret", fetched.as_deref());
+
+        self.copy_to_clipboard(&text);
+        self.log_audit(AuditAction::Copy, Some(&cred.id), Some(&cred.name), cred.username.as_deref(), Some(&details))?;
+        self.announce_copy("Password", i18n::trf("Password copied ({}s)", &[&self.config.clipboard_timeout.as_secs()]));
         Ok(())
     }
 
-    pub fn copy_username(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn copy_username(&mut self) -> AppResult<()> {
         let Some(cred) = &self.selected_credential else { return Ok(()) };
         let Some(username) = &cred.username else { return Ok(()) };
 
         let text = username.clone();
         let (id, name, u) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
 
-        super::clipboard::copy_with_timeout(&text, self.config.clipboard_timeout);
+        self.copy_to_clipboard(&text);
         self.log_audit(AuditAction::Copy, Some(&id), Some(&name), u.as_deref(), Some("Username"))?;
-        self.set_message(&format!("Username copied ({}s)", self.config.clipboard_timeout.as_secs()), MessageType::Success);
+        self.announce_copy("Username", i18n::trf("Username copied ({}s)", &[&self.config.clipboard_timeout.as_secs()]));
+        Ok(())
+    }
+
+    /// `2y` and the like in the detail view copy its numbered fields
+    pub fn copy_field(&mut self, number: usize) -> AppResult<()> {
+        if self.view != View::Detail {
+            self.set_message("Open a credential to copy its fields by number", MessageType::Info);
+            return Ok(());
+        }
+        let Some(detail) = &self.selected_detail else { return Ok(()) };
+        let Some(field) = number.checked_sub(1).and_then(|i| detail.copy_fields().get(i).copied()) else {
+            self.set_message(&i18n::trf("No field {}", &[&number]), MessageType::Error);
+            return Ok(());
+        };
+        let Some(cred) = &self.selected_credential else { return Ok(()) };
+        let (label, text) = match field {
+            DetailField::Username => return self.copy_username(),
+            DetailField::Secret => return self.copy_secret(),
+            DetailField::Totp => return self.copy_totp(),
+            DetailField::Url => ("URL".to_string(), cred.url.clone()),
+            DetailField::EnvVar => ("Env var".to_string(), cred.env_var.clone()),
+            DetailField::PublicKey => return self.copy_public_key(),
+            DetailField::Attribute(i) => match detail.attributes.get(i) {
+                Some((label, value)) => (label.clone(), Some(value.clone())),
+                None => return Ok(()),
+            },
+            DetailField::Notes => ("Notes".to_string(), cred.notes.as_ref().map(|n| n.expose_secret().to_string())),
+            _ => return Ok(()),
+        };
+        let Some(text) = text else { return Ok(()) };
+        let (id, name, username) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
+
+        self.copy_to_clipboard(&text);
+        self.log_audit(AuditAction::Copy, Some(&id), Some(&name), username.as_deref(), Some(&label))?;
+        let copied = i18n::trf("{} copied ({}s)", &[&label, &self.config.clipboard_timeout.as_secs()]);
+        self.announce_copy(&label, copied);
         Ok(())
     }
 
-    pub fn copy_totp(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn initiate_open_url(&mut self) {
+        let Some(cred) = &self.selected_credential else { return };
+        let Some(url) = cred.url.as_deref().filter(|u| !u.trim().is_empty()) else {
+            self.set_message("No URL for this credential", MessageType::Info);
+            return;
+        };
+        let Some(url) = super::browser::launch_target(url) else {
+            self.set_message("Only http(s) URLs can be opened", MessageType::Error);
+            return;
+        };
+
+        self.request_confirm(OpenUrl { id: cred.id.clone(), url });
+    }
+
+    pub fn open_url(&mut self, id: &str, url: &str) -> AppResult<()> {
+        if let Err(e) = super::browser::open(url) {
+            self.set_message(&i18n::trf("Failed to open browser: {}", &[&e]), MessageType::Error);
+            return Ok(());
+        }
+        let cred = self.credentials.iter().find(|c| c.id == id);
+        let (name, username) = (cred.map(|c| c.name.clone()), cred.and_then(|c| c.username.clone()));
+        self.log_audit(AuditAction::Read, Some(id), name.as_deref(), username.as_deref(), Some(&format!("Open URL {}", url)))?;
+        let message = if self.privacy { "Opened URL".to_string() } else { format!("Opened {}", url) };
+        self.set_message(&message, MessageType::Success);
+        Ok(())
+    }
+
+    pub fn copy_totp(&mut self) -> AppResult<()> {
         let Some(cred) = &self.selected_credential else { return Ok(()) };
         if cred.credential_type != CredentialType::Totp {
             return Ok(());
         }
         let Some(secret_str) = &cred.secret else { return Ok(()) };
 
-        let totp_secret = parse_totp_secret(secret_str.expose_secret(), &cred.name);
+        let totp_secret = TotpSecret::parse(secret_str.expose_secret(), &cred.name)?;
         let code = totp::generate_totp(&totp_secret)?;
         let remaining = totp::time_remaining(&totp_secret);
         let (id, name, username) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
 
-        super::clipboard::copy_with_timeout(&code, self.config.clipboard_timeout);
+        self.copy_to_clipboard(&code);
         self.log_audit(AuditAction::Copy, Some(&id), Some(&name), username.as_deref(), Some("TOTP"))?;
-        self.set_message(&format!("TOTP: {} ({}s remaining)", code, remaining), MessageType::Success);
+        let message = match self.privacy {
+            true => format!("TOTP copied ({}s remaining)", remaining),
+            false => format!("TOTP: {} ({}s remaining)", code, remaining),
+        };
+        self.announce_copy("TOTP", message);
+        Ok(())
+    }
+
+    pub fn copy_public_key(&mut self) -> AppResult<()> {
+        let Some(cred) = &self.selected_credential else { return Ok(()) };
+        let Some(public_key) = &cred.public_key else {
+            self.set_message("No public key; load one with :sshkey PATH", MessageType::Info);
+            return Ok(());
+        };
+
+        let text = public_key.clone();
+        let (id, name, username) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
+
+        self.copy_to_clipboard(&text);
+        self.log_audit(AuditAction::Copy, Some(&id), Some(&name), username.as_deref(), Some("Public key"))?;
+        self.announce_copy("Public key", i18n::trf("Public key copied ({}s)", &[&self.config.clipboard_timeout.as_secs()]));
         Ok(())
     }
 
-    pub fn generate_and_copy_password(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn copy_private_key(&mut self) -> AppResult<()> {
+        let Some(cred) = &self.selected_credential else { return Ok(()) };
+        if cred.credential_type != CredentialType::SshKey {
+            self.set_message("Not an SSH key", MessageType::Info);
+            return Ok(());
+        }
+        let Some(secret) = &cred.secret else { return Ok(()) };
+
+        let text = secret.expose_secret().to_string();
+        let (id, name, username) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
+
+        self.copy_to_clipboard(&text);
+        self.log_audit(AuditAction::Copy, Some(&id), Some(&name), username.as_deref(), Some("Private key"))?;
+        self.announce_copy("Private key", i18n::trf("Private key copied ({}s)", &[&self.config.clipboard_timeout.as_secs()]));
+        Ok(())
+    }
+
+    /// `yd`: offer the URL schemes a database credential can be copied as
+    pub(super) fn open_connection_menu(&mut self) {
+        let Some(cred) = &self.selected_credential else { return };
+        if cred.credential_type != CredentialType::Database {
+            self.set_message("Not a database credential", MessageType::Info);
+            return;
+        }
+        let Some(conn) = Connection::from_credential(cred) else {
+            self.set_message("No host; edit the credential to add one", MessageType::Info);
+            return;
+        };
+
+        let options = Scheme::ALL.iter().map(|&scheme| (scheme, conn.preview(scheme))).collect();
+        self.connection_state.set_options(options);
+        self.mode_state.to_connection();
+    }
+
+    /// Copy the selected credential as a URL of the scheme picked in the menu
+    pub(super) fn copy_connection_string(&mut self) {
+        self.mode_state.to_normal();
+        let Some(scheme) = self.connection_state.selected_scheme() else { return };
+        let Some(cred) = &self.selected_credential else { return };
+        let Some(url) = Connection::from_credential(cred).map(|conn| conn.url(scheme)) else { return };
+        let (id, name, username) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
+
+        self.copy_to_clipboard(&url);
+        if let Err(e) = self.log_audit(AuditAction::Copy, Some(&id), Some(&name), username.as_deref(), Some("Connection string")) {
+            self.set_message(&i18n::trf("Failed to log copy: {}", &[&e]), MessageType::Error);
+            return;
+        }
+        let what = format!("{} connection string", scheme.display_name());
+        let message = format!("{} copied ({}s)", what, self.config.clipboard_timeout.as_secs());
+        self.announce_copy(&what, message);
+    }
+
+    pub fn generate_and_copy_password(&mut self) -> AppResult<()> {
         let password = crate::crypto::generate_password(&crate::crypto::PasswordPolicy::default());
-        super::clipboard::copy_with_timeout(&password, self.config.clipboard_timeout);
-        self.set_message(
-            &format!("Generated: {} (copied for {}s)", password, self.config.clipboard_timeout.as_secs()),
-            MessageType::Success,
-        );
+        self.copy_to_clipboard(&password);
+        self.announce_generated("Password");
+        Ok(())
+    }
+
+    pub fn generate_and_copy_passphrase(&mut self, words: usize) -> AppResult<()> {
+        let passphrase = crate::crypto::generate_diceware(&crate::crypto::PassphrasePolicy::with_words(words));
+        self.copy_to_clipboard(&passphrase);
+        self.announce_generated("Passphrase");
         Ok(())
     }
+
+    /// The message never shows what was generated, which would otherwise
+    /// stay on screen and in the terminal's scrollback
+    fn announce_generated(&mut self, what: &str) {
+        let secs = self.config.clipboard_timeout.as_secs();
+        self.announce_copy(what, i18n::trf("{} generated and copied ({}s)", &[&what, &secs]));
+    }
 }
 
-pub fn credential_to_item(cred: &Credential) -> CredentialItem {
+pub fn credential_to_item(cred: &Credential, custom_types: &[CustomType]) -> CredentialItem {
+    let (type_name, type_icon) = type_label(cred.credential_type, &cred.attributes, custom_types);
     CredentialItem {
         id: cred.id.clone(),
         name: cred.name.clone(),
         username: cred.username.clone(),
         credential_type: cred.credential_type,
+        type_name,
+        type_icon,
         tags: cred.tags.clone(),
+        updated_at: cred.updated_at,
+        expires_on: cred.expires_on(),
     }
 }
 
-pub fn build_detail(cred: &DecryptedCredential, password_visible: bool) -> CredentialDetail {
+/// The public key line of an SSH private key typed or pasted into the form
+fn public_key_of(credential_type: CredentialType, secret: &str) -> Option<String> {
+    if credential_type != CredentialType::SshKey {
+        return None;
+    }
+    SshPublicKey::from_private_key(secret).ok().map(|key| key.to_line())
+}
+
+fn changed_fields(before: &DecryptedCredential, form: &CredentialForm) -> Vec<&'static str> {
+    let secret = before.secret.as_ref().map(|s| s.expose_secret()).unwrap_or_default();
+    let notes = before.notes.as_ref().map(|s| s.expose_secret());
+
+    [
+        ("name", before.name != form.get_name()),
+        ("type", before.credential_type != form.credential_type),
+        ("username", before.username != form.get_username()),
+        (SECRET_FIELD, secret != form.get_secret()),
+        ("url", before.url != form.get_url()),
+        ("tags", before.tags != form.get_tags()),
+        ("notes", notes != form.get_notes()),
+        ("env var", before.env_var != form.get_env_var()),
+        ("attributes", before.attributes != form.get_attributes()),
+        ("policy", before.password_policy != form.password_policy),
+    ]
+    .into_iter()
+    .filter_map(|(field, changed)| changed.then_some(field))
+    .collect()
+}
+
+/// One line per changed field for the save confirmation, always ending
+/// with whether the secret changed. Secrets, notes and attributes are
+/// only said to have changed, and so are usernames and URLs in privacy
+/// mode.
+fn edit_summary(before: &DecryptedCredential, form: &CredentialForm, changes: &[&str], private: bool) -> Vec<String> {
+    let shown = |value: Option<&str>| value.filter(|v| !v.is_empty()).unwrap_or("(none)").to_string();
+    let mut lines: Vec<String> = changes
+        .iter()
+        .filter(|field| **field != SECRET_FIELD)
+        .map(|field| {
+            let (old, new) = match *field {
+                "name" => (Some(before.name.clone()), Some(form.get_name().to_string())),
+                "type" => (Some(before.credential_type.display_name().to_string()), Some(form.credential_type.display_name().to_string())),
+                "username" if !private => (before.username.clone(), form.get_username()),
+                "url" if !private => (before.url.clone(), form.get_url()),
+                "env var" => (before.env_var.clone(), form.get_env_var()),
+                "policy" => (before.password_policy.clone(), form.password_policy.clone()),
+                "tags" => {
+                    let after = form.get_tags();
+                    let added = after.iter().filter(|t| !before.tags.contains(t)).map(|t| format!("+{}", t));
+                    let removed = before.tags.iter().filter(|t| !after.contains(t)).map(|t| format!("-{}", t));
+                    return format!("{:<11}{}", field, added.chain(removed).collect::<Vec<_>>().join(" "));
+                }
+                _ => return format!("{:<11}changed", field),
+            };
+            format!("{:<11}{} -> {}", field, shown(old.as_deref()), shown(new.as_deref()))
+        })
+        .collect();
+    let secret = if changes.contains(&SECRET_FIELD) { "changed" } else { "unchanged" };
+    lines.push(format!("{:<11}{}", SECRET_FIELD, secret));
+    lines
+}
+
+/// Labels and values of the attributes that are set, in the type's
+/// order. Values no field is defined for any more come last, labelled
+/// with their keys.
+fn detail_attributes(cred: &DecryptedCredential, custom: Option<&CustomType>) -> Vec<(String, String)> {
+    let fields = cred.credential_type.attributes_with(custom);
+    let mut rows: Vec<(String, String)> = fields
+        .iter()
+        .filter_map(|a| {
+            let value = match cred.expires_on() {
+                Some(expires) if a.key == EXPIRES_AT => expiry::summary(expires, Local::now().date_naive()),
+                _ => cred.attributes.get(&a.key)?.clone(),
+            };
+            Some((a.label.clone(), value))
+        })
+        .collect();
+    let undefined = cred
+        .attributes
+        .iter()
+        .filter(|(key, _)| *key != CUSTOM_TYPE && !fields.iter().any(|a| &a.key == *key));
+    rows.extend(undefined.map(|(key, value)| (key.clone(), value.clone())));
+    rows
+}
+
+/// The name and icon a credential's type is shown with. A custom type
+/// that is no longer configured keeps its name.
+fn type_label(
+    credential_type: CredentialType,
+    attributes: &BTreeMap<String, String>,
+    custom_types: &[CustomType],
+) -> (String, String) {
+    let custom_name = attributes.get(CUSTOM_TYPE).filter(|_| credential_type == CredentialType::Custom);
+    match (CustomType::find(custom_types, attributes), custom_name) {
+        (Some(custom), Some(_)) => (custom.name.clone(), theme::custom_icon(custom).to_string()),
+        (None, Some(name)) => (name.clone(), theme::type_icon(credential_type).to_string()),
+        _ => (credential_type.display_name().to_string(), theme::type_icon(credential_type).to_string()),
+    }
+}
+
+pub fn build_detail(
+    cred: &DecryptedCredential,
+    stats: &AccessStats,
+    activity: &[AuditLog],
+    secret_display: SecretDisplay,
+    custom_types: &[CustomType],
+) -> CredentialDetail {
     let (totp_code, totp_remaining) = compute_totp(cred);
+    let (type_name, type_icon) = type_label(cred.credential_type, &cred.attributes, custom_types);
+    let custom = CustomType::find(custom_types, &cred.attributes);
 
     CredentialDetail {
         name: cred.name.clone(),
         credential_type: cred.credential_type,
+        type_name,
+        type_icon,
         username: cred.username.clone(),
-        secret: cred.secret.as_ref().map(|s| s.expose_secret().to_string()),
-        secret_visible: password_visible,
+        secret: cred.secret.clone(),
+        secret_display,
         url: cred.url.clone(),
-        notes: cred.notes.as_ref().map(|s| s.expose_secret().to_string()),
+        notes: cred.notes.clone(),
         tags: cred.tags.clone(),
+        password_policy: cred.password_policy.clone(),
+        env_var: cred.env_var.clone(),
+        alias: cred.alias.clone(),
+        public_key: cred.public_key.as_deref().and_then(|line| SshPublicKey::from_line(line).ok()),
+        certificate: parse_certificate(cred),
+        attributes: detail_attributes(cred, custom.filter(|_| cred.credential_type == CredentialType::Custom)),
         created_at: cred.created_at.format("%d-%b-%Y at %H:%M").to_string(),
         updated_at: cred.updated_at.format("%d-%b-%Y at %H:%M").to_string(),
+        accessed_at: stats.last_accessed.map(|t| t.format("%d-%b-%Y at %H:%M").to_string()),
+        view_count: stats.reads,
+        copy_count: stats.copies,
         totp_code,
         totp_remaining,
+        activity: activity.iter().map(activity_entry).collect(),
+        breached: false,
+        encrypted: false,
+    }
+}
+
+fn activity_entry(log: &AuditLog) -> ActivityEntry {
+    ActivityEntry {
+        at: log.timestamp.format("%d-%b %H:%M").to_string(),
+        action: log.action,
+        details: log.details.clone(),
+    }
+}
+
+fn parse_certificate(cred: &DecryptedCredential) -> Option<Certificate> {
+    if cred.credential_type != CredentialType::Certificate {
+        return None;
     }
+    Certificate::from_pem(cred.secret.as_ref()?.expose_secret()).ok()
 }
 
 fn compute_totp(cred: &DecryptedCredential) -> (Option<String>, Option<u64>) {
@@ -276,15 +969,12 @@ fn compute_totp(cred: &DecryptedCredential) -> (Option<String>, Option<u64>) {
         return (None, None);
     };
 
-    let totp_secret = parse_totp_secret(secret_str.expose_secret(), &cred.name);
+    let Ok(totp_secret) = TotpSecret::parse(secret_str.expose_secret(), &cred.name) else {
+        return (None, None);
+    };
 
     totp::generate_totp(&totp_secret)
         .ok()
         .map(|code| (Some(code), Some(totp::time_remaining(&totp_secret))))
         .unwrap_or((None, None))
 }
-
-fn parse_totp_secret(secret: &str, name: &str) -> TotpSecret {
-    serde_json::from_str::<TotpSecret>(secret)
-        .unwrap_or_else(|_| TotpSecret::new(secret.to_string(), name.to_string(), "Vault".to_string()))
-}
@@ -0,0 +1,132 @@
+//! Form Drafts
+//!
+//! A credential form still open when the vault locks is not thrown away.
+//! Its field values are encrypted with the data key and kept in memory,
+//! with the plaintext form wiped; unlocking the same vault decrypts them
+//! and reopens the form where it was. The rest of the form, labels and
+//! which credential it edits, is not secret and is kept as it is.
+//! Nothing is written to disk, so quitting while locked drops the draft.
+
+use zeroize::Zeroizing;
+
+use crate::crypto::{decrypt_bytes, encrypt_bytes};
+use crate::i18n;
+use crate::ui::components::{CredentialForm, MessageType};
+use crate::ui::renderer::View;
+
+use super::{App, AppResult};
+
+/// A form whose values were sealed when the vault locked
+pub struct Draft {
+    /// The form with its values cleared
+    form: CredentialForm,
+    /// Every field's value, length-prefixed, encrypted with the data key
+    values: String,
+}
+
+/// Each value as its length (u32, little-endian) followed by its bytes
+fn encode(form: &CredentialForm) -> Zeroizing<Vec<u8>> {
+    let mut out = Zeroizing::new(Vec::new());
+    for field in &form.fields {
+        let value = field.value.as_str().as_bytes();
+        out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        out.extend_from_slice(value);
+    }
+    out
+}
+
+fn decode(mut bytes: &[u8]) -> Option<Vec<Zeroizing<String>>> {
+    let mut values = Vec::new();
+    while !bytes.is_empty() {
+        let len = u32::from_le_bytes(bytes.get(..4)?.try_into().ok()?) as usize;
+        let value = bytes.get(4..4 + len)?;
+        values.push(Zeroizing::new(String::from_utf8(value.to_vec()).ok()?));
+        bytes = &bytes[4 + len..];
+    }
+    Some(values)
+}
+
+impl App {
+    /// Seal the open form, if any, ahead of locking
+    pub(super) fn stash_form(&mut self) {
+        let Ok(dek) = self.vault.dek() else { return };
+        let Some(form) = self.credential_form.as_ref() else { return };
+        let Ok(values) = encrypt_bytes(dek.as_ref(), &encode(form)) else { return };
+
+        let Some(mut form) = self.credential_form.take() else { return };
+        for field in &mut form.fields {
+            field.value.clear();
+            field.error = None;
+        }
+        form.generator = None;
+        // The detail it came from is gone once locked
+        form.previous_view = View::List;
+        self.view = View::List;
+        self.draft = Some(Draft { form, values });
+    }
+
+    /// Reopen a form sealed when the vault locked
+    pub(super) fn restore_form(&mut self) -> AppResult<()> {
+        let Some(Draft { mut form, values }) = self.draft.take() else { return Ok(()) };
+        let restored = decrypt_bytes(self.vault.dek()?.as_ref(), &values)
+            .ok()
+            .map(Zeroizing::new)
+            .and_then(|bytes| decode(&bytes))
+            .filter(|values| values.len() == form.fields.len());
+        let Some(values) = restored else {
+            self.set_message("The form open when the vault locked could not be restored", MessageType::Warning);
+            return Ok(());
+        };
+        for (field, value) in form.fields.iter_mut().zip(&values) {
+            field.value.set(value);
+        }
+        self.credential_form = Some(form);
+        self.view = View::Form;
+        self.set_message("Reopened the form that was open when the vault locked", MessageType::Info);
+        Ok(())
+    }
+
+    /// Say, once per countdown, that an open form will be sealed
+    pub(super) fn warn_form_before_lock(&mut self) {
+        match (self.lock_countdown(), self.credential_form.is_some()) {
+            (Some(secs), true) if !self.draft_warned => {
+                self.draft_warned = true;
+                let msg = i18n::trf("Locking in {}s; the unsaved form will be kept encrypted until you unlock", &[&secs]);
+                self.set_message(&msg, MessageType::Warning);
+            }
+            (None, _) => self.draft_warned = false,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::{unlocked_app, PASSWORD};
+    use crate::app::AppConfig;
+
+    #[test]
+    fn test_form_survives_lock() {
+        let (_dir, mut app) = unlocked_app(AppConfig::default(), &[]);
+        app.new_credential();
+        let form = app.credential_form.as_mut().unwrap();
+        form.fields[0].value.set("GitHub");
+        form.fields[3].value.set("p\u{e4}ss\nword");
+
+        app.lock();
+        assert!(app.credential_form.is_none());
+        assert_eq!(app.view, View::List);
+        let draft = app.draft.as_ref().unwrap();
+        assert!(draft.form.fields.iter().all(|f| f.value.as_str().is_empty()));
+        assert!(!draft.values.contains("GitHub"));
+
+        app.vault.unlock(PASSWORD).unwrap();
+        app.restore_form().unwrap();
+        assert_eq!(app.view, View::Form);
+        let form = app.credential_form.as_ref().unwrap();
+        assert_eq!(form.fields[0].value.as_str(), "GitHub");
+        assert_eq!(form.fields[3].value.as_str(), "p\u{e4}ss\nword");
+        assert!(app.draft.is_none());
+    }
+}
@@ -0,0 +1,81 @@
+use crate::i18n;
+use crate::ui::components::MessageType;
+use crate::ui::components::layout::truncate_with_ellipsis;
+use crate::vault::dupes;
+
+use super::pending::EditDuplicate;
+use super::{App, AppResult};
+
+/// Longest credential name quoted in the duplicate warning
+const WARNING_NAME_LEN: usize = 20;
+
+impl App {
+    /// Warn before saving a new credential that looks like an existing
+    /// one. Returns whether the warning was raised.
+    pub(super) fn warn_if_duplicate(&mut self) -> AppResult<bool> {
+        let Some(form) = &self.credential_form else { return Ok(false) };
+        if form.editing_id.is_some() {
+            return Ok(false);
+        }
+
+        let db = self.vault.db()?;
+        let similar = dupes::similar_to(
+            db.conn(),
+            form.get_name(),
+            form.get_username().as_deref(),
+            form.get_url().as_deref(),
+            None,
+        )?;
+        let Some(existing) = similar.into_iter().next() else { return Ok(false) };
+
+        self.request_confirm(EditDuplicate {
+            id: existing.id,
+            name: truncate_with_ellipsis(&existing.name, WARNING_NAME_LEN),
+        });
+        Ok(true)
+    }
+
+    /// Drop the new credential's form and edit the existing one instead
+    pub(super) fn edit_duplicate(&mut self, id: &str) -> AppResult<()> {
+        if let Some(form) = self.credential_form.take() {
+            self.view = form.previous_view;
+        }
+        self.go_to_credential(id)?;
+        self.edit_credential()
+    }
+
+    pub(super) fn show_dupes(&mut self) -> AppResult<()> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let pairs = dupes::find_duplicates(self.vault.db()?.conn(), self.vault.dek()?)?;
+        if pairs.is_empty() {
+            self.set_message("No duplicates found", MessageType::Success);
+            return Ok(());
+        }
+        self.dupes_state.set_pairs(pairs);
+        self.dupes_state.home();
+        self.dupes_state.scroll.pending_g = false;
+        self.mode_state.to_dupes();
+        Ok(())
+    }
+
+    /// Leave the report on one side of the selected pair, naming the other
+    /// so the two can be compared and one removed
+    pub(super) fn open_duplicate(&mut self, second: bool) {
+        let Some(pair) = self.dupes_state.selected_pair() else { return };
+        let (target, other) = match second {
+            true => (&pair.second, &pair.first),
+            false => (&pair.first, &pair.second),
+        };
+        let (id, message) = (target.id.clone(), format!("Possible duplicate of '{}' ({})", other.name, pair.reason()));
+
+        self.mode_state.to_normal();
+        let result = self.go_to_credential(&id).and_then(|_| self.select_credential());
+        match result {
+            Ok(()) => self.set_message(&message, MessageType::Warning),
+            Err(e) => self.set_message(&i18n::trf("Failed to open credential: {}", &[&e]), MessageType::Error),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Application Errors
+//!
+//! What the app layer fails with. Vault, database and crypto failures keep
+//! their own variants so the event loop can answer each differently: a
+//! locked vault goes back to the unlock screen, a damaged vault file points
+//! at `:fsck`, and anything else becomes a status line message.
+
+use rusqlite::ErrorCode;
+use thiserror::Error;
+
+use crate::crypto::CryptoError;
+use crate::db::DbError;
+use crate::vault::VaultError;
+
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error(transparent)]
+    Vault(#[from] VaultError),
+
+    #[error(transparent)]
+    Database(#[from] DbError),
+
+    #[error(transparent)]
+    Crypto(#[from] CryptoError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    Message(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::Message(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        Self::Message(message.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Database(DbError::Sqlite(e))
+    }
+}
+
+/// What the event loop does about an error that reached it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// The vault locked underneath the action; show the unlock screen
+    Unlock,
+    /// The vault file or a ciphertext in it looks damaged
+    CheckVault,
+    /// Nothing to undo, just say what went wrong
+    Report,
+}
+
+impl AppError {
+    pub fn recovery(&self) -> Recovery {
+        match self {
+            Self::Vault(VaultError::Locked) => Recovery::Unlock,
+            Self::Vault(VaultError::DatabaseError(e)) | Self::Database(e) if is_corrupt(e) => Recovery::CheckVault,
+            Self::Vault(VaultError::CryptoError(_)) => Recovery::CheckVault,
+            Self::Crypto(CryptoError::DecryptionFailed(_)) => Recovery::CheckVault,
+            _ => Recovery::Report,
+        }
+    }
+}
+
+fn is_corrupt(e: &DbError) -> bool {
+    match e {
+        DbError::Sqlite(e) => matches!(e.sqlite_error_code(), Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recovery() {
+        assert_eq!(AppError::from(VaultError::Locked).recovery(), Recovery::Unlock);
+        assert_eq!(AppError::from("no such tag").recovery(), Recovery::Report);
+
+        let corrupt = rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CORRUPT), None);
+        assert_eq!(AppError::from(corrupt).recovery(), Recovery::CheckVault);
+        let busy = rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY), None);
+        assert_eq!(AppError::from(VaultError::from(busy)).recovery(), Recovery::Report);
+    }
+}
@@ -0,0 +1,204 @@
+//! Event Hooks
+//!
+//! Security events can be passed on to other programs: a failed unlock,
+//! a break in the audit chain, an export, a breached password or an
+//! emergency code being used. Each entry of `event_hooks` names the events
+//! it fires on and runs a command, shows a desktop notification, or both.
+//! Commands get `{event}` and `{message}` in their arguments replaced and
+//! the same in `VAULT_EVENT` and `VAULT_MESSAGE`. Hooks run in the
+//! background, and one that cannot be started is noted in the status line,
+//! or on stderr when the event comes from the command line, agent or API.
+//!
+//! ```json
+//! "event_hooks": [
+//!   { "events": ["failed_unlock", "audit_tampered"], "notify": true },
+//!   { "exec": ["logger", "-t", "vault", "{event}: {message}"] }
+//! ]
+//! ```
+
+use std::fmt;
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use super::App;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEvent {
+    /// A wrong master password, or attempts recorded while the app was
+    /// not running
+    FailedUnlock,
+    /// The audit log's HMAC chain does not verify
+    AuditTampered,
+    /// Credentials written out of the vault
+    Export,
+    /// Passwords found in the breach index
+    Breach,
+    /// The vault was opened with a single-use emergency code
+    EmergencyUnlock,
+}
+
+impl SecurityEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FailedUnlock => "failed_unlock",
+            Self::AuditTampered => "audit_tampered",
+            Self::Export => "export",
+            Self::Breach => "breach",
+            Self::EmergencyUnlock => "emergency_unlock",
+        }
+    }
+}
+
+impl fmt::Display for SecurityEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What to do when one of `events` happens
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventHook {
+    /// Events the hook fires on; empty for all of them
+    pub events: Vec<SecurityEvent>,
+    /// Program and arguments to run
+    pub exec: Vec<String>,
+    /// Show a desktop notification
+    pub notify: bool,
+}
+
+impl EventHook {
+    fn fires_on(&self, event: SecurityEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+/// Start the commands and notifications of the hooks configured for
+/// `event` without waiting on them. Returns why each one that failed to
+/// start did.
+pub fn run_hooks(hooks: &[EventHook], event: SecurityEvent, message: &str) -> Vec<String> {
+    hooks
+        .iter()
+        .filter(|hook| hook.fires_on(event))
+        .filter_map(|hook| run_hook(hook, event, message).err())
+        .collect()
+}
+
+fn run_hook(hook: &EventHook, event: SecurityEvent, message: &str) -> Result<(), String> {
+    if let Some((program, args)) = hook.exec.split_first() {
+        let args = args.iter().map(|arg| arg.replace("{event}", event.as_str()).replace("{message}", message));
+        let mut command = Command::new(program);
+        command.args(args).env("VAULT_EVENT", event.as_str()).env("VAULT_MESSAGE", message);
+        spawn(command).map_err(|e| format!("{}: {}", program, e))?;
+    }
+    if hook.notify {
+        notify(message)?;
+    }
+    Ok(())
+}
+
+/// Start `command` with a thread waiting on it, so it is reaped when it
+/// exits rather than left a zombie
+fn spawn(mut command: Command) -> std::io::Result<()> {
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    let mut child = command.spawn()?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn notify(message: &str) -> Result<(), String> {
+    spawn(notifier(message)).map_err(|e| format!("notification: {}", e))
+}
+
+#[cfg(windows)]
+fn notify(_message: &str) -> Result<(), String> {
+    Err("desktop notifications are not supported on Windows".to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn notifier(message: &str) -> Command {
+    let script = format!("display notification {:?} with title \"Vault\"", message);
+    let mut command = Command::new("osascript");
+    command.args(["-e", &script]);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn notifier(message: &str) -> Command {
+    let mut command = Command::new("notify-send");
+    command.args(["--urgency=critical", "--app-name=vault", "Vault"]).arg(message);
+    command
+}
+
+impl App {
+    /// Run the hooks configured for `event`. A hook that fails to start
+    /// is added to the message on screen rather than replacing it.
+    pub fn fire_event(&mut self, event: SecurityEvent, message: &str) {
+        let failures = run_hooks(&self.config.event_hooks, event, message);
+        if failures.is_empty() {
+            return;
+        }
+        let note = format!("{} hook failed: {}", event, failures.join("; "));
+        match &mut self.message {
+            Some((text, _, _)) => *text = format!("{} ({})", text, note),
+            None => self.set_message(&note, crate::ui::components::MessageType::Warning),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::app::AppConfig;
+    use crate::ui::components::MessageType;
+
+    #[test]
+    fn test_event_hooks_config() {
+        let json = r#"[{"events": ["failed_unlock", "breach"], "notify": true}, {"exec": ["logger", "{message}"]}]"#;
+        let hooks: Vec<EventHook> = serde_json::from_str(json).unwrap();
+        assert!(hooks[0].fires_on(SecurityEvent::Breach) && !hooks[0].fires_on(SecurityEvent::Export));
+        assert!(hooks[1].fires_on(SecurityEvent::Export) && !hooks[1].notify);
+        assert!(serde_json::from_str::<Vec<EventHook>>(r#"[{"events": ["reboot"]}]"#).is_err());
+    }
+
+    #[test]
+    fn test_run_hooks() {
+        let hooks = vec![EventHook { events: vec![SecurityEvent::Export], exec: vec!["/nonexistent/hook".into()], notify: false }];
+        assert!(run_hooks(&hooks, SecurityEvent::FailedUnlock, "API refused a bad token").is_empty());
+        let failures = run_hooks(&hooks, SecurityEvent::Export, "Printed a recovery kit");
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].starts_with("/nonexistent/hook: "));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fire_event() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let script = format!("printf '%s %s' \"$VAULT_EVENT\" \"$0\" > {}", out.display());
+        let hooks = vec![
+            EventHook { events: vec![SecurityEvent::Export], exec: vec!["sh".into(), "-c".into(), script, "{message}".into()], notify: false },
+            EventHook { events: vec![SecurityEvent::Breach], exec: vec!["/nonexistent/hook".into()], notify: false },
+        ];
+        let mut app = App::new(AppConfig { event_hooks: hooks, ..AppConfig::default() });
+
+        app.fire_event(SecurityEvent::Export, "Exported vault to copy.db");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while std::fs::read_to_string(&out).map_or(true, |s| s.is_empty()) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "export Exported vault to copy.db");
+        assert!(app.message.is_none());
+
+        app.set_message("2 of 9 passwords found in breaches", MessageType::Warning);
+        app.fire_event(SecurityEvent::Breach, "2 of 9 passwords found in breaches");
+        let (text, kind, _) = app.message.as_ref().unwrap();
+        assert!(text.starts_with("2 of 9 passwords found in breaches (breach hook failed: /nonexistent/hook"));
+        assert_eq!(*kind, MessageType::Warning);
+    }
+}
@@ -0,0 +1,113 @@
+//! Vault Info
+//!
+//! `:info` gathers what would otherwise take sqlite3 to find out: where
+//! the vault file is and how big, when it was last backed up, how the
+//! session locks, and, while unlocked, what the vault holds and how it is
+//! encrypted.
+
+use chrono::Local;
+
+use crate::i18n;
+use crate::ui::components::backups::format_size;
+use crate::ui::components::info::InfoLine;
+use crate::ui::components::list::format_age;
+use crate::vault::backup;
+use crate::vault::stats::vault_stats;
+
+use super::actions::format_timeout;
+use super::{App, AppResult};
+
+impl App {
+    pub(super) fn show_vault_info(&mut self) -> AppResult<()> {
+        let lines = self.vault_info()?;
+        self.info_state.set_lines(lines);
+        self.mode_state.to_info();
+        Ok(())
+    }
+
+    fn vault_info(&self) -> AppResult<Vec<InfoLine>> {
+        let path = self.vault.config().path.clone();
+        let now = Local::now();
+        let ago = |time| i18n::trf("{} ago", &[&format_age(time, now)]);
+
+        // The write-ahead log holds changes not yet checkpointed into the file
+        let size = |path: &std::path::Path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        let wal = path.with_file_name(format!("{}-wal", path.file_name().unwrap_or_default().to_string_lossy()));
+        let last_backup = match backup::list_backups(&self.config.backup_dir())?.first() {
+            Some(entry) => format!("{} ({}, {})", entry.created.format("%Y-%m-%d %H:%M"), ago(entry.created), entry.reason),
+            None => i18n::tr("never").to_string(),
+        };
+        let mut lines = vec![
+            InfoLine::Heading("File"),
+            InfoLine::field("Path", path.display().to_string()),
+            InfoLine::field("Fingerprint", self.vault.fingerprint()?.unwrap_or_else(|| "none".to_string())),
+            InfoLine::field("Size", format_size(size(&path) + size(&wal))),
+            InfoLine::field("Last backup", last_backup),
+            InfoLine::field("Backups in", self.config.backup_dir().display().to_string()),
+        ];
+
+        let state = match (self.vault.inspected(), self.vault.is_read_only(), self.vault.is_unlocked()) {
+            (Some(backup), _, _) => i18n::trf("inspecting {}", &[&backup.display()]),
+            (None, true, _) => i18n::tr("unlocked, read-only").to_string(),
+            (None, false, true) => i18n::tr("unlocked").to_string(),
+            (None, false, false) => i18n::tr("locked").to_string(),
+        };
+        lines.extend([InfoLine::Heading("Session"), InfoLine::field("State", state)]);
+        if let Some(since) = self.authenticated_at.filter(|_| self.vault.is_unlocked()) {
+            let since = now - chrono::Duration::from_std(since.elapsed()).unwrap_or_default();
+            lines.push(InfoLine::field("Unlocked", ago(since)));
+        }
+        lines.push(InfoLine::field("Auto-lock", i18n::trf("after {} idle", &[&format_timeout(self.config.auto_lock_timeout)])));
+        if let Some(left) = self.vault.time_until_auto_lock() {
+            lines.push(InfoLine::field("Locks in", format_timeout(std::time::Duration::from_secs(left.as_secs()))));
+        }
+
+        let Ok(database) = self.vault.db() else {
+            lines.extend([InfoLine::Heading("Contents"), InfoLine::field("", i18n::tr("Unlock to see what the vault holds"))]);
+            return Ok(lines);
+        };
+        let stats = vault_stats(database.conn())?;
+        lines.extend([InfoLine::Heading("Contents"), InfoLine::field("Credentials", stats.credentials.to_string())]);
+        lines.extend(stats.by_type.iter().map(|(kind, count)| InfoLine::item(kind.as_str(), count.to_string())));
+        lines.extend([
+            InfoLine::field("Tags", stats.tags.to_string()),
+            InfoLine::field("Audit log entries", stats.audit_entries.to_string()),
+            InfoLine::field("Schema version", stats.schema_version.to_string()),
+            InfoLine::Heading("Encryption"),
+            InfoLine::field("Key derivation", stats.kdf.unwrap_or_else(|| "unknown".to_string())),
+            InfoLine::field("Key slots", i18n::trf("primary + {}", &[&stats.key_slots])),
+            InfoLine::field("Credential blobs", stats.formats.values().sum::<usize>().to_string()),
+        ]);
+        lines.extend(stats.formats.iter().map(|(format, count)| InfoLine::item(format.label(), count.to_string())));
+        Ok(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::unlocked_app;
+    use crate::app::AppConfig;
+    use crate::input::InputMode;
+
+    #[test]
+    fn test_vault_info() {
+        let (_dir, mut app) = unlocked_app(AppConfig::default(), &[]);
+        app.show_vault_info().unwrap();
+        assert_eq!(app.mode_state.mode, InputMode::Info);
+        let field = |app: &App, label: &str| {
+            app.info_state.lines.iter().find_map(|line| match line {
+                InfoLine::Field(name, value) if name == label => Some(value.clone()),
+                _ => None,
+            })
+        };
+        assert_eq!(field(&app, "Credentials").as_deref(), Some("0"));
+        assert_eq!(field(&app, "Last backup").as_deref(), Some("never"));
+        assert!(field(&app, "Key derivation").unwrap().starts_with("argon2id"));
+
+        app.lock();
+        app.show_vault_info().unwrap();
+        assert_eq!(field(&app, "State").as_deref(), Some("locked"));
+        assert_eq!(field(&app, "Credentials"), None);
+    }
+}
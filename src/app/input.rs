@@ -1,47 +1,167 @@
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 
-use crate::input::keymap::{confirm_action, normal_mode_action, text_input_action, Action};
+use crate::i18n;
+use crate::input::keymap::{text_input_action, Action};
+use crate::input::line::EditLine;
 use crate::input::modes::InputMode;
 use crate::ui::components::help::HelpScreen;
 use crate::ui::components::logs::LogsScreen;
 use crate::ui::components::tags::TagsPopup;
+use crate::ui::components::timeline::TimelinePopup;
+use crate::ui::components::detail::DetailField;
+use crate::ui::components::statusline::hint_at;
 use crate::ui::components::{CredentialForm, MessageType};
 use crate::ui::renderer::View;
 
-use super::App;
+use super::{App, AppResult};
 
 type KeyHandler = fn(&mut App, KeyCode, KeyModifiers) -> Option<Action>;
 
+/// The key press a footer hint stands for; hints naming several keys,
+/// like `j/k`, are not clickable
+fn hint_key(hint: &str) -> Option<KeyEvent> {
+    let mut chars = hint.chars();
+    let code = match (hint, chars.next(), chars.next()) {
+        ("Enter", _, _) => KeyCode::Enter,
+        ("Esc", _, _) => KeyCode::Esc,
+        ("Backspace", _, _) => KeyCode::Backspace,
+        (_, Some(c), None) => KeyCode::Char(c),
+        _ => return None,
+    };
+    let modifiers = match code {
+        KeyCode::Char(c) if c.is_ascii_uppercase() => KeyModifiers::SHIFT,
+        _ => KeyModifiers::NONE,
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
 impl App {
-    pub fn handle_key_event(&mut self, key: KeyEvent) -> Result<bool, Box<dyn std::error::Error>> {
+    pub(super) fn handle_key_event(&mut self, key: KeyEvent) -> AppResult<bool> {
         if key.kind != KeyEventKind::Press {
             return Ok(false);
         }
 
-        if self.view == View::Form && self.credential_form.is_some() {
+        if self.vault.is_unlocked() && self.is_lock_key(key) {
+            return self.execute_action(Action::Lock);
+        }
+
+        if self.view == View::Form && self.credential_form.is_some() && self.mode_state.mode != InputMode::Confirm {
             return self.handle_form_key(key);
         }
 
+        if self.mode_state.mode == InputMode::Normal {
+            let (action, count) = self.resolve_normal_action(key);
+            return self.execute_counted(action, count);
+        }
+
         let action = self.resolve_action(key);
         self.execute_action(action)
     }
 
+    /// `L` locks from any mode; where it would be typed, as in the form or
+    /// on the command line, Ctrl+l does
+    fn is_lock_key(&self, key: KeyEvent) -> bool {
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('l'), KeyModifiers::CONTROL) => true,
+            (KeyCode::Char('L'), KeyModifiers::SHIFT) => !self.is_typing(),
+            _ => false,
+        }
+    }
+
+    fn is_typing(&self) -> bool {
+        let in_form = self.view == View::Form && self.credential_form.is_some();
+        in_form
+            || self.mode_state.mode.is_text_input()
+            || match self.mode_state.mode {
+                InputMode::Finder => true,
+                InputMode::Help => self.help_state.search_input.is_some(),
+                InputMode::Logs => self.logs_state.search_input.is_some(),
+                _ => false,
+            }
+    }
+
+    /// The wheel scrolls like the arrow keys; clicks select list rows, copy
+    /// detail fields and press footer hints
+    pub(super) fn handle_mouse_event(&mut self, mouse: MouseEvent) -> AppResult<bool> {
+        let arrow = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        match mouse.kind {
+            MouseEventKind::ScrollDown if self.view != View::Form => self.handle_key_event(arrow(KeyCode::Down)),
+            MouseEventKind::ScrollUp if self.view != View::Form => self.handle_key_event(arrow(KeyCode::Up)),
+            MouseEventKind::Down(MouseButton::Left) => self.handle_click(mouse.column, mouse.row),
+            _ => Ok(false),
+        }
+    }
+
+    fn handle_click(&mut self, column: u16, row: u16) -> AppResult<bool> {
+        let help_bar = self.screen_areas.help_bar;
+        if row == help_bar.y && help_bar.height > 0 {
+            let key = hint_at(self.hint_context(), column.saturating_sub(help_bar.x)).and_then(hint_key);
+            return match key {
+                Some(key) => self.handle_key_event(key),
+                None => Ok(false),
+            };
+        }
+        if self.mode_state.mode != InputMode::Normal || self.view == View::Form {
+            return Ok(false);
+        }
+
+        if let Some(index) = self.list_state.index_at(column, row) {
+            if self.view == View::List && self.list_state.selected() == Some(index) {
+                return self.execute_action(Action::Select);
+            }
+            self.list_state.select(Some(index));
+            self.update_selected_detail()?;
+            return Ok(false);
+        }
+
+        let field = match (self.screen_areas.detail, &self.selected_detail) {
+            (Some(area), Some(detail)) => detail.field_at(area, self.detail_scroll.v_scroll, column, row),
+            _ => None,
+        };
+        let action = match field {
+            Some(DetailField::Username) => Action::CopyUsername,
+            // The secret itself, even where `yy` copies a custom type's default
+            Some(DetailField::Secret) => {
+                self.copy_secret()?;
+                return Ok(false);
+            }
+            Some(DetailField::Totp) => Action::CopyTotp,
+            Some(DetailField::Url) => Action::OpenUrl,
+            _ => return Ok(false),
+        };
+        self.execute_action(action)
+    }
+
     fn resolve_action(&mut self, key: KeyEvent) -> Action {
         match self.mode_state.mode {
-            InputMode::Normal => self.resolve_normal_action(key),
             InputMode::Command | InputMode::Search => self.resolve_text_action(key),
-            InputMode::Confirm => confirm_action(key),
+            InputMode::Confirm => self.confirm_key_action(key),
             InputMode::Help => self.popup_action(key, help_key_handler),
             InputMode::Logs => self.popup_action(key, logs_key_handler),
             InputMode::Tags => self.popup_action(key, tags_key_handler),
+            InputMode::Timeline => self.popup_action(key, timeline_key_handler),
+            InputMode::Finder => self.popup_action(key, finder_key_handler),
+            InputMode::Palette => self.popup_action(key, palette_key_handler),
+            InputMode::Backups => self.popup_action(key, backups_key_handler),
+            InputMode::Dupes => self.popup_action(key, dupes_key_handler),
+            InputMode::Merge => self.popup_action(key, merge_key_handler),
+            InputMode::Security => self.popup_action(key, security_key_handler),
+            InputMode::Tips => self.popup_action(key, tips_key_handler),
+            InputMode::Info => self.popup_action(key, info_key_handler),
+            InputMode::CryptoInfo => self.popup_action(key, crypto_info_key_handler),
+            InputMode::Connection => self.popup_action(key, connection_key_handler),
             _ => Action::None,
         }
     }
 
-    fn resolve_normal_action(&mut self, key: KeyEvent) -> Action {
-        let (action, pending) = normal_mode_action(key, self.mode_state.pending);
-        self.mode_state.pending = pending;
-        action
+    fn resolve_normal_action(&mut self, key: KeyEvent) -> (Action, Option<usize>) {
+        let (action, count) = self.mode_state.pending.feed(key);
+        // While a search is active `n` steps through its matches, as in vim,
+        // unless it matched nothing, when it creates what was searched for
+        match action {
+            Action::New if self.search_term().is_some() && self.missed_search().is_none() => (Action::NextMatch, count),
+            action => (action, count),
+        }
     }
 
     fn resolve_text_action(&mut self, key: KeyEvent) -> Action {
@@ -62,15 +182,32 @@ impl App {
 
     fn handle_text_input(&mut self, action: Action) -> Action {
         match action {
-            Action::InsertChar(c) => { self.mode_state.insert_char(c); Action::None }
-            Action::DeleteChar => { self.mode_state.delete_char(); Action::None }
+            Action::InsertChar(c) => { self.mode_state.insert_char(c); self.schedule_search(); Action::None }
+            Action::DeleteChar => { self.mode_state.delete_char(); self.schedule_search(); Action::None }
+            Action::DeleteCharForward => { self.mode_state.delete_char_forward(); self.schedule_search(); Action::None }
             Action::CursorLeft => { self.mode_state.cursor_left(); Action::None }
             Action::CursorRight => { self.mode_state.cursor_right(); Action::None }
             Action::CursorHome => { self.mode_state.cursor_home(); Action::None }
             Action::CursorEnd => { self.mode_state.cursor_end(); Action::None }
-            Action::ClearLine => { self.mode_state.clear_buffer(); Action::None }
+            Action::ClearLine => { self.mode_state.clear_buffer(); self.schedule_search(); Action::None }
+            Action::HistoryPrev => { self.recall_history(true); Action::None }
+            Action::HistoryNext => { self.recall_history(false); Action::None }
+            Action::Complete => {
+                if let Err(e) = self.complete_command() {
+                    self.set_message(&i18n::trf("Completion failed: {}", &[&e]), MessageType::Error);
+                }
+                Action::None
+            }
             Action::Submit => self.submit_text_input(),
-            Action::Cancel => { self.mode_state.to_normal(); Action::None }
+            Action::Cancel => {
+                if self.mode_state.mode == InputMode::Search
+                    && let Err(e) = self.cancel_search()
+                {
+                    self.set_message(&i18n::trf("Failed to restore filter: {}", &[&e]), MessageType::Error);
+                }
+                self.mode_state.to_normal();
+                Action::None
+            }
             _ => action,
         }
     }
@@ -78,16 +215,27 @@ impl App {
     fn submit_text_input(&mut self) -> Action {
         let buffer = self.mode_state.get_buffer().to_string();
         let result = match self.mode_state.mode {
-            InputMode::Command => Action::ExecuteCommand(buffer),
-            InputMode::Search => Action::Search(buffer),
+            InputMode::Command => {
+                self.command_history.push(&buffer);
+                Action::ExecuteCommand(buffer)
+            }
+            InputMode::Search => {
+                self.finish_search();
+                Action::Search(buffer)
+            }
             _ => Action::None,
         };
         self.mode_state.to_normal();
         result
     }
 
-    fn handle_form_key(&mut self, key: KeyEvent) -> Result<bool, Box<dyn std::error::Error>> {
+    fn handle_form_key(&mut self, key: KeyEvent) -> AppResult<bool> {
         let form = self.credential_form.as_mut().unwrap();
+        if form.generator.is_some() {
+            dispatch_generator_key(form, key.code, key.modifiers);
+            return Ok(false);
+        }
+
         let return_to = form.previous_view.clone();
 
         if key.code == KeyCode::Esc {
@@ -101,16 +249,24 @@ impl App {
         }
 
         let form = self.credential_form.as_mut().unwrap();
+        if key.code == KeyCode::Char('g') && key.modifiers == KeyModifiers::CONTROL {
+            form.open_generator(&self.config.password_policies);
+            return Ok(false);
+        }
+        if key.code == KeyCode::Char('r') && key.modifiers == KeyModifiers::CONTROL {
+            if form.fill_generated(&self.config.password_policies) {
+                self.set_message("Generated password filled in", MessageType::Success);
+            }
+            return Ok(false);
+        }
 
         dispatch_form_key(form, key.code, key.modifiers);
         Ok(false)
     }
 
-    fn submit_form(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
-        let form = self.credential_form.as_ref().unwrap();
-        if let Err(e) = form.validate() {
-            self.set_message(&e, MessageType::Error);
-        } else {
+    fn submit_form(&mut self) -> AppResult<bool> {
+        let form = self.credential_form.as_mut().unwrap();
+        if form.validate() && !self.warn_if_duplicate()? && !self.confirm_edit()? {
             self.save_credential_form()?;
         }
         Ok(false)
@@ -125,6 +281,7 @@ fn dispatch_form_key(form: &mut CredentialForm, code: KeyCode, mods: KeyModifier
         (KeyCode::Char(' '), m) if form.is_select_field() => form.cycle_type(m != KeyModifiers::CONTROL),
         (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => form.insert_char(c),
         (KeyCode::Backspace, _) => form.delete_char(),
+        (KeyCode::Delete, _) => form.delete_char_forward(),
         (KeyCode::Left, _) => form.cursor_left(),
         (KeyCode::Right, _) => form.cursor_right(),
         _ => {}
@@ -132,6 +289,11 @@ fn dispatch_form_key(form: &mut CredentialForm, code: KeyCode, mods: KeyModifier
 }
 
 fn help_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    if app.help_state.search_input.is_some() {
+        help_search_key(app, code, mods);
+        return None;
+    }
+
     match (code, mods) {
         (KeyCode::Char('?'), KeyModifiers::NONE | KeyModifiers::SHIFT)
         | (KeyCode::Char('q'), KeyModifiers::NONE)
@@ -149,8 +311,8 @@ fn help_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<
 
     let size = app.terminal_size;
     let visible = HelpScreen::visible_height(size) as usize;
-    let max_v = HelpScreen::max_scroll(size);
-    let max_h = HelpScreen::max_h_scroll(size);
+    let max_v = HelpScreen::max_scroll(size, &app.help_state);
+    let max_h = HelpScreen::max_h_scroll(size, &app.help_state);
 
     match (code, mods) {
         (KeyCode::Char('g'), KeyModifiers::NONE) if was_pending => app.help_state.home(),
@@ -166,15 +328,47 @@ fn help_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<
         (KeyCode::Char('l'), KeyModifiers::NONE) | (KeyCode::Right, _) => app.help_state.scroll_right(5, max_h),
         (KeyCode::Char('0'), KeyModifiers::NONE) => app.help_state.h_home(),
         (KeyCode::Char('$'), _) => app.help_state.h_end(max_h),
+        (KeyCode::Char('/'), KeyModifiers::NONE) => app.help_state.start_search(),
+        (KeyCode::Backspace, _) => app.help_state.clear_query(),
         _ => {}
     }
 
     None
 }
 
+fn help_search_key(app: &mut App, code: KeyCode, mods: KeyModifiers) {
+    let state = &mut app.help_state;
+
+    match (code, mods) {
+        (KeyCode::Esc, _) => state.cancel_search(),
+        (KeyCode::Enter, _) => state.submit_search(),
+        (KeyCode::Char('u'), KeyModifiers::CONTROL) => state.search_input = Some(EditLine::new()),
+        (KeyCode::Backspace, _) => state.pop_char(),
+        (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => state.push_char(c),
+        _ => {}
+    }
+}
+
 fn logs_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
     let size = app.terminal_size;
+
+    if app.logs_state.search_input.is_some() {
+        logs_search_key(app, code, mods);
+        return None;
+    }
+
+    match (code, mods, app.mode_state.pending.operator.take()) {
+        (KeyCode::Char('w'), KeyModifiers::CONTROL, None) => {
+            app.mode_state.pending.operator = Some('w');
+            return None;
+        }
+        (KeyCode::Char('z'), KeyModifiers::NONE, Some('w')) => return Some(Action::ZoomPane),
+        (_, _, Some('w')) => return None,
+        _ => {}
+    }
+
     let state = &mut app.logs_state;
+    let zoomed = state.zoomed;
 
     match (code, mods) {
         (KeyCode::Char('i'), KeyModifiers::NONE)
@@ -185,15 +379,38 @@ fn logs_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<
         }
         (KeyCode::Char('?'), KeyModifiers::NONE | KeyModifiers::SHIFT) => return Some(Action::ShowHelp),
         (KeyCode::Char('t'), KeyModifiers::NONE) => return Some(Action::ShowTags),
+        (KeyCode::Char('/'), KeyModifiers::NONE) => {
+            state.start_search();
+            return None;
+        }
+        (KeyCode::Char('a'), KeyModifiers::NONE) => {
+            state.query.cycle_action();
+            app.reload_logs();
+            return None;
+        }
+        (KeyCode::Char('c'), KeyModifiers::NONE) => {
+            let Some(name) = app.selected_credential.as_ref().map(|c| c.name.clone()) else {
+                app.set_message("No credential selected", MessageType::Error);
+                return None;
+            };
+            app.logs_state.toggle_credential(&name);
+            app.reload_logs();
+            return None;
+        }
+        (KeyCode::Backspace, _) => {
+            state.clear_query();
+            app.reload_logs();
+            return None;
+        }
         _ => {}
     }
 
     let was_pending = state.scroll.pending_g;
     state.scroll.pending_g = false;
 
-    let visible = LogsScreen::visible_height(size) as usize;
+    let visible = LogsScreen::visible_height(size, zoomed) as usize;
     let max_v = state.max_scroll(visible as u16);
-    let visible_width = LogsScreen::visible_width(size);
+    let visible_width = LogsScreen::visible_width(size, zoomed);
     let max_h = state.max_h_scroll(visible_width);
 
     match (code, mods) {
@@ -216,6 +433,22 @@ fn logs_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<
     None
 }
 
+fn logs_search_key(app: &mut App, code: KeyCode, mods: KeyModifiers) {
+    let state = &mut app.logs_state;
+
+    match (code, mods) {
+        (KeyCode::Esc, _) => state.search_input = None,
+        (KeyCode::Enter, _) => match state.submit_search() {
+            Ok(()) => app.reload_logs(),
+            Err(e) => app.set_message(&e, MessageType::Error),
+        },
+        (KeyCode::Char('u'), KeyModifiers::CONTROL) => state.search_input = Some(EditLine::new()),
+        (KeyCode::Backspace, _) => state.pop_char(),
+        (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => state.push_char(c),
+        _ => {}
+    }
+}
+
 fn tags_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
     let size = app.terminal_size;
     let state = &mut app.tags_state;
@@ -260,6 +493,251 @@ fn tags_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<
     None
 }
 
+fn timeline_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let size = app.terminal_size;
+    let state = &mut app.timeline_state;
+
+    match (code, mods) {
+        (KeyCode::Char('H'), KeyModifiers::SHIFT)
+        | (KeyCode::Char('q'), KeyModifiers::NONE)
+        | (KeyCode::Esc, _) => {
+            app.mode_state.to_normal();
+            return None;
+        }
+        (KeyCode::Char('?'), KeyModifiers::NONE | KeyModifiers::SHIFT) => return Some(Action::ShowHelp),
+        _ => {}
+    }
+
+    let was_pending = state.scroll.pending_g;
+    state.scroll.pending_g = false;
+
+    let visible = TimelinePopup::visible_height(size) as usize;
+    let max_v = state.max_scroll(visible as u16);
+
+    match (code, mods) {
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.scroll_down(1, max_v),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.scroll_up(1),
+        (KeyCode::Char('g'), KeyModifiers::NONE) if was_pending => state.home(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) => state.scroll.pending_g = true,
+        (KeyCode::Char('G'), KeyModifiers::SHIFT) => state.end(max_v),
+        (KeyCode::Char('d'), KeyModifiers::CONTROL) => state.scroll_down(visible / 2, max_v),
+        (KeyCode::Char('u'), KeyModifiers::CONTROL) => state.scroll_up(visible / 2),
+        _ => {}
+    }
+
+    None
+}
+
+fn finder_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.finder_state;
+
+    match (code, mods) {
+        (KeyCode::Esc, _) => {
+            state.close();
+            app.mode_state.to_normal();
+        }
+        (KeyCode::Enter, _) => {
+            if let Err(e) = app.open_finder_selection() {
+                app.set_message(&i18n::trf("Error: {}", &[&e]), MessageType::Error);
+            }
+        }
+        (KeyCode::Up, _) | (KeyCode::Char('k' | 'p'), KeyModifiers::CONTROL) => state.move_up(),
+        (KeyCode::Down, _) | (KeyCode::Char('j' | 'n'), KeyModifiers::CONTROL) => state.move_down(),
+        (KeyCode::Char('u'), KeyModifiers::CONTROL) => state.clear_query(),
+        (KeyCode::Backspace, _) => state.pop_char(),
+        (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => state.push_char(c),
+        _ => {}
+    }
+
+    None
+}
+
+fn palette_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.palette_state;
+
+    match (code, mods) {
+        (KeyCode::Esc, _) => {
+            state.close();
+            app.mode_state.to_normal();
+        }
+        (KeyCode::Enter, _) => return app.run_palette_selection(),
+        (KeyCode::Up, _) | (KeyCode::Char('k' | 'p'), KeyModifiers::CONTROL) => state.move_up(),
+        (KeyCode::Down, _) | (KeyCode::Char('j' | 'n'), KeyModifiers::CONTROL) => state.move_down(),
+        (KeyCode::Char('u'), KeyModifiers::CONTROL) => state.clear_query(),
+        (KeyCode::Backspace, _) => state.pop_char(),
+        (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => state.push_char(c),
+        _ => {}
+    }
+
+    None
+}
+
+fn backups_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.backups_state;
+
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => {
+            app.mode_state.to_normal();
+            return None;
+        }
+        (KeyCode::Char('?'), KeyModifiers::NONE | KeyModifiers::SHIFT) => return Some(Action::ShowHelp),
+        _ => {}
+    }
+
+    let was_pending = state.scroll.pending_g;
+    state.scroll.pending_g = false;
+
+    match (code, mods) {
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.scroll_down(),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.scroll_up(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) if was_pending => state.home(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) => state.scroll.pending_g = true,
+        (KeyCode::Char('G'), KeyModifiers::SHIFT) => state.end(),
+        (KeyCode::Char('b'), KeyModifiers::NONE) => app.backup_now(),
+        (KeyCode::Char('i'), KeyModifiers::NONE) => app.inspect_selected_backup(),
+        (KeyCode::Enter, _) => app.request_restore(),
+        _ => {}
+    }
+
+    None
+}
+
+fn dupes_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.dupes_state;
+
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => {
+            app.mode_state.to_normal();
+            return None;
+        }
+        (KeyCode::Char('?'), KeyModifiers::NONE | KeyModifiers::SHIFT) => return Some(Action::ShowHelp),
+        _ => {}
+    }
+
+    let was_pending = state.scroll.pending_g;
+    state.scroll.pending_g = false;
+
+    match (code, mods) {
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.scroll_down(),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.scroll_up(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) if was_pending => state.home(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) => state.scroll.pending_g = true,
+        (KeyCode::Char('G'), KeyModifiers::SHIFT) => state.end(),
+        (KeyCode::Enter, _) => app.open_duplicate(false),
+        (KeyCode::Char('s'), KeyModifiers::NONE) => app.open_duplicate(true),
+        (KeyCode::Char('m'), KeyModifiers::NONE) => app.merge_selected_dupe(),
+        _ => {}
+    }
+
+    None
+}
+
+fn security_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => app.mode_state.to_normal(),
+        (KeyCode::Char('?'), KeyModifiers::NONE | KeyModifiers::SHIFT) => return Some(Action::ShowHelp),
+        _ => {}
+    }
+
+    None
+}
+
+fn tips_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.tips_state;
+
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => app.mode_state.to_normal(),
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.scroll_down(),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.scroll_up(),
+        (KeyCode::Char('?'), KeyModifiers::NONE | KeyModifiers::SHIFT) => return Some(Action::ShowHelp),
+        _ => {}
+    }
+
+    None
+}
+
+fn info_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.info_state;
+
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => app.mode_state.to_normal(),
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.scroll_down(),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.scroll_up(),
+        (KeyCode::Char('?'), KeyModifiers::NONE | KeyModifiers::SHIFT) => return Some(Action::ShowHelp),
+        _ => {}
+    }
+
+    None
+}
+
+fn crypto_info_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.crypto_info_state;
+
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => {
+            app.mode_state.to_normal();
+            return None;
+        }
+        (KeyCode::Char('?'), KeyModifiers::NONE | KeyModifiers::SHIFT) => return Some(Action::ShowHelp),
+        _ => {}
+    }
+
+    let was_pending = state.scroll.pending_g;
+    state.scroll.pending_g = false;
+
+    match (code, mods) {
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.scroll_down(),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.scroll_up(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) if was_pending => state.home(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) => state.scroll.pending_g = true,
+        (KeyCode::Char('G'), KeyModifiers::SHIFT) => state.end(),
+        _ => {}
+    }
+
+    None
+}
+
+fn connection_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.connection_state;
+
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => app.mode_state.to_normal(),
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.move_down(),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.move_up(),
+        (KeyCode::Char(c @ '1'..='9'), KeyModifiers::NONE) => {
+            let index = c as usize - '1' as usize;
+            if index < state.options.len() {
+                state.selected = index;
+                app.copy_connection_string();
+            }
+        }
+        (KeyCode::Enter, _) => app.copy_connection_string(),
+        _ => {}
+    }
+
+    None
+}
+
+fn merge_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.merge_state;
+
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => {
+            state.close();
+            app.mode_state.to_normal();
+        }
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.move_down(),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.move_up(),
+        (KeyCode::Char('h' | 'l' | ' '), KeyModifiers::NONE) | (KeyCode::Left | KeyCode::Right, _) => {
+            state.toggle_selected()
+        }
+        (KeyCode::Enter, _) => app.finish_merge(),
+        _ => {}
+    }
+
+    None
+}
+
 fn handle_tags_select(app: &mut App) -> Option<Action> {
     let tags = if app.tags_state.has_selection() {
         app.tags_state.get_selected_tags()
@@ -276,9 +754,165 @@ fn handle_tags_select(app: &mut App) -> Option<Action> {
     None
 }
 
+fn dispatch_generator_key(form: &mut CredentialForm, code: KeyCode, mods: KeyModifiers) {
+    let Some(generator) = form.generator.as_mut() else {
+        return;
+    };
+    match (code, mods) {
+        (KeyCode::Esc, _) => form.close_generator(),
+        (KeyCode::Enter, _) => form.accept_generated(),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) | (KeyCode::BackTab, _) => generator.move_up(),
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) | (KeyCode::Tab, _) => generator.move_down(),
+        (KeyCode::Char('l' | '+'), _) | (KeyCode::Right, _) => generator.increase(),
+        (KeyCode::Char('h' | '-'), _) | (KeyCode::Left, _) => generator.decrease(),
+        (KeyCode::Char(' '), _) => generator.toggle(),
+        (KeyCode::Char('r'), _) | (KeyCode::Char('g'), KeyModifiers::CONTROL) => generator.regenerate(),
+        _ => {}
+    }
+}
+
 // Helper trait for CredentialForm
 impl crate::ui::components::CredentialForm {
     pub fn is_select_field(&self) -> bool {
         self.active_field().field_type == crate::ui::components::form::FieldType::Select
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::components::statusline::HintContext;
+
+    #[test]
+    fn test_footer_hint_keys() {
+        let context = HintContext {
+            mode: InputMode::Normal,
+            view: View::List,
+            has_selection: true,
+            filtered: false,
+            searching: false,
+            read_only: false,
+        };
+        // "Enter open │ n new │ / search"
+        assert_eq!(hint_at(context, 0), Some("Enter"));
+        assert_eq!(hint_at(context, 9), Some("Enter"));
+        assert_eq!(hint_at(context, 11), None);
+        assert_eq!(hint_at(context, 13), Some("n"));
+        assert_eq!(hint_at(HintContext { read_only: true, ..context }, 13), Some("/"));
+        // "Enter open │ n next match │ N prev match"
+        assert_eq!(hint_at(HintContext { searching: true, ..context }, 28), Some("N"));
+
+        assert_eq!(hint_key("L"), Some(KeyEvent::new(KeyCode::Char('L'), KeyModifiers::SHIFT)));
+        assert_eq!(hint_key(":"), Some(KeyEvent::new(KeyCode::Char(':'), KeyModifiers::NONE)));
+        assert_eq!(hint_key("Esc"), Some(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert_eq!(hint_key("j/k"), None);
+        assert_eq!(hint_key("C-u"), None);
+    }
+
+    #[test]
+    fn test_lock_key_and_discarded_form() {
+        let mut app = App::new(crate::app::AppConfig::default());
+        let shift_l = KeyEvent::new(KeyCode::Char('L'), KeyModifiers::SHIFT);
+        let ctrl_l = KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL);
+        app.mode_state.to_dupes();
+        assert!(app.is_lock_key(shift_l));
+
+        // In the form `L` is typed, so only Ctrl+l locks
+        app.credential_form = Some(CredentialForm::new());
+        app.view = View::Form;
+        assert!(!app.is_lock_key(shift_l));
+        assert!(app.is_lock_key(ctrl_l));
+
+        app.lock();
+        assert!(app.credential_form.is_none());
+        assert_eq!(app.view, View::List);
+        assert_eq!(app.mode_state.mode, InputMode::Normal);
+    }
+
+    #[test]
+    fn test_edit_asks_before_saving() {
+        use crate::app::test_support::{add_credential, unlocked_app};
+        use crate::vault::audit;
+
+        let (_dir, mut app) = unlocked_app(Default::default(), &[]);
+        let cred = add_credential(&app, "GitHub", Some("me"), &["work"]);
+        app.refresh_data().unwrap();
+        app.go_to_credential(&cred.id).unwrap();
+        app.edit_credential().unwrap();
+
+        let form = app.credential_form.as_mut().unwrap();
+        form.fields[2].value.set("you");
+        form.fields[5].value.set("home");
+        app.submit_form().unwrap();
+        let details = app.pending_action.as_ref().unwrap().prompt().details;
+        assert_eq!(details, ["username   me -> you", "tags       +home -work", "password   unchanged"]);
+
+        // Turning it down keeps the form; saying yes saves and audits the fields
+        app.cancel_pending().unwrap();
+        assert!(app.credential_form.is_some());
+        app.submit_form().unwrap();
+        app.handle_confirm().unwrap();
+        assert!(app.credential_form.is_none());
+        let logs = audit::get_credential_logs(app.vault.db().unwrap().conn(), &cred.id).unwrap();
+        assert!(logs.iter().any(|log| log.details.as_deref() == Some("Changed: username, tags")));
+    }
+
+    #[test]
+    fn test_n_creates_missed_search() {
+        use crate::app::test_support::unlocked_app;
+
+        let (_dir, mut app) = unlocked_app(Default::default(), &["GitHub"]);
+        let n = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE);
+
+        app.search_credentials("git").unwrap();
+        assert_eq!(app.resolve_normal_action(n).0, Action::NextMatch);
+
+        app.search_credentials("acme vpn").unwrap();
+        assert_eq!(app.missed_search(), Some("acme vpn"));
+        let (action, _) = app.resolve_normal_action(n);
+        app.execute_action(action).unwrap();
+        assert_eq!(app.view, View::Form);
+        assert_eq!(app.credential_form.as_ref().unwrap().fields[0].value.as_str(), "acme vpn");
+    }
+
+    #[test]
+    fn test_detail_scrolls_when_focused() {
+        use crate::db::{AccessStats, Credential, CredentialType};
+        use crate::ui::components::detail::SecretDisplay;
+        use crate::ui::components::panes::Pane;
+        use crate::vault::credential::DecryptedCredential;
+
+        let cred = Credential::new("GitHub".to_string(), CredentialType::Password, String::new());
+        let notes = (1..=30).map(|i| format!("line{}", i)).collect::<Vec<_>>().join("\n");
+        let decrypted = DecryptedCredential::from_credential(&cred, None, Some(notes));
+        let mut app = App::new(crate::app::AppConfig::default());
+        app.selected_detail = Some(crate::app::credentials_handler::build_detail(
+            &decrypted,
+            &AccessStats::default(),
+            &[],
+            SecretDisplay::Masked,
+            &[],
+        ));
+        app.screen_areas.detail = Some(ratatui::layout::Rect::new(0, 0, 40, 12));
+        app.view = View::Detail;
+
+        app.run_action(Action::MoveDown).unwrap();
+        assert_eq!(app.detail_scroll.v_scroll, 1);
+        app.execute_counted(Action::MoveDown, Some(3)).unwrap();
+        assert_eq!(app.detail_scroll.v_scroll, 4);
+        app.run_action(Action::MoveToBottom).unwrap();
+        let max = app.selected_detail.as_ref().unwrap().max_scroll(app.screen_areas.detail.unwrap());
+        assert_eq!(app.detail_scroll.v_scroll, max);
+        app.run_action(Action::MoveToTop).unwrap();
+        assert_eq!(app.detail_scroll.v_scroll, 0);
+
+        // With the list focused, or in the list view, j moves the selection
+        app.panes.focus = Pane::List;
+        app.run_action(Action::MoveDown).unwrap();
+        assert_eq!(app.detail_scroll.v_scroll, 0);
+        app.panes.focus = Pane::Detail;
+        app.view = View::List;
+        app.run_action(Action::MoveDown).unwrap();
+        assert_eq!(app.detail_scroll.v_scroll, 0);
+    }
+}
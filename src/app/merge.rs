@@ -0,0 +1,87 @@
+use crate::db::AuditAction;
+use crate::i18n;
+use crate::ui::components::MessageType;
+use crate::vault::credential::{decrypt_credential, get_credential, list_credentials};
+use crate::vault::merge::{self, MergePlan};
+use crate::vault::timeline::merged_details;
+
+use super::{App, AppResult};
+
+impl App {
+    /// `:merge NAME` merges the named credential into the selected one
+    pub(super) fn merge_with(&mut self, name: &str) -> AppResult<()> {
+        let Some(selected) = &self.selected_credential else {
+            self.set_message("No credential selected", MessageType::Error);
+            return Ok(());
+        };
+        let left = selected.id.clone();
+
+        let db = self.vault.db()?;
+        let all = list_credentials(db.conn())?;
+        let Some(other) = all.iter().find(|c| c.name.eq_ignore_ascii_case(name.trim())) else {
+            self.set_message(&i18n::trf("No credential named '{}'", &[&name.trim()]), MessageType::Error);
+            return Ok(());
+        };
+        if other.id == left {
+            self.set_message("Cannot merge a credential with itself", MessageType::Error);
+            return Ok(());
+        }
+        let right = other.id.clone();
+        self.start_merge(&left, &right)
+    }
+
+    /// Merge the selected pair of the duplicates report
+    pub(super) fn merge_selected_dupe(&mut self) {
+        let Some(pair) = self.dupes_state.selected_pair() else { return };
+        let (left, right) = (pair.first.id.clone(), pair.second.id.clone());
+        if let Err(e) = self.start_merge(&left, &right) {
+            self.set_message(&i18n::trf("Failed to start merge: {}", &[&e]), MessageType::Error);
+        }
+    }
+
+    fn start_merge(&mut self, left_id: &str, right_id: &str) -> AppResult<()> {
+        if self.vault.is_read_only() {
+            self.set_message("Vault is open read-only", MessageType::Error);
+            return Ok(());
+        }
+        let db = self.vault.db()?;
+        let key = self.vault.dek()?;
+        let left = decrypt_credential(db.conn(), key, &get_credential(db.conn(), left_id)?, false)?;
+        let right = decrypt_credential(db.conn(), key, &get_credential(db.conn(), right_id)?, false)?;
+
+        self.merge_state.open(MergePlan::new(left, right));
+        self.mode_state.to_merge();
+        Ok(())
+    }
+
+    /// Apply the merge shown in the popup, keeping the left credential
+    pub(super) fn finish_merge(&mut self) {
+        let Some(plan) = self.merge_state.plan.take() else { return };
+        self.mode_state.to_normal();
+        match self.apply_merge(&plan) {
+            Ok(()) => self.set_message(
+                &format!("Merged '{}' into '{}'", plan.right.name, plan.name()),
+                MessageType::Success,
+            ),
+            Err(e) => self.set_message(&i18n::trf("Merge failed: {}", &[&e]), MessageType::Error),
+        }
+    }
+
+    fn apply_merge(&mut self, plan: &MergePlan) -> AppResult<()> {
+        self.backup_before("merge")?;
+        let db = self.vault.db()?;
+        let merged = merge::apply(db.conn(), self.vault.dek()?, plan)?;
+
+        let details = merged_details(&plan.right.name, &plan.changed_fields());
+        self.log_audit(AuditAction::Update, Some(&merged.id), Some(&merged.name), merged.username.as_deref(), Some(&details))?;
+        let removed = format!("Merged into {}", merged.name);
+        self.log_audit(
+            AuditAction::Delete,
+            Some(&plan.right.id),
+            Some(&plan.right.name),
+            plan.right.username.as_deref(),
+            Some(&removed),
+        )?;
+        self.go_to_credential(&merged.id)
+    }
+}
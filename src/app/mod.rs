@@ -3,29 +3,81 @@
 //! Core application logic tying together vault, UI, and input.
 
 mod actions;
+mod backups;
+mod breach;
+mod browser;
 mod clipboard;
+mod commands;
 mod config;
 mod credentials_handler;
+mod draft;
+mod dupes;
+mod error;
+mod hooks;
+mod info;
 mod input;
+mod merge;
+mod pending;
+mod preview;
+mod reauth;
+mod search;
+mod session;
+mod startup;
+mod sync;
+pub mod system_events;
+#[cfg(test)]
+mod test_support;
+mod update;
+mod wipe;
 
+use std::collections::HashMap;
+use std::path::Path;
 use std::time::{Duration, Instant};
 
 use ratatui::layout::Rect;
 use ratatui::Frame;
 
+use crate::crypto::MasterKey;
 use crate::db::models::Credential;
-use crate::db::AuditAction;
+use crate::db::{AuditAction, SearchQuery, SortOrder};
+use crate::hardening::Mitigation;
+use crate::i18n;
+use crate::input::commands::{CommandHistory, Completion};
+use crate::input::keymap::Action;
 use crate::input::modes::ModeState;
-use crate::ui::components::{CredentialDetail, CredentialForm, CredentialItem, ListViewState, MessageType};
+use crate::ui::components::{
+    CredentialDetail, CredentialForm, CredentialItem, ListViewState, MessageType, PaneLayout, SecretDisplay,
+};
+use crate::ui::components::backups::BackupsState;
+use crate::ui::components::connection::ConnectionState;
+use crate::ui::components::cryptoinfo::CryptoInfoState;
+use crate::ui::components::dupes::DupesState;
+use crate::ui::components::finder::FinderState;
+use crate::ui::components::info::InfoState;
+use crate::ui::components::palette::PaletteState;
 use crate::ui::components::help::HelpState;
-use crate::ui::components::logs::LogsState;
+use crate::ui::components::logs::{LogsState, LOGS_LIMIT};
+use crate::ui::components::merge::MergeState;
+use crate::ui::components::scroll::ScrollState;
+use crate::ui::components::statusline::HintContext;
 use crate::ui::components::tags::TagsState;
-use crate::ui::renderer::{Renderer, UiState, View};
+use crate::ui::components::timeline::TimelineState;
+use crate::ui::components::tips::TipsState;
+use crate::ui::renderer::{Renderer, ScreenAreas, UiState, View};
+use crate::vault::backend::Backends;
 use crate::vault::credential::DecryptedCredential;
+use crate::vault::decrypt_cache::DecryptCache;
+use crate::vault::emergency;
+use crate::vault::search_index::MemoryIndex;
 use crate::vault::manager::VaultState;
-use crate::vault::{audit, Vault};
+use crate::vault::fsck::{self, FsckReport};
+use crate::vault::audit::{self, ChainReport};
+use crate::vault::Vault;
 
-pub use config::{AppConfig, PendingAction};
+pub use config::AppConfig;
+pub use error::{AppError, AppResult, Recovery};
+pub use hooks::{run_hooks, EventHook, SecurityEvent};
+pub use update::{Cmd, Msg};
 
 pub struct App {
     pub config: AppConfig,
@@ -33,25 +85,100 @@ pub struct App {
     pub mode_state: ModeState,
     pub view: View,
     pub terminal_size: Rect,
+    pub screen_areas: ScreenAreas,
     pub list_state: ListViewState,
+    /// The listed credentials: all of a filtered list, or the window of
+    /// rows around the cursor when nothing is filtered
     pub credentials: Vec<Credential>,
     pub credential_items: Vec<CredentialItem>,
+    /// List row of `credentials[0]`
+    pub credentials_start: usize,
     pub selected_credential: Option<DecryptedCredential>,
     pub selected_detail: Option<CredentialDetail>,
+    /// Credential an action decrypted while previews stay encrypted
+    pub opened: Option<String>,
+    /// Decrypted detail state was dropped after `wipe_detail_after`
+    pub detail_wiped: bool,
+    /// How far the detail panel is scrolled; back at the top for each credential
+    pub detail_scroll: ScrollState,
     pub message: Option<(String, MessageType, Instant)>,
-    pub pending_action: Option<PendingAction>,
-    pub password_visible: bool,
+    /// Decrypted credentials around the cursor
+    pub decrypt_cache: DecryptCache,
+    /// Text search for vaults that keep no index on disk
+    pub search_index: MemoryIndex,
+    /// Where secrets kept outside the vault are fetched from
+    pub backends: Backends,
+    pub pending_action: Option<pending::PendingConfirm>,
+    pub secret_display: SecretDisplay,
+    /// Screen-share safe mode, see `set_privacy`
+    pub privacy: bool,
     pub should_quit: bool,
     pub credential_form: Option<CredentialForm>,
-    pub wants_password_change: bool,
+    /// Queued for the event loop by the current `update`
+    commands: Vec<Cmd>,
+    pub copier: clipboard::Copier,
+    /// Reports how the last copy left the clipboard
+    clipboard_outcome: Option<std::sync::mpsc::Receiver<clipboard::ClearOutcome>>,
+    /// Clipboard managers found running, once looked for
+    clipboard_managers: Option<Vec<&'static clipboard::Manager>>,
+    /// The warning that a clipboard manager may keep copies was shown
+    clipboard_warned: bool,
+    /// Form open when the vault locked, sealed until unlock, see `draft`
+    draft: Option<draft::Draft>,
+    /// The coming lock was announced to the open form
+    draft_warned: bool,
+    /// A sensitive action waiting for the master password, see `reauth`
+    pub wants_reauth: Option<Action>,
+    /// When the master password was last entered
+    authenticated_at: Option<Instant>,
     pub help_state: HelpState,
     pub logs_state: LogsState,
     pub tags_state: TagsState,
+    pub timeline_state: TimelineState,
+    pub finder_state: FinderState,
+    pub palette_state: PaletteState,
+    pub backups_state: BackupsState,
+    pub dupes_state: DupesState,
+    pub merge_state: MergeState,
+    /// Hardening measures as of the last `:securityinfo`
+    pub security_report: Vec<Mitigation>,
+    pub tips_state: TipsState,
+    pub info_state: InfoState,
+    pub crypto_info_state: CryptoInfoState,
+    /// Schemes offered by `yd` for the selected database credential
+    pub connection_state: ConnectionState,
+    /// When the scheduled backup was last considered
+    last_backup_check: Option<Instant>,
+    pub panes: PaneLayout,
+    pub active_query: Option<SearchQuery>,
+    pub sort_order: SortOrder,
+    pub command_history: CommandHistory,
+    /// Tab completion being cycled through on the command line
+    completion: Option<Completion>,
+    /// Filter in place when `/` was pressed, restored if the search is cancelled
+    search_origin: Option<Option<SearchQuery>>,
+    /// When the incremental search should next run
+    search_due: Option<Instant>,
+    /// The last change made in each view, replayed by `.`
+    repeat_registers: HashMap<View, Action>,
+    /// Work left over from unlocking, see `startup`
+    startup: startup::Startup,
 }
 
 impl App {
     pub fn new(config: AppConfig) -> Self {
-        let vault_config = crate::vault::VaultConfig::with_path(&config.vault_path);
+        let vault_config = crate::vault::VaultConfig {
+            auto_lock_timeout: config.auto_lock_timeout,
+            read_only: config.read_only,
+            wipe_after_failures: config.wipe_after_failures,
+            search_index: config.search_index,
+            encrypt_metadata: config.encrypt_metadata,
+            ..crate::vault::VaultConfig::with_path(&config.vault_path)
+        };
+
+        let panes = PaneLayout::new(config.list_pane_width);
+        let privacy = config.privacy_mode;
+        let backends = Backends::from_commands(&config.secret_backends);
 
         Self {
             vault: Vault::new(vault_config),
@@ -59,20 +186,58 @@ impl App {
             mode_state: ModeState::new(),
             view: View::List,
             terminal_size: Rect::default(),
+            screen_areas: ScreenAreas::default(),
             list_state: ListViewState::new(),
             credentials: Vec::new(),
             credential_items: Vec::new(),
+            credentials_start: 0,
             selected_credential: None,
             selected_detail: None,
+            opened: None,
+            detail_wiped: false,
+            detail_scroll: ScrollState::new(),
             message: None,
+            decrypt_cache: DecryptCache::new(),
+            search_index: MemoryIndex::new(),
+            backends,
             pending_action: None,
-            password_visible: false,
+            secret_display: SecretDisplay::Masked,
+            privacy,
             should_quit: false,
             credential_form: None,
-            wants_password_change: false,
+            draft: None,
+            draft_warned: false,
+            commands: Vec::new(),
+            wants_reauth: None,
+            copier: Box::new(clipboard::copy_with_timeout),
+            clipboard_outcome: None,
+            clipboard_managers: None,
+            clipboard_warned: false,
+            authenticated_at: None,
             help_state: HelpState::new(),
             logs_state: LogsState::new(),
             tags_state: TagsState::new(),
+            timeline_state: TimelineState::new(),
+            finder_state: FinderState::new(),
+            palette_state: PaletteState::new(),
+            backups_state: BackupsState::new(),
+            dupes_state: DupesState::new(),
+            merge_state: MergeState::new(),
+            security_report: Vec::new(),
+            tips_state: TipsState::new(),
+            info_state: InfoState::new(),
+            crypto_info_state: CryptoInfoState::new(),
+            connection_state: ConnectionState::new(),
+            last_backup_check: None,
+            panes,
+            active_query: None,
+            sort_order: SortOrder::default(),
+            command_history: CommandHistory::default(),
+            completion: None,
+            search_origin: None,
+            search_due: None,
+            repeat_registers: HashMap::new(),
+            startup: startup::Startup::Done,
         }
     }
 
@@ -84,47 +249,130 @@ impl App {
         self.vault.state() == VaultState::Locked
     }
 
-    pub fn initialize(&mut self, password: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.vault.initialize(password)?;
+    /// Create the vault from a key derived in the background
+    pub fn initialize(&mut self, master_key: MasterKey, password_hash: String) -> AppResult<()> {
+        self.vault.initialize_with_key(master_key, password_hash)?;
         self.log_audit(AuditAction::Unlock, None, None, None, Some("Vault Initialized!"))?;
         self.refresh_data()
     }
 
-    pub fn unlock(&mut self, password: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.vault.unlock(password)?;
+    /// Take over a vault that was unlocked in the background. The list
+    /// is loaded once the next frame is drawn, see `startup`.
+    pub fn finish_unlock(&mut self, vault: Vault) -> AppResult<()> {
+        self.vault = vault;
+        self.authenticated_at = Some(Instant::now());
+        self.restore_form()?;
         self.handle_failed_attempts()?;
-        self.check_audit_integrity();
+        self.handle_emergency_unlocks()?;
         self.log_audit(AuditAction::Unlock, None, None, None, None)?;
-        self.refresh_data()?;
-        self.update_selected_detail()
+        if !self.vault.fingerprint_matches()? {
+            let msg = "Warning: the vault fingerprint was not made with this vault's key; the file may have been replaced";
+            self.set_message(msg, MessageType::Error);
+        }
+        self.begin_startup();
+        Ok(())
     }
 
-    fn handle_failed_attempts(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn handle_failed_attempts(&mut self) -> AppResult<()> {
         let Some((count, timestamp)) = self.vault.take_pending_failed_attempts()? else {
             return Ok(());
         };
 
         let details = format!("{} unlock attempt(s) on {}", count, timestamp);
         self.log_audit(AuditAction::FailedUnlock, None, None, None, Some(&details))?;
-        self.set_message(&format!("Warning: {} failed unlock attempt(s) detected", count), MessageType::Error);
+        self.set_message(&i18n::trf("Warning: {} failed unlock attempt(s) detected", &[&count]), MessageType::Error);
+        self.fire_event(SecurityEvent::FailedUnlock, &format!("{} failed unlock attempt(s) since the last unlock, last on {}", count, timestamp));
+        Ok(())
+    }
+
+    /// An emergency code opens the vault once; say so to whoever used it,
+    /// and to the owner the next time they unlock
+    fn handle_emergency_unlocks(&mut self) -> AppResult<()> {
+        let slot = self.vault.active_slot().to_string();
+        if emergency::is_emergency_slot(&slot) {
+            self.set_message(&i18n::trf("Unlocked with emergency code {}; it no longer works", &[&slot]), MessageType::Warning);
+            self.fire_event(SecurityEvent::EmergencyUnlock, &format!("Vault unlocked with emergency code {}", slot));
+            return Ok(());
+        }
+        let used = self.vault.take_emergency_unlocks()?;
+        let Some(last) = used.last() else { return Ok(()) };
+        let when = last.at.format("%Y-%m-%d %H:%M").to_string();
+        let msg = match used.len() {
+            1 => i18n::trf("Warning: emergency code {} was used on {} (see :audit)", &[&last.label, &when]),
+            n => i18n::trf("Warning: {} emergency codes were used, last on {} (see :audit)", &[&n, &when]),
+        };
+        self.set_message(&msg, MessageType::Error);
         Ok(())
     }
 
     fn check_audit_integrity(&mut self) {
-        let Ok((tampered, total)) = self.verify_audit_logs() else { return };
-        if tampered == 0 { return }
-        self.set_message(
-            &format!("Warning: {} of {} audit logs may be tampered", tampered, total),
-            MessageType::Error,
-        );
+        let Ok(report) = self.verify_audit_logs() else { return };
+        let Some(first) = report.breaks.first() else { return };
+        let summary = format!("audit log {} ({} problem(s), see :audit)", first, report.breaks.len());
+        self.set_message(&format!("Warning: {}", summary), MessageType::Error);
+        self.fire_event(SecurityEvent::AuditTampered, &summary);
     }
 
     pub fn lock(&mut self) {
+        self.vault.close_inspected();
         let _ = self.log_audit(AuditAction::Lock, None, None, None, None);
+        self.save_session();
+        self.stash_form();
         self.vault.lock();
+        self.authenticated_at = None;
+        self.wants_reauth = None;
+        self.close_for_lock();
         self.clear_credentials();
     }
 
+    /// Drop what was open over the vault: a form that could not be kept as
+    /// a draft is zeroized and discarded, popups and pending confirmations
+    /// closed
+    fn close_for_lock(&mut self) {
+        if let Some(form) = self.credential_form.take() {
+            self.view = form.previous_view;
+            self.set_message("Unsaved changes were discarded when the vault locked", MessageType::Warning);
+        }
+        self.pending_action = None;
+        self.merge_state.close();
+        self.finder_state.close();
+        self.mode_state.to_normal();
+    }
+
+    pub(super) fn record_activity(&mut self) {
+        self.vault.update_activity();
+        self.restore_wiped_detail();
+    }
+
+    pub(super) fn handle_focus_lost(&mut self) {
+        if self.config.lock_on_focus_loss && self.vault.is_unlocked() {
+            self.lock();
+        }
+    }
+
+    /// Lock on suspend; lock and quit on SIGTERM/SIGHUP
+    pub fn handle_system_events(&mut self) {
+        if system_events::shutdown_requested() {
+            if self.vault.is_unlocked() {
+                self.lock();
+            }
+            self.should_quit = true;
+            return;
+        }
+
+        if system_events::take_suspend() {
+            if self.vault.is_unlocked() {
+                self.lock();
+            }
+            system_events::suspend_handled();
+        }
+    }
+
+    fn lock_countdown(&self) -> Option<u64> {
+        let remaining = self.vault.time_until_auto_lock()?;
+        (remaining <= self.config.lock_warning).then(|| remaining.as_secs() + 1)
+    }
+
     pub fn log_audit(
         &self,
         action: AuditAction,
@@ -132,35 +380,93 @@ impl App {
         credential_name: Option<&str>,
         username: Option<&str>,
         details: Option<&str>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> AppResult<()> {
+        // Secrets read from an inspected backup are logged in the vault
+        let db = self.vault.live_db()?;
+        if db.is_read_only() {
+            return Ok(());
+        }
         let keys = self.vault.keys()?;
         let audit_key = keys.derive_audit_key()?;
-        let db = self.vault.db()?;
-        audit::log_action(db.conn(), &audit_key, action, credential_id, credential_name, username, details)?;
+        let Some(backup) = self.vault.inspected() else {
+            audit::log_action(db.conn(), &audit_key, action, credential_id, credential_name, username, details)?;
+            if let (AuditAction::Read | AuditAction::Copy, Some(id)) = (action, credential_id) {
+                crate::vault::credential::touch_credential(db.conn(), id)?;
+            }
+            return Ok(());
+        };
+        let details = match details {
+            Some(details) => format!("{} (from backup {})", details, backup.display()),
+            None => format!("From backup {}", backup.display()),
+        };
+        audit::log_action(db.conn(), &audit_key, action, credential_id, credential_name, username, Some(&details))?;
         Ok(())
     }
 
-    fn verify_audit_logs(&self) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+    /// Run several writes, like a change and its audit entry, as one
+    /// transaction so an error or crash part way leaves none of them.
+    /// Inside another `atomic` call the writes join the outer transaction.
+    pub(super) fn atomic<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> AppResult<T>,
+    ) -> AppResult<T> {
+        let db = self.vault.db()?;
+        if db.is_read_only() || !db.conn().is_autocommit() {
+            return f(self);
+        }
+        db.begin()?;
+        let result = f(self);
+        let db = self.vault.db()?;
+        let committed = match result {
+            Ok(value) => db.commit().map(|()| value).map_err(Into::into),
+            Err(e) => Err(e),
+        };
+        if committed.is_err() {
+            let _ = db.rollback();
+        }
+        committed
+    }
+
+    fn verify_audit_logs(&self) -> AppResult<ChainReport> {
         let keys = self.vault.keys()?;
         let audit_key = keys.derive_audit_key()?;
         let db = self.vault.db()?;
-        let results = audit::verify_all_logs(db.conn(), &audit_key)?;
-        let total = results.len();
-        let tampered = results.iter().filter(|(_, valid)| !valid).count();
-        Ok((tampered, total))
+        Ok(audit::verify_chain(db.conn(), &audit_key)?)
     }
 
-    fn load_audit_logs(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn check_vault(&self, repair: bool) -> AppResult<FsckReport> {
+        let keys = self.vault.keys()?;
+        let audit_key = keys.derive_audit_key()?;
+        let db = self.vault.db()?;
+        Ok(fsck::check_vault(db.conn(), keys.dek(), &audit_key, repair)?)
+    }
+
+    fn load_audit_logs(&mut self) -> AppResult<()> {
         let keys = self.vault.keys()?;
         let _audit_key = keys.derive_audit_key()?;
         let db = self.vault.db()?;
-        let logs = crate::vault::audit::get_recent_logs(db.conn(), 500)?;
+        let logs = audit::search_logs(db.conn(), &self.logs_state.query, LOGS_LIMIT)?;
         self.logs_state.set_logs(logs);
         Ok(())
     }
 
-    fn load_tags(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.tags_state.set_tags_from_credentials(&self.credentials);
+    /// Re-run the logs query after its filters changed
+    pub(crate) fn reload_logs(&mut self) {
+        if let Err(e) = self.load_audit_logs() {
+            self.set_message(&i18n::trf("Error: {}", &[&e]), MessageType::Error);
+        }
+    }
+
+    fn load_timeline(&mut self, credential_id: &str, name: &str) -> AppResult<()> {
+        let db = self.vault.db()?;
+        let entries = crate::vault::timeline::credential_timeline(db.conn(), credential_id)?;
+        self.timeline_state.set_entries(name, entries);
+        Ok(())
+    }
+
+    fn load_tags(&mut self) -> AppResult<()> {
+        let db = self.vault.db()?;
+        self.tags_state.set_tags(crate::vault::search::get_tags_with_counts(db.conn())?);
         Ok(())
     }
 
@@ -169,26 +475,72 @@ impl App {
         self.check_message_expiry();
 
         let message = self.message.as_ref().map(|(m, t, _)| (m.as_str(), *t));
-        let command_buffer = self.mode_state.mode.is_text_input().then(|| self.mode_state.get_buffer());
-        let confirm_message = self.pending_action.as_ref().map(|a| a.confirm_message());
+        let command_buffer = self.mode_state.mode.is_text_input().then_some(&self.mode_state.line);
+        let lock_countdown = self.lock_countdown();
+        let filter_chips = self.active_query.as_ref().map(|q| q.chips()).unwrap_or_default();
+        let hints = self.hint_context();
+        let loading = self.is_loading();
+        let migrating = self.is_migrating();
+        let vault_name = self.config.vault_path.file_stem().map(|s| s.to_string_lossy().into_owned());
+        let inspecting = self.vault.inspected().and_then(Path::file_name).map(|s| s.to_string_lossy().into_owned());
 
         let mut state = UiState {
             view: self.view,
             mode: self.mode_state.mode,
             credentials: &self.credential_items,
+            credentials_start: self.credentials_start,
+            list_columns: &self.config.list_columns,
+            search_term: self.active_query.as_ref().and_then(SearchQuery::free_text),
             list_state: &mut self.list_state,
             selected_detail: self.selected_detail.as_ref(),
+            detail_wiped: self.detail_wiped,
+            detail_scroll: self.detail_scroll.v_scroll,
             command_buffer,
             message,
-            confirm_message,
+            confirm: self.pending_action.as_ref().map(pending::PendingConfirm::prompt),
             password_prompt: None,
             credential_form: self.credential_form.as_ref(),
             help_state: &self.help_state,
             logs_state: &self.logs_state,
             tags_state: &self.tags_state,
+            timeline_state: &self.timeline_state,
+            finder_state: &self.finder_state,
+            palette_state: &self.palette_state,
+            backups_state: &self.backups_state,
+            dupes_state: &self.dupes_state,
+            merge_state: &self.merge_state,
+            security_report: &self.security_report,
+            tips_state: &self.tips_state,
+            info_state: &self.info_state,
+            crypto_info_state: &self.crypto_info_state,
+            migrating,
+            connection_state: &self.connection_state,
+            panes: &self.panes,
+            lock_countdown,
+            filter_chips: &filter_chips,
+            read_only: self.vault.is_read_only(),
+            inspecting: inspecting.as_deref(),
+            private: self.privacy,
+            vault_name: vault_name.as_deref(),
+            hints,
+            loading,
+            areas: &mut self.screen_areas,
         };
 
         Renderer::render(frame, &mut state);
+        self.painted();
+    }
+
+    /// What the footer hints are chosen from
+    pub fn hint_context(&self) -> HintContext {
+        HintContext {
+            mode: self.mode_state.mode,
+            view: self.view,
+            has_selection: self.list_state.selected().is_some(),
+            filtered: self.active_query.is_some(),
+            searching: self.search_term().is_some(),
+            read_only: self.vault.is_read_only(),
+        }
     }
 
     fn check_message_expiry(&mut self) {
@@ -203,6 +555,22 @@ impl App {
     }
 
     pub fn set_message(&mut self, msg: &str, msg_type: MessageType) {
-        self.message = Some((msg.to_string(), msg_type, Instant::now()));
+        self.message = Some((i18n::tr(msg).to_string(), msg_type, Instant::now()));
+    }
+
+    /// Tell the user about an error a key or mouse handler gave up with,
+    /// and get back to a state the next key can work from
+    pub fn report_error(&mut self, err: AppError) {
+        match err.recovery() {
+            Recovery::Unlock => {
+                self.lock();
+                self.set_message("Vault locked; unlock to continue", MessageType::Warning);
+            }
+            Recovery::CheckVault => {
+                let msg = i18n::trf("{} (the vault may be damaged; run :fsck)", &[&err]);
+                self.set_message(&msg, MessageType::Error);
+            }
+            Recovery::Report => self.set_message(&i18n::trf("Error: {}", &[&err]), MessageType::Error),
+        }
     }
 }
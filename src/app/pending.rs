@@ -0,0 +1,257 @@
+//! Pending actions
+//!
+//! Actions that wait on an answer in the confirm dialog. Each one knows how
+//! to describe itself and what to do when confirmed or turned down, so new
+//! confirmations only need a type implementing [`PendingAction`].
+
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::i18n;
+use crate::input::keymap::{confirm_action, Action};
+use crate::input::line::EditLine;
+use crate::ui::components::layout::truncate_with_ellipsis;
+use crate::ui::components::MessageType;
+use crate::ui::renderer::ConfirmPrompt;
+
+use super::{App, AppResult};
+
+type ActionResult = AppResult<()>;
+
+/// Longest credential name quoted in a confirmation
+const CONFIRM_NAME_LEN: usize = 30;
+
+pub trait PendingAction {
+    /// Question shown in the confirm dialog
+    fn confirm_message(&self) -> String;
+
+    /// Lines shown under the question, such as what an edit changes
+    fn confirm_details(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Text the user has to type before confirming, for actions that are
+    /// hard to take back
+    fn confirm_phrase(&self) -> Option<&str> {
+        None
+    }
+
+    /// Whether an unanswered confirmation is cancelled after
+    /// `confirm_timeout`
+    fn expires(&self) -> bool {
+        true
+    }
+
+    fn confirm(self: Box<Self>, app: &mut App) -> ActionResult;
+
+    /// Answering no, or letting the confirmation time out
+    fn cancel(self: Box<Self>, _app: &mut App) -> ActionResult {
+        Ok(())
+    }
+}
+
+/// The action awaiting an answer, with what has been typed towards its
+/// confirm phrase
+pub struct PendingConfirm {
+    action: Box<dyn PendingAction>,
+    deadline: Option<Instant>,
+    typed: EditLine,
+}
+
+impl PendingConfirm {
+    fn phrase_matches(&self) -> bool {
+        self.action.confirm_phrase().is_none_or(|phrase| self.typed.as_str() == phrase)
+    }
+
+    pub fn prompt(&self) -> ConfirmPrompt<'_> {
+        ConfirmPrompt {
+            message: self.action.confirm_message(),
+            details: self.action.confirm_details(),
+            phrase: self.action.confirm_phrase(),
+            typed: &self.typed,
+            remaining: self.deadline.map(|d| d.saturating_duration_since(Instant::now()).as_secs() + 1),
+        }
+    }
+}
+
+pub struct DeleteCredential {
+    pub id: String,
+    pub name: String,
+}
+
+impl PendingAction for DeleteCredential {
+    fn confirm_message(&self) -> String {
+        format!("Delete '{}'?", truncate_with_ellipsis(&self.name, CONFIRM_NAME_LEN))
+    }
+
+    fn confirm(self: Box<Self>, app: &mut App) -> ActionResult {
+        app.delete_credential(&self.id)
+    }
+}
+
+pub struct RestoreBackup(pub PathBuf);
+
+impl PendingAction for RestoreBackup {
+    fn confirm_message(&self) -> String {
+        "Restore this backup? The vault will lock.".to_string()
+    }
+
+    fn confirm(self: Box<Self>, app: &mut App) -> ActionResult {
+        app.restore_backup(&self.0)
+    }
+}
+
+/// Removing a key slot can lock someone out, so its label has to be typed
+pub struct RemoveKeySlot(pub String);
+
+impl PendingAction for RemoveKeySlot {
+    fn confirm_message(&self) -> String {
+        format!("Remove key slot '{}'? Its password will stop working.", self.0)
+    }
+
+    fn confirm_phrase(&self) -> Option<&str> {
+        Some(&self.0)
+    }
+
+    fn confirm(self: Box<Self>, app: &mut App) -> ActionResult {
+        app.remove_key_slot(&self.0)
+    }
+}
+
+/// Credential id and the URL to open
+pub struct OpenUrl {
+    pub id: String,
+    pub url: String,
+}
+
+impl PendingAction for OpenUrl {
+    fn confirm_message(&self) -> String {
+        format!("Open {} in the browser?", self.url)
+    }
+
+    fn confirm(self: Box<Self>, app: &mut App) -> ActionResult {
+        app.open_url(&self.id, &self.url)
+    }
+}
+
+/// A new credential looks like this existing one
+pub struct EditDuplicate {
+    pub id: String,
+    pub name: String,
+}
+
+impl PendingAction for EditDuplicate {
+    fn confirm_message(&self) -> String {
+        format!("'{}' looks like the same login. Edit it instead? n saves a new one", self.name)
+    }
+
+    /// Nothing is lost while the form waits, so there is no need to hurry
+    fn expires(&self) -> bool {
+        false
+    }
+
+    fn confirm(self: Box<Self>, app: &mut App) -> ActionResult {
+        app.edit_duplicate(&self.id)
+    }
+
+    /// Answering no to the duplicate warning still saves the new credential
+    fn cancel(self: Box<Self>, app: &mut App) -> ActionResult {
+        app.save_credential_form()
+    }
+}
+
+/// Saving an edited credential, shown with what changes
+pub struct SaveEdit {
+    pub name: String,
+    /// One line per changed field, see `edit_summary`
+    pub changes: Vec<String>,
+}
+
+impl PendingAction for SaveEdit {
+    fn confirm_message(&self) -> String {
+        format!("Save changes to '{}'?", truncate_with_ellipsis(&self.name, CONFIRM_NAME_LEN))
+    }
+
+    fn confirm_details(&self) -> Vec<String> {
+        self.changes.clone()
+    }
+
+    /// The form stays open meanwhile, so nothing is lost by waiting
+    fn expires(&self) -> bool {
+        false
+    }
+
+    fn confirm(self: Box<Self>, app: &mut App) -> ActionResult {
+        app.save_credential_form()
+    }
+
+    fn cancel(self: Box<Self>, app: &mut App) -> ActionResult {
+        app.set_message("Not saved; the form is still open", MessageType::Info);
+        Ok(())
+    }
+}
+
+impl App {
+    /// Ask for confirmation before running `action`
+    pub(super) fn request_confirm(&mut self, action: impl PendingAction + 'static) {
+        let timeout = self.config.confirm_timeout;
+        let deadline = (action.expires() && !timeout.is_zero()).then(|| Instant::now() + timeout);
+        self.pending_action = Some(PendingConfirm { action: Box::new(action), deadline, typed: EditLine::new() });
+        self.mode_state.to_confirm();
+    }
+
+    pub(super) fn handle_confirm(&mut self) -> ActionResult {
+        if self.pending_action.as_ref().is_some_and(|p| !p.phrase_matches()) {
+            self.set_message("Type the name shown to confirm", MessageType::Error);
+            return Ok(());
+        }
+        self.mode_state.to_normal();
+        match self.pending_action.take() {
+            Some(pending) => pending.action.confirm(self),
+            None => Ok(()),
+        }
+    }
+
+    pub(super) fn cancel_pending(&mut self) -> ActionResult {
+        self.mode_state.to_normal();
+        match self.pending_action.take() {
+            Some(pending) => pending.action.cancel(self),
+            None => Ok(()),
+        }
+    }
+
+    /// Cancel a confirmation nobody answered in time
+    pub(super) fn expire_pending_action(&mut self) {
+        let expired = self.pending_action.as_ref().and_then(|p| p.deadline).is_some_and(|d| Instant::now() >= d);
+        if !expired {
+            return;
+        }
+        match self.cancel_pending() {
+            Ok(()) => self.set_message("Confirmation timed out", MessageType::Warning),
+            Err(e) => self.set_message(&i18n::trf("Cancel failed: {}", &[&e]), MessageType::Error),
+        }
+    }
+
+    /// Keys in the confirm dialog. Actions with a confirm phrase take typed
+    /// text, so y and n are just letters there.
+    pub(super) fn confirm_key_action(&mut self, key: KeyEvent) -> Action {
+        let Some(pending) = self.pending_action.as_mut().filter(|p| p.action.confirm_phrase().is_some()) else {
+            return confirm_action(key);
+        };
+        match key.code {
+            KeyCode::Enter => Action::Confirm,
+            KeyCode::Esc => Action::Cancel,
+            KeyCode::Backspace => {
+                pending.typed.backspace();
+                Action::None
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                pending.typed.insert(c);
+                Action::None
+            }
+            _ => Action::None,
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Preview Decryption
+//!
+//! Moving through the list shows each credential beside it. The preview
+//! decrypts its notes but never its secret, which stays encrypted until an
+//! action that uses it opens the credential: Enter, revealing, copying,
+//! editing or rotating. Moving to another credential closes it again.
+//! Preview reads are told apart from opening: with `audit_previews` each
+//! one is logged as a `preview`, while opening, revealing and copying stay
+//! `read` and `copy`. `preview_decryption` off leaves the preview to what
+//! is stored in plaintext.
+
+use crate::db::AuditAction;
+use crate::input::keymap::Action;
+use crate::vault::credential::DecryptedCredential;
+
+use super::{App, AppResult};
+
+impl App {
+    /// Whether `action` works on the selected credential's secret
+    fn opens_selected(action: &Action) -> bool {
+        matches!(
+            action,
+            Action::Select
+                | Action::CopyPassword
+                | Action::CopyTotp
+                | Action::CopyPrivateKey
+                | Action::CopyConnectionString
+                | Action::CopyField(_)
+                | Action::TogglePasswordVisibility
+                | Action::TogglePartialReveal
+                | Action::Edit
+                | Action::Rotate
+        )
+    }
+
+    /// Open the selected credential ahead of an action that needs it
+    pub(super) fn open_for(&mut self, action: &Action) -> AppResult<()> {
+        match Self::opens_selected(action) {
+            true => self.open_selected(),
+            false => Ok(()),
+        }
+    }
+
+    /// Decrypt the selected credential's secret, unless it already is
+    pub(super) fn open_selected(&mut self) -> AppResult<()> {
+        let Some(id) = self.list_state.selected().and_then(|i| self.credential_at(i)).map(|c| c.id.clone()) else {
+            return Ok(());
+        };
+        if self.opened.as_deref() != Some(id.as_str()) {
+            self.opened = Some(id);
+            self.update_selected_detail()?;
+        }
+        Ok(())
+    }
+
+    /// Log a preview the first time the cursor lands on a credential
+    pub(super) fn audit_preview(&mut self, cred: &DecryptedCredential) -> AppResult<()> {
+        let moved = self.selected_credential.as_ref().is_none_or(|c| c.id != cred.id);
+        if !self.config.audit_previews || !moved {
+            return Ok(());
+        }
+        self.log_audit(AuditAction::Preview, Some(&cred.id), Some(&cred.name), cred.username.as_deref(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::ExposeSecret;
+
+    use super::*;
+    use crate::app::test_support::unlocked_app;
+    use crate::app::AppConfig;
+
+    fn app(config: AppConfig) -> (tempfile::TempDir, App) {
+        let (dir, mut app) = unlocked_app(config, &["Bank", "GitHub"]);
+        app.update_selected_detail().unwrap();
+        (dir, app)
+    }
+
+    fn actions(app: &App) -> Vec<AuditAction> {
+        let logs = crate::vault::audit::get_all_logs(app.vault.db().unwrap().conn()).unwrap();
+        logs.into_iter().map(|log| log.action).collect()
+    }
+
+    #[test]
+    fn test_secret_decrypted_on_open() {
+        let (_dir, mut app) = app(AppConfig::default());
+        let preview = app.selected_credential.clone().unwrap();
+        assert!(preview.secret.is_none());
+        assert_eq!(preview.notes.unwrap().expose_secret(), "memo");
+        assert!(app.selected_detail.as_ref().unwrap().encrypted);
+
+        app.execute_action(Action::Select).unwrap();
+        assert_eq!(app.selected_credential.as_ref().unwrap().secret.as_ref().unwrap().expose_secret(), "s3cret");
+        assert!(!app.selected_detail.as_ref().unwrap().encrypted);
+
+        app.view = crate::ui::renderer::View::List;
+        app.execute_action(Action::MoveDown).unwrap();
+        app.execute_action(Action::MoveUp).unwrap();
+        assert_eq!(app.selected_credential.as_ref().unwrap().id, preview.id);
+        assert!(app.selected_credential.as_ref().unwrap().secret.is_none());
+    }
+
+    #[test]
+    fn test_preview_decryption_off() {
+        let (_dir, mut app) = app(AppConfig { preview_decryption: false, ..AppConfig::default() });
+        assert!(app.selected_credential.as_ref().unwrap().notes.is_none());
+
+        app.execute_action(Action::Select).unwrap();
+        let opened = app.selected_credential.clone().unwrap();
+        assert!(opened.secret.is_some());
+        assert_eq!(actions(&app), [AuditAction::Read]);
+
+        app.view = crate::ui::renderer::View::List;
+        app.execute_action(Action::MoveDown).unwrap();
+        assert_ne!(app.selected_credential.as_ref().unwrap().id, opened.id);
+        assert!(app.selected_credential.as_ref().unwrap().secret.is_none());
+    }
+
+    #[test]
+    fn test_audit_previews() {
+        let (_dir, mut app) = app(AppConfig { audit_previews: true, ..AppConfig::default() });
+        app.execute_action(Action::MoveDown).unwrap();
+        app.update_selected_detail().unwrap();
+        app.execute_action(Action::Select).unwrap();
+        assert_eq!(actions(&app), [AuditAction::Preview, AuditAction::Preview, AuditAction::Read]);
+    }
+}
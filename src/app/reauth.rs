@@ -0,0 +1,93 @@
+//! Re-authentication
+//!
+//! Exporting, except a CSV without secrets, revealing a secret, removing
+//! a key slot, turning privacy mode off and changing saved settings (the
+//! auto-lock timeout, where the vault lives) ask for the master password
+//! again unless it was entered within `reauth_grace`, the way sudo caches
+//! credentials. Unlocking counts as entering it.
+
+use std::time::Instant;
+
+use crate::input::keymap::Action;
+use crate::ui::components::SecretDisplay;
+
+use super::{App, AppResult, Cmd};
+
+impl App {
+    /// Whether `action` has to wait for the master password. Hiding things
+    /// again never does.
+    fn is_sensitive(&self, action: &Action) -> bool {
+        match action {
+            Action::Export(_) | Action::OtpExport { .. } | Action::KeySlotRemove(_) => true,
+            Action::SetAutoLock(_) | Action::VaultMove(_) => true,
+            Action::ExportCsv { fields, .. } => fields.iter().any(|f| f.is_secret()),
+            Action::TogglePasswordVisibility => !self.privacy && self.secret_display != SecretDisplay::Full,
+            Action::Privacy(on) => self.privacy && !on.unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    /// Whether `action` may run now. A sensitive action outside the grace
+    /// period is held until the password has been entered again.
+    pub(super) fn authorize(&mut self, action: &Action) -> bool {
+        let recent = self.authenticated_at.is_some_and(|at| at.elapsed() < self.config.reauth_grace);
+        if recent || !self.is_sensitive(action) {
+            return true;
+        }
+        self.wants_reauth = Some(action.clone());
+        self.emit(Cmd::Reauthenticate);
+        false
+    }
+
+    /// The password was entered again; run the action that waited for it
+    pub(super) fn reauthenticated(&mut self) -> AppResult<bool> {
+        self.authenticated_at = Some(Instant::now());
+        match self.wants_reauth.take() {
+            Some(action) => self.run_action(action),
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::app::AppConfig;
+
+    #[test]
+    fn test_authorize() {
+        let config = AppConfig { reauth_grace: Duration::from_secs(60), ..AppConfig::default() };
+        let mut app = App::new(config);
+        assert!(app.authorize(&Action::MoveDown));
+        assert!(!app.authorize(&Action::Export("copy.db".into())));
+        assert_eq!(app.wants_reauth, Some(Action::Export("copy.db".into())));
+
+        // Within the grace period nothing is asked again
+        app.authenticated_at = Some(Instant::now());
+        assert!(app.authorize(&Action::TogglePasswordVisibility));
+        app.authenticated_at = Some(Instant::now() - Duration::from_secs(61));
+        assert!(!app.authorize(&Action::TogglePasswordVisibility));
+
+        // Masking again is always allowed
+        app.secret_display = SecretDisplay::Full;
+        assert!(app.authorize(&Action::TogglePasswordVisibility));
+        assert!(app.authorize(&Action::Privacy(Some(true))));
+        app.privacy = true;
+        assert!(!app.authorize(&Action::Privacy(None)));
+    }
+
+    #[test]
+    fn test_settings_need_reauth() {
+        let config = AppConfig { reauth_grace: Duration::from_secs(60), ..AppConfig::default() };
+        let mut app = App::new(config);
+        let stretch = Action::SetAutoLock(Duration::from_secs(24 * 3600));
+        assert!(!app.authorize(&stretch));
+        assert_eq!(app.wants_reauth, Some(stretch.clone()));
+        assert!(!app.authorize(&Action::VaultMove("/tmp/elsewhere.db".into())));
+
+        app.authenticated_at = Some(Instant::now());
+        assert!(app.authorize(&stretch));
+    }
+}
@@ -0,0 +1,115 @@
+use std::time::{Duration, Instant};
+
+use crate::db::SearchQuery;
+use crate::i18n;
+use crate::input::InputMode;
+use crate::ui::components::MessageType;
+
+use super::{App, AppResult};
+
+/// Pause after the last keystroke before the list is filtered
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(150);
+
+impl App {
+    /// Open the search line, remembering the filter to go back to if the
+    /// search is cancelled
+    pub(super) fn start_search(&mut self) {
+        self.search_origin = Some(self.active_query.clone());
+        self.search_due = None;
+        self.mode_state.to_search();
+    }
+
+    /// Filter again once typing pauses
+    pub(super) fn schedule_search(&mut self) {
+        if self.mode_state.mode == InputMode::Search {
+            self.search_due = Some(Instant::now() + SEARCH_DEBOUNCE);
+        }
+    }
+
+    /// Run a scheduled incremental search. Queries that do not parse yet,
+    /// like a half-typed date, leave the list as it is.
+    pub(super) fn run_pending_search(&mut self) {
+        let Some(due) = self.search_due else { return };
+        if Instant::now() < due {
+            return;
+        }
+        self.search_due = None;
+        if self.mode_state.mode != InputMode::Search {
+            return;
+        }
+
+        let buffer = self.mode_state.get_buffer().to_string();
+        if SearchQuery::parse(&buffer).is_err() {
+            return;
+        }
+        if let Err(e) = self.search_credentials(&buffer) {
+            self.set_message(&i18n::trf("Search failed: {}", &[&e]), MessageType::Error);
+        }
+    }
+
+    /// Enter keeps the search, so there is nothing left to restore
+    pub(super) fn finish_search(&mut self) {
+        self.search_due = None;
+        self.search_origin = None;
+    }
+
+    /// Esc puts back the filter that was active before `/`
+    pub(super) fn cancel_search(&mut self) -> AppResult<()> {
+        self.search_due = None;
+        match self.search_origin.take() {
+            Some(Some(query)) => {
+                self.apply_query(query)?;
+                self.update_selected_detail()
+            }
+            Some(None) if self.active_query.is_some() => {
+                self.refresh_data()?;
+                self.update_selected_detail()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Free text of the active search, which the list highlights
+    pub fn search_term(&self) -> Option<&str> {
+        self.active_query.as_ref().and_then(SearchQuery::free_text)
+    }
+
+    /// Free text of an active search that matched nothing, which `n`
+    /// offers as the name of a new credential
+    pub fn missed_search(&self) -> Option<&str> {
+        self.search_term().filter(|_| self.credential_items.is_empty())
+    }
+
+    /// `n` and `N` move to the next or previous listed credential whose
+    /// name or username contains the search text, wrapping around
+    pub(super) fn jump_to_match(&mut self, forward: bool) -> AppResult<()> {
+        let Some(term) = self.search_term().map(str::to_string) else {
+            self.set_message("No active search", MessageType::Info);
+            return Ok(());
+        };
+        let total = self.credential_items.len();
+        let start = self.list_state.selected().unwrap_or(0);
+        let found = (1..=total)
+            .map(|step| match forward {
+                true => (start + step) % total,
+                false => (start + total - step) % total,
+            })
+            .find(|&i| self.credential_items[i].matches(&term));
+
+        let Some(index) = found else {
+            self.set_message(&i18n::trf("Pattern not found: {}", &[&term]), MessageType::Error);
+            return Ok(());
+        };
+        let wrapped = match forward {
+            true => index <= start,
+            false => index >= start,
+        };
+        self.list_state.select(Some(index));
+        self.update_selected_detail()?;
+        if wrapped {
+            let edge = if forward { "bottom, continuing at top" } else { "top, continuing at bottom" };
+            self.set_message(&i18n::trf("Search hit {}", &[&edge]), MessageType::Warning);
+        }
+        Ok(())
+    }
+}
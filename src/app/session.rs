@@ -0,0 +1,180 @@
+//! Last Session
+//!
+//! Where the list was left: its filter, sort order, selected credential
+//! and scroll position. Saved in the vault's metadata, encrypted with the
+//! data key, whenever the vault locks or the app quits, and put back
+//! after the next unlock. `startup_view` can instead start on the whole
+//! list, or on a search such as `tag:favorite`; the sort order is kept
+//! either way.
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{decrypt_string_bound, encrypt_string_bound};
+use crate::db::{SearchQuery, SortOrder};
+use crate::i18n;
+use crate::ui::components::MessageType;
+
+use super::{App, AppResult};
+
+/// Associated data the session is sealed with
+const SESSION_KEY: &str = "last_session";
+
+/// What the list shows after unlocking
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum StartupView {
+    /// Every credential, from the top
+    All,
+    /// The list as it was left
+    #[default]
+    Last,
+    /// The results of a search
+    Search(String),
+}
+
+impl From<String> for StartupView {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "all" => Self::All,
+            "last" => Self::Last,
+            _ => Self::Search(value),
+        }
+    }
+}
+
+impl From<StartupView> for String {
+    fn from(view: StartupView) -> Self {
+        match view {
+            StartupView::All => "all".to_string(),
+            StartupView::Last => "last".to_string(),
+            StartupView::Search(query) => query,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Session {
+    /// The filter, as typed
+    query: Option<String>,
+    sort: SortOrder,
+    /// Id of the credential under the cursor
+    selected: Option<String>,
+    /// First row on screen
+    offset: usize,
+}
+
+impl App {
+    /// Seal where the list is, ahead of locking or quitting
+    pub(super) fn save_session(&self) {
+        if self.vault.is_read_only() {
+            return;
+        }
+        let Ok(dek) = self.vault.dek() else { return };
+        let session = Session {
+            query: self.active_query.as_ref().map(|q| q.to_string()),
+            sort: self.sort_order,
+            selected: self.list_state.selected().and_then(|i| self.credential_at(i)).map(|c| c.id.clone()),
+            offset: self.list_state.scroll_offset(),
+        };
+        let Ok(json) = serde_json::to_string(&session) else { return };
+        let Ok(sealed) = encrypt_string_bound(dek.as_ref(), &json, SESSION_KEY.as_bytes()) else { return };
+        let _ = self.vault.save_session(&sealed);
+    }
+
+    /// The saved session, if there is one that opens
+    fn load_session(&self) -> Option<Session> {
+        let sealed = self.vault.saved_session().ok()??;
+        let json = decrypt_string_bound(self.vault.dek().ok()?.as_ref(), &sealed, SESSION_KEY.as_bytes()).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    /// Load the first page of the list the way `startup_view` asks
+    pub(super) fn restore_session(&mut self) -> AppResult<()> {
+        let saved = self.load_session();
+        if let Some(session) = &saved {
+            self.sort_order = session.sort;
+        }
+        let (query, selected, offset) = match &self.config.startup_view {
+            StartupView::All => (None, None, 0),
+            StartupView::Last => saved.map(|s| (s.query, s.selected, s.offset)).unwrap_or_default(),
+            StartupView::Search(query) => (Some(query.clone()), None, 0),
+        };
+
+        match query.as_deref().map(SearchQuery::parse) {
+            Some(Ok(query)) if !query.is_empty() => self.apply_query(query)?,
+            Some(Err(e)) => {
+                self.refresh_data()?;
+                self.set_message(&i18n::trf("Startup view: {}", &[&e]), MessageType::Error);
+            }
+            _ => self.refresh_data()?,
+        }
+        let Some(index) = selected.map(|id| self.list_position(&id)).transpose()?.flatten() else {
+            return Ok(());
+        };
+        self.list_state.select(Some(index));
+        self.list_state.set_scroll_offset(offset);
+        self.ensure_window()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::{add_credential, reopened_app, unlocked_app};
+    use crate::app::AppConfig;
+
+    fn app_with_credentials() -> (tempfile::TempDir, App) {
+        let (dir, app) = unlocked_app(AppConfig::default(), &[]);
+        for (name, tag) in [("Bank", "home"), ("GitHub", "work"), ("Jira", "work"), ("Stripe", "work")] {
+            add_credential(&app, name, None, &[tag]);
+        }
+        (dir, app)
+    }
+
+    fn reopen(dir: &tempfile::TempDir, startup_view: StartupView) -> App {
+        reopened_app(dir.path(), AppConfig { startup_view, ..AppConfig::default() })
+    }
+
+    fn selected_name(app: &App) -> Option<String> {
+        app.list_state.selected().and_then(|i| app.credential_at(i)).map(|c| c.name.clone())
+    }
+
+    #[test]
+    fn test_restore_session() {
+        let (dir, mut app) = app_with_credentials();
+        app.restore_session().unwrap();
+        app.search_credentials("tag:work").unwrap();
+        app.sort_order = SortOrder::Type;
+        app.list_state.select(Some(1));
+        app.save_session();
+        app.vault.lock();
+
+        let mut app = reopen(&dir, StartupView::Last);
+        app.restore_session().unwrap();
+        assert_eq!(app.active_query.as_ref().map(|q| q.to_string()).as_deref(), Some("tag:work"));
+        assert_eq!(app.sort_order, SortOrder::Type);
+        assert_eq!(selected_name(&app).as_deref(), Some("Jira"));
+        app.vault.lock();
+
+        let mut app = reopen(&dir, StartupView::All);
+        app.restore_session().unwrap();
+        assert!(app.active_query.is_none());
+        assert_eq!(app.sort_order, SortOrder::Type);
+        app.vault.lock();
+
+        let mut app = reopen(&dir, StartupView::from("tag:home".to_string()));
+        app.restore_session().unwrap();
+        assert_eq!(app.credentials.len(), 1);
+        assert_eq!(selected_name(&app).as_deref(), Some("Bank"));
+    }
+
+    #[test]
+    fn test_session_is_sealed() {
+        let (_dir, mut app) = app_with_credentials();
+        app.restore_session().unwrap();
+        app.search_credentials("tag:work").unwrap();
+        app.save_session();
+        let stored = app.vault.saved_session().unwrap().unwrap();
+        assert!(!stored.contains("work"));
+    }
+}
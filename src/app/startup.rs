@@ -0,0 +1,116 @@
+//! Deferred Startup
+//!
+//! Unlocking only derives the key and opens the database. The rest of
+//! what a session needs waits until the list has been drawn, then runs
+//! one step per tick: the first page of the list, which shows skeleton
+//! rows until then, the audit chain check, and bringing the search index
+//! in line with the config. The unlock dialog so closes as soon as the
+//! key is derived, even on a slow disk or a large vault.
+//!
+//! Last, ciphertexts sealed in an older format are re-encrypted in the
+//! current one, a batch per tick until none are left.
+
+use crate::vault::credential;
+
+use super::{App, AppResult};
+
+/// Credentials, and archived secrets, re-encrypted per tick
+const MIGRATE_BATCH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Startup {
+    /// Nothing drawn since the vault was unlocked
+    Unpainted,
+    LoadList,
+    CheckAudit,
+    SearchIndex,
+    /// Re-encrypting old ciphertexts
+    Migrate,
+    Done,
+}
+
+impl App {
+    pub(super) fn begin_startup(&mut self) {
+        self.startup = Startup::Unpainted;
+    }
+
+    /// Whether the list is still waiting for its first page
+    pub fn is_loading(&self) -> bool {
+        matches!(self.startup, Startup::Unpainted | Startup::LoadList) && self.active_query.is_none()
+    }
+
+    /// Called once a frame is on screen
+    pub(super) fn painted(&mut self) {
+        if self.startup == Startup::Unpainted {
+            self.startup = Startup::LoadList;
+        }
+    }
+
+    /// Run the next deferred step, if a frame has been drawn
+    pub(super) fn continue_startup(&mut self) -> AppResult<()> {
+        let step = self.startup;
+        self.startup = match step {
+            Startup::Unpainted | Startup::Done => return Ok(()),
+            Startup::LoadList => Startup::CheckAudit,
+            Startup::CheckAudit => Startup::SearchIndex,
+            Startup::SearchIndex => Startup::Migrate,
+            Startup::Migrate => Startup::Done,
+        };
+        match step {
+            // A search typed in the meantime has loaded its own results
+            Startup::LoadList if self.active_query.is_none() => {
+                self.restore_session()?;
+                self.update_selected_detail()
+            }
+            Startup::CheckAudit => {
+                self.check_audit_integrity();
+                Ok(())
+            }
+            Startup::SearchIndex => Ok(self.vault.apply_search_index()?),
+            Startup::Migrate => self.migrate_ciphertexts(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether old ciphertexts are still being re-encrypted
+    pub fn is_migrating(&self) -> bool {
+        self.startup == Startup::Migrate
+    }
+
+    /// One batch; stays on this step while there is more to do
+    fn migrate_ciphertexts(&mut self) -> AppResult<()> {
+        if self.vault.is_read_only() {
+            return Ok(());
+        }
+        let db = self.vault.db()?;
+        if credential::migrate_ciphertexts(db.conn(), self.vault.dek()?, MIGRATE_BATCH)? > 0 {
+            self.startup = Startup::Migrate;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::unlocked_app;
+    use crate::app::AppConfig;
+
+    #[test]
+    fn test_steps_wait_for_first_frame() {
+        let (_dir, mut app) = unlocked_app(AppConfig::default(), &[]);
+        app.begin_startup();
+        assert!(app.is_loading());
+
+        app.continue_startup().unwrap();
+        assert_eq!(app.startup, Startup::Unpainted);
+
+        app.painted();
+        for _ in 0..3 {
+            app.continue_startup().unwrap();
+        }
+        assert!(!app.is_loading());
+        app.continue_startup().unwrap();
+        assert_eq!(app.startup, Startup::Done);
+    }
+}
@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use crate::i18n;
+use crate::ui::components::MessageType;
+use crate::vault::sync::{self, ScpTransport, SyncOutcome, Transport};
+use crate::vault::{backup, VaultError};
+
+use super::{App, AppResult};
+
+impl App {
+    pub(super) fn sync_push(&mut self, force: bool) {
+        let Some(transport) = self.sync_transport() else { return };
+        let result = self
+            .vault
+            .db()
+            .and_then(|db| sync::push(db.conn(), &transport, &self.sync_work_dir(), force));
+
+        match result {
+            Ok(SyncOutcome::UpToDate) => self.set_message("Remote is up to date", MessageType::Info),
+            Ok(_) => self.set_message(&i18n::trf("Pushed to {}", &[&transport.remote()]), MessageType::Success),
+            Err(e) => self.report_sync_error(e),
+        }
+    }
+
+    pub(super) fn sync_pull(&mut self, force: bool) {
+        let Some(transport) = self.sync_transport() else { return };
+        let result = self.vault.db().and_then(|db| {
+            let dek = self.vault.dek()?;
+            sync::pull(db.conn(), dek, &transport, &self.sync_work_dir(), force)
+        });
+
+        match result {
+            Ok(SyncOutcome::Pulled(snapshot)) => {
+                if let Err(e) = self.replace_with_pulled(&snapshot) {
+                    self.set_message(&i18n::trf("Sync failed: {}", &[&e]), MessageType::Error);
+                }
+            }
+            Ok(_) => self.set_message("Already up to date", MessageType::Info),
+            Err(e) => self.report_sync_error(e),
+        }
+    }
+
+    /// Keep a backup of the local vault, then lock and swap in the pulled
+    /// file; it may have been pushed under a different master password
+    fn replace_with_pulled(&mut self, snapshot: &[u8]) -> AppResult<()> {
+        self.backup_before("pull")?;
+        self.lock();
+        backup::replace_vault_file(snapshot, &self.config.vault_path)?;
+        self.set_message("Pulled remote vault; unlock to continue", MessageType::Success);
+        Ok(())
+    }
+
+    fn sync_transport(&mut self) -> Option<ScpTransport> {
+        let Some(remote) = self.config.sync_remote.clone() else {
+            self.set_message("Set sync_remote in the config to use :sync", MessageType::Error);
+            return None;
+        };
+        match ScpTransport::new(remote) {
+            Ok(transport) => Some(transport),
+            Err(e) => {
+                self.set_message(&e.to_string(), MessageType::Error);
+                None
+            }
+        }
+    }
+
+    fn sync_work_dir(&self) -> PathBuf {
+        self.config
+            .vault_path
+            .parent()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    fn report_sync_error(&mut self, e: VaultError) {
+        let msg_type = match e {
+            VaultError::SyncConflict(_) => MessageType::Warning,
+            _ => MessageType::Error,
+        };
+        self.set_message(&e.to_string(), msg_type);
+    }
+}
@@ -0,0 +1,164 @@
+//! System Events
+//!
+//! Watches for termination signals and system suspend so the vault can be
+//! locked (and its keys zeroized) before the process is killed or written
+//! to a hibernation image.
+
+#[cfg(target_os = "linux")]
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(target_os = "linux")]
+use std::sync::Mutex;
+
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+static SUSPEND_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// `gdbus monitor` following logind
+#[cfg(target_os = "linux")]
+static MONITOR: Mutex<Option<Child>> = Mutex::new(None);
+/// `systemd-inhibit` holding a delay lock on sleep, so the vault can be
+/// locked before the system goes down
+#[cfg(target_os = "linux")]
+static SLEEP_DELAY: Mutex<Option<Child>> = Mutex::new(None);
+
+/// Watching for system events; dropping it stops the watch and reaps the
+/// helper processes
+pub struct Watch;
+
+/// Start watching, for commands that keep a vault unlocked: the TUI,
+/// `vault agent` and `vault api`
+pub fn install() -> Watch {
+    #[cfg(unix)]
+    install_signal_handlers();
+
+    #[cfg(target_os = "linux")]
+    watch_prepare_for_sleep();
+    Watch
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        {
+            stop(&MONITOR);
+            stop(&SLEEP_DELAY);
+        }
+    }
+}
+
+/// SIGTERM or SIGHUP (terminal disconnect) was received
+pub fn shutdown_requested() -> bool {
+    SHUTDOWN_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// The system announced it is about to sleep; resets the flag
+pub fn take_suspend() -> bool {
+    SUSPEND_PENDING.swap(false, Ordering::SeqCst)
+}
+
+/// The vault is locked; let the announced sleep go ahead
+pub fn suspend_handled() {
+    #[cfg(target_os = "linux")]
+    stop(&SLEEP_DELAY);
+}
+
+#[cfg(unix)]
+extern "C" fn on_shutdown_signal(_: libc::c_int) {
+    SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+#[cfg(unix)]
+fn install_signal_handlers() {
+    let handler = on_shutdown_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGHUP, handler);
+    }
+}
+
+/// Follow logind's PrepareForSleep signal via gdbus, holding a delay lock
+/// while awake. Silently does nothing when gdbus or the system bus is
+/// unavailable.
+#[cfg(target_os = "linux")]
+fn watch_prepare_for_sleep() {
+    use std::io::{BufRead, BufReader};
+
+    let child = Command::new("gdbus")
+        .args(["monitor", "--system", "--dest", "org.freedesktop.login1", "--object-path", "/org/freedesktop/login1"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let Ok(mut child) = child else { return };
+    let stdout = child.stdout.take();
+    *MONITOR.lock().unwrap_or_else(|e| e.into_inner()) = Some(child);
+    let Some(stdout) = stdout else { return };
+    delay_sleep();
+
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            match sleep_announcement(&line) {
+                Some(true) => SUSPEND_PENDING.store(true, Ordering::SeqCst),
+                Some(false) => delay_sleep(),
+                None => {}
+            }
+        }
+    });
+}
+
+/// Take logind's delay lock on sleep, unless already held. It lasts until
+/// `systemd-inhibit` exits, which it does once its `cat` sees its input
+/// closed; logind stops waiting after `InhibitDelayMaxSec` regardless.
+#[cfg(target_os = "linux")]
+fn delay_sleep() {
+    let mut held = SLEEP_DELAY.lock().unwrap_or_else(|e| e.into_inner());
+    if held.is_some() {
+        return;
+    }
+    *held = Command::new("systemd-inhibit")
+        .args(["--what=sleep", "--mode=delay", "--who=vault", "--why=Lock the vault before sleeping", "cat"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok();
+}
+
+#[cfg(target_os = "linux")]
+fn stop(slot: &Mutex<Option<Child>>) {
+    let child = slot.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(mut child) = child {
+        drop(child.stdin.take());
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// `Some(true)` when the system is about to sleep, `Some(false)` when it
+/// has woken up
+#[cfg(target_os = "linux")]
+fn sleep_announcement(line: &str) -> Option<bool> {
+    if !line.contains("PrepareForSleep") {
+        return None;
+    }
+    match (line.contains("(true,)"), line.contains("(false,)")) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sleep_announcement() {
+        let sleep = "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (true,)";
+        let wake = "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (false,)";
+        assert_eq!(sleep_announcement(sleep), Some(true));
+        assert_eq!(sleep_announcement(wake), Some(false));
+        assert_eq!(sleep_announcement("/org/freedesktop/login1: org.freedesktop.login1.Manager.SessionNew"), None);
+    }
+}
@@ -0,0 +1,49 @@
+//! Test Fixtures
+//!
+//! Apps over a fresh vault in a temporary directory, shared by the tests
+//! of the app's modules.
+
+use std::path::Path;
+
+use tempfile::TempDir;
+
+use super::{App, AppConfig};
+use crate::db::{Credential, CredentialType};
+use crate::vault::credential::{create_credential, NewCredential};
+
+pub const PASSWORD: &str = "correct horse battery staple";
+
+/// An app over a new vault, unlocked, holding a password credential for
+/// each of `names`. The vault lives in the returned directory, which has
+/// to outlive the app.
+pub fn unlocked_app(config: AppConfig, names: &[&str]) -> (TempDir, App) {
+    let dir = tempfile::tempdir().unwrap();
+    let mut app = App::new(AppConfig { vault_path: dir.path().join("vault.db"), ..config });
+    app.vault.initialize(PASSWORD).unwrap();
+    for name in names {
+        add_credential(&app, name, None, &[]);
+    }
+    app.refresh_data().unwrap();
+    (dir, app)
+}
+
+/// A new app unlocking the vault an earlier [`unlocked_app`] made in `dir`
+pub fn reopened_app(dir: &Path, config: AppConfig) -> App {
+    let mut app = App::new(AppConfig { vault_path: dir.join("vault.db"), ..config });
+    app.vault.unlock(PASSWORD).unwrap();
+    app
+}
+
+/// Store a password credential with secret `s3cret` and notes `memo`
+pub fn add_credential(app: &App, name: &str, username: Option<&str>, tags: &[&str]) -> Credential {
+    let (conn, dek) = (app.vault.db().unwrap().conn(), app.vault.dek().unwrap());
+    let username = username.map(str::to_string);
+    let tags = tags.iter().map(|tag| tag.to_string()).collect();
+    let new = NewCredential {
+        username,
+        tags,
+        notes: Some("memo"),
+        ..NewCredential::new(name.to_string(), CredentialType::Password, "s3cret")
+    };
+    create_credential(conn, dek, new).unwrap()
+}
@@ -0,0 +1,145 @@
+//! Update Loop
+//!
+//! The event loop talks to the app in messages, Elm style. Terminal events
+//! and timer ticks become a `Msg`, `App::update` applies it, and what only
+//! the loop can do, because it takes over the terminal with a prompt of
+//! its own, comes back as `Cmd`s. Features queue those with `emit` rather
+//! than setting flags for the loop to poll, so what an update asked for
+//! can be checked in a test.
+
+use crossterm::event::{Event, KeyEvent, KeyEventKind, MouseEvent};
+
+use super::App;
+
+/// Something that happened, for `App::update` to react to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Msg {
+    /// Between events: timers, system events, background work
+    Tick,
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    /// Input that only counts against the auto-lock timer
+    Activity,
+    FocusLost,
+    /// The master password was entered again after `Cmd::Reauthenticate`
+    Reauthenticated,
+}
+
+impl Msg {
+    /// The message for a terminal event, if it means anything to the app
+    pub fn from_event(event: Event) -> Option<Self> {
+        match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => Some(Self::Key(key)),
+            Event::Key(_) | Event::Paste(_) => Some(Self::Activity),
+            Event::Mouse(mouse) => Some(Self::Mouse(mouse)),
+            Event::FocusLost => Some(Self::FocusLost),
+            _ => None,
+        }
+    }
+}
+
+/// Work the event loop does on the app's behalf
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cmd {
+    /// Prompt for a new master password
+    ChangePassword,
+    /// Prompt for the password of a new key slot with this label
+    AddKeySlot(String),
+    /// Ask for the master password, then send `Msg::Reauthenticated`
+    Reauthenticate,
+}
+
+impl App {
+    /// Apply a message and hand back the commands it produced. Errors end
+    /// up in the status line, see `report_error`.
+    pub fn update(&mut self, msg: Msg) -> Vec<Cmd> {
+        let handled = match msg {
+            Msg::Tick => {
+                self.tick();
+                Ok(false)
+            }
+            Msg::Key(key) => {
+                self.record_activity();
+                self.handle_key_event(key)
+            }
+            Msg::Mouse(mouse) => {
+                self.record_activity();
+                self.handle_mouse_event(mouse)
+            }
+            Msg::Activity => {
+                self.record_activity();
+                Ok(false)
+            }
+            Msg::FocusLost => {
+                self.handle_focus_lost();
+                Ok(false)
+            }
+            Msg::Reauthenticated => self.reauthenticated(),
+        };
+        match handled {
+            Ok(quit) => self.should_quit |= quit,
+            Err(e) => self.report_error(e),
+        }
+        std::mem::take(&mut self.commands)
+    }
+
+    pub(super) fn emit(&mut self, cmd: Cmd) {
+        self.commands.push(cmd);
+    }
+
+    fn tick(&mut self) {
+        self.handle_system_events();
+        if self.should_quit {
+            return;
+        }
+        if let Err(e) = self.continue_startup() {
+            self.report_error(e);
+        }
+        self.run_scheduled_backup();
+        self.run_pending_search();
+        self.poll_clipboard();
+        self.expire_pending_action();
+        self.warn_form_before_lock();
+        self.wipe_idle_detail();
+        if self.vault.should_auto_lock() {
+            self.lock();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::*;
+    use crate::app::AppConfig;
+    use crate::input::keymap::Action;
+
+    fn type_command(app: &mut App, command: &str) -> Vec<Cmd> {
+        let mut commands = Vec::new();
+        for c in format!(":{}", command).chars() {
+            commands.extend(app.update(Msg::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))));
+        }
+        commands.extend(app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))));
+        commands
+    }
+
+    #[test]
+    fn test_sensitive_command_asks_for_reauthentication() {
+        let mut app = App::new(AppConfig::default());
+        assert_eq!(type_command(&mut app, "export copy.db"), vec![Cmd::Reauthenticate]);
+        assert_eq!(app.wants_reauth, Some(Action::Export("copy.db".into())));
+        assert!(app.update(Msg::Tick).is_empty());
+
+        // A CSV only needs the password again when it holds secrets
+        assert!(type_command(&mut app, "export csv names.csv --fields name").is_empty());
+        assert_eq!(type_command(&mut app, "export csv all.csv --include-secrets"), vec![Cmd::Reauthenticate]);
+    }
+
+    #[test]
+    fn test_quit() {
+        let mut app = App::new(AppConfig::default());
+        assert!(type_command(&mut app, "q").is_empty());
+        assert!(app.should_quit);
+    }
+}
@@ -0,0 +1,71 @@
+//! Wipe on Idle
+//!
+//! A credential left open on screen stays decrypted in memory for as long
+//! as the vault is unlocked. After `wipe_detail_after` without input, well
+//! before auto-lock, the selected credential and its detail are dropped
+//! along with the decrypt cache, a revealed secret is masked again and the
+//! credential is closed, so its secret has to be opened again. The vault
+//! stays unlocked; the next key press brings the preview back.
+
+use crate::ui::components::SecretDisplay;
+
+use super::App;
+
+impl App {
+    /// Drop decrypted detail state once input has been idle long enough
+    pub(super) fn wipe_idle_detail(&mut self) {
+        let after = self.config.wipe_detail_after;
+        if after.is_zero() || self.detail_wiped || !self.vault.is_unlocked() || self.vault.idle_time() < after {
+            return;
+        }
+        self.decrypt_cache.clear();
+        self.selected_credential = None;
+        self.selected_detail = None;
+        self.opened = None;
+        self.secret_display = SecretDisplay::Masked;
+        self.detail_wiped = true;
+    }
+
+    /// Show the selected credential again after a wipe
+    pub(super) fn restore_wiped_detail(&mut self) {
+        if !std::mem::take(&mut self.detail_wiped) {
+            return;
+        }
+        if let Err(e) = self.update_selected_detail() {
+            self.report_error(e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::app::test_support::unlocked_app;
+    use crate::app::AppConfig;
+    use crate::input::keymap::Action;
+
+    #[test]
+    fn test_wipe_idle_detail() {
+        let config = AppConfig { wipe_detail_after: Duration::from_millis(20), ..AppConfig::default() };
+        let (_dir, mut app) = unlocked_app(config, &["GitHub"]);
+        app.execute_action(Action::Select).unwrap();
+        app.secret_display = SecretDisplay::Full;
+
+        app.record_activity();
+        app.wipe_idle_detail();
+        assert!(app.selected_detail.is_some());
+
+        std::thread::sleep(Duration::from_millis(40));
+        app.wipe_idle_detail();
+        assert!(app.selected_credential.is_none() && app.selected_detail.is_none());
+        assert_eq!(app.secret_display, SecretDisplay::Masked);
+        assert!(app.vault.is_unlocked());
+
+        app.record_activity();
+        let restored = app.selected_credential.as_ref().unwrap();
+        assert_eq!(restored.name, "GitHub");
+        assert!(restored.secret.is_none());
+    }
+}
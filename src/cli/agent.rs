@@ -0,0 +1,481 @@
+//! Agent
+//!
+//! `vault agent` keeps one vault unlocked and answers other `vault`
+//! invocations over a per-vault Unix socket, so they don't each prompt for
+//! the master password. A request is one tab-separated line; the reply
+//! starts with `ok` or `error <reason>` on its own line, followed by the
+//! body. Only processes of the same user are served, every request is
+//! audited, and the agent exits when idle or told to `lock`.
+
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::vault::credential::Field;
+use crate::vault::runner::Selection;
+
+/// Longest request line accepted
+#[cfg_attr(not(unix), allow(dead_code))]
+const MAX_REQUEST: u64 = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    Status,
+    Names,
+    Get { field: Field, name: String },
+    Env(Selection),
+    /// Credentials for a page's URL, as printed by `vault match`
+    Match(String),
+    Lock,
+}
+
+impl Request {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut parts = line.trim_end_matches(['\r', '\n']).split('\t');
+        let request = match parts.next().unwrap_or_default() {
+            "status" => Self::Status,
+            "names" => Self::Names,
+            "lock" => Self::Lock,
+            "match" => Self::Match(parts.next().ok_or("match needs a URL")?.to_string()),
+            "get" => {
+                let field = parts.next().and_then(Field::parse).ok_or("get needs a field")?;
+                let name = parts.next().ok_or("get needs a name")?.to_string();
+                Self::Get { field, name }
+            }
+            "env" => {
+                let mut selection = Selection::default();
+                for part in parts.by_ref() {
+                    match part.split_once('=') {
+                        Some(("tag", tag)) => selection.tags.push(tag.to_string()),
+                        Some(("name", name)) => selection.names.push(name.to_string()),
+                        _ => return Err(format!("bad selector '{}'", part)),
+                    }
+                }
+                Self::Env(selection)
+            }
+            other => return Err(format!("unknown request '{}'", other)),
+        };
+        match parts.next() {
+            Some(_) => Err("too many arguments".to_string()),
+            None => Ok(request),
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        match self {
+            Self::Status => "status".to_string(),
+            Self::Names => "names".to_string(),
+            Self::Lock => "lock".to_string(),
+            Self::Get { field, name } => format!("get\t{}\t{}", field.label(), name),
+            Self::Match(url) => format!("match\t{}", url),
+            Self::Env(selection) => {
+                let tags = selection.tags.iter().map(|t| format!("\ttag={}", t));
+                let names = selection.names.iter().map(|n| format!("\tname={}", n));
+                std::iter::once("env".to_string()).chain(tags).chain(names).collect()
+            }
+        }
+    }
+}
+
+/// Socket for the agent serving `vault_path`, inside the private runtime directory
+pub fn socket_path(vault_path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(super::private_runtime_dir()?.join(format!("agent-{}.sock", vault_digest(vault_path))))
+}
+
+/// Short hash of the vault's canonical path, naming per-vault runtime files
+pub(super) fn vault_digest(vault_path: &Path) -> String {
+    let vault_path = vault_path.canonicalize().unwrap_or_else(|_| vault_path.to_path_buf());
+    hex::encode(&Sha256::digest(vault_path.to_string_lossy().as_bytes())[..8])
+}
+
+/// Send one request to the agent for `vault_path`. `None` when no agent
+/// is listening, otherwise the body of the reply or its error.
+#[cfg(unix)]
+pub fn try_request(vault_path: &Path, request: &Request) -> Option<Result<String, Box<dyn std::error::Error>>> {
+    let stream = std::os::unix::net::UnixStream::connect(socket_path(vault_path).ok()?).ok()?;
+    Some(exchange(stream, request))
+}
+
+#[cfg(unix)]
+fn exchange(
+    mut stream: std::os::unix::net::UnixStream,
+    request: &Request,
+) -> Result<String, Box<dyn std::error::Error>> {
+    use std::io::{Read, Write};
+
+    stream.write_all(format!("{}\n", request.to_line()).as_bytes())?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    parse_reply(reply)
+}
+
+#[cfg(not(unix))]
+pub fn try_request(_vault_path: &Path, _request: &Request) -> Option<Result<String, Box<dyn std::error::Error>>> {
+    None
+}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+fn parse_reply(mut reply: String) -> Result<String, Box<dyn std::error::Error>> {
+    let (status, body) = reply.split_once('\n').unwrap_or((reply.as_str(), ""));
+    let result = match status {
+        "ok" => Ok(body.to_string()),
+        _ => Err(status.strip_prefix("error ").unwrap_or("Malformed reply from agent").into()),
+    };
+    zeroize::Zeroize::zeroize(&mut reply);
+    result
+}
+
+/// Body of an `env` reply: one `NAME<tab>hex value` line per variable
+pub fn encode_env(vars: &[(String, String)]) -> String {
+    vars.iter().map(|(name, value)| format!("{}\t{}\n", name, hex::encode(value))).collect()
+}
+
+pub fn decode_env(body: &str) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    body.lines()
+        .map(|line| {
+            let (name, value) = line.split_once('\t').ok_or("Malformed env reply from agent")?;
+            let value = String::from_utf8(hex::decode(value)?)?;
+            Ok((name.to_string(), value))
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+pub use server::serve;
+
+#[cfg(unix)]
+mod server {
+    use std::io::{self, BufRead, BufReader, Read, Write};
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use zeroize::Zeroize;
+
+    use crate::app::{system_events, EventHook, SecurityEvent};
+    use crate::db::{AuditAction, Credential};
+    use crate::vault::backend::{self, Backends};
+    use crate::vault::{audit, runner, Vault};
+
+    use super::{encode_env, Request, MAX_REQUEST};
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+    const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Removes the socket file when the agent stops
+    struct SocketGuard(PathBuf);
+
+    impl Drop for SocketGuard {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Serve requests until the vault's idle timeout passes, a `lock`
+    /// request arrives or the process is asked to terminate. The vault is
+    /// locked, zeroizing its keys, before returning.
+    pub fn serve(mut vault: Vault, backends: &Backends, hooks: &[EventHook], socket: &Path) -> Result<String, Box<dyn std::error::Error>> {
+        if socket.exists() {
+            if UnixStream::connect(socket).is_ok() {
+                return Err("An agent is already running for this vault".into());
+            }
+            std::fs::remove_file(socket)?;
+        }
+        let listener = UnixListener::bind(socket)?;
+        let _guard = SocketGuard(socket.to_path_buf());
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600))?;
+        }
+        listener.set_nonblocking(true)?;
+        log(&vault, AuditAction::Unlock, None, "Agent started")?;
+
+        let reason = loop {
+            if system_events::shutdown_requested() {
+                break "terminated";
+            }
+            if system_events::take_suspend() {
+                break "system suspend";
+            }
+            if vault.should_auto_lock() {
+                break "idle timeout";
+            }
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    vault.update_activity();
+                    if handle(&vault, backends, hooks, stream)? == Some(Request::Lock) {
+                        break "lock request";
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => std::thread::sleep(POLL_INTERVAL),
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        log(&vault, AuditAction::Lock, None, &format!("Agent stopped: {}", reason))?;
+        vault.lock();
+        system_events::suspend_handled();
+        Ok(reason.to_string())
+    }
+
+    /// Answer one connection; returns the request when it was understood
+    fn handle(
+        vault: &Vault,
+        backends: &Backends,
+        hooks: &[EventHook],
+        mut stream: UnixStream,
+    ) -> Result<Option<Request>, Box<dyn std::error::Error>> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+        let own_uid = unsafe { libc::geteuid() };
+        match peer_uid(&stream) {
+            Ok(uid) if uid == own_uid => {}
+            Ok(uid) => {
+                let details = format!("Agent refused uid {}", uid);
+                super::super::fire_event(hooks, SecurityEvent::FailedUnlock, &details);
+                log(vault, AuditAction::FailedUnlock, None, &details)?;
+                return Ok(None);
+            }
+            Err(e) => {
+                let details = format!("Agent refused unknown peer: {}", e);
+                super::super::fire_event(hooks, SecurityEvent::FailedUnlock, &details);
+                log(vault, AuditAction::FailedUnlock, None, &details)?;
+                return Ok(None);
+            }
+        }
+
+        let mut line = String::new();
+        if BufReader::new((&stream).take(MAX_REQUEST)).read_line(&mut line).is_err() {
+            return Ok(None);
+        }
+        let request = Request::parse(&line);
+        let mut reply = match &request {
+            Ok(request) => match respond(vault, backends, request) {
+                Ok(body) => format!("ok\n{}", body),
+                Err(e) => format!("error {}\n", e),
+            },
+            Err(e) => format!("error {}\n", e),
+        };
+        let _ = stream.write_all(reply.as_bytes());
+        reply.zeroize();
+        Ok(request.ok())
+    }
+
+    /// Body of the reply to a request, auditing it
+    pub(super) fn respond(vault: &Vault, backends: &Backends, request: &Request) -> Result<String, Box<dyn std::error::Error>> {
+        let conn = vault.db()?.conn();
+        match request {
+            Request::Status => {
+                log(vault, AuditAction::Read, None, "Agent status")?;
+                let idle = vault.time_until_auto_lock().unwrap_or_default();
+                Ok(format!("unlocked, locks in {}s\n", idle.as_secs()))
+            }
+            Request::Names => {
+                log(vault, AuditAction::Read, None, "Agent names")?;
+                let names: String = crate::vault::credential::list_credentials(conn)?.into_iter().map(|c| c.name + "\n").collect();
+                Ok(names)
+            }
+            Request::Get { field, name } => {
+                let cred = super::super::find_by_name(conn, name)?;
+                let (decrypted, fetched) = super::super::decrypt_for_field(vault, backends, &cred, *field)?;
+                let value = field
+                    .value(&decrypted)
+                    .ok_or_else(|| format!("'{}' has no {}", cred.name, field.label()))?;
+                let details = backend::audit_details(&format!("Agent get {}", field.label()), fetched.as_deref());
+                log(vault, AuditAction::Read, Some(&cred), &details)?;
+                Ok(value)
+            }
+            Request::Env(selection) => {
+                let vars = runner::resolve_env(conn, vault.dek()?, backends, selection)?;
+                let mut pairs = Vec::with_capacity(vars.len());
+                for var in &vars {
+                    let details = backend::audit_details(&format!("Agent env as {}", var.name), var.backend.as_deref());
+                    log(vault, AuditAction::Read, Some(&var.credential), &details)?;
+                    pairs.push((var.name.clone(), secrecy::ExposeSecret::expose_secret(&var.value).to_string()));
+                }
+                let body = encode_env(&pairs);
+                pairs.iter_mut().for_each(|(_, value)| value.zeroize());
+                Ok(body)
+            }
+            Request::Match(url) => {
+                log(vault, AuditAction::Read, None, &format!("Agent match {}", url))?;
+                Ok(super::super::format_matches(&crate::vault::search::match_url(conn, url)?))
+            }
+            Request::Lock => {
+                log(vault, AuditAction::Lock, None, "Agent lock request")?;
+                Ok(String::new())
+            }
+        }
+    }
+
+    fn log(vault: &Vault, action: AuditAction, cred: Option<&Credential>, details: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let audit_key = vault.keys()?.derive_audit_key()?;
+        audit::log_action(
+            vault.db()?.conn(),
+            &audit_key,
+            action,
+            cred.map(|c| c.id.as_str()),
+            cred.map(|c| c.name.as_str()),
+            cred.and_then(|c| c.username.as_deref()),
+            Some(details),
+        )?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+        let mut cred = libc::ucred { pid: 0, uid: 0, gid: 0 };
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        let rc = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                (&mut cred as *mut libc::ucred).cast(),
+                &mut len,
+            )
+        };
+        if rc != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(cred.uid)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+        let (mut uid, mut gid) = (0, 0);
+        if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(uid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_roundtrip() {
+        let requests = [
+            Request::Status,
+            Request::Names,
+            Request::Lock,
+            Request::Match("https://github.com/login".to_string()),
+            Request::Get { field: Field::Username, name: "My Bank".to_string() },
+            Request::Env(Selection { tags: vec!["prod".to_string()], names: vec!["db".to_string()] }),
+        ];
+        for request in requests {
+            assert_eq!(Request::parse(&request.to_line()), Ok(request));
+        }
+        assert!(Request::parse("get\tcolour\tx").is_err());
+        assert!(Request::parse("names\textra").is_err());
+        assert!(Request::parse("env\tfoo").is_err());
+        assert!(Request::parse("unlock").is_err());
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(parse_reply("ok\nGitHub\nBank\n".to_string()).unwrap(), "GitHub\nBank\n");
+        assert_eq!(parse_reply("ok".to_string()).unwrap(), "");
+        assert_eq!(parse_reply("error Vault is locked\n".to_string()).unwrap_err().to_string(), "Vault is locked");
+        assert!(parse_reply(String::new()).is_err());
+    }
+
+    #[test]
+    fn test_env_encoding() {
+        let vars = vec![("DB_URL".to_string(), "postgres://a\nb".to_string())];
+        assert_eq!(decode_env(&encode_env(&vars)).unwrap(), vars);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_respond() {
+        use crate::db::CredentialType;
+        use crate::db::models::SECRET_BACKEND;
+        use crate::vault::backend::Backends;
+        use crate::vault::{credential, Vault, VaultConfig};
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut vault = Vault::new(VaultConfig::with_path(dir.path().join("vault.db")));
+        vault.initialize("password").unwrap();
+        let conn = vault.db().unwrap().conn();
+        let mut cred = credential::create_credential(
+            conn,
+            vault.dek().unwrap(),
+            credential::NewCredential {
+                username: Some("octocat".to_string()),
+                tags: vec!["dev".to_string()],
+                ..credential::NewCredential::new("GitHub".to_string(), CredentialType::Password, "hunter2")
+            },
+        )
+        .unwrap();
+        credential::set_env_var(conn, &mut cred, Some("GH_TOKEN".to_string())).unwrap();
+        crate::vault::alias::set_alias(conn, &mut cred, Some("gh".to_string())).unwrap();
+        let backends = Backends::new();
+
+        assert_eq!(server::respond(&vault, &backends, &Request::Names).unwrap(), "GitHub\n");
+        let get = Request::Get { field: Field::Username, name: "github".to_string() };
+        assert_eq!(server::respond(&vault, &backends, &get).unwrap(), "octocat");
+        let get = Request::Get { field: Field::Username, name: "gh".to_string() };
+        assert_eq!(server::respond(&vault, &backends, &get).unwrap(), "octocat");
+        let env = Request::Env(Selection { tags: vec!["dev".to_string()], names: vec![] });
+        let vars = decode_env(&server::respond(&vault, &backends, &env).unwrap()).unwrap();
+        assert_eq!(vars, vec![("GH_TOKEN".to_string(), "hunter2".to_string())]);
+        assert!(server::respond(&vault, &backends, &Request::Get { field: Field::Url, name: "GitHub".to_string() }).is_err());
+
+        let logged = crate::vault::audit::get_credential_logs(conn, &cred.id).unwrap();
+        assert_eq!(logged.len(), 3);
+
+        // A secret kept in a backend is fetched through it and the fetch audited
+        let mut deploy = credential::create_credential(
+            conn,
+            vault.dek().unwrap(),
+            credential::NewCredential::new("Deploy".to_string(), CredentialType::ApiKey, "prod/deploy"),
+        )
+        .unwrap();
+        let attributes = [(SECRET_BACKEND.to_string(), "sh".to_string())].into();
+        credential::set_attributes(conn, &mut deploy, attributes).unwrap();
+        let get = Request::Get { field: Field::Secret, name: "Deploy".to_string() };
+        assert!(server::respond(&vault, &backends, &get).is_err());
+        let command = ["sh", "-c", "echo \"fetched $0\"", "{}"].map(String::from).to_vec();
+        let backends = Backends::from_commands(&[("sh".to_string(), command)].into());
+        assert_eq!(server::respond(&vault, &backends, &get).unwrap(), "fetched prod/deploy");
+        let logged = crate::vault::audit::get_credential_logs(conn, &deploy.id).unwrap();
+        assert_eq!(logged[0].details.as_deref(), Some("Agent get secret (fetched from sh)"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_serve_until_locked() {
+        use std::os::unix::net::UnixStream;
+        use crate::vault::backend::Backends;
+        use crate::vault::{Vault, VaultConfig};
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut vault = Vault::new(VaultConfig::with_path(dir.path().join("vault.db")));
+        vault.initialize("password").unwrap();
+        let socket = dir.path().join("agent.sock");
+
+        let server = {
+            let socket = socket.clone();
+            std::thread::spawn(move || serve(vault, &Backends::new(), &[], &socket).unwrap())
+        };
+        let connect = || loop {
+            if let Ok(stream) = UnixStream::connect(&socket) {
+                return stream;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        };
+
+        assert_eq!(exchange(connect(), &Request::Names).unwrap(), "");
+        assert!(exchange(connect(), &Request::Get { field: Field::Secret, name: "x".to_string() }).is_err());
+        exchange(connect(), &Request::Lock).unwrap();
+        assert_eq!(server.join().unwrap(), "lock request");
+        assert!(!socket.exists());
+    }
+}
@@ -0,0 +1,441 @@
+//! Local HTTP API
+//!
+//! `vault api` unlocks the vault and answers read-only lookups over HTTP
+//! on 127.0.0.1, for tools that cannot talk to the agent's Unix socket.
+//! Every request needs the bearer token printed at startup, which is also
+//! written to a file only the owner can read. Requests are rate limited,
+//! repeated bad tokens shut the API out for a while, and lookups and bad
+//! tokens are audited. It stops, locking the vault, after the idle timeout.
+//!
+//! | Route | Reply |
+//! |-------|-------|
+//! | `GET /v1/status` | `{"locks_in"}`, seconds until the idle lock |
+//! | `GET /v1/names` | Array of credential names |
+//! | `GET /v1/credentials/NAME` | Credential metadata as `show --json`; `?secrets=true` adds the secret and notes |
+//! | `GET /v1/match?url=URL` | Array of credential metadata, best match first |
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use rand::RngCore;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+use zeroize::Zeroize;
+
+use crate::app::{system_events, EventHook, SecurityEvent};
+use crate::db::{AuditAction, Credential};
+use crate::vault::backend::{self, Backends};
+use crate::vault::{audit, search, Vault};
+
+use super::json::CredentialJson;
+
+pub const DEFAULT_PORT: u16 = 7277;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Requests with a token answered per `WINDOW`, good or not
+const MAX_REQUESTS: u32 = 20;
+const WINDOW: Duration = Duration::from_secs(60);
+/// Bad tokens in a row before every request is refused for `LOCKOUT`
+const MAX_BAD_TOKENS: u32 = 3;
+const LOCKOUT: Duration = Duration::from_secs(300);
+
+/// A reply other than 200, with the message sent as `{"error"}`
+type Refusal = (u16, String);
+
+/// File holding the token for the API serving `vault_path`
+pub fn token_path(vault_path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(super::private_runtime_dir()?.join(format!("api-{}.token", super::agent::vault_digest(vault_path))))
+}
+
+/// 256 random bits, hex encoded
+pub fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+    bytes.zeroize();
+    token
+}
+
+/// Removes the token file when the API stops
+struct TokenGuard(PathBuf);
+
+impl Drop for TokenGuard {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Serve requests on `127.0.0.1:port` until the vault's idle timeout
+/// passes or the process is asked to terminate. The vault is locked,
+/// zeroizing its keys, before returning.
+pub fn serve(
+    mut vault: Vault,
+    backends: &Backends,
+    hooks: &[EventHook],
+    port: u16,
+    token: &str,
+    token_file: &Path,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let server = Server::http(("127.0.0.1", port)).map_err(|e| format!("Cannot listen on 127.0.0.1:{}: {}", port, e))?;
+    let _guard = TokenGuard(token_file.to_path_buf());
+    log(&vault, AuditAction::Unlock, None, &format!("API started on port {}", port))?;
+
+    let mut limiter = RateLimiter::default();
+    let reason = loop {
+        if system_events::shutdown_requested() {
+            break "terminated";
+        }
+        if system_events::take_suspend() {
+            break "system suspend";
+        }
+        if vault.should_auto_lock() {
+            break "idle timeout";
+        }
+        if let Some(request) = server.recv_timeout(POLL_INTERVAL)? {
+            handle(&mut vault, backends, hooks, &mut limiter, request, port, token)?;
+        }
+    };
+
+    log(&vault, AuditAction::Lock, None, &format!("API stopped: {}", reason))?;
+    vault.lock();
+    system_events::suspend_handled();
+    Ok(reason.to_string())
+}
+
+fn handle(
+    vault: &mut Vault,
+    backends: &Backends,
+    hooks: &[EventHook],
+    limiter: &mut RateLimiter,
+    request: Request,
+    port: u16,
+    token: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let now = Instant::now();
+    let mut retry_after = None;
+    // Refusals up to the token check neither count against the limits nor
+    // get audited, so a web page firing requests cannot shut the API out
+    let result = if !host_is_local(&request, port) {
+        // A page that rebinds its own domain to 127.0.0.1 still sends that domain
+        Err((403, "Unexpected Host header".to_string()))
+    } else if from_browser(&request) {
+        Err((403, "Requests from web pages are refused".to_string()))
+    } else if header(&request, "Authorization").is_none() {
+        Err((401, "Missing bearer token".to_string()))
+    } else if let Err(wait) = limiter.admit(now) {
+        retry_after = Some(wait.as_secs().max(1));
+        Err((429, "Too many requests".to_string()))
+    } else if !bearer_matches(&request, token) {
+        limiter.bad_token(now);
+        super::fire_event(hooks, SecurityEvent::FailedUnlock, "API refused a bad token");
+        log(vault, AuditAction::FailedUnlock, None, "API refused a bad token")?;
+        Err((401, "Wrong bearer token".to_string()))
+    } else if *request.method() != Method::Get {
+        Err((405, "Only GET is supported".to_string()))
+    } else {
+        limiter.good_token();
+        vault.update_activity();
+        respond(vault, backends, request.url())
+    };
+
+    let (status, mut body) = match result {
+        Ok(body) => (200, body),
+        Err((status, message)) => (status, serde_json::json!({ "error": message }).to_string()),
+    };
+    let mut headers = vec![Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).expect("valid header")];
+    if let Some(seconds) = retry_after {
+        headers.extend(Header::from_bytes(&b"Retry-After"[..], seconds.to_string().as_bytes()));
+    }
+    let length = body.len();
+    let _ = request.respond(Response::new(StatusCode(status), headers, Cursor::new(body.as_bytes()), Some(length), None));
+    body.zeroize();
+    Ok(())
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request.headers().iter().find(|h| h.field.equiv(name)).map(|h| h.value.as_str())
+}
+
+fn host_is_local(request: &Request, port: u16) -> bool {
+    header(request, "Host").is_some_and(|host| {
+        host == format!("127.0.0.1:{}", port) || host == format!("localhost:{}", port)
+    })
+}
+
+/// Browsers add these to every request a page makes; other clients
+/// have no reason to
+fn from_browser(request: &Request) -> bool {
+    header(request, "Origin").is_some() || header(request, "Sec-Fetch-Site").is_some()
+}
+
+fn bearer_matches(request: &Request, token: &str) -> bool {
+    header(request, "Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// JSON body for an authorized GET of `url`, auditing it
+pub(super) fn respond(vault: &Vault, backends: &Backends, url: &str) -> Result<String, Refusal> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let route = path.strip_prefix("/v1/").ok_or_else(not_found)?;
+    let conn = vault.db().map_err(internal)?.conn();
+
+    if let Some(name) = route.strip_prefix("credentials/") {
+        let name = percent_decode(name, false).ok_or_else(|| (400, "Malformed name".to_string()))?;
+        let secrets = query_param(query, "secrets").as_deref() == Some("true");
+        let cred = super::find_by_name(conn, &name).map_err(|e| (404, e.to_string()))?;
+        let (decrypted, fetched) = match secrets {
+            true => {
+                let dek = vault.dek().map_err(internal)?;
+                let (decrypted, fetched) = backend::decrypt_resolved(conn, dek, backends, &cred).map_err(internal)?;
+                (Some(decrypted), fetched)
+            }
+            false => (None, None),
+        };
+        let details = if secrets { "API get with secrets" } else { "API get" };
+        log(vault, AuditAction::Read, Some(&cred), &backend::audit_details(details, fetched.as_deref())).map_err(internal)?;
+        return to_json(&CredentialJson::new(&cred, decrypted.as_ref()));
+    }
+
+    match route {
+        "status" => {
+            let locks_in = vault.time_until_auto_lock().unwrap_or_default().as_secs();
+            Ok(serde_json::json!({ "locks_in": locks_in }).to_string())
+        }
+        "names" => {
+            log(vault, AuditAction::Read, None, "API names").map_err(internal)?;
+            let all = crate::vault::credential::list_credentials(conn).map_err(internal)?;
+            to_json(&all.iter().map(|c| c.name.as_str()).collect::<Vec<_>>())
+        }
+        "match" => {
+            let url = query_param(query, "url").ok_or_else(|| (400, "match needs ?url=".to_string()))?;
+            log(vault, AuditAction::Read, None, &format!("API match {}", url)).map_err(internal)?;
+            let matches = search::match_url(conn, &url).map_err(internal)?;
+            to_json(&matches.iter().map(|m| CredentialJson::new(&m.credential, None)).collect::<Vec<_>>())
+        }
+        _ => Err(not_found()),
+    }
+}
+
+fn to_json(value: &impl serde::Serialize) -> Result<String, Refusal> {
+    serde_json::to_string(value).map_err(internal)
+}
+
+fn not_found() -> Refusal {
+    (404, "No such route".to_string())
+}
+
+fn internal(error: impl std::fmt::Display) -> Refusal {
+    (500, error.to_string())
+}
+
+fn query_param(query: &str, key: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .and_then(|(_, value)| percent_decode(value, true))
+}
+
+/// Undo URL encoding; `+` is a space only in query strings
+fn percent_decode(text: &str, plus_is_space: bool) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.bytes();
+    while let Some(byte) = rest.next() {
+        match byte {
+            b'%' => {
+                let hex = [rest.next()?, rest.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            b'+' if plus_is_space => bytes.push(b' '),
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// At most `MAX_REQUESTS` per `WINDOW`, and nothing at all for `LOCKOUT`
+/// after `MAX_BAD_TOKENS` bad tokens in a row
+#[derive(Debug, Default)]
+struct RateLimiter {
+    window_start: Option<Instant>,
+    requests: u32,
+    bad_tokens: u32,
+    locked_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    /// Count a request, or say how long to wait before the next one
+    fn admit(&mut self, now: Instant) -> Result<(), Duration> {
+        if let Some(until) = self.locked_until {
+            if now < until {
+                return Err(until - now);
+            }
+            self.locked_until = None;
+            self.bad_tokens = 0;
+        }
+        let start = *self.window_start.get_or_insert(now);
+        if now.duration_since(start) >= WINDOW {
+            self.window_start = Some(now);
+            self.requests = 0;
+        }
+        if self.requests >= MAX_REQUESTS {
+            return Err(WINDOW.saturating_sub(now.duration_since(start)));
+        }
+        self.requests += 1;
+        Ok(())
+    }
+
+    fn bad_token(&mut self, now: Instant) {
+        self.bad_tokens += 1;
+        if self.bad_tokens >= MAX_BAD_TOKENS {
+            self.locked_until = Some(now + LOCKOUT);
+        }
+    }
+
+    fn good_token(&mut self) {
+        self.bad_tokens = 0;
+    }
+}
+
+fn log(vault: &Vault, action: AuditAction, cred: Option<&Credential>, details: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let audit_key = vault.keys()?.derive_audit_key()?;
+    audit::log_action(
+        vault.db()?.conn(),
+        &audit_key,
+        action,
+        cred.map(|c| c.id.as_str()),
+        cred.map(|c| c.name.as_str()),
+        cred.and_then(|c| c.username.as_deref()),
+        Some(details),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::default();
+        for _ in 0..MAX_REQUESTS {
+            assert!(limiter.admit(start).is_ok());
+        }
+        assert!(limiter.admit(start + Duration::from_secs(59)).is_err());
+        assert!(limiter.admit(start + WINDOW).is_ok());
+
+        let later = start + WINDOW * 2;
+        for _ in 0..MAX_BAD_TOKENS {
+            limiter.bad_token(later);
+        }
+        assert_eq!(limiter.admit(later + Duration::from_secs(1)), Err(LOCKOUT - Duration::from_secs(1)));
+        assert!(limiter.admit(later + LOCKOUT).is_ok());
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("My%20Bank", false).as_deref(), Some("My Bank"));
+        assert_eq!(percent_decode("a+b", false).as_deref(), Some("a+b"));
+        assert_eq!(query_param("secrets=true&url=https%3A%2F%2Fgithub.com+x", "url").as_deref(), Some("https://github.com x"));
+        assert!(percent_decode("%zz", false).is_none());
+        assert!(percent_decode("%4", false).is_none());
+    }
+
+    #[test]
+    fn test_respond() {
+        use crate::db::CredentialType;
+        use crate::vault::{credential, VaultConfig};
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut vault = Vault::new(VaultConfig::with_path(dir.path().join("vault.db")));
+        vault.initialize("password").unwrap();
+        let conn = vault.db().unwrap().conn();
+        let cred = credential::create_credential(
+            conn,
+            vault.dek().unwrap(),
+            credential::NewCredential {
+                username: Some("me".to_string()),
+                url: Some("https://bank.example".to_string()),
+                ..credential::NewCredential::new("My Bank".to_string(), CredentialType::Password, "hunter2")
+            },
+        )
+        .unwrap();
+
+        assert_eq!(respond(&vault, &Backends::new(), "/v1/names").unwrap(), r#"["My Bank"]"#);
+        let meta: serde_json::Value = serde_json::from_str(&respond(&vault, &Backends::new(), "/v1/credentials/my%20bank").unwrap()).unwrap();
+        assert_eq!(meta["username"], "me");
+        assert!(meta.get("secret").is_none());
+        let full: serde_json::Value =
+            serde_json::from_str(&respond(&vault, &Backends::new(), "/v1/credentials/My%20Bank?secrets=true").unwrap()).unwrap();
+        assert_eq!(full["secret"], "hunter2");
+        let matched: serde_json::Value =
+            serde_json::from_str(&respond(&vault, &Backends::new(), "/v1/match?url=https%3A%2F%2Fbank.example%2Flogin").unwrap()).unwrap();
+        assert_eq!(matched[0]["name"], "My Bank");
+
+        assert_eq!(respond(&vault, &Backends::new(), "/v1/credentials/Nope").unwrap_err().0, 404);
+        assert_eq!(respond(&vault, &Backends::new(), "/v1/match").unwrap_err().0, 400);
+        assert_eq!(respond(&vault, &Backends::new(), "/v2/names").unwrap_err().0, 404);
+
+        let logged = crate::vault::audit::get_credential_logs(conn, &cred.id).unwrap();
+        assert_eq!(logged.len(), 2);
+    }
+
+    #[test]
+    fn test_serve_until_idle() {
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+        use crate::vault::VaultConfig;
+
+        const PORT: u16 = 47277;
+        let dir = tempfile::tempdir().unwrap();
+        let config = VaultConfig { auto_lock_timeout: Duration::from_secs(1), ..VaultConfig::with_path(dir.path().join("vault.db")) };
+        let mut vault = Vault::new(config);
+        vault.initialize("password").unwrap();
+        let token_file = dir.path().join("api.token");
+        std::fs::write(&token_file, "token").unwrap();
+
+        let server = {
+            let token_file = token_file.clone();
+            std::thread::spawn(move || serve(vault, &Backends::new(), &[], PORT, "token", &token_file).unwrap())
+        };
+        let get = |host: &str, auth: &str| loop {
+            if let Ok(mut stream) = TcpStream::connect(("127.0.0.1", PORT)) {
+                let request = format!("GET /v1/names HTTP/1.1\r\nHost: {}\r\n{}Connection: close\r\n\r\n", host, auth);
+                stream.write_all(request.as_bytes()).unwrap();
+                let mut reply = String::new();
+                stream.read_to_string(&mut reply).unwrap();
+                return reply;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        };
+
+        let host = format!("127.0.0.1:{}", PORT);
+        let ok = get(&host, "Authorization: Bearer token\r\n");
+        assert!(ok.starts_with("HTTP/1.1 200") && ok.ends_with("[]"));
+        assert!(get(&host, "Authorization: Bearer wrong\r\n").starts_with("HTTP/1.1 401"));
+        assert!(get("evil.example", "Authorization: Bearer token\r\n").starts_with("HTTP/1.1 403"));
+        assert!(get(&host, "Origin: https://evil.example\r\nAuthorization: Bearer wrong\r\n").starts_with("HTTP/1.1 403"));
+        assert!(get(&host, "Sec-Fetch-Site: cross-site\r\n").starts_with("HTTP/1.1 403"));
+        // Tokenless requests, as a page's no-cors fetch sends, do not lock anyone out
+        for _ in 0..MAX_REQUESTS + MAX_BAD_TOKENS {
+            assert!(get(&host, "").starts_with("HTTP/1.1 401"));
+        }
+        assert!(get(&host, "Authorization: Bearer token\r\n").starts_with("HTTP/1.1 200"));
+
+        assert_eq!(server.join().unwrap(), "idle timeout");
+        assert!(!token_file.exists());
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+}
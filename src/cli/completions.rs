@@ -0,0 +1,322 @@
+//! Shell Completions
+//!
+//! Generates bash, zsh and fish completion scripts from one table of
+//! subcommands and options. Credential names are completed by calling
+//! `vault names`, which only answers while an agent holds the vault open.
+
+use super::subcommands;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// What an option's value, or a subcommand's first argument, completes to
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Value {
+    None,
+    Field,
+    Name,
+    File,
+    Text,
+}
+
+struct Opt {
+    long: &'static str,
+    short: Option<&'static str>,
+    value: Value,
+}
+
+struct Spec {
+    name: &'static str,
+    argument: Value,
+    options: &'static [Opt],
+}
+
+const fn opt(long: &'static str, short: Option<&'static str>, value: Value) -> Opt {
+    Opt { long, short, value }
+}
+
+const FIELDS: &str = "password username url notes name";
+
+/// Options taken before any subcommand
+const GLOBALS: &[Opt] = &[
+    opt("--vault", None, Value::File),
+    opt("--config", None, Value::File),
+    opt("--lock-timeout", None, Value::Text),
+    opt("--read-only", None, Value::None),
+    opt("--help", Some("-h"), Value::None),
+    opt("--version", Some("-V"), Value::None),
+];
+
+const SPECS: &[Spec] = &[
+    Spec {
+        name: "check",
+        argument: Value::File,
+        options: &[opt("--repair", None, Value::None), opt("--json", None, Value::None)],
+    },
+    Spec {
+        name: "show",
+        argument: Value::Name,
+        options: &[
+            opt("--field", Some("-f"), Value::Field),
+            opt("--no-newline", Some("-n"), Value::None),
+            opt("--json", None, Value::None),
+            opt("--show-secrets", None, Value::None),
+        ],
+    },
+    Spec {
+        name: "add",
+        argument: Value::Text,
+        options: &[
+            opt("--stdin", None, Value::None),
+            opt("--username", Some("-u"), Value::Text),
+            opt("--url", None, Value::Text),
+            opt("--tag", Some("-t"), Value::Text),
+            opt("--backend", None, Value::Text),
+        ],
+    },
+    Spec {
+        name: "exec",
+        argument: Value::File,
+        options: &[opt("--tag", Some("-t"), Value::Text), opt("--name", None, Value::Name)],
+    },
+    Spec {
+        name: "render",
+        argument: Value::File,
+        options: &[opt("--output", Some("-o"), Value::File), opt("--tmpfs", None, Value::None)],
+    },
+    Spec {
+        name: "match",
+        argument: Value::Text,
+        options: &[opt("--json", None, Value::None), opt("--show-secrets", None, Value::None)],
+    },
+    Spec {
+        name: "pick",
+        argument: Value::File,
+        options: &[opt("--field", Some("-f"), Value::Field), opt("--no-newline", Some("-n"), Value::None)],
+    },
+    Spec { name: "names", argument: Value::File, options: &[opt("--json", None, Value::None)] },
+    Spec { name: "agent", argument: Value::File, options: &[opt("--idle", None, Value::Text)] },
+    Spec { name: "lock", argument: Value::File, options: &[] },
+    Spec { name: "api", argument: Value::File, options: &[opt("--port", None, Value::Text)] },
+    Spec { name: "completions", argument: Value::Text, options: &[] },
+    Spec { name: "recovery-kit", argument: Value::File, options: &[] },
+    Spec { name: "recover", argument: Value::File, options: &[] },
+    Spec { name: "emergency-codes", argument: Value::File, options: &[opt("--count", None, Value::Text)] },
+    Spec { name: "breach-index", argument: Value::Text, options: &[opt("--output", Some("-o"), Value::File)] },
+    Spec { name: "diff", argument: Value::File, options: &[] },
+];
+
+pub fn script(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash(),
+        Shell::Zsh => zsh(),
+        Shell::Fish => fish(),
+    }
+}
+
+fn option_words(options: &[Opt]) -> String {
+    options
+        .iter()
+        .flat_map(|o| std::iter::once(o.long).chain(o.short))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Options across all subcommands taking this kind of value, as a `case` pattern
+fn options_taking(value: Value, separator: &str) -> String {
+    let mut words: Vec<&str> = SPECS
+        .iter()
+        .flat_map(|s| s.options)
+        .chain(GLOBALS)
+        .filter(|o| o.value == value)
+        .flat_map(|o| std::iter::once(o.long).chain(o.short))
+        .collect();
+    words.sort_unstable();
+    words.dedup();
+    words.join(separator)
+}
+
+fn bash() -> String {
+    let mut cases = String::new();
+    for spec in SPECS {
+        cases.push_str(&format!("        {}) opts=\"{}\" ;;\n", spec.name, option_words(spec.options)));
+    }
+    let name_first: Vec<&str> = SPECS.iter().filter(|s| s.argument == Value::Name).map(|s| s.name).collect();
+
+    format!(
+        r#"_vault_names() {{
+    local IFS=$'\n'
+    COMPREPLY=($(compgen -W "$(vault names 2>/dev/null)" -- "$1"))
+}}
+
+_vault() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" prev="${{COMP_WORDS[COMP_CWORD-1]}}" sub="${{COMP_WORDS[1]}}" opts
+    case "$prev" in
+        {field}) COMPREPLY=($(compgen -W "{fields}" -- "$cur")); return ;;
+        {name}) _vault_names "$cur"; return ;;
+        {text}) return ;;
+    esac
+    if [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W "{subcommands} {globals}" -- "$cur") $(compgen -f -- "$cur"))
+        return
+    fi
+    case "$sub" in
+{cases}    esac
+    if [[ "$cur" == -* ]]; then
+        COMPREPLY=($(compgen -W "$opts" -- "$cur"))
+    elif [[ $COMP_CWORD -eq 2 && " {name_first} " == *" $sub "* ]]; then
+        _vault_names "$cur"
+    else
+        COMPREPLY=($(compgen -f -- "$cur"))
+    fi
+}}
+
+complete -o filenames -F _vault vault
+"#,
+        field = options_taking(Value::Field, "|"),
+        fields = FIELDS,
+        name = options_taking(Value::Name, "|"),
+        text = options_taking(Value::Text, "|"),
+        subcommands = subcommands().join(" "),
+        globals = option_words(GLOBALS),
+        cases = cases,
+        name_first = name_first.join(" "),
+    )
+}
+
+fn zsh() -> String {
+    let mut cases = String::new();
+    for spec in SPECS {
+        cases.push_str(&format!("        {}) opts=({}) ;;\n", spec.name, option_words(spec.options)));
+    }
+    let name_first: Vec<&str> = SPECS.iter().filter(|s| s.argument == Value::Name).map(|s| s.name).collect();
+
+    format!(
+        r#"#compdef vault
+
+_vault_names() {{
+    local -a names
+    names=("${{(@f)$(vault names 2>/dev/null)}}")
+    compadd -a names
+}}
+
+_vault() {{
+    local -a opts
+    if (( CURRENT == 2 )); then
+        compadd -- {subcommands} {globals}
+        _files
+        return
+    fi
+    case "$words[CURRENT-1]" in
+        {field}) compadd -- {fields}; return ;;
+        {name}) _vault_names; return ;;
+        {text}) return ;;
+    esac
+    case "$words[2]" in
+{cases}    esac
+    if [[ "$PREFIX" == -* ]]; then
+        compadd -- $opts
+    elif (( CURRENT == 3 )) && [[ " {name_first} " == *" $words[2] "* ]]; then
+        _vault_names
+    else
+        _files
+    fi
+}}
+
+compdef _vault vault
+"#,
+        subcommands = subcommands().join(" "),
+        globals = option_words(GLOBALS),
+        field = options_taking(Value::Field, "|"),
+        fields = FIELDS,
+        name = options_taking(Value::Name, "|"),
+        text = options_taking(Value::Text, "|"),
+        cases = cases,
+        name_first = name_first.join(" "),
+    )
+}
+
+fn fish() -> String {
+    let mut script = String::from("function __vault_names\n    vault names 2>/dev/null\nend\n\n");
+    for option in GLOBALS {
+        script.push_str(&fish_option("__fish_use_subcommand", option));
+    }
+    script.push_str(&format!("complete -c vault -n __fish_use_subcommand -a \"{}\"\n", subcommands().join(" ")));
+
+    for spec in SPECS {
+        let condition = format!("__fish_seen_subcommand_from {}", spec.name);
+        if spec.argument == Value::Name {
+            script.push_str(&format!(
+                "complete -c vault -n \"{}; and test (count (commandline -opc)) -eq 2\" -f -a \"(__vault_names)\"\n",
+                condition
+            ));
+        }
+        for option in spec.options {
+            script.push_str(&fish_option(&condition, option));
+        }
+    }
+    script
+}
+
+fn fish_option(condition: &str, option: &Opt) -> String {
+    let mut line = format!("complete -c vault -n \"{}\" -l {}", condition, option.long.trim_start_matches("--"));
+    if let Some(short) = option.short {
+        line.push_str(&format!(" -s {}", short.trim_start_matches('-')));
+    }
+    match option.value {
+        Value::None => {}
+        Value::Field => line.push_str(&format!(" -x -a \"{}\"", FIELDS)),
+        Value::Name => line.push_str(" -x -a \"(__vault_names)\""),
+        Value::File => line.push_str(" -r -F"),
+        Value::Text => line.push_str(" -x"),
+    }
+    line.push('\n');
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_subcommand_has_a_spec() {
+        let subcommands = subcommands();
+        for subcommand in &subcommands {
+            assert!(SPECS.iter().any(|s| s.name == subcommand), "no completion spec for {}", subcommand);
+        }
+        assert_eq!(SPECS.len(), subcommands.len());
+
+        // Every option completed is one the parser takes
+        let cli = <super::super::Cli as clap::CommandFactory>::command();
+        for spec in SPECS {
+            let command = cli.find_subcommand(spec.name).unwrap();
+            let longs: Vec<&str> = command
+                .get_subcommands()
+                .chain([command])
+                .flat_map(|c| c.get_arguments())
+                .filter_map(|a| a.get_long())
+                .collect();
+            for option in spec.options {
+                assert!(longs.contains(&option.long.trim_start_matches("--")), "{} takes no {}", spec.name, option.long);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scripts_complete_names_and_fields() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = script(shell);
+            assert!(script.contains("vault names"));
+            assert!(script.contains(FIELDS));
+            assert!(script.contains("recovery-kit"));
+        }
+        assert!(bash().contains("--field|-f) COMPREPLY"));
+        assert!(zsh().contains("--vault --config --lock-timeout --read-only --help -h --version -V"));
+        assert!(fish().contains("-n \"__fish_use_subcommand\" -l lock-timeout -x"));
+        assert!(fish().contains("-n \"__fish_seen_subcommand_from exec\" -l name -x -a \"(__vault_names)\""));
+    }
+}
@@ -0,0 +1,126 @@
+//! `--json` output
+//!
+//! Query subcommands print exactly one JSON document on stdout, errors
+//! included, so scripts can hand it straight to tools like jq. Field names
+//! only ever get added to, never renamed or removed.
+
+use std::io::{self, Write};
+
+use chrono::SecondsFormat;
+use secrecy::ExposeSecret;
+use serde::Serialize;
+use zeroize::Zeroize;
+
+use crate::db::Credential;
+use crate::vault::credential::DecryptedCredential;
+use crate::vault::fsck::FsckReport;
+
+/// The query succeeded
+pub const EXIT_OK: i32 = 0;
+/// Nothing matched, no agent answered, or the check found problems
+pub const EXIT_EMPTY: i32 = 1;
+/// Any other failure, reported as `{"error": ...}`
+pub const EXIT_ERROR: i32 = 3;
+
+/// A credential's metadata, with its secret and notes only when they were
+/// asked for with `--show-secrets`
+#[derive(Debug, Serialize)]
+pub struct CredentialJson<'a> {
+    id: &'a str,
+    name: &'a str,
+    #[serde(rename = "type")]
+    credential_type: &'static str,
+    username: Option<&'a str>,
+    url: Option<&'a str>,
+    tags: &'a [String],
+    alias: Option<&'a str>,
+    created_at: String,
+    updated_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<Option<&'a str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<Option<&'a str>>,
+}
+
+impl<'a> CredentialJson<'a> {
+    pub fn new(cred: &'a Credential, decrypted: Option<&'a DecryptedCredential>) -> Self {
+        Self {
+            id: &cred.id,
+            name: &cred.name,
+            credential_type: cred.credential_type.as_str(),
+            username: cred.username.as_deref(),
+            url: cred.url.as_deref(),
+            tags: &cred.tags,
+            alias: cred.alias.as_deref(),
+            created_at: cred.created_at.to_rfc3339_opts(SecondsFormat::Secs, false),
+            updated_at: cred.updated_at.to_rfc3339_opts(SecondsFormat::Secs, false),
+            secret: decrypted.map(|d| d.secret.as_ref().map(|s| s.expose_secret())),
+            notes: decrypted.map(|d| d.notes.as_ref().map(|s| s.expose_secret())),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckJson {
+    clean: bool,
+    summary: String,
+    problems: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl CheckJson {
+    pub fn new(report: &FsckReport) -> Self {
+        Self {
+            clean: report.is_clean(),
+            summary: report.summary(),
+            problems: report.problems(),
+            warnings: report.warnings(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorJson<'a> {
+    error: &'a str,
+}
+
+/// Write one document and a newline, wiping the text afterwards since it
+/// may hold secrets
+pub fn print(value: &impl Serialize) -> Result<(), Box<dyn std::error::Error>> {
+    let mut text = serde_json::to_string(value)?;
+    text.push('\n');
+    let mut stdout = io::stdout().lock();
+    let written = stdout.write_all(text.as_bytes()).and_then(|_| stdout.flush());
+    text.zeroize();
+    Ok(written?)
+}
+
+pub fn print_error(error: &dyn std::error::Error) {
+    let _ = print(&ErrorJson { error: &error.to_string() });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::CredentialType;
+
+    #[test]
+    fn test_credential_json() {
+        let mut cred = Credential::new("GitHub".to_string(), CredentialType::Password, String::new());
+        cred.username = Some("me".to_string());
+        cred.tags = vec!["dev".to_string()];
+
+        let value = serde_json::to_value(CredentialJson::new(&cred, None)).unwrap();
+        assert_eq!(value["name"], "GitHub");
+        assert_eq!(value["type"], "password");
+        assert_eq!(value["username"], "me");
+        assert!(value["url"].is_null());
+        assert_eq!(value["tags"], serde_json::json!(["dev"]));
+        assert!(value.get("secret").is_none() && value.get("notes").is_none());
+
+        let decrypted = DecryptedCredential::from_credential(&cred, Some("hunter2".to_string()), None);
+        let value = serde_json::to_value(CredentialJson::new(&cred, Some(&decrypted))).unwrap();
+        assert_eq!(value["secret"], "hunter2");
+        assert!(value["notes"].is_null());
+    }
+}
@@ -0,0 +1,1453 @@
+//! Command Line
+//!
+//! Subcommands that run without the TUI. With no subcommand the vault
+//! path (if any) is taken from the first argument and the TUI starts.
+//! Global options may come before or after the subcommand.
+
+mod agent;
+mod api;
+mod completions;
+mod json;
+mod picker;
+
+use std::ffi::OsString;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::error::ErrorKind;
+use clap::{ArgGroup, CommandFactory, Parser};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use secrecy::ExposeSecret;
+use zeroize::Zeroize;
+
+use crate::app::{run_hooks, AppConfig, EventHook, SecurityEvent};
+use crate::db::models::SECRET_BACKEND;
+use crate::db::{AuditAction, Credential, CredentialType, Database, DatabaseConfig};
+use crate::input::commands::parse_duration;
+use crate::input::line::EditLine;
+use crate::vault::backend::{self, Backends};
+use crate::vault::credential::{self, DecryptedCredential, Field};
+use crate::vault::recovery::{self, RecoveryKit};
+use crate::vault::runner::{self, Selection};
+use crate::vault::search::{self, UrlMatch};
+use crate::vault::template;
+use crate::vault::diff::{self, DiffKind};
+use crate::vault::{audit, bench, breach, emergency, fsck, Vault, VaultConfig, VaultError};
+
+pub use completions::Shell;
+use agent::Request;
+use picker::Candidate;
+
+/// The parsed command line
+#[derive(Debug, PartialEq, Eq)]
+pub struct Args {
+    pub command: Command,
+    /// `--config`, read instead of the default config file
+    pub config_path: Option<PathBuf>,
+    /// `--lock-timeout`, overriding `auto_lock_timeout` for this run
+    pub lock_timeout: Option<Duration>,
+    pub output: Output,
+}
+
+/// How `show`, `match`, `names` and `check` print their results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Output {
+    #[default]
+    Text,
+    /// `--json`, with secrets and notes only given `--show-secrets`
+    Json { show_secrets: bool },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Tui { vault_path: Option<PathBuf>, read_only: bool },
+    Check { vault_path: Option<PathBuf>, repair: bool },
+    /// Print one field of a credential to stdout
+    Show { name: String, field: Field, newline: bool, vault_path: Option<PathBuf> },
+    /// Create a password credential, the secret read from stdin or a hidden prompt
+    Add {
+        name: String,
+        from_stdin: bool,
+        username: Option<String>,
+        url: Option<String>,
+        tags: Vec<String>,
+        /// Keep the secret in this backend; what is read is its reference
+        backend: Option<String>,
+        vault_path: Option<PathBuf>,
+    },
+    /// Run a command with the selected credentials in its environment
+    Exec { selection: Selection, argv: Vec<String>, vault_path: Option<PathBuf> },
+    /// Fill a template's placeholders with credential fields
+    Render { template: PathBuf, output: RenderTarget, vault_path: Option<PathBuf> },
+    /// List credentials for a page's URL, best match first
+    Match { url: String, vault_path: Option<PathBuf> },
+    /// Choose a credential interactively and print one of its fields
+    Pick { field: Field, newline: bool, vault_path: Option<PathBuf> },
+    /// List credential names from a running agent, for shell completion
+    Names { vault_path: Option<PathBuf> },
+    /// Keep the vault unlocked and serve other invocations over a socket
+    Agent { idle: Option<Duration>, vault_path: Option<PathBuf> },
+    /// Stop the running agent
+    Lock { vault_path: Option<PathBuf> },
+    /// Serve read-only lookups over HTTP on 127.0.0.1
+    Api { port: u16, vault_path: Option<PathBuf> },
+    /// Print a completion script
+    Completions { shell: Shell },
+    /// Print a recovery kit for the vault
+    RecoveryKit { vault_path: Option<PathBuf> },
+    /// Rebuild the vault's key metadata from a recovery kit
+    Recover { kit_path: PathBuf, vault_path: Option<PathBuf> },
+    /// Replace the vault's single-use emergency unlock codes and print them
+    EmergencyCodes { count: usize, vault_path: Option<PathBuf> },
+    /// Time key derivation and vault operations on this machine
+    Bench { count: usize },
+    /// Build the offline breach filter from a Pwned Passwords SHA-1 download
+    BreachIndex { dump: PathBuf, output: Option<PathBuf> },
+    /// List the credentials added, removed or changed from one vault file
+    /// to another
+    Diff { left: PathBuf, right: PathBuf },
+}
+
+impl Command {
+    pub fn vault_path(&self) -> Option<&PathBuf> {
+        match self {
+            Self::Tui { vault_path, .. }
+            | Self::Check { vault_path, .. }
+            | Self::Show { vault_path, .. }
+            | Self::Add { vault_path, .. }
+            | Self::Exec { vault_path, .. }
+            | Self::Render { vault_path, .. }
+            | Self::Match { vault_path, .. }
+            | Self::Pick { vault_path, .. }
+            | Self::Names { vault_path }
+            | Self::Agent { vault_path, .. }
+            | Self::Lock { vault_path }
+            | Self::Api { vault_path, .. }
+            | Self::RecoveryKit { vault_path }
+            | Self::Recover { vault_path, .. }
+            | Self::EmergencyCodes { vault_path, .. } => vault_path.as_ref(),
+            Self::Completions { .. } | Self::Bench { .. } | Self::BreachIndex { .. } | Self::Diff { .. } => None,
+        }
+    }
+
+    fn with_vault_path(mut self, path: Option<PathBuf>) -> Self {
+        match &mut self {
+            Self::Tui { vault_path, .. }
+            | Self::Check { vault_path, .. }
+            | Self::Show { vault_path, .. }
+            | Self::Add { vault_path, .. }
+            | Self::Exec { vault_path, .. }
+            | Self::Render { vault_path, .. }
+            | Self::Match { vault_path, .. }
+            | Self::Pick { vault_path, .. }
+            | Self::Names { vault_path }
+            | Self::Agent { vault_path, .. }
+            | Self::Lock { vault_path }
+            | Self::Api { vault_path, .. }
+            | Self::RecoveryKit { vault_path }
+            | Self::Recover { vault_path, .. }
+            | Self::EmergencyCodes { vault_path, .. } => *vault_path = path,
+            Self::Completions { .. } | Self::Bench { .. } | Self::BreachIndex { .. } | Self::Diff { .. } => {}
+        }
+        self
+    }
+}
+
+/// Where `vault render` writes its output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderTarget {
+    Stdout,
+    /// A file only the owner can read
+    File(PathBuf),
+    /// A private file under the runtime directory, which is normally tmpfs
+    Tmpfs,
+}
+
+/// A local-first encrypted credential manager. With no subcommand the
+/// TUI starts on VAULT_PATH.
+#[derive(Parser)]
+#[command(name = "vault", version)]
+struct Cli {
+    /// Vault file, instead of passing VAULT_PATH
+    #[arg(long, global = true, value_name = "PATH")]
+    vault: Option<PathBuf>,
+    /// Config file to read and save settings to
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Auto-lock after TIME idle, e.g. 90s, 10m or 1h (TUI, agent and api)
+    #[arg(long, global = true, value_name = "TIME", value_parser = lock_timeout)]
+    lock_timeout: Option<Duration>,
+    /// Open the vault without allowing changes
+    #[arg(long)]
+    read_only: bool,
+    /// Vault file to open
+    vault_path: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Subcommand>,
+}
+
+#[derive(clap::Subcommand)]
+enum Subcommand {
+    /// Check the vault's integrity, exiting non-zero if problems remain
+    Check {
+        /// Fix what can be fixed
+        #[arg(long)]
+        repair: bool,
+        #[arg(long)]
+        json: bool,
+        vault_path: Option<PathBuf>,
+    },
+    /// Print one field of a credential
+    Show {
+        name: String,
+        #[command(flatten)]
+        field: FieldArgs,
+        /// Print every field as JSON
+        #[arg(long, conflicts_with_all = ["field", "no_newline"])]
+        json: bool,
+        /// Include secrets and notes in the JSON
+        #[arg(long, requires = "json")]
+        show_secrets: bool,
+        vault_path: Option<PathBuf>,
+    },
+    /// Add a password credential, the secret read from stdin or a prompt
+    Add {
+        name: String,
+        /// Read the secret from stdin instead of prompting
+        #[arg(long)]
+        stdin: bool,
+        #[arg(short, long, value_name = "USER")]
+        username: Option<String>,
+        #[arg(long)]
+        url: Option<String>,
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Keep the secret in this backend; what is read is its reference
+        #[arg(long, value_name = "NAME")]
+        backend: Option<String>,
+        vault_path: Option<PathBuf>,
+    },
+    /// Run a command with the selected credentials in its environment
+    #[command(group = ArgGroup::new("selection").args(["tags", "names"]).required(true).multiple(true))]
+    Exec {
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        #[arg(long = "name", value_name = "NAME")]
+        names: Vec<String>,
+        vault_path: Option<PathBuf>,
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        argv: Vec<String>,
+    },
+    /// Fill a template's placeholders with credential fields
+    Render {
+        template: PathBuf,
+        /// Write to a file only the owner can read
+        #[arg(short, long, value_name = "OUT", conflicts_with = "tmpfs")]
+        output: Option<PathBuf>,
+        /// Write to a private file under the runtime directory
+        #[arg(long)]
+        tmpfs: bool,
+        vault_path: Option<PathBuf>,
+    },
+    /// List credentials for a page's URL, best match first
+    Match {
+        url: String,
+        #[arg(long)]
+        json: bool,
+        /// Include secrets and notes in the JSON
+        #[arg(long, requires = "json")]
+        show_secrets: bool,
+        vault_path: Option<PathBuf>,
+    },
+    /// Choose a credential interactively and print one of its fields
+    Pick {
+        #[command(flatten)]
+        field: FieldArgs,
+        vault_path: Option<PathBuf>,
+    },
+    /// List credential names from a running agent
+    Names {
+        #[arg(long)]
+        json: bool,
+        vault_path: Option<PathBuf>,
+    },
+    /// Keep the vault unlocked and serve other invocations over a socket
+    Agent {
+        /// Lock and exit after this many idle minutes
+        #[arg(long, value_name = "MINUTES", value_parser = minutes)]
+        idle: Option<Duration>,
+        vault_path: Option<PathBuf>,
+    },
+    /// Stop the running agent
+    Lock { vault_path: Option<PathBuf> },
+    /// Serve read-only lookups over HTTP on 127.0.0.1
+    Api {
+        #[arg(long, default_value_t = api::DEFAULT_PORT, value_parser = clap::value_parser!(u16).range(1..))]
+        port: u16,
+        vault_path: Option<PathBuf>,
+    },
+    /// Print a completion script
+    Completions { shell: Shell },
+    /// Print a recovery kit for the vault
+    RecoveryKit { vault_path: Option<PathBuf> },
+    /// Rebuild the vault's key metadata from a recovery kit
+    Recover {
+        #[arg(value_name = "KIT_FILE")]
+        kit_path: PathBuf,
+        vault_path: Option<PathBuf>,
+    },
+    /// Replace the vault's single-use emergency unlock codes and print them
+    EmergencyCodes {
+        #[arg(long, value_name = "N", default_value_t = emergency::DEFAULT_CODES, value_parser = count)]
+        count: usize,
+        vault_path: Option<PathBuf>,
+    },
+    /// Time key derivation and vault operations on this machine
+    #[command(hide = true)]
+    Bench {
+        #[arg(long, value_name = "N", default_value_t = bench::DEFAULT_COUNT, value_parser = count)]
+        count: usize,
+    },
+    /// Manage the offline breach filter
+    BreachIndex {
+        #[command(subcommand)]
+        action: BreachIndexAction,
+    },
+    /// List the credentials added, removed or changed from one vault file
+    /// to another
+    Diff { left: PathBuf, right: PathBuf },
+}
+
+#[derive(clap::Subcommand)]
+enum BreachIndexAction {
+    /// Build the filter from a Pwned Passwords SHA-1 download
+    Build {
+        dump: PathBuf,
+        #[arg(short, long, value_name = "OUT")]
+        output: Option<PathBuf>,
+    },
+}
+
+/// `--field` and `--no-newline`, for `show` and `pick`
+#[derive(clap::Args)]
+struct FieldArgs {
+    /// password, username, url, notes or name
+    #[arg(short, long, default_value = "password", value_parser = field)]
+    field: Field,
+    /// Leave out the trailing newline
+    #[arg(short = 'n', long)]
+    no_newline: bool,
+}
+
+fn lock_timeout(value: &str) -> Result<Duration, String> {
+    parse_duration(value)
+        .filter(|t| !t.is_zero())
+        .ok_or_else(|| format!("takes a time like 90s, 10m or 1h, not '{}'", value))
+}
+
+fn minutes(value: &str) -> Result<Duration, String> {
+    let minutes: u64 = value.parse().map_err(|_| format!("takes minutes, not '{}'", value))?;
+    Ok(Duration::from_secs(minutes * 60))
+}
+
+fn count(value: &str) -> Result<usize, String> {
+    value.parse().ok().filter(|n| *n != 0).ok_or_else(|| format!("takes a number above zero, not '{}'", value))
+}
+
+fn field(value: &str) -> Result<Field, String> {
+    Field::parse(value).ok_or_else(|| format!("unknown field '{}'", value))
+}
+
+/// Subcommands listed in the help, for the completion scripts
+fn subcommands() -> Vec<String> {
+    Cli::command()
+        .get_subcommands()
+        .filter(|c| !c.is_hide_set())
+        .map(|c| c.get_name().to_string())
+        .collect()
+}
+
+/// Parse the command line, program name first. Help and version requests
+/// come back as errors too, which print themselves on `exit`.
+pub fn parse_args<I, T>(args: I) -> Result<Args, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let cli = Cli::try_parse_from(args)?;
+    let invalid = |message: &str| Cli::command().error(ErrorKind::ArgumentConflict, message);
+
+    if cli.command.is_some() && (cli.read_only || cli.vault_path.is_some()) {
+        return Err(invalid("--read-only and a leading VAULT_PATH only apply to the TUI"));
+    }
+
+    let mut output = Output::Text;
+    let mut json_output = |json: bool, show_secrets: bool| {
+        if json {
+            output = Output::Json { show_secrets };
+        }
+    };
+    let command = match cli.command {
+        None => Command::Tui { vault_path: cli.vault_path, read_only: cli.read_only },
+        Some(Subcommand::Check { repair, json: j, vault_path }) => {
+            json_output(j, false);
+            Command::Check { vault_path, repair }
+        }
+        Some(Subcommand::Show { name, field, json: j, show_secrets, vault_path }) => {
+            json_output(j, show_secrets);
+            Command::Show { name, field: field.field, newline: !field.no_newline, vault_path }
+        }
+        Some(Subcommand::Add { name, stdin, username, url, tags, backend, vault_path }) => {
+            Command::Add { name, from_stdin: stdin, username, url, tags, backend, vault_path }
+        }
+        Some(Subcommand::Exec { tags, names, vault_path, argv }) => {
+            Command::Exec { selection: Selection { tags, names }, argv, vault_path }
+        }
+        Some(Subcommand::Render { template, output, tmpfs, vault_path }) => {
+            let output = match (output, tmpfs) {
+                (Some(path), _) => RenderTarget::File(path),
+                (None, true) => RenderTarget::Tmpfs,
+                (None, false) => RenderTarget::Stdout,
+            };
+            Command::Render { template, output, vault_path }
+        }
+        Some(Subcommand::Match { url, json: j, show_secrets, vault_path }) => {
+            json_output(j, show_secrets);
+            Command::Match { url, vault_path }
+        }
+        Some(Subcommand::Pick { field, vault_path }) => {
+            Command::Pick { field: field.field, newline: !field.no_newline, vault_path }
+        }
+        Some(Subcommand::Names { json: j, vault_path }) => {
+            json_output(j, false);
+            Command::Names { vault_path }
+        }
+        Some(Subcommand::Agent { idle, vault_path }) => Command::Agent { idle, vault_path },
+        Some(Subcommand::Lock { vault_path }) => Command::Lock { vault_path },
+        Some(Subcommand::Api { port, vault_path }) => Command::Api { port, vault_path },
+        Some(Subcommand::Completions { shell }) => Command::Completions { shell },
+        Some(Subcommand::RecoveryKit { vault_path }) => Command::RecoveryKit { vault_path },
+        Some(Subcommand::Recover { kit_path, vault_path }) => Command::Recover { kit_path, vault_path },
+        Some(Subcommand::EmergencyCodes { count, vault_path }) => Command::EmergencyCodes { count, vault_path },
+        Some(Subcommand::Bench { count }) => Command::Bench { count },
+        Some(Subcommand::BreachIndex { action: BreachIndexAction::Build { dump, output } }) => {
+            Command::BreachIndex { dump, output }
+        }
+        Some(Subcommand::Diff { left, right }) => Command::Diff { left, right },
+    };
+
+    let takes_path = !matches!(command, Command::Completions { .. } | Command::Bench { .. } | Command::BreachIndex { .. } | Command::Diff { .. });
+    let vault_path = match (command.vault_path(), cli.vault) {
+        (Some(arg), Some(option)) => {
+            return Err(invalid(&format!("vault path given twice: {} and --vault {}", arg.display(), option.display())));
+        }
+        (_, Some(_)) if !takes_path => return Err(invalid("--vault does not apply to this subcommand")),
+        (arg, option) => arg.cloned().or(option),
+    };
+    match (&command, cli.lock_timeout) {
+        (_, None) | (Command::Tui { .. } | Command::Agent { idle: None, .. } | Command::Api { .. }, Some(_)) => {}
+        (Command::Agent { .. }, Some(_)) => return Err(invalid("agent takes --idle or --lock-timeout, not both")),
+        (_, Some(_)) => return Err(invalid("--lock-timeout only applies to the TUI, agent and api")),
+    }
+    Ok(Args {
+        command: command.with_vault_path(vault_path),
+        config_path: cli.config,
+        lock_timeout: cli.lock_timeout,
+        output,
+    })
+}
+
+/// Unlock the vault, run the integrity check and print the report.
+/// Returns false when problems remain.
+pub fn run_check(config: &AppConfig, repair: bool) -> Result<bool, Box<dyn std::error::Error>> {
+    let report = check_report(config, repair)?;
+    println!("{}", report.summary());
+    for problem in report.problems() {
+        println!("  - {}", problem);
+    }
+    for warning in report.warnings() {
+        println!("  ! {}", warning);
+    }
+    if report.is_clean() {
+        println!("No problems found");
+    }
+    Ok(report.is_clean())
+}
+
+fn check_report(config: &AppConfig, repair: bool) -> Result<fsck::FsckReport, Box<dyn std::error::Error>> {
+    let vault = unlock_with_prompt(config, false)?;
+    let keys = vault.keys()?;
+    let audit_key = keys.derive_audit_key()?;
+    Ok(fsck::check_vault(vault.db()?.conn(), keys.dek(), &audit_key, repair)?)
+}
+
+/// Run a query subcommand with `--json`, printing one document, and
+/// return the exit code. `show` and `match` unlock the vault themselves
+/// since the agent only hands out single fields.
+pub fn run_json(config: &AppConfig, command: &Command, show_secrets: bool) -> i32 {
+    let result = match command {
+        Command::Show { name, .. } => json_show(config, name, show_secrets),
+        Command::Match { url, .. } => json_match(config, url, show_secrets),
+        Command::Names { .. } => json_names(config),
+        Command::Check { repair, .. } => check_report(config, *repair).and_then(|report| {
+            json::print(&json::CheckJson::new(&report))?;
+            Ok(report.is_clean())
+        }),
+        _ => Err("This command has no JSON output".into()),
+    };
+    match result {
+        Ok(true) => json::EXIT_OK,
+        Ok(false) => json::EXIT_EMPTY,
+        Err(e) => {
+            json::print_error(&*e);
+            json::EXIT_ERROR
+        }
+    }
+}
+
+fn json_show(config: &AppConfig, name: &str, show_secrets: bool) -> Result<bool, Box<dyn std::error::Error>> {
+    let vault = unlock_with_prompt(config, false)?;
+    let conn = vault.db()?.conn();
+    let cred = find_by_name(conn, name)?;
+    let (decrypted, fetched) = match show_secrets {
+        true => {
+            let (decrypted, fetched) = backend::decrypt_resolved(conn, vault.dek()?, &backends(config), &cred)?;
+            (Some(decrypted), fetched)
+        }
+        false => (None, None),
+    };
+    let details = if show_secrets { "CLI show --json --show-secrets" } else { "CLI show --json" };
+    log_cli_action(&vault, AuditAction::Read, &cred, &backend::audit_details(details, fetched.as_deref()))?;
+    json::print(&json::CredentialJson::new(&cred, decrypted.as_ref()))?;
+    Ok(true)
+}
+
+fn json_match(config: &AppConfig, url: &str, show_secrets: bool) -> Result<bool, Box<dyn std::error::Error>> {
+    let vault = unlock_with_prompt(config, false)?;
+    let conn = vault.db()?.conn();
+    let matches = search::match_url(conn, url)?;
+    let mut decrypted = Vec::new();
+    if show_secrets {
+        let backends = backends(config);
+        for m in &matches {
+            let (secrets, fetched) = backend::decrypt_resolved(conn, vault.dek()?, &backends, &m.credential)?;
+            decrypted.push(secrets);
+            let details = backend::audit_details("CLI match --json --show-secrets", fetched.as_deref());
+            log_cli_action(&vault, AuditAction::Read, &m.credential, &details)?;
+        }
+    }
+    let entries: Vec<json::CredentialJson> = matches
+        .iter()
+        .enumerate()
+        .map(|(i, m)| json::CredentialJson::new(&m.credential, decrypted.get(i)))
+        .collect();
+    json::print(&entries)?;
+    Ok(!entries.is_empty())
+}
+
+/// Names from a running agent; an empty list when none is running
+fn json_names(config: &AppConfig) -> Result<bool, Box<dyn std::error::Error>> {
+    let names = match agent::try_request(&config.vault_path, &Request::Names) {
+        Some(names) => Some(names?),
+        None => None,
+    };
+    let list: Vec<&str> = names.as_deref().map(|n| n.lines().collect()).unwrap_or_default();
+    json::print(&list)?;
+    Ok(names.is_some())
+}
+
+/// Print exactly one field of the named credential, so scripts can
+/// capture it without the secret passing through arguments
+pub fn run_show(config: &AppConfig, name: &str, field: Field, newline: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let get = Request::Get { field, name: name.to_string() };
+    if let Some(reply) = agent::try_request(&config.vault_path, &get) {
+        return print_value(reply?, newline);
+    }
+
+    let vault = unlock_with_prompt(config, false)?;
+    let conn = vault.db()?.conn();
+    let cred = find_by_name(conn, name)?;
+    let (decrypted, fetched) = decrypt_for_field(&vault, &backends(config), &cred, field)?;
+    let value = field
+        .value(&decrypted)
+        .ok_or_else(|| format!("'{}' has no {}", cred.name, field.label()))?;
+
+    let details = backend::audit_details(&format!("CLI show {}", field.label()), fetched.as_deref());
+    log_cli_action(&vault, AuditAction::Read, &cred, &details)?;
+    print_value(value, newline)
+}
+
+/// Print `name<TAB>username` for each credential matching the URL, best
+/// first. Exits unsuccessfully when nothing matches.
+pub fn run_match(config: &AppConfig, url: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let lines = match agent::try_request(&config.vault_path, &Request::Match(url.to_string())) {
+        Some(reply) => reply?,
+        None => {
+            let vault = unlock_with_prompt(config, false)?;
+            format_matches(&search::match_url(vault.db()?.conn(), url)?)
+        }
+    };
+    print!("{}", lines);
+    Ok(!lines.is_empty())
+}
+
+fn format_matches(matches: &[UrlMatch]) -> String {
+    matches
+        .iter()
+        .map(|m| format!("{}\t{}\n", m.credential.name, m.credential.username.as_deref().unwrap_or_default()))
+        .collect()
+}
+
+/// Fuzzy-pick a credential on the terminal and print one of its fields
+pub fn run_pick(config: &AppConfig, field: Field, newline: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if !io::stderr().is_terminal() {
+        return Err("pick needs a terminal".into());
+    }
+    if let Some(names) = agent::try_request(&config.vault_path, &Request::Names) {
+        let names: Vec<String> = names?.lines().map(str::to_string).collect();
+        let candidates: Vec<Candidate> =
+            names.iter().map(|name| Candidate { label: name.clone(), keywords: Vec::new() }).collect();
+        let Some(index) = picker::pick(&candidates)? else {
+            return Err("cancelled".into());
+        };
+        let get = Request::Get { field, name: names[index].clone() };
+        let value = agent::try_request(&config.vault_path, &get).ok_or("The agent stopped")??;
+        return print_value(value, newline);
+    }
+
+    let vault = unlock_with_prompt(config, false)?;
+    let conn = vault.db()?.conn();
+    let all = credential::list_credentials(conn)?;
+    let candidates: Vec<Candidate> = all
+        .iter()
+        .map(|c| Candidate {
+            label: c.name.clone(),
+            keywords: c.alias.iter().chain(&c.username).chain(&c.url).chain(&c.tags).cloned().collect(),
+        })
+        .collect();
+
+    let Some(index) = picker::pick(&candidates)? else {
+        return Err("cancelled".into());
+    };
+    let cred = &all[index];
+    let (decrypted, fetched) = decrypt_for_field(&vault, &backends(config), cred, field)?;
+    let value = field
+        .value(&decrypted)
+        .ok_or_else(|| format!("'{}' has no {}", cred.name, field.label()))?;
+
+    let details = backend::audit_details(&format!("CLI pick {}", field.label()), fetched.as_deref());
+    log_cli_action(&vault, AuditAction::Read, cred, &details)?;
+    print_value(value, newline)
+}
+
+/// Write a value to stdout and wipe it
+fn print_value(mut value: String, newline: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stdout = io::stdout().lock();
+    let written = stdout.write_all(value.as_bytes()).and_then(|_| match newline {
+        true => stdout.write_all(b"\n"),
+        false => Ok(()),
+    });
+    value.zeroize();
+    written?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Print the credential names a running agent reports, one per line.
+/// Silent when no agent is running, so completion simply offers nothing.
+pub fn run_names(config: &AppConfig) -> Result<bool, Box<dyn std::error::Error>> {
+    match agent::try_request(&config.vault_path, &Request::Names) {
+        Some(Ok(names)) => {
+            print!("{}", names);
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Unlock once and serve other invocations until idle or locked
+#[cfg(unix)]
+pub fn run_agent(config: &AppConfig, idle: Option<Duration>) -> Result<(), Box<dyn std::error::Error>> {
+    let config = AppConfig { auto_lock_timeout: idle.unwrap_or(config.auto_lock_timeout), ..config.clone() };
+    let socket = agent::socket_path(&config.vault_path)?;
+    let vault = unlock_with_prompt(&config, false)?;
+    eprintln!(
+        "Agent serving {} on {}; locks after {} min idle",
+        config.vault_path.display(),
+        socket.display(),
+        config.auto_lock_timeout.as_secs() / 60
+    );
+    let reason = agent::serve(vault, &backends(&config), &config.event_hooks, &socket)?;
+    eprintln!("Agent stopped: {}", reason);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn run_agent(_config: &AppConfig, _idle: Option<Duration>) -> Result<(), Box<dyn std::error::Error>> {
+    Err("The agent needs Unix domain sockets".into())
+}
+
+/// Unlock and serve the local HTTP API until idle
+pub fn run_api(config: &AppConfig, port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    let token_file = api::token_path(&config.vault_path)?;
+    let vault = unlock_with_prompt(config, false)?;
+    let mut token = api::new_token();
+    write_private(&token_file, &token)?;
+    eprintln!("API serving {} on http://127.0.0.1:{}/v1/", config.vault_path.display(), port);
+    eprintln!("Token (also in {}): {}", token_file.display(), token);
+    eprintln!("Locks after {} min idle", config.auto_lock_timeout.as_secs() / 60);
+    let served = api::serve(vault, &backends(config), &config.event_hooks, port, &token, &token_file);
+    token.zeroize();
+    eprintln!("API stopped: {}", served?);
+    Ok(())
+}
+
+/// Tell the running agent to lock the vault and exit
+pub fn run_lock(config: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+    agent::try_request(&config.vault_path, &Request::Lock).ok_or("No agent is running for this vault")??;
+    eprintln!("Agent locked");
+    Ok(())
+}
+
+/// Build the breach filter at `output`, reading the download twice
+pub fn run_breach_index(dump: &Path, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    eprintln!("Reading {}...", dump.display());
+    let entries = breach::build(dump, output, breach::DEFAULT_FP_RATE)?;
+    println!(
+        "Wrote {} ({} hashes, {}% false positives)",
+        output.display(),
+        entries,
+        breach::DEFAULT_FP_RATE * 100.0
+    );
+    Ok(())
+}
+
+/// Unlock both vaults read-only and print what changed from `left` to
+/// `right`. Returns whether they hold the same credentials.
+pub fn run_diff(config: &AppConfig, left: &Path, right: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+    let before = unlock_read_only(config, left)?;
+    let after = unlock_read_only(config, right)?;
+    let result = diff::diff((before.db()?.conn(), before.dek()?), (after.db()?.conn(), after.dek()?))?;
+
+    let time = |at: Option<chrono::DateTime<chrono::Local>>| at.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default();
+    for entry in &result.entries {
+        match &entry.kind {
+            DiffKind::Added => println!("+ {}", entry.name),
+            DiffKind::Removed => println!("- {}", entry.name),
+            DiffKind::Changed(fields) => println!(
+                "~ {}  {} (updated {} -> {})",
+                entry.name,
+                fields.join(", "),
+                time(entry.left_updated),
+                time(entry.right_updated)
+            ),
+        }
+    }
+    println!("{}", result.summary());
+    Ok(result.is_empty())
+}
+
+/// Time the benchmark workloads and print the results. Runs in memory
+/// and never touches a vault.
+pub fn run_bench(count: usize) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Key derivation");
+    for (name, params) in bench::kdf_presets() {
+        println!("  {:<28}{:>10}", name, format_duration(bench::best_of(3, || bench::derive_key(&params))?));
+    }
+
+    println!("{} credentials", count);
+    let start = std::time::Instant::now();
+    let fixture = bench::Fixture::new(count)?;
+    let rows = [
+        ("create vault", start.elapsed()),
+        ("encrypt all secrets", bench::best_of(3, || fixture.encrypt_all())?),
+        ("decrypt all credentials", bench::best_of(3, || fixture.decrypt_all())?),
+        ("search \"github\"", bench::best_of(10, || fixture.search("github"))?),
+        ("refresh list", bench::best_of(10, || fixture.refresh())?),
+    ];
+    for (name, elapsed) in rows {
+        println!("  {:<28}{:>10}", name, format_duration(elapsed));
+    }
+    Ok(())
+}
+
+fn format_duration(elapsed: Duration) -> String {
+    match elapsed.as_micros() {
+        us if us < 10_000 => format!("{:.2} ms", us as f64 / 1000.0),
+        us => format!("{} ms", us / 1000),
+    }
+}
+
+pub fn print_completions(shell: Shell) {
+    print!("{}", completions::script(shell));
+}
+
+/// Create a password credential. With `from_stdin` the secret is read
+/// from stdin and the master password from the terminal.
+pub fn run_add(
+    config: &AppConfig,
+    name: &str,
+    from_stdin: bool,
+    username: Option<String>,
+    url: Option<String>,
+    tags: Vec<String>,
+    backend: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(backend) = backend.as_ref().filter(|b| !config.secret_backends.contains_key(*b)) {
+        return Err(format!("No secret backend '{}' in the config", backend).into());
+    }
+    let mut secret = match from_stdin {
+        true => read_secret_from_stdin()?,
+        false => String::new(),
+    };
+    let vault = unlock_with_prompt(config, from_stdin)?;
+    if !from_stdin {
+        secret = match &backend {
+            Some(backend) => prompt_password(&format!("Reference in {} for {}: ", backend, name))?,
+            None => prompt_password(&format!("Secret for {}: ", name))?,
+        };
+    }
+    if secret.is_empty() {
+        return Err("No secret given".into());
+    }
+
+    let conn = vault.db()?.conn();
+    if credential::list_credentials(conn)?.iter().any(|c| c.name == name) {
+        secret.zeroize();
+        return Err(format!("Credential '{}' already exists", name).into());
+    }
+    let created = credential::create_credential(
+        conn,
+        vault.dek()?,
+        credential::NewCredential {
+            username,
+            url,
+            tags,
+            ..credential::NewCredential::new(name.to_string(), CredentialType::Password, &secret)
+        },
+    );
+    secret.zeroize();
+    let mut cred = created?;
+    if let Some(backend) = backend {
+        let attributes = [(SECRET_BACKEND.to_string(), backend)].into();
+        credential::set_attributes(conn, &mut cred, attributes)?;
+    }
+
+    log_cli_action(&vault, AuditAction::Create, &cred, "CLI add")?;
+    eprintln!("Added {}", cred.name);
+    Ok(())
+}
+
+/// Exact name match, then an alias, falling back to a unique
+/// case-insensitive name
+fn find_by_name(conn: &rusqlite::Connection, name: &str) -> Result<Credential, Box<dyn std::error::Error>> {
+    let all = credential::list_credentials(conn)?;
+    if let Some(cred) = all.iter().find(|c| c.name == name) {
+        return Ok(cred.clone());
+    }
+    if let Some(cred) = crate::vault::alias::find(conn, name)? {
+        return Ok(cred);
+    }
+    let mut matches = all.into_iter().filter(|c| c.name.eq_ignore_ascii_case(name));
+    match (matches.next(), matches.next()) {
+        (Some(cred), None) => Ok(cred),
+        (Some(_), Some(_)) => Err(format!("Several credentials match '{}'; use the exact name", name).into()),
+        (None, _) => Err(format!("No credential named '{}'", name).into()),
+    }
+}
+
+/// The secret backends the config defines
+fn backends(config: &AppConfig) -> Backends {
+    Backends::from_commands(&config.secret_backends)
+}
+
+/// Decrypt a credential to print one field, fetching the secret from its
+/// backend only when the secret is the field asked for
+pub(super) fn decrypt_for_field(
+    vault: &Vault,
+    backends: &Backends,
+    cred: &Credential,
+    field: Field,
+) -> Result<(DecryptedCredential, Option<String>), VaultError> {
+    let conn = vault.db()?.conn();
+    match field {
+        Field::Secret => backend::decrypt_resolved(conn, vault.dek()?, backends, cred),
+        _ => Ok((credential::decrypt_credential(conn, vault.dek()?, cred, true)?, None)),
+    }
+}
+
+fn log_cli_action(
+    vault: &Vault,
+    action: AuditAction,
+    cred: &Credential,
+    details: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let audit_key = vault.keys()?.derive_audit_key()?;
+    let conn = vault.db()?.conn();
+    audit::log_action(conn, &audit_key, action, Some(&cred.id), Some(&cred.name), cred.username.as_deref(), Some(details))?;
+    Ok(())
+}
+
+/// The whole of stdin, minus one trailing line break
+fn read_secret_from_stdin() -> Result<String, Box<dyn std::error::Error>> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    let trimmed = input.strip_suffix('\n').map(|s| s.strip_suffix('\r').unwrap_or(s)).unwrap_or(&input);
+    let secret = trimmed.to_string();
+    input.zeroize();
+    Ok(secret)
+}
+
+/// Resolve the selected credentials and run the command with them in its
+/// environment, returning its exit code where the process is not replaced
+pub fn run_exec(config: &AppConfig, selection: &Selection, argv: &[String]) -> Result<i32, Box<dyn std::error::Error>> {
+    if let Some(reply) = agent::try_request(&config.vault_path, &Request::Env(selection.clone())) {
+        let mut reply = reply?;
+        let vars = agent::decode_env(&reply);
+        reply.zeroize();
+        let mut vars = vars?;
+        if vars.is_empty() {
+            return Err("No selected credential has an environment variable set".into());
+        }
+        let code = runner::exec(argv, vars.iter().map(|(name, value)| (name.as_str(), value.as_str())));
+        vars.iter_mut().for_each(|(_, value)| value.zeroize());
+        return Ok(code?);
+    }
+
+    let vault = unlock_with_prompt(config, false)?;
+    let conn = vault.db()?.conn();
+    let vars = runner::resolve_env(conn, vault.dek()?, &backends(config), selection)?;
+    if vars.is_empty() {
+        return Err("No selected credential has an environment variable set".into());
+    }
+    for var in &vars {
+        let details = backend::audit_details(&format!("CLI exec as {}", var.name), var.backend.as_deref());
+        log_cli_action(&vault, AuditAction::Read, &var.credential, &details)?;
+    }
+    drop(vault);
+    Ok(runner::exec(argv, vars.iter().map(|v| (v.name.as_str(), v.value.expose_secret())))?)
+}
+
+/// Render a template, decrypting each credential it names once
+pub fn run_render(config: &AppConfig, template_path: &Path, output: &RenderTarget) -> Result<(), Box<dyn std::error::Error>> {
+    let template_text = std::fs::read_to_string(template_path)?;
+    if agent::try_request(&config.vault_path, &Request::Status).is_some() {
+        let rendered = template::render(&template_text, |placeholder| {
+            let get = Request::Get { field: placeholder.field, name: placeholder.name.clone() };
+            agent::try_request(&config.vault_path, &get)
+                .unwrap_or_else(|| Err("The agent stopped".into()))
+                .map_err(|e| VaultError::OperationFailed(e.to_string()))
+        })?;
+        return write_rendered(config, rendered, template_path, output);
+    }
+
+    let vault = unlock_with_prompt(config, false)?;
+    let conn = vault.db()?.conn();
+    let backends = backends(config);
+
+    let mut used: Vec<(Credential, DecryptedCredential, Option<String>)> = Vec::new();
+    let rendered = template::render(&template_text, |placeholder| {
+        if !used.iter().any(|(c, ..)| c.name == placeholder.name) {
+            let cred = find_by_name(conn, &placeholder.name).map_err(|e| VaultError::OperationFailed(e.to_string()))?;
+            let (decrypted, fetched) = backend::decrypt_resolved(conn, vault.dek()?, &backends, &cred)?;
+            used.push((cred, decrypted, fetched));
+        }
+        let (cred, decrypted, _) = used.iter().find(|(c, ..)| c.name == placeholder.name).expect("just inserted");
+        placeholder.field.value(decrypted).ok_or_else(|| {
+            VaultError::OperationFailed(format!("'{}' has no {}", cred.name, placeholder.field.label()))
+        })
+    });
+    let rendered = rendered?;
+
+    let details = format!("CLI render {}", template_path.display());
+    for (cred, _, fetched) in &used {
+        log_cli_action(&vault, AuditAction::Read, cred, &backend::audit_details(&details, fetched.as_deref()))?;
+    }
+    write_rendered(config, rendered, template_path, output)
+}
+
+/// Write the rendered template out; to a file, that counts as an export
+fn write_rendered(
+    config: &AppConfig,
+    mut rendered: String,
+    template_path: &Path,
+    output: &RenderTarget,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let written = match output {
+        RenderTarget::Stdout => io::stdout().lock().write_all(rendered.as_bytes()).map_err(Into::into),
+        RenderTarget::File(path) => write_private(path, &rendered),
+        RenderTarget::Tmpfs => tmpfs_path(template_path).and_then(|path| {
+            write_private(&path, &rendered)?;
+            println!("{}", path.display());
+            Ok(())
+        }),
+    };
+    rendered.zeroize();
+    if written.is_ok() && *output != RenderTarget::Stdout {
+        let message = format!("Rendered {} with credentials to a file", template_path.display());
+        fire_event(&config.event_hooks, SecurityEvent::Export, &message);
+    }
+    written
+}
+
+/// Create or overwrite a file readable only by its owner
+fn write_private(path: &Path, contents: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)?.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+/// `<private runtime dir>/<template name>`
+fn tmpfs_path(template_path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let name = template_path.file_stem().ok_or("Template path has no file name")?;
+    Ok(private_runtime_dir()?.join(name))
+}
+
+/// `$XDG_RUNTIME_DIR/vault`, falling back to `/dev/shm/vault`, created
+/// with access for the owner only
+fn private_runtime_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let base = dirs::runtime_dir()
+        .or_else(|| Some(PathBuf::from("/dev/shm")).filter(|p| p.is_dir()))
+        .ok_or("No runtime directory or /dev/shm available")?;
+    let dir = base.join("vault");
+    std::fs::create_dir_all(&dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(dir)
+}
+
+/// Unlock the vault and print its recovery kit to stdout
+pub fn run_recovery_kit(config: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let vault = unlock_with_prompt(config, false)?;
+    let kit = RecoveryKit::export(&vault)?;
+    print!("{}", kit.render(&config.vault_path)?);
+    fire_event(&config.event_hooks, SecurityEvent::Export, "Printed a recovery kit");
+    eprintln!("Print this or keep it offline. With the master password it restores the vault's key.");
+    Ok(())
+}
+
+/// Unlock the vault, replace its emergency codes and print the new ones
+pub fn run_emergency_codes(config: &AppConfig, count: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let mut vault = unlock_with_prompt(config, false)?;
+    let codes = vault.create_emergency_codes(count)?;
+    println!("Emergency unlock codes for {}", config.vault_path.display());
+    for (label, code) in codes {
+        println!("  {:<14}{}", label, code);
+    }
+    eprintln!("Each code opens the vault once in place of the master password and is revoked when used.");
+    eprintln!("They are shown only now; codes printed before no longer work.");
+    Ok(())
+}
+
+/// Restore the vault's key metadata from a recovery kit file
+pub fn run_recover(config: &AppConfig, kit_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if !config.vault_path.exists() {
+        return Err(VaultError::NotFound.into());
+    }
+    let kit = RecoveryKit::parse(&std::fs::read_to_string(kit_path)?)?;
+    eprintln!("Recovering {} with key {}", config.vault_path.display(), kit.fingerprint);
+
+    let db = Database::open(DatabaseConfig::with_path(&config.vault_path))?;
+    let mut password = prompt_password("Master password for this kit: ")?;
+    let recovered = recovery::recover(db.conn(), &kit, &password);
+    password.zeroize();
+    recovered?;
+
+    eprintln!("Vault key restored");
+    Ok(())
+}
+
+/// Prompt for the master password and unlock. When stdin carries other
+/// input the password is always read from the terminal.
+fn unlock_with_prompt(config: &AppConfig, stdin_taken: bool) -> Result<Vault, Box<dyn std::error::Error>> {
+    let vault_config = VaultConfig {
+        auto_lock_timeout: config.auto_lock_timeout,
+        wipe_after_failures: config.wipe_after_failures,
+        search_index: config.search_index,
+        encrypt_metadata: config.encrypt_metadata,
+        ..VaultConfig::with_path(&config.vault_path)
+    };
+    unlock_config(vault_config, &config.event_hooks, "Master password: ", stdin_taken)
+}
+
+/// Prompt for the password of the vault at `path` and open it read-only
+fn unlock_read_only(config: &AppConfig, path: &Path) -> Result<Vault, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Err(format!("No vault at {}", path.display()).into());
+    }
+    let vault_config = VaultConfig {
+        read_only: true,
+        wipe_after_failures: config.wipe_after_failures,
+        ..VaultConfig::with_path(path)
+    };
+    unlock_config(vault_config, &config.event_hooks, &format!("Master password for {}: ", path.display()), false)
+}
+
+fn unlock_config(
+    vault_config: VaultConfig,
+    hooks: &[EventHook],
+    prompt: &str,
+    stdin_taken: bool,
+) -> Result<Vault, Box<dyn std::error::Error>> {
+    let mut vault = Vault::new(vault_config);
+    let mut password = match stdin_taken {
+        true => prompt_on_terminal(prompt)?,
+        false => prompt_password(prompt)?,
+    };
+    let unlocked = vault.unlock(&password);
+    password.zeroize();
+    if let Err(VaultError::InvalidPassword) = unlocked {
+        fire_event(hooks, SecurityEvent::FailedUnlock, "Wrong master password entered on the command line");
+        if vault.record_failed_unlock().is_ok_and(|failure| failure.wiped) {
+            return Err(VaultError::Wiped.into());
+        }
+    }
+    unlocked?;
+    warn_emergency_unlocks(&vault, hooks)?;
+    Ok(vault)
+}
+
+/// Run the hooks configured for `event`, noting on stderr any that fail
+/// to start
+fn fire_event(hooks: &[EventHook], event: SecurityEvent, message: &str) {
+    for failure in run_hooks(hooks, event, message) {
+        eprintln!("{} hook failed: {}", event, failure);
+    }
+}
+
+/// Tell whoever just unlocked about emergency codes, their own or ones
+/// used since they last did
+fn warn_emergency_unlocks(vault: &Vault, hooks: &[EventHook]) -> Result<(), Box<dyn std::error::Error>> {
+    if emergency::is_emergency_slot(vault.active_slot()) {
+        eprintln!("Unlocked with emergency code {}; it no longer works", vault.active_slot());
+        fire_event(hooks, SecurityEvent::EmergencyUnlock, &format!("Vault unlocked with emergency code {}", vault.active_slot()));
+    }
+    for used in vault.take_emergency_unlocks()? {
+        eprintln!("WARNING: emergency code {} was used on {}", used.label, used.at.format("%Y-%m-%d %H:%M"));
+    }
+    Ok(())
+}
+
+/// Read a password without echo, falling back to a plain line when
+/// stdin is not a terminal
+fn prompt_password(prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
+    if !io::stdin().is_terminal() {
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+        let password = line.trim_end_matches(['\r', '\n']).to_string();
+        line.zeroize();
+        return Ok(password);
+    }
+    prompt_on_terminal(prompt)
+}
+
+/// Hidden prompt on the controlling terminal, even when stdin is a pipe
+fn prompt_on_terminal(prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+    enable_raw_mode()?;
+    let result = read_hidden_line();
+    disable_raw_mode()?;
+    eprintln!();
+    result
+}
+
+fn read_hidden_line() -> Result<String, Box<dyn std::error::Error>> {
+    let mut password = EditLine::new();
+    loop {
+        let Event::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => return Ok(password.to_string()),
+            KeyCode::Esc => break,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+            KeyCode::Backspace => {
+                password.backspace();
+            }
+            KeyCode::Char(c) => password.insert(c),
+            _ => {}
+        }
+    }
+    Err("cancelled".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_full(args: &[&str]) -> Result<Args, String> {
+        parse_args(std::iter::once("vault").chain(args.iter().copied())).map_err(|e| e.to_string())
+    }
+
+    fn error_kind(args: &[&str]) -> Option<ErrorKind> {
+        parse_args(std::iter::once("vault").chain(args.iter().copied())).err().map(|e| e.kind())
+    }
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_full(args).map(|args| args.command)
+    }
+
+    #[test]
+    fn test_parse_tui() {
+        assert_eq!(parse(&[]), Ok(Command::Tui { vault_path: None, read_only: false }));
+        assert_eq!(parse(&["my.db"]), Ok(Command::Tui { vault_path: Some(PathBuf::from("my.db")), read_only: false }));
+        assert_eq!(
+            parse(&["--read-only", "my.db"]),
+            Ok(Command::Tui { vault_path: Some(PathBuf::from("my.db")), read_only: true })
+        );
+        assert!(parse(&["--repair"]).is_err());
+        assert!(parse(&["--read-only", "names"]).is_err());
+        assert!(parse(&["my.db", "names"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_global_options() {
+        let args = parse_full(&["--vault", "my.db", "--config", "alt.json", "--lock-timeout", "90s"]).unwrap();
+        assert_eq!(args.command, Command::Tui { vault_path: Some(PathBuf::from("my.db")), read_only: false });
+        assert_eq!(args.config_path, Some(PathBuf::from("alt.json")));
+        assert_eq!(args.lock_timeout, Some(Duration::from_secs(90)));
+
+        // Before or after the subcommand
+        assert_eq!(parse(&["--vault", "my.db", "names"]), Ok(Command::Names { vault_path: Some(PathBuf::from("my.db")) }));
+        assert_eq!(parse(&["names", "--vault", "my.db"]), Ok(Command::Names { vault_path: Some(PathBuf::from("my.db")) }));
+        assert_eq!(
+            parse(&["--vault", "my.db", "--read-only"]),
+            Ok(Command::Tui { vault_path: Some(PathBuf::from("my.db")), read_only: true })
+        );
+
+        assert_eq!(error_kind(&["--help"]), Some(ErrorKind::DisplayHelp));
+        assert_eq!(error_kind(&["show", "-h"]), Some(ErrorKind::DisplayHelp));
+        assert_eq!(error_kind(&["-V"]), Some(ErrorKind::DisplayVersion));
+    }
+
+    #[test]
+    fn test_parse_json_output() {
+        let args = parse_full(&["show", "GitHub", "--json"]).unwrap();
+        assert_eq!(args.output, Output::Json { show_secrets: false });
+        let args = parse_full(&["match", "https://github.com", "--json", "--show-secrets"]).unwrap();
+        assert_eq!(args.output, Output::Json { show_secrets: true });
+        assert_eq!(parse_full(&["names"]).unwrap().output, Output::Text);
+
+        assert!(parse(&["show", "GitHub", "--show-secrets"]).unwrap_err().contains("--json"));
+        assert!(parse(&["show", "GitHub", "--json", "--field", "url"]).is_err());
+        assert!(parse(&["names", "--show-secrets", "--json"]).is_err());
+        assert!(parse(&["add", "x", "--json"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_global_conflicts() {
+        assert!(parse(&["--vault", "a.db", "b.db"]).unwrap_err().contains("given twice"));
+        assert!(parse(&["--vault", "a.db", "--vault", "b.db"]).unwrap_err().contains("cannot be used multiple times"));
+        assert!(parse(&["--vault"]).is_err());
+        assert!(parse(&["--lock-timeout", "soon"]).is_err());
+        assert!(parse(&["--lock-timeout", "0"]).is_err());
+        assert!(parse(&["--lock-timeout", "5m", "names"]).unwrap_err().contains("only applies"));
+        assert!(parse(&["--lock-timeout", "5m", "api"]).is_ok());
+        assert!(parse(&["--lock-timeout", "5m", "agent", "--idle", "60"]).is_err());
+        assert!(parse(&["--vault", "a.db", "completions", "bash"]).is_err());
+        assert_eq!(
+            parse_full(&["agent", "--lock-timeout", "1h"]).map(|a| a.lock_timeout),
+            Ok(Some(Duration::from_secs(3600)))
+        );
+    }
+
+    #[test]
+    fn test_parse_check() {
+        assert_eq!(parse(&["check"]), Ok(Command::Check { vault_path: None, repair: false }));
+        assert_eq!(
+            parse(&["check", "--repair", "my.db"]),
+            Ok(Command::Check { vault_path: Some(PathBuf::from("my.db")), repair: true })
+        );
+        assert!(parse(&["check", "--force"]).is_err());
+        assert!(parse(&["check", "--read-only"]).is_err());
+        assert!(parse(&["check", "a.db", "b.db"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_recovery() {
+        assert_eq!(parse(&["recovery-kit"]), Ok(Command::RecoveryKit { vault_path: None }));
+        assert_eq!(
+            parse(&["recover", "kit.txt", "my.db"]),
+            Ok(Command::Recover { kit_path: PathBuf::from("kit.txt"), vault_path: Some(PathBuf::from("my.db")) })
+        );
+        assert!(parse(&["recover"]).is_err());
+        assert!(parse(&["recovery-kit", "--repair"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_show_and_add() {
+        assert_eq!(
+            parse(&["show", "github", "--field", "username", "-n", "my.db"]),
+            Ok(Command::Show {
+                name: "github".to_string(),
+                field: Field::Username,
+                newline: false,
+                vault_path: Some(PathBuf::from("my.db")),
+            })
+        );
+        assert!(matches!(parse(&["show", "github"]), Ok(Command::Show { field: Field::Secret, newline: true, .. })));
+        assert!(parse(&["show", "github", "--field", "colour"]).is_err());
+        assert!(parse(&["show", "github", "--field"]).is_err());
+        assert!(parse(&["show"]).is_err());
+
+        assert_eq!(
+            parse(&["add", "db", "--stdin", "-u", "root", "--tag", "prod", "-t", "sql"]),
+            Ok(Command::Add {
+                name: "db".to_string(),
+                from_stdin: true,
+                username: Some("root".to_string()),
+                url: None,
+                tags: vec!["prod".to_string(), "sql".to_string()],
+                backend: None,
+                vault_path: None,
+            })
+        );
+        assert!(matches!(
+            parse(&["add", "deploy", "--backend", "pass"]),
+            Ok(Command::Add { backend: Some(b), .. }) if b == "pass"
+        ));
+        assert!(parse(&["add", "db", "--field", "url"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_exec() {
+        assert_eq!(
+            parse(&["exec", "--tag", "prod", "my.db", "--", "psql", "--tag", "x"]),
+            Ok(Command::Exec {
+                selection: Selection { tags: vec!["prod".to_string()], names: vec![] },
+                argv: vec!["psql".to_string(), "--tag".to_string(), "x".to_string()],
+                vault_path: Some(PathBuf::from("my.db")),
+            })
+        );
+        assert!(parse(&["exec", "--", "env"]).is_err());
+        assert!(parse(&["exec", "--name", "db"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_render() {
+        assert_eq!(
+            parse(&["render", "app.conf.tmpl", "-o", "app.conf"]),
+            Ok(Command::Render {
+                template: PathBuf::from("app.conf.tmpl"),
+                output: RenderTarget::File(PathBuf::from("app.conf")),
+                vault_path: None,
+            })
+        );
+        assert!(matches!(parse(&["render", "t.tmpl", "--tmpfs"]), Ok(Command::Render { output: RenderTarget::Tmpfs, .. })));
+        assert!(parse(&["render", "t.tmpl", "--tmpfs", "-o", "x"]).is_err());
+        assert!(parse(&["render"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_pick_names_and_completions() {
+        assert_eq!(
+            parse(&["pick", "-f", "username", "my.db"]),
+            Ok(Command::Pick { field: Field::Username, newline: true, vault_path: Some(PathBuf::from("my.db")) })
+        );
+        assert_eq!(parse(&["names"]), Ok(Command::Names { vault_path: None }));
+        assert_eq!(
+            parse(&["match", "https://github.com/login"]),
+            Ok(Command::Match { url: "https://github.com/login".to_string(), vault_path: None })
+        );
+        assert!(parse(&["match"]).is_err());
+        assert_eq!(parse(&["completions", "fish"]), Ok(Command::Completions { shell: Shell::Fish }));
+        assert!(parse(&["completions", "tcsh"]).is_err());
+        assert!(parse(&["completions", "zsh", "my.db"]).is_err());
+        assert!(parse(&["pick", "--stdin"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_agent() {
+        assert_eq!(
+            parse(&["agent", "--idle", "30", "my.db"]),
+            Ok(Command::Agent { idle: Some(Duration::from_secs(1800)), vault_path: Some(PathBuf::from("my.db")) })
+        );
+        assert_eq!(parse(&["agent"]), Ok(Command::Agent { idle: None, vault_path: None }));
+        assert!(parse(&["agent", "--idle", "soon"]).is_err());
+        assert_eq!(parse(&["lock"]), Ok(Command::Lock { vault_path: None }));
+    }
+
+    #[test]
+    fn test_parse_api() {
+        assert_eq!(parse(&["api"]), Ok(Command::Api { port: api::DEFAULT_PORT, vault_path: None }));
+        assert_eq!(
+            parse(&["api", "--port", "9000", "my.db"]),
+            Ok(Command::Api { port: 9000, vault_path: Some(PathBuf::from("my.db")) })
+        );
+        assert!(parse(&["api", "--port", "0"]).is_err());
+        assert!(parse(&["api", "--port", "http"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_emergency_codes() {
+        assert_eq!(parse(&["emergency-codes"]), Ok(Command::EmergencyCodes { count: emergency::DEFAULT_CODES, vault_path: None }));
+        assert_eq!(parse(&["emergency-codes", "--count", "3", "v.db"]), Ok(Command::EmergencyCodes { count: 3, vault_path: Some(PathBuf::from("v.db")) }));
+        assert!(parse(&["emergency-codes", "--count", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_bench() {
+        assert_eq!(parse(&["bench"]), Ok(Command::Bench { count: bench::DEFAULT_COUNT }));
+        assert_eq!(parse(&["bench", "--count", "500"]), Ok(Command::Bench { count: 500 }));
+        assert!(parse(&["bench", "--count", "0"]).is_err());
+        assert!(parse(&["bench", "my.db"]).is_err());
+        assert!(!Cli::command().render_help().to_string().contains("bench"));
+    }
+
+    #[test]
+    fn test_parse_breach_index() {
+        let dump = PathBuf::from("pwned.txt");
+        assert_eq!(parse(&["breach-index", "build", "pwned.txt"]), Ok(Command::BreachIndex { dump: dump.clone(), output: None }));
+        assert_eq!(
+            parse(&["breach-index", "build", "pwned.txt", "-o", "hibp.bloom"]),
+            Ok(Command::BreachIndex { dump, output: Some(PathBuf::from("hibp.bloom")) })
+        );
+        assert!(parse(&["breach-index", "query", "pwned.txt"]).is_err());
+        assert!(parse(&["breach-index", "build"]).is_err());
+        assert!(parse(&["breach-index", "build", "pwned.txt", "my.db"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_diff() {
+        assert_eq!(
+            parse(&["diff", "backup.db", "vault.db"]),
+            Ok(Command::Diff { left: PathBuf::from("backup.db"), right: PathBuf::from("vault.db") })
+        );
+        assert!(parse(&["diff", "backup.db"]).is_err());
+        assert!(parse(&["diff", "a.db", "b.db", "c.db"]).is_err());
+        assert!(parse(&["--vault", "c.db", "diff", "a.db", "b.db"]).is_err());
+    }
+
+    #[test]
+    fn test_write_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.conf");
+        std::fs::write(&path, "old contents that are longer").unwrap();
+        write_private(&path, "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Picker
+//!
+//! A small fzf-style chooser drawn on stderr below the cursor, so stdout
+//! stays free for whatever is picked.
+
+use std::io::{self, Write};
+
+use crossterm::cursor::{MoveToColumn, MoveUp};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::queue;
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType};
+
+use crate::input::fuzzy::best_score;
+use crate::input::line::EditLine;
+
+const MAX_ROWS: usize = 10;
+
+/// One choice: the text shown and the extra fields it can be matched on
+pub struct Candidate {
+    pub label: String,
+    pub keywords: Vec<String>,
+}
+
+/// Indices of the candidates matching `query`, best first. Ties keep
+/// their original order.
+pub fn ranked(query: &str, candidates: &[Candidate]) -> Vec<usize> {
+    let mut scored: Vec<(i64, usize)> = candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| {
+            let fields = std::iter::once(c.label.as_str()).chain(c.keywords.iter().map(String::as_str));
+            best_score(query, fields).map(|score| (score, i))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    scored.into_iter().map(|(_, i)| i).collect()
+}
+
+/// Let the user choose a candidate. Returns `None` when cancelled.
+pub fn pick(candidates: &[Candidate]) -> io::Result<Option<usize>> {
+    enable_raw_mode()?;
+    let result = run(candidates);
+    let mut err = io::stderr();
+    let cleared = queue!(err, MoveToColumn(0), Clear(ClearType::FromCursorDown)).and_then(|_| err.flush());
+    disable_raw_mode()?;
+    cleared?;
+    result
+}
+
+fn run(candidates: &[Candidate]) -> io::Result<Option<usize>> {
+    let mut query = EditLine::new();
+    let mut selected = 0;
+    let mut matches = ranked(&query, candidates);
+    loop {
+        draw(&query, &matches, candidates, selected)?;
+        let Event::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => return Ok(matches.get(selected).copied()),
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if ctrl => return Ok(None),
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Char('p' | 'k') if ctrl => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected = (selected + 1).min(matches.len().saturating_sub(1)),
+            KeyCode::Char('n' | 'j') if ctrl => selected = (selected + 1).min(matches.len().saturating_sub(1)),
+            KeyCode::Backspace => {
+                query.backspace();
+                matches = ranked(&query, candidates);
+                selected = 0;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                query.insert(c);
+                matches = ranked(&query, candidates);
+                selected = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Prompt line followed by the visible matches, leaving the cursor at the
+/// end of the prompt
+fn draw(query: &str, matches: &[usize], candidates: &[Candidate], selected: usize) -> io::Result<()> {
+    let mut err = io::stderr();
+    queue!(err, MoveToColumn(0), Clear(ClearType::FromCursorDown))?;
+
+    let first = selected.saturating_sub(MAX_ROWS - 1);
+    let visible = &matches[first..matches.len().min(first + MAX_ROWS)];
+    for (row, &index) in visible.iter().enumerate() {
+        queue!(err, Print("\r\n"))?;
+        if first + row == selected {
+            queue!(err, SetAttribute(Attribute::Reverse), Print(format!("> {}", candidates[index].label)))?;
+            queue!(err, SetAttribute(Attribute::Reset))?;
+        } else {
+            queue!(err, Print(format!("  {}", candidates[index].label)))?;
+        }
+    }
+    if !visible.is_empty() {
+        queue!(err, MoveUp(visible.len() as u16))?;
+    }
+
+    let prompt = format!("{}/{} > {}", matches.len(), candidates.len(), query);
+    queue!(err, MoveToColumn(0), Print(&prompt))?;
+    err.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(label: &str, keywords: &[&str]) -> Candidate {
+        Candidate { label: label.to_string(), keywords: keywords.iter().map(|k| k.to_string()).collect() }
+    }
+
+    #[test]
+    fn test_ranked() {
+        let candidates = [
+            candidate("GitLab", &["me@work"]),
+            candidate("GitHub", &["octocat"]),
+            candidate("Bank", &[]),
+        ];
+        assert_eq!(ranked("", &candidates), vec![0, 1, 2]);
+        assert_eq!(ranked("gh", &candidates), vec![1]);
+        assert_eq!(ranked("octo", &candidates), vec![1]);
+        assert!(ranked("zzz", &candidates).is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+//! Process hardening
+//!
+//! Applied first thing at startup, before any secret is read: no core
+//! dumps, no ptrace attach from unprivileged processes, no loader
+//! variables passed on to the tools the app starts, and on Windows DEP and
+//! no legacy extension points. `:securityinfo` lists what took effect.
+
+use std::sync::OnceLock;
+
+use crate::crypto::{memory_lock_stats, MemoryLockStats};
+
+/// Variables that make the dynamic loader inject or redirect libraries.
+/// Already too late for this process, but the clipboard tools and browser
+/// it starts would inherit them.
+#[cfg(unix)]
+const LOADER_VARS: &[&str] = &[
+    "LD_PRELOAD",
+    "LD_LIBRARY_PATH",
+    "LD_AUDIT",
+    "DYLD_INSERT_LIBRARIES",
+    "DYLD_LIBRARY_PATH",
+    "DYLD_FRAMEWORK_PATH",
+];
+
+static APPLIED: OnceLock<Vec<Mitigation>> = OnceLock::new();
+
+/// One hardening measure and whether it is in effect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mitigation {
+    pub name: &'static str,
+    pub active: bool,
+    pub detail: String,
+}
+
+impl Mitigation {
+    pub fn new(name: &'static str, active: bool, detail: impl Into<String>) -> Self {
+        Self { name, active, detail: detail.into() }
+    }
+}
+
+/// Apply every mitigation the platform has. Must run while the process is
+/// still single-threaded, as it edits the environment.
+pub fn apply() {
+    let mut applied = Vec::new();
+    #[cfg(unix)]
+    {
+        applied.push(disable_core_dumps());
+        applied.push(sanitize_environment());
+    }
+    #[cfg(target_os = "linux")]
+    applied.push(disable_dumpable());
+    #[cfg(windows)]
+    {
+        applied.push(windows::check_dep());
+        applied.push(windows::disable_extension_points());
+    }
+    let _ = APPLIED.set(applied);
+}
+
+/// The startup mitigations followed by how key buffers were locked so far
+pub fn report() -> Vec<Mitigation> {
+    let mut report = APPLIED.get().cloned().unwrap_or_default();
+    report.extend(memory_mitigations(memory_lock_stats()));
+    report
+}
+
+fn memory_mitigations(stats: MemoryLockStats) -> Vec<Mitigation> {
+    let total = stats.locked + stats.not_locked;
+    if total == 0 {
+        return vec![Mitigation::new("Memory locking", false, "No keys in memory yet")];
+    }
+    let mut mitigations = vec![Mitigation::new(
+        "Memory locking",
+        stats.not_locked == 0,
+        format!("{} of {} key buffers locked against swapping", stats.locked, total),
+    )];
+    if cfg!(target_os = "linux") {
+        mitigations.push(Mitigation::new(
+            "MADV_DONTDUMP",
+            stats.not_dumped == total,
+            format!("{} of {} key buffers left out of core dumps", stats.not_dumped, total),
+        ));
+    }
+    mitigations
+}
+
+#[cfg(unix)]
+fn disable_core_dumps() -> Mitigation {
+    let none = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    let mut current = libc::rlimit { rlim_cur: libc::RLIM_INFINITY, rlim_max: libc::RLIM_INFINITY };
+    // SAFETY: both pointers are to live rlimit structs
+    let active = unsafe {
+        libc::setrlimit(libc::RLIMIT_CORE, &none);
+        libc::getrlimit(libc::RLIMIT_CORE, &mut current) == 0 && current.rlim_cur == 0
+    };
+    let detail = if active { "RLIMIT_CORE is 0" } else { "Could not lower RLIMIT_CORE" };
+    Mitigation::new("No core dumps", active, detail)
+}
+
+#[cfg(target_os = "linux")]
+fn disable_dumpable() -> Mitigation {
+    // SAFETY: prctl with these options reads and writes no memory
+    let active = unsafe {
+        libc::prctl(libc::PR_SET_DUMPABLE, 0);
+        libc::prctl(libc::PR_GET_DUMPABLE) == 0
+    };
+    let detail = if active { "PR_SET_DUMPABLE is 0, ptrace attach refused" } else { "PR_SET_DUMPABLE failed" };
+    Mitigation::new("Not dumpable", active, detail)
+}
+
+#[cfg(unix)]
+fn sanitize_environment() -> Mitigation {
+    let present: Vec<&str> = LOADER_VARS.iter().copied().filter(|var| std::env::var_os(var).is_some()).collect();
+    for var in &present {
+        // SAFETY: called from `apply` before any thread is started
+        unsafe { std::env::remove_var(var) };
+    }
+    let detail = match present.is_empty() {
+        true => "No loader variables set".to_string(),
+        false => format!("Removed {}", present.join(", ")),
+    };
+    Mitigation::new("Environment", true, detail)
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::mem::{size_of, zeroed};
+
+    use windows_sys::Win32::System::SystemServices::{
+        PROCESS_MITIGATION_DEP_POLICY, PROCESS_MITIGATION_EXTENSION_POINT_DISABLE_POLICY,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcess, GetProcessMitigationPolicy, ProcessDEPPolicy, ProcessExtensionPointDisablePolicy,
+        SetProcessMitigationPolicy,
+    };
+
+    use super::Mitigation;
+
+    pub fn check_dep() -> Mitigation {
+        // SAFETY: the policy struct is plain data of the size passed
+        let active = unsafe {
+            let mut policy: PROCESS_MITIGATION_DEP_POLICY = zeroed();
+            GetProcessMitigationPolicy(
+                GetCurrentProcess(),
+                ProcessDEPPolicy,
+                &mut policy as *mut _ as *mut _,
+                size_of::<PROCESS_MITIGATION_DEP_POLICY>(),
+            ) != 0
+                && policy.Anonymous.Flags & 1 != 0
+        };
+        let detail = if active { "Data execution prevention is on" } else { "Data execution prevention is off" };
+        Mitigation::new("DEP", active, detail)
+    }
+
+    /// Stop legacy extension points such as AppInit DLLs from loading
+    /// into the process
+    pub fn disable_extension_points() -> Mitigation {
+        // SAFETY: the policy struct is plain data of the size passed
+        let active = unsafe {
+            let mut policy: PROCESS_MITIGATION_EXTENSION_POINT_DISABLE_POLICY = zeroed();
+            policy.Anonymous.Flags = 1;
+            SetProcessMitigationPolicy(
+                ProcessExtensionPointDisablePolicy,
+                &policy as *const _ as *const _,
+                size_of::<PROCESS_MITIGATION_EXTENSION_POINT_DISABLE_POLICY>(),
+            ) != 0
+        };
+        let detail = if active { "AppInit DLLs and other extension points blocked" } else { "SetProcessMitigationPolicy failed" };
+        Mitigation::new("Extension points", active, detail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_mitigations() {
+        let none = memory_mitigations(MemoryLockStats::default());
+        assert_eq!(none, [Mitigation::new("Memory locking", false, "No keys in memory yet")]);
+
+        let partial = memory_mitigations(MemoryLockStats { locked: 2, not_locked: 1, not_dumped: 3 });
+        assert!(!partial[0].active);
+        assert_eq!(partial[0].detail, "2 of 3 key buffers locked against swapping");
+        if cfg!(target_os = "linux") {
+            assert!(partial[1].active);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Translations
+//!
+//! UI strings are looked up by their English text, gettext style, in a
+//! catalog read once at startup from the JSON file the `locale` setting
+//! names. Anything the catalog leaves out is shown in English, so a
+//! partial translation still works. Placeholders are `{}`, filled in
+//! order, or `{0}`, `{1}`... so a translation can reorder them.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+
+static CATALOG: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Read the catalog, a JSON object of English text to translation
+pub fn load(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read translations {}: {}", path.display(), e))?;
+    let catalog: HashMap<String, String> = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid translations in {}: {}", path.display(), e))?;
+    CATALOG.set(catalog).map_err(|_| "Translations already loaded")?;
+    Ok(())
+}
+
+/// The translation of `text`, or `text` itself
+pub fn tr(text: &str) -> &str {
+    lookup(CATALOG.get(), text)
+}
+
+/// `tr` for popup titles and footers, keeping the spaces they are padded with
+pub fn tr_padded(text: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.len() == text.len() {
+        return tr(text).to_string();
+    }
+    let start = text.len() - text.trim_start().len();
+    let end = start + trimmed.len();
+    format!("{}{}{}", &text[..start], tr(trimmed), &text[end..])
+}
+
+/// Translate a template and fill in its placeholders
+pub fn trf(template: &str, args: &[&dyn Display]) -> String {
+    fill(tr(template), args)
+}
+
+fn lookup<'a>(catalog: Option<&'a HashMap<String, String>>, text: &'a str) -> &'a str {
+    catalog.and_then(|c| c.get(text)).map_or(text, String::as_str)
+}
+
+/// Replace `{}` with the next argument and `{n}` with argument n. `{{` and
+/// `}}` are literal braces; a placeholder with no argument is left as is.
+fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut next = 0;
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        let close = tail.find('}').filter(|_| tail.starts_with('{'));
+        let Some(close) = close else {
+            out.push_str(&tail[..1]);
+            rest = &tail[1..];
+            continue;
+        };
+        let inner = &tail[1..close];
+        let index = if inner.is_empty() {
+            next += 1;
+            Some(next - 1)
+        } else {
+            inner.parse::<usize>().ok()
+        };
+        match index.and_then(|n| args.get(n)) {
+            Some(arg) => {
+                let _ = write!(out, "{}", arg);
+            }
+            None => out.push_str(&tail[..=close]),
+        }
+        rest = &tail[close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(fill("Copied {} to clipboard", &[&"password"]), "Copied password to clipboard");
+        assert_eq!(fill("{1} de {0}", &[&"a", &3]), "3 de a");
+        assert_eq!(fill("{{literal}} {}", &[&1]), "{literal} 1");
+        assert_eq!(fill("missing {} {name}", &[]), "missing {} {name}");
+    }
+
+    #[test]
+    fn test_lookup_falls_back_to_english() {
+        let catalog = HashMap::from([("Locked".to_string(), "Verrouillé".to_string())]);
+        assert_eq!(lookup(Some(&catalog), "Locked"), "Verrouillé");
+        assert_eq!(lookup(Some(&catalog), "Unlocked"), "Unlocked");
+        assert_eq!(lookup(None, "Locked"), "Locked");
+    }
+}
@@ -0,0 +1,579 @@
+//! Commands
+//!
+//! The `:` command registry, with argument parsing, Tab completion and
+//! history.
+
+use std::time::Duration;
+
+use crate::db::SortOrder;
+use crate::vault::csv_export::{self, CsvField};
+use crate::vault::otp_backup::OtpFormat;
+
+use super::keymap::Action;
+
+/// How many commands the history remembers
+const HISTORY_LIMIT: usize = 100;
+
+/// What a command's arguments complete to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    None,
+    /// A fixed first argument
+    Words(&'static [&'static str]),
+    /// A credential name, which may contain spaces
+    Credential,
+    /// `add`/`rm` and a tag, or a tag to filter by
+    Tag,
+}
+
+pub struct Command {
+    /// Name and aliases; completion offers the first
+    pub names: &'static [&'static str],
+    /// Arguments as help shows them
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub args: ArgKind,
+    parse: fn(Option<&str>) -> Action,
+}
+
+pub static COMMANDS: &[Command] = &[
+    Command { names: &["clear", "cls"], args: ArgKind::None, parse: |_| Action::Clear,
+        usage: "", help: "Clear message" },
+    Command { names: &["quit", "q"], args: ArgKind::None, parse: |_| Action::Quit,
+        usage: "", help: "Quit" },
+    Command { names: &["quit!", "q!"], args: ArgKind::None, parse: |_| Action::ForceQuit,
+        usage: "", help: "Quit, skipping the confirmation" },
+    // Changes are saved as they are made
+    Command { names: &["write", "w"], args: ArgKind::None, parse: |_| Action::None,
+        usage: "", help: "Save (changes are saved as made)" },
+    Command { names: &["wq"], args: ArgKind::None, parse: |_| Action::Quit,
+        usage: "", help: "Quit" },
+    Command { names: &["new", "n"], args: ArgKind::None, parse: |_| Action::New,
+        usage: "", help: "New credential" },
+    Command { names: &["edit", "e"], args: ArgKind::None, parse: |_| Action::Edit,
+        usage: "", help: "Edit credential" },
+    Command { names: &["delete", "del"], args: ArgKind::None, parse: |_| Action::Delete,
+        usage: "", help: "Delete credential" },
+    Command { names: &["generate", "gen"], args: ArgKind::Words(&["phrase"]), parse: parse_generate,
+        usage: "[phrase [N]]", help: "Generate password or passphrase" },
+    Command { names: &["help", "h"], args: ArgKind::None, parse: |_| Action::ShowHelp,
+        usage: "", help: "Show this help" },
+    Command { names: &["passwd", "password", "changepw"], args: ArgKind::None, parse: |_| Action::ChangePassword,
+        usage: "", help: "Change master key" },
+    Command { names: &["keyslot", "keyslots"], args: ArgKind::Words(&["add", "remove", "list"]), parse: parse_keyslot,
+        usage: "add/rm/list", help: "Manage extra master keys" },
+    Command { names: &["lock"], args: ArgKind::None, parse: |_| Action::Lock,
+        usage: "", help: "Lock vault" },
+    Command { names: &["set"], args: ArgKind::Words(&["autolock"]), parse: parse_set,
+        usage: "autolock TIME", help: "Change the auto-lock timeout, e.g. 10m, and save it" },
+    Command { names: &["open", "browse"], args: ArgKind::None, parse: |_| Action::OpenUrl,
+        usage: "", help: "Open URL in browser" },
+    Command { names: &["refresh"], args: ArgKind::None, parse: |_| Action::Refresh,
+        usage: "", help: "Reload credentials" },
+    Command { names: &["logs", "log"], args: ArgKind::None, parse: |_| Action::ShowLogs,
+        usage: "", help: "View logs" },
+    Command { names: &["audit", "verify"], args: ArgKind::None, parse: |_| Action::VerifyAudit,
+        usage: "", help: "Verify audit log integrity" },
+    Command { names: &["fsck", "check"], args: ArgKind::Words(&["repair"]), parse: parse_fsck,
+        usage: "[repair]", help: "Check vault integrity" },
+    Command { names: &["compact", "vacuum"], args: ArgKind::None, parse: |_| Action::Compact,
+        usage: "", help: "Erase deleted data, shrink file" },
+    Command { names: &["privacy"], args: ArgKind::Words(&["on", "off"]), parse: parse_privacy,
+        usage: "[on/off]", help: "Mask usernames and URLs on screen" },
+    Command { names: &["sync"], args: ArgKind::Words(&["push", "pull"]), parse: |args| parse_sync(args, false),
+        usage: "push/pull", help: "Sync vault over SSH" },
+    Command { names: &["sync!"], args: ArgKind::Words(&["push", "pull"]), parse: |args| parse_sync(args, true),
+        usage: "push/pull", help: "Sync, overriding a conflict" },
+    Command { names: &["tags"], args: ArgKind::None, parse: |_| Action::ShowTags,
+        usage: "", help: "View tags" },
+    Command { names: &["tag"], args: ArgKind::Tag, parse: parse_tag,
+        usage: "[add/rm] TAG", help: "Tag, untag or filter by tag" },
+    Command { names: &["nofilter", "nof"], args: ArgKind::None, parse: |_| Action::ClearFilters,
+        usage: "", help: "Clear search filters" },
+    Command { names: &["unused"], args: ArgKind::None, parse: parse_unused,
+        usage: "[days]", help: "Credentials unused for a year" },
+    Command { names: &["timeline", "history"], args: ArgKind::None, parse: |_| Action::ShowTimeline,
+        usage: "", help: "Credential timeline" },
+    Command { names: &["find", "f"], args: ArgKind::None, parse: |_| Action::ShowFinder,
+        usage: "", help: "Fuzzy finder" },
+    Command { names: &["palette"], args: ArgKind::None, parse: |_| Action::ShowPalette,
+        usage: "", help: "Search and run any command" },
+    Command { names: &["backups", "backup"], args: ArgKind::None, parse: |_| Action::ShowBackups,
+        usage: "", help: "List and restore backups" },
+    Command { names: &["inspect"], args: ArgKind::None, parse: parse_inspect,
+        usage: "[PATH]", help: "Browse a backup or export read-only, or go back" },
+    Command { names: &["dupes", "duplicates"], args: ArgKind::None, parse: |_| Action::ShowDupes,
+        usage: "", help: "Find probable duplicates" },
+    Command { names: &["breaches", "breach"], args: ArgKind::None, parse: |_| Action::CheckBreaches,
+        usage: "", help: "Find passwords in the offline breach index" },
+    Command { names: &["securityinfo", "security"], args: ArgKind::None, parse: |_| Action::ShowSecurityInfo,
+        usage: "", help: "Show which process hardening is active" },
+    Command { names: &["tips"], args: ArgKind::None, parse: |_| Action::ShowTips,
+        usage: "", help: "Security tips, such as clipboard managers found running" },
+    Command { names: &["info"], args: ArgKind::None, parse: |_| Action::ShowVaultInfo,
+        usage: "", help: "Show the vault file and its fingerprint" },
+    Command { names: &["cryptoinfo", "crypto"], args: ArgKind::None, parse: |_| Action::ShowCryptoInfo,
+        usage: "", help: "Show the encryption format of each credential" },
+    Command { names: &["merge"], args: ArgKind::Credential, parse: parse_merge,
+        usage: "NAME", help: "Merge a credential into this one" },
+    Command { names: &["sort"], args: ArgKind::Words(&["name", "updated", "created", "type"]), parse: parse_sort,
+        usage: "ORDER", help: "Sort by name, updated, created or type" },
+    Command { names: &["export"], args: ArgKind::Words(&["csv"]), parse: parse_export,
+        usage: "[csv] PATH", help: "Write an encrypted copy, or chosen fields as CSV" },
+    Command { names: &["vaultmove"], args: ArgKind::None, parse: parse_vault_move,
+        usage: "PATH", help: "Move the vault file and keep working from there" },
+    Command { names: &["sshkey", "keyfile"], args: ArgKind::None, parse: parse_ssh_key,
+        usage: "PATH", help: "Load an SSH key credential from a key file" },
+    Command { names: &["alias"], args: ArgKind::None, parse: parse_alias,
+        usage: "[NAME]", help: "Give the credential a short name, or a memorable one" },
+    Command { names: &["unalias"], args: ArgKind::None, parse: |_| Action::Unalias,
+        usage: "", help: "Remove the credential's alias" },
+    Command { names: &["otpimport"], args: ArgKind::None, parse: parse_otp_import,
+        usage: "PATH", help: "Import TOTP accounts from an Aegis or andOTP backup" },
+    Command { names: &["otpexport"], args: ArgKind::Words(&["aegis", "andotp"]), parse: parse_otp_export,
+        usage: "aegis/andotp PATH", help: "Export TOTP accounts for Aegis or andOTP" },
+    Command { names: &["rotate"], args: ArgKind::None, parse: |_| Action::Rotate,
+        usage: "", help: "Replace the secret, keeping the old one in history" },
+];
+
+impl Command {
+    /// How help writes the command, e.g. `:merge NAME`
+    pub fn label(&self) -> String {
+        match self.usage {
+            "" => format!(":{}", self.names[0]),
+            usage => format!(":{} {}", self.names[0], usage),
+        }
+    }
+
+    /// Whether the command without arguments changes the vault
+    pub fn changes_vault(&self) -> bool {
+        self.bare_action().is_mutating()
+    }
+
+    /// What the command does without arguments
+    pub fn bare_action(&self) -> Action {
+        (self.parse)(None)
+    }
+
+    /// Whether every argument is optional, as `[on/off]` is
+    pub fn runs_bare(&self) -> bool {
+        self.usage.is_empty() || (self.usage.starts_with('[') && self.usage.ends_with(']'))
+    }
+}
+
+/// Look a command up by name or alias
+pub fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|c| c.names.contains(&name))
+}
+
+/// Parse command string into action
+pub fn parse_command(cmd: &str) -> Action {
+    let cmd = cmd.trim();
+    let (name, args) = match cmd.split_once(' ') {
+        Some((name, args)) => (name, Some(args)),
+        None => (cmd, None),
+    };
+    if name.is_empty() {
+        return Action::None;
+    }
+    match find_command(name) {
+        Some(command) => (command.parse)(args),
+        None => Action::Invalid(name.to_string()),
+    }
+}
+
+const DEFAULT_PASSPHRASE_WORDS: usize = 6;
+
+fn parse_generate(args: Option<&str>) -> Action {
+    let mut parts = args.unwrap_or("").split_whitespace();
+    match (parts.next(), parts.next()) {
+        (None, _) => Action::GeneratePassword,
+        (Some("phrase" | "passphrase"), None) => Action::GeneratePassphrase(DEFAULT_PASSPHRASE_WORDS),
+        (Some("phrase" | "passphrase"), Some(n)) => match n.parse::<usize>() {
+            Ok(words) if words > 0 => Action::GeneratePassphrase(words),
+            _ => Action::Invalid(format!("gen phrase {}", n)),
+        },
+        (Some(other), _) => Action::Invalid(format!("gen {}", other)),
+    }
+}
+
+const DEFAULT_UNUSED_DAYS: u32 = 365;
+
+fn parse_unused(args: Option<&str>) -> Action {
+    match args.map(str::trim) {
+        None | Some("") => Action::FilterUnused(DEFAULT_UNUSED_DAYS),
+        Some(days) => match days.parse::<u32>() {
+            Ok(days) => Action::FilterUnused(days),
+            Err(_) => Action::Invalid(format!("unused {} (expected a number of days)", days)),
+        },
+    }
+}
+
+fn parse_fsck(args: Option<&str>) -> Action {
+    match args.map(str::trim) {
+        None | Some("") => Action::Fsck { repair: false },
+        Some("repair") => Action::Fsck { repair: true },
+        Some(other) => Action::Invalid(format!("fsck {}", other)),
+    }
+}
+
+fn parse_privacy(args: Option<&str>) -> Action {
+    match args.map(str::trim) {
+        None | Some("") => Action::Privacy(None),
+        Some("on") => Action::Privacy(Some(true)),
+        Some("off") => Action::Privacy(Some(false)),
+        Some(other) => Action::Invalid(format!("privacy {} (expected on or off)", other)),
+    }
+}
+
+fn parse_set(args: Option<&str>) -> Action {
+    let args = args.unwrap_or("").trim();
+    match args.split_once(char::is_whitespace) {
+        Some(("autolock", value)) => match parse_duration(value.trim()) {
+            Some(timeout) if !timeout.is_zero() => Action::SetAutoLock(timeout),
+            _ => Action::Invalid(format!("set autolock {} (expected a time like 90s, 10m or 1h)", value.trim())),
+        },
+        _ => Action::Invalid(format!("set {} (expected autolock and a time)", args)),
+    }
+}
+
+/// `90s`, `10m` or `1h`; a bare number is minutes
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let (number, unit) = text.split_at(text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len()));
+    let unit_secs = match unit {
+        "s" => 1,
+        "" | "m" => 60,
+        "h" => 60 * 60,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(unit_secs).map(Duration::from_secs)
+}
+
+fn parse_sync(args: Option<&str>, force: bool) -> Action {
+    match args.map(str::trim) {
+        Some("push") => Action::SyncPush { force },
+        Some("pull") => Action::SyncPull { force },
+        Some(other) => Action::Invalid(format!("sync {}", other)),
+        None => Action::Invalid("sync (expected push or pull)".to_string()),
+    }
+}
+
+fn parse_keyslot(args: Option<&str>) -> Action {
+    let mut parts = args.unwrap_or("").split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (None | Some("list"), None, _) => Action::KeySlotList,
+        (Some("add"), Some(label), None) => Action::KeySlotAdd(label.to_string()),
+        (Some("remove" | "rm"), Some(label), None) => Action::KeySlotRemove(label.to_string()),
+        _ => Action::Invalid(format!("keyslot {} (expected add/remove <label> or list)", args.unwrap_or("").trim())),
+    }
+}
+
+fn parse_tag(args: Option<&str>) -> Action {
+    let mut parts = args.unwrap_or("").split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (None, _, _) => Action::ShowTags,
+        (Some("add"), Some(tag), None) => Action::TagAdd(tag.to_string()),
+        (Some("remove" | "rm"), Some(tag), None) => Action::TagRemove(tag.to_string()),
+        (Some(tag), None, _) if !matches!(tag, "add" | "remove" | "rm") => Action::FilterByTag(tag.to_string()),
+        _ => Action::Invalid(format!("tag {} (expected add/rm <tag> or a tag to filter by)", args.unwrap_or("").trim())),
+    }
+}
+
+fn parse_merge(args: Option<&str>) -> Action {
+    match args.map(str::trim) {
+        Some(name) if !name.is_empty() => Action::Merge(name.to_string()),
+        _ => Action::Invalid("merge (expected a credential name)".to_string()),
+    }
+}
+
+fn parse_sort(args: Option<&str>) -> Action {
+    let arg = args.unwrap_or("").trim();
+    match SortOrder::parse(arg) {
+        Some(order) => Action::Sort(order),
+        None => Action::Invalid(format!("sort {} (expected name, updated, created or type)", arg)),
+    }
+}
+
+fn parse_export(args: Option<&str>) -> Action {
+    match args.map(str::trim) {
+        Some(rest) if rest.starts_with("csv ") => parse_csv_export(&rest[4..]),
+        Some(path) if !path.is_empty() => Action::Export(path.to_string()),
+        _ => Action::Invalid("export (expected a file path)".to_string()),
+    }
+}
+
+/// `csv PATH [--fields a,b,...] [--include-secrets]`
+fn parse_csv_export(args: &str) -> Action {
+    let invalid = |why: &str| Action::Invalid(format!("export csv {} ({})", args.trim(), why));
+    let mut path = None;
+    let mut fields = None;
+    let mut include_secrets = false;
+    let mut words = args.split_whitespace();
+    while let Some(word) = words.next() {
+        match word {
+            "--include-secrets" => include_secrets = true,
+            "--fields" => match words.next().map(csv_export::parse_fields) {
+                Some(Ok(list)) => fields = Some(list),
+                Some(Err(e)) => return invalid(&e),
+                None => return invalid("--fields needs a list such as name,username,url"),
+            },
+            _ if path.is_none() && !word.starts_with("--") => path = Some(word.to_string()),
+            _ => return invalid(&format!("unexpected '{}'", word)),
+        }
+    }
+    let Some(path) = path else { return invalid("expected a file path") };
+    let fields = match fields {
+        Some(fields) => fields,
+        None if include_secrets => [&CsvField::DEFAULT[..], &[CsvField::Secret, CsvField::Notes]].concat(),
+        None => CsvField::DEFAULT.to_vec(),
+    };
+    if fields.iter().any(|f| f.is_secret()) && !include_secrets {
+        return invalid("secret and notes need --include-secrets");
+    }
+    Action::ExportCsv { path, fields }
+}
+
+fn parse_vault_move(args: Option<&str>) -> Action {
+    match args.map(str::trim) {
+        Some(path) if !path.is_empty() => Action::VaultMove(path.to_string()),
+        _ => Action::Invalid("vaultmove (expected the new vault path)".to_string()),
+    }
+}
+
+fn parse_inspect(args: Option<&str>) -> Action {
+    Action::Inspect(args.map(str::trim).filter(|path| !path.is_empty()).map(str::to_string))
+}
+
+fn parse_ssh_key(args: Option<&str>) -> Action {
+    match args.map(str::trim) {
+        Some(path) if !path.is_empty() => Action::ImportSshKey(path.to_string()),
+        _ => Action::Invalid("sshkey (expected a private key file path)".to_string()),
+    }
+}
+
+fn parse_alias(args: Option<&str>) -> Action {
+    match args.map(str::trim) {
+        Some(alias) if !alias.is_empty() => Action::Alias(Some(alias.to_string())),
+        _ => Action::Alias(None),
+    }
+}
+
+fn parse_otp_import(args: Option<&str>) -> Action {
+    match args.map(str::trim) {
+        Some(path) if !path.is_empty() => Action::OtpImport(path.to_string()),
+        _ => Action::Invalid("otpimport (expected a backup file path)".to_string()),
+    }
+}
+
+fn parse_otp_export(args: Option<&str>) -> Action {
+    let args = args.unwrap_or("").trim();
+    let (format, path) = args.split_once(' ').unwrap_or((args, ""));
+    match (OtpFormat::parse(format), path.trim()) {
+        (Some(format), path) if !path.is_empty() => Action::OtpExport { format, path: path.to_string() },
+        _ => Action::Invalid(format!("otpexport {} (expected aegis or andotp and a file path)", args)),
+    }
+}
+
+/// Command lines `buffer` can be completed to, given the vault's
+/// credential names and tags
+pub fn complete(buffer: &str, credentials: &[String], tags: &[String]) -> Vec<String> {
+    let Some((name, rest)) = buffer.split_once(' ') else {
+        return COMMANDS
+            .iter()
+            .map(|c| c.names[0])
+            .filter(|n| n.starts_with(buffer))
+            .map(str::to_string)
+            .collect();
+    };
+    let Some(command) = find_command(name) else { return Vec::new() };
+
+    // Credential names may contain spaces, so the whole rest is the prefix
+    if command.args == ArgKind::Credential {
+        return matching(&format!("{} ", name), rest, credentials.iter().map(String::as_str));
+    }
+
+    let (partial, done) = match rest.rsplit_once(' ') {
+        Some((done, partial)) => (partial, done.split_whitespace().collect::<Vec<_>>()),
+        None => (rest, Vec::new()),
+    };
+    let base = &buffer[..buffer.len() - partial.len()];
+    match (command.args, done.as_slice()) {
+        (ArgKind::Words(words), []) => matching(base, partial, words.iter().copied()),
+        (ArgKind::Tag, []) => matching(base, partial, ["add", "rm"].into_iter().chain(tags.iter().map(String::as_str))),
+        (ArgKind::Tag, ["add" | "rm" | "remove"]) => matching(base, partial, tags.iter().map(String::as_str)),
+        _ => Vec::new(),
+    }
+}
+
+fn matching<'a>(base: &str, partial: &str, options: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let partial = partial.to_lowercase();
+    options
+        .filter(|o| o.to_lowercase().starts_with(&partial))
+        .map(|o| format!("{}{}", base, o))
+        .collect()
+}
+
+/// Candidates offered by repeated Tab presses
+#[derive(Debug, Clone)]
+pub struct Completion {
+    candidates: Vec<String>,
+    index: usize,
+}
+
+impl Completion {
+    pub fn new(candidates: Vec<String>) -> Option<Self> {
+        (!candidates.is_empty()).then_some(Self { candidates, index: 0 })
+    }
+
+    pub fn current(&self) -> &str {
+        &self.candidates[self.index]
+    }
+
+    pub fn advance(&mut self) -> &str {
+        self.index = (self.index + 1) % self.candidates.len();
+        self.current()
+    }
+}
+
+/// Previously run commands, recalled with ↑/↓
+#[derive(Debug, Clone, Default)]
+pub struct CommandHistory {
+    entries: Vec<String>,
+    position: Option<usize>,
+    /// What was typed before recalling, restored past the newest entry
+    draft: String,
+}
+
+impl CommandHistory {
+    pub fn push(&mut self, cmd: &str) {
+        self.position = None;
+        let cmd = cmd.trim();
+        if cmd.is_empty() {
+            return;
+        }
+        self.entries.retain(|e| e != cmd);
+        self.entries.push(cmd.to_string());
+        if self.entries.len() > HISTORY_LIMIT {
+            self.entries.remove(0);
+        }
+    }
+
+    /// Stop recalling, as when command mode is entered again
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+
+    pub fn prev(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+            Some(p) => p.saturating_sub(1),
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    pub fn next(&mut self) -> Option<&str> {
+        let position = self.position?;
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            return Some(&self.entries[position + 1]);
+        }
+        self.position = None;
+        Some(&self.draft)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_arguments() {
+        assert_eq!(parse_command("tag"), Action::ShowTags);
+        assert_eq!(parse_command("tag add work"), Action::TagAdd("work".to_string()));
+        assert_eq!(parse_command("tag rm work"), Action::TagRemove("work".to_string()));
+        assert_eq!(parse_command("tag work"), Action::FilterByTag("work".to_string()));
+        assert!(matches!(parse_command("tag add"), Action::Invalid(_)));
+        assert_eq!(parse_command("sort updated"), Action::Sort(SortOrder::Updated));
+        assert!(matches!(parse_command("sort size"), Action::Invalid(_)));
+        assert_eq!(parse_command("export ~/backup.vault"), Action::Export("~/backup.vault".to_string()));
+        assert!(matches!(parse_command("export"), Action::Invalid(_)));
+        assert_eq!(
+            parse_command("export csv ~/inventory.csv --fields name,url"),
+            Action::ExportCsv { path: "~/inventory.csv".into(), fields: vec![CsvField::Name, CsvField::Url] }
+        );
+        assert!(matches!(parse_command("export csv out.csv --fields name,secret"), Action::Invalid(_)));
+        assert!(matches!(
+            parse_command("export csv out.csv --include-secrets"),
+            Action::ExportCsv { fields, .. } if fields.contains(&CsvField::Secret)
+        ));
+        assert_eq!(parse_command("vaultmove ~/sync/vault.db"), Action::VaultMove("~/sync/vault.db".into()));
+        assert_eq!(parse_command("inspect ~/old.bak"), Action::Inspect(Some("~/old.bak".into())));
+        assert_eq!(parse_command("inspect"), Action::Inspect(None));
+        assert_eq!(parse_command("sshkey ~/.ssh/id_ed25519"), Action::ImportSshKey("~/.ssh/id_ed25519".into()));
+        assert!(matches!(parse_command("sshkey"), Action::Invalid(_)));
+        assert_eq!(parse_command("alias gh"), Action::Alias(Some("gh".into())));
+        assert_eq!(parse_command("alias"), Action::Alias(None));
+        assert_eq!(parse_command("otpimport aegis.json"), Action::OtpImport("aegis.json".into()));
+        assert_eq!(
+            parse_command("otpexport andotp ~/otp.json"),
+            Action::OtpExport { format: OtpFormat::AndOtp, path: "~/otp.json".into() }
+        );
+        assert!(matches!(parse_command("otpexport ~/otp.json"), Action::Invalid(_)));
+        assert!(matches!(parse_command("vaultmove"), Action::Invalid(_)));
+        assert_eq!(parse_command("  "), Action::None);
+        assert_eq!(parse_command("bogus"), Action::Invalid("bogus".to_string()));
+    }
+
+    #[test]
+    fn test_names_are_unique() {
+        let mut names: Vec<&str> = COMMANDS.iter().flat_map(|c| c.names.iter().copied()).collect();
+        let count = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+
+    #[test]
+    fn test_complete() {
+        let creds = vec!["GitHub".to_string(), "GitHub Work".to_string(), "Gmail".to_string()];
+        let tags = vec!["work".to_string(), "personal".to_string()];
+
+        assert_eq!(complete("dup", &creds, &tags), ["dupes"]);
+        assert_eq!(complete("sort u", &creds, &tags), ["sort updated"]);
+        assert_eq!(complete("tag ", &creds, &tags), ["tag add", "tag rm", "tag work", "tag personal"]);
+        assert_eq!(complete("tag add p", &creds, &tags), ["tag add personal"]);
+        assert_eq!(complete("merge github", &creds, &tags), ["merge GitHub", "merge GitHub Work"]);
+        assert!(complete("quit now", &creds, &tags).is_empty());
+        assert!(complete("nope ", &creds, &tags).is_empty());
+    }
+
+    #[test]
+    fn test_completion_cycles() {
+        let mut completion = Completion::new(vec!["a".to_string(), "b".to_string()]).unwrap();
+        assert_eq!(completion.current(), "a");
+        assert_eq!(completion.advance(), "b");
+        assert_eq!(completion.advance(), "a");
+        assert!(Completion::new(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_history() {
+        let mut history = CommandHistory::default();
+        assert_eq!(history.prev(""), None);
+
+        history.push("sort updated");
+        history.push("tags");
+        history.push("sort updated");
+        assert_eq!(history.prev("dr"), Some("sort updated"));
+        assert_eq!(history.prev("dr"), Some("tags"));
+        assert_eq!(history.prev("dr"), Some("tags"));
+        assert_eq!(history.next(), Some("sort updated"));
+        assert_eq!(history.next(), Some("dr"));
+        assert_eq!(history.next(), None);
+    }
+}
@@ -0,0 +1,116 @@
+//! Fuzzy Matching
+//!
+//! fzf-style subsequence scoring used by the quick-open finder.
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 12;
+const BONUS_BOUNDARY: i64 = 10;
+const BONUS_FIRST_CHAR: i64 = 8;
+const PENALTY_GAP: i64 = 1;
+const PENALTY_GAP_START: i64 = 3;
+
+/// Score `candidate` against `pattern`. Returns `None` unless every pattern
+/// character appears in order. Matching is case-insensitive unless the
+/// pattern contains an uppercase letter.
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i64> {
+    if pattern.is_empty() {
+        return Some(0);
+    }
+
+    let case_sensitive = pattern.chars().any(char::is_uppercase);
+    let normalize = |c: char| if case_sensitive { c } else { c.to_ascii_lowercase() };
+
+    let pattern: Vec<char> = pattern.chars().map(normalize).collect();
+    let chars: Vec<char> = candidate.chars().collect();
+
+    let mut score = 0;
+    let mut p = 0;
+    let mut last_match: Option<usize> = None;
+
+    for (i, &c) in chars.iter().enumerate() {
+        if p == pattern.len() {
+            break;
+        }
+        if normalize(c) != pattern[p] {
+            continue;
+        }
+
+        score += SCORE_MATCH + position_bonus(&chars, i);
+        score -= gap_penalty(last_match, i);
+        if last_match.is_some_and(|last| last + 1 == i) {
+            score += BONUS_CONSECUTIVE;
+        }
+
+        last_match = Some(i);
+        p += 1;
+    }
+
+    (p == pattern.len()).then_some(score)
+}
+
+/// Best score across several fields of one candidate
+pub fn best_score<'a>(pattern: &str, fields: impl IntoIterator<Item = &'a str>) -> Option<i64> {
+    fields.into_iter().filter_map(|f| fuzzy_score(pattern, f)).max()
+}
+
+fn position_bonus(chars: &[char], i: usize) -> i64 {
+    if i == 0 {
+        return BONUS_FIRST_CHAR + BONUS_BOUNDARY;
+    }
+    let prev = chars[i - 1];
+    let is_boundary = !prev.is_alphanumeric() || (prev.is_lowercase() && chars[i].is_uppercase());
+    if is_boundary { BONUS_BOUNDARY } else { 0 }
+}
+
+fn gap_penalty(last_match: Option<usize>, i: usize) -> i64 {
+    match last_match {
+        Some(last) if i > last + 1 => PENALTY_GAP_START + PENALTY_GAP * (i - last - 2) as i64,
+        Some(_) => 0,
+        None => (i as i64).min(10) * PENALTY_GAP,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subsequence_required() {
+        assert!(fuzzy_score("gh", "GitHub").is_some());
+        assert!(fuzzy_score("hg", "GitHub").is_none());
+        assert!(fuzzy_score("xyz", "GitHub").is_none());
+    }
+
+    #[test]
+    fn test_empty_pattern_matches() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn test_consecutive_beats_scattered() {
+        let tight = fuzzy_score("git", "github").unwrap();
+        let loose = fuzzy_score("git", "gandalf-in-tunnel").unwrap();
+        assert!(tight > loose);
+    }
+
+    #[test]
+    fn test_boundary_bonus() {
+        let boundary = fuzzy_score("ap", "aws-prod").unwrap();
+        let middle = fuzzy_score("ap", "xaxpx").unwrap();
+        assert!(boundary > middle);
+    }
+
+    #[test]
+    fn test_smart_case() {
+        assert!(fuzzy_score("GH", "github").is_none());
+        assert!(fuzzy_score("GH", "GitHub").is_some());
+        assert!(fuzzy_score("gh", "GITHUB").is_some());
+    }
+
+    #[test]
+    fn test_best_score() {
+        let score = best_score("alice", ["GitHub", "alice@example.com"]);
+        assert_eq!(score, fuzzy_score("alice", "alice@example.com"));
+        assert!(best_score("zzz", ["GitHub", "alice"]).is_none());
+    }
+}
@@ -2,8 +2,16 @@
 //!
 //! Vim-style key bindings mapped to actions.
 
+use std::time::Duration;
+
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
+use crate::db::SortOrder;
+use crate::vault::csv_export::CsvField;
+use crate::vault::otp_backup::OtpFormat;
+
+pub use super::commands::parse_command;
+
 /// Actions that can be triggered by key presses
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Action {
@@ -30,23 +38,105 @@ pub enum Action {
     CopyPassword,
     CopyUsername,
     CopyTotp,
+    /// Copy the public or private half of an SSH key
+    CopyPublicKey,
+    CopyPrivateKey,
+    /// Pick a URL scheme and copy a database credential as a connection string
+    CopyConnectionString,
+    /// Copy the detail field with this number, counted from 1
+    CopyField(usize),
+
+    /// Open the selected credential's URL in the browser
+    OpenUrl,
 
     // View
     TogglePasswordVisibility,
+    /// Show only the last characters of the secret
+    TogglePartialReveal,
+
+    // Panes
+    ZoomPane,
+    CyclePaneFocus,
+    GrowPane,
+    ShrinkPane,
+    ResetPanes,
     
     // Mode changes
     EnterCommand,
     EnterSearch,
+    /// Jump to the next or previous credential matching the search
+    NextMatch,
+    PrevMatch,
+    /// Repeat the last change made in this view
+    RepeatChange,
     ShowHelp,
     ShowTags,
+    ShowTimeline,
+    ShowFinder,
+    /// Searchable list of every command and key binding
+    ShowPalette,
+    ShowBackups,
+    ShowDupes,
+    /// Look up every password in the offline breach index
+    CheckBreaches,
+    /// Which process hardening measures are in effect
+    ShowSecurityInfo,
+    /// Advice on clipboard managers and other risks on this system
+    ShowTips,
+    /// Which format and algorithm each credential is encrypted with
+    ShowCryptoInfo,
+    /// Vault file, fingerprint and size
+    ShowVaultInfo,
+    /// Merge the named credential into the selected one
+    Merge(String),
+    /// Add a tag to the selected credential
+    TagAdd(String),
+    TagRemove(String),
+    Sort(SortOrder),
+    /// Write an encrypted copy of the vault to this path
+    Export(String),
+    /// Write these fields of every credential to this path as CSV
+    ExportCsv { path: String, fields: Vec<CsvField> },
+    /// Move the vault file to this path and keep working from there
+    VaultMove(String),
+    /// Browse this backup read-only in place of the vault, or go back
+    Inspect(Option<String>),
+    /// Load the selected SSH key credential's secret from this key file
+    ImportSshKey(String),
+    /// Give the selected credential this alias, or a memorable one
+    Alias(Option<String>),
+    Unalias,
+    /// Add the TOTP accounts in an Aegis or andOTP backup
+    OtpImport(String),
+    /// Write the TOTP credentials to this path as an Aegis or andOTP backup
+    OtpExport { format: OtpFormat, path: String },
+    /// Enter a new secret for the selected credential, archiving the old one
+    Rotate,
 
     // Commands
     ExecuteCommand(String),
     Search(String),
     FilterByTag(String),
+    /// Credentials not viewed or copied for this many days
+    FilterUnused(u32),
+    ClearFilters,
     GeneratePassword,
+    /// Diceware passphrase with the given word count
+    GeneratePassphrase(usize),
     ChangePassword,
+    /// Add a key slot with this label, prompting for its password
+    KeySlotAdd(String),
+    KeySlotRemove(String),
+    KeySlotList,
     VerifyAudit,
+    /// Full integrity check, rebuilding the search index when set
+    Fsck { repair: bool },
+    /// Erase deleted data and shrink the vault file
+    Compact,
+    /// Turn privacy mode on or off, or toggle it
+    Privacy(Option<bool>),
+    SyncPush { force: bool },
+    SyncPull { force: bool },
     ShowLogs,
     
     // Confirmation
@@ -59,6 +149,8 @@ pub enum Action {
     ForceQuit,
     Refresh,
     Lock,
+    /// `:set autolock`: change the idle timeout and save it
+    SetAutoLock(Duration),
 
     // Text input
     InsertChar(char),
@@ -70,80 +162,241 @@ pub enum Action {
     CursorEnd,
     ClearLine,
     Submit,
+    HistoryPrev,
+    HistoryNext,
+    Complete,
 
     // No action
     None,
     Invalid(String),
 }
 
-/// Pending key state for multi-key sequences
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum PendingKey {
-    G,  // waiting for second 'g' for gg
-    D,  // waiting for second 'd' for dd
-    Y,  // waiting for second key for yank
+impl Action {
+    /// Whether the action changes the vault and so is refused in read-only mode
+    pub fn is_mutating(&self) -> bool {
+        matches!(
+            self,
+            Self::New
+                | Self::Edit
+                | Self::Delete
+                | Self::ChangePassword
+                | Self::KeySlotAdd(_)
+                | Self::KeySlotRemove(_)
+                | Self::Merge(_)
+                | Self::TagAdd(_)
+                | Self::TagRemove(_)
+                | Self::Fsck { repair: true }
+                | Self::Compact
+                | Self::VaultMove(_)
+                | Self::ImportSshKey(_)
+                | Self::OtpImport(_)
+                | Self::Alias(_)
+                | Self::Unalias
+                | Self::Rotate
+                | Self::SyncPush { .. }
+                | Self::SyncPull { .. }
+        )
+    }
+
+    /// Changes to credentials, which `.` repeats
+    pub fn is_change(&self) -> bool {
+        matches!(
+            self,
+            Self::New | Self::Edit | Self::Delete | Self::Merge(_) | Self::TagAdd(_) | Self::TagRemove(_)
+        )
+    }
+}
+
+/// Largest count accepted before a normal-mode key
+pub const MAX_COUNT: usize = 9999;
+
+/// A normal-mode key sequence in progress: a count like the `5` of `5j`
+/// and an operator key waiting for its second key, like the first `d` of
+/// `dd` or `Ctrl+w` before a pane command
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pending {
+    pub count: Option<usize>,
+    pub operator: Option<char>,
+}
+
+impl Pending {
+    /// Feed the next key of the sequence. Returns the action and its count
+    /// once the sequence is complete, and `Action::None` while it is not.
+    pub fn feed(&mut self, key: KeyEvent) -> (Action, Option<usize>) {
+        if let (KeyCode::Char(c @ '0'..='9'), KeyModifiers::NONE, None) = (key.code, key.modifiers, self.operator)
+            && (c != '0' || self.count.is_some())
+        {
+            let digit = c.to_digit(10).unwrap_or_default() as usize;
+            self.count = Some((self.count.unwrap_or(0) * 10 + digit).min(MAX_COUNT));
+            return (Action::None, None);
+        }
+        // With a count `y` copies that detail field instead of waiting for `yy`
+        if let (KeyCode::Char('y'), KeyModifiers::NONE, None, Some(count)) = (key.code, key.modifiers, self.operator, self.count) {
+            self.count = None;
+            return (Action::CopyField(count), None);
+        }
+
+        let (action, operator) = normal_mode_action(key, self.operator);
+        self.operator = operator;
+        if operator.is_some() {
+            return (Action::None, None);
+        }
+        (action, self.count.take())
+    }
+}
+
+/// A key press, optionally after an operator key like the first `g` of `gg`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    pub operator: Option<char>,
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+const fn ch(c: char) -> Chord {
+    Chord { operator: None, code: KeyCode::Char(c), modifiers: KeyModifiers::NONE }
+}
+
+const fn ctrl(c: char) -> Chord {
+    Chord { operator: None, code: KeyCode::Char(c), modifiers: KeyModifiers::CONTROL }
+}
+
+const fn special(code: KeyCode) -> Chord {
+    Chord { operator: None, code, modifiers: KeyModifiers::NONE }
 }
 
-/// Map key event to action in normal mode
+const fn then(operator: char, chord: Chord) -> Chord {
+    Chord { operator: Some(operator), ..chord }
+}
+
+impl Chord {
+    /// How help and hints write the chord, e.g. `gg`, `Ctrl+d` or `Ctrl+w z`
+    pub fn label(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(c) if self.modifiers.contains(KeyModifiers::CONTROL) => format!("Ctrl+{}", c),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            KeyCode::PageDown => "PgDn".to_string(),
+            KeyCode::PageUp => "PgUp".to_string(),
+            code => code.to_string(),
+        };
+        let prefix = self.operator.and_then(|op| OPERATORS.iter().find(|(_, o)| *o == op)).map(|(c, _)| c.label());
+        match prefix {
+            Some(prefix) if prefix.chars().count() > 1 => format!("{} {}", prefix, key),
+            Some(prefix) => format!("{}{}", prefix, key),
+            None => key,
+        }
+    }
+}
+
+/// A normal-mode key binding. Help lists these, so a new binding shows up
+/// there by being added here.
+pub struct Binding {
+    pub section: &'static str,
+    pub chords: &'static [Chord],
+    pub action: Action,
+    /// Help text; aliases of a listed binding have none and are not listed
+    pub help: Option<&'static str>,
+}
+
+const fn bind(section: &'static str, chords: &'static [Chord], action: Action, help: &'static str) -> Binding {
+    Binding { section, chords, action, help: Some(help) }
+}
+
+const fn alias(chords: &'static [Chord], action: Action) -> Binding {
+    Binding { section: "", chords, action, help: None }
+}
+
+impl Binding {
+    pub fn keys_label(&self) -> String {
+        self.chords.iter().map(Chord::label).collect::<Vec<_>>().join(" / ")
+    }
+}
+
+/// Keys that wait for a second key, and the operator they start
+const OPERATORS: &[(Chord, char)] = &[
+    (ch('g'), 'g'),
+    (ch('d'), 'd'),
+    (ch('y'), 'y'),
+    (ch('c'), 'y'),
+    (ctrl('w'), 'w'),
+];
+
+/// Normal-mode bindings, looked up in order
+pub static NORMAL_BINDINGS: &[Binding] = &[
+    bind("Navigation", &[ch('j'), special(KeyCode::Down)], Action::MoveDown, "Move down (scrolls the details when focused)"),
+    bind("Navigation", &[ch('k'), special(KeyCode::Up)], Action::MoveUp, "Move up (scrolls the details when focused)"),
+    bind("Navigation", &[then('g', ch('g'))], Action::MoveToTop, "Go to top (5gg: row 5)"),
+    bind("Navigation", &[ch('G')], Action::MoveToBottom, "Go to bottom (5G: row 5)"),
+    bind("Navigation", &[ctrl('d')], Action::HalfPageDown, "Half page down"),
+    bind("Navigation", &[ctrl('u')], Action::HalfPageUp, "Half page up"),
+    bind("Navigation", &[ctrl('f'), special(KeyCode::PageDown)], Action::PageDown, "Page down"),
+    bind("Navigation", &[ctrl('b'), special(KeyCode::PageUp)], Action::PageUp, "Page up"),
+    bind("Actions", &[special(KeyCode::Enter), ch('l'), special(KeyCode::Right)], Action::Select, "View details"),
+    bind("Actions", &[special(KeyCode::Esc), ch('h'), special(KeyCode::Left)], Action::Back, "Back to the list"),
+    bind("Actions", &[ch('n')], Action::New, "New credential"),
+    bind("Actions", &[ch('e')], Action::Edit, "Edit credential"),
+    bind("Actions", &[then('d', ch('d')), ch('x')], Action::Delete, "Delete credential"),
+    bind("Actions", &[ch('.')], Action::RepeatChange, "Repeat last change"),
+    bind("Clipboard", &[then('y', ch('y'))], Action::CopyPassword, "Copy password/secret (2y: field 2)"),
+    alias(&[then('y', ch('c'))], Action::CopyPassword),
+    bind("Clipboard", &[ch('u')], Action::CopyUsername, "Copy username"),
+    bind("Clipboard", &[ch('T')], Action::CopyTotp, "Copy TOTP code"),
+    bind("Clipboard", &[then('y', ch('p'))], Action::CopyPublicKey, "Copy SSH public key"),
+    bind("Clipboard", &[then('y', ch('k'))], Action::CopyPrivateKey, "Copy SSH private key"),
+    bind("Clipboard", &[then('y', ch('d'))], Action::CopyConnectionString, "Copy as connection string"),
+    bind("Clipboard", &[ch('o')], Action::OpenUrl, "Open URL in browser"),
+    bind("View", &[ctrl('s')], Action::TogglePasswordVisibility, "Toggle password"),
+    bind("View", &[ch('P')], Action::TogglePartialReveal, "Show last 4 characters of the secret"),
+    bind("View", &[ch('/')], Action::EnterSearch, "Search (tag: type: user: updated:)"),
+    // `n` is New above; while a search is active the app reads it as this
+    bind("View", &[ch('n')], Action::NextMatch, "Next search match"),
+    bind("View", &[ch('N')], Action::PrevMatch, "Previous search match"),
+    bind("View", &[special(KeyCode::Backspace)], Action::ClearFilters, "Clear search filters"),
+    bind("View", &[ch('i')], Action::ShowLogs, "Show logs"),
+    bind("View", &[ch('t')], Action::ShowTags, "Show tags"),
+    bind("View", &[ch('H')], Action::ShowTimeline, "Credential timeline"),
+    bind("View", &[ctrl('k')], Action::ShowFinder, "Fuzzy finder"),
+    // Ctrl+Shift+P only reaches us as such where the terminal reports Shift
+    bind("View", &[ctrl('P'), ctrl('o')], Action::ShowPalette, "Command palette"),
+    bind("Panes", &[then('w', ch('z'))], Action::ZoomPane, "Zoom focused pane"),
+    bind("Panes", &[then('w', ch('w'))], Action::CyclePaneFocus, "Switch pane focus"),
+    alias(&[then('w', ctrl('w'))], Action::CyclePaneFocus),
+    bind("Panes", &[then('w', ch('>'))], Action::GrowPane, "Grow pane"),
+    alias(&[then('w', ch('+'))], Action::GrowPane),
+    bind("Panes", &[then('w', ch('<'))], Action::ShrinkPane, "Shrink pane"),
+    alias(&[then('w', ch('-'))], Action::ShrinkPane),
+    bind("Panes", &[then('w', ch('='))], Action::ResetPanes, "Reset pane sizes"),
+    bind("Commands", &[ch(':')], Action::EnterCommand, "Command mode"),
+    bind("Other", &[ch('?')], Action::ShowHelp, "Show this help"),
+    bind("Other", &[ctrl('l')], Action::Clear, "Clear message"),
+    bind("Other", &[ctrl('r')], Action::Refresh, "Reload credentials"),
+    bind("Other", &[ctrl('p')], Action::ChangePassword, "Change master key"),
+    bind("Other", &[ch('L')], Action::Lock, "Lock vault (Ctrl+l while typing)"),
+    bind("Other", &[ch('q')], Action::Quit, "Quit"),
+    bind("Other", &[ch('Q')], Action::ForceQuit, "Quit, skipping the confirmation"),
+];
+
+/// Map key event to action in normal mode. Shift is implied by the
+/// character, and keys other than characters ignore modifiers.
 pub fn normal_mode_action(key: KeyEvent, pending: Option<char>) -> (Action, Option<char>) {
-    match (key.code, key.modifiers, pending) {
-        // Navigation
-        (KeyCode::Char('j'), KeyModifiers::NONE, _) => (Action::MoveDown, None),
-        (KeyCode::Down, _, _) => (Action::MoveDown, None),
-        (KeyCode::Char('k'), KeyModifiers::NONE, _) => (Action::MoveUp, None),
-        (KeyCode::Up, _, _) => (Action::MoveUp, None),
-        (KeyCode::Char('g'), KeyModifiers::NONE, None) => (Action::None, Some('g')),
-        (KeyCode::Char('g'), KeyModifiers::NONE, Some('g')) => (Action::MoveToTop, None),
-        (KeyCode::Char('G'), KeyModifiers::SHIFT, _) => (Action::MoveToBottom, None),
-        (KeyCode::Char('d'), KeyModifiers::CONTROL, _) => (Action::HalfPageDown, None),
-        (KeyCode::Char('u'), KeyModifiers::CONTROL, _) => (Action::HalfPageUp, None),
-        (KeyCode::Char('f'), KeyModifiers::CONTROL, _) => (Action::PageDown, None),
-        (KeyCode::Char('b'), KeyModifiers::CONTROL, _) => (Action::PageUp, None),
-        (KeyCode::PageDown, _, _) => (Action::PageDown, None),
-        (KeyCode::PageUp, _, _) => (Action::PageUp, None),
-
-        // Selection
-        (KeyCode::Char('l'), KeyModifiers::CONTROL, _) => (Action::Clear, None),
-        (KeyCode::Enter, _, _) => (Action::Select, None),
-        (KeyCode::Char('l'), KeyModifiers::NONE, _) => (Action::Select, None),
-        (KeyCode::Right, _, _) => (Action::Select, None),
-        (KeyCode::Esc, _, _) => (Action::Back, None),
-        (KeyCode::Char('h'), KeyModifiers::NONE, _) => (Action::Back, None),
-        (KeyCode::Left, _, _) => (Action::Back, None),
-
-        // CRUD
-        (KeyCode::Char('n'), KeyModifiers::NONE, _) => (Action::New, None),
-        (KeyCode::Char('e'), KeyModifiers::NONE, _) => (Action::Edit, None),
-        (KeyCode::Char('d'), KeyModifiers::NONE, None) => (Action::None, Some('d')),
-        (KeyCode::Char('d'), KeyModifiers::NONE, Some('d')) => (Action::Delete, None),
-        (KeyCode::Char('x'), KeyModifiers::NONE, _) => (Action::Delete, None),
-
-        // Clipboard
-        (KeyCode::Char('c'), KeyModifiers::NONE, None) => (Action::None, Some('y')),
-        (KeyCode::Char('y'), KeyModifiers::NONE, None) => (Action::None, Some('y')),
-        (KeyCode::Char('y'), KeyModifiers::NONE, Some('y')) => (Action::CopyPassword, None),
-        (KeyCode::Char('c'), KeyModifiers::NONE, Some('y')) => (Action::CopyPassword, None),
-        (KeyCode::Char('u'), KeyModifiers::NONE, None) => (Action::CopyUsername, None),
-        (KeyCode::Char('T'), KeyModifiers::SHIFT, _) => (Action::CopyTotp, None),
-
-        // View
-        (KeyCode::Char('s'), KeyModifiers::CONTROL, _) => (Action::TogglePasswordVisibility, None),
-
-        // Mode changes
-        (KeyCode::Char(':'), KeyModifiers::NONE | KeyModifiers::SHIFT, _) => (Action::EnterCommand, None),
-        (KeyCode::Char('/'), KeyModifiers::NONE, _) => (Action::EnterSearch, None),
-        (KeyCode::Char('?'), KeyModifiers::NONE | KeyModifiers::SHIFT, _) => (Action::ShowHelp, None),
-        (KeyCode::Char('t'), KeyModifiers::NONE, _) => (Action::ShowTags, None),
-
-        // Application
-        (KeyCode::Char('q'), KeyModifiers::NONE, _) => (Action::Quit, None),
-        (KeyCode::Char('Q'), KeyModifiers::SHIFT, _) => (Action::ForceQuit, None),
-        (KeyCode::Char('r'), KeyModifiers::CONTROL, _) => (Action::Refresh, None),
-        (KeyCode::Char('p'), KeyModifiers::CONTROL, _) => (Action::ChangePassword, None),
-        (KeyCode::Char('i'), KeyModifiers::NONE, _) => (Action::ShowLogs, None),
-        (KeyCode::Char('L'), KeyModifiers::SHIFT, _) => (Action::Lock, None),
-
-        _ => (Action::None, None),
+    let (code, modifiers) = match key.code {
+        KeyCode::Char(_) => (key.code, key.modifiers.difference(KeyModifiers::SHIFT)),
+        code => (code, KeyModifiers::NONE),
+    };
+    let chord = Chord { operator: pending, code, modifiers };
+
+    if pending.is_none()
+        && let Some((_, operator)) = OPERATORS.iter().find(|(c, _)| *c == chord)
+    {
+        return (Action::None, Some(*operator));
+    }
+    match NORMAL_BINDINGS.iter().find(|b| b.chords.contains(&chord)) {
+        Some(binding) => (binding.action.clone(), None),
+        None => (Action::None, None),
     }
 }
 
@@ -159,6 +412,9 @@ pub fn text_input_action(key: KeyEvent) -> Action {
         (KeyCode::Home, _) | (KeyCode::Char('a'), KeyModifiers::CONTROL) => Action::CursorHome,
         (KeyCode::End, _) | (KeyCode::Char('e'), KeyModifiers::CONTROL) => Action::CursorEnd,
         (KeyCode::Char('u'), KeyModifiers::CONTROL) => Action::ClearLine,
+        (KeyCode::Up, _) => Action::HistoryPrev,
+        (KeyCode::Down, _) => Action::HistoryNext,
+        (KeyCode::Tab, _) => Action::Complete,
         (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => Action::InsertChar(c),
         _ => Action::None,
     }
@@ -183,35 +439,6 @@ pub fn help_action(key: KeyEvent) -> Action {
     }
 }
 
-/// Parse command string into action
-pub fn parse_command(cmd: &str) -> Action {
-    let cmd = cmd.trim();
-    let parts: Vec<&str> = cmd.splitn(2, ' ').collect();
-    let command = parts[0];
-    let args = parts.get(1).copied();
-
-    match command {
-        "cls" | "clear" => Action::Clear,
-        "q" | "quit" => Action::Quit,
-        "q!" | "quit!" => Action::ForceQuit,
-        "w" | "write" => Action::None, // Auto-save, no action needed
-        "wq" => Action::Quit,
-        "new" | "n" => Action::New,
-        "edit" | "e" => Action::Edit,
-        "delete" | "del" => Action::Delete,
-        "gen" | "generate" => Action::GeneratePassword,
-        "help" | "h" => Action::ShowHelp,
-        "passwd" | "password" | "changepw" => Action::ChangePassword,
-        "lock" => Action::Lock,
-        "refresh" => Action::Refresh,
-        "logs" | "log" => Action::ShowLogs,
-        "audit" | "verify" => Action::VerifyAudit,
-        "tags" | "tag" => Action::ShowTags,
-        "" => Action::None,
-        other => Action::Invalid(other.to_string()),
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -253,6 +480,72 @@ mod tests {
         assert_eq!(pending2, None);
     }
 
+    #[test]
+    fn test_pane_sequences() {
+        let (action, pending) = normal_mode_action(key_ctrl(KeyCode::Char('w')), None);
+        assert_eq!(action, Action::None);
+        assert_eq!(pending, Some('w'));
+
+        assert_eq!(normal_mode_action(key(KeyCode::Char('z')), pending), (Action::ZoomPane, None));
+        assert_eq!(normal_mode_action(key_ctrl(KeyCode::Char('w')), pending), (Action::CyclePaneFocus, None));
+        assert_eq!(normal_mode_action(key(KeyCode::Char('>')), pending), (Action::GrowPane, None));
+        assert_eq!(normal_mode_action(key(KeyCode::Char('<')), pending), (Action::ShrinkPane, None));
+        assert_eq!(normal_mode_action(key(KeyCode::Char('j')), pending), (Action::None, None));
+    }
+
+    #[test]
+    fn test_bindings_resolve() {
+        for binding in NORMAL_BINDINGS {
+            for chord in binding.chords {
+                let pending = chord.operator.map(|op| {
+                    let (starter, _) = OPERATORS.iter().find(|(_, o)| *o == op).unwrap();
+                    normal_mode_action(KeyEvent::new(starter.code, starter.modifiers), None).1
+                });
+                let (action, _) = normal_mode_action(KeyEvent::new(chord.code, chord.modifiers), pending.flatten());
+                // `n` only means NextMatch to the app, during a search
+                if binding.action != Action::NextMatch {
+                    assert_eq!(action, binding.action, "{}", chord.label());
+                }
+            }
+        }
+
+        let labels: Vec<String> = [then('g', ch('g')), then('w', ch('z')), ctrl('d'), special(KeyCode::Enter)]
+            .iter()
+            .map(Chord::label)
+            .collect();
+        assert_eq!(labels, ["gg", "Ctrl+w z", "Ctrl+d", "Enter"]);
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut pending = Pending::default();
+        assert_eq!(pending.feed(key(KeyCode::Char('1'))), (Action::None, None));
+        assert_eq!(pending.feed(key(KeyCode::Char('0'))), (Action::None, None));
+        assert_eq!(pending.count, Some(10));
+        assert_eq!(pending.feed(key(KeyCode::Char('j'))), (Action::MoveDown, Some(10)));
+        assert_eq!(pending, Pending::default());
+
+        // A count carries across an operator, and a lone 0 is not a count
+        for c in ['3', 'd'] {
+            assert_eq!(pending.feed(key(KeyCode::Char(c))), (Action::None, None));
+        }
+        assert_eq!(pending.feed(key(KeyCode::Char('d'))), (Action::Delete, Some(3)));
+        assert_eq!(pending.feed(key(KeyCode::Char('0'))), (Action::None, None));
+        assert_eq!(pending, Pending::default());
+
+        for _ in 0..6 {
+            pending.feed(key(KeyCode::Char('9')));
+        }
+        assert_eq!(pending.count, Some(MAX_COUNT));
+        assert_eq!(pending.feed(key(KeyCode::Char('.'))), (Action::RepeatChange, Some(MAX_COUNT)));
+
+        // `2y` copies a field at once, while a bare `y` still waits for `yy`
+        pending.feed(key(KeyCode::Char('2')));
+        assert_eq!(pending.feed(key(KeyCode::Char('y'))), (Action::CopyField(2), None));
+        assert_eq!(pending.feed(key(KeyCode::Char('y'))), (Action::None, None));
+        assert_eq!(pending.feed(key(KeyCode::Char('y'))), (Action::CopyPassword, None));
+    }
+
     #[test]
     fn test_text_input() {
         assert_eq!(text_input_action(key(KeyCode::Char('a'))), Action::InsertChar('a'));
@@ -276,6 +569,86 @@ mod tests {
         assert_eq!(parse_command("new"), Action::New);
         assert_eq!(parse_command("help"), Action::ShowHelp);
         assert_eq!(parse_command("tags"), Action::ShowTags);
+        assert_eq!(parse_command("history"), Action::ShowTimeline);
+        assert_eq!(parse_command("open"), Action::OpenUrl);
+        assert_eq!(parse_command("dupes"), Action::ShowDupes);
+        assert_eq!(parse_command("breaches"), Action::CheckBreaches);
+        assert_eq!(parse_command("securityinfo"), Action::ShowSecurityInfo);
+        assert_eq!(parse_command("tips"), Action::ShowTips);
+        assert_eq!(parse_command("palette"), Action::ShowPalette);
+        assert_eq!(parse_command("cryptoinfo"), Action::ShowCryptoInfo);
+        assert_eq!(parse_command("info"), Action::ShowVaultInfo);
+        assert_eq!(parse_command("set autolock 10m"), Action::SetAutoLock(Duration::from_secs(600)));
+        assert_eq!(parse_command("set autolock 90s"), Action::SetAutoLock(Duration::from_secs(90)));
+        assert_eq!(parse_command("set autolock 2"), Action::SetAutoLock(Duration::from_secs(120)));
+        assert!(matches!(parse_command("set autolock 0"), Action::Invalid(_)));
+        assert!(matches!(parse_command("set autolock soon"), Action::Invalid(_)));
+        assert!(matches!(parse_command("set colour red"), Action::Invalid(_)));
+        assert_eq!(parse_command("unused"), Action::FilterUnused(365));
+        assert_eq!(parse_command("unused 90"), Action::FilterUnused(90));
+        assert!(matches!(parse_command("unused soon"), Action::Invalid(_)));
+        assert_eq!(parse_command("merge GitHub Work"), Action::Merge("GitHub Work".to_string()));
+        assert!(matches!(parse_command("merge"), Action::Invalid(_)));
+        assert!(parse_command("merge GitHub").is_mutating());
+        assert_eq!(normal_mode_action(key(KeyCode::Char('o')), None).0, Action::OpenUrl);
+    }
+
+    #[test]
+    fn test_parse_gen_command() {
+        assert_eq!(parse_command("gen"), Action::GeneratePassword);
+        assert_eq!(parse_command("gen phrase"), Action::GeneratePassphrase(6));
+        assert_eq!(parse_command("gen phrase 5"), Action::GeneratePassphrase(5));
+        assert!(matches!(parse_command("gen phrase x"), Action::Invalid(_)));
+        assert!(matches!(parse_command("gen bogus"), Action::Invalid(_)));
+    }
+
+    #[test]
+    fn test_parse_fsck_command() {
+        assert_eq!(parse_command("fsck"), Action::Fsck { repair: false });
+        assert_eq!(parse_command("check"), Action::Fsck { repair: false });
+        assert_eq!(parse_command("fsck repair"), Action::Fsck { repair: true });
+        assert!(matches!(parse_command("fsck now"), Action::Invalid(_)));
+        assert_eq!(parse_command("compact"), Action::Compact);
+        assert!(parse_command("vacuum").is_mutating());
+        assert!(parse_command("rotate").is_mutating());
+    }
+
+    #[test]
+    fn test_parse_privacy_command() {
+        assert_eq!(parse_command("privacy"), Action::Privacy(None));
+        assert_eq!(parse_command("privacy on"), Action::Privacy(Some(true)));
+        assert_eq!(parse_command("privacy off"), Action::Privacy(Some(false)));
+        assert!(matches!(parse_command("privacy maybe"), Action::Invalid(_)));
+        assert!(!parse_command("privacy on").is_mutating());
+    }
+
+    #[test]
+    fn test_parse_sync_command() {
+        assert_eq!(parse_command("sync push"), Action::SyncPush { force: false });
+        assert_eq!(parse_command("sync pull"), Action::SyncPull { force: false });
+        assert_eq!(parse_command("sync! push"), Action::SyncPush { force: true });
+        assert!(matches!(parse_command("sync"), Action::Invalid(_)));
+        assert!(matches!(parse_command("sync both"), Action::Invalid(_)));
+    }
+
+    #[test]
+    fn test_parse_keyslot_command() {
+        assert_eq!(parse_command("keyslot"), Action::KeySlotList);
+        assert_eq!(parse_command("keyslot list"), Action::KeySlotList);
+        assert_eq!(parse_command("keyslot add alice"), Action::KeySlotAdd("alice".to_string()));
+        assert_eq!(parse_command("keyslot rm alice"), Action::KeySlotRemove("alice".to_string()));
+        assert!(matches!(parse_command("keyslot add"), Action::Invalid(_)));
+        assert!(matches!(parse_command("keyslot add two words"), Action::Invalid(_)));
+    }
+
+    #[test]
+    fn test_mutating_actions() {
+        assert!(Action::Delete.is_mutating());
+        assert!(parse_command("fsck repair").is_mutating());
+        assert!(!parse_command("fsck").is_mutating());
+        assert!(parse_command("keyslot add alice").is_mutating());
+        assert!(!parse_command("keyslot list").is_mutating());
+        assert!(!Action::CopyPassword.is_mutating());
     }
 
     #[test]
@@ -0,0 +1,214 @@
+//! Editable Line
+//!
+//! The text and cursor behind every single-line input: the command line,
+//! search, the form fields, the fuzzy finder and the password prompts.
+//! The cursor moves and deletes by grapheme, so an emoji or an accented
+//! letter typed as two code points goes in and out as one character.
+
+use std::ops::Deref;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+use zeroize::{Zeroize, Zeroizing};
+
+/// A line of text being typed, wiped when dropped or replaced since it may
+/// be a secret
+#[derive(Debug, Clone, Default)]
+pub struct EditLine {
+    text: Zeroizing<String>,
+    /// Byte offset of the cursor, always on a grapheme boundary
+    cursor: usize,
+}
+
+impl EditLine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text, leaving the cursor at the end
+    pub fn set(&mut self, text: &str) {
+        self.text.zeroize();
+        self.reserve(text.len());
+        self.text.push_str(text);
+        self.cursor = self.text.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.text.zeroize();
+        self.cursor = 0;
+    }
+
+    /// Insert at the cursor. A full buffer is moved to a larger one here
+    /// rather than by `String`, so the old one is zeroized instead of freed
+    /// with the text still in it.
+    pub fn insert(&mut self, c: char) {
+        self.reserve(c.len_utf8());
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+        // A joiner can merge with the grapheme after it
+        if !self.is_boundary(self.cursor) {
+            self.cursor = self.next_boundary();
+        }
+    }
+
+    /// Delete the grapheme before the cursor (backspace)
+    pub fn backspace(&mut self) -> bool {
+        if self.cursor == 0 {
+            return false;
+        }
+        let start = self.prev_boundary();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+        true
+    }
+
+    /// Delete the grapheme under the cursor (delete key)
+    pub fn delete(&mut self) -> bool {
+        if self.cursor == self.text.len() {
+            return false;
+        }
+        let end = self.next_boundary();
+        self.text.replace_range(self.cursor..end, "");
+        true
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.prev_boundary();
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = self.next_boundary();
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /// Graphemes before the cursor, which is where it sits when masked
+    pub fn cursor_index(&self) -> usize {
+        self.text[..self.cursor].graphemes(true).count()
+    }
+
+    /// Terminal columns before the cursor; masked text is one column per
+    /// grapheme
+    pub fn cursor_column(&self, masked: bool) -> usize {
+        match masked {
+            true => self.cursor_index(),
+            false => self.text[..self.cursor].width(),
+        }
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        if self.text.len() + additional <= self.text.capacity() {
+            return;
+        }
+        let capacity = (self.text.len() + additional).max(self.text.capacity() * 2).max(32);
+        let mut grown = Zeroizing::new(String::with_capacity(capacity));
+        grown.push_str(&self.text);
+        self.text = grown;
+    }
+
+    fn is_boundary(&self, at: usize) -> bool {
+        at == self.text.len() || self.text.grapheme_indices(true).any(|(i, _)| i == at)
+    }
+
+    fn prev_boundary(&self) -> usize {
+        self.text[..self.cursor].grapheme_indices(true).next_back().map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        let start = self.text[..self.cursor].grapheme_indices(true).next_back().map_or(0, |(i, _)| i);
+        self.text[start..]
+            .grapheme_indices(true)
+            .map(|(i, g)| start + i + g.len())
+            .find(|&end| end > self.cursor)
+            .unwrap_or(self.text.len())
+    }
+}
+
+impl Deref for EditLine {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl From<Zeroizing<String>> for EditLine {
+    fn from(text: Zeroizing<String>) -> Self {
+        let cursor = text.len();
+        Self { text, cursor }
+    }
+}
+
+impl From<String> for EditLine {
+    fn from(text: String) -> Self {
+        Self::from(Zeroizing::new(text))
+    }
+}
+
+impl From<&str> for EditLine {
+    fn from(text: &str) -> Self {
+        Self::from(text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> EditLine {
+        let mut line = EditLine::new();
+        text.chars().for_each(|c| line.insert(c));
+        line
+    }
+
+    #[test]
+    fn test_graphemes_edit_as_one() {
+        // "e" with a combining acute, a flag, and a family joined with ZWJ
+        let mut line = typed("ae\u{301}🇫🇷👨\u{200d}👩\u{200d}👧z");
+        assert_eq!(line.cursor_index(), 5);
+
+        line.backspace();
+        line.backspace();
+        assert_eq!(line.as_str(), "ae\u{301}🇫🇷");
+        line.left();
+        line.left();
+        assert_eq!(line.cursor_index(), 1);
+        line.delete();
+        assert_eq!(line.as_str(), "a🇫🇷");
+        line.insert('語');
+        assert_eq!(line.as_str(), "a語🇫🇷");
+        line.end();
+        line.right();
+        assert_eq!(line.cursor_index(), 3);
+    }
+
+    #[test]
+    fn test_columns() {
+        let mut line = typed("a語🔑b");
+        assert_eq!(line.cursor_column(false), 6);
+        assert_eq!(line.cursor_column(true), 4);
+        line.left();
+        assert_eq!(line.cursor_column(false), 5);
+        assert_eq!(line.cursor_column(true), 3);
+        line.home();
+        assert!(!line.backspace());
+        assert_eq!(line.cursor_column(false), 0);
+    }
+
+    #[test]
+    fn test_grows_in_place() {
+        let line = typed("correct-horse-battery-staple-and-then-some");
+        assert!(line.text.capacity() >= 64);
+        assert_eq!(&*line, "correct-horse-battery-staple-and-then-some");
+    }
+}
@@ -2,7 +2,10 @@
 //!
 //! Handles keyboard input with vim-style modal editing.
 
+pub mod commands;
+pub mod fuzzy;
 pub mod keymap;
+pub mod line;
 pub mod modes;
 
 // Re-exports
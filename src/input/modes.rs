@@ -2,6 +2,9 @@
 //!
 //! Modal editing state machine for vim-style interface.
 
+use super::keymap::Pending;
+use super::line::EditLine;
+
 /// Input mode enum
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InputMode {
@@ -21,6 +24,28 @@ pub enum InputMode {
     Logs,
     /// Tags screen
     Tags,
+    /// Credential timeline
+    Timeline,
+    /// Fuzzy finder
+    Finder,
+    /// Command palette
+    Palette,
+    /// Backups screen
+    Backups,
+    /// Probable duplicates report
+    Dupes,
+    /// Field-by-field merge of two credentials
+    Merge,
+    /// Process hardening report
+    Security,
+    /// Encryption format of each credential
+    CryptoInfo,
+    /// Connection string schemes for a database credential
+    Connection,
+    /// Security tips
+    Tips,
+    /// Vault info and statistics
+    Info,
 }
 
 impl InputMode {
@@ -35,6 +60,17 @@ impl InputMode {
             Self::Help => "HELP",
             Self::Logs => "LOG",
             Self::Tags => "TAG",
+            Self::Timeline => "TIMELINE",
+            Self::Finder => "FIND",
+            Self::Palette => "PALETTE",
+            Self::Backups => "BACKUP",
+            Self::Dupes => "DUPES",
+            Self::Merge => "MERGE",
+            Self::Security => "SECURITY",
+            Self::CryptoInfo => "CRYPTO",
+            Self::Connection => "CONNECT",
+            Self::Tips => "TIPS",
+            Self::Info => "INFO",
         }
     }
 
@@ -49,21 +85,18 @@ impl InputMode {
 pub struct ModeState {
     /// Current mode
     pub mode: InputMode,
-    /// Text buffer for input modes
-    pub buffer: String,
-    /// Cursor position in buffer
-    pub cursor: usize,
-    /// Pending key sequence (for multi-key commands like gg, dd)
-    pub pending: Option<char>,
+    /// Text and cursor for input modes
+    pub line: EditLine,
+    /// Pending key sequence (counts and multi-key commands like gg, dd)
+    pub pending: Pending,
 }
 
 impl Default for ModeState {
     fn default() -> Self {
         Self {
             mode: InputMode::Normal,
-            buffer: String::new(),
-            cursor: 0,
-            pending: None,
+            line: EditLine::new(),
+            pending: Pending::default(),
         }
     }
 }
@@ -77,9 +110,8 @@ impl ModeState {
     /// Switch to a new mode
     pub fn set_mode(&mut self, mode: InputMode) {
         self.mode = mode;
-        self.buffer.clear();
-        self.cursor = 0;
-        self.pending = None;
+        self.line.clear();
+        self.pending = Pending::default();
     }
 
     /// Switch to normal mode
@@ -122,66 +154,109 @@ impl ModeState {
         self.mode = InputMode::Logs;
     }
 
+    /// Switch to timeline mode
+    pub fn to_timeline(&mut self) {
+        self.mode = InputMode::Timeline;
+    }
+
+    /// Switch to fuzzy finder mode
+    pub fn to_finder(&mut self) {
+        self.mode = InputMode::Finder;
+    }
+
+    /// Switch to command palette mode
+    pub fn to_palette(&mut self) {
+        self.mode = InputMode::Palette;
+    }
+
+    /// Switch to backups mode
+    pub fn to_backups(&mut self) {
+        self.mode = InputMode::Backups;
+    }
+
+    /// Switch to duplicates mode
+    pub fn to_dupes(&mut self) {
+        self.mode = InputMode::Dupes;
+    }
+
+    /// Switch to merge mode
+    pub fn to_merge(&mut self) {
+        self.mode = InputMode::Merge;
+    }
+
+    /// Switch to security info mode
+    pub fn to_security(&mut self) {
+        self.mode = InputMode::Security;
+    }
+
+    /// Switch to security tips mode
+    pub fn to_tips(&mut self) {
+        self.mode = InputMode::Tips;
+    }
+
+    /// Switch to vault info mode
+    pub fn to_info(&mut self) {
+        self.mode = InputMode::Info;
+    }
+
+    /// Switch to crypto info mode
+    pub fn to_crypto_info(&mut self) {
+        self.mode = InputMode::CryptoInfo;
+    }
+
+    /// Switch to connection string mode
+    pub fn to_connection(&mut self) {
+        self.mode = InputMode::Connection;
+    }
+
     /// Insert character at cursor
     pub fn insert_char(&mut self, c: char) {
-        self.buffer.insert(self.cursor, c);
-        self.cursor += 1;
+        self.line.insert(c);
     }
 
     /// Delete character before cursor (backspace)
     pub fn delete_char(&mut self) {
-        if self.cursor > 0 {
-            self.cursor -= 1;
-            self.buffer.remove(self.cursor);
-        }
+        self.line.backspace();
     }
 
     /// Delete character at cursor (delete key)
     pub fn delete_char_forward(&mut self) {
-        if self.cursor < self.buffer.len() {
-            self.buffer.remove(self.cursor);
-        }
+        self.line.delete();
     }
 
     /// Move cursor left
     pub fn cursor_left(&mut self) {
-        if self.cursor > 0 {
-            self.cursor -= 1;
-        }
+        self.line.left();
     }
 
     /// Move cursor right
     pub fn cursor_right(&mut self) {
-        if self.cursor < self.buffer.len() {
-            self.cursor += 1;
-        }
+        self.line.right();
     }
 
     /// Move cursor to start
     pub fn cursor_home(&mut self) {
-        self.cursor = 0;
+        self.line.home();
     }
 
     /// Move cursor to end
     pub fn cursor_end(&mut self) {
-        self.cursor = self.buffer.len();
+        self.line.end();
     }
 
     /// Clear buffer
     pub fn clear_buffer(&mut self) {
-        self.buffer.clear();
-        self.cursor = 0;
+        self.line.clear();
     }
 
     /// Get buffer contents
     pub fn get_buffer(&self) -> &str {
-        &self.buffer
+        self.line.as_str()
     }
 
     /// Set buffer contents
     pub fn set_buffer(&mut self, content: &str) {
-        self.buffer = content.to_string();
-        self.cursor = self.buffer.len();
+        self.line.set(content);
     }
 }
 
@@ -235,7 +310,7 @@ mod tests {
         state.insert_char('o');
 
         assert_eq!(state.get_buffer(), "hello");
-        assert_eq!(state.cursor, 5);
+        assert_eq!(state.line.cursor_index(), 5);
 
         state.delete_char();
         assert_eq!(state.get_buffer(), "hell");
@@ -247,16 +322,16 @@ mod tests {
         state.set_buffer("hello");
 
         state.cursor_home();
-        assert_eq!(state.cursor, 0);
+        assert_eq!(state.line.cursor_index(), 0);
 
         state.cursor_end();
-        assert_eq!(state.cursor, 5);
+        assert_eq!(state.line.cursor_index(), 5);
 
         state.cursor_left();
-        assert_eq!(state.cursor, 4);
+        assert_eq!(state.line.cursor_index(), 4);
 
         state.cursor_right();
-        assert_eq!(state.cursor, 5);
+        assert_eq!(state.line.cursor_index(), 5);
     }
 
     #[test]
@@ -3,33 +3,125 @@
 //! A local-first, vim-style TUI credential manager.
 
 use std::io;
-use std::path::PathBuf;
-use std::time::Duration;
 
-use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::event::{
+    DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, KeyCode, KeyEvent, KeyEventState,
+    KeyModifiers,
+};
 use crossterm::execute;
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
-use zeroize::Zeroize;
+use zeroize::Zeroizing;
 
 mod app;
-mod crypto;
-mod db;
+mod cli;
+mod hardening;
+mod i18n;
 mod input;
+mod tui;
 mod ui;
-mod vault;
 
-use app::{App, AppConfig};
+use vault_core::{crypto, db, vault};
+
+use app::{App, AppConfig, AppError, Cmd, Msg, SecurityEvent};
+use crypto::strength::check_master_password;
+use crypto::MasterKey;
+use input::line::EditLine;
+use tui::Screen;
+use vault::kdf_task::KdfTask;
+use vault::VaultError;
 
 type Term = Terminal<CrosstermBackend<io::Stdout>>;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    harden_process();
+    hardening::apply();
+
+    let args = cli::parse_args(std::env::args_os()).unwrap_or_else(|e| e.exit());
+    match args.command {
+        cli::Command::Completions { shell } => {
+            cli::print_completions(shell);
+            return Ok(());
+        }
+        cli::Command::Bench { count } => return cli::run_bench(count),
+        _ => {}
+    }
+    let mut config = parse_config(&args)?;
+    if let Some(path) = &config.locale {
+        i18n::load(path)?;
+    }
+    ui::theme::init(config.icons, config.high_contrast, &config.tag_styles);
+    let command = args.command;
+
+    if let cli::Output::Json { show_secrets } = args.output {
+        std::process::exit(cli::run_json(&config, &command, show_secrets));
+    }
+
+    if let cli::Command::Check { repair, .. } = command {
+        let clean = cli::run_check(&config, repair)?;
+        std::process::exit(if clean { 0 } else { 1 });
+    }
+    if let cli::Command::Show { name, field, newline, .. } = &command {
+        return cli::run_show(&config, name, *field, *newline);
+    }
+    if let cli::Command::Add { name, from_stdin, username, url, tags, backend, .. } = command {
+        return cli::run_add(&config, &name, from_stdin, username, url, tags, backend);
+    }
+    if let cli::Command::Exec { selection, argv, .. } = &command {
+        let code = cli::run_exec(&config, selection, argv)?;
+        std::process::exit(code);
+    }
+    if let cli::Command::Render { template, output, .. } = &command {
+        return cli::run_render(&config, template, output);
+    }
+    if let cli::Command::Match { url, .. } = &command {
+        let found = cli::run_match(&config, url)?;
+        std::process::exit(if found { 0 } else { 1 });
+    }
+    if let cli::Command::Pick { field, newline, .. } = command {
+        return cli::run_pick(&config, field, newline);
+    }
+    if let cli::Command::Names { .. } = command {
+        let found = cli::run_names(&config)?;
+        std::process::exit(if found { 0 } else { 1 });
+    }
+    if let cli::Command::Agent { idle, .. } = command {
+        let _events = app::system_events::install();
+        return cli::run_agent(&config, idle);
+    }
+    if let cli::Command::Lock { .. } = command {
+        return cli::run_lock(&config);
+    }
+    if let cli::Command::Api { port, .. } = command {
+        let _events = app::system_events::install();
+        return cli::run_api(&config, port);
+    }
+    if let cli::Command::RecoveryKit { .. } = command {
+        return cli::run_recovery_kit(&config);
+    }
+    if let cli::Command::EmergencyCodes { count, .. } = command {
+        return cli::run_emergency_codes(&config, count);
+    }
+    if let cli::Command::Recover { kit_path, .. } = &command {
+        return cli::run_recover(&config, kit_path);
+    }
+    if let cli::Command::BreachIndex { dump, output } = &command {
+        return cli::run_breach_index(dump, output.as_deref().unwrap_or(&config.breach_index_path()));
+    }
+    if let cli::Command::Diff { left, right } = &command {
+        let same = cli::run_diff(&config, left, right)?;
+        std::process::exit(if same { 0 } else { 1 });
+    }
 
-    let config = parse_config();
+    if let cli::Command::Tui { read_only: true, .. } = command {
+        config.read_only = true;
+    }
+    if config.read_only && !config.vault_path.exists() {
+        return Err(format!("No vault at {} to open read-only", config.vault_path.display()).into());
+    }
     ensure_vault_dir(&config)?;
 
+    let _events = app::system_events::install();
     let mut terminal = setup_terminal()?;
     let mut app = App::new(config);
 
@@ -39,17 +131,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     result
 }
 
-fn harden_process() {
-    #[cfg(unix)]
-    unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0); }
-}
-
-fn parse_config() -> AppConfig {
-    let mut config = AppConfig::default();
-    if let Some(path) = std::env::args().nth(1) {
-        config.vault_path = PathBuf::from(path);
+fn parse_config(args: &cli::Args) -> Result<AppConfig, Box<dyn std::error::Error>> {
+    let mut config = match &args.config_path {
+        Some(path) => AppConfig::load_from(path)?,
+        None => AppConfig::load()?,
+    };
+    if let Some(path) = args.command.vault_path() {
+        config.vault_path = path.clone();
     }
-    config
+    if let Some(timeout) = args.lock_timeout {
+        config.auto_lock_timeout = timeout;
+    }
+    Ok(config)
 }
 
 fn ensure_vault_dir(config: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
@@ -62,19 +155,19 @@ fn ensure_vault_dir(config: &AppConfig) -> Result<(), Box<dyn std::error::Error>
 fn setup_terminal() -> Result<Term, Box<dyn std::error::Error>> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableFocusChange)?;
     let backend = CrosstermBackend::new(stdout);
     Ok(Terminal::new(backend)?)
 }
 
 fn cleanup_terminal(terminal: &mut Term) -> Result<(), Box<dyn std::error::Error>> {
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture, DisableFocusChange)?;
     terminal.show_cursor()?;
     Ok(())
 }
 
-fn run_with_auth(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
+fn run_with_auth(terminal: &mut dyn Screen, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
     if app.needs_init() {
         run_init(terminal, app)?;
     } else if app.is_locked() {
@@ -87,69 +180,67 @@ fn run_with_auth(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::
     Ok(())
 }
 
-fn poll_key_press() -> Result<Option<KeyEvent>, Box<dyn std::error::Error>> {
-    if !event::poll(Duration::from_millis(100))? {
-        return Ok(None);
-    }
-    let Event::Key(key) = event::read()? else { return Ok(None) };
-    if key.kind != KeyEventKind::Press { return Ok(None) }
-    Ok(Some(key))
-}
-
+/// Wiped when dropped, as `EditLine` is
+#[derive(Default)]
 struct PasswordField {
-    value: String,
-    cursor: usize,
-}
-
-impl Default for PasswordField {
-    fn default() -> Self {
-        Self { value: String::new(), cursor: 0 }
-    }
-}
-
-impl Drop for PasswordField {
-    fn drop(&mut self) {
-        self.value.zeroize();
-    }
+    value: EditLine,
+    revealed: bool,
+    caps_lock: bool,
 }
 
 impl PasswordField {
     fn clear(&mut self) {
-        self.value.zeroize();
-        self.cursor = 0;
+        self.value.clear();
+        self.revealed = false;
     }
 }
 
-fn handle_password_key(field: &mut PasswordField, code: KeyCode) {
-    match code {
-        KeyCode::Backspace if field.cursor > 0 => password_backspace(field),
-        KeyCode::Char(c) => password_insert(field, c),
-        KeyCode::Left if field.cursor > 0 => field.cursor -= 1,
-        KeyCode::Right if field.cursor < field.value.len() => field.cursor += 1,
+fn handle_password_key(field: &mut PasswordField, key: KeyEvent) {
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        if key.code == KeyCode::Char('s') {
+            field.revealed = !field.revealed;
+        }
+        return;
+    }
+    track_caps_lock(field, key);
+    match key.code {
+        KeyCode::Backspace => {
+            field.value.backspace();
+        }
+        KeyCode::Delete => {
+            field.value.delete();
+        }
+        KeyCode::Char(c) => field.value.insert(c),
+        KeyCode::Left => field.value.left(),
+        KeyCode::Right => field.value.right(),
         _ => {}
     }
 }
 
-fn password_backspace(field: &mut PasswordField) {
-    field.cursor -= 1;
-    field.value.remove(field.cursor);
-}
-
-fn password_insert(field: &mut PasswordField, c: char) {
-    field.value.insert(field.cursor, c);
-    field.cursor += 1;
+/// Terminals that report lock state say so directly; otherwise a letter
+/// whose case disagrees with Shift gives caps lock away
+fn track_caps_lock(field: &mut PasswordField, key: KeyEvent) {
+    if key.state.contains(KeyEventState::CAPS_LOCK) {
+        field.caps_lock = true;
+        return;
+    }
+    let KeyCode::Char(c) = key.code else { return };
+    if c.is_alphabetic() && c.is_uppercase() != c.is_lowercase() {
+        field.caps_lock = c.is_uppercase() != key.modifiers.contains(KeyModifiers::SHIFT);
+    }
 }
 
 fn draw_password_dialog(
-    terminal: &mut Term,
+    terminal: &mut dyn Screen,
     title: &str,
     prompt: &str,
     field: &PasswordField,
     error: Option<&str>,
+    min_entropy: Option<f64>,
+    working: Option<char>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    terminal.draw(|frame| {
-        let dialog = build_password_dialog(title, prompt, field, error);
-        frame.render_widget(dialog, frame.area());
+    terminal.draw(&mut |frame| {
+        frame.render_widget(build_password_dialog(title, prompt, field, error, min_entropy, working), frame.area());
     })?;
     Ok(())
 }
@@ -159,15 +250,30 @@ fn build_password_dialog<'a>(
     prompt: &'a str,
     field: &'a PasswordField,
     error: Option<&'a str>,
+    min_entropy: Option<f64>,
+    working: Option<char>,
 ) -> ui::PasswordDialog<'a> {
-    let dialog = ui::PasswordDialog::new(title, prompt, &field.value, field.cursor);
-    match error {
-        Some(err) => dialog.error(err),
+    let mut dialog = ui::PasswordDialog::new(title, prompt, &field.value)
+        .revealed(field.revealed)
+        .caps_lock(field.caps_lock);
+    if let Some(err) = error {
+        dialog = dialog.error(err);
+    }
+    if let Some(spinner) = working {
+        dialog = dialog.working(spinner);
+    }
+    match min_entropy {
+        Some(min) => dialog.strength(min),
         None => dialog,
     }
 }
 
-fn run_init(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
+/// A copy of the typed password for a worker thread, wiped when dropped
+fn password_for_task(field: &PasswordField) -> Zeroizing<String> {
+    Zeroizing::new(field.value.to_string())
+}
+
+fn run_init(terminal: &mut dyn Screen, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
     let mut state = InitState::default();
 
     while !state.done {
@@ -183,13 +289,30 @@ struct InitState {
     confirming: bool,
     error: Option<String>,
     done: bool,
+    /// Deriving the new master key
+    task: Option<KdfTask<(MasterKey, String)>>,
 }
 
-fn init_iteration(terminal: &mut Term, app: &mut App, state: &mut InitState) -> Result<(), Box<dyn std::error::Error>> {
+fn init_iteration(terminal: &mut dyn Screen, app: &mut App, state: &mut InitState) -> Result<(), Box<dyn std::error::Error>> {
+    if app::system_events::shutdown_requested() {
+        app.should_quit = true;
+        state.done = true;
+        return Ok(());
+    }
+
     let (title, prompt, field) = init_dialog_params(state.confirming, &state.password, &state.confirm);
-    draw_password_dialog(terminal, title, prompt, field, state.error.as_deref())?;
+    let strength = (!state.confirming).then_some(app.config.min_master_entropy);
+    let working = state.task.as_ref().map(KdfTask::spinner);
+    draw_password_dialog(terminal, title, prompt, field, state.error.as_deref(), strength, working)?;
+
+    if let Some(result) = state.task.as_ref().and_then(KdfTask::poll) {
+        state.task = None;
+        state.error = finish_init(state, app, result);
+        state.done = state.error.is_none();
+        return Ok(());
+    }
 
-    let Some(key) = poll_key_press()? else { return Ok(()) };
+    let Some(key) = terminal.poll_key_press()? else { return Ok(()) };
 
     handle_init_key(key, state, app);
     Ok(())
@@ -208,6 +331,14 @@ fn init_dialog_params<'a>(
 }
 
 fn handle_init_key(key: KeyEvent, state: &mut InitState, app: &mut App) {
+    if state.task.is_some() {
+        if key.code == KeyCode::Esc {
+            state.task = None;
+            state.error = Some("Cancelled".into());
+        }
+        return;
+    }
+
     if key.code == KeyCode::Esc {
         app.should_quit = true;
         state.done = true;
@@ -215,43 +346,45 @@ fn handle_init_key(key: KeyEvent, state: &mut InitState, app: &mut App) {
     }
 
     if key.code == KeyCode::Enter {
-        process_init_submit(state, app);
+        state.error = process_init_enter(state, app);
         return;
     }
 
     let field = if state.confirming { &mut state.confirm } else { &mut state.password };
-    handle_password_key(field, key.code);
-}
-
-fn process_init_submit(state: &mut InitState, app: &mut App) {
-    state.error = process_init_enter(state, app);
-    state.done = state.error.is_none() && !state.confirming;
+    handle_password_key(field, key);
 }
 
 fn process_init_enter(state: &mut InitState, app: &mut App) -> Option<String> {
     if !state.confirming {
-        return validate_init_password(&state.password, &mut state.confirming);
+        return validate_init_password(&state.password, app.config.min_master_entropy, &mut state.confirming);
     }
-    finalize_init(state, app)
+    start_init(state)
 }
 
-fn validate_init_password(password: &PasswordField, confirming: &mut bool) -> Option<String> {
-    if password.value.len() < 8 {
-        return Some("Password must be at least 8 characters".into());
+fn validate_init_password(password: &PasswordField, min_entropy: f64, confirming: &mut bool) -> Option<String> {
+    if let Err(weakness) = check_master_password(&password.value, min_entropy) {
+        return Some(weakness.to_string());
     }
     *confirming = true;
     None
 }
 
-fn finalize_init(state: &mut InitState, app: &mut App) -> Option<String> {
-    if state.password.value != state.confirm.value {
+fn start_init(state: &mut InitState) -> Option<String> {
+    if state.password.value.as_str() != state.confirm.value.as_str() {
         state.password.clear();
         state.confirm.clear();
         state.confirming = false;
         return Some("Passwords do not match".into());
     }
 
-    if let Err(e) = app.initialize(&state.password.value) {
+    let password = password_for_task(&state.password);
+    state.task = Some(KdfTask::spawn(move || vault::Vault::derive_key(&password)));
+    None
+}
+
+fn finish_init(state: &mut InitState, app: &mut App, derived: vault::VaultResult<(MasterKey, String)>) -> Option<String> {
+    let result = derived.map_err(Into::into).and_then(|(key, hash)| app.initialize(key, hash));
+    if let Err(e) = result {
         state.confirm.clear();
         return Some(format!("Failed to initialize: {}", e));
     }
@@ -260,8 +393,8 @@ fn finalize_init(state: &mut InitState, app: &mut App) -> Option<String> {
     None
 }
 
-fn run_unlock(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
-    let mut state = UnlockState::default();
+fn run_unlock(terminal: &mut dyn Screen, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
+    let mut state = UnlockState { fingerprint: app.vault.fingerprint().ok().flatten(), ..UnlockState::default() };
 
     while !state.done {
         unlock_iteration(terminal, app, &mut state)?;
@@ -275,18 +408,46 @@ struct UnlockState {
     error: Option<String>,
     attempts: u32,
     done: bool,
+    /// Unlocking a copy of the vault on a worker thread
+    task: Option<KdfTask<vault::Vault>>,
+    /// Which vault file this is, shown before the password is typed
+    fingerprint: Option<String>,
 }
 
-fn unlock_iteration(terminal: &mut Term, app: &mut App, state: &mut UnlockState) -> Result<(), Box<dyn std::error::Error>> {
-    draw_password_dialog(terminal, " Unlock Vault ", "Enter master password:", &state.password, state.error.as_deref())?;
+fn unlock_iteration(terminal: &mut dyn Screen, app: &mut App, state: &mut UnlockState) -> Result<(), Box<dyn std::error::Error>> {
+    if app::system_events::shutdown_requested() {
+        app.should_quit = true;
+        state.done = true;
+        return Ok(());
+    }
+
+    let working = state.task.as_ref().map(KdfTask::spinner);
+    terminal.draw(&mut |frame| {
+        let dialog = build_password_dialog(" Unlock Vault ", "Enter master password:", &state.password, state.error.as_deref(), None, working);
+        frame.render_widget(dialog.fingerprint(state.fingerprint.as_deref()), frame.area());
+    })?;
+
+    if let Some(result) = state.task.as_ref().and_then(KdfTask::poll) {
+        state.task = None;
+        finish_unlock_attempt(state, app, result);
+        return Ok(());
+    }
 
-    let Some(key) = poll_key_press()? else { return Ok(()) };
+    let Some(key) = terminal.poll_key_press()? else { return Ok(()) };
 
     handle_unlock_key(key, state, app);
     Ok(())
 }
 
 fn handle_unlock_key(key: KeyEvent, state: &mut UnlockState, app: &mut App) {
+    if state.task.is_some() {
+        if key.code == KeyCode::Esc {
+            state.task = None;
+            state.error = Some("Cancelled".into());
+        }
+        return;
+    }
+
     if key.code == KeyCode::Esc {
         app.should_quit = true;
         state.done = true;
@@ -294,23 +455,49 @@ fn handle_unlock_key(key: KeyEvent, state: &mut UnlockState, app: &mut App) {
     }
 
     if key.code == KeyCode::Enter {
-        process_unlock_attempt(state, app);
+        start_unlock_attempt(state, app);
         return;
     }
 
-    handle_password_key(&mut state.password, key.code);
+    handle_password_key(&mut state.password, key);
+}
+
+/// The worker unlocks its own vault, which replaces the app's once it
+/// succeeds, so a cancelled attempt leaves nothing behind
+fn start_unlock_attempt(state: &mut UnlockState, app: &App) {
+    let config = app.vault.config().clone();
+    let password = password_for_task(&state.password);
+    state.error = None;
+    state.task = Some(KdfTask::spawn(move || {
+        let mut vault = vault::Vault::new(config);
+        vault.unlock_without_index(&password)?;
+        Ok(vault)
+    }));
 }
 
-fn process_unlock_attempt(state: &mut UnlockState, app: &mut App) {
-    if app.unlock(&state.password.value).is_ok() {
+fn finish_unlock_attempt(state: &mut UnlockState, app: &mut App, result: vault::VaultResult<vault::Vault>) {
+    let result = result.map_err(Into::into).and_then(|unlocked| app.finish_unlock(unlocked));
+    let Err(e) = result else {
         state.done = true;
         return;
+    };
+    state.password.clear();
+
+    // Throttled or wiped vaults never got as far as checking the password
+    if !matches!(e, AppError::Vault(VaultError::InvalidPassword)) {
+        state.error = Some(e.to_string());
+        return;
     }
 
     state.attempts += 1;
-    state.password.clear();
-    let _ = app.vault.record_failed_unlock();
-    state.error = Some(format!("Invalid password ({}/5)", state.attempts));
+    app.fire_event(SecurityEvent::FailedUnlock, &format!("Wrong master password entered ({}/5)", state.attempts));
+    state.error = Some(match app.vault.record_failed_unlock() {
+        Ok(failure) if failure.wiped => VaultError::Wiped.to_string(),
+        Ok(failure) if !failure.retry_after.is_zero() => {
+            format!("Invalid password ({}/5), wait {}s", state.attempts, failure.retry_after.as_secs())
+        }
+        _ => format!("Invalid password ({}/5)", state.attempts),
+    });
 
     if state.attempts >= 5 {
         app.should_quit = true;
@@ -318,17 +505,45 @@ fn process_unlock_attempt(state: &mut UnlockState, app: &mut App) {
     }
 }
 
+/// Slow password work the change dialog waits on
+enum ChangeTask {
+    /// Checking the current password
+    Verify(KdfTask<()>),
+    /// Deriving the key for the new password
+    Derive(KdfTask<(MasterKey, String)>),
+}
+
+impl ChangeTask {
+    fn spinner(&self) -> char {
+        match self {
+            Self::Verify(task) => task.spinner(),
+            Self::Derive(task) => task.spinner(),
+        }
+    }
+}
+
 struct PasswordChangeState {
     current: PasswordField,
     new_pass: PasswordField,
     confirm: PasswordField,
     step: u8,
     error: Option<String>,
+    /// Label of the key slot being added; `None` changes the active password
+    slot: Option<String>,
+    task: Option<ChangeTask>,
 }
 
 impl Default for PasswordChangeState {
     fn default() -> Self {
-        Self { current: PasswordField::default(), new_pass: PasswordField::default(), confirm: PasswordField::default(), step: 0, error: None }
+        Self {
+            current: PasswordField::default(),
+            new_pass: PasswordField::default(),
+            confirm: PasswordField::default(),
+            step: 0,
+            error: None,
+            slot: None,
+            task: None,
+        }
     }
 }
 
@@ -362,8 +577,8 @@ enum ChangeResult {
     Success,
 }
 
-fn run_password_change(terminal: &mut Term, app: &mut App) -> Result<bool, Box<dyn std::error::Error>> {
-    let mut state = PasswordChangeState::default();
+fn run_password_change(terminal: &mut dyn Screen, app: &mut App, slot: Option<String>) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut state = PasswordChangeState { slot, ..Default::default() };
     let mut result = ChangeResult::Continue;
 
     while matches!(result, ChangeResult::Continue) {
@@ -373,38 +588,81 @@ fn run_password_change(terminal: &mut Term, app: &mut App) -> Result<bool, Box<d
     Ok(matches!(result, ChangeResult::Success))
 }
 
-fn change_iteration(terminal: &mut Term, app: &mut App, state: &mut PasswordChangeState) -> Result<ChangeResult, Box<dyn std::error::Error>> {
+fn change_iteration(terminal: &mut dyn Screen, app: &mut App, state: &mut PasswordChangeState) -> Result<ChangeResult, Box<dyn std::error::Error>> {
+    app.handle_system_events();
+    if app.is_locked() {
+        return Ok(ChangeResult::Cancel);
+    }
+
     let (prompt, field) = change_prompt_and_field(state);
-    draw_password_dialog(terminal, " Change Master Key ", prompt, field, state.error.as_deref())?;
+    let title = if state.slot.is_some() { " Add Key Slot " } else { " Change Master Key " };
+    let strength = (state.step == 1).then_some(app.config.min_master_entropy);
+    let working = state.task.as_ref().map(ChangeTask::spinner);
+    draw_password_dialog(terminal, title, prompt, field, state.error.as_deref(), strength, working)?;
+
+    if let Some(result) = poll_change_task(state, app) {
+        return Ok(result);
+    }
 
-    let Some(key) = poll_key_press()? else { return Ok(ChangeResult::Continue) };
+    let Some(key) = terminal.poll_key_press()? else { return Ok(ChangeResult::Continue) };
+
+    Ok(handle_change_key(key, state, app))
+}
 
-    Ok(handle_change_key(key, state, &mut app.vault))
+fn poll_change_task(state: &mut PasswordChangeState, app: &mut App) -> Option<ChangeResult> {
+    let result = match state.task.as_ref()? {
+        ChangeTask::Verify(task) => finish_change_verify(state, task.poll()?),
+        ChangeTask::Derive(task) => {
+            let derived = task.poll()?;
+            finish_change_confirm(state, app, derived)
+        }
+    };
+    state.task = None;
+    Some(result)
 }
 
-fn handle_change_key(key: KeyEvent, state: &mut PasswordChangeState, vault: &mut vault::Vault) -> ChangeResult {
+fn handle_change_key(key: KeyEvent, state: &mut PasswordChangeState, app: &mut App) -> ChangeResult {
+    if state.task.is_some() {
+        if key.code == KeyCode::Esc {
+            state.task = None;
+            state.error = Some("Cancelled".into());
+        }
+        return ChangeResult::Continue;
+    }
+
     if key.code == KeyCode::Esc {
         return ChangeResult::Cancel;
     }
 
     if key.code == KeyCode::Enter {
-        return process_change_step(state, vault);
+        return process_change_step(state, app);
     }
 
-    handle_password_key(change_current_field(state), key.code);
+    handle_password_key(change_current_field(state), key);
     ChangeResult::Continue
 }
 
-fn process_change_step(state: &mut PasswordChangeState, vault: &mut vault::Vault) -> ChangeResult {
+fn process_change_step(state: &mut PasswordChangeState, app: &mut App) -> ChangeResult {
     match state.step {
-        0 => process_change_verify(state, vault),
-        1 => process_change_new(state),
-        _ => process_change_confirm(state, vault),
+        0 => start_change_verify(state, &app.vault),
+        1 => process_change_new(state, app.config.min_master_entropy),
+        _ => start_change_confirm(state, app),
+    }
+}
+
+fn start_change_verify(state: &mut PasswordChangeState, vault: &vault::Vault) -> ChangeResult {
+    match vault.password_verifier() {
+        Ok(verify) => {
+            let password = password_for_task(&state.current);
+            state.task = Some(ChangeTask::Verify(KdfTask::spawn(move || verify(&password))));
+        }
+        Err(e) => state.error = Some(change_verify_error_msg(e)),
     }
+    ChangeResult::Continue
 }
 
-fn process_change_verify(state: &mut PasswordChangeState, vault: &mut vault::Vault) -> ChangeResult {
-    if let Err(e) = vault.verify_password(&state.current.value) {
+fn finish_change_verify(state: &mut PasswordChangeState, result: vault::VaultResult<()>) -> ChangeResult {
+    if let Err(e) = result {
         state.current.clear();
         state.error = Some(change_verify_error_msg(e));
         return ChangeResult::Continue;
@@ -422,14 +680,14 @@ fn change_verify_error_msg(e: vault::VaultError) -> String {
     }.into()
 }
 
-fn process_change_new(state: &mut PasswordChangeState) -> ChangeResult {
-    if state.new_pass.value.len() < 8 {
+fn process_change_new(state: &mut PasswordChangeState, min_entropy: f64) -> ChangeResult {
+    if let Err(weakness) = check_master_password(&state.new_pass.value, min_entropy) {
         state.new_pass.clear();
-        state.error = Some("Password must be at least 8 characters".into());
+        state.error = Some(weakness.to_string());
         return ChangeResult::Continue;
     }
 
-    if state.new_pass.value == state.current.value {
+    if state.new_pass.value.as_str() == state.current.value.as_str() {
         state.new_pass.clear();
         state.error = Some("New password must be different".into());
         return ChangeResult::Continue;
@@ -440,16 +698,38 @@ fn process_change_new(state: &mut PasswordChangeState) -> ChangeResult {
     ChangeResult::Continue
 }
 
-fn process_change_confirm(state: &mut PasswordChangeState, vault: &mut vault::Vault) -> ChangeResult {
-    if state.new_pass.value != state.confirm.value {
+fn start_change_confirm(state: &mut PasswordChangeState, app: &mut App) -> ChangeResult {
+    if state.new_pass.value.as_str() != state.confirm.value.as_str() {
         state.confirm.clear();
         state.error = Some("Passwords do not match".into());
         return ChangeResult::Continue;
     }
 
-    if let Err(e) = vault.change_password(&state.current.value, &state.new_pass.value) {
-        state.error = Some(change_confirm_error_msg(e));
+    if let Err(e) = app.backup_before(if state.slot.is_some() { "keyslot" } else { "passwd" }) {
+        state.error = Some(format!("Backup failed: {}", e));
+        return ChangeResult::Continue;
+    }
+
+    let password = password_for_task(&state.new_pass);
+    state.task = Some(ChangeTask::Derive(KdfTask::spawn(move || vault::Vault::derive_key(&password))));
+    state.error = None;
+    ChangeResult::Continue
+}
+
+/// The current password was checked at the first step, so the new key
+/// only has to be stored
+fn finish_change_confirm(
+    state: &mut PasswordChangeState,
+    app: &mut App,
+    derived: vault::VaultResult<(MasterKey, String)>,
+) -> ChangeResult {
+    let result = derived.and_then(|(key, hash)| match &state.slot {
+        Some(label) => app.vault.add_key_slot_with_key(label, key, hash),
+        None => app.vault.change_password_with_key(key, hash),
+    });
+    if let Err(e) = result {
         change_reset(state);
+        state.error = Some(change_confirm_error_msg(e));
         return ChangeResult::Continue;
     }
 
@@ -458,59 +738,199 @@ fn process_change_confirm(state: &mut PasswordChangeState, vault: &mut vault::Va
 
 fn change_confirm_error_msg(e: vault::VaultError) -> String {
     match e {
-        vault::VaultError::InvalidPassword => "Current password is incorrect",
-        _ => "Failed to change password",
-    }.into()
+        vault::VaultError::InvalidPassword => "Current password is incorrect".into(),
+        vault::VaultError::OperationFailed(msg) => msg,
+        _ => "Failed to change password".into(),
+    }
 }
 
-fn run_app(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
+fn run_app(terminal: &mut dyn Screen, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
     while !app.should_quit && !app_iteration(terminal, app)? {}
     Ok(())
 }
 
-fn app_iteration(terminal: &mut Term, app: &mut App) -> Result<bool, Box<dyn std::error::Error>> {
-    terminal.draw(|frame| app.render(frame))?;
-
-    if process_app_input(terminal, app)? {
+fn app_iteration(terminal: &mut dyn Screen, app: &mut App) -> Result<bool, Box<dyn std::error::Error>> {
+    let commands = app.update(Msg::Tick);
+    run_commands(terminal, app, commands)?;
+    if app.should_quit {
         return Ok(true);
     }
 
-    check_auto_lock(terminal, app)?;
-    Ok(false)
-}
+    terminal.draw(&mut |frame| app.render(frame))?;
 
-fn process_app_input(terminal: &mut Term, app: &mut App) -> Result<bool, Box<dyn std::error::Error>> {
-    let Some(key) = poll_key_press()? else { return Ok(false) };
+    let event = terminal.poll_event().inspect_err(|_| app.lock())?;
+    if let Some(msg) = event.and_then(Msg::from_event) {
+        let commands = app.update(msg);
+        run_commands(terminal, app, commands)?;
+    }
 
-    if app.handle_key_event(key)? {
-        return Ok(true);
+    while app.is_locked() && !app.should_quit {
+        run_unlock(terminal, app)?;
     }
+    Ok(app.should_quit)
+}
 
-    handle_password_change_request(terminal, app)?;
-    Ok(false)
+/// Carry out what an update asked for, along with anything the messages
+/// they send back ask for in turn
+fn run_commands(terminal: &mut dyn Screen, app: &mut App, commands: Vec<Cmd>) -> Result<(), Box<dyn std::error::Error>> {
+    for cmd in commands {
+        match cmd {
+            Cmd::ChangePassword => change_password(terminal, app, None),
+            Cmd::AddKeySlot(label) => change_password(terminal, app, Some(label)),
+            Cmd::Reauthenticate => {
+                if run_reauth(terminal, app)? {
+                    let commands = app.update(Msg::Reauthenticated);
+                    run_commands(terminal, app, commands)?;
+                }
+            }
+        }
+    }
+    Ok(())
 }
 
-fn handle_password_change_request(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
-    if !app.wants_password_change {
+/// Wrong passwords allowed when re-authenticating before the vault locks
+const REAUTH_ATTEMPTS: u32 = 3;
+
+#[derive(Default)]
+struct ReauthState {
+    password: PasswordField,
+    error: Option<String>,
+    attempts: u32,
+    done: bool,
+    task: Option<KdfTask<()>>,
+}
+
+/// Ask for the master password before running a sensitive action; true
+/// once it has been entered
+fn run_reauth(terminal: &mut dyn Screen, app: &mut App) -> Result<bool, Box<dyn std::error::Error>> {
+    if app.wants_reauth.is_none() {
+        return Ok(false);
+    }
+    let mut state = ReauthState::default();
+    while !state.done {
+        reauth_iteration(terminal, app, &mut state)?;
+    }
+    Ok(app.wants_reauth.is_some() && !app.is_locked())
+}
+
+fn reauth_iteration(terminal: &mut dyn Screen, app: &mut App, state: &mut ReauthState) -> Result<(), Box<dyn std::error::Error>> {
+    app.handle_system_events();
+    if app.is_locked() {
+        state.done = true;
         return Ok(());
     }
 
-    app.wants_password_change = false;
-    match run_password_change(terminal, app) {
-        Ok(true) => app.set_message("Password changed successfully", ui::MessageType::Success),
-        Ok(false) => {}
-        Err(e) => app.set_message(&format!("Error: {}", e), ui::MessageType::Error),
+    let working = state.task.as_ref().map(KdfTask::spinner);
+    draw_password_dialog(terminal, " Confirm Password ", "Master password:", &state.password, state.error.as_deref(), None, working)?;
+
+    if let Some(result) = state.task.as_ref().and_then(KdfTask::poll) {
+        state.task = None;
+        finish_reauth_attempt(state, app, result);
+        return Ok(());
     }
+
+    let Some(key) = terminal.poll_key_press()? else { return Ok(()) };
+
+    handle_reauth_key(key, state, app);
     Ok(())
 }
 
-fn check_auto_lock(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
-    if app.vault.should_auto_lock() {
+fn handle_reauth_key(key: KeyEvent, state: &mut ReauthState, app: &mut App) {
+    if state.task.is_some() {
+        if key.code == KeyCode::Esc {
+            state.task = None;
+            state.error = Some("Cancelled".into());
+        }
+        return;
+    }
+
+    if key.code == KeyCode::Esc {
+        app.wants_reauth = None;
+        state.done = true;
+        return;
+    }
+
+    if key.code == KeyCode::Enter {
+        match app.vault.password_verifier() {
+            Ok(verify) => {
+                let password = password_for_task(&state.password);
+                state.error = None;
+                state.task = Some(KdfTask::spawn(move || verify(&password)));
+            }
+            Err(e) => state.error = Some(change_verify_error_msg(e)),
+        }
+        return;
+    }
+
+    handle_password_key(&mut state.password, key);
+}
+
+fn finish_reauth_attempt(state: &mut ReauthState, app: &mut App, result: vault::VaultResult<()>) {
+    let Err(e) = result else {
+        state.done = true;
+        return;
+    };
+    state.password.clear();
+    state.attempts += 1;
+    if state.attempts >= REAUTH_ATTEMPTS {
         app.lock();
+        state.done = true;
+        return;
     }
+    state.error = Some(format!("{} ({}/{})", change_verify_error_msg(e), state.attempts, REAUTH_ATTEMPTS));
+}
 
-    while app.is_locked() && !app.should_quit {
-        run_unlock(terminal, app)?;
+fn change_password(terminal: &mut dyn Screen, app: &mut App, slot: Option<String>) {
+    let success = match &slot {
+        Some(label) => format!("Key slot '{}' added", label),
+        None => "Password changed successfully".to_string(),
+    };
+    match run_password_change(terminal, app, slot) {
+        Ok(true) => app.set_message(&success, ui::MessageType::Success),
+        Ok(false) => {}
+        Err(e) => app.set_message(&i18n::trf("Error: {}", &[&e]), ui::MessageType::Error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{mpsc, Arc, Mutex};
+
+    use super::*;
+    use tui::script::{ScriptedScreen, SCRIPT_DONE};
+
+    const PASSWORD: &str = "plinth-marmot-cobalt-42";
+
+    #[test]
+    fn test_init_add_search_copy_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = App::new(AppConfig { vault_path: dir.path().join("vault.db"), ..AppConfig::default() });
+        let copied = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&copied);
+        app.copier = Box::new(move |text, _| {
+            sink.lock().unwrap().push(text.to_string());
+            mpsc::channel().1
+        });
+
+        let mut screen = ScriptedScreen::new(100, 30);
+        screen
+            .wait_for("Create Master Password").keys(PASSWORD).press(KeyCode::Enter)
+            .wait_for("Confirm Password").keys(PASSWORD).press(KeyCode::Enter)
+            .wait_for("No credentials").keys("n")
+            .wait_for("New Credential").keys("GitHub")
+            .press(KeyCode::Tab).press(KeyCode::Tab).keys("octocat")
+            .press(KeyCode::Tab).keys("hunter2-but-longer")
+            .press(KeyCode::Enter).wait_for("octocat")
+            .keys("/git").press(KeyCode::Enter)
+            .keys("yy").wait_for("Password copied")
+            .keys("L").wait_for("Unlock Vault");
+
+        let err = run_with_auth(&mut screen, &mut app).unwrap_err();
+        assert_eq!(err.to_string(), SCRIPT_DONE);
+        assert!(app.is_locked());
+        assert_eq!(*copied.lock().unwrap(), ["hunter2-but-longer"]);
+        assert!(screen.contents().contains("Enter master password:"));
+        let fingerprint = app.vault.fingerprint().unwrap().unwrap();
+        assert!(screen.contents().contains(&fingerprint));
     }
-    Ok(())
 }
@@ -0,0 +1,143 @@
+//! Terminal Session
+//!
+//! The interactive flows (init, unlock, the main loop and the password
+//! prompts) draw and read input through `Screen`, so they run the same on
+//! the real terminal and, in tests, on a `TestBackend` fed a script of keys.
+
+use std::io;
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyEvent, KeyEventKind};
+use ratatui::backend::CrosstermBackend;
+use ratatui::{Frame, Terminal};
+
+/// How long a poll waits for input before the caller gets to do other work
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub trait Screen {
+    fn draw(&mut self, render: &mut dyn FnMut(&mut Frame)) -> io::Result<()>;
+
+    /// The next input event, or None if none arrived within the poll interval
+    fn poll_event(&mut self) -> io::Result<Option<Event>>;
+
+    fn poll_key_press(&mut self) -> io::Result<Option<KeyEvent>> {
+        Ok(self.poll_event()?.and_then(as_key_press))
+    }
+}
+
+impl Screen for Terminal<CrosstermBackend<io::Stdout>> {
+    fn draw(&mut self, render: &mut dyn FnMut(&mut Frame)) -> io::Result<()> {
+        Terminal::draw(self, |frame| render(frame))?;
+        Ok(())
+    }
+
+    fn poll_event(&mut self) -> io::Result<Option<Event>> {
+        if !event::poll(POLL_INTERVAL)? {
+            return Ok(None);
+        }
+        Ok(Some(event::read()?))
+    }
+}
+
+fn as_key_press(event: Event) -> Option<KeyEvent> {
+    let Event::Key(key) = event else { return None };
+    (key.kind == KeyEventKind::Press).then_some(key)
+}
+
+#[cfg(test)]
+pub mod script {
+    //! A screen for tests: keys come from a script, and the script can wait
+    //! for text to appear before it goes on, e.g. while a key is derived.
+
+    use std::collections::VecDeque;
+    use std::time::Instant;
+
+    use crossterm::event::{KeyCode, KeyModifiers};
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+
+    /// What `poll_event` fails with once every step has been played
+    pub const SCRIPT_DONE: &str = "end of script";
+
+    /// How long `wait_for` gives the screen to show its text
+    const WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    enum Step {
+        Key(KeyEvent),
+        WaitFor(String),
+    }
+
+    pub struct ScriptedScreen {
+        terminal: Terminal<TestBackend>,
+        steps: VecDeque<Step>,
+        waiting_since: Option<Instant>,
+    }
+
+    impl ScriptedScreen {
+        pub fn new(width: u16, height: u16) -> Self {
+            let terminal = Terminal::new(TestBackend::new(width, height)).expect("test backend");
+            Self { terminal, steps: VecDeque::new(), waiting_since: None }
+        }
+
+        /// Type each character of `text`
+        pub fn keys(&mut self, text: &str) -> &mut Self {
+            for c in text.chars() {
+                let modifiers = if c.is_uppercase() { KeyModifiers::SHIFT } else { KeyModifiers::NONE };
+                self.steps.push_back(Step::Key(KeyEvent::new(KeyCode::Char(c), modifiers)));
+            }
+            self
+        }
+
+        pub fn press(&mut self, code: KeyCode) -> &mut Self {
+            self.steps.push_back(Step::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+            self
+        }
+
+        /// Hold back the next keys until the screen shows `text`
+        pub fn wait_for(&mut self, text: &str) -> &mut Self {
+            self.steps.push_back(Step::WaitFor(text.to_string()));
+            self
+        }
+
+        /// The last frame drawn, one line per row
+        pub fn contents(&self) -> String {
+            let buffer = self.terminal.backend().buffer();
+            let width = buffer.area.width as usize;
+            let symbols: Vec<&str> = buffer.content.iter().map(|cell| cell.symbol()).collect();
+            symbols.chunks(width).map(|row| row.concat().trim_end().to_string()).collect::<Vec<_>>().join("\n")
+        }
+    }
+
+    impl Screen for ScriptedScreen {
+        fn draw(&mut self, render: &mut dyn FnMut(&mut Frame)) -> io::Result<()> {
+            self.terminal.draw(|frame| render(frame))?;
+            Ok(())
+        }
+
+        fn poll_event(&mut self) -> io::Result<Option<Event>> {
+            match self.steps.front() {
+                None => Err(io::Error::other(SCRIPT_DONE)),
+                Some(Step::Key(key)) => {
+                    let key = *key;
+                    self.steps.pop_front();
+                    Ok(Some(Event::Key(key)))
+                }
+                Some(Step::WaitFor(text)) if self.contents().contains(text.as_str()) => {
+                    self.steps.pop_front();
+                    self.waiting_since = None;
+                    Ok(None)
+                }
+                Some(Step::WaitFor(text)) => {
+                    let since = *self.waiting_since.get_or_insert_with(Instant::now);
+                    if since.elapsed() > WAIT_TIMEOUT {
+                        let msg = format!("Timed out waiting for {:?} on screen:\n{}", text, self.contents());
+                        return Err(io::Error::other(msg));
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                    Ok(None)
+                }
+            }
+        }
+    }
+}
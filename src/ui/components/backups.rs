@@ -0,0 +1,157 @@
+//! Backups popup and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::vault::backup::BackupEntry;
+
+use super::layout::{
+    centered_rect_fixed, create_popup_block, highlight_row, render_empty_message, render_footer,
+    render_separator_line, truncate_with_ellipsis,
+};
+use super::scroll::{render_v_scroll_indicator, ScrollState};
+
+const TIMESTAMP_WIDTH: u16 = 22;
+const SIZE_WIDTH: u16 = 9;
+
+#[derive(Default)]
+pub struct BackupsState {
+    pub scroll: ScrollState,
+    pub entries: Vec<BackupEntry>,
+    pub selected: usize,
+}
+
+impl BackupsState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_entries(&mut self, entries: Vec<BackupEntry>) {
+        self.entries = entries;
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        self.scroll.reset();
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.selected < self.entries.len().saturating_sub(1) {
+            self.selected += 1;
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.selected = self.entries.len().saturating_sub(1);
+    }
+
+    pub fn selected_entry(&self) -> Option<&BackupEntry> {
+        self.entries.get(self.selected)
+    }
+}
+
+pub struct BackupsPopup<'a> {
+    state: &'a BackupsState,
+}
+
+impl<'a> BackupsPopup<'a> {
+    pub fn new(state: &'a BackupsState) -> Self {
+        Self { state }
+    }
+}
+
+impl Widget for BackupsPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let height = (self.state.entries.len() as u16 + 4).min((area.height * 80) / 100).max(8);
+        let popup = centered_rect_fixed(60, height, area, true);
+        Clear.render(popup, buf);
+
+        let block = create_popup_block(" Backups ", Color::Green);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        render_footer(buf, popup, " j/k nav - Enter restore - i inspect - b backup now - q close ");
+
+        if self.state.entries.is_empty() {
+            render_empty_message(inner, buf, "No backups yet");
+            return;
+        }
+
+        // Header takes 2 rows (header + separator)
+        let header_height = 2u16;
+        let list_area_height = inner.height.saturating_sub(header_height) as usize;
+        let max_v = self.state.entries.len().saturating_sub(list_area_height);
+        let needs_v_scroll = max_v > 0;
+
+        render_backups_header(inner, buf);
+        render_separator_line(buf, inner.x, inner.y + 1, inner.width);
+
+        let list_height = if needs_v_scroll {
+            list_area_height.saturating_sub(1)
+        } else {
+            list_area_height
+        };
+        let scroll_offset = calculate_scroll_offset(self.state.selected, list_height);
+
+        for (i, entry) in self.state.entries.iter().enumerate().skip(scroll_offset).take(list_height) {
+            let y = inner.y + header_height + (i - scroll_offset) as u16;
+            render_backup_row(inner, buf, y, entry, i == self.state.selected);
+        }
+
+        let indicator_area = Rect::new(
+            inner.x,
+            inner.y + header_height,
+            inner.width,
+            inner.height.saturating_sub(header_height),
+        );
+        if needs_v_scroll {
+            render_v_scroll_indicator(buf, &indicator_area, scroll_offset, max_v, Color::Green);
+        }
+    }
+}
+
+fn calculate_scroll_offset(selected: usize, visible: usize) -> usize {
+    if selected >= visible { selected - visible + 1 } else { 0 }
+}
+
+fn render_backups_header(inner: Rect, buf: &mut Buffer) {
+    let style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    buf.set_string(inner.x, inner.y, "CREATED", style);
+    buf.set_string(inner.x + TIMESTAMP_WIDTH, inner.y, "REASON", style);
+    buf.set_string(inner.x + inner.width.saturating_sub(SIZE_WIDTH - 5), inner.y, "SIZE", style);
+}
+
+fn render_backup_row(inner: Rect, buf: &mut Buffer, y: u16, entry: &BackupEntry, is_cursor: bool) {
+    if is_cursor {
+        highlight_row(buf, inner.x, y, inner.width);
+    }
+    let style = |color: Color| {
+        let style = Style::default().fg(color);
+        if is_cursor { style.bg(Color::DarkGray) } else { style }
+    };
+
+    let timestamp = entry.created.format("%d-%b-%Y %H:%M:%S").to_string();
+    let reason_width = inner.width.saturating_sub(TIMESTAMP_WIDTH + SIZE_WIDTH) as usize;
+    let size = format!("{:>width$}", format_size(entry.size), width = SIZE_WIDTH as usize);
+
+    buf.set_string(inner.x, y, &timestamp, style(Color::Magenta));
+    buf.set_string(inner.x + TIMESTAMP_WIDTH, y, truncate_with_ellipsis(&entry.reason, reason_width), style(Color::White));
+    buf.set_string(inner.x + inner.width.saturating_sub(SIZE_WIDTH), y, &size, style(Color::Cyan));
+}
+
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{} B", b),
+    }
+}
@@ -0,0 +1,88 @@
+//! Connection string popup and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::vault::connection::Scheme;
+
+use super::layout::{centered_rect_fixed, create_popup_block, highlight_row, render_footer, truncate_with_ellipsis};
+
+const NAME_WIDTH: u16 = 14;
+
+/// The schemes on offer, each with a preview whose password is masked
+#[derive(Default)]
+pub struct ConnectionState {
+    pub options: Vec<(Scheme, String)>,
+    pub selected: usize,
+}
+
+impl ConnectionState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_options(&mut self, options: Vec<(Scheme, String)>) {
+        self.options = options;
+        self.selected = 0;
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected < self.options.len().saturating_sub(1) {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected_scheme(&self) -> Option<Scheme> {
+        self.options.get(self.selected).map(|(scheme, _)| *scheme)
+    }
+}
+
+pub struct ConnectionPopup<'a> {
+    state: &'a ConnectionState,
+}
+
+impl<'a> ConnectionPopup<'a> {
+    pub fn new(state: &'a ConnectionState) -> Self {
+        Self { state }
+    }
+}
+
+impl Widget for ConnectionPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let height = (self.state.options.len() as u16 + 2).min(area.height);
+        let popup = centered_rect_fixed(72, height, area, true);
+        Clear.render(popup, buf);
+
+        let block = create_popup_block(" Copy as Connection String ", Color::Cyan);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        render_footer(buf, popup, " j/k nav - Enter copy - q close ");
+
+        for (i, (scheme, preview)) in self.state.options.iter().take(inner.height as usize).enumerate() {
+            let y = inner.y + i as u16;
+            if i == self.state.selected {
+                highlight_row(buf, inner.x, y, inner.width);
+            }
+            render_option_row(inner, buf, y, i, *scheme, preview);
+        }
+    }
+}
+
+fn render_option_row(inner: Rect, buf: &mut Buffer, y: u16, index: usize, scheme: Scheme, preview: &str) {
+    let name = format!("{} {}", index + 1, scheme.display_name());
+    let name_style = Style::default().fg(Color::White).add_modifier(Modifier::BOLD);
+    buf.set_string(inner.x + 1, y, name, name_style);
+
+    let preview_width = inner.width.saturating_sub(NAME_WIDTH + 1) as usize;
+    let preview = truncate_with_ellipsis(preview, preview_width);
+    buf.set_string(inner.x + 1 + NAME_WIDTH, y, preview, Style::default().fg(Color::Gray));
+}
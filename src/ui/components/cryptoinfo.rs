@@ -0,0 +1,158 @@
+//! Crypto info popup and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::vault::cryptoinfo::{CryptoInfo, CryptoRecord};
+
+use super::layout::{
+    centered_rect_fixed, create_popup_block, highlight_row, render_footer, render_separator_line,
+    truncate_with_ellipsis,
+};
+use super::scroll::{render_v_scroll_indicator, ScrollState};
+
+const NAME_WIDTH: u16 = 30;
+
+#[derive(Default)]
+pub struct CryptoInfoState {
+    pub scroll: ScrollState,
+    pub info: CryptoInfo,
+    pub selected: usize,
+}
+
+impl CryptoInfoState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_info(&mut self, info: CryptoInfo) {
+        self.info = info;
+        self.selected = self.selected.min(self.info.records.len().saturating_sub(1));
+        self.scroll.reset();
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.selected < self.info.records.len().saturating_sub(1) {
+            self.selected += 1;
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.selected = self.info.records.len().saturating_sub(1);
+    }
+}
+
+pub struct CryptoInfoPopup<'a> {
+    state: &'a CryptoInfoState,
+    migrating: bool,
+}
+
+impl<'a> CryptoInfoPopup<'a> {
+    pub fn new(state: &'a CryptoInfoState) -> Self {
+        Self { state, migrating: false }
+    }
+
+    pub fn migrating(mut self, migrating: bool) -> Self {
+        self.migrating = migrating;
+        self
+    }
+}
+
+impl Widget for CryptoInfoPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let records = &self.state.info.records;
+        let height = (records.len() as u16 + 5).min((area.height * 80) / 100).max(8);
+        let popup = centered_rect_fixed(72, height, area, true);
+        Clear.render(popup, buf);
+
+        let block = create_popup_block(" Crypto ", Color::Cyan);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        render_footer(buf, popup, " j/k nav - q close ");
+
+        // Summary, header and separator take 3 rows
+        let header_height = 3u16;
+        render_summary(inner, buf, &self.state.info, self.migrating);
+        render_header(inner, buf);
+        render_separator_line(buf, inner.x, inner.y + 2, inner.width);
+
+        let list_area_height = inner.height.saturating_sub(header_height) as usize;
+        let max_v = records.len().saturating_sub(list_area_height);
+        let needs_v_scroll = max_v > 0;
+        let list_height = if needs_v_scroll {
+            list_area_height.saturating_sub(1)
+        } else {
+            list_area_height
+        };
+        let scroll_offset = calculate_scroll_offset(self.state.selected, list_height);
+
+        for (i, record) in records.iter().enumerate().skip(scroll_offset).take(list_height) {
+            let y = inner.y + header_height + (i - scroll_offset) as u16;
+            render_record_row(inner, buf, y, record, i == self.state.selected);
+        }
+
+        let indicator_area = Rect::new(
+            inner.x,
+            inner.y + header_height,
+            inner.width,
+            inner.height.saturating_sub(header_height),
+        );
+        if needs_v_scroll {
+            render_v_scroll_indicator(buf, &indicator_area, scroll_offset, max_v, Color::Cyan);
+        }
+    }
+}
+
+fn calculate_scroll_offset(selected: usize, visible: usize) -> usize {
+    if selected >= visible { selected - visible + 1 } else { 0 }
+}
+
+fn render_summary(inner: Rect, buf: &mut Buffer, info: &CryptoInfo, migrating: bool) {
+    let summary = match (info.outdated, migrating) {
+        (0, _) => "All credentials use the current format".to_string(),
+        (n, true) => format!("{} ciphertext(s) in an older format, re-encrypting", n),
+        (n, false) => format!("{} ciphertext(s) in an older format", n),
+    };
+    let color = if info.outdated == 0 { Color::Green } else { Color::Yellow };
+    let summary = truncate_with_ellipsis(&summary, inner.width as usize);
+    buf.set_string(inner.x, inner.y, summary, Style::default().fg(color));
+}
+
+fn render_header(inner: Rect, buf: &mut Buffer) {
+    let style = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+    buf.set_string(inner.x, inner.y + 1, "RECORD", style);
+    buf.set_string(inner.x + NAME_WIDTH, inner.y + 1, "ALGORITHM", style);
+}
+
+fn render_record_row(inner: Rect, buf: &mut Buffer, y: u16, record: &CryptoRecord, is_cursor: bool) {
+    if is_cursor {
+        highlight_row(buf, inner.x, y, inner.width);
+    }
+    let style = |color: Color| {
+        let style = Style::default().fg(color);
+        if is_cursor { style.bg(Color::DarkGray) } else { style }
+    };
+
+    let name_color = if record.key { Color::Magenta } else { Color::White };
+    let name = truncate_with_ellipsis(&record.name, NAME_WIDTH.saturating_sub(1) as usize);
+    buf.set_string(inner.x, y, name, style(name_color));
+
+    let formats: Vec<String> = record.formats.iter().map(|format| format.label()).collect();
+    let format_color = if record.is_current() { Color::Gray } else { Color::Yellow };
+    let width = inner.width.saturating_sub(NAME_WIDTH) as usize;
+    let formats = truncate_with_ellipsis(&formats.join(" + "), width);
+    buf.set_string(inner.x + NAME_WIDTH, y, formats, style(format_color));
+}
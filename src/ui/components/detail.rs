@@ -1,6 +1,7 @@
 //! Detail View Component
 //!
-//! Displays credential details in a panel.
+//! Displays credential details in a panel. Content taller than the panel
+//! scrolls, with an indicator while part of it is out of view.
 
 use ratatui::{
     buffer::Buffer,
@@ -10,37 +11,116 @@ use ratatui::{
     widgets::{Block, Borders, BorderType, Paragraph, Widget, Wrap},
 };
 
-use crate::db::models::CredentialType;
+use secrecy::{ExposeSecret, SecretString};
+use zeroize::Zeroizing;
+
+use chrono::Utc;
+
+use crate::crypto::{Certificate, SshPublicKey};
+use crate::db::models::{AuditAction, CredentialType};
+use crate::i18n;
+use crate::ui::theme;
+
+use super::layout::privacy_mask;
+use super::scroll::render_v_scroll_indicator;
+
+/// How much of the secret the detail view shows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SecretDisplay {
+    #[default]
+    Masked,
+    /// Only the last few characters, to check it is the expected one
+    LastFour,
+    Full,
+}
+
+/// Characters shown by `SecretDisplay::LastFour`
+const REVEALED_CHARS: usize = 4;
+/// Created, updated and used lines
+const TIMESTAMP_ROWS: usize = 3;
 
 #[derive(Debug, Clone)]
 pub struct CredentialDetail {
     pub name: String,
     pub credential_type: CredentialType,
+    /// Type name and icon, a custom type's own where it has them
+    pub type_name: String,
+    pub type_icon: String,
     pub username: Option<String>,
-    pub secret: Option<String>,
-    pub secret_visible: bool,
+    pub secret: Option<SecretString>,
+    pub secret_display: SecretDisplay,
     pub url: Option<String>,
-    pub notes: Option<String>,
+    pub notes: Option<SecretString>,
     pub tags: Vec<String>,
+    pub password_policy: Option<String>,
+    pub env_var: Option<String>,
+    pub alias: Option<String>,
+    /// Public half of an SSH key, shown by its fingerprint
+    pub public_key: Option<SshPublicKey>,
+    /// Parsed from the secret of a certificate credential
+    pub certificate: Option<Certificate>,
+    /// Labels and values of the type's attributes that are set
+    pub attributes: Vec<(String, String)>,
     pub created_at: String,
     pub updated_at: String,
+    /// Last view or copy, if ever
+    pub accessed_at: Option<String>,
+    pub view_count: usize,
+    pub copy_count: usize,
     pub totp_code: Option<String>,
     pub totp_remaining: Option<u64>,
+    /// Latest audit events for this credential, newest first
+    pub activity: Vec<ActivityEntry>,
+    /// The secret is in the offline breach index
+    pub breached: bool,
+    /// The secret stays encrypted until the credential is opened
+    pub encrypted: bool,
+}
+
+/// One audit event in the detail view's activity section
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivityEntry {
+    pub at: String,
+    pub action: AuditAction,
+    /// What was read, copied or changed, e.g. "Secret"
+    pub details: Option<String>,
 }
 
 pub struct DetailView<'a> {
     detail: &'a CredentialDetail,
+    preview: bool,
+    private: bool,
+    v_scroll: usize,
 }
 
 impl<'a> DetailView<'a> {
     pub fn new(detail: &'a CredentialDetail) -> Self {
-        Self { detail }
+        Self { detail, preview: false, private: false, v_scroll: 0 }
+    }
+
+    /// Rows scrolled past at the top, clamped to the content
+    pub fn scroll(mut self, v_scroll: usize) -> Self {
+        self.v_scroll = v_scroll;
+        self
+    }
+
+    /// Privacy mode: mask the username and URL and keep the secret hidden
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    /// Passive preview beside the list: the secret stays masked and the
+    /// TOTP code hidden until the credential is opened
+    pub fn preview(mut self, preview: bool) -> Self {
+        self.preview = preview;
+        self
     }
 }
 
 fn render_field(buf: &mut Buffer, x: u16, y: &mut u16, _width: u16, label: &str, value: &[Span]) {
-    let label_style = Style::default().fg(Color::DarkGray);
-    buf.set_string(x, *y, format!("{}:", label), label_style);
+    let label_style = Style::default().fg(theme::muted());
+    buf.set_string(x, *y, format!("{}:", i18n::tr(label)), label_style);
 
     let value_x = x + 12;
     let line = Line::from(value.to_vec());
@@ -76,9 +156,9 @@ fn render_type_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, detail:
     let color = type_color(detail.credential_type);
     let value_style = Style::default().fg(Color::White);
     render_field(buf, x, y, width, "Type", &[
-        Span::styled(detail.credential_type.icon(), Style::default().fg(color)),
+        Span::styled(detail.type_icon.as_str(), Style::default().fg(color)),
         Span::raw(" "),
-        Span::styled(detail.credential_type.display_name(), value_style),
+        Span::styled(detail.type_name.as_str(), value_style),
     ]);
 }
 
@@ -87,14 +167,30 @@ fn render_username_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, user
     render_field(buf, x, y, width, "Username", &[Span::styled(username, value_style)]);
 }
 
-fn render_secret_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, secret: &str, visible: bool) {
+/// The secret as drawn. Secrets too short to keep most of them hidden
+/// stay fully masked under `LastFour`.
+fn displayed_secret(secret: &str, display: SecretDisplay) -> Zeroizing<String> {
+    let secret = secret.trim_end();
+    let len = secret.chars().count();
+    Zeroizing::new(match display {
+        SecretDisplay::Full => secret.to_string(),
+        SecretDisplay::LastFour if len >= 2 * REVEALED_CHARS => {
+            let mut shown = "•".repeat((len - REVEALED_CHARS).min(16));
+            shown.extend(secret.chars().skip(len - REVEALED_CHARS));
+            shown
+        }
+        _ => "•".repeat(len.min(20)),
+    })
+}
+
+fn render_secret_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, secret: &str, display: SecretDisplay, breached: bool) {
     let secret_style = Style::default().fg(Color::Yellow);
-    let display_secret = if visible {
-        secret.to_string()
-    } else {
-        "•".repeat(secret.len().min(20))
-    };
-    render_field(buf, x, y, width, "Secret", &[Span::styled(display_secret, secret_style)]);
+    let display_secret = displayed_secret(secret, display);
+    let mut spans = vec![Span::styled(display_secret.as_str(), secret_style)];
+    if breached {
+        spans.push(Span::styled(format!("  {}", i18n::tr("breached")), Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
+    }
+    render_field(buf, x, y, width, "Secret", &spans);
 }
 
 fn render_strength_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, secret: &str) {
@@ -113,92 +209,394 @@ fn render_totp_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, code: &s
     ]);
 }
 
+fn render_public_key_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, key: &SshPublicKey) {
+    render_field(buf, x, y, width, "Key", &[
+        Span::styled(key.summary(), Style::default().fg(Color::Cyan)),
+    ]);
+}
+
+fn render_certificate_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, cert: &Certificate, field: DetailField) {
+    let value_style = Style::default().fg(Color::White);
+    match field {
+        DetailField::Subject => render_field(buf, x, y, width, "Subject", &[Span::styled(cert.subject.as_str(), value_style)]),
+        DetailField::Issuer => render_field(buf, x, y, width, "Issuer", &[Span::styled(cert.issuer.as_str(), value_style)]),
+        DetailField::Names => render_field(buf, x, y, width, "Names", &[Span::styled(cert.names.join(", "), value_style)]),
+        _ => {
+            let now = Utc::now();
+            let color = match (cert.is_expired(now), cert.expires_soon(now)) {
+                (true, _) => Color::Red,
+                (false, true) => Color::Yellow,
+                _ => Color::Green,
+            };
+            render_field(buf, x, y, width, "Expires", &[Span::styled(cert.expiry_summary(now), Style::default().fg(color))]);
+        }
+    }
+}
+
 fn render_url_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, url: &str) {
     render_field(buf, x, y, width, "URL", &[
         Span::styled(url, Style::default().fg(Color::Blue)),
     ]);
 }
 
+fn render_policy_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, policy: &str) {
+    render_field(buf, x, y, width, "Policy", &[
+        Span::styled(policy, Style::default().fg(Color::Yellow)),
+    ]);
+}
+
+fn render_env_var_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, env_var: &str) {
+    render_field(buf, x, y, width, "Env var", &[
+        Span::styled(format!("${}", env_var), Style::default().fg(Color::Cyan)),
+    ]);
+}
+
+fn render_alias_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, alias: &str) {
+    render_field(buf, x, y, width, "Alias", &[
+        Span::styled(alias, Style::default().fg(Color::Cyan)),
+    ]);
+}
+
 fn render_tags_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, tags: &[String]) {
     let tag_spans: Vec<Span> = tags
         .iter()
-        .flat_map(|tag| vec![
-            Span::styled(format!("#{}", tag), Style::default().fg(Color::Magenta)),
-            Span::raw(" "),
-        ])
+        .flat_map(|tag| {
+            let span = match theme::is_styled_tag(tag) {
+                true => theme::tag_span(tag, Style::default()),
+                false => Span::styled(format!("#{}", tag), Style::default().fg(Color::Magenta)),
+            };
+            vec![span, Span::raw(" ")]
+        })
         .collect();
     render_field(buf, x, y, width, "Tags", &tag_spans);
 }
 
+fn notes_paragraph(notes: &str) -> Paragraph<'_> {
+    Paragraph::new(notes)
+        .style(Style::default().fg(Color::Gray))
+        .wrap(Wrap { trim: true })
+}
+
 fn render_notes_section(buf: &mut Buffer, inner: &Rect, y: &mut u16, notes: &str) {
     let label_style = Style::default().fg(Color::DarkGray);
     buf.set_string(inner.x, *y, "Notes:", label_style);
     *y += 1;
 
-    let note_area = Rect::new(inner.x, *y, inner.width, inner.height.saturating_sub(*y - inner.y));
-    let note_widget = Paragraph::new(notes)
-        .style(Style::default().fg(Color::Gray))
-        .wrap(Wrap { trim: true });
-    note_widget.render(note_area, buf);
+    let paragraph = notes_paragraph(notes);
+    let height = paragraph.line_count(inner.width) as u16;
+    let note_area = Rect::new(inner.x, *y, inner.width, height.min(inner.bottom().saturating_sub(*y)));
+    paragraph.render(note_area, buf);
+    *y += height;
 }
 
-fn render_timestamps(buf: &mut Buffer, inner: &Rect, y: u16, created: &str, updated: &str) {
-    let footer_y = inner.y + inner.height.saturating_sub(2);
-    if footer_y <= y {
-        return;
+fn action_color(action: AuditAction) -> Color {
+    match action {
+        AuditAction::Copy => Color::Yellow,
+        AuditAction::Create | AuditAction::Update => Color::Green,
+        AuditAction::Delete => Color::Red,
+        _ => Color::Blue,
+    }
+}
+
+/// Compact timeline of the latest events; details are left out in
+/// privacy mode, as they can name a URL
+fn render_activity_section(buf: &mut Buffer, inner: &Rect, y: &mut u16, activity: &[ActivityEntry], private: bool) {
+    let label_style = Style::default().fg(Color::DarkGray);
+    buf.set_string(inner.x, *y, format!("{}:", i18n::tr("Activity")), label_style);
+    *y += 1;
+
+    for entry in activity {
+        let mut spans = vec![
+            Span::styled(format!("{}  ", entry.at), Style::default().fg(Color::DarkGray)),
+            Span::styled(format!("{:<7}", entry.action.as_str()), Style::default().fg(action_color(entry.action))),
+        ];
+        if let Some(details) = entry.details.as_deref().filter(|_| !private) {
+            spans.push(Span::styled(format!(" {}", details), Style::default().fg(Color::Gray)));
+        }
+        buf.set_line(inner.x, *y, &Line::from(spans), inner.width);
+        *y += 1;
     }
+}
+
+/// Pinned to the bottom of `inner`, or one row below `y` when the
+/// content reaches past that
+fn render_timestamps(buf: &mut Buffer, inner: &Rect, y: u16, detail: &CredentialDetail) {
+    let footer_y = (inner.y + inner.height.saturating_sub(TIMESTAMP_ROWS as u16)).max(y + 1);
     let style = Style::default().fg(Color::DarkGray);
-    buf.set_string(inner.x, footer_y, format!("Created: {}", created), style);
-    buf.set_string(inner.x, footer_y + 1, format!("Updated: {}", updated), style);
+    buf.set_string(inner.x, footer_y, format!("Created: {}", detail.created_at), style);
+    buf.set_string(inner.x, footer_y + 1, format!("Updated: {}", detail.updated_at), style);
+    buf.set_string(inner.x, footer_y + 2, access_summary(detail), style);
+}
+
+fn access_summary(detail: &CredentialDetail) -> String {
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    let counts = format!("{}, {}", plural(detail.view_count, "view"), plural(detail.copy_count, "copy"));
+    match &detail.accessed_at {
+        Some(at) => format!("Used:    {} ({})", at, counts),
+        None => "Used:    never".to_string(),
+    }
 }
 
-fn render_detail_block(area: Rect, buf: &mut Buffer, name: &str) -> Rect {
-    let block = Block::default()
+fn detail_block(name: &str, border_color: Color) -> Block<'static> {
+    Block::default()
         .title(format!(" {} ", name))
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
-        .border_style(Style::default().fg(Color::Magenta));
+        .border_style(Style::default().fg(border_color))
+}
 
+fn render_detail_block(area: Rect, buf: &mut Buffer, name: &str, border_color: Color) -> Rect {
+    let block = detail_block(name, border_color);
     let inner = block.inner(area);
     block.render(area, buf);
     inner
 }
 
-impl<'a> Widget for DetailView<'a> {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        let inner = render_detail_block(area, buf, &self.detail.name);
-        let mut y = inner.y;
+/// A row of the detail panel, from the top of its inner area
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetailField {
+    Type,
+    Username,
+    Secret,
+    Strength,
+    Totp,
+    /// SSH key type and fingerprint; copies the public key
+    PublicKey,
+    /// The type attribute at this index of `CredentialDetail::attributes`
+    Attribute(usize),
+    /// Rows of a certificate credential
+    Subject,
+    Issuer,
+    /// Subject alternative names
+    Names,
+    Expires,
+    Url,
+    Policy,
+    EnvVar,
+    Alias,
+    Tags,
+    /// The notes section below the field rows
+    Notes,
+}
 
-        render_type_field(buf, inner.x, &mut y, inner.width, self.detail);
+impl CredentialDetail {
+    /// The field rows shown for this credential, in display order
+    pub fn field_rows(&self, preview: bool) -> Vec<DetailField> {
+        let mut rows = vec![DetailField::Type];
+        if self.username.is_some() {
+            rows.push(DetailField::Username);
+        }
+        if self.encrypted {
+            rows.push(DetailField::Secret);
+        } else if self.secret.is_some() {
+            rows.push(DetailField::Secret);
+            if self.credential_type == CredentialType::Password {
+                rows.push(DetailField::Strength);
+            }
+        }
+        if self.totp_code.is_some() && self.totp_remaining.is_some() && !preview {
+            rows.push(DetailField::Totp);
+        }
+        if self.public_key.is_some() && self.credential_type == CredentialType::SshKey {
+            rows.push(DetailField::PublicKey);
+        }
+        rows.extend((0..self.attributes.len()).map(DetailField::Attribute));
+        if let Some(cert) = &self.certificate {
+            rows.extend([DetailField::Subject, DetailField::Issuer]);
+            if !cert.names.is_empty() {
+                rows.push(DetailField::Names);
+            }
+            rows.push(DetailField::Expires);
+        }
+        if self.url.is_some() {
+            rows.push(DetailField::Url);
+        }
+        if self.password_policy.is_some() {
+            rows.push(DetailField::Policy);
+        }
+        if self.env_var.is_some() {
+            rows.push(DetailField::EnvVar);
+        }
+        if self.alias.is_some() {
+            rows.push(DetailField::Alias);
+        }
+        if !self.tags.is_empty() {
+            rows.push(DetailField::Tags);
+        }
+        rows
+    }
 
-        if let Some(ref username) = self.detail.username {
-            render_username_field(buf, inner.x, &mut y, inner.width, username);
+    /// The field on a row of the full detail panel drawn in `area`,
+    /// scrolled by `v_scroll` rows
+    pub fn field_at(&self, area: Rect, v_scroll: usize, column: u16, row: u16) -> Option<DetailField> {
+        let inner = detail_block(&self.name, Color::Magenta).inner(area);
+        let inside = column >= inner.x && column < inner.x + inner.width && row >= inner.y && row < inner.bottom();
+        if !inside {
+            return None;
         }
+        let v_scroll = v_scroll.min(self.max_scroll(area));
+        self.field_rows(false).get((row - inner.y) as usize + v_scroll).copied()
+    }
 
-        if let Some(ref secret) = self.detail.secret {
-            render_secret_field(buf, inner.x, &mut y, inner.width, secret, self.detail.secret_visible);
-            if self.detail.credential_type == CredentialType::Password { render_strength_field(buf, inner.x, &mut y, inner.width, secret); }
+    /// Rows the view needs at `width`: the field rows, the notes and
+    /// activity sections and the timestamps, each after a blank row
+    pub fn content_height(&self, width: u16, preview: bool) -> usize {
+        let notes = match &self.notes {
+            Some(notes) => 1 + notes_paragraph(notes.expose_secret()).line_count(width),
+            None => 0,
+        };
+        self.field_rows(preview).len() + 1 + notes + self.activity_height(preview) + 1 + TIMESTAMP_ROWS
+    }
+
+    /// The activity section is left out of the preview
+    fn activity_height(&self, preview: bool) -> usize {
+        if preview || self.activity.is_empty() {
+            return 0;
         }
+        let gap = usize::from(self.notes.is_some());
+        gap + 1 + self.activity.len()
+    }
+
+    /// Rows the full view drawn in `area` can scroll by
+    pub fn max_scroll(&self, area: Rect) -> usize {
+        let inner = detail_block(&self.name, Color::Magenta).inner(area);
+        self.content_height(inner.width, false).saturating_sub(inner.height as usize)
+    }
 
-        if let (Some(code), Some(remaining)) = (&self.detail.totp_code, self.detail.totp_remaining) {
-            render_totp_field(buf, inner.x, &mut y, inner.width, code, remaining);
+    /// The fields `1y`, `2y`, ... copy, numbered from 1 in display order
+    pub fn copy_fields(&self) -> Vec<DetailField> {
+        let copyable = |field: &DetailField| {
+            matches!(
+                field,
+                DetailField::Username
+                    | DetailField::Secret
+                    | DetailField::Totp
+                    | DetailField::PublicKey
+                    | DetailField::Attribute(_)
+                    | DetailField::Url
+                    | DetailField::EnvVar
+            )
+        };
+        let mut fields: Vec<DetailField> = self.field_rows(false).into_iter().filter(copyable).collect();
+        if self.notes.is_some() {
+            fields.push(DetailField::Notes);
         }
+        fields
+    }
+}
 
-        if let Some(ref url) = self.detail.url {
-            render_url_field(buf, inner.x, &mut y, inner.width, url);
+/// Dim copy number beside the label of a copyable field
+fn render_copy_number(buf: &mut Buffer, x: u16, y: u16, number: usize) {
+    buf.set_string(x + 10, y, number.to_string(), Style::default().fg(Color::DarkGray));
+}
+
+fn render_field_row(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, view: &DetailView, field: DetailField) {
+    let detail = view.detail;
+    let shown = |value: &Option<String>| {
+        let value = value.as_deref().unwrap_or_default();
+        if view.private { privacy_mask(value) } else { value.to_string() }
+    };
+    match field {
+        DetailField::Type => render_type_field(buf, x, y, width, detail),
+        DetailField::Username => render_username_field(buf, x, y, width, &shown(&detail.username)),
+        DetailField::Secret if detail.encrypted => render_field(buf, x, y, width, "Secret", &[
+            Span::styled(i18n::tr("not decrypted - Enter to open"), Style::default().fg(Color::DarkGray)),
+        ]),
+        DetailField::Secret => {
+            let secret = detail.secret.as_ref().map(|s| s.expose_secret()).unwrap_or_default();
+            let display = if view.preview || view.private { SecretDisplay::Masked } else { detail.secret_display };
+            render_secret_field(buf, x, y, width, secret, display, detail.breached);
+        }
+        DetailField::Strength => {
+            render_strength_field(buf, x, y, width, detail.secret.as_ref().map(|s| s.expose_secret()).unwrap_or_default())
         }
+        DetailField::Totp => {
+            let code = detail.totp_code.as_deref().unwrap_or_default();
+            render_totp_field(buf, x, y, width, code, detail.totp_remaining.unwrap_or_default());
+        }
+        DetailField::PublicKey => {
+            if let Some(key) = &detail.public_key {
+                render_public_key_field(buf, x, y, width, key);
+            }
+        }
+        DetailField::Attribute(i) => {
+            if let Some((label, value)) = detail.attributes.get(i) {
+                render_field(buf, x, y, width, label, &[Span::styled(value.as_str(), Style::default().fg(Color::White))]);
+            }
+        }
+        DetailField::Subject | DetailField::Issuer | DetailField::Names | DetailField::Expires => {
+            if let Some(cert) = &detail.certificate {
+                render_certificate_field(buf, x, y, width, cert, field);
+            }
+        }
+        DetailField::Url => render_url_field(buf, x, y, width, &shown(&detail.url)),
+        DetailField::Policy => render_policy_field(buf, x, y, width, detail.password_policy.as_deref().unwrap_or_default()),
+        DetailField::EnvVar => render_env_var_field(buf, x, y, width, detail.env_var.as_deref().unwrap_or_default()),
+        DetailField::Alias => render_alias_field(buf, x, y, width, detail.alias.as_deref().unwrap_or_default()),
+        DetailField::Tags => render_tags_field(buf, x, y, width, &detail.tags),
+        // Drawn as its own section below the rows
+        DetailField::Notes => {}
+    }
+}
 
-        if !self.detail.tags.is_empty() {
-            render_tags_field(buf, inner.x, &mut y, inner.width, &self.detail.tags);
+impl<'a> Widget for DetailView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let border_color = if self.preview { Color::DarkGray } else { Color::Magenta };
+        let inner = render_detail_block(area, buf, &self.detail.name, border_color);
+        let content_height = self.detail.content_height(inner.width, self.preview);
+        let max_v = content_height.saturating_sub(inner.height as usize);
+        if max_v == 0 {
+            self.render_content(inner, buf);
+            return;
+        }
+
+        // Draw everything off screen, then copy the rows in view. The
+        // preview never scrolls, but still shows that it is cut off.
+        let full = Rect::new(inner.x, 0, inner.width, content_height as u16);
+        let mut content = Buffer::empty(full);
+        self.render_content(full, &mut content);
+        let v_scroll = if self.preview { 0 } else { self.v_scroll.min(max_v) };
+        for row in 0..inner.height {
+            for x in inner.x..inner.right() {
+                if let (Some(from), Some(to)) = (content.cell((x, v_scroll as u16 + row)), buf.cell_mut((x, inner.y + row))) {
+                    *to = from.clone();
+                }
+            }
+        }
+        render_v_scroll_indicator(buf, &inner, v_scroll, max_v, border_color);
+    }
+}
+
+impl DetailView<'_> {
+    fn render_content(&self, inner: Rect, buf: &mut Buffer) {
+        let mut y = inner.y;
+        let copy_fields = if self.preview { Vec::new() } else { self.detail.copy_fields() };
+        let copy_number = |field| copy_fields.iter().position(|f| *f == field).map(|i| i + 1);
+
+        for field in self.detail.field_rows(self.preview) {
+            if let Some(number) = copy_number(field) {
+                render_copy_number(buf, inner.x, y, number);
+            }
+            render_field_row(buf, inner.x, &mut y, inner.width, self, field);
         }
 
         y += 1;
 
         if let Some(ref notes) = self.detail.notes {
-            render_notes_section(buf, &inner, &mut y, notes);
+            if let Some(number) = copy_number(DetailField::Notes) {
+                render_copy_number(buf, inner.x, y, number);
+            }
+            render_notes_section(buf, &inner, &mut y, notes.expose_secret());
         }
 
-        render_timestamps(buf, &inner, y, &self.detail.created_at, &self.detail.updated_at);
+        if !self.preview && !self.detail.activity.is_empty() {
+            if self.detail.notes.is_some() {
+                y += 1;
+            }
+            render_activity_section(buf, &inner, &mut y, &self.detail.activity, self.private);
+        }
+
+        render_timestamps(buf, &inner, y, self.detail);
     }
 }
 
@@ -287,3 +685,145 @@ impl<'a> Widget for TotpDisplay<'a> {
         buf.set_string(time_x, area.y, &time_str, Style::default().fg(Color::DarkGray));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detail() -> CredentialDetail {
+        CredentialDetail {
+            name: "GitHub".to_string(),
+            credential_type: CredentialType::Password,
+            type_name: "Password".to_string(),
+            type_icon: CredentialType::Password.icon().to_string(),
+            username: Some("alice".to_string()),
+            secret: Some(SecretString::from("correct-horse")),
+            secret_display: SecretDisplay::Masked,
+            url: None,
+            notes: Some(SecretString::from("recovery codes in the safe")),
+            tags: vec!["work".to_string()],
+            password_policy: None,
+            env_var: Some("GITHUB_TOKEN".to_string()),
+            alias: None,
+            public_key: None,
+            certificate: None,
+            attributes: Vec::new(),
+            created_at: String::new(),
+            updated_at: String::new(),
+            accessed_at: None,
+            view_count: 0,
+            copy_count: 0,
+            totp_code: None,
+            totp_remaining: None,
+            activity: Vec::new(),
+            breached: false,
+            encrypted: false,
+        }
+    }
+
+    #[test]
+    fn test_copy_fields() {
+        use DetailField::*;
+        assert_eq!(detail().copy_fields(), [Username, Secret, EnvVar, Notes]);
+    }
+
+    #[test]
+    fn test_ssh_key_shows_fingerprint() {
+        use DetailField::*;
+        let line = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDUOrKNG1Wes9Dc8GTM64Dq7WyHM5KhZNVLkRUopgeDg me@laptop";
+        let mut detail = detail();
+        detail.credential_type = CredentialType::SshKey;
+        detail.public_key = Some(SshPublicKey::from_line(line).unwrap());
+        assert_eq!(detail.copy_fields(), [Username, Secret, PublicKey, EnvVar, Notes]);
+
+        let area = Rect::new(0, 0, 80, 20);
+        let mut buf = Buffer::empty(area);
+        DetailView::new(&detail).render(area, &mut buf);
+        let text: String = buf.content().iter().map(|c| c.symbol()).collect();
+        assert!(text.contains("ED25519  SHA256:8DKO+d9N4SiCKOmj0ENdIXzIT4R0K4M/O6HxHa8Hj2Y"));
+    }
+
+    #[test]
+    fn test_private_view_masks_username_and_url() {
+        let mut detail = detail();
+        detail.url = Some("https://github.com".to_string());
+        detail.secret_display = SecretDisplay::Full;
+        let area = Rect::new(0, 0, 60, 20);
+        let text = |view: DetailView| {
+            let mut buf = Buffer::empty(area);
+            view.render(area, &mut buf);
+            buf.content().iter().map(|c| c.symbol()).collect::<String>()
+        };
+        let shown = text(DetailView::new(&detail));
+        assert!(shown.contains("alice") && shown.contains("github.com") && shown.contains("correct-horse"));
+        let private = text(DetailView::new(&detail).private(true));
+        assert!(!private.contains("alice") && !private.contains("github.com") && !private.contains("correct-horse"));
+        assert!(private.contains("GITHUB_TOKEN"));
+    }
+
+    #[test]
+    fn test_activity_timeline() {
+        let mut detail = detail();
+        detail.activity = vec![
+            ActivityEntry { at: "17-Oct 09:12".to_string(), action: AuditAction::Read, details: Some("Open URL https://example.com".to_string()) },
+            ActivityEntry { at: "16-Oct 18:40".to_string(), action: AuditAction::Update, details: None },
+        ];
+        // Blank, "Activity:" and two events after the notes
+        assert_eq!(detail.content_height(58, false), 6 + 1 + 2 + 1 + 1 + 2 + 1 + 3);
+        assert_eq!(detail.content_height(58, true), 6 + 1 + 2 + 1 + 3);
+
+        let area = Rect::new(0, 0, 60, 24);
+        let text = |view: DetailView| {
+            let mut buf = Buffer::empty(area);
+            view.render(area, &mut buf);
+            buf.content().iter().map(|c| c.symbol()).collect::<String>()
+        };
+        let shown = text(DetailView::new(&detail));
+        assert!(shown.contains("17-Oct 09:12  read    Open URL") && shown.contains("update"));
+        assert!(!text(DetailView::new(&detail).preview(true)).contains("Activity:"));
+        let private = text(DetailView::new(&detail).private(true));
+        assert!(private.contains("read") && !private.contains("example.com"));
+    }
+
+    #[test]
+    fn test_long_notes_scroll() {
+        let mut detail = detail();
+        let notes: Vec<String> = (1..=30).map(|i| format!("line{}", i)).collect();
+        detail.notes = Some(SecretString::from(notes.join("\n")));
+        let area = Rect::new(0, 0, 40, 20);
+        // Type, Username, Secret, Strength, EnvVar, Tags, then blank,
+        // "Notes:" and 30 lines, then blank and 3 timestamp rows
+        assert_eq!(detail.content_height(38, false), 6 + 1 + 31 + 1 + 3);
+        assert_eq!(detail.max_scroll(area), 42 - 18);
+
+        let text = |view: DetailView| {
+            let mut buf = Buffer::empty(area);
+            view.render(area, &mut buf);
+            (0..area.height)
+                .map(|y| (0..area.width).map(|x| buf[(x, y)].symbol()).collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let top = text(DetailView::new(&detail));
+        assert!(top.contains("Username") && top.contains("line10") && !top.contains("line12 "));
+        assert!(top.contains("\u{f103}"));
+
+        let bottom = text(DetailView::new(&detail).scroll(usize::MAX));
+        assert!(!bottom.contains("Username") && bottom.contains("line30") && bottom.contains("Created:"));
+        assert!(bottom.contains("\u{f102}"));
+
+        // Clicks land on the field drawn at that row
+        assert_eq!(detail.field_at(area, 0, 1, 2), Some(DetailField::Username));
+        assert_eq!(detail.field_at(area, 1, 1, 1), Some(DetailField::Username));
+        assert_eq!(detail.field_at(area, 1, 1, 19), None);
+    }
+
+    #[test]
+    fn test_displayed_secret() {
+        assert_eq!(*displayed_secret("correct-horse\n", SecretDisplay::LastFour), "•••••••••orse");
+        assert_eq!(*displayed_secret("correct-horse", SecretDisplay::Masked), "•".repeat(13));
+        assert_eq!(*displayed_secret("correct-horse", SecretDisplay::Full), "correct-horse");
+        // Too short to show a part without showing most of it
+        assert_eq!(*displayed_secret("hunter2", SecretDisplay::LastFour), "•".repeat(7));
+    }
+}
@@ -8,35 +8,84 @@ use ratatui::{
     widgets::{Block, BorderType, Borders, Clear, Paragraph, Widget, Wrap},
 };
 
+use crate::crypto::strength::{check_master_password, estimate_entropy, Weakness};
+use crate::i18n;
+use crate::input::line::EditLine;
+
 use super::input_field::InputField;
-use super::layout::{centered_rect_fixed, create_popup_block};
+use super::layout::{centered_rect_fixed, create_popup_block, render_footer};
 
 pub struct ConfirmDialog<'a> {
     title: &'a str,
     message: &'a str,
+    details: &'a [String],
+    /// Text to type before confirming, and what has been typed so far
+    phrase: Option<(&'a str, &'a EditLine)>,
+    countdown: Option<u64>,
 }
 
 impl<'a> ConfirmDialog<'a> {
     pub fn new(title: &'a str, message: &'a str) -> Self {
-        Self { title, message }
+        Self { title, message, details: &[], phrase: None, countdown: None }
+    }
+
+    /// Lines listed under the message, e.g. the fields an edit changes
+    pub fn details(mut self, details: &'a [String]) -> Self {
+        self.details = details;
+        self
+    }
+
+    pub fn phrase(mut self, phrase: &'a str, typed: &'a EditLine) -> Self {
+        self.phrase = Some((phrase, typed));
+        self
+    }
+
+    /// Seconds left before the confirmation cancels itself
+    pub fn countdown(mut self, secs: u64) -> Self {
+        self.countdown = Some(secs);
+        self
     }
 }
 
 impl Widget for ConfirmDialog<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let popup_area = centered_rect_fixed(50, 6, area, true);
+        let extra = match self.details.len() {
+            0 => 0,
+            n => n as u16 + 1,
+        };
+        let height = if self.phrase.is_some() { 8 } else { 6 } + extra;
+        let width = if self.details.is_empty() { 50 } else { 64 };
+        let popup_area = centered_rect_fixed(width, height, area, true);
         Clear.render(popup_area, buf);
 
         let block = create_popup_block(self.title, Color::Yellow);
         let inner = block.inner(popup_area);
         block.render(popup_area, buf);
 
-        Paragraph::new(self.message)
+        if let Some(secs) = self.countdown {
+            render_footer(buf, popup_area, &format!(" cancels in {}s ", secs));
+        }
+
+        Paragraph::new(i18n::tr(self.message))
             .style(Style::default().fg(Color::White))
             .wrap(Wrap { trim: true })
             .render(Rect::new(inner.x, inner.y, inner.width, 2), buf);
 
-        render_confirm_hint(buf, inner.x, inner.y + 3, inner.width);
+        let style = Style::default().fg(Color::Gray);
+        for (i, line) in self.details.iter().enumerate() {
+            buf.set_stringn(inner.x + 1, inner.y + 3 + i as u16, line, inner.width.saturating_sub(1) as usize, style);
+        }
+        let y = inner.y + extra;
+
+        let Some((phrase, typed)) = self.phrase else {
+            render_confirm_hint(buf, inner.x, y + 3, inner.width);
+            return;
+        };
+        let prompt = format!("Type '{}' and press Enter:", phrase);
+        let input_rect = Rect::new(inner.x, y + 2, inner.width, 2);
+        InputField::new(&prompt, typed).render(input_rect, buf);
+        let color = if typed.as_str() == phrase { Color::Green } else { Color::DarkGray };
+        buf.set_string(inner.x, y + 5, "Enter confirm - Esc cancel", Style::default().fg(color));
     }
 }
 
@@ -76,7 +125,7 @@ impl Widget for MessagePopup<'_> {
         Clear.render(popup_area, buf);
 
         let block = Block::default()
-            .title(self.title)
+            .title(i18n::tr_padded(self.title))
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
             .border_style(self.style)
@@ -84,7 +133,7 @@ impl Widget for MessagePopup<'_> {
         let inner = block.inner(popup_area);
         block.render(popup_area, buf);
 
-        Paragraph::new(self.message)
+        Paragraph::new(i18n::tr(self.message))
             .style(Style::default().fg(Color::White))
             .wrap(Wrap { trim: true })
             .render(inner, buf);
@@ -94,25 +143,72 @@ impl Widget for MessagePopup<'_> {
 pub struct PasswordDialog<'a> {
     title: &'a str,
     prompt: &'a str,
-    value: &'a str,
-    cursor: usize,
+    line: &'a EditLine,
     error: Option<&'a str>,
+    /// Entropy the password needs, when a new one is being chosen
+    min_entropy: Option<f64>,
+    revealed: bool,
+    caps_lock: bool,
+    /// Spinner frame while the key is being derived
+    working: Option<char>,
+    /// Which vault the password is for
+    fingerprint: Option<&'a str>,
 }
 
 impl<'a> PasswordDialog<'a> {
-    pub fn new(title: &'a str, prompt: &'a str, value: &'a str, cursor: usize) -> Self {
-        Self { title, prompt, value, cursor, error: None }
+    pub fn new(title: &'a str, prompt: &'a str, line: &'a EditLine) -> Self {
+        Self {
+            title,
+            prompt,
+            line,
+            error: None,
+            min_entropy: None,
+            revealed: false,
+            caps_lock: false,
+            working: None,
+            fingerprint: None,
+        }
     }
 
     pub fn error(mut self, err: &'a str) -> Self {
         self.error = Some(err);
         self
     }
+
+    /// Show a live strength bar measured against this minimum entropy
+    pub fn strength(mut self, min_entropy: f64) -> Self {
+        self.min_entropy = Some(min_entropy);
+        self
+    }
+
+    /// Show the typed password instead of asterisks
+    pub fn revealed(mut self, revealed: bool) -> Self {
+        self.revealed = revealed;
+        self
+    }
+
+    pub fn caps_lock(mut self, on: bool) -> Self {
+        self.caps_lock = on;
+        self
+    }
+
+    /// Show progress instead of the error while the key is derived
+    pub fn working(mut self, spinner: char) -> Self {
+        self.working = Some(spinner);
+        self
+    }
+
+    /// Show the vault fingerprint on the top border
+    pub fn fingerprint(mut self, fingerprint: Option<&'a str>) -> Self {
+        self.fingerprint = fingerprint;
+        self
+    }
 }
 
 impl Widget for PasswordDialog<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let height = if self.error.is_some() { 7 } else { 6 };
+        let has_status = self.error.is_some() || self.working.is_some();
+        let height = 6 + u16::from(has_status) + u16::from(self.min_entropy.is_some());
         let popup_area = centered_rect_fixed(40, height, area, false);
         Clear.render(popup_area, buf);
 
@@ -120,13 +216,68 @@ impl Widget for PasswordDialog<'_> {
         let inner = block.inner(popup_area);
         block.render(popup_area, buf);
 
-        buf.set_string(inner.x, inner.y, self.prompt, Style::default().fg(Color::White));
+        let footer = match (self.working, self.revealed) {
+            (Some(_), _) => " Esc cancel ",
+            (None, true) => " Ctrl+s hide ",
+            (None, false) => " Ctrl+s show ",
+        };
+        render_footer(buf, popup_area, footer);
+        if let Some(fingerprint) = self.fingerprint {
+            let label = format!(" {} ", fingerprint);
+            let x = popup_area.x + popup_area.width.saturating_sub(label.len() as u16 + 1);
+            buf.set_string(x, popup_area.y, label, Style::default().fg(Color::DarkGray));
+        }
+
+        buf.set_string(inner.x, inner.y, i18n::tr(self.prompt), Style::default().fg(Color::White));
+        if self.caps_lock {
+            let label = "CAPS LOCK";
+            let x = inner.x + inner.width.saturating_sub(label.len() as u16);
+            buf.set_string(x, inner.y, label, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+        }
 
         let input_rect = Rect::new(inner.x, inner.y + 1, inner.width, 2);
-        InputField::new("", self.value, self.cursor).masked().render(input_rect, buf);
+        let field = InputField::new("", self.line);
+        match self.revealed {
+            true => field.render(input_rect, buf),
+            false => field.masked().render(input_rect, buf),
+        }
+
+        let mut y = inner.y + 3;
+        if let Some(min_entropy) = self.min_entropy {
+            render_master_strength(buf, Rect::new(inner.x, y, inner.width, 1), self.line, min_entropy);
+            y += 1;
+        }
+        if let Some(spinner) = self.working {
+            let status = format!("{} Deriving key...", spinner);
+            buf.set_string(inner.x, y, status, Style::default().fg(Color::Cyan));
+        } else if let Some(err) = self.error {
+            buf.set_string(inner.x, y, err, Style::default().fg(Color::Red));
+        }
+    }
+}
+
+/// A bar filling towards one and a half times the required entropy, red
+/// until the password would be accepted
+fn render_master_strength(buf: &mut Buffer, area: Rect, password: &str, min_entropy: f64) {
+    if password.is_empty() {
+        return;
+    }
+    let bits = estimate_entropy(password);
+    let (label, color) = match check_master_password(password, min_entropy) {
+        Err(Weakness::Common) => ("Too common".to_string(), Color::Red),
+        Err(_) => (format!("Too weak ({:.0}/{:.0} bits)", bits, min_entropy), Color::Red),
+        Ok(()) if bits < min_entropy * 1.5 => (format!("Good ({:.0} bits)", bits), Color::Yellow),
+        Ok(()) => (format!("Strong ({:.0} bits)", bits), Color::Green),
+    };
 
-        if let Some(err) = self.error {
-            buf.set_string(inner.x, inner.y + 3, err, Style::default().fg(Color::Red));
+    let target = (min_entropy * 1.5).max(1.0);
+    let filled = ((bits / target).min(1.0) * f64::from(area.width)) as u16;
+    for x in area.x..area.x + area.width {
+        let bg = if x < area.x + filled { color } else { Color::DarkGray };
+        if let Some(cell) = buf.cell_mut((x, area.y)) {
+            cell.set_style(Style::default().bg(bg));
         }
     }
+    let label_x = area.x + area.width.saturating_sub(label.len() as u16) / 2;
+    buf.set_string(label_x, area.y, &label, Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
 }
@@ -0,0 +1,146 @@
+//! Duplicates popup and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::vault::dupes::DuplicatePair;
+
+use super::layout::{
+    centered_rect_fixed, create_popup_block, highlight_row, render_empty_message, render_footer,
+    render_separator_line, truncate_with_ellipsis,
+};
+use super::scroll::{render_v_scroll_indicator, ScrollState};
+
+const REASON_WIDTH: u16 = 22;
+
+#[derive(Default)]
+pub struct DupesState {
+    pub scroll: ScrollState,
+    pub pairs: Vec<DuplicatePair>,
+    pub selected: usize,
+}
+
+impl DupesState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_pairs(&mut self, pairs: Vec<DuplicatePair>) {
+        self.pairs = pairs;
+        self.selected = self.selected.min(self.pairs.len().saturating_sub(1));
+        self.scroll.reset();
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.selected < self.pairs.len().saturating_sub(1) {
+            self.selected += 1;
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.selected = self.pairs.len().saturating_sub(1);
+    }
+
+    pub fn selected_pair(&self) -> Option<&DuplicatePair> {
+        self.pairs.get(self.selected)
+    }
+}
+
+pub struct DupesPopup<'a> {
+    state: &'a DupesState,
+}
+
+impl<'a> DupesPopup<'a> {
+    pub fn new(state: &'a DupesState) -> Self {
+        Self { state }
+    }
+}
+
+impl Widget for DupesPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let height = (self.state.pairs.len() as u16 + 4).min((area.height * 80) / 100).max(8);
+        let popup = centered_rect_fixed(70, height, area, true);
+        Clear.render(popup, buf);
+
+        let block = create_popup_block(" Probable Duplicates ", Color::Yellow);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        render_footer(buf, popup, " j/k nav - Enter first - s second - m merge - q close ");
+
+        if self.state.pairs.is_empty() {
+            render_empty_message(inner, buf, "No duplicates found");
+            return;
+        }
+
+        // Header takes 2 rows (header + separator)
+        let header_height = 2u16;
+        let list_area_height = inner.height.saturating_sub(header_height) as usize;
+        let max_v = self.state.pairs.len().saturating_sub(list_area_height);
+        let needs_v_scroll = max_v > 0;
+
+        let name_width = inner.width.saturating_sub(REASON_WIDTH) / 2;
+        render_dupes_header(inner, buf, name_width);
+        render_separator_line(buf, inner.x, inner.y + 1, inner.width);
+
+        let list_height = if needs_v_scroll {
+            list_area_height.saturating_sub(1)
+        } else {
+            list_area_height
+        };
+        let scroll_offset = calculate_scroll_offset(self.state.selected, list_height);
+
+        for (i, pair) in self.state.pairs.iter().enumerate().skip(scroll_offset).take(list_height) {
+            let y = inner.y + header_height + (i - scroll_offset) as u16;
+            render_pair_row(inner, buf, y, name_width, pair, i == self.state.selected);
+        }
+
+        let indicator_area = Rect::new(
+            inner.x,
+            inner.y + header_height,
+            inner.width,
+            inner.height.saturating_sub(header_height),
+        );
+        if needs_v_scroll {
+            render_v_scroll_indicator(buf, &indicator_area, scroll_offset, max_v, Color::Yellow);
+        }
+    }
+}
+
+fn calculate_scroll_offset(selected: usize, visible: usize) -> usize {
+    if selected >= visible { selected - visible + 1 } else { 0 }
+}
+
+fn render_dupes_header(inner: Rect, buf: &mut Buffer, name_width: u16) {
+    let style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    buf.set_string(inner.x, inner.y, "FIRST", style);
+    buf.set_string(inner.x + name_width, inner.y, "SECOND", style);
+    buf.set_string(inner.x + name_width * 2, inner.y, "REASON", style);
+}
+
+fn render_pair_row(inner: Rect, buf: &mut Buffer, y: u16, name_width: u16, pair: &DuplicatePair, is_cursor: bool) {
+    if is_cursor {
+        highlight_row(buf, inner.x, y, inner.width);
+    }
+    let style = |color: Color| {
+        let style = Style::default().fg(color);
+        if is_cursor { style.bg(Color::DarkGray) } else { style }
+    };
+
+    let width = name_width.saturating_sub(1) as usize;
+    buf.set_string(inner.x, y, truncate_with_ellipsis(&pair.first.name, width), style(Color::White));
+    buf.set_string(inner.x + name_width, y, truncate_with_ellipsis(&pair.second.name, width), style(Color::White));
+    buf.set_string(inner.x + name_width * 2, y, pair.reason(), style(Color::Magenta));
+}
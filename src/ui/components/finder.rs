@@ -0,0 +1,193 @@
+//! Fuzzy finder popup and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::db::Credential;
+use crate::input::fuzzy::best_score;
+use crate::input::line::EditLine;
+
+use super::input_field::{render_input_cursor, VisibleText};
+
+use super::layout::{
+    centered_rect, create_popup_block, highlight_row, privacy_mask, render_empty_message,
+    render_footer, render_separator_line, truncate_with_ellipsis,
+};
+
+#[derive(Debug, Clone)]
+pub struct FinderCandidate {
+    pub id: String,
+    pub name: String,
+    pub alias: Option<String>,
+    pub username: Option<String>,
+    pub url: Option<String>,
+}
+
+impl FinderCandidate {
+    fn score(&self, query: &str) -> Option<i64> {
+        let fields = [Some(self.name.as_str()), self.alias.as_deref(), self.username.as_deref(), self.url.as_deref()];
+        best_score(query, fields.into_iter().flatten())
+    }
+}
+
+#[derive(Default)]
+pub struct FinderState {
+    pub query: EditLine,
+    pub candidates: Vec<FinderCandidate>,
+    /// Indexes into `candidates`, best match first
+    pub matches: Vec<usize>,
+    pub selected: usize,
+}
+
+impl FinderState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open(&mut self, credentials: &[Credential]) {
+        self.candidates = credentials
+            .iter()
+            .map(|c| FinderCandidate {
+                id: c.id.clone(),
+                name: c.name.clone(),
+                alias: c.alias.clone(),
+                username: c.username.clone(),
+                url: c.url.clone(),
+            })
+            .collect();
+        self.query.clear();
+        self.refilter();
+    }
+
+    pub fn close(&mut self) {
+        self.query.clear();
+        self.candidates.clear();
+        self.matches.clear();
+        self.selected = 0;
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        self.query.insert(c);
+        self.refilter();
+    }
+
+    pub fn pop_char(&mut self) {
+        self.query.backspace();
+        self.refilter();
+    }
+
+    pub fn clear_query(&mut self) {
+        self.query.clear();
+        self.refilter();
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected_id(&self) -> Option<&str> {
+        let idx = *self.matches.get(self.selected)?;
+        Some(self.candidates[idx].id.as_str())
+    }
+
+    fn refilter(&mut self) {
+        let query = self.query.trim();
+        let mut scored: Vec<(usize, i64)> = self
+            .candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| c.score(query).map(|s| (i, s)))
+            .collect();
+        scored.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        self.matches = scored.into_iter().map(|(i, _)| i).collect();
+        self.selected = 0;
+    }
+}
+
+pub struct FinderPopup<'a> {
+    state: &'a FinderState,
+    private: bool,
+}
+
+impl<'a> FinderPopup<'a> {
+    pub fn new(state: &'a FinderState) -> Self {
+        Self { state, private: false }
+    }
+
+    /// Privacy mode: mask the username or URL beside each name
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+}
+
+impl Widget for FinderPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let popup = centered_rect(60, 60, area);
+        Clear.render(popup, buf);
+
+        let title = format!(" Find ({}/{}) ", self.state.matches.len(), self.state.candidates.len());
+        let block = create_popup_block(&title, Color::Cyan);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        render_footer(buf, popup, " type to filter - C-j/C-k move - Enter open - Esc close ");
+        render_finder_prompt(inner, buf, &self.state.query);
+        render_separator_line(buf, inner.x, inner.y + 1, inner.width);
+
+        let list_area = Rect::new(inner.x, inner.y + 2, inner.width, inner.height.saturating_sub(2));
+        if self.state.matches.is_empty() {
+            render_empty_message(list_area, buf, "No matches");
+            return;
+        }
+        render_finder_matches(list_area, buf, self.state, self.private);
+    }
+}
+
+fn render_finder_prompt(inner: Rect, buf: &mut Buffer, query: &EditLine) {
+    let prompt_style = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+    buf.set_string(inner.x, inner.y, "> ", prompt_style);
+
+    let width = inner.width.saturating_sub(2);
+    let visible = VisibleText::new(query, false, width as usize);
+    buf.set_string(inner.x + 2, inner.y, visible.text.as_str(), Style::default().fg(Color::White));
+    render_input_cursor(buf, inner.x + 2, inner.y, width, visible.cursor);
+}
+
+fn render_finder_matches(area: Rect, buf: &mut Buffer, state: &FinderState, private: bool) {
+    let visible = area.height as usize;
+    let offset = (state.selected + 1).saturating_sub(visible);
+
+    for (row, &idx) in state.matches.iter().skip(offset).take(visible).enumerate() {
+        let y = area.y + row as u16;
+        let is_selected = offset + row == state.selected;
+        if is_selected {
+            highlight_row(buf, area.x, y, area.width);
+        }
+        render_finder_row(area, buf, y, &state.candidates[idx], is_selected, private);
+    }
+}
+
+fn render_finder_row(area: Rect, buf: &mut Buffer, y: u16, candidate: &FinderCandidate, highlight: bool, private: bool) {
+    let bg = if highlight { Color::DarkGray } else { Color::Reset };
+    let name_width = (area.width as usize / 2).saturating_sub(1);
+    let name = truncate_with_ellipsis(&candidate.name, name_width);
+    buf.set_string(area.x, y, &name, Style::default().fg(Color::White).bg(bg));
+
+    let secondary = candidate.username.as_deref().or(candidate.url.as_deref()).unwrap_or("");
+    let secondary = if private { privacy_mask(secondary) } else { secondary.to_string() };
+    let secondary_width = (area.width as usize).saturating_sub(name_width + 1);
+    let secondary = truncate_with_ellipsis(&secondary, secondary_width);
+    buf.set_string(area.x + name_width as u16 + 1, y, &secondary, Style::default().fg(Color::DarkGray).bg(bg));
+}
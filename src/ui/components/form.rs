@@ -5,23 +5,44 @@
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
-    style::{Color, Modifier, Style},
+    style::{Color, Style},
     text::{Line, Span},
     widgets::{Block, Borders, BorderType, Clear, Widget},
 };
 
-use crate::db::models::CredentialType;
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use zeroize::Zeroizing;
+
+use crate::crypto::totp::TotpSecret;
+use crate::crypto::PasswordPolicy;
+use crate::db::models::{Attribute, AttributeKind, CredentialType, CustomType, CUSTOM_TYPE, DATE_FORMAT, EXPIRES_AT};
+use crate::i18n;
+use crate::input::line::EditLine;
 use crate::ui::renderer::View;
+use crate::ui::theme;
+use crate::vault::runner::is_valid_env_var;
+use crate::vault::search::UrlParts;
 
+use super::generator::{GeneratorPopup, GeneratorState};
+use super::input_field::VisibleText;
+use super::layout::truncate_with_ellipsis;
 use super::scroll::render_v_scroll_indicator;
 
 #[derive(Debug, Clone)]
 pub struct FormField {
-    pub label: &'static str,
-    pub value: String,
+    pub label: String,
+    /// Zeroized when the form is dropped, whether cancelled or saved
+    pub value: EditLine,
     pub required: bool,
     pub masked: bool,
     pub field_type: FieldType,
+    /// Why the last `validate` rejected the field, shown under it until
+    /// it is edited
+    pub error: Option<String>,
+    /// The type-specific attribute the field edits, if any
+    pub attribute: Option<Attribute>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -33,48 +54,61 @@ pub enum FieldType {
 }
 
 impl FormField {
-    pub fn text(label: &'static str, required: bool) -> Self {
+    pub fn text(label: &str, required: bool) -> Self {
         Self {
-            label,
-            value: String::new(),
+            label: label.to_string(),
+            value: EditLine::new(),
             required,
             masked: false,
             field_type: FieldType::Text,
+            error: None,
+            attribute: None,
         }
     }
 
-    pub fn password(label: &'static str, required: bool) -> Self {
+    pub fn password(label: &str, required: bool) -> Self {
         Self {
-            label,
-            value: String::new(),
+            label: label.to_string(),
+            value: EditLine::new(),
             required,
             masked: true,
             field_type: FieldType::Password,
+            error: None,
+            attribute: None,
         }
     }
 
-    pub fn select(label: &'static str) -> Self {
+    pub fn select(label: &str) -> Self {
         Self {
-            label,
-            value: String::new(),
+            label: label.to_string(),
+            value: EditLine::new(),
             required: true,
             masked: false,
             field_type: FieldType::Select,
+            error: None,
+            attribute: None,
         }
     }
 
-    pub fn multiline(label: &'static str) -> Self {
+    pub fn multiline(label: &str) -> Self {
         Self {
-            label,
-            value: String::new(),
+            label: label.to_string(),
+            value: EditLine::new(),
             required: false,
             masked: false,
             field_type: FieldType::MultiLine,
+            error: None,
+            attribute: None,
         }
     }
 
+    pub fn for_attribute(attribute: Attribute) -> Self {
+        let field = Self::text(&attribute.label, false);
+        Self { attribute: Some(attribute), ..field }
+    }
+
     pub fn with_value(mut self, value: impl Into<String>) -> Self {
-        self.value = value.into();
+        self.value = EditLine::from(value.into());
         self
     }
 }
@@ -83,12 +117,21 @@ impl FormField {
 pub struct CredentialForm {
     pub fields: Vec<FormField>,
     pub active_field: usize,
-    pub cursor: usize,
     pub credential_type: CredentialType,
     pub editing_id: Option<String>,
     pub show_password: bool,
     pub scroll_offset: usize,
     pub previous_view: View,
+    /// Generator popup opened over the secret field with Ctrl+g
+    pub generator: Option<GeneratorState>,
+    /// Named password policy the secret was generated with
+    pub password_policy: Option<String>,
+    /// Opened by `:rotate`: saving keeps the old secret in the history
+    pub rotating: bool,
+    /// Custom types defined in the config, offered after Custom
+    pub custom_types: Vec<CustomType>,
+    /// Name of the custom type picked, when the type is Custom
+    pub custom_type: Option<String>,
 }
 
 impl Default for CredentialForm {
@@ -97,6 +140,13 @@ impl Default for CredentialForm {
     }
 }
 
+const USERNAME: usize = 2;
+const SECRET: usize = 3;
+const URL: usize = 4;
+const ENV_VAR: usize = 6;
+/// Fields of the credential's type go after the env var, before notes
+const TYPE_FIELDS: usize = ENV_VAR + 1;
+
 fn default_fields() -> Vec<FormField> {
     vec![
         FormField::text("Name", true),
@@ -105,34 +155,22 @@ fn default_fields() -> Vec<FormField> {
         FormField::password("Password/Secret", true),
         FormField::text("URL", false),
         FormField::text("Tags (multiple)", false),
+        FormField::text("Env var", false),
         FormField::multiline("Notes"),
     ]
 }
 
-fn cycle_type_forward(cred_type: CredentialType) -> CredentialType {
-    match cred_type {
-        CredentialType::Password => CredentialType::ApiKey,
-        CredentialType::ApiKey => CredentialType::SshKey,
-        CredentialType::SshKey => CredentialType::Certificate,
-        CredentialType::Certificate => CredentialType::Totp,
-        CredentialType::Totp => CredentialType::Note,
-        CredentialType::Note => CredentialType::Database,
-        CredentialType::Database => CredentialType::Custom,
-        CredentialType::Custom => CredentialType::Password,
-    }
-}
-
-fn cycle_type_backward(cred_type: CredentialType) -> CredentialType {
-    match cred_type {
-        CredentialType::Password => CredentialType::Custom,
-        CredentialType::ApiKey => CredentialType::Password,
-        CredentialType::SshKey => CredentialType::ApiKey,
-        CredentialType::Certificate => CredentialType::SshKey,
-        CredentialType::Totp => CredentialType::Certificate,
-        CredentialType::Note => CredentialType::Totp,
-        CredentialType::Database => CredentialType::Note,
-        CredentialType::Custom => CredentialType::Database,
-    }
+/// `Me@Example.COM` as `Me@example.com`, or `None` if the value does not
+/// look like an email address. Only the domain is case-insensitive.
+fn normalize_email(value: &str) -> Option<String> {
+    let value = value.trim();
+    let (local, domain) = value.split_once('@')?;
+    let looks_like_email = !local.is_empty()
+        && !domain.contains('@')
+        && domain.split('.').count() > 1
+        && domain.split('.').all(|label| !label.is_empty())
+        && !value.contains(char::is_whitespace);
+    looks_like_email.then(|| format!("{}@{}", local, domain.to_ascii_lowercase()))
 }
 
 fn trim_to_option(val: &str) -> Option<String> {
@@ -149,12 +187,16 @@ impl CredentialForm {
         Self {
             fields: default_fields(),
             active_field: 0,
-            cursor: 0,
             credential_type: CredentialType::Password,
             editing_id: None,
             show_password: false,
             scroll_offset: 0,
             previous_view: View::List,
+            generator: None,
+            password_policy: None,
+            rotating: false,
+            custom_types: Vec::new(),
+            custom_type: None,
         }
     }
 
@@ -163,10 +205,10 @@ impl CredentialForm {
         name: String,
         cred_type: CredentialType,
         username: Option<String>,
-        secret: String,
+        secret: &str,
         url: Option<String>,
         tags: Vec<String>,
-        notes: Option<String>,
+        notes: Option<&str>,
         previous_view: View,
     ) -> Self {
         let mut form = Self::new();
@@ -174,17 +216,34 @@ impl CredentialForm {
         form.credential_type = cred_type;
         form.previous_view = previous_view;
 
-        form.fields[0].value = name;
-        form.fields[1].value = cred_type.display_name().to_string();
-        form.fields[2].value = username.unwrap_or_default();
-        form.fields[3].value = secret;
-        form.fields[4].value = url.unwrap_or_default();
-        form.fields[5].value = tags.join(" ");
-        form.fields[6].value = notes.unwrap_or_default();
+        form.fields[0].value = EditLine::from(name);
+        form.fields[1].value = EditLine::from(cred_type.display_name().to_string());
+        form.fields[2].value = EditLine::from(username.unwrap_or_default());
+        form.fields[3].value = EditLine::from(secret.to_string());
+        form.fields[4].value = EditLine::from(url.unwrap_or_default());
+        form.fields[5].value = EditLine::from(tags.join(" "));
+        form.set_type_fields();
+        let notes_idx = form.notes_index();
+        form.fields[notes_idx].value = EditLine::from(notes.unwrap_or_default().to_string());
 
         form
     }
 
+    /// Turn an edit form into a rotation: the secret is emptied for the
+    /// new one, the issue date set to `today` and the expiry cleared
+    pub fn start_rotation(&mut self, today: NaiveDate) {
+        self.rotating = true;
+        self.fields[SECRET].value.clear();
+        for field in &mut self.fields {
+            match field.attribute.as_ref().map(|a| a.key.as_str()) {
+                Some("issued_at") => field.value = EditLine::from(today.format(DATE_FORMAT).to_string()),
+                Some(EXPIRES_AT) => field.value.clear(),
+                _ => {}
+            }
+        }
+        self.active_field = SECRET;
+    }
+
     pub fn is_editing(&self) -> bool {
         self.editing_id.is_some()
     }
@@ -209,7 +268,7 @@ impl CredentialForm {
 
     pub fn next_field(&mut self) {
         self.active_field = (self.active_field + 1) % self.fields.len();
-        self.cursor = self.fields[self.active_field].value.len();
+        self.fields[self.active_field].value.end();
         self.ensure_visible(5);
     }
 
@@ -219,7 +278,7 @@ impl CredentialForm {
         } else {
             self.active_field -= 1;
         }
-        self.cursor = self.fields[self.active_field].value.len();
+        self.fields[self.active_field].value.end();
         self.ensure_visible(5);
     }
 
@@ -228,53 +287,178 @@ impl CredentialForm {
         if field.field_type == FieldType::Select {
             return;
         }
-        field.value.insert(self.cursor, c);
-        self.cursor += 1;
+        field.value.insert(c);
+        field.error = None;
     }
 
     pub fn delete_char(&mut self) {
         let field = &mut self.fields[self.active_field];
-        if self.cursor == 0 || field.field_type == FieldType::Select {
+        if field.field_type == FieldType::Select {
             return;
         }
-        self.cursor -= 1;
-        field.value.remove(self.cursor);
+        field.value.backspace();
+        field.error = None;
     }
 
-    pub fn cursor_left(&mut self) {
-        if self.cursor > 0 {
-            self.cursor -= 1;
+    pub fn delete_char_forward(&mut self) {
+        let field = &mut self.fields[self.active_field];
+        if field.field_type == FieldType::Select {
+            return;
         }
+        field.value.delete();
+        field.error = None;
+    }
+
+    pub fn cursor_left(&mut self) {
+        self.fields[self.active_field].value.left();
     }
 
     pub fn cursor_right(&mut self) {
-        if self.cursor < self.fields[self.active_field].value.len() {
-            self.cursor += 1;
-        }
+        self.fields[self.active_field].value.right();
     }
 
     pub fn cycle_type(&mut self, forward: bool) {
         if self.fields[self.active_field].field_type != FieldType::Select {
             return;
         }
-        self.credential_type = if forward {
-            cycle_type_forward(self.credential_type)
-        } else {
-            cycle_type_backward(self.credential_type)
+        let choices = self.type_choices();
+        let current = choices
+            .iter()
+            .position(|(t, name)| *t == self.credential_type && *name == self.custom_type)
+            .unwrap_or(0);
+        let next = match forward {
+            true => (current + 1) % choices.len(),
+            false => (current + choices.len() - 1) % choices.len(),
         };
-        self.fields[1].value = self.credential_type.display_name().to_string();
+        (self.credential_type, self.custom_type) = choices[next].clone();
+        self.fields[1].value = EditLine::from(self.type_name());
+        self.fields[SECRET].error = None;
+        self.set_type_fields();
+    }
+
+    /// The built-in types in order, with the custom types after Custom
+    fn type_choices(&self) -> Vec<(CredentialType, Option<String>)> {
+        let mut choices = Vec::new();
+        for &cred_type in CredentialType::all() {
+            choices.push((cred_type, None));
+            if cred_type == CredentialType::Custom {
+                choices.extend(self.custom_types.iter().map(|t| (cred_type, Some(t.name.clone()))));
+            }
+        }
+        choices
+    }
+
+    /// The definition of the custom type picked, if it is still configured
+    pub fn custom_definition(&self) -> Option<&CustomType> {
+        let name = self.custom_type.as_ref().filter(|_| self.credential_type == CredentialType::Custom)?;
+        self.custom_types.iter().find(|t| &t.name == name)
+    }
+
+    fn type_name(&self) -> String {
+        match &self.custom_type {
+            Some(name) if self.credential_type == CredentialType::Custom => name.clone(),
+            _ => self.credential_type.display_name().to_string(),
+        }
+    }
+
+    fn type_icon(&self) -> &str {
+        match self.custom_definition() {
+            Some(custom) => theme::custom_icon(custom),
+            None => theme::type_icon(self.credential_type),
+        }
+    }
+
+    /// Swap in the fields of the current type, keeping what was typed
+    /// into any the old type shared with it
+    fn set_type_fields(&mut self) {
+        let old: Vec<FormField> = self.fields.drain(TYPE_FIELDS..self.notes_index()).collect();
+        let attributes = self.credential_type.attributes_with(self.custom_definition());
+        let new = attributes.into_iter().map(|attribute| {
+            match old.iter().find(|f| f.attribute.as_ref().is_some_and(|a| a.key == attribute.key)) {
+                Some(field) => field.clone(),
+                None => FormField::for_attribute(attribute),
+            }
+        });
+        self.fields.splice(TYPE_FIELDS..TYPE_FIELDS, new.collect::<Vec<_>>());
+    }
+
+    fn notes_index(&self) -> usize {
+        self.fields.len() - 1
     }
 
     pub fn toggle_password_visibility(&mut self) {
         self.show_password = !self.show_password;
     }
 
-    pub fn validate(&self) -> Result<(), String> {
-        for field in &self.fields {
-            let is_empty_required = field.required && field.value.trim().is_empty();
-            if is_empty_required { return Err(format!("{} is required", field.label)); }
+    /// Open the generator when the secret field is active
+    pub fn open_generator(&mut self, policies: &BTreeMap<String, PasswordPolicy>) -> bool {
+        if self.active_field().field_type != FieldType::Password {
+            return false;
+        }
+        self.generator = Some(GeneratorState::with_policies(policies, self.password_policy.as_deref()));
+        true
+    }
+
+    /// Fill the active secret field with a new password from the
+    /// credential's policy, without opening the generator
+    pub fn fill_generated(&mut self, policies: &BTreeMap<String, PasswordPolicy>) -> bool {
+        if !self.open_generator(policies) {
+            return false;
+        }
+        self.accept_generated();
+        true
+    }
+
+    pub fn close_generator(&mut self) {
+        self.generator = None;
+    }
+
+    /// Replace the active field with the generator's candidate
+    pub fn accept_generated(&mut self) {
+        let Some(generator) = self.generator.take() else {
+            return;
+        };
+        self.password_policy = generator.applied_policy();
+        let field = &mut self.fields[self.active_field];
+        field.value = EditLine::from(generator.candidate);
+        field.error = None;
+    }
+
+    /// Check each field against its type, leaving a message under every
+    /// one that fails and moving to the first of them. A username that is
+    /// an email address has its domain lowercased on the way. Returns
+    /// whether the form can be saved.
+    pub fn validate(&mut self) -> bool {
+        if let Some(email) = normalize_email(&self.fields[USERNAME].value) {
+            self.fields[USERNAME].value.set(&email);
+        }
+        let errors: Vec<Option<String>> = (0..self.fields.len()).map(|idx| self.field_error(idx)).collect();
+        for (field, error) in self.fields.iter_mut().zip(errors) {
+            field.error = error;
+        }
+        let Some(first) = self.fields.iter().position(|field| field.error.is_some()) else {
+            return true;
+        };
+        self.active_field = first;
+        self.fields[first].value.end();
+        self.ensure_visible(5);
+        false
+    }
+
+    fn field_error(&self, idx: usize) -> Option<String> {
+        let field = &self.fields[idx];
+        let value = field.value.trim();
+        if value.is_empty() {
+            return field.required.then(|| format!("{} is required", field.label));
+        }
+        match idx {
+            URL if UrlParts::parse(value).is_none() => Some("Not a valid URL".to_string()),
+            SECRET if self.credential_type == CredentialType::Totp && TotpSecret::parse(value, self.get_name()).is_err() => {
+                Some("Not a base32 key or otpauth:// URI".to_string())
+            }
+            ENV_VAR if !is_valid_env_var(value) => Some(format!("'{}' is not a valid environment variable name", value)),
+            _ => field.attribute.as_ref().and_then(|a| a.kind.check(value)).map(str::to_string),
         }
-        Ok(())
     }
 
     pub fn get_name(&self) -> &str {
@@ -302,9 +486,57 @@ impl CredentialForm {
             .collect()
     }
 
-    pub fn get_notes(&self) -> Option<String> {
+    pub fn get_env_var(&self) -> Option<String> {
         trim_to_option(&self.fields[6].value)
     }
+
+    pub fn set_env_var(&mut self, env_var: Option<String>) {
+        self.fields[6].value = EditLine::from(env_var.unwrap_or_default());
+    }
+
+    /// The type's attributes that were filled in, by key, along with the
+    /// name of a custom type
+    pub fn get_attributes(&self) -> BTreeMap<String, String> {
+        let mut attributes: BTreeMap<String, String> = self
+            .fields
+            .iter()
+            .filter_map(|f| Some((f.attribute.as_ref()?.key.clone(), trim_to_option(&f.value)?)))
+            .collect();
+        if let Some(name) = self.custom_type.as_ref().filter(|_| self.credential_type == CredentialType::Custom) {
+            attributes.insert(CUSTOM_TYPE.to_string(), name.clone());
+        }
+        attributes
+    }
+
+    /// Fill in the type's fields. A custom type's name picks its fields;
+    /// values its definition no longer has get plain text fields, so
+    /// saving does not drop them.
+    pub fn set_attributes(&mut self, attributes: &BTreeMap<String, String>) {
+        if self.credential_type == CredentialType::Custom
+            && let Some(name) = attributes.get(CUSTOM_TYPE)
+        {
+            self.custom_type = Some(name.clone());
+            self.fields[1].value = EditLine::from(self.type_name());
+            self.set_type_fields();
+        }
+        for (key, value) in attributes.iter().filter(|(key, _)| *key != CUSTOM_TYPE) {
+            match self.fields.iter_mut().find(|f| f.attribute.as_ref().is_some_and(|a| &a.key == key)) {
+                Some(field) => field.value = EditLine::from(value.clone()),
+                None => {
+                    let attribute = Attribute::new(key, key, AttributeKind::Text);
+                    let field = FormField::for_attribute(attribute).with_value(value.clone());
+                    let notes = self.notes_index();
+                    self.fields.insert(notes, field);
+                }
+            }
+        }
+    }
+
+    /// Borrowed rather than copied like the other fields, as notes often
+    /// hold secrets too
+    pub fn get_notes(&self) -> Option<&str> {
+        Some(self.fields[self.notes_index()].value.trim()).filter(|notes| !notes.is_empty())
+    }
 }
 
 pub struct CredentialFormWidget<'a> {
@@ -314,7 +546,9 @@ pub struct CredentialFormWidget<'a> {
 
 impl<'a> CredentialFormWidget<'a> {
     pub fn new(form: &'a CredentialForm) -> Self {
-        let title = if form.is_editing() {
+        let title = if form.rotating {
+            " Rotate Credential "
+        } else if form.is_editing() {
             " Edit Credential "
         } else {
             " New Credential "
@@ -348,15 +582,15 @@ fn render_form_block(buf: &mut Buffer, form_area: Rect, title: &str) -> Rect {
 
 fn format_label(field: &FormField) -> String {
     if field.required {
-        format!("{}*:", field.label)
+        format!("{}*:", i18n::tr(&field.label))
     } else {
-        format!("{}:", field.label)
+        format!("{}:", i18n::tr(&field.label))
     }
 }
 
 fn label_style(is_active: bool) -> Style {
     if is_active {
-        Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)
+        theme::active_style(Color::Magenta)
     } else {
         Style::default().fg(Color::Gray)
     }
@@ -378,40 +612,17 @@ fn fill_field_background(buf: &mut Buffer, x: u16, y: u16, width: u16, style: St
     }
 }
 
-struct DisplayValue {
-    text: String,
-    cursor: usize,
-}
-
-fn compute_select_display(form: &CredentialForm, field: &FormField) -> DisplayValue {
-    let icon = form.credential_type.icon();
-    DisplayValue {
-        text: format!("{} {}  [Space/Ctrl+Space]", icon, field.value),
+fn compute_select_display(form: &CredentialForm, field: &FormField) -> VisibleText {
+    let icon = form.type_icon();
+    VisibleText {
+        text: Zeroizing::new(format!("{} {}  [Space/Ctrl+Space]", icon, field.value.as_str())),
         cursor: 0,
     }
 }
 
-fn compute_text_display(form: &CredentialForm, field: &FormField, value_width: usize) -> DisplayValue {
-    let text = if field.masked && !form.show_password {
-        "*".repeat(field.value.len())
-    } else {
-        field.value.clone()
-    };
-
-    let cursor_pos = form.cursor;
-    let scroll = if cursor_pos >= value_width.saturating_sub(1) {
-        cursor_pos.saturating_sub(value_width.saturating_sub(2))
-    } else {
-        0
-    };
-
-    let visible: String = text.chars().skip(scroll).take(value_width).collect();
-    let adjusted_cursor = cursor_pos.saturating_sub(scroll);
-
-    DisplayValue {
-        text: visible,
-        cursor: adjusted_cursor,
-    }
+/// Only the visible part is copied, and only when it is not masked
+fn compute_text_display(form: &CredentialForm, field: &FormField, value_width: usize) -> VisibleText {
+    VisibleText::new(&field.value, field.masked && !form.show_password, value_width)
 }
 
 fn value_style(field: &FormField, is_active: bool) -> Style {
@@ -458,11 +669,15 @@ fn render_field(
         compute_text_display(form, field, value_width as usize)
     };
 
-    buf.set_string(value_x, y, &display.text, value_style(field, is_active));
+    buf.set_string(value_x, y, display.text.as_str(), value_style(field, is_active));
 
     if is_active && field.field_type != FieldType::Select {
         render_cursor(buf, value_x + display.cursor as u16, y, value_x + value_width);
     }
+    if let Some(error) = &field.error {
+        let error = truncate_with_ellipsis(error, value_width as usize);
+        buf.set_string(value_x, y + 1, error, Style::default().fg(Color::Red));
+    }
 }
 
 fn render_help_footer(buf: &mut Buffer, inner: &Rect) {
@@ -477,13 +692,26 @@ fn render_help_footer(buf: &mut Buffer, inner: &Rect) {
         Span::raw("Esc"),
         Span::styled(" cancel  ", Style::default().fg(Color::White)),
         Span::raw("Ctrl+s"),
-        Span::styled(" show pwd", Style::default().fg(Color::White)),
+        Span::styled(" show pwd  ", Style::default().fg(Color::White)),
+        Span::raw("Ctrl+g"),
+        Span::styled(" generate  ", Style::default().fg(Color::White)),
+        Span::raw("Ctrl+r"),
+        Span::styled(" fill", Style::default().fg(Color::White)),
     ]);
 
     let text_width = help_text.width() as u16;
     let help_x = inner.x + inner.width.saturating_sub(text_width) / 2;
 
-    buf.set_line(help_x, help_y, &help_text, text_width);
+    buf.set_line(help_x, help_y, &help_text, text_width.min(inner.width));
+}
+
+/// `scroll_offset` moved just enough to show the active field. The form
+/// scrolls as if five fields fit, but a short terminal may show fewer.
+fn visible_offset(scroll_offset: usize, active: usize, shown: usize) -> usize {
+    match active.checked_sub(shown.max(1) - 1) {
+        Some(min_offset) if min_offset > scroll_offset => min_offset,
+        _ => scroll_offset.min(active),
+    }
 }
 
 impl<'a> Widget for CredentialFormWidget<'a> {
@@ -494,7 +722,6 @@ impl<'a> Widget for CredentialFormWidget<'a> {
         let visible_height = inner.height + 1;
         let max_visible_fields = (visible_height / 2) as usize;
         let needs_scrolling = self.form.fields.len() > max_visible_fields;
-        let scroll_offset = if needs_scrolling { self.form.scroll_offset } else { 0 };
 
         // Reserve bottom line for indicator when scrolling is needed
         let fields_to_show = if needs_scrolling {
@@ -504,6 +731,10 @@ impl<'a> Widget for CredentialFormWidget<'a> {
         };
 
         let max_v = self.form.fields.len().saturating_sub(fields_to_show);
+        let scroll_offset = match needs_scrolling {
+            true => visible_offset(self.form.scroll_offset, self.form.active_field, fields_to_show).min(max_v),
+            false => 0,
+        };
 
         let mut y = inner.y;
         for (i, field) in self.form.fields.iter().enumerate().skip(scroll_offset) {
@@ -515,5 +746,215 @@ impl<'a> Widget for CredentialFormWidget<'a> {
             render_v_scroll_indicator(buf, &inner, scroll_offset, max_v, Color::Magenta);
         }
         render_help_footer(buf, &inner);
+
+        if let Some(generator) = &self.form.generator {
+            GeneratorPopup::new(generator).render(area, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_offset_keeps_active_field_shown() {
+        assert_eq!(visible_offset(0, 3, 5), 0);
+        assert_eq!(visible_offset(0, 6, 3), 4);
+        assert_eq!(visible_offset(4, 2, 3), 2);
+        assert_eq!(visible_offset(2, 4, 1), 4);
+    }
+
+    #[test]
+    fn test_typing_grows_value_in_place() {
+        let mut form = CredentialForm::new();
+        form.active_field = 3;
+        for c in "correct-horse-battery-staple-and-then-some".chars() {
+            form.insert_char(c);
+        }
+        assert_eq!(form.get_secret(), "correct-horse-battery-staple-and-then-some");
+
+        form.fields[7].value = EditLine::from("  recovery codes \n".to_string());
+        assert_eq!(form.get_notes(), Some("recovery codes"));
+        form.fields[7].value = EditLine::from(" ".to_string());
+        assert_eq!(form.get_notes(), None);
+    }
+
+    #[test]
+    fn test_editing_wide_and_combined_text() {
+        let mut form = CredentialForm::new();
+        for c in "日本e\u{301}🔑".chars() {
+            form.insert_char(c);
+        }
+        form.cursor_left();
+        form.delete_char();
+        form.cursor_left();
+        form.delete_char_forward();
+        form.insert_char('語');
+        assert_eq!(form.get_name(), "日語🔑");
+
+        // The cursor lands after the wide characters, not mid-way through
+        let display = compute_text_display(&form, &form.fields[0], 20);
+        assert_eq!((display.text.as_str(), display.cursor), ("日語🔑", 4));
+    }
+
+    #[test]
+    fn test_validate_marks_each_field() {
+        let mut form = CredentialForm::new();
+        form.fields[URL].value.set("https://exa mple.com");
+        form.fields[ENV_VAR].value.set("1TOKEN");
+        assert!(!form.validate());
+        assert_eq!(form.fields[0].error.as_deref(), Some("Name is required"));
+        assert_eq!(form.fields[URL].error.as_deref(), Some("Not a valid URL"));
+        assert!(form.fields[ENV_VAR].error.is_some());
+        assert!(form.fields[USERNAME].error.is_none());
+        assert_eq!(form.active_field, 0);
+
+        // Editing a field clears its message
+        form.insert_char('G');
+        assert!(form.fields[0].error.is_none());
+
+        form.fields[SECRET].value.set("hunter2");
+        form.fields[URL].value.set("github.com/login");
+        form.fields[ENV_VAR].value.set("GITHUB_TOKEN");
+        form.fields[USERNAME].value.set(" Me@GitHub.COM ");
+        assert!(form.validate());
+        assert_eq!(form.get_username().as_deref(), Some("Me@github.com"));
+    }
+
+    #[test]
+    fn test_validate_totp_secret() {
+        let mut form = CredentialForm::new();
+        form.fields[0].value.set("GitHub");
+        form.active_field = 1;
+        while form.credential_type != CredentialType::Totp {
+            form.cycle_type(true);
+        }
+        form.fields[SECRET].value.set("not base32!");
+        assert!(!form.validate());
+        assert_eq!(form.active_field, SECRET);
+
+        form.fields[SECRET].value.set("hxdm vjec jjws rb3h wizr 4ifu gftm xboz");
+        assert!(form.validate());
+        form.fields[SECRET].value.set("otpauth://totp/ACME:me?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME");
+        assert!(form.validate());
+    }
+
+    #[test]
+    fn test_type_fields() {
+        let mut form = CredentialForm::new();
+        form.fields[0].value.set("prod-db");
+        form.fields[SECRET].value.set("hunter2");
+        form.active_field = 1;
+        while form.credential_type != CredentialType::Database {
+            form.cycle_type(true);
+        }
+        assert_eq!(form.fields[TYPE_FIELDS].label, "Host");
+        assert_eq!(form.fields.len(), default_fields().len() + 3);
+
+        form.fields[TYPE_FIELDS].value.set("db.example.com");
+        form.fields[TYPE_FIELDS + 1].value.set("99999");
+        assert!(!form.validate());
+        assert_eq!(form.active_field, TYPE_FIELDS + 1);
+        form.fields[TYPE_FIELDS + 1].value.set("5432");
+        assert!(form.validate());
+        let attributes = form.get_attributes();
+        assert_eq!(attributes.get("host").map(String::as_str), Some("db.example.com"));
+        assert!(!attributes.contains_key("database"));
+
+        // Cycling to a type without them drops the fields
+        form.active_field = 1;
+        form.cycle_type(true);
+        assert_eq!(form.fields.len(), default_fields().len());
+        assert!(form.get_attributes().is_empty());
+    }
+
+    #[test]
+    fn test_custom_types() {
+        let wifi: CustomType = serde_json::from_str(
+            r#"{"name": "Wi-Fi", "icon": "W", "fields": [{"key": "ssid", "label": "SSID"}], "copy": "secret"}"#,
+        )
+        .unwrap();
+        let mut form = CredentialForm::new();
+        form.custom_types = vec![wifi];
+        form.active_field = 1;
+        while form.credential_type != CredentialType::Custom {
+            form.cycle_type(true);
+        }
+        assert_eq!(form.custom_type, None);
+        form.cycle_type(true);
+        assert_eq!(form.fields[1].value.as_str(), "Wi-Fi");
+        assert_eq!(form.fields[TYPE_FIELDS].label, "SSID");
+
+        form.fields[TYPE_FIELDS].value.set("home");
+        let attributes = form.get_attributes();
+        assert_eq!(attributes.get(CUSTOM_TYPE).map(String::as_str), Some("Wi-Fi"));
+        assert_eq!(attributes.get("ssid").map(String::as_str), Some("home"));
+
+        // Wraps around to Password after the last custom type
+        form.cycle_type(true);
+        assert_eq!(form.credential_type, CredentialType::Password);
+        assert!(form.get_attributes().is_empty());
+    }
+
+    #[test]
+    fn test_keeps_values_of_undefined_custom_fields() {
+        let attributes = BTreeMap::from([
+            (CUSTOM_TYPE.to_string(), "Gone".to_string()),
+            ("pin".to_string(), "1234".to_string()),
+        ]);
+        let mut form = CredentialForm::for_edit(
+            "id".to_string(),
+            "Locker".to_string(),
+            CredentialType::Custom,
+            None,
+            "secret",
+            None,
+            vec![],
+            None,
+            View::List,
+        );
+        form.set_attributes(&attributes);
+        assert_eq!(form.fields[1].value.as_str(), "Gone");
+        assert_eq!(form.get_attributes(), attributes);
+    }
+
+    #[test]
+    fn test_start_rotation() {
+        let attributes = BTreeMap::from([
+            ("issued_at".to_string(), "2024-01-01".to_string()),
+            ("expires_at".to_string(), "2025-01-01".to_string()),
+            ("scope".to_string(), "read".to_string()),
+        ]);
+        let mut form = CredentialForm::for_edit(
+            "id".to_string(),
+            "Stripe".to_string(),
+            CredentialType::ApiKey,
+            None,
+            "sk_old",
+            None,
+            vec![],
+            None,
+            View::List,
+        );
+        form.set_attributes(&attributes);
+
+        form.start_rotation(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap());
+        assert!(form.rotating);
+        assert_eq!(form.active_field, SECRET);
+        assert!(!form.validate());
+        let attributes = form.get_attributes();
+        assert_eq!(attributes.get("issued_at").map(String::as_str), Some("2025-06-01"));
+        assert!(!attributes.contains_key("expires_at"));
+        assert_eq!(attributes.get("scope").map(String::as_str), Some("read"));
+    }
+
+    #[test]
+    fn test_normalize_email() {
+        assert_eq!(normalize_email("Me@Example.COM").as_deref(), Some("Me@example.com"));
+        assert_eq!(normalize_email("admin"), None);
+        assert_eq!(normalize_email("a@localhost"), None);
+        assert_eq!(normalize_email("a b@example.com"), None);
+        assert_eq!(normalize_email("@example.com"), None);
     }
 }
@@ -0,0 +1,422 @@
+//! In-form password generator popup and state
+
+use std::collections::BTreeMap;
+
+use zeroize::Zeroizing;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::crypto::{
+    generate_diceware, generate_password, password_strength, strength_label, PassphrasePolicy, PasswordPolicy,
+};
+
+use super::layout::{
+    centered_rect_fixed, create_popup_block, highlight_row, render_footer, render_separator_line,
+    truncate_with_ellipsis,
+};
+
+const MIN_LENGTH: usize = 4;
+const MAX_LENGTH: usize = 128;
+const MIN_WORDS: usize = 3;
+const MAX_WORDS: usize = 12;
+const SEPARATORS: [&str; 4] = ["-", ".", "_", " "];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneratorKind {
+    Password,
+    Passphrase,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneratorOption {
+    Kind,
+    Policy,
+    Length,
+    Symbols,
+    Digits,
+    ExcludeAmbiguous,
+    Words,
+    Separator,
+    Capitalize,
+    WordDigit,
+}
+
+impl GeneratorOption {
+    const PASSWORD: [Self; 6] = [Self::Kind, Self::Policy, Self::Length, Self::Symbols, Self::Digits, Self::ExcludeAmbiguous];
+    const PASSPHRASE: [Self; 5] = [Self::Kind, Self::Words, Self::Separator, Self::Capitalize, Self::WordDigit];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Kind => "Kind",
+            Self::Policy => "Policy",
+            Self::Length => "Length",
+            Self::Symbols => "Symbols",
+            Self::Digits => "Digits",
+            Self::ExcludeAmbiguous => "Exclude ambiguous",
+            Self::Words => "Words",
+            Self::Separator => "Separator",
+            Self::Capitalize => "Capitalize",
+            Self::WordDigit => "Add digit",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratorState {
+    pub kind: GeneratorKind,
+    pub policy: PasswordPolicy,
+    pub passphrase: PassphrasePolicy,
+    /// Named policies from the config, in display order
+    pub named: Vec<(String, PasswordPolicy)>,
+    /// Named policy `policy` was loaded from, cleared once it is edited
+    pub policy_name: Option<String>,
+    pub candidate: Zeroizing<String>,
+    pub selected: usize,
+}
+
+impl Default for GeneratorState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GeneratorState {
+    pub fn new() -> Self {
+        let mut state = Self {
+            kind: GeneratorKind::Password,
+            policy: PasswordPolicy::default(),
+            passphrase: PassphrasePolicy::default(),
+            named: Vec::new(),
+            policy_name: None,
+            candidate: Zeroizing::default(),
+            selected: 0,
+        };
+        state.regenerate();
+        state
+    }
+
+    /// Start from the credential's stored policy when it is still configured
+    pub fn with_policies(policies: &BTreeMap<String, PasswordPolicy>, current: Option<&str>) -> Self {
+        let mut state = Self::new();
+        state.named = policies.iter().map(|(name, policy)| (name.clone(), policy.clone())).collect();
+        if let Some(idx) = current.and_then(|name| state.named.iter().position(|(n, _)| n == name)) {
+            state.apply_named(Some(idx));
+        }
+        state
+    }
+
+    /// Policy name to store with the credential when the candidate is accepted
+    pub fn applied_policy(&self) -> Option<String> {
+        match self.kind {
+            GeneratorKind::Password => self.policy_name.clone(),
+            GeneratorKind::Passphrase => None,
+        }
+    }
+
+    fn apply_named(&mut self, idx: Option<usize>) {
+        match idx.and_then(|i| self.named.get(i)) {
+            Some((name, policy)) => {
+                self.policy = policy.clone();
+                self.policy_name = Some(name.clone());
+            }
+            None => self.policy_name = None,
+        }
+        self.regenerate();
+    }
+
+    fn cycle_policy(&mut self, forward: bool) {
+        if self.named.is_empty() {
+            return;
+        }
+        // Position 0 is the custom policy, named policies follow
+        let count = self.named.len() + 1;
+        let current = self
+            .policy_name
+            .as_ref()
+            .and_then(|name| self.named.iter().position(|(n, _)| n == name))
+            .map_or(0, |i| i + 1);
+        let next = if forward { (current + 1) % count } else { (current + count - 1) % count };
+        self.apply_named(next.checked_sub(1));
+    }
+
+    pub fn regenerate(&mut self) {
+        self.candidate = Zeroizing::new(match self.kind {
+            GeneratorKind::Password => generate_password(&self.policy),
+            GeneratorKind::Passphrase => generate_diceware(&self.passphrase),
+        });
+    }
+
+    fn options(&self) -> &'static [GeneratorOption] {
+        match self.kind {
+            GeneratorKind::Password => &GeneratorOption::PASSWORD,
+            GeneratorKind::Passphrase => &GeneratorOption::PASSPHRASE,
+        }
+    }
+
+    pub fn selected_option(&self) -> GeneratorOption {
+        self.options()[self.selected]
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected + 1 < self.options().len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Increase, cycle or toggle the selected option and regenerate
+    pub fn increase(&mut self) {
+        match self.selected_option() {
+            GeneratorOption::Policy => self.cycle_policy(true),
+            GeneratorOption::Length => self.set_length(self.policy.length + 1),
+            GeneratorOption::Words => self.set_words(self.passphrase.words + 1),
+            GeneratorOption::Separator => self.cycle_separator(true),
+            _ => self.toggle(),
+        }
+    }
+
+    /// Decrease, cycle or toggle the selected option and regenerate
+    pub fn decrease(&mut self) {
+        match self.selected_option() {
+            GeneratorOption::Policy => self.cycle_policy(false),
+            GeneratorOption::Length => self.set_length(self.policy.length.saturating_sub(1)),
+            GeneratorOption::Words => self.set_words(self.passphrase.words.saturating_sub(1)),
+            GeneratorOption::Separator => self.cycle_separator(false),
+            _ => self.toggle(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        let flag = match self.selected_option() {
+            GeneratorOption::Kind => return self.toggle_kind(),
+            GeneratorOption::Policy => return self.cycle_policy(true),
+            GeneratorOption::Length | GeneratorOption::Words => return,
+            GeneratorOption::Separator => return self.cycle_separator(true),
+            GeneratorOption::Symbols => &mut self.policy.symbols,
+            GeneratorOption::Digits => &mut self.policy.digits,
+            GeneratorOption::ExcludeAmbiguous => &mut self.policy.exclude_ambiguous,
+            GeneratorOption::Capitalize => &mut self.passphrase.capitalize,
+            GeneratorOption::WordDigit => &mut self.passphrase.include_digit,
+        };
+        *flag = !*flag;
+        if self.kind == GeneratorKind::Password {
+            self.policy_name = None;
+        }
+        self.regenerate();
+    }
+
+    fn toggle_kind(&mut self) {
+        self.kind = match self.kind {
+            GeneratorKind::Password => GeneratorKind::Passphrase,
+            GeneratorKind::Passphrase => GeneratorKind::Password,
+        };
+        self.regenerate();
+    }
+
+    fn set_words(&mut self, words: usize) {
+        let words = words.clamp(MIN_WORDS, MAX_WORDS);
+        if words == self.passphrase.words {
+            return;
+        }
+        self.passphrase.words = words;
+        self.regenerate();
+    }
+
+    fn cycle_separator(&mut self, forward: bool) {
+        let current = SEPARATORS.iter().position(|s| *s == self.passphrase.separator).unwrap_or(0);
+        let next = if forward {
+            (current + 1) % SEPARATORS.len()
+        } else {
+            (current + SEPARATORS.len() - 1) % SEPARATORS.len()
+        };
+        self.passphrase.separator = SEPARATORS[next].to_string();
+        self.regenerate();
+    }
+
+    fn set_length(&mut self, length: usize) {
+        let length = length.clamp(MIN_LENGTH, MAX_LENGTH);
+        if length == self.policy.length {
+            return;
+        }
+        self.policy.length = length;
+        self.policy_name = None;
+        self.regenerate();
+    }
+
+    fn option_value(&self, option: GeneratorOption) -> String {
+        let on_off = |b: bool| if b { "[x]" } else { "[ ]" }.to_string();
+        match option {
+            GeneratorOption::Kind => match self.kind {
+                GeneratorKind::Password => "< password >".to_string(),
+                GeneratorKind::Passphrase => "< passphrase >".to_string(),
+            },
+            GeneratorOption::Policy => format!("< {} >", self.policy_name.as_deref().unwrap_or("custom")),
+            GeneratorOption::Length => format!("< {} >", self.policy.length),
+            GeneratorOption::Symbols => on_off(self.policy.symbols),
+            GeneratorOption::Digits => on_off(self.policy.digits),
+            GeneratorOption::ExcludeAmbiguous => on_off(self.policy.exclude_ambiguous),
+            GeneratorOption::Words => format!("< {} >", self.passphrase.words),
+            GeneratorOption::Separator => match self.passphrase.separator.as_str() {
+                " " => "< space >".to_string(),
+                sep => format!("< {} >", sep),
+            },
+            GeneratorOption::Capitalize => on_off(self.passphrase.capitalize),
+            GeneratorOption::WordDigit => on_off(self.passphrase.include_digit),
+        }
+    }
+
+    fn strength_line(&self) -> String {
+        match self.kind {
+            GeneratorKind::Password => {
+                format!("Strength: {}", strength_label(password_strength(&self.candidate)))
+            }
+            GeneratorKind::Passphrase => format!("Entropy: ~{:.0} bits", self.passphrase.entropy_bits()),
+        }
+    }
+}
+
+pub struct GeneratorPopup<'a> {
+    state: &'a GeneratorState,
+}
+
+impl<'a> GeneratorPopup<'a> {
+    pub fn new(state: &'a GeneratorState) -> Self {
+        Self { state }
+    }
+}
+
+impl Widget for GeneratorPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let popup = centered_rect_fixed(60, 14, area, false);
+        Clear.render(popup, buf);
+
+        let block = create_popup_block(" Generate Password ", Color::Magenta);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        render_footer(buf, popup, " j/k select - h/l change - r new - Enter use - Esc cancel ");
+        render_candidate(inner, buf, self.state);
+        render_separator_line(buf, inner.x, inner.y + 2, inner.width);
+        render_options(inner, buf, self.state);
+    }
+}
+
+fn render_candidate(inner: Rect, buf: &mut Buffer, state: &GeneratorState) {
+    let candidate = truncate_with_ellipsis(&state.candidate, inner.width as usize);
+    buf.set_string(inner.x, inner.y, &candidate, Style::default().fg(Color::Green).add_modifier(Modifier::BOLD));
+
+    buf.set_string(inner.x, inner.y + 1, state.strength_line(), Style::default().fg(Color::DarkGray));
+}
+
+fn render_options(inner: Rect, buf: &mut Buffer, state: &GeneratorState) {
+    let label_width = 20u16;
+    for (i, option) in state.options().iter().enumerate() {
+        let y = inner.y + 3 + i as u16;
+        if y >= inner.y + inner.height {
+            break;
+        }
+        let is_selected = i == state.selected;
+        if is_selected {
+            highlight_row(buf, inner.x, y, inner.width);
+        }
+        let bg = if is_selected { Color::DarkGray } else { Color::Black };
+        let label_fg = if is_selected { Color::Magenta } else { Color::Gray };
+        buf.set_string(inner.x + 1, y, option.label(), Style::default().fg(label_fg).bg(bg));
+        buf.set_string(inner.x + label_width, y, state.option_value(*option), Style::default().fg(Color::Yellow).bg(bg));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_is_clamped_and_applied() {
+        let mut state = GeneratorState::new();
+        state.move_down();
+        state.move_down();
+        state.policy.length = MIN_LENGTH;
+        state.decrease();
+        assert_eq!(state.policy.length, MIN_LENGTH);
+        state.increase();
+        assert_eq!(state.policy.length, MIN_LENGTH + 1);
+        assert_eq!(state.candidate.chars().count(), MIN_LENGTH + 1);
+    }
+
+    #[test]
+    fn test_toggle_options_regenerate() {
+        let mut state = GeneratorState::new();
+        state.move_down();
+        state.move_down();
+        state.move_down();
+        assert_eq!(state.selected_option(), GeneratorOption::Symbols);
+        state.toggle();
+        assert!(!state.policy.symbols);
+        assert!(state.candidate.chars().all(|c| c.is_ascii_alphanumeric()));
+
+        state.move_down();
+        state.toggle();
+        assert!(!state.policy.digits);
+        assert!(state.candidate.chars().all(|c| c.is_ascii_alphabetic()));
+    }
+
+    #[test]
+    fn test_selection_bounds() {
+        let mut state = GeneratorState::new();
+        state.move_up();
+        assert_eq!(state.selected, 0);
+        for _ in 0..10 {
+            state.move_down();
+        }
+        assert_eq!(state.selected_option(), GeneratorOption::ExcludeAmbiguous);
+    }
+
+    #[test]
+    fn test_named_policies() {
+        let mut policies = BTreeMap::new();
+        policies.insert("banking".to_string(), PasswordPolicy { length: 16, symbols: false, ..PasswordPolicy::default() });
+        policies.insert("infra".to_string(), PasswordPolicy::maximum(64));
+
+        let mut state = GeneratorState::with_policies(&policies, Some("banking"));
+        assert_eq!(state.applied_policy().as_deref(), Some("banking"));
+        assert_eq!(state.candidate.len(), 16);
+        assert!(state.candidate.chars().all(|c| c.is_ascii_alphanumeric()));
+
+        state.move_down();
+        state.increase();
+        assert_eq!(state.applied_policy().as_deref(), Some("infra"));
+        assert_eq!(state.candidate.len(), 64);
+        state.increase();
+        assert_eq!(state.applied_policy(), None);
+
+        state.decrease();
+        state.move_down();
+        state.increase();
+        assert_eq!(state.applied_policy(), None, "editing a named policy makes it custom");
+    }
+
+    #[test]
+    fn test_passphrase_kind() {
+        let mut state = GeneratorState::new();
+        state.toggle();
+        assert_eq!(state.kind, GeneratorKind::Passphrase);
+        assert!(state.candidate.matches('-').count() >= 5);
+
+        state.move_down();
+        state.decrease();
+        assert_eq!(state.passphrase.words, 5);
+
+        state.move_down();
+        state.increase();
+        assert_eq!(state.passphrase.separator, ".");
+        assert_eq!(state.candidate.split('.').count(), 5);
+    }
+}
@@ -7,12 +7,21 @@ use ratatui::{
     widgets::{Clear, Widget},
 };
 
-use super::layout::{centered_rect, create_popup_block, render_footer, render_text_at_virtual_x};
+use crate::i18n;
+use crate::input::commands::COMMANDS;
+use crate::input::keymap::{Action, NORMAL_BINDINGS};
+use crate::input::line::EditLine;
+
+use super::layout::{centered_rect, create_popup_block, render_empty_message, render_footer, render_text_at_virtual_x};
 use super::scroll::{render_h_scroll_indicator, render_v_scroll_indicator, ScrollState};
 
 #[derive(Default)]
 pub struct HelpState {
     pub scroll: ScrollState,
+    /// Entries are narrowed to those containing this text
+    pub query: String,
+    /// Text being typed after `/`, if the search prompt is open
+    pub search_input: Option<EditLine>,
 }
 
 impl HelpState {
@@ -20,6 +29,47 @@ impl HelpState {
         Self::default()
     }
 
+    pub fn start_search(&mut self) {
+        self.search_input = Some(EditLine::from(self.query.as_str()));
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        if let Some(input) = &mut self.search_input {
+            input.insert(c);
+            self.scroll.home();
+        }
+    }
+
+    pub fn pop_char(&mut self) {
+        if let Some(input) = &mut self.search_input {
+            input.backspace();
+            self.scroll.home();
+        }
+    }
+
+    /// Keep the typed text as the filter and close the prompt
+    pub fn submit_search(&mut self) {
+        if let Some(input) = self.search_input.take() {
+            self.query = input.trim().to_string();
+        }
+    }
+
+    pub fn cancel_search(&mut self) {
+        self.search_input = None;
+        self.scroll.home();
+    }
+
+    pub fn clear_query(&mut self) {
+        self.query.clear();
+        self.search_input = None;
+        self.scroll.home();
+    }
+
+    /// The filter in effect, following the prompt while it is open
+    pub fn filter(&self) -> &str {
+        self.search_input.as_deref().unwrap_or(&self.query)
+    }
+
     pub fn scroll_up(&mut self, amount: usize) {
         self.scroll.scroll_up(amount);
     }
@@ -63,11 +113,18 @@ impl HelpState {
 
 pub struct HelpScreen<'a> {
     state: &'a HelpState,
+    read_only: bool,
 }
 
 impl<'a> HelpScreen<'a> {
     pub fn new(state: &'a HelpState) -> Self {
-        Self { state }
+        Self { state, read_only: false }
+    }
+
+    /// Grey out the bindings that would change the vault
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
     }
 
     pub fn visible_height(area: Rect) -> u16 {
@@ -75,49 +132,139 @@ impl<'a> HelpScreen<'a> {
         popup.height.saturating_sub(2)
     }
 
-    pub fn max_scroll(area: Rect) -> usize {
+    pub fn max_scroll(area: Rect, state: &HelpState) -> usize {
         let visible = Self::visible_height(area) as usize - 1; // Account for scroll indicator line
-        let content = Self::content_height(area);
+        let content = Self::content_height(area, &help_sections(state.filter()));
         content.saturating_sub(visible)
     }
 
-    pub fn max_h_scroll(area: Rect) -> usize {
+    pub fn max_h_scroll(area: Rect, state: &HelpState) -> usize {
         let popup = centered_rect(65, 65, area);
         let inner_width = popup.width.saturating_sub(2) as usize;
-        single_column_width().saturating_sub(inner_width)
+        single_column_width(&help_sections(state.filter())).saturating_sub(inner_width)
     }
 
-    fn content_height(area: Rect) -> usize {
+    fn content_height(area: Rect, sections: &[HelpSection]) -> usize {
         let popup = centered_rect(65, 65, area);
         let inner_width = popup.width.saturating_sub(2);
         if inner_width >= TWO_COLUMN_MIN_WIDTH {
-            two_column_height()
+            two_column_height(sections)
         } else {
-            single_column_height()
+            sections_height(sections)
         }
     }
 }
 
 const TWO_COLUMN_MIN_WIDTH: u16 = 85;
+/// Indent of the keys under a section header
+const KEY_INDENT: u16 = 4;
+const MIN_KEY_WIDTH: u16 = 14;
+
+pub struct HelpEntry {
+    pub keys: String,
+    pub description: &'static str,
+    /// Greyed out when the vault is open read-only
+    pub changes_vault: bool,
+}
 
-fn single_column_height() -> usize {
-    help_sections().iter().map(|(_, b)| 1 + b.len() + 1).sum::<usize>().saturating_sub(1)
+pub struct HelpSection {
+    pub title: &'static str,
+    pub entries: Vec<HelpEntry>,
 }
 
-fn two_column_height() -> usize {
-    let sections = help_sections();
-    let (left, right) = split_sections_for_columns(&sections);
-    let left_h: usize = left.iter().map(|(_, b)| 1 + b.len() + 1).sum::<usize>().saturating_sub(1);
-    let right_h: usize = right.iter().map(|(_, b)| 1 + b.len() + 1).sum::<usize>().saturating_sub(1);
-    left_h.max(right_h)
+/// The credential form reads its own keys rather than the normal keymap
+const FORM_KEYS: &[(&str, &str)] = &[
+    ("Tab / Shift+Tab", "Next / previous field"),
+    ("Ctrl+g", "Generate password"),
+    ("Ctrl+r", "Fill in a generated password"),
+    ("Ctrl+s", "Toggle password"),
+    ("Ctrl+l", "Lock vault, discarding the form"),
+    ("Enter", "Save"),
+    ("Esc", "Cancel"),
+];
+
+/// Keys of the command line, which edits text rather than using the keymap
+const COMMAND_LINE_KEYS: (&str, &str) = ("↑ / ↓ / Tab", "History / complete");
+
+/// Help content, generated from the normal-mode keymap and the command
+/// registry and narrowed to the entries containing `filter`. A filter
+/// matching a section title keeps the whole section.
+pub fn help_sections(filter: &str) -> Vec<HelpSection> {
+    let mut sections: Vec<HelpSection> = Vec::new();
+    let mut push = |title: &'static str, mut entry: HelpEntry| {
+        let title = i18n::tr(title);
+        entry.description = i18n::tr(entry.description);
+        match sections.iter_mut().find(|s| s.title == title) {
+            Some(section) => section.entries.push(entry),
+            None => sections.push(HelpSection { title, entries: vec![entry] }),
+        }
+    };
+
+    for binding in NORMAL_BINDINGS {
+        let Some(description) = binding.help else { continue };
+        push(binding.section, HelpEntry {
+            keys: binding.keys_label(),
+            description,
+            changes_vault: binding.action.is_mutating(),
+        });
+        if binding.action == Action::EnterCommand {
+            let (keys, description) = COMMAND_LINE_KEYS;
+            push(binding.section, HelpEntry { keys: keys.to_string(), description, changes_vault: false });
+        }
+    }
+    for command in COMMANDS {
+        push("Commands", HelpEntry {
+            keys: command.label(),
+            description: command.help,
+            changes_vault: command.changes_vault(),
+        });
+    }
+    for (keys, description) in FORM_KEYS {
+        push("Form", HelpEntry { keys: keys.to_string(), description, changes_vault: false });
+    }
+
+    let filter = filter.trim().to_lowercase();
+    if filter.is_empty() {
+        return sections;
+    }
+    let matches = |text: &str| text.to_lowercase().contains(&filter);
+    sections
+        .into_iter()
+        .filter_map(|mut section| {
+            if !matches(section.title) {
+                section.entries.retain(|e| matches(&e.keys) || matches(e.description));
+            }
+            (!section.entries.is_empty()).then_some(section)
+        })
+        .collect()
+}
+
+fn section_lines(section: &HelpSection) -> usize {
+    1 + section.entries.len() + 1
+}
+
+fn sections_height(sections: &[HelpSection]) -> usize {
+    sections.iter().map(section_lines).sum::<usize>().saturating_sub(1)
 }
 
-fn single_column_width() -> usize {
+fn two_column_height(sections: &[HelpSection]) -> usize {
+    let split = column_split(sections);
+    sections_height(&sections[..split]).max(sections_height(&sections[split..]))
+}
+
+/// Where a section's descriptions start, past its widest key
+fn description_offset(section: &HelpSection) -> u16 {
+    let widest = section.entries.iter().map(|e| e.keys.chars().count() as u16).max().unwrap_or(0);
+    KEY_INDENT + widest.max(MIN_KEY_WIDTH) + 2
+}
+
+fn single_column_width(sections: &[HelpSection]) -> usize {
     let mut max_width = 0usize;
-    for (header, bindings) in &help_sections() {
-        max_width = max_width.max(header.len());
-        for (_key, desc) in bindings {
-            max_width = max_width.max(16 + desc.len());
+    for section in sections {
+        max_width = max_width.max(section.title.len());
+        let offset = description_offset(section) as usize;
+        for entry in &section.entries {
+            max_width = max_width.max(offset + entry.description.chars().count());
         }
     }
     max_width
@@ -128,20 +275,33 @@ impl Widget for HelpScreen<'_> {
         let popup = centered_rect(65, 65, area);
         Clear.render(popup, buf);
 
-        let block = create_popup_block(" Help Page ", Color::Magenta);
+        let title = match self.state.filter() {
+            "" => " Help Page ".to_string(),
+            filter => format!(" {} ", i18n::trf("Help Page: {}", &[&filter])),
+        };
+        let block = create_popup_block(&title, Color::Magenta);
         let inner = block.inner(popup);
         block.render(popup, buf);
 
+        let sections = help_sections(self.state.filter());
         let use_two_columns = inner.width >= TWO_COLUMN_MIN_WIDTH;
-        let content_height = if use_two_columns { two_column_height() } else { single_column_height() };
+        let content_height = if use_two_columns { two_column_height(&sections) } else { sections_height(&sections) };
         let visible_height = inner.height as usize;
         let max_v = content_height.saturating_sub(visible_height);
-        let max_h = if use_two_columns { 0 } else { HelpScreen::max_h_scroll(area) };
+        let max_h = if use_two_columns { 0 } else { HelpScreen::max_h_scroll(area, self.state) };
 
         let needs_v_scroll = max_v > 0;
         let needs_h_scroll = max_h > 0;
 
-        render_help_footer(buf, popup, needs_h_scroll);
+        match &self.state.search_input {
+            Some(input) => render_footer(buf, popup, &format!(" /{}_ - Enter keep - Esc cancel ", input.as_str())),
+            None => render_help_footer(buf, popup, needs_h_scroll, !self.state.query.is_empty()),
+        }
+
+        if sections.is_empty() {
+            render_empty_message(inner, buf, "No matching keys or commands");
+            return;
+        }
 
         // Calculate content area that reserves bottom line for scroll indicator
         let content_height_adjusted = if needs_v_scroll {
@@ -152,9 +312,10 @@ impl Widget for HelpScreen<'_> {
         let content_area = Rect::new(inner.x, inner.y, inner.width, content_height_adjusted);
 
         if use_two_columns {
-            render_help_two_columns(content_area, buf, self.state.scroll.v_scroll);
+            render_help_two_columns(content_area, buf, &sections, self.state.scroll.v_scroll, self.read_only);
         } else {
-            render_help_single_column(content_area, buf, self.state.scroll.v_scroll, self.state.scroll.h_scroll);
+            let scroll = &self.state.scroll;
+            render_help_single_column(content_area, buf, &sections, scroll.v_scroll, scroll.h_scroll, self.read_only);
         }
 
         // Render scroll indicators
@@ -167,18 +328,25 @@ impl Widget for HelpScreen<'_> {
     }
 }
 
-fn render_help_footer(buf: &mut Buffer, popup: Rect, needs_h_scroll: bool) {
-    let text = if needs_h_scroll {
-        " j/k scroll - h/l pan - gg/G top/bottom - q close "
-    } else {
-        " j/k scroll - gg/G top/bottom - q close "
+fn render_help_footer(buf: &mut Buffer, popup: Rect, needs_h_scroll: bool, filtered: bool) {
+    let text = match (needs_h_scroll, filtered) {
+        (true, true) => " j/k scroll - h/l pan - / search - BS clear - q close ",
+        (true, false) => " j/k scroll - h/l pan - / search - q close ",
+        (false, true) => " j/k scroll - gg/G top/bottom - / search - BS clear - q close ",
+        (false, false) => " j/k scroll - gg/G top/bottom - / search - q close ",
     };
     render_footer(buf, popup, text);
 }
 
-fn render_help_single_column(area: Rect, buf: &mut Buffer, v_scroll: usize, h_scroll: usize) {
-    let sections = help_sections();
-    let lines = build_help_lines(&sections);
+fn render_help_single_column(
+    area: Rect,
+    buf: &mut Buffer,
+    sections: &[HelpSection],
+    v_scroll: usize,
+    h_scroll: usize,
+    read_only: bool,
+) {
+    let lines = build_help_lines(sections, read_only);
 
     for (i, line) in lines.iter().enumerate().skip(v_scroll) {
         let y = area.y + (i - v_scroll) as u16;
@@ -189,11 +357,11 @@ fn render_help_single_column(area: Rect, buf: &mut Buffer, v_scroll: usize, h_sc
     }
 }
 
-fn render_help_two_columns(area: Rect, buf: &mut Buffer, v_scroll: usize) {
-    let sections = help_sections();
-    let (left_sections, right_sections) = split_sections_for_columns(&sections);
-    let left_lines = build_help_lines(&left_sections);
-    let right_lines = build_help_lines(&right_sections);
+fn render_help_two_columns(area: Rect, buf: &mut Buffer, sections: &[HelpSection], v_scroll: usize, read_only: bool) {
+    let split = column_split(sections);
+    let (left_sections, right_sections) = sections.split_at(split);
+    let left_lines = build_help_lines(left_sections, read_only);
+    let right_lines = build_help_lines(right_sections, read_only);
 
     let gap = 4u16;
     let col_width = (area.width.saturating_sub(gap)) / 2;
@@ -216,16 +384,19 @@ fn render_help_two_columns(area: Rect, buf: &mut Buffer, v_scroll: usize) {
 
 enum HelpLine<'a> {
     Header(&'a str),
-    Binding(&'a str, &'a str),
+    /// Key, description, the description's offset and whether the binding
+    /// is disabled
+    Binding(&'a str, &'a str, u16, bool),
     Empty,
 }
 
-fn build_help_lines<'a>(sections: &'a [(&'a str, Vec<(&'a str, &'a str)>)]) -> Vec<HelpLine<'a>> {
+fn build_help_lines(sections: &[HelpSection], read_only: bool) -> Vec<HelpLine<'_>> {
     let mut lines = Vec::new();
-    for (i, (header, bindings)) in sections.iter().enumerate() {
-        lines.push(HelpLine::Header(header));
-        for (key, desc) in bindings {
-            lines.push(HelpLine::Binding(key, desc));
+    for (i, section) in sections.iter().enumerate() {
+        lines.push(HelpLine::Header(section.title));
+        let offset = description_offset(section);
+        for entry in &section.entries {
+            lines.push(HelpLine::Binding(&entry.keys, entry.description, offset, read_only && entry.changes_vault));
         }
         if i < sections.len() - 1 {
             lines.push(HelpLine::Empty);
@@ -240,12 +411,13 @@ fn render_help_line(x: u16, y: u16, width: u16, line: &HelpLine, buf: &mut Buffe
             let style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
             buf.set_string(x, y, *title, style);
         }
-        HelpLine::Binding(key, desc) => {
-            buf.set_string(x + 4, y, *key, Style::default().fg(Color::Cyan));
-            let desc_x = x + 16;
-            let desc_width = width.saturating_sub(16) as usize;
+        HelpLine::Binding(key, desc, desc_offset, disabled) => {
+            let (key_style, desc_style) = binding_styles(*disabled);
+            buf.set_string(x + KEY_INDENT, y, *key, key_style);
+            let desc_x = x + desc_offset;
+            let desc_width = width.saturating_sub(*desc_offset) as usize;
             let truncated: String = desc.chars().take(desc_width).collect();
-            buf.set_string(desc_x, y, &truncated, Style::default().fg(Color::Gray));
+            buf.set_string(desc_x, y, &truncated, desc_style);
         }
         HelpLine::Empty => {}
     }
@@ -264,83 +436,54 @@ fn render_help_line_scrollable(
             let style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
             render_text_at_virtual_x(buf, base_x, y, view_width, h_scroll, 0, title, style);
         }
-        HelpLine::Binding(key, desc) => {
-            render_text_at_virtual_x(buf, base_x, y, view_width, h_scroll, 4, key, Style::default().fg(Color::Cyan));
-            render_text_at_virtual_x(buf, base_x, y, view_width, h_scroll, 16, desc, Style::default().fg(Color::Gray));
+        HelpLine::Binding(key, desc, desc_offset, disabled) => {
+            let (key_style, desc_style) = binding_styles(*disabled);
+            render_text_at_virtual_x(buf, base_x, y, view_width, h_scroll, KEY_INDENT, key, key_style);
+            render_text_at_virtual_x(buf, base_x, y, view_width, h_scroll, *desc_offset, desc, desc_style);
         }
         HelpLine::Empty => {}
     }
 }
 
-fn split_sections_for_columns<'a>(
-    sections: &'a [(&'a str, Vec<(&'a str, &'a str)>)],
-) -> (Vec<(&'a str, Vec<(&'a str, &'a str)>)>, Vec<(&'a str, Vec<(&'a str, &'a str)>)>) {
-    let total_lines: usize = sections.iter().map(|(_, b)| 1 + b.len() + 1).sum();
-    let target = total_lines / 2;
-
-    let mut left = Vec::new();
-    let mut right = Vec::new();
-    let mut current = 0;
-
-    for section in sections {
-        let section_lines = 1 + section.1.len() + 1;
-        if current < target {
-            left.push((section.0, section.1.clone()));
-        } else {
-            right.push((section.0, section.1.clone()));
-        }
-        current += section_lines;
+fn binding_styles(disabled: bool) -> (Style, Style) {
+    if disabled {
+        let style = Style::default().fg(Color::DarkGray);
+        return (style, style);
     }
+    (Style::default().fg(Color::Cyan), Style::default().fg(Color::Gray))
+}
 
-    (left, right)
+/// How many sections go in the left column, keeping the taller column as
+/// short as possible
+fn column_split(sections: &[HelpSection]) -> usize {
+    (0..=sections.len())
+        .min_by_key(|&i| sections_height(&sections[..i]).max(sections_height(&sections[i..])))
+        .unwrap_or(0)
 }
 
-fn help_sections() -> Vec<(&'static str, Vec<(&'static str, &'static str)>)> {
-    vec![
-        ("Navigation", vec![
-            ("j / ↓", "Move down"),
-            ("k / ↑", "Move up"),
-            ("gg", "Go to top"),
-            ("G", "Go to bottom"),
-            ("Ctrl-d", "Half page down"),
-            ("Ctrl-u", "Half page up"),
-            ("Ctrl-f", "Page down"),
-            ("Ctrl-b", "Page up"),
-        ]),
-        ("Actions", vec![
-            ("l / Enter", "View details"),
-            ("n", "New credential"),
-            ("e", "Edit credential"),
-            ("dd / x", "Delete credential"),
-        ]),
-        ("Clipboard", vec![
-            ("yy / c", "Copy password/secret"),
-            ("u", "Copy username"),
-            ("T", "Copy TOTP code"),
-        ]),
-        ("View", vec![
-            ("Ctrl+s", "Toggle password"),
-            ("/", "Search"),
-            ("i", "Show logs"),
-            ("t", "Show tags"),
-        ]),
-        ("Commands", vec![
-            (":", "Command mode"),
-            (":q", "Quit"),
-            (":clear", "Clear message"),
-            (":changepw", "Change master key"),
-            (":audit", "Verify audit log integrity"),
-            (":log", "View logs"),
-            (":tag", "View tags"),
-            (":new", "New credential"),
-            (":gen", "Generate password"),
-        ]),
-        ("Other", vec![
-            ("?", "Show this help"),
-            ("Ctrl+l", "Clear message"),
-            ("Ctrl+p", "Change master key"),
-            ("L", "Lock vault"),
-            ("q", "Quit"),
-        ]),
-    ]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_help_sections() {
+        let sections = help_sections("");
+        let keys = |title: &str| -> Vec<String> {
+            let section = sections.iter().find(|s| s.title == title).unwrap();
+            section.entries.iter().map(|e| e.keys.clone()).collect()
+        };
+        assert!(keys("Navigation").contains(&"Ctrl+f / PgDn".to_string()));
+        assert!(keys("Navigation").contains(&"Ctrl+b / PgUp".to_string()));
+        assert!(keys("Panes").contains(&"Ctrl+w z".to_string()));
+        assert!(keys("Commands").contains(&":merge NAME".to_string()));
+
+        let filtered = help_sections("PANE");
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].title, "Panes");
+
+        let filtered = help_sections("tag");
+        assert!(filtered.iter().any(|s| s.entries.iter().any(|e| e.keys == "t")));
+        assert!(filtered.iter().all(|s| s.title != "Navigation"));
+        assert!(help_sections("no such key").is_empty());
+    }
 }
@@ -0,0 +1,102 @@
+//! Vault info popup and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Widget},
+};
+
+use super::layout::{centered_rect_fixed, create_popup_block, render_footer};
+
+const LABEL_WIDTH: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InfoLine {
+    Heading(&'static str),
+    Field(String, String),
+    /// A count or detail under the field above it
+    Item(String, String),
+}
+
+impl InfoLine {
+    pub fn field(label: &str, value: impl Into<String>) -> Self {
+        Self::Field(label.to_string(), value.into())
+    }
+
+    pub fn item(label: impl Into<String>, value: impl Into<String>) -> Self {
+        Self::Item(label.into(), value.into())
+    }
+}
+
+#[derive(Default)]
+pub struct InfoState {
+    pub lines: Vec<InfoLine>,
+    /// First line shown
+    pub scroll: u16,
+}
+
+impl InfoState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_lines(&mut self, lines: Vec<InfoLine>) {
+        self.lines = lines;
+        self.scroll = 0;
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1);
+    }
+
+    pub fn scroll_down(&mut self) {
+        if (self.scroll as usize) + 1 < self.lines.len() {
+            self.scroll += 1;
+        }
+    }
+}
+
+pub struct InfoPopup<'a> {
+    state: &'a InfoState,
+}
+
+impl<'a> InfoPopup<'a> {
+    pub fn new(state: &'a InfoState) -> Self {
+        Self { state }
+    }
+}
+
+impl Widget for InfoPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let height = (self.state.lines.len() as u16 + 2).clamp(8, (area.height * 80) / 100);
+        let popup = centered_rect_fixed(72, height, area, true);
+        Clear.render(popup, buf);
+
+        let block = create_popup_block(" Vault info ", Color::Cyan);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        render_footer(buf, popup, " j/k scroll - q close ");
+
+        let label = |text: &str, indent: usize| {
+            let text = format!("{:indent$}{:<width$}", "", text, indent = indent, width = LABEL_WIDTH - indent);
+            Span::styled(text, Style::default().fg(Color::Gray))
+        };
+        let value = |text: &str| Span::styled(text.to_string(), Style::default().fg(Color::White));
+        let lines: Vec<Line> = self
+            .state
+            .lines
+            .iter()
+            .map(|line| match line {
+                InfoLine::Heading(title) => {
+                    Line::styled(*title, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+                }
+                InfoLine::Field(name, text) => Line::from(vec![label(name, 1), value(text)]),
+                InfoLine::Item(name, text) => Line::from(vec![label(name, 3), value(text)]),
+            })
+            .collect();
+        Paragraph::new(lines).scroll((self.state.scroll, 0)).render(inner, buf);
+    }
+}
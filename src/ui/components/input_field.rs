@@ -6,17 +6,21 @@ use ratatui::{
     style::{Color, Style},
     widgets::Widget,
 };
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+use zeroize::Zeroizing;
+
+use crate::input::line::EditLine;
 
 pub struct InputField<'a> {
     label: &'a str,
-    value: &'a str,
-    cursor: usize,
+    line: &'a EditLine,
     masked: bool,
 }
 
 impl<'a> InputField<'a> {
-    pub fn new(label: &'a str, value: &'a str, cursor: usize) -> Self {
-        Self { label, value, cursor, masked: false }
+    pub fn new(label: &'a str, line: &'a EditLine) -> Self {
+        Self { label, line, masked: false }
     }
 
     pub fn masked(mut self) -> Self {
@@ -31,8 +35,43 @@ impl Widget for InputField<'_> {
 
         let input_y = area.y + 1;
         render_input_background(buf, area.x, input_y, area.width);
-        render_input_value(buf, area.x, input_y, self.value, self.masked);
-        render_input_cursor(buf, area.x, input_y, area.width, self.cursor);
+        let visible = VisibleText::new(self.line, self.masked, area.width as usize);
+        buf.set_string(area.x, input_y, visible.text.as_str(), Style::default().fg(Color::White));
+        render_input_cursor(buf, area.x, input_y, area.width, visible.cursor);
+    }
+}
+
+/// The stretch of a line that fits a field, scrolled to keep the cursor
+/// in view. Masked text shows one `*` per grapheme, and a wide character
+/// is never cut in half.
+pub struct VisibleText {
+    pub text: Zeroizing<String>,
+    /// Column of the cursor within `text`
+    pub cursor: usize,
+}
+
+impl VisibleText {
+    pub fn new(line: &EditLine, masked: bool, width: usize) -> Self {
+        let cursor = line.cursor_column(masked);
+        // The cursor needs a cell of its own past the last character
+        let skip = (cursor + 1).saturating_sub(width);
+        let mut text = Zeroizing::new(String::new());
+        let mut column = 0;
+        let mut start = None;
+        for grapheme in line.graphemes(true) {
+            let shown = if masked { "*" } else { grapheme };
+            let grapheme_width = shown.width();
+            if column >= skip {
+                let first = *start.get_or_insert(column);
+                if column + grapheme_width > first + width {
+                    break;
+                }
+                text.push_str(shown);
+            }
+            column += grapheme_width;
+        }
+        let start = start.unwrap_or(column).min(cursor);
+        Self { text, cursor: cursor - start }
     }
 }
 
@@ -44,17 +83,35 @@ fn render_input_background(buf: &mut Buffer, x: u16, y: u16, width: u16) {
     }
 }
 
-fn render_input_value(buf: &mut Buffer, x: u16, y: u16, value: &str, masked: bool) {
-    let display = if masked { "*".repeat(value.len()) } else { value.to_string() };
-    buf.set_string(x, y, &display, Style::default().fg(Color::White));
-}
-
-fn render_input_cursor(buf: &mut Buffer, x: u16, y: u16, width: u16, cursor: usize) {
-    let cursor_x = x + cursor as u16;
-    if cursor_x >= x + width {
+pub fn render_input_cursor(buf: &mut Buffer, x: u16, y: u16, width: u16, column: usize) {
+    if column >= width as usize {
         return;
     }
+    let cursor_x = x + column as u16;
     if let Some(cell) = buf.cell_mut((cursor_x, y)) {
         cell.set_style(Style::default().bg(Color::White).fg(Color::Black));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_text() {
+        let line = EditLine::from("ab語🔑cd");
+        let visible = VisibleText::new(&line, false, 20);
+        assert_eq!((visible.text.as_str(), visible.cursor), ("ab語🔑cd", 8));
+
+        // Scrolled so the cursor fits, without splitting a wide character
+        let visible = VisibleText::new(&line, false, 6);
+        assert_eq!((visible.text.as_str(), visible.cursor), ("🔑cd", 4));
+        let visible = VisibleText::new(&line, true, 6);
+        assert_eq!((visible.text.as_str(), visible.cursor), ("*****", 5));
+
+        let mut line = line;
+        line.home();
+        let visible = VisibleText::new(&line, false, 5);
+        assert_eq!((visible.text.as_str(), visible.cursor), ("ab語", 0));
+    }
+}
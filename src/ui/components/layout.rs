@@ -6,6 +6,10 @@ use ratatui::{
     style::{Color, Style},
     widgets::{Widget, Block, BorderType, Borders, Paragraph},
 };
+use unicode_width::UnicodeWidthStr;
+
+use crate::i18n;
+use crate::ui::theme;
 
 pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let content_area = Rect::new(r.x, r.y, r.width, r.height.saturating_sub(2));
@@ -34,9 +38,9 @@ pub fn centered_rect_fixed(width: u16, height: u16, r: Rect, unlocked: bool) ->
     Rect::new(x, final_y, width.min(r.width), height.min(r.height))
 }
 
-pub fn create_popup_block(title: &str, color: Color) -> Block {
+pub fn create_popup_block(title: &str, color: Color) -> Block<'static> {
     Block::default()
-        .title(title)
+        .title(i18n::tr_padded(title))
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
         .border_style(Style::default().fg(color))
@@ -44,36 +48,48 @@ pub fn create_popup_block(title: &str, color: Color) -> Block {
 }
 
 pub fn render_empty_message(area: Rect, buf: &mut Buffer, msg: &str) {
-    Paragraph::new(msg)
-        .style(Style::default().fg(Color::DarkGray))
+    Paragraph::new(i18n::tr(msg))
+        .style(Style::default().fg(theme::muted()))
         .render(area, buf);
 }
 
 pub fn render_separator_line(buf: &mut Buffer, x: u16, y: u16, width: u16) {
     for px in x..x + width {
-        buf.set_string(px, y, "─", Style::default().fg(Color::DarkGray));
+        buf.set_string(px, y, "─", Style::default().fg(theme::muted()));
     }
 }
 
 pub fn render_footer(buf: &mut Buffer, popup: Rect, text: &str) {
+    if popup.height == 0 {
+        return;
+    }
     let y = popup.y + popup.height - 1;
-    let x = popup.x + (popup.width.saturating_sub(text.len() as u16)) / 2;
-    buf.set_string(x, y, text, Style::default().fg(Color::DarkGray));
+    let text = i18n::tr_padded(text);
+    let x = popup.x + (popup.width.saturating_sub(text.width() as u16)) / 2;
+    buf.set_string(x, y, &text, Style::default().fg(theme::muted()));
 }
 
 pub fn highlight_row(buf: &mut Buffer, x: u16, y: u16, width: u16) {
     for px in x..x + width {
         if let Some(cell) = buf.cell_mut((px, y)) {
             cell.set_bg(Color::DarkGray);
+            cell.modifier |= theme::highlight_modifier();
         }
     }
 }
 
+/// What privacy mode shows instead of a username or URL. The mask has a
+/// fixed width so it does not give away the length.
+pub fn privacy_mask(s: &str) -> String {
+    if s.is_empty() { String::new() } else { "••••••".to_string() }
+}
+
 pub fn truncate_with_ellipsis(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
+    if s.chars().count() <= max_len {
         return s.to_string();
     }
-    format!("{}…", &s[..max_len.saturating_sub(1)])
+    let kept: String = s.chars().take(max_len.saturating_sub(1)).collect();
+    format!("{}…", kept)
 }
 
 pub fn render_text_at_virtual_x(
@@ -1,7 +1,8 @@
 //! List View Component
 //!
-//! Displays credentials in a scrollable list.
+//! Displays credentials in a scrollable, column-aligned list.
 
+use chrono::{DateTime, Local, NaiveDate};
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
@@ -10,8 +11,19 @@ use ratatui::{
     widgets::{Block, List, ListItem, ListState, StatefulWidget, Widget},
 };
 
+use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthStr;
+
 use crate::db::models::CredentialType;
 use crate::ui::renderer::Renderer;
+use crate::ui::theme;
+use crate::vault::expiry;
+
+use super::layout::{privacy_mask, truncate_with_ellipsis};
+
+/// Selection symbol and type icon before the first column
+const PREFIX_WIDTH: u16 = 4;
+const COLUMN_GAP: u16 = 2;
 
 #[derive(Debug, Clone)]
 pub struct CredentialItem {
@@ -19,7 +31,218 @@ pub struct CredentialItem {
     pub name: String,
     pub username: Option<String>,
     pub credential_type: CredentialType,
+    /// Type name and icon, a custom type's own where it has them
+    pub type_name: String,
+    pub type_icon: String,
     pub tags: Vec<String>,
+    pub updated_at: DateTime<Local>,
+    /// An API key's expiry date, flagged once it is near
+    pub expires_on: Option<NaiveDate>,
+}
+
+impl CredentialItem {
+    /// Whether the name or username contains `term`, ignoring case
+    pub fn matches(&self, term: &str) -> bool {
+        !match_ranges(&self.name, term).is_empty()
+            || self.username.as_deref().is_some_and(|u| !match_ranges(u, term).is_empty())
+    }
+
+    /// The expiry date, if it has passed or is near enough to flag
+    pub fn expiring(&self, now: DateTime<Local>) -> Option<NaiveDate> {
+        self.expires_on.filter(|&expires| expiry::expires_soon(expires, now.date_naive()))
+    }
+}
+
+/// Char ranges of `text` that match any word of `term`, ignoring case,
+/// in order and with overlaps joined
+pub fn match_ranges(text: &str, term: &str) -> Vec<(usize, usize)> {
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let text: Vec<char> = text.chars().map(fold).collect();
+
+    let mut ranges = Vec::new();
+    for word in term.split_whitespace() {
+        let word: Vec<char> = word.chars().map(fold).collect();
+        let mut i = 0;
+        while i + word.len() <= text.len() {
+            if text[i..i + word.len()] == word[..] {
+                ranges.push((i, i + word.len()));
+                i += word.len();
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Split a cell into spans with the matches of `term` picked out
+fn highlighted_spans(cell: String, term: Option<&str>, style: Style) -> Vec<Span<'static>> {
+    let ranges = term.map(|t| match_ranges(&cell, t)).unwrap_or_default();
+    if ranges.is_empty() {
+        return vec![Span::styled(cell, style)];
+    }
+
+    let chars: Vec<char> = cell.chars().collect();
+    let match_style = style.fg(Color::Black).bg(Color::Yellow);
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for (start, end) in ranges {
+        if start > last {
+            spans.push(Span::styled(chars[last..start].iter().collect::<String>(), style));
+        }
+        spans.push(Span::styled(chars[start..end].iter().collect::<String>(), match_style));
+        last = end;
+    }
+    if last < chars.len() {
+        spans.push(Span::styled(chars[last..].iter().collect::<String>(), style));
+    }
+    spans
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListColumn {
+    Name,
+    Username,
+    Type,
+    Tags,
+    Updated,
+}
+
+impl ListColumn {
+    pub const ALL: [Self; 5] = [Self::Name, Self::Username, Self::Type, Self::Tags, Self::Updated];
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Name => "NAME",
+            Self::Username => "USERNAME",
+            Self::Type => "TYPE",
+            Self::Tags => "TAGS",
+            Self::Updated => "UPDATED",
+        }
+    }
+
+    /// Narrowest useful width; fixed-width columns never grow past it
+    fn min_width(self) -> u16 {
+        match self {
+            Self::Name => 16,
+            Self::Username => 12,
+            Self::Type => 11,
+            Self::Tags => 10,
+            Self::Updated => 7,
+        }
+    }
+
+    /// Share of the spare width a flexible column takes; 0 for fixed columns
+    fn weight(self) -> u16 {
+        match self {
+            Self::Name => 3,
+            Self::Username | Self::Tags => 2,
+            Self::Type | Self::Updated => 0,
+        }
+    }
+
+    /// Columns are dropped lowest priority first when the list is narrow
+    fn priority(self) -> u8 {
+        match self {
+            Self::Name => 4,
+            Self::Username => 3,
+            Self::Updated => 2,
+            Self::Type => 1,
+            Self::Tags => 0,
+        }
+    }
+
+    fn cell(self, item: &CredentialItem, now: DateTime<Local>) -> String {
+        match self {
+            Self::Name => match item.expiring(now) {
+                Some(expires) => format!("{} ({})", item.name, expiry::short(expires, now.date_naive())),
+                None => item.name.clone(),
+            },
+            Self::Username => item.username.clone().unwrap_or_default(),
+            Self::Type => item.type_name.clone(),
+            Self::Tags => item.tags.join(", "),
+            Self::Updated => format_age(item.updated_at, now),
+        }
+    }
+
+    fn color(self, item: &CredentialItem, now: DateTime<Local>) -> Color {
+        match self {
+            Self::Name => match item.expiring(now) {
+                Some(expires) if expiry::days_left(expires, now.date_naive()) <= 0 => Color::Red,
+                Some(_) => Color::Yellow,
+                None => Color::White,
+            },
+            Self::Type => type_color(item.credential_type),
+            Self::Tags => Color::Cyan,
+            Self::Username | Self::Updated if theme::high_contrast() => Color::Gray,
+            Self::Username | Self::Updated => Renderer::hex_color(0x4C566A),
+        }
+    }
+}
+
+/// Fit the configured columns into `width`, dropping the lowest priority
+/// ones until the rest fit, then sharing the spare width between the
+/// flexible columns. The name column is always shown.
+pub fn layout_columns(columns: &[ListColumn], width: u16) -> Vec<(ListColumn, u16)> {
+    let mut shown: Vec<ListColumn> = Vec::new();
+    if !columns.contains(&ListColumn::Name) {
+        shown.push(ListColumn::Name);
+    }
+    for column in columns {
+        if !shown.contains(column) {
+            shown.push(*column);
+        }
+    }
+
+    let needed = |cols: &[ListColumn]| {
+        cols.iter().map(|c| c.min_width()).sum::<u16>() + COLUMN_GAP * cols.len().saturating_sub(1) as u16
+    };
+    while shown.len() > 1 && needed(&shown) > width {
+        let lowest = shown.iter().enumerate().min_by_key(|(_, c)| c.priority()).map(|(i, _)| i);
+        if let Some(i) = lowest {
+            shown.remove(i);
+        }
+    }
+
+    let spare = width.saturating_sub(needed(&shown));
+    let total_weight: u16 = shown.iter().map(|c| c.weight()).sum();
+    let mut widths: Vec<(ListColumn, u16)> = shown
+        .iter()
+        .map(|c| match total_weight {
+            0 => (*c, c.min_width()),
+            _ => (*c, c.min_width() + spare * c.weight() / total_weight),
+        })
+        .collect();
+
+    // Rounding leftovers go to the name column
+    let used = needed(&shown) + widths.iter().map(|(c, w)| w - c.min_width()).sum::<u16>();
+    if let Some((_, w)) = widths.iter_mut().find(|(c, _)| *c == ListColumn::Name) {
+        *w = (*w + width.saturating_sub(used)).min(width);
+    }
+    widths
+}
+
+/// Compact age such as `5m`, `3d` or `2y`
+pub fn format_age(time: DateTime<Local>, now: DateTime<Local>) -> String {
+    let age = now.signed_duration_since(time);
+    match age.num_minutes() {
+        m if m < 1 => "now".to_string(),
+        m if m < 60 => format!("{}m", m),
+        _ if age.num_hours() < 24 => format!("{}h", age.num_hours()),
+        _ if age.num_days() < 30 => format!("{}d", age.num_days()),
+        _ if age.num_days() < 365 => format!("{}mo", age.num_days() / 30),
+        _ => format!("{}y", age.num_days() / 365),
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -28,6 +251,8 @@ pub struct ListViewState {
     pub total: usize,
     pub offset: usize,
     pub search: Option<String>,
+    /// Where the rows were last drawn, for mouse clicks
+    pub rows_area: Rect,
     list_state: ListState,
 }
 
@@ -38,6 +263,7 @@ impl Default for ListViewState {
             total: 0,
             offset: 0,
             search: None,
+            rows_area: Rect::default(),
             list_state: ListState::default(),
         }
     }
@@ -90,6 +316,13 @@ impl ListViewState {
         }
     }
 
+    /// Select a row by index, clamped to the list
+    pub fn move_to(&mut self, index: usize) {
+        if self.total > 0 {
+            self.select(Some(index.min(self.total - 1)));
+        }
+    }
+
     pub fn page_up(&mut self, page_size: usize) {
         if self.total == 0 {
             return;
@@ -106,11 +339,42 @@ impl ListViewState {
         self.select(Some(new_index));
     }
 
-    pub fn list_state_mut(&mut self) -> &mut ListState {
-        &mut self.list_state
+    /// First row on screen
+    pub fn scroll_offset(&self) -> usize {
+        self.list_state.offset()
+    }
+
+    /// Scroll so `offset` is the first row, as far as the selection allows
+    pub fn set_scroll_offset(&mut self, offset: usize) {
+        *self.list_state.offset_mut() = offset;
+    }
+
+    /// The item drawn at a screen position, if any
+    pub fn index_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.rows_area;
+        let inside = column >= area.x && column < area.x + area.width && row >= area.y && row < area.y + area.height;
+        if !inside {
+            return None;
+        }
+        let index = self.list_state.offset() + (row - area.y) as usize;
+        (index < self.total).then_some(index)
     }
 }
 
+/// First row to draw so the selection stays in view, moving no further
+/// than needed from where the list was scrolled last
+fn scroll_offset(offset: usize, selected: Option<usize>, height: usize, total: usize) -> usize {
+    let mut offset = offset.min(total.saturating_sub(height));
+    if let Some(selected) = selected {
+        if selected < offset {
+            offset = selected;
+        } else if height > 0 && selected >= offset + height {
+            offset = selected + 1 - height;
+        }
+    }
+    offset
+}
+
 fn compute_selection_after_total_change(selected: Option<usize>, total: usize) -> Option<usize> {
     if total == 0 {
         return None;
@@ -124,21 +388,36 @@ fn compute_selection_after_total_change(selected: Option<usize>, total: usize) -
 
 pub struct CredentialList<'a> {
     items: &'a [CredentialItem],
+    first_row: usize,
     block: Option<Block<'a>>,
     highlight_style: Style,
     show_username: bool,
+    columns: &'a [ListColumn],
+    search_term: Option<&'a str>,
+    private: bool,
 }
 
 impl<'a> CredentialList<'a> {
     pub fn new(items: &'a [CredentialItem]) -> Self {
         Self {
             items,
+            first_row: 0,
             block: None,
             highlight_style: Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD),
             show_username: true,
+            columns: &ListColumn::ALL,
+            search_term: None,
+            private: false,
         }
     }
 
+    /// List row of the first item, when `items` is a window of a longer
+    /// list whose length is the state's `total`
+    pub fn first_row(mut self, row: usize) -> Self {
+        self.first_row = row;
+        self
+    }
+
     pub fn block(mut self, block: Block<'a>) -> Self {
         self.block = Some(block);
         self
@@ -153,6 +432,23 @@ impl<'a> CredentialList<'a> {
         self.show_username = show;
         self
     }
+
+    pub fn columns(mut self, columns: &'a [ListColumn]) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Highlight this search text in names and usernames
+    pub fn search_term(mut self, term: Option<&'a str>) -> Self {
+        self.search_term = term;
+        self
+    }
+
+    /// Privacy mode: mask the username column
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
 }
 
 fn type_color(cred_type: CredentialType) -> Color {
@@ -170,55 +466,87 @@ fn type_color(cred_type: CredentialType) -> Color {
 
 fn build_selection_symbol(is_selected: bool) -> Span<'static> {
     if is_selected {
-        Span::styled(" ", Style::default().fg(Color::Magenta).bg(Color::DarkGray))
+        Span::styled(theme::selection_marker(), Style::default().fg(Color::Magenta).bg(Color::DarkGray))
     } else {
         Span::raw("  ")
     }
 }
 
-fn build_item_spans<'a>(
-    item: &'a CredentialItem,
+fn pad_cell(text: &str, width: u16, last: bool) -> String {
+    let text = truncate_with_ellipsis(text, width as usize);
+    match last {
+        true => text,
+        false => format!("{:<w$}", text, w = (width + COLUMN_GAP) as usize),
+    }
+}
+
+/// The tags column: tags with a style as chips, the rest as text, cut
+/// off with an ellipsis where they run out of room
+fn tag_spans(tags: &[String], width: u16, last: bool, plain: Style) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut left = width as usize;
+    for (i, tag) in tags.iter().enumerate() {
+        if i > 0 {
+            let separator = if theme::is_styled_tag(tag) || theme::is_styled_tag(&tags[i - 1]) { " " } else { ", " };
+            if separator.len() >= left {
+                break;
+            }
+            spans.push(Span::styled(separator, plain));
+            left -= separator.len();
+        }
+        let span = theme::tag_span(tag, plain);
+        let span_width = span.width();
+        if span_width > left {
+            let cut = truncate_with_ellipsis(&span.content, left);
+            left -= cut.width();
+            spans.push(Span::styled(cut, span.style));
+            break;
+        }
+        left -= span_width;
+        spans.push(span);
+    }
+    if !last {
+        spans.push(Span::styled(" ".repeat(left + COLUMN_GAP as usize), plain));
+    }
+    spans
+}
+
+fn build_item_spans(
+    item: &CredentialItem,
     is_selected: bool,
     highlight_style: Style,
-    show_username: bool,
-) -> Vec<Span<'a>> {
+    layout: &[(ListColumn, u16)],
+    now: DateTime<Local>,
+    search_term: Option<&str>,
+    private: bool,
+) -> Vec<Span<'static>> {
     let base_style = if is_selected { highlight_style } else { Style::default() };
-    let icon = item.credential_type.icon();
-    let color = type_color(item.credential_type);
+    let icon = &item.type_icon;
     let mut spans = vec![
         build_selection_symbol(is_selected),
-        Span::styled(format!("{} ", icon), base_style.fg(color)),
-        Span::styled(item.name.as_str(), base_style.fg(Color::White)),
+        Span::styled(format!("{} ", icon), base_style.fg(type_color(item.credential_type))),
     ];
-    append_username_span(&mut spans, item, base_style, show_username);
+    for (i, (column, width)) in layout.iter().enumerate() {
+        if *column == ListColumn::Tags {
+            spans.extend(tag_spans(&item.tags, *width, i + 1 == layout.len(), base_style.fg(column.color(item, now))));
+            continue;
+        }
+        let masked = private && *column == ListColumn::Username;
+        let text = if masked { privacy_mask(&column.cell(item, now)) } else { column.cell(item, now) };
+        let cell = pad_cell(&text, *width, i + 1 == layout.len());
+        let term = search_term.filter(|_| *column == ListColumn::Name || (*column == ListColumn::Username && !masked));
+        spans.extend(highlighted_spans(cell, term, base_style.fg(column.color(item, now))));
+    }
     spans
 }
 
-fn append_username_span<'a>(spans: &mut Vec<Span<'a>>, item: &'a CredentialItem, base_style: Style, show_username: bool) {
-    if !show_username { return }
-    let Some(ref username) = item.username else { return };
-    spans.push(Span::styled(
-        format!(" ({})", username),
-        base_style.fg(Renderer::hex_color(0x4C566A)),
-    ));
-}
-
-fn build_list_item<'a>(
-    item: &'a CredentialItem,
-    index: usize,
-    selected: Option<usize>,
-    highlight_style: Style,
-    show_username: bool,
-) -> ListItem<'a> {
-    let is_selected = Some(index) == selected;
-    let spans = build_item_spans(item, is_selected, highlight_style, show_username);
-    let mut list_item = ListItem::new(Line::from(spans));
-
-    if is_selected {
-        list_item = list_item.style(highlight_style);
+fn build_header_line(layout: &[(ListColumn, u16)]) -> Line<'static> {
+    let style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let mut spans = vec![Span::raw(" ".repeat(PREFIX_WIDTH as usize))];
+    for (i, (column, width)) in layout.iter().enumerate() {
+        spans.push(Span::styled(pad_cell(column.title(), *width, i + 1 == layout.len()), style));
     }
-
-    list_item
+    Line::from(spans)
 }
 
 impl<'a> StatefulWidget for CredentialList<'a> {
@@ -226,21 +554,90 @@ impl<'a> StatefulWidget for CredentialList<'a> {
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
         let selected = state.selected();
+        let inner = match self.block {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
 
-        let items: Vec<ListItem> = self
-            .items
+        let columns: Vec<ListColumn> = self
+            .columns
             .iter()
-            .enumerate()
-            .map(|(i, item)| build_list_item(item, i, selected, self.highlight_style, self.show_username))
+            .copied()
+            .filter(|c| self.show_username || *c != ListColumn::Username)
             .collect();
+        let layout = layout_columns(&columns, inner.width.saturating_sub(PREFIX_WIDTH));
+
+        // A lone name column reads fine without a header
+        let mut list_area = inner;
+        if layout.len() > 1 && inner.height > 1 {
+            build_header_line(&layout).render(Rect { height: 1, ..inner }, buf);
+            list_area.y += 1;
+            list_area.height -= 1;
+        }
 
-        let list = List::new(items);
-        let list = match self.block {
-            Some(block) => list.block(block),
-            None => list,
-        };
+        // Only the rows in view are built, so a long list costs no more to
+        // draw than a short one
+        let height = list_area.height as usize;
+        let offset = scroll_offset(state.list_state.offset(), selected, height, state.total);
+        *state.list_state.offset_mut() = offset;
+
+        let now = Local::now();
+        let items: Vec<ListItem> = (offset..(offset + height).min(state.total))
+            .map(|row| {
+                let Some(item) = row.checked_sub(self.first_row).and_then(|i| self.items.get(i)) else {
+                    return ListItem::new("");
+                };
+                let is_selected = Some(row) == selected;
+                let line = Line::from(build_item_spans(item, is_selected, self.highlight_style, &layout, now, self.search_term, self.private));
+                match is_selected {
+                    true => ListItem::new(line).style(self.highlight_style),
+                    false => ListItem::new(line),
+                }
+            })
+            .collect();
 
-        StatefulWidget::render(list, area, buf, state.list_state_mut());
+        state.rows_area = list_area;
+        Widget::render(List::new(items), list_area, buf);
+    }
+}
+
+/// Placeholder rows drawn while the list's first page loads
+#[derive(Default)]
+pub struct SkeletonList<'a> {
+    block: Option<Block<'a>>,
+}
+
+impl<'a> SkeletonList<'a> {
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+/// Bar lengths, as percent of the row, so the rows do not look uniform
+const SKELETON_WIDTHS: [u16; 5] = [60, 45, 75, 50, 65];
+
+impl<'a> Widget for SkeletonList<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let inner = match self.block {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        let fill = if theme::ascii() { "-" } else { "\u{2591}" };
+        let style = Style::default().fg(theme::muted());
+        let room = inner.width.saturating_sub(PREFIX_WIDTH);
+        for (row, y) in (inner.y..inner.bottom()).enumerate() {
+            let width = room * SKELETON_WIDTHS[row % SKELETON_WIDTHS.len()] / 100;
+            buf.set_string(inner.x + PREFIX_WIDTH, y, fill.repeat(width as usize), style);
+        }
     }
 }
 
@@ -312,4 +709,78 @@ mod tests {
         state.move_down();
         assert_eq!(state.selected(), None);
     }
+
+    #[test]
+    fn test_scroll_offset() {
+        assert_eq!(scroll_offset(0, Some(5), 10, 100), 0);
+        assert_eq!(scroll_offset(0, Some(15), 10, 100), 6);
+        assert_eq!(scroll_offset(20, Some(15), 10, 100), 15);
+        assert_eq!(scroll_offset(95, Some(99), 10, 100), 90);
+        assert_eq!(scroll_offset(40, None, 10, 100), 40);
+    }
+
+    #[test]
+    fn test_index_at() {
+        let mut state = ListViewState::new();
+        state.set_total(3);
+        state.rows_area = Rect::new(1, 2, 40, 10);
+
+        assert_eq!(state.index_at(5, 2), Some(0));
+        assert_eq!(state.index_at(5, 4), Some(2));
+        assert_eq!(state.index_at(5, 5), None);
+        assert_eq!(state.index_at(0, 2), None);
+        assert_eq!(state.index_at(5, 1), None);
+    }
+
+    #[test]
+    fn test_layout_columns_fills_width() {
+        let layout = layout_columns(&ListColumn::ALL, 120);
+        assert_eq!(layout.len(), 5);
+        let gaps = COLUMN_GAP * 4;
+        assert_eq!(layout.iter().map(|(_, w)| w).sum::<u16>() + gaps, 120);
+        assert_eq!(layout[2], (ListColumn::Type, 11));
+    }
+
+    #[test]
+    fn test_layout_columns_drops_low_priority() {
+        let layout = layout_columns(&ListColumn::ALL, 40);
+        let shown: Vec<ListColumn> = layout.iter().map(|(c, _)| *c).collect();
+        assert_eq!(shown, [ListColumn::Name, ListColumn::Username, ListColumn::Updated]);
+
+        let layout = layout_columns(&[ListColumn::Tags], 10);
+        assert_eq!(layout, [(ListColumn::Name, 10)]);
+    }
+
+    #[test]
+    fn test_match_ranges() {
+        assert_eq!(match_ranges("GitHub Enterprise", "git"), [(0, 3)]);
+        assert_eq!(match_ranges("aAaA", "aa"), [(0, 4)]);
+        assert_eq!(match_ranges("Ünïcode ünï", "ÜNÏ"), [(0, 3), (8, 11)]);
+        assert!(match_ranges("GitHub", " ").is_empty());
+        assert_eq!(match_ranges("GitHub work", "work git ithu"), [(0, 5), (7, 11)]);
+
+        let spans = highlighted_spans("my github".to_string(), Some("GIT"), Style::default());
+        let texts: Vec<&str> = spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(texts, ["my ", "git", "hub"]);
+    }
+
+    #[test]
+    fn test_format_age() {
+        let now = Local::now();
+        assert_eq!(format_age(now, now), "now");
+        assert_eq!(format_age(now - chrono::Duration::minutes(5), now), "5m");
+        assert_eq!(format_age(now - chrono::Duration::days(3), now), "3d");
+        assert_eq!(format_age(now - chrono::Duration::days(65), now), "2mo");
+        assert_eq!(format_age(now - chrono::Duration::days(800), now), "2y");
+    }
+
+    #[test]
+    fn test_tag_spans() {
+        let tags = vec!["work".to_string(), "prod".to_string(), "infra".to_string()];
+        let text = |spans: Vec<Span>| spans.iter().map(|s| s.content.to_string()).collect::<String>();
+        assert_eq!(text(tag_spans(&tags, 20, true, Style::default())), "work, prod, infra");
+        assert_eq!(text(tag_spans(&tags, 20, false, Style::default())), format!("{:<22}", "work, prod, infra"));
+        let cut = text(tag_spans(&tags, 12, true, Style::default()));
+        assert!(cut.starts_with("work, prod") && cut.width() <= 12, "{}", cut);
+    }
 }
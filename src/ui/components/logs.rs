@@ -7,7 +7,8 @@ use ratatui::{
     widgets::{Clear, Widget},
 };
 
-use crate::db::{AuditAction, AuditLog};
+use crate::db::{AuditAction, AuditLog, AuditQuery};
+use crate::input::line::EditLine;
 
 use super::layout::{
     centered_rect, create_popup_block, render_empty_message, render_footer, render_separator_line,
@@ -70,10 +71,17 @@ impl LogsColumns {
     }
 }
 
+/// How many matching rows the logs screen loads
+pub const LOGS_LIMIT: usize = 500;
+
 #[derive(Default)]
 pub struct LogsState {
     pub scroll: ScrollState,
     pub logs: Vec<AuditLog>,
+    pub zoomed: bool,
+    pub query: AuditQuery,
+    /// Text being typed after `/`, if the search prompt is open
+    pub search_input: Option<EditLine>,
     columns: Option<LogsColumns>,
 }
 
@@ -82,6 +90,42 @@ impl LogsState {
         Self::default()
     }
 
+    pub fn start_search(&mut self) {
+        self.search_input = Some(EditLine::from(self.query.to_string()));
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        if let Some(input) = &mut self.search_input {
+            input.insert(c);
+        }
+    }
+
+    pub fn pop_char(&mut self) {
+        if let Some(input) = &mut self.search_input {
+            input.backspace();
+        }
+    }
+
+    /// Parse the search prompt into the active query and close the prompt
+    pub fn submit_search(&mut self) -> Result<(), String> {
+        let Some(input) = self.search_input.take() else { return Ok(()) };
+        self.query = AuditQuery::parse(&input)?;
+        Ok(())
+    }
+
+    /// Filter to one credential, or drop the filter if it is already applied
+    pub fn toggle_credential(&mut self, name: &str) {
+        self.query.credential = match self.query.credential.as_deref() == Some(name) {
+            true => None,
+            false => Some(name.to_string()),
+        };
+    }
+
+    pub fn clear_query(&mut self) {
+        self.query = AuditQuery::default();
+        self.search_input = None;
+    }
+
     pub fn set_logs(&mut self, logs: Vec<AuditLog>) {
         self.columns = Some(LogsColumns::from_logs(&logs));
         self.logs = logs;
@@ -151,28 +195,44 @@ impl<'a> LogsScreen<'a> {
         Self { state }
     }
 
-    pub fn visible_height(area: Rect) -> u16 {
-        let popup = centered_rect(85, 75, area);
+    pub fn popup_area(area: Rect, zoomed: bool) -> Rect {
+        match zoomed {
+            true => centered_rect(100, 100, area),
+            false => centered_rect(85, 75, area),
+        }
+    }
+
+    pub fn visible_height(area: Rect, zoomed: bool) -> u16 {
+        let popup = Self::popup_area(area, zoomed);
         popup.height.saturating_sub(5) // -1 to account for indicator line
     }
 
-    pub fn visible_width(area: Rect) -> u16 {
-        let popup = centered_rect(85, 75, area);
+    pub fn visible_width(area: Rect, zoomed: bool) -> u16 {
+        let popup = Self::popup_area(area, zoomed);
         popup.width.saturating_sub(2)
     }
 }
 
 impl Widget for LogsScreen<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let popup = centered_rect(85, 75, area);
+        let popup = Self::popup_area(area, self.state.zoomed);
         Clear.render(popup, buf);
 
-        let block = create_popup_block(" Audit Logs (last 500) ", Color::Magenta);
+        let title = logs_title(self.state);
+        let block = create_popup_block(&title, Color::Magenta);
         let inner = block.inner(popup);
         block.render(popup, buf);
 
+        if let Some(input) = &self.state.search_input {
+            render_footer(buf, popup, &format!(" /{}_ - Enter apply - Esc cancel ", input.as_str()));
+        }
+
         if self.state.logs.is_empty() {
-            render_empty_message(inner, buf, "No audit logs found");
+            if self.state.search_input.is_none() {
+                render_logs_footer(buf, popup, false);
+            }
+            let message = if self.state.query.is_empty() { "No audit logs found" } else { "No audit logs match" };
+            render_empty_message(inner, buf, message);
             return;
         }
 
@@ -187,7 +247,9 @@ impl Widget for LogsScreen<'_> {
         let needs_v_scroll = max_v > 0;
         let needs_h_scroll = max_h > 0;
 
-        render_logs_footer(buf, popup, needs_h_scroll);
+        if self.state.search_input.is_none() {
+            render_logs_footer(buf, popup, needs_h_scroll);
+        }
 
         // Render header (always at top)
         render_logs_header(inner, buf, self.state.scroll.h_scroll, &columns);
@@ -227,11 +289,19 @@ impl Widget for LogsScreen<'_> {
     }
 }
 
+fn logs_title(state: &LogsState) -> String {
+    let chips = state.query.chips();
+    match chips.is_empty() {
+        true => format!(" Audit Logs (last {}) ", LOGS_LIMIT),
+        false => format!(" Audit Logs ({}) - {} ", state.logs.len(), chips.join(" ")),
+    }
+}
+
 fn render_logs_footer(buf: &mut Buffer, popup: Rect, needs_h_scroll: bool) {
     let text = if needs_h_scroll {
-        " j/k scroll - h/l pan - 0/$ pan start/end - q close "
+        " j/k scroll - h/l pan - / search - a action - c credential - q close "
     } else {
-        " j/k scroll - gg/G top/bottom - q close "
+        " j/k scroll - / search - a action - c credential - BS clear - q close "
     };
     render_footer(buf, popup, text);
 }
@@ -306,10 +376,11 @@ fn render_log_row(
     );
 }
 
-fn action_display(action: &AuditAction) -> (&'static str, Color) {
+pub fn action_display(action: &AuditAction) -> (&'static str, Color) {
     match action {
         AuditAction::Create => ("CREATE", Color::Green),
         AuditAction::Read => ("READ", Color::Blue),
+        AuditAction::Preview => ("PREVIEW", Color::DarkGray),
         AuditAction::Update => ("UPDATE", Color::Yellow),
         AuditAction::Delete => ("DELETE", Color::Red),
         AuditAction::Copy => ("COPY", Color::Magenta),
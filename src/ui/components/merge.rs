@@ -0,0 +1,143 @@
+//! Merge popup and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::vault::merge::{MergeField, MergePlan, Side};
+
+use super::layout::{
+    centered_rect_fixed, create_popup_block, highlight_row, render_footer, render_separator_line,
+    truncate_with_ellipsis,
+};
+
+const LABEL_WIDTH: u16 = 10;
+const POPUP_WIDTH: u16 = 80;
+/// Borders, header, separator, field rows, a gap and the deletion note
+const POPUP_HEIGHT: u16 = MergeField::ALL.len() as u16 + 6;
+
+#[derive(Default)]
+pub struct MergeState {
+    pub plan: Option<MergePlan>,
+    pub selected: usize,
+}
+
+impl MergeState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open(&mut self, plan: MergePlan) {
+        self.plan = Some(plan);
+        self.selected = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.plan = None;
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        self.selected = (self.selected + 1).min(MergeField::ALL.len() - 1);
+    }
+
+    pub fn toggle_selected(&mut self) {
+        if let Some(plan) = &mut self.plan {
+            plan.toggle(MergeField::ALL[self.selected]);
+        }
+    }
+}
+
+pub struct MergePopup<'a> {
+    state: &'a MergeState,
+}
+
+impl<'a> MergePopup<'a> {
+    pub fn new(state: &'a MergeState) -> Self {
+        Self { state }
+    }
+}
+
+impl Widget for MergePopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let Some(plan) = &self.state.plan else { return };
+        let popup = centered_rect_fixed(POPUP_WIDTH, POPUP_HEIGHT, area, true);
+        Clear.render(popup, buf);
+
+        let block = create_popup_block(" Merge ", Color::Magenta);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        render_footer(buf, popup, " j/k field - h/l choose side - Enter merge - q cancel ");
+
+        let column_width = inner.width.saturating_sub(LABEL_WIDTH) / 2;
+        render_merge_header(inner, buf, column_width, plan);
+        render_separator_line(buf, inner.x, inner.y + 1, inner.width);
+
+        for (i, field) in MergeField::ALL.into_iter().enumerate() {
+            let y = inner.y + 2 + i as u16;
+            render_field_row(inner, buf, y, column_width, plan, field, i == self.state.selected);
+        }
+
+        let note = format!("'{}' will be deleted", plan.right.name);
+        let note_y = inner.y + 3 + MergeField::ALL.len() as u16;
+        buf.set_string(
+            inner.x,
+            note_y,
+            truncate_with_ellipsis(&note, inner.width as usize),
+            Style::default().fg(Color::Red),
+        );
+    }
+}
+
+fn render_merge_header(inner: Rect, buf: &mut Buffer, column_width: u16, plan: &MergePlan) {
+    let style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let width = column_width.saturating_sub(1) as usize;
+    buf.set_string(inner.x, inner.y, "FIELD", style);
+    buf.set_string(inner.x + LABEL_WIDTH, inner.y, truncate_with_ellipsis(&plan.left.name, width), style);
+    buf.set_string(inner.x + LABEL_WIDTH + column_width, inner.y, truncate_with_ellipsis(&plan.right.name, width), style);
+}
+
+fn render_field_row(
+    inner: Rect,
+    buf: &mut Buffer,
+    y: u16,
+    column_width: u16,
+    plan: &MergePlan,
+    field: MergeField,
+    is_cursor: bool,
+) {
+    if is_cursor {
+        highlight_row(buf, inner.x, y, inner.width);
+    }
+    let style = |color: Color| {
+        let style = Style::default().fg(color);
+        if is_cursor { style.bg(Color::DarkGray) } else { style }
+    };
+
+    buf.set_string(inner.x, y, field.label(), style(Color::Cyan));
+
+    let Some(chosen) = plan.side(field) else {
+        // Both sides feed this field, so show the combined value across both columns
+        let width = (column_width * 2).saturating_sub(1) as usize;
+        let merged = truncate_with_ellipsis(&plan.display(field, Side::Left), width);
+        buf.set_string(inner.x + LABEL_WIDTH, y, merged, style(Color::White));
+        return;
+    };
+
+    let width = column_width.saturating_sub(1) as usize;
+    for (offset, side) in [(0, Side::Left), (column_width, Side::Right)] {
+        let value_style = match side == chosen {
+            true => style(Color::Green).add_modifier(Modifier::BOLD),
+            false => style(Color::Gray),
+        };
+        let value = truncate_with_ellipsis(&plan.display(field, side), width);
+        buf.set_string(inner.x + LABEL_WIDTH + offset, y, value, value_style);
+    }
+}
@@ -2,23 +2,37 @@
 //!
 //! Reusable TUI widgets for the credential manager.
 
+pub mod backups;
+pub mod connection;
+pub mod cryptoinfo;
 pub mod detail;
+pub mod finder;
 pub mod form;
+pub mod generator;
 pub mod list;
 pub mod statusline;
 pub mod dialogs;
+pub mod dupes;
 pub mod help;
+pub mod info;
 pub mod input_field;
 pub mod layout;
 pub mod logs;
+pub mod merge;
+pub mod palette;
+pub mod panes;
 pub mod scroll;
+pub mod security;
 pub mod tags;
+pub mod timeline;
+pub mod tips;
 
 // Re-exports
-pub use detail::{CredentialDetail, DetailView};
+pub use detail::{CredentialDetail, DetailView, SecretDisplay};
 pub use form::{CredentialForm, CredentialFormWidget};
-pub use list::{CredentialItem, CredentialList, EmptyState, ListViewState};
+pub use list::{CredentialItem, CredentialList, EmptyState, ListViewState, SkeletonList};
 pub use statusline::{HelpBar, MessageType, StatusLine};
 pub use dialogs::{ConfirmDialog, PasswordDialog};
 pub use logs::{LogsScreen, LogsState};
+pub use panes::{Pane, PaneLayout};
 pub use help::{HelpScreen};
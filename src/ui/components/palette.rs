@@ -0,0 +1,260 @@
+//! Command palette popup and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::i18n;
+use crate::input::commands::COMMANDS;
+use crate::input::fuzzy::best_score;
+use crate::input::keymap::{Action, NORMAL_BINDINGS};
+use crate::input::line::EditLine;
+
+use super::input_field::{render_input_cursor, VisibleText};
+use super::layout::{
+    centered_rect, create_popup_block, highlight_row, render_empty_message, render_footer, render_separator_line,
+    truncate_with_ellipsis,
+};
+
+/// What choosing an entry does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteRun {
+    Action(Action),
+    /// Open the command line with this text, for a command that needs
+    /// arguments
+    Prefill(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteEntry {
+    pub description: &'static str,
+    /// The command as help writes it, e.g. `:merge NAME`
+    pub command: Option<String>,
+    /// Keys bound to the same action
+    pub keys: Option<String>,
+    pub run: PaletteRun,
+}
+
+impl PaletteEntry {
+    fn score(&self, query: &str) -> Option<i64> {
+        let fields = [Some(self.description), self.command.as_deref(), self.keys.as_deref()];
+        best_score(query, fields.into_iter().flatten())
+    }
+}
+
+/// Every command in the registry, with the keys bound to the same action,
+/// followed by the key bindings no command covers. Help is generated from
+/// the same two lists.
+pub fn palette_entries() -> Vec<PaletteEntry> {
+    let bound = |action: &Action| {
+        let keys: Vec<String> = NORMAL_BINDINGS
+            .iter()
+            .filter(|b| b.help.is_some() && b.action == *action)
+            .map(|b| b.keys_label())
+            .collect();
+        (!keys.is_empty()).then(|| keys.join(" / "))
+    };
+
+    let mut entries: Vec<PaletteEntry> = COMMANDS
+        .iter()
+        .map(|command| {
+            let run = match command.runs_bare() {
+                true => PaletteRun::Action(command.bare_action()),
+                false => PaletteRun::Prefill(format!("{} ", command.names[0])),
+            };
+            let keys = match &run {
+                PaletteRun::Action(action) => bound(action),
+                PaletteRun::Prefill(_) => None,
+            };
+            PaletteEntry { description: i18n::tr(command.help), command: Some(command.label()), keys, run }
+        })
+        .collect();
+
+    for binding in NORMAL_BINDINGS {
+        let Some(help) = binding.help else { continue };
+        if entries.iter().any(|e| e.run == PaletteRun::Action(binding.action.clone())) {
+            continue;
+        }
+        entries.push(PaletteEntry {
+            description: i18n::tr(help),
+            command: None,
+            keys: Some(binding.keys_label()),
+            run: PaletteRun::Action(binding.action.clone()),
+        });
+    }
+    entries
+}
+
+#[derive(Default)]
+pub struct PaletteState {
+    pub query: EditLine,
+    pub entries: Vec<PaletteEntry>,
+    /// Indexes into `entries`, best match first
+    pub matches: Vec<usize>,
+    pub selected: usize,
+}
+
+impl PaletteState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open(&mut self) {
+        self.entries = palette_entries();
+        self.query.clear();
+        self.refilter();
+    }
+
+    pub fn close(&mut self) {
+        self.query.clear();
+        self.entries.clear();
+        self.matches.clear();
+        self.selected = 0;
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        self.query.insert(c);
+        self.refilter();
+    }
+
+    pub fn pop_char(&mut self) {
+        self.query.backspace();
+        self.refilter();
+    }
+
+    pub fn clear_query(&mut self) {
+        self.query.clear();
+        self.refilter();
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected_entry(&self) -> Option<&PaletteEntry> {
+        self.entries.get(*self.matches.get(self.selected)?)
+    }
+
+    fn refilter(&mut self) {
+        let query = self.query.trim();
+        let mut scored: Vec<(usize, i64)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.score(query).map(|s| (i, s)))
+            .collect();
+        scored.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        self.matches = scored.into_iter().map(|(i, _)| i).collect();
+        self.selected = 0;
+    }
+}
+
+pub struct PalettePopup<'a> {
+    state: &'a PaletteState,
+}
+
+impl<'a> PalettePopup<'a> {
+    pub fn new(state: &'a PaletteState) -> Self {
+        Self { state }
+    }
+}
+
+impl Widget for PalettePopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let popup = centered_rect(70, 60, area);
+        Clear.render(popup, buf);
+
+        let title = format!(" Commands ({}/{}) ", self.state.matches.len(), self.state.entries.len());
+        let block = create_popup_block(&title, Color::Cyan);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        render_footer(buf, popup, " type to filter - C-j/C-k move - Enter run - Esc close ");
+        render_palette_prompt(inner, buf, &self.state.query);
+        render_separator_line(buf, inner.x, inner.y + 1, inner.width);
+
+        let list_area = Rect::new(inner.x, inner.y + 2, inner.width, inner.height.saturating_sub(2));
+        if self.state.matches.is_empty() {
+            render_empty_message(list_area, buf, "No matches");
+            return;
+        }
+        render_palette_matches(list_area, buf, self.state);
+    }
+}
+
+fn render_palette_prompt(inner: Rect, buf: &mut Buffer, query: &EditLine) {
+    let prompt_style = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+    buf.set_string(inner.x, inner.y, "> ", prompt_style);
+
+    let width = inner.width.saturating_sub(2);
+    let visible = VisibleText::new(query, false, width as usize);
+    buf.set_string(inner.x + 2, inner.y, visible.text.as_str(), Style::default().fg(Color::White));
+    render_input_cursor(buf, inner.x + 2, inner.y, width, visible.cursor);
+}
+
+fn render_palette_matches(area: Rect, buf: &mut Buffer, state: &PaletteState) {
+    let visible = area.height as usize;
+    let offset = (state.selected + 1).saturating_sub(visible);
+
+    for (row, &idx) in state.matches.iter().skip(offset).take(visible).enumerate() {
+        let y = area.y + row as u16;
+        let is_selected = offset + row == state.selected;
+        if is_selected {
+            highlight_row(buf, area.x, y, area.width);
+        }
+        render_palette_row(area, buf, y, &state.entries[idx], is_selected);
+    }
+}
+
+/// Description on the left; command and keys right-aligned
+fn render_palette_row(area: Rect, buf: &mut Buffer, y: u16, entry: &PaletteEntry, highlight: bool) {
+    let bg = if highlight { Color::DarkGray } else { Color::Reset };
+    let shortcut = [entry.command.as_deref(), entry.keys.as_deref()].into_iter().flatten().collect::<Vec<_>>().join("  ");
+    let shortcut_width = shortcut.chars().count().min(area.width as usize / 2);
+    let shortcut = truncate_with_ellipsis(&shortcut, shortcut_width);
+
+    let description_width = (area.width as usize).saturating_sub(shortcut_width + 1);
+    let description = truncate_with_ellipsis(entry.description, description_width);
+    buf.set_string(area.x, y, &description, Style::default().fg(Color::White).bg(bg));
+
+    let x = area.right().saturating_sub(shortcut.chars().count() as u16);
+    buf.set_string(x, y, &shortcut, Style::default().fg(Color::Cyan).bg(bg));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_entries() {
+        let entries = palette_entries();
+        let find = |command: &str| entries.iter().find(|e| e.command.as_deref() == Some(command)).unwrap();
+
+        // A command shows the keys bound to the same action, which are not
+        // listed again on their own
+        let new = find(":new");
+        assert_eq!(new.keys.as_deref(), Some("n"));
+        assert_eq!(new.run, PaletteRun::Action(Action::New));
+        assert_eq!(entries.iter().filter(|e| e.run == PaletteRun::Action(Action::New)).count(), 1);
+
+        assert_eq!(find(":merge NAME").run, PaletteRun::Prefill("merge ".to_string()));
+        assert_eq!(find(":privacy [on/off]").run, PaletteRun::Action(Action::Privacy(None)));
+        let zoom = entries.iter().find(|e| e.run == PaletteRun::Action(Action::ZoomPane)).unwrap();
+        assert_eq!((zoom.command.as_deref(), zoom.keys.as_deref()), (None, Some("Ctrl+w z")));
+
+        let mut state = PaletteState::new();
+        state.open();
+        "lockvault".chars().for_each(|c| state.push_char(c));
+        assert_eq!(state.selected_entry().unwrap().run, PaletteRun::Action(Action::Lock));
+    }
+}
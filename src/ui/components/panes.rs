@@ -0,0 +1,181 @@
+//! Pane Layout
+//!
+//! Tracks the list/detail split, pane focus and zoom.
+
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+pub const DEFAULT_LIST_WIDTH: u16 = 50;
+const MIN_LIST_WIDTH: u16 = 20;
+const MAX_LIST_WIDTH: u16 = 80;
+const RESIZE_STEP: u16 = 5;
+/// Narrowest terminal that shows a preview beside the list view
+pub const PREVIEW_MIN_WIDTH: u16 = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    List,
+    Detail,
+}
+
+#[derive(Debug, Clone)]
+pub struct PaneLayout {
+    /// Width of the list pane in the detail view, as a percentage
+    pub list_width: u16,
+    pub focus: Pane,
+    pub zoomed: bool,
+}
+
+impl Default for PaneLayout {
+    fn default() -> Self {
+        Self::new(DEFAULT_LIST_WIDTH)
+    }
+}
+
+impl PaneLayout {
+    pub fn new(list_width: u16) -> Self {
+        Self {
+            list_width: list_width.clamp(MIN_LIST_WIDTH, MAX_LIST_WIDTH),
+            focus: Pane::Detail,
+            zoomed: false,
+        }
+    }
+
+    pub fn toggle_zoom(&mut self) {
+        self.zoomed = !self.zoomed;
+    }
+
+    pub fn cycle_focus(&mut self) {
+        self.focus = match self.focus {
+            Pane::List => Pane::Detail,
+            Pane::Detail => Pane::List,
+        };
+    }
+
+    /// Grow the focused pane by one step
+    pub fn grow(&mut self) {
+        match self.focus {
+            Pane::List => self.widen_list(),
+            Pane::Detail => self.narrow_list(),
+        }
+    }
+
+    /// Shrink the focused pane by one step
+    pub fn shrink(&mut self) {
+        match self.focus {
+            Pane::List => self.narrow_list(),
+            Pane::Detail => self.widen_list(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.list_width = DEFAULT_LIST_WIDTH;
+        self.zoomed = false;
+    }
+
+    fn widen_list(&mut self) {
+        self.list_width = (self.list_width + RESIZE_STEP).min(MAX_LIST_WIDTH);
+    }
+
+    fn narrow_list(&mut self) {
+        self.list_width = self.list_width.saturating_sub(RESIZE_STEP).max(MIN_LIST_WIDTH);
+    }
+
+    /// Split the detail view area into (list, detail), hiding the
+    /// unfocused pane while zoomed
+    pub fn split(&self, area: Rect) -> (Option<Rect>, Option<Rect>) {
+        if self.zoomed {
+            return match self.focus {
+                Pane::List => (Some(area), None),
+                Pane::Detail => (None, Some(area)),
+            };
+        }
+
+        let (list, detail) = self.columns(area);
+        (Some(list), Some(detail))
+    }
+
+    /// Split the list view into the list and, on terminals wide enough, a
+    /// preview of the selected credential
+    pub fn preview_split(&self, area: Rect) -> (Rect, Option<Rect>) {
+        if area.width < PREVIEW_MIN_WIDTH {
+            return (area, None);
+        }
+        let (list, preview) = self.columns(area);
+        (list, Some(preview))
+    }
+
+    fn columns(&self, area: Rect) -> (Rect, Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(self.list_width),
+                Constraint::Percentage(100 - self.list_width),
+            ])
+            .split(area);
+        (chunks[0], chunks[1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_clamps_width() {
+        assert_eq!(PaneLayout::new(5).list_width, MIN_LIST_WIDTH);
+        assert_eq!(PaneLayout::new(95).list_width, MAX_LIST_WIDTH);
+    }
+
+    #[test]
+    fn test_resize_follows_focus() {
+        let mut panes = PaneLayout::default();
+        panes.grow();
+        assert_eq!(panes.list_width, 45);
+
+        panes.cycle_focus();
+        assert_eq!(panes.focus, Pane::List);
+        panes.grow();
+        panes.grow();
+        assert_eq!(panes.list_width, 55);
+    }
+
+    #[test]
+    fn test_resize_bounds() {
+        let mut panes = PaneLayout::new(MAX_LIST_WIDTH);
+        panes.focus = Pane::List;
+        panes.grow();
+        assert_eq!(panes.list_width, MAX_LIST_WIDTH);
+
+        for _ in 0..20 {
+            panes.shrink();
+        }
+        assert_eq!(panes.list_width, MIN_LIST_WIDTH);
+    }
+
+    #[test]
+    fn test_zoom_split() {
+        let area = Rect::new(0, 0, 100, 20);
+        let mut panes = PaneLayout::default();
+
+        let (list, detail) = panes.split(area);
+        assert_eq!(list.unwrap().width, 50);
+        assert_eq!(detail.unwrap().width, 50);
+
+        panes.toggle_zoom();
+        assert_eq!(panes.split(area), (None, Some(area)));
+
+        panes.cycle_focus();
+        assert_eq!(panes.split(area), (Some(area), None));
+    }
+
+    #[test]
+    fn test_preview_only_on_wide_terminals() {
+        let panes = PaneLayout::default();
+        let narrow = Rect::new(0, 0, PREVIEW_MIN_WIDTH - 1, 40);
+        assert_eq!(panes.preview_split(narrow), (narrow, None));
+
+        let (list, preview) = panes.preview_split(Rect::new(0, 0, 160, 40));
+        assert_eq!(list.width, 80);
+        assert_eq!(preview.map(|p| p.width), Some(80));
+    }
+}
@@ -5,6 +5,9 @@ use ratatui::{
     layout::Rect,
     style::{Color, Style},
 };
+use unicode_width::UnicodeWidthStr;
+
+use crate::ui::theme;
 
 #[derive(Default, Clone)]
 pub struct ScrollState {
@@ -62,10 +65,11 @@ pub fn render_v_scroll_indicator(buf: &mut Buffer, inner: &Rect, v_offset: usize
     if max_v == 0 {
         return;
     }
+    let [down, up, both] = theme::v_scroll_arrows();
     let icon = match (v_offset == 0, v_offset >= max_v) {
-        (true, _) => "",   // at top, can scroll down
-        (_, true) => "",   // at bottom, can scroll up
-        _ => "",           // mid-scroll, can scroll both
+        (true, _) => down, // at top, can scroll down
+        (_, true) => up,   // at bottom, can scroll up
+        _ => both,         // mid-scroll, can scroll both
     };
     let x = inner.x + inner.width / 2;
     let y = inner.y + inner.height.saturating_sub(1);
@@ -83,11 +87,12 @@ pub fn render_h_scroll_indicator(
     if max_h == 0 {
         return;
     }
+    let [right, left, both] = theme::h_scroll_arrows();
     let indicator = match (h_offset == 0, h_offset >= max_h) {
-        (true, _) => "",
-        (_, true) => "",
-        _ => "",
+        (true, _) => right,
+        (_, true) => left,
+        _ => both,
     };
-    let x = inner.x + inner.width.saturating_sub(indicator.len() as u16);
+    let x = inner.x + inner.width.saturating_sub(indicator.width() as u16);
     buf.set_string(x, inner.y, indicator, Style::default().fg(color));
 }
@@ -0,0 +1,54 @@
+//! Security info popup
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::hardening::Mitigation;
+
+use super::layout::{centered_rect_fixed, create_popup_block, render_footer, truncate_with_ellipsis};
+
+const NAME_WIDTH: u16 = 20;
+
+pub struct SecurityPopup<'a> {
+    mitigations: &'a [Mitigation],
+}
+
+impl<'a> SecurityPopup<'a> {
+    pub fn new(mitigations: &'a [Mitigation]) -> Self {
+        Self { mitigations }
+    }
+}
+
+impl Widget for SecurityPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let height = (self.mitigations.len() as u16 + 2).min(area.height);
+        let popup = centered_rect_fixed(72, height, area, true);
+        Clear.render(popup, buf);
+
+        let block = create_popup_block(" Security ", Color::Cyan);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        render_footer(buf, popup, " q close ");
+
+        for (i, mitigation) in self.mitigations.iter().take(inner.height as usize).enumerate() {
+            render_mitigation_row(inner, buf, inner.y + i as u16, mitigation);
+        }
+    }
+}
+
+fn render_mitigation_row(inner: Rect, buf: &mut Buffer, y: u16, mitigation: &Mitigation) {
+    let (mark, color) = if mitigation.active { ("●", Color::Green) } else { ("○", Color::Red) };
+    buf.set_string(inner.x, y, mark, Style::default().fg(color));
+
+    let name_style = Style::default().fg(Color::White).add_modifier(Modifier::BOLD);
+    buf.set_string(inner.x + 2, y, mitigation.name, name_style);
+
+    let detail_width = inner.width.saturating_sub(NAME_WIDTH + 2) as usize;
+    let detail = truncate_with_ellipsis(&mitigation.detail, detail_width);
+    buf.set_string(inner.x + 2 + NAME_WIDTH, y, detail, Style::default().fg(Color::Gray));
+}
@@ -9,8 +9,15 @@ use ratatui::{
     text::{Line, Span},
     widgets::Widget,
 };
+use unicode_width::UnicodeWidthStr;
 
+use crate::i18n;
 use crate::input::InputMode;
+use crate::input::line::EditLine;
+use crate::ui::renderer::View;
+use crate::ui::theme;
+
+use super::input_field::{render_input_cursor, VisibleText};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MessageType {
@@ -29,14 +36,30 @@ impl MessageType {
             Self::Error => Color::Red,
         }
     }
+
+    /// Says what kind of message it is in high contrast mode
+    pub fn marker(&self) -> &'static str {
+        match self {
+            Self::Info => "[i] ",
+            Self::Success => "[ok] ",
+            Self::Warning => "[!] ",
+            Self::Error => "[x] ",
+        }
+    }
 }
 
 pub struct StatusLine<'a> {
     mode: InputMode,
-    command_buffer: Option<&'a str>,
+    command_buffer: Option<&'a EditLine>,
     message: Option<(&'a str, MessageType)>,
     vault_name: Option<&'a str>,
     item_count: Option<(usize, usize)>,
+    lock_countdown: Option<u64>,
+    filters: &'a [String],
+    read_only: bool,
+    /// File name of the backup shown in place of the vault
+    inspecting: Option<&'a str>,
+    private: bool,
 }
 
 impl<'a> StatusLine<'a> {
@@ -47,10 +70,15 @@ impl<'a> StatusLine<'a> {
             message: None,
             vault_name: None,
             item_count: None,
+            lock_countdown: None,
+            filters: &[],
+            read_only: false,
+            inspecting: None,
+            private: false,
         }
     }
 
-    pub fn command_buffer(mut self, buffer: &'a str) -> Self {
+    pub fn command_buffer(mut self, buffer: &'a EditLine) -> Self {
         self.command_buffer = Some(buffer);
         self
     }
@@ -69,6 +97,31 @@ impl<'a> StatusLine<'a> {
         self.item_count = Some((selected, total));
         self
     }
+
+    pub fn lock_countdown(mut self, secs: u64) -> Self {
+        self.lock_countdown = Some(secs);
+        self
+    }
+
+    pub fn filters(mut self, chips: &'a [String]) -> Self {
+        self.filters = chips;
+        self
+    }
+
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn inspecting(mut self, backup: Option<&'a str>) -> Self {
+        self.inspecting = backup;
+        self
+    }
+
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
 }
 
 fn mode_style(mode: InputMode) -> Style {
@@ -82,6 +135,14 @@ fn mode_style(mode: InputMode) -> Style {
         InputMode::Help => base.bg(Color::Yellow),
         InputMode::Logs => base.bg(Color::Green),
         InputMode::Tags => base.bg(Color::Magenta),
+        InputMode::Timeline => base.bg(Color::Cyan),
+        InputMode::Finder | InputMode::Palette => base.bg(Color::Cyan),
+        InputMode::Backups => base.bg(Color::Green),
+        InputMode::Dupes => base.bg(Color::Yellow),
+        InputMode::Merge => base.bg(Color::Magenta),
+        InputMode::Security | InputMode::Tips | InputMode::Info => base.bg(Color::Cyan),
+        InputMode::CryptoInfo => base.bg(Color::Cyan),
+        InputMode::Connection => base.bg(Color::Cyan),
     }
 }
 
@@ -95,29 +156,63 @@ fn command_prefix(mode: InputMode) -> &'static str {
 
 fn render_mode_indicator(buf: &mut Buffer, area: Rect, mode: InputMode) -> u16 {
     let style = mode_style(mode).add_modifier(Modifier::BOLD);
-    let mode_text = format!(" {} ", mode.indicator());
+    let mode_text = format!(" {} ", i18n::tr(mode.indicator()));
     buf.set_string(area.x, area.y, &mode_text, style);
-    mode_text.len() as u16
+    mode_text.width() as u16
+}
+
+fn render_read_only_badge(buf: &mut Buffer, x: u16, y: u16) -> u16 {
+    let text = i18n::tr_padded(" READ-ONLY ");
+    let style = Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD);
+    buf.set_string(x, y, &text, style);
+    text.width() as u16
+}
+
+fn render_inspecting_badge(buf: &mut Buffer, x: u16, y: u16, backup: &str) -> u16 {
+    let text = format!(" {} ", i18n::trf("BACKUP {}", &[&backup]));
+    let style = Style::default().fg(Color::Black).bg(Color::Magenta).add_modifier(Modifier::BOLD);
+    buf.set_string(x, y, &text, style);
+    text.width() as u16
+}
+
+fn render_private_badge(buf: &mut Buffer, x: u16, y: u16) -> u16 {
+    let text = i18n::tr_padded(" PRIVATE ");
+    let style = Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD);
+    buf.set_string(x, y, &text, style);
+    text.width() as u16
 }
 
 fn render_command_or_message(
     buf: &mut Buffer,
+    area: Rect,
     x: u16,
-    y: u16,
     mode: InputMode,
-    command_buffer: Option<&str>,
+    command_buffer: Option<&EditLine>,
     message: Option<(&str, MessageType)>,
 ) {
     let style_base = Style::default().bg(Color::DarkGray);
-
-    if let Some(buffer) = command_buffer {
-        let cmd_text = format!("{}{}", command_prefix(mode), buffer);
-        buf.set_string(x, y, &cmd_text, style_base.fg(Color::White));
+    let y = area.y;
+
+    if let Some(line) = command_buffer {
+        let prefix = command_prefix(mode);
+        buf.set_string(x, y, prefix, style_base.fg(Color::White));
+        let x = x + prefix.len() as u16;
+        let width = area.right().saturating_sub(x);
+        let visible = VisibleText::new(line, false, width as usize);
+        buf.set_string(x, y, visible.text.as_str(), style_base.fg(Color::White));
+        render_input_cursor(buf, x, y, width, visible.cursor);
         return;
     }
 
     if let Some((msg, msg_type)) = message {
-        buf.set_string(x, y, msg, style_base.fg(msg_type.color()));
+        let style = style_base.fg(msg_type.color());
+        if theme::high_contrast() {
+            let marker = msg_type.marker();
+            buf.set_string(x, y, marker, style.add_modifier(Modifier::BOLD));
+            buf.set_string(x + marker.len() as u16, y, msg, style);
+        } else {
+            buf.set_string(x, y, msg, style);
+        }
     }
 }
 
@@ -135,10 +230,31 @@ fn build_right_text(item_count: Option<(usize, usize)>, vault_name: Option<&str>
     right_parts.join(" ")
 }
 
-fn render_right_section(buf: &mut Buffer, area: Rect, right_text: &str) {
+fn render_right_section(buf: &mut Buffer, area: Rect, right_text: &str) -> u16 {
     let right_x = area.x + area.width.saturating_sub(right_text.len() as u16 + 1);
     let style = Style::default().fg(Color::Gray).bg(Color::DarkGray);
     buf.set_string(right_x, area.y, right_text, style);
+    right_x
+}
+
+fn render_lock_countdown(buf: &mut Buffer, area: Rect, right_x: u16, secs: u64) -> u16 {
+    let text = format!("{} ", i18n::trf("Locking in {}s", &[&secs]));
+    let x = right_x.saturating_sub(text.width() as u16 + 1).max(area.x);
+    let style = Style::default().fg(MessageType::Warning.color()).bg(Color::DarkGray).add_modifier(Modifier::BOLD);
+    buf.set_string(x, area.y, &text, style);
+    x
+}
+
+fn render_filter_chips(buf: &mut Buffer, area: Rect, right_x: u16, chips: &[String]) {
+    let width: usize = chips.iter().map(|c| c.chars().count() + 3).sum();
+    let mut x = right_x.saturating_sub(width as u16 + 1).max(area.x);
+    let style = Style::default().fg(Color::Black).bg(Color::Cyan);
+
+    for chip in chips {
+        let text = format!(" {} ", chip);
+        buf.set_string(x, area.y, &text, style);
+        x += text.chars().count() as u16 + 1;
+    }
 }
 
 impl<'a> Widget for StatusLine<'a> {
@@ -146,15 +262,32 @@ impl<'a> Widget for StatusLine<'a> {
         buf.set_style(area, Style::default().bg(Color::DarkGray));
 
         let mode_width = render_mode_indicator(buf, area, self.mode);
-        let x = area.x + mode_width;
+        let mut x = area.x + mode_width;
+        if let Some(backup) = self.inspecting {
+            x += render_inspecting_badge(buf, x, area.y, backup);
+        } else if self.read_only {
+            x += render_read_only_badge(buf, x, area.y);
+        }
+        if self.private {
+            x += render_private_badge(buf, x, area.y);
+        }
 
         buf.set_string(x, area.y, " ", Style::default().bg(Color::DarkGray));
         let x = x + 1;
 
-        render_command_or_message(buf, x, area.y, self.mode, self.command_buffer, self.message);
+        render_command_or_message(buf, area, x, self.mode, self.command_buffer, self.message);
 
         let right_text = build_right_text(self.item_count, self.vault_name);
-        render_right_section(buf, area, &right_text);
+        let right_x = render_right_section(buf, area, &right_text);
+
+        let right_x = match self.lock_countdown {
+            Some(secs) => render_lock_countdown(buf, area, right_x, secs),
+            None => right_x,
+        };
+
+        if !self.filters.is_empty() {
+            render_filter_chips(buf, area, right_x, self.filters);
+        }
     }
 }
 
@@ -167,21 +300,65 @@ impl<'a> HelpBar<'a> {
         Self { hints }
     }
 
-    pub fn for_mode(mode: InputMode) -> Self {
-        Self { hints: hints_for_mode(mode) }
+    pub fn for_context(context: HintContext) -> Self {
+        Self { hints: hints_for_context(context) }
+    }
+}
+
+/// What the footer hints depend on besides the mode
+#[derive(Debug, Clone, Copy)]
+pub struct HintContext {
+    pub mode: InputMode,
+    pub view: View,
+    pub has_selection: bool,
+    pub filtered: bool,
+    /// A search with free text is active, so `n`/`N` step through matches
+    pub searching: bool,
+    pub read_only: bool,
+}
+
+fn hints_for_context(context: HintContext) -> Vec<(&'static str, &'static str)> {
+    if context.mode != InputMode::Normal {
+        return hints_for_mode(context.mode);
+    }
+    match context.view {
+        View::Form => vec![
+            ("Tab", "next field"),
+            ("C-g", "generate"),
+            ("Enter", "save"),
+            ("Esc", "cancel"),
+        ],
+        View::Detail => {
+            let mut hints = vec![("yy", "copy"), ("u", "username"), ("o", "open url")];
+            if !context.read_only {
+                hints.push(("e", "edit"));
+            }
+            hints.extend([("C-s", "show"), ("H", "timeline"), ("h", "back"), ("?", "help")]);
+            hints
+        }
+        View::List => {
+            let mut hints = Vec::new();
+            if context.has_selection {
+                hints.push(("Enter", "open"));
+            }
+            if context.searching {
+                hints.extend([("n", "next match"), ("N", "prev match")]);
+            } else if !context.read_only {
+                hints.push(("n", "new"));
+            }
+            hints.push(("/", "search"));
+            if context.filtered {
+                hints.push(("Backspace", "clear filters"));
+            }
+            hints.extend([(":", "command"), ("t", "tags"), ("i", "logs"), ("L", "lock vault"), ("?", "help")]);
+            hints
+        }
     }
 }
 
 fn hints_for_mode(mode: InputMode) -> Vec<(&'static str, &'static str)> {
     match mode {
-        InputMode::Normal => vec![
-            ("L", "lock vault"),
-            ("i", "logs"),
-            ("t", "tags"),
-            ("/", "search"),
-            (":", "command"),
-            ("?", "help"),
-        ],
+        InputMode::Normal => Vec::new(),
         InputMode::Insert => vec![
             ("Esc", "cancel"),
             ("Enter", "confirm"),
@@ -191,6 +368,52 @@ fn hints_for_mode(mode: InputMode) -> Vec<(&'static str, &'static str)> {
             ("Esc", "cancel"),
             ("Enter", "execute"),
         ],
+        InputMode::Finder => vec![
+            ("Esc", "close"),
+            ("C-j/C-k", "move"),
+            ("Enter", "open"),
+        ],
+        InputMode::Palette => vec![
+            ("Esc", "close"),
+            ("C-j/C-k", "move"),
+            ("Enter", "run"),
+        ],
+        InputMode::Backups => vec![
+            ("j/k", "move"),
+            ("Enter", "restore"),
+            ("i", "inspect"),
+            ("b", "backup now"),
+            ("q", "close"),
+        ],
+        InputMode::Dupes => vec![
+            ("j/k", "move"),
+            ("Enter", "first"),
+            ("s", "second"),
+            ("m", "merge"),
+            ("q", "close"),
+        ],
+        InputMode::Security => vec![
+            ("q", "close"),
+        ],
+        InputMode::Tips | InputMode::Info => vec![
+            ("j/k", "scroll"),
+            ("q", "close"),
+        ],
+        InputMode::CryptoInfo => vec![
+            ("j/k", "move"),
+            ("q", "close"),
+        ],
+        InputMode::Connection => vec![
+            ("j/k", "move"),
+            ("Enter", "copy"),
+            ("q", "close"),
+        ],
+        InputMode::Merge => vec![
+            ("j/k", "field"),
+            ("h/l", "side"),
+            ("Enter", "merge"),
+            ("q", "cancel"),
+        ],
         InputMode::Confirm => vec![
             ("y", "yes"),
             ("n", "no"),
@@ -198,8 +421,16 @@ fn hints_for_mode(mode: InputMode) -> Vec<(&'static str, &'static str)> {
         InputMode::Help => vec![
             ("q", "close"),
             ("j/k", "scroll"),
+            ("/", "search"),
         ],
-        InputMode::Logs | InputMode::Tags => vec![
+        InputMode::Logs => vec![
+            ("j/k", "scroll"),
+            ("/", "search"),
+            ("a", "action"),
+            ("c", "credential"),
+            ("q", "close"),
+        ],
+        InputMode::Tags | InputMode::Timeline => vec![
             ("j/k", "scroll"),
             ("Ctrl-d/u", "page"),
             ("q", "close"),
@@ -207,15 +438,35 @@ fn hints_for_mode(mode: InputMode) -> Vec<(&'static str, &'static str)> {
     }
 }
 
+/// Separator drawn between footer hints
+const HINT_SEPARATOR: &str = " │ ";
+
+/// The key of the footer hint drawn at `column`, counted from the left edge
+/// of the help bar
+pub fn hint_at(context: HintContext, column: u16) -> Option<&'static str> {
+    let mut x = 0usize;
+    for (i, (key, desc)) in hints_for_context(context).into_iter().enumerate() {
+        if i > 0 {
+            x += HINT_SEPARATOR.chars().count();
+        }
+        let width = key.chars().count() + 1 + i18n::tr(desc).width();
+        if (x..x + width).contains(&(column as usize)) {
+            return Some(key);
+        }
+        x += width;
+    }
+    None
+}
+
 fn build_hint_spans<'a>(hints: &[(&'a str, &'a str)]) -> Vec<Span<'a>> {
     let mut spans: Vec<Span> = Vec::new();
 
     for (i, (key, desc)) in hints.iter().enumerate() {
         if i > 0 {
-            spans.push(Span::styled(" │ ", Style::default().fg(Color::DarkGray)));
+            spans.push(Span::styled(HINT_SEPARATOR, Style::default().fg(Color::DarkGray)));
         }
         spans.push(Span::styled(*key, Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)));
-        spans.push(Span::styled(format!(" {}", desc), Style::default().fg(Color::Gray)));
+        spans.push(Span::styled(format!(" {}", i18n::tr(desc)), Style::default().fg(Color::Gray)));
     }
 
     spans
@@ -1,6 +1,6 @@
 //! Tags popup and state
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
 use ratatui::{
     buffer::Buffer,
@@ -8,8 +8,9 @@ use ratatui::{
     style::{Color, Modifier, Style},
     widgets::{Clear, Widget},
 };
+use unicode_width::UnicodeWidthStr;
 
-use crate::db::Credential;
+use crate::ui::theme;
 
 use super::layout::{
     centered_rect_fixed, create_popup_block, highlight_row, render_empty_message, render_footer,
@@ -30,8 +31,9 @@ impl TagsState {
         Self::default()
     }
 
-    pub fn set_tags_from_credentials(&mut self, credentials: &[Credential]) {
-        self.tags = aggregate_tags(credentials);
+    /// Tags with how many credentials carry each, most used first
+    pub fn set_tags(&mut self, tags: Vec<(String, usize)>) {
+        self.tags = tags;
         self.scroll.reset();
         self.selected = 0;
         self.selected_tags.clear();
@@ -91,18 +93,6 @@ impl TagsState {
     }
 }
 
-fn aggregate_tags(credentials: &[Credential]) -> Vec<(String, usize)> {
-    let mut counts: HashMap<String, usize> = HashMap::new();
-    for cred in credentials {
-        for tag in &cred.tags {
-            *counts.entry(tag.clone()).or_insert(0) += 1;
-        }
-    }
-    let mut tags: Vec<_> = counts.into_iter().collect();
-    tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
-    tags
-}
-
 pub struct TagsPopup<'a> {
     state: &'a TagsState,
 }
@@ -218,24 +208,27 @@ fn render_tag_row(
         highlight_row(buf, inner.x, y, inner.width);
     }
 
-    render_tag_checkbox(buf, inner.x, y, is_checked, is_cursor);
-    render_tag_name(buf, inner.x + 2, y, inner.width, tag, is_cursor);
+    let checkbox_width = render_tag_checkbox(buf, inner.x, y, is_checked, is_cursor);
+    render_tag_name(buf, inner.x + checkbox_width, y, inner.width, tag, is_cursor);
     render_tag_count(buf, inner.x + inner.width - 5, y, count, is_cursor);
 }
 
-fn render_tag_checkbox(buf: &mut Buffer, x: u16, y: u16, checked: bool, highlight: bool) {
-    let icon = if checked { "󰗠 " } else { "󰄰 " };
+fn render_tag_checkbox(buf: &mut Buffer, x: u16, y: u16, checked: bool, highlight: bool) -> u16 {
+    let icon = theme::checkbox(checked);
     let style = Style::default().fg(Color::Green);
     let style = if highlight { style.bg(Color::DarkGray) } else { style };
     buf.set_string(x, y, icon, style);
+    icon.width() as u16
 }
 
 fn render_tag_name(buf: &mut Buffer, x: u16, y: u16, inner_width: u16, tag: &str, highlight: bool) {
     let max_width = (inner_width as usize).saturating_sub(8);
-    let display = truncate_with_ellipsis(tag, max_width);
     let style = Style::default().fg(Color::White);
     let style = if highlight { style.bg(Color::DarkGray) } else { style };
-    buf.set_string(x, y, &display, style);
+    // Styled tags show as their chips, so the popup doubles as a legend
+    let span = theme::tag_span(tag, style);
+    let display = truncate_with_ellipsis(&span.content, max_width);
+    buf.set_string(x, y, &display, span.style);
 }
 
 fn render_tag_count(buf: &mut Buffer, x: u16, y: u16, count: usize, highlight: bool) {
@@ -0,0 +1,157 @@
+//! Credential timeline popup and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::vault::timeline::TimelineEntry;
+
+use super::layout::{
+    centered_rect, create_popup_block, render_empty_message, render_footer, render_separator_line,
+    truncate_with_ellipsis,
+};
+use super::logs::action_display;
+use super::scroll::{render_v_scroll_indicator, ScrollState};
+
+const TIMESTAMP_WIDTH: u16 = 20;
+const ACTION_WIDTH: u16 = 8;
+
+#[derive(Default)]
+pub struct TimelineState {
+    pub scroll: ScrollState,
+    pub credential_name: String,
+    pub entries: Vec<TimelineEntry>,
+}
+
+impl TimelineState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_entries(&mut self, credential_name: &str, entries: Vec<TimelineEntry>) {
+        self.credential_name = credential_name.to_string();
+        self.entries = entries;
+        self.scroll.reset();
+    }
+
+    pub fn clear(&mut self) {
+        self.credential_name.clear();
+        self.entries.clear();
+        self.scroll.reset();
+    }
+
+    pub fn scroll_up(&mut self, amount: usize) {
+        self.scroll.scroll_up(amount);
+    }
+
+    pub fn scroll_down(&mut self, amount: usize, max: usize) {
+        self.scroll.scroll_down(amount, max);
+    }
+
+    pub fn home(&mut self) {
+        self.scroll.home();
+    }
+
+    pub fn end(&mut self, max: usize) {
+        self.scroll.end(max);
+    }
+
+    pub fn max_scroll(&self, visible_height: u16) -> usize {
+        self.entries.len().saturating_sub(visible_height as usize)
+    }
+}
+
+pub struct TimelinePopup<'a> {
+    state: &'a TimelineState,
+}
+
+impl<'a> TimelinePopup<'a> {
+    pub fn new(state: &'a TimelineState) -> Self {
+        Self { state }
+    }
+
+    pub fn visible_height(area: Rect) -> u16 {
+        let popup = centered_rect(70, 70, area);
+        popup.height.saturating_sub(5)
+    }
+}
+
+impl Widget for TimelinePopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let popup = centered_rect(70, 70, area);
+        Clear.render(popup, buf);
+
+        let title = format!(" Timeline: {} ", self.state.credential_name);
+        let block = create_popup_block(&title, Color::Magenta);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        if self.state.entries.is_empty() {
+            render_empty_message(inner, buf, "No recorded events");
+            return;
+        }
+
+        // Header takes 2 rows (header + separator)
+        let header_height = 2u16;
+        let entries_area_height = inner.height.saturating_sub(header_height) as usize;
+        let max_v = self.state.entries.len().saturating_sub(entries_area_height);
+        let needs_v_scroll = max_v > 0;
+
+        render_footer(buf, popup, " j/k scroll - gg/G top/bottom - q close ");
+
+        render_timeline_header(inner, buf);
+        render_separator_line(buf, inner.x, inner.y + 1, inner.width);
+
+        let entries_height = if needs_v_scroll {
+            entries_area_height.saturating_sub(1)
+        } else {
+            entries_area_height
+        };
+        render_timeline_entries(inner, buf, inner.y + header_height, entries_height, self.state);
+
+        let indicator_area = Rect::new(
+            inner.x,
+            inner.y + header_height,
+            inner.width,
+            inner.height.saturating_sub(header_height),
+        );
+        if needs_v_scroll {
+            render_v_scroll_indicator(buf, &indicator_area, self.state.scroll.v_scroll, max_v, Color::Magenta);
+        }
+    }
+}
+
+fn render_timeline_header(inner: Rect, buf: &mut Buffer) {
+    let style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    buf.set_string(inner.x, inner.y, "WHEN", style);
+    buf.set_string(inner.x + TIMESTAMP_WIDTH, inner.y, "ACTION", style);
+    buf.set_string(inner.x + TIMESTAMP_WIDTH + ACTION_WIDTH, inner.y, "EVENT", style);
+}
+
+fn render_timeline_entries(inner: Rect, buf: &mut Buffer, start_y: u16, visible_count: usize, state: &TimelineState) {
+    for (i, entry) in state.entries.iter().enumerate().skip(state.scroll.v_scroll) {
+        let row = i - state.scroll.v_scroll;
+        if row >= visible_count {
+            break;
+        }
+        render_timeline_row(inner, buf, start_y + row as u16, entry);
+    }
+}
+
+fn render_timeline_row(inner: Rect, buf: &mut Buffer, y: u16, entry: &TimelineEntry) {
+    let timestamp = entry.timestamp.format("%d-%b-%Y %H:%M").to_string();
+    let (action_str, action_color) = action_display(&entry.action);
+    let event_width = inner.width.saturating_sub(TIMESTAMP_WIDTH + ACTION_WIDTH) as usize;
+
+    buf.set_string(inner.x, y, &timestamp, Style::default().fg(Color::Magenta));
+    buf.set_string(inner.x + TIMESTAMP_WIDTH, y, action_str, Style::default().fg(action_color));
+    buf.set_string(
+        inner.x + TIMESTAMP_WIDTH + ACTION_WIDTH,
+        y,
+        truncate_with_ellipsis(&entry.label(), event_width),
+        Style::default().fg(Color::White),
+    );
+}
@@ -0,0 +1,87 @@
+//! Security tips popup and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Clear, Paragraph, Widget, Wrap},
+};
+
+use super::layout::{centered_rect_fixed, create_popup_block, render_footer};
+
+/// One piece of advice; a warning applies to this system as configured
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tip {
+    pub title: &'static str,
+    pub text: String,
+    pub warning: bool,
+}
+
+impl Tip {
+    pub fn new(title: &'static str, text: impl Into<String>, warning: bool) -> Self {
+        Self { title, text: text.into(), warning }
+    }
+}
+
+#[derive(Default)]
+pub struct TipsState {
+    pub tips: Vec<Tip>,
+    /// First line shown
+    pub scroll: u16,
+}
+
+impl TipsState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_tips(&mut self, tips: Vec<Tip>) {
+        self.tips = tips;
+        self.scroll = 0;
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1);
+    }
+
+    pub fn scroll_down(&mut self) {
+        self.scroll = self.scroll.saturating_add(1);
+    }
+}
+
+pub struct TipsPopup<'a> {
+    state: &'a TipsState,
+}
+
+impl<'a> TipsPopup<'a> {
+    pub fn new(state: &'a TipsState) -> Self {
+        Self { state }
+    }
+}
+
+impl Widget for TipsPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let height = ((area.height * 80) / 100).clamp(8, 24);
+        let popup = centered_rect_fixed(72, height, area, true);
+        Clear.render(popup, buf);
+
+        let block = create_popup_block(" Security tips ", Color::Cyan);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        render_footer(buf, popup, " j/k scroll - q close ");
+
+        let mut lines = Vec::new();
+        for tip in &self.state.tips {
+            let color = if tip.warning { Color::Yellow } else { Color::Cyan };
+            lines.push(Line::styled(tip.title, Style::default().fg(color).add_modifier(Modifier::BOLD)));
+            lines.push(Line::styled(tip.text.clone(), Style::default().fg(Color::Gray)));
+            lines.push(Line::default());
+        }
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: true })
+            .scroll((self.state.scroll, 0))
+            .render(inner, buf);
+    }
+}
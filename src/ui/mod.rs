@@ -4,6 +4,7 @@
 
 pub mod components;
 pub mod renderer;
+pub mod theme;
 
 // Re-exports
 pub use components::{
@@ -3,23 +3,40 @@
 //! Main rendering logic for the application.
 
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
-    widgets::{Block, Borders, BorderType, Widget},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, BorderType, Paragraph, Widget, Wrap},
     Frame,
 };
 
 use super::components::{
     ConfirmDialog, CredentialDetail, CredentialForm, CredentialFormWidget, CredentialItem,
     CredentialList, DetailView, EmptyState, HelpBar, HelpScreen, ListViewState, MessageType,
-    PasswordDialog, StatusLine,
+    Pane, PaneLayout, PasswordDialog, SkeletonList, StatusLine,
 };
+use crate::hardening::Mitigation;
+use crate::i18n;
 use crate::input::InputMode;
+use crate::input::line::EditLine;
+use crate::ui::components::backups::{BackupsPopup, BackupsState};
+use crate::ui::components::connection::{ConnectionPopup, ConnectionState};
+use crate::ui::components::dupes::{DupesPopup, DupesState};
+use crate::ui::components::security::SecurityPopup;
+use crate::ui::components::tips::{TipsPopup, TipsState};
+use crate::ui::components::cryptoinfo::{CryptoInfoPopup, CryptoInfoState};
+use crate::ui::components::merge::{MergePopup, MergeState};
+use crate::ui::components::finder::{FinderPopup, FinderState};
+use crate::ui::components::info::{InfoPopup, InfoState};
+use crate::ui::components::palette::{PalettePopup, PaletteState};
 use crate::ui::components::help::HelpState;
+use crate::ui::components::list::ListColumn;
+use crate::ui::components::statusline::HintContext;
 use crate::ui::components::logs::{LogsScreen, LogsState};
 use crate::ui::components::tags::{TagsPopup, TagsState};
+use crate::ui::components::timeline::{TimelinePopup, TimelineState};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum View {
     List,
     Detail,
@@ -30,23 +47,74 @@ pub struct UiState<'a> {
     pub view: View,
     pub mode: InputMode,
     pub credentials: &'a [CredentialItem],
+    /// List row of the first of `credentials`
+    pub credentials_start: usize,
+    pub list_columns: &'a [ListColumn],
+    /// Search text highlighted in the list
+    pub search_term: Option<&'a str>,
     pub list_state: &'a mut ListViewState,
     pub selected_detail: Option<&'a CredentialDetail>,
-    pub command_buffer: Option<&'a str>,
+    /// The detail was wiped after a spell without input
+    pub detail_wiped: bool,
+    pub detail_scroll: usize,
+    pub command_buffer: Option<&'a EditLine>,
     pub message: Option<(&'a str, MessageType)>,
-    pub confirm_message: Option<&'a str>,
+    pub confirm: Option<ConfirmPrompt<'a>>,
     pub password_prompt: Option<PasswordPrompt<'a>>,
     pub credential_form: Option<&'a CredentialForm>,
     pub help_state: &'a HelpState,
     pub logs_state: &'a LogsState,
     pub tags_state: &'a TagsState,
+    pub timeline_state: &'a TimelineState,
+    pub finder_state: &'a FinderState,
+    pub palette_state: &'a PaletteState,
+    pub backups_state: &'a BackupsState,
+    pub dupes_state: &'a DupesState,
+    pub merge_state: &'a MergeState,
+    pub security_report: &'a [Mitigation],
+    pub tips_state: &'a TipsState,
+    pub info_state: &'a InfoState,
+    pub crypto_info_state: &'a CryptoInfoState,
+    /// Old ciphertexts are being re-encrypted in the background
+    pub migrating: bool,
+    pub connection_state: &'a ConnectionState,
+    pub panes: &'a PaneLayout,
+    pub lock_countdown: Option<u64>,
+    pub filter_chips: &'a [String],
+    pub read_only: bool,
+    /// File name of the backup shown in place of the vault
+    pub inspecting: Option<&'a str>,
+    /// Privacy mode: usernames and URLs masked, secrets kept hidden
+    pub private: bool,
+    pub vault_name: Option<&'a str>,
+    pub hints: HintContext,
+    /// Just unlocked, the list's first page not yet loaded
+    pub loading: bool,
+    pub areas: &'a mut ScreenAreas,
+}
+
+/// Where clickable parts of the screen were last drawn
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScreenAreas {
+    pub detail: Option<Rect>,
+    pub help_bar: Rect,
+}
+
+pub struct ConfirmPrompt<'a> {
+    pub message: String,
+    /// Lines shown under the message
+    pub details: Vec<String>,
+    /// Text to type before confirming, and what has been typed so far
+    pub phrase: Option<&'a str>,
+    pub typed: &'a EditLine,
+    /// Seconds left before the confirmation is cancelled
+    pub remaining: Option<u64>,
 }
 
 pub struct PasswordPrompt<'a> {
     pub title: &'a str,
     pub prompt: &'a str,
-    pub value: &'a str,
-    pub cursor: usize,
+    pub line: &'a EditLine,
     pub error: Option<&'a str>,
 }
 
@@ -63,15 +131,38 @@ impl Renderer {
 
     pub fn render(frame: &mut Frame, state: &mut UiState) {
         let size = frame.area();
+        if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+            *state.areas = ScreenAreas::default();
+            state.list_state.rows_area = Rect::default();
+            render_too_small(frame, size);
+            return;
+        }
         let chunks = create_main_layout(size);
 
         render_content(frame, chunks[0], state);
         render_status_line(frame, chunks[1], state);
-        render_help_bar(frame, chunks[2], state.mode);
+        state.areas.detail = None;
+        state.areas.help_bar = chunks[2];
+        render_help_bar(frame, chunks[2], state.hints);
         render_overlays(frame, size, state);
     }
 }
 
+/// Below this the popups cannot fit their contents, so nothing but a
+/// notice is drawn until the terminal grows again
+const MIN_WIDTH: u16 = 80;
+const MIN_HEIGHT: u16 = 24;
+
+fn render_too_small(frame: &mut Frame, size: Rect) {
+    let lines = vec![
+        Line::styled(i18n::tr("Terminal too small"), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Line::raw(i18n::trf("{}x{}, needs at least {}x{}", &[&size.width, &size.height, &MIN_WIDTH, &MIN_HEIGHT])),
+    ];
+    let y = size.y + size.height.saturating_sub(lines.len() as u16) / 2;
+    let area = Rect::new(size.x, y, size.width, size.height.saturating_sub(y - size.y));
+    frame.render_widget(Paragraph::new(lines).alignment(Alignment::Center).wrap(Wrap { trim: true }), area);
+}
+
 fn create_main_layout(size: Rect) -> std::rc::Rc<[Rect]> {
     Layout::default()
         .direction(Direction::Vertical)
@@ -92,7 +183,10 @@ fn render_content(frame: &mut Frame, area: Rect, state: &mut UiState) {
 }
 
 fn render_status_line(frame: &mut Frame, area: Rect, state: &UiState) {
-    let mut status = StatusLine::new(state.mode);
+    let mut status = StatusLine::new(state.mode).read_only(state.read_only).inspecting(state.inspecting).private(state.private);
+    if let Some(name) = state.vault_name {
+        status = status.vault_name(name);
+    }
 
     if let Some(buffer) = state.command_buffer {
         status = status.command_buffer(buffer);
@@ -104,46 +198,98 @@ fn render_status_line(frame: &mut Frame, area: Rect, state: &UiState) {
         status = status.item_count(selected, state.list_state.total);
     }
 
+    if let Some(secs) = state.lock_countdown {
+        status = status.lock_countdown(secs);
+    }
+
+    if !state.filter_chips.is_empty() {
+        status = status.filters(state.filter_chips);
+    }
+
     frame.render_widget(status, area);
 }
 
-fn render_help_bar(frame: &mut Frame, area: Rect, mode: InputMode) {
-    let help_bar = HelpBar::for_mode(mode);
+fn render_help_bar(frame: &mut Frame, area: Rect, hints: HintContext) {
+    let help_bar = HelpBar::for_context(hints);
     frame.render_widget(help_bar, area);
 }
 
 fn render_list(frame: &mut Frame, area: Rect, state: &mut UiState) {
+    if state.loading {
+        let (list_area, preview_area) = state.panes.preview_split(area);
+        frame.render_widget(SkeletonList::default().block(create_credentials_block(Color::Magenta)), list_area);
+        if let Some(preview_area) = preview_area {
+            frame.render_widget(EmptyState::new(i18n::tr("Loading...")), preview_area);
+        }
+        return;
+    }
     if state.credentials.is_empty() {
-        let empty = EmptyState::new("No credentials").hint("Press 'n' to add one");
+        let (message, hint) = match state.search_term {
+            Some(term) => (i18n::trf("No matches for '{}'", &[&term]), i18n::trf("Press 'n' to create '{}'", &[&term])),
+            None => (i18n::tr("No credentials").to_string(), i18n::tr("Press 'n' to add one").to_string()),
+        };
+        let hint = if state.read_only { "Vault is open read-only" } else { &hint };
+        let empty = EmptyState::new(&message).hint(hint);
         frame.render_widget(empty, area);
         return;
     }
 
+    let (list_area, preview_area) = state.panes.preview_split(area);
     let block = create_credentials_block(Color::Magenta);
-    let list = CredentialList::new(state.credentials).block(block);
-    frame.render_stateful_widget(list, area, state.list_state);
+    let list = CredentialList::new(state.credentials)
+        .first_row(state.credentials_start)
+        .columns(state.list_columns)
+        .search_term(state.search_term)
+        .private(state.private)
+        .block(block);
+    frame.render_stateful_widget(list, list_area, state.list_state);
+
+    if let Some(preview_area) = preview_area {
+        match state.selected_detail {
+            Some(d) => frame.render_widget(DetailView::new(d).preview(true).private(state.private), preview_area),
+            None => frame.render_widget(empty_detail(state), preview_area),
+        }
+    }
 }
 
 fn render_detail(frame: &mut Frame, area: Rect, state: &mut UiState) {
-    let chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(area);
+    let (list_area, detail_area) = state.panes.split(area);
 
-    render_detail_list(frame, chunks[0], state);
-    render_detail_panel(frame, chunks[1], state.selected_detail);
+    if let Some(list_area) = list_area {
+        render_detail_list(frame, list_area, state);
+    }
+    if let Some(detail_area) = detail_area {
+        state.areas.detail = Some(detail_area);
+        render_detail_panel(frame, detail_area, state);
+    }
 }
 
 fn render_detail_list(frame: &mut Frame, area: Rect, state: &mut UiState) {
-    let block = create_credentials_block(Color::DarkGray);
-    let list = CredentialList::new(state.credentials).block(block);
+    let color = match state.panes.focus {
+        Pane::List => Color::Magenta,
+        Pane::Detail => Color::DarkGray,
+    };
+    let block = create_credentials_block(color);
+    let list = CredentialList::new(state.credentials)
+        .first_row(state.credentials_start)
+        .columns(state.list_columns)
+        .search_term(state.search_term)
+        .private(state.private)
+        .block(block);
     frame.render_stateful_widget(list, area, state.list_state);
 }
 
-fn render_detail_panel(frame: &mut Frame, area: Rect, detail: Option<&CredentialDetail>) {
-    match detail {
-        Some(d) => frame.render_widget(DetailView::new(d), area),
-        None => frame.render_widget(EmptyState::new("Select a credential"), area),
+fn render_detail_panel(frame: &mut Frame, area: Rect, state: &UiState) {
+    match state.selected_detail {
+        Some(d) => frame.render_widget(DetailView::new(d).private(state.private).scroll(state.detail_scroll), area),
+        None => frame.render_widget(empty_detail(state), area),
+    }
+}
+
+fn empty_detail(state: &UiState) -> EmptyState<'static> {
+    match state.detail_wiped {
+        true => EmptyState::new("Hidden while idle").hint("Press any key to show it again"),
+        false => EmptyState::new("Select a credential"),
     }
 }
 
@@ -177,6 +323,17 @@ fn render_overlays(frame: &mut Frame, area: Rect, state: &UiState) {
 
     render_tags_overlay(frame, state);
     render_logs_overlay(frame, state);
+    render_timeline_overlay(frame, state);
+    render_finder_overlay(frame, state);
+    render_palette_overlay(frame, state);
+    render_backups_overlay(frame, state);
+    render_dupes_overlay(frame, state);
+    render_merge_overlay(frame, state);
+    render_security_overlay(frame, state);
+    render_tips_overlay(frame, state);
+    render_info_overlay(frame, state);
+    render_crypto_info_overlay(frame, state);
+    render_connection_overlay(frame, state);
 
     if render_confirm_overlay(frame, area, state) {
         return;
@@ -189,7 +346,7 @@ fn render_help_overlay(frame: &mut Frame, area: Rect, state: &UiState) -> bool {
     if state.mode != InputMode::Help {
         return false;
     }
-    frame.render_widget(HelpScreen::new(state.help_state), area);
+    frame.render_widget(HelpScreen::new(state.help_state).read_only(state.read_only), area);
     true
 }
 
@@ -207,12 +364,95 @@ fn render_logs_overlay(frame: &mut Frame, state: &UiState) {
     LogsScreen::new(state.logs_state).render(frame.area(), frame.buffer_mut());
 }
 
+fn render_timeline_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Timeline {
+        return;
+    }
+    TimelinePopup::new(state.timeline_state).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_finder_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Finder {
+        return;
+    }
+    FinderPopup::new(state.finder_state).private(state.private).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_palette_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Palette {
+        return;
+    }
+    PalettePopup::new(state.palette_state).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_backups_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Backups {
+        return;
+    }
+    BackupsPopup::new(state.backups_state).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_dupes_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Dupes {
+        return;
+    }
+    DupesPopup::new(state.dupes_state).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_merge_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Merge {
+        return;
+    }
+    MergePopup::new(state.merge_state).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_security_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Security {
+        return;
+    }
+    SecurityPopup::new(state.security_report).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_tips_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Tips {
+        return;
+    }
+    TipsPopup::new(state.tips_state).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_info_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Info {
+        return;
+    }
+    InfoPopup::new(state.info_state).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_crypto_info_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::CryptoInfo {
+        return;
+    }
+    CryptoInfoPopup::new(state.crypto_info_state).migrating(state.migrating).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_connection_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Connection {
+        return;
+    }
+    ConnectionPopup::new(state.connection_state).render(frame.area(), frame.buffer_mut());
+}
+
 fn render_confirm_overlay(frame: &mut Frame, area: Rect, state: &UiState) -> bool {
     if state.mode != InputMode::Confirm {
         return false;
     }
-    if let Some(msg) = state.confirm_message {
-        let dialog = ConfirmDialog::new(" Confirm ", msg);
+    if let Some(prompt) = &state.confirm {
+        let mut dialog = ConfirmDialog::new(" Confirm ", &prompt.message).details(&prompt.details);
+        if let Some(phrase) = prompt.phrase {
+            dialog = dialog.phrase(phrase, prompt.typed);
+        }
+        if let Some(secs) = prompt.remaining {
+            dialog = dialog.countdown(secs);
+        }
         frame.render_widget(dialog, area);
     }
     true
@@ -224,7 +464,7 @@ fn render_password_overlay(frame: &mut Frame, area: Rect, state: &UiState) {
         None => return,
     };
 
-    let mut dialog = PasswordDialog::new(prompt.title, prompt.prompt, prompt.value, prompt.cursor);
+    let mut dialog = PasswordDialog::new(prompt.title, prompt.prompt, prompt.line);
     if let Some(err) = prompt.error {
         dialog = dialog.error(err);
     }
@@ -0,0 +1,199 @@
+//! Icons and Contrast
+//!
+//! Chosen once at startup from the config. The default icons are Nerd
+//! Font glyphs, which stock terminal fonts show as boxes, so there is a
+//! plain ASCII set too, picked automatically on the Linux console and
+//! under non-UTF-8 locales. High contrast brightens dim text and marks
+//! selections and message kinds with more than color. Tags given a
+//! color or icon in the config are drawn as chips wherever they appear.
+
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::db::models::{CredentialType, CustomType};
+
+/// Which glyphs the UI draws icons with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IconSet {
+    /// Nerd Font, unless the terminal looks like it cannot show it
+    #[default]
+    Auto,
+    Nerd,
+    Ascii,
+}
+
+impl IconSet {
+    /// `Auto` resolved against the environment
+    fn resolve(self) -> Self {
+        match self {
+            Self::Auto => detect(|name| std::env::var(name).ok()),
+            set => set,
+        }
+    }
+}
+
+/// How a tag is drawn, from the config's `tag_styles`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TagStyle {
+    /// A color name such as `red` or `lightblue`, or `#rrggbb`
+    #[serde(deserialize_with = "color_name", skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+fn color_name<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    let name = Option::<String>::deserialize(deserializer)?;
+    if let Some(name) = &name {
+        Color::from_str(name).map_err(|_| serde::de::Error::custom(format!("unknown color '{}'", name)))?;
+    }
+    Ok(name)
+}
+
+struct Theme {
+    icons: IconSet,
+    high_contrast: bool,
+    tags: HashMap<String, (Option<Color>, Option<String>)>,
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+pub fn init(icons: IconSet, high_contrast: bool, tag_styles: &BTreeMap<String, TagStyle>) {
+    let tags = tag_styles
+        .iter()
+        .map(|(tag, style)| {
+            let color = style.color.as_deref().and_then(|c| Color::from_str(c).ok());
+            (tag.clone(), (color, style.icon.clone()))
+        })
+        .collect();
+    let _ = THEME.set(Theme { icons: icons.resolve(), high_contrast, tags });
+}
+
+pub fn ascii() -> bool {
+    THEME.get().is_some_and(|t| t.icons == IconSet::Ascii)
+}
+
+pub fn high_contrast() -> bool {
+    THEME.get().is_some_and(|t| t.high_contrast)
+}
+
+/// The Linux console has no Nerd Font, and without a UTF-8 locale the
+/// glyphs cannot be written at all. Anything else gets the benefit of
+/// the doubt.
+fn detect(var: impl Fn(&str) -> Option<String>) -> IconSet {
+    if matches!(var("TERM").as_deref(), Some("linux" | "dumb")) {
+        return IconSet::Ascii;
+    }
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"].into_iter().find_map(|name| var(name).filter(|v| !v.is_empty()));
+    match locale {
+        Some(locale) if !locale.to_lowercase().replace('-', "").contains("utf8") => IconSet::Ascii,
+        _ => IconSet::Nerd,
+    }
+}
+
+pub fn type_icon(cred_type: CredentialType) -> &'static str {
+    if ascii() { cred_type.ascii_icon() } else { cred_type.icon() }
+}
+
+/// A custom type's own icon, unless it cannot be shown
+pub fn custom_icon(custom: &CustomType) -> &str {
+    match custom.icon.as_deref() {
+        Some(icon) if !ascii() || icon.is_ascii() => icon,
+        _ => type_icon(CredentialType::Custom),
+    }
+}
+
+/// A tag as text, after its icon if it has one that can be shown
+pub fn tag_label(tag: &str) -> String {
+    let icon = THEME.get().and_then(|t| t.tags.get(tag)).and_then(|(_, icon)| icon.as_deref());
+    match icon {
+        Some(icon) if !ascii() || icon.is_ascii() => format!("{} {}", icon, tag),
+        _ => tag.to_string(),
+    }
+}
+
+/// Whether the config gives a tag a color or icon
+pub fn is_styled_tag(tag: &str) -> bool {
+    THEME.get().is_some_and(|t| t.tags.contains_key(tag))
+}
+
+/// A tag as a chip in its color, or as plain text in `plain` if it has
+/// none
+pub fn tag_span(tag: &str, plain: Style) -> Span<'static> {
+    match THEME.get().and_then(|t| t.tags.get(tag)).and_then(|(color, _)| *color) {
+        Some(color) => Span::styled(format!(" {} ", tag_label(tag)), Style::default().fg(Color::Black).bg(color)),
+        None => Span::styled(tag_label(tag), plain),
+    }
+}
+
+pub fn selection_marker() -> &'static str {
+    if ascii() { "> " } else { "\u{f105} " }
+}
+
+pub fn checkbox(checked: bool) -> &'static str {
+    match (ascii(), checked) {
+        (false, true) => "󰗠 ",
+        (false, false) => "󰄰 ",
+        (true, true) => "[x] ",
+        (true, false) => "[ ] ",
+    }
+}
+
+/// Scroll arrows: can scroll forward only, back only, or both ways
+pub fn v_scroll_arrows() -> [&'static str; 3] {
+    if ascii() { ["v", "^", "|"] } else { ["\u{f103}", "\u{f102}", "\u{f07d}"] }
+}
+
+pub fn h_scroll_arrows() -> [&'static str; 3] {
+    if ascii() { [">", "<", "-"] } else { ["\u{f101}", "\u{f100}", "\u{f07e}"] }
+}
+
+/// Secondary text: labels, footers, timestamps
+pub fn muted() -> Color {
+    if high_contrast() { Color::Gray } else { Color::DarkGray }
+}
+
+/// Applied to a highlighted row's cells on top of its background, so the
+/// selection does not rest on a slightly different gray alone
+pub fn highlight_modifier() -> Modifier {
+    if high_contrast() { Modifier::REVERSED | Modifier::BOLD } else { Modifier::empty() }
+}
+
+/// The style of the field being edited, set apart by more than color
+pub fn active_style(color: Color) -> Style {
+    let style = Style::default().fg(color).add_modifier(Modifier::BOLD);
+    if high_contrast() { style.add_modifier(Modifier::UNDERLINED) } else { style }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| vars.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(env(&[("TERM", "xterm-256color"), ("LANG", "en_US.UTF-8")])), IconSet::Nerd);
+        assert_eq!(detect(env(&[("TERM", "linux"), ("LANG", "en_US.UTF-8")])), IconSet::Ascii);
+        assert_eq!(detect(env(&[("LC_ALL", "C"), ("LANG", "en_US.utf8")])), IconSet::Ascii);
+        assert_eq!(detect(env(&[("LC_ALL", ""), ("LANG", "de_DE.utf8")])), IconSet::Nerd);
+        assert_eq!(detect(env(&[])), IconSet::Nerd);
+    }
+
+    #[test]
+    fn test_tag_style() {
+        let style: TagStyle = serde_json::from_str(r#"{"color": "light-red", "icon": "!"}"#).unwrap();
+        assert_eq!(style.color.as_deref(), Some("light-red"));
+        assert!(serde_json::from_str::<TagStyle>(r#"{"color": "reddish"}"#).is_err());
+        assert_eq!(serde_json::to_string(&TagStyle::default()).unwrap(), "{}");
+    }
+}
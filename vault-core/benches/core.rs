@@ -0,0 +1,55 @@
+//! Crypto and database benchmarks
+//!
+//! `cargo bench` runs everything; `cargo bench -- search` runs one group.
+//! The workloads are the ones `vault bench` times on a user's machine.
+
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use vault_core::vault::bench::{self, Fixture, DEFAULT_COUNT};
+
+fn kdf(c: &mut Criterion) {
+    let mut group = c.benchmark_group("kdf");
+    group.sample_size(10).measurement_time(Duration::from_secs(20));
+    for (name, params) in bench::kdf_presets() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &params, |b, params| {
+            b.iter(|| bench::derive_key(params).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bulk_crypto(c: &mut Criterion) {
+    let fixture = Fixture::new(DEFAULT_COUNT).unwrap();
+    let mut group = c.benchmark_group("bulk");
+    group.sample_size(10).throughput(Throughput::Elements(DEFAULT_COUNT as u64));
+    group.bench_function("encrypt", |b| b.iter(|| fixture.encrypt_all().unwrap()));
+    group.bench_function("decrypt", |b| b.iter(|| fixture.decrypt_all().unwrap()));
+    group.finish();
+}
+
+fn search(c: &mut Criterion) {
+    let fixture = Fixture::new(DEFAULT_COUNT).unwrap();
+    let mut group = c.benchmark_group("search");
+    for query in ["github", "user42", "tag:finance", "zzz-no-match"] {
+        group.bench_with_input(BenchmarkId::from_parameter(query), query, |b, query| {
+            b.iter(|| fixture.search(query).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn refresh(c: &mut Criterion) {
+    let mut group = c.benchmark_group("refresh");
+    for count in [1_000, DEFAULT_COUNT, 50_000] {
+        let fixture = Fixture::new(count).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(count), &fixture, |b, fixture| {
+            b.iter(|| fixture.refresh().unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, kdf, bulk_crypto, search, refresh);
+criterion_main!(benches);
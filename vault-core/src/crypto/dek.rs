@@ -19,6 +19,8 @@ use super::{CryptoError, CryptoResult, LockedBuffer, MasterKey};
 #[derive(Clone)]
 pub struct DataEncryptionKey {
     key: LockedBuffer<32>,
+    /// Refuse blobs sealed without associated data
+    bound_only: bool,
 }
 
 impl DataEncryptionKey {
@@ -28,6 +30,7 @@ impl DataEncryptionKey {
         rand::thread_rng().fill_bytes(&mut key);
         let dek = Self {
             key: LockedBuffer::new(key),
+            bound_only: false,
         };
 
         // Zeroize the temporary buffer
@@ -40,6 +43,7 @@ impl DataEncryptionKey {
     pub fn from_bytes(bytes: [u8; 32]) -> Self {
         Self {
             key: LockedBuffer::new(bytes),
+            bound_only: false,
         }
     }
 
@@ -62,6 +66,16 @@ impl DataEncryptionKey {
         result
     }
 
+    /// Stop accepting blobs from before ciphertexts were bound, once the
+    /// vault has none left; one found after that was put there
+    pub fn require_bound(&mut self) {
+        self.bound_only = true;
+    }
+
+    pub fn requires_bound(&self) -> bool {
+        self.bound_only
+    }
+
     /// Re-wrap the DEK with a new master key
     /// This is the core operation for password changes
     pub fn rewrap(&self, new_master_key: &MasterKey) -> CryptoResult<String> {
@@ -91,7 +105,7 @@ fn build_dek_from_bytes(bytes: &[u8]) -> CryptoResult<DataEncryptionKey> {
     }
     let mut key = [0u8; 32];
     key.copy_from_slice(bytes);
-    let dek = DataEncryptionKey { key: LockedBuffer::new(key) };
+    let dek = DataEncryptionKey { key: LockedBuffer::new(key), bound_only: false };
     key.zeroize();
     Ok(dek)
 }
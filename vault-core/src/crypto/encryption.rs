@@ -0,0 +1,386 @@
+//! Encryption Module
+//!
+//! AEAD encryption for credential secrets.
+//!
+//! Blobs are sealed in a versioned envelope: `v2:` then hex of a byte
+//! naming the algorithm, the nonce and the ciphertext, so a later switch
+//! of algorithm can tell every existing blob apart. Credential fields are
+//! sealed with associated data naming the record and field they belong
+//! to; a blob moved to another row or field then fails to authenticate.
+//!
+//! Two older formats still decrypt: `v1:` then hex of a ChaCha20-Poly1305
+//! nonce and ciphertext with associated data, and the same hex without a
+//! prefix or associated data from before either existed.
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Nonce, Payload},
+    ChaCha20Poly1305, XChaCha20Poly1305,
+};
+use rand::RngCore;
+
+use super::{CryptoError, CryptoResult};
+
+/// Nonce size for ChaCha20-Poly1305 (96 bits)
+pub const NONCE_SIZE: usize = 12;
+
+/// Nonce size for XChaCha20-Poly1305 (192 bits)
+pub const XNONCE_SIZE: usize = 24;
+
+/// Encrypted blob with nonce prepended
+pub type EncryptedBlob = String;
+
+/// Marks a ChaCha20-Poly1305 blob sealed with associated data
+pub const BOUND_PREFIX: &str = "v1:";
+
+/// Marks a blob in the versioned envelope
+pub const ENVELOPE_PREFIX: &str = "v2:";
+
+/// AEAD algorithms an envelope can name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Algorithm {
+    ChaCha20Poly1305,
+    /// Random 192-bit nonces, safe for any number of messages under one key
+    XChaCha20Poly1305,
+}
+
+impl Algorithm {
+    /// What new blobs are sealed with
+    pub const CURRENT: Self = Self::XChaCha20Poly1305;
+
+    pub fn id(self) -> u8 {
+        match self {
+            Self::ChaCha20Poly1305 => 1,
+            Self::XChaCha20Poly1305 => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Self::ChaCha20Poly1305),
+            2 => Some(Self::XChaCha20Poly1305),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ChaCha20Poly1305 => "ChaCha20-Poly1305",
+            Self::XChaCha20Poly1305 => "XChaCha20-Poly1305",
+        }
+    }
+
+    fn seal(self, key: &[u8], plaintext: &[u8], aad: &[u8]) -> CryptoResult<Vec<u8>> {
+        match self {
+            Self::ChaCha20Poly1305 => seal_with::<ChaCha20Poly1305>(key, NONCE_SIZE, plaintext, aad),
+            Self::XChaCha20Poly1305 => seal_with::<XChaCha20Poly1305>(key, XNONCE_SIZE, plaintext, aad),
+        }
+    }
+
+    fn open(self, key: &[u8], data: &[u8], aad: &[u8]) -> CryptoResult<Vec<u8>> {
+        match self {
+            Self::ChaCha20Poly1305 => open_with::<ChaCha20Poly1305>(key, NONCE_SIZE, data, aad),
+            Self::XChaCha20Poly1305 => open_with::<XChaCha20Poly1305>(key, XNONCE_SIZE, data, aad),
+        }
+    }
+}
+
+/// How a blob was written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Format {
+    /// Unprefixed ChaCha20-Poly1305, no associated data
+    Legacy,
+    /// `v1:` ChaCha20-Poly1305 with associated data
+    Bound,
+    /// `v2:` envelope
+    Envelope(Algorithm),
+    /// A version or algorithm this build does not know
+    Unknown,
+}
+
+impl Format {
+    pub fn of(blob: &str) -> Self {
+        if blob.starts_with(BOUND_PREFIX) {
+            return Self::Bound;
+        }
+        if let Some(rest) = blob.strip_prefix(ENVELOPE_PREFIX) {
+            let id = rest.get(..2).and_then(|id| u8::from_str_radix(id, 16).ok());
+            return id.and_then(Algorithm::from_id).map_or(Self::Unknown, Self::Envelope);
+        }
+        // Hex never starts with a `v`, so anything that does is a newer version
+        if blob.starts_with('v') { Self::Unknown } else { Self::Legacy }
+    }
+
+    pub fn algorithm(self) -> Option<Algorithm> {
+        match self {
+            Self::Legacy | Self::Bound => Some(Algorithm::ChaCha20Poly1305),
+            Self::Envelope(algorithm) => Some(algorithm),
+            Self::Unknown => None,
+        }
+    }
+
+    /// Whether new blobs are written this way
+    pub fn is_current(self) -> bool {
+        self == Self::Envelope(Algorithm::CURRENT)
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Self::Legacy => "ChaCha20-Poly1305, unversioned".to_string(),
+            Self::Bound => "ChaCha20-Poly1305, v1".to_string(),
+            Self::Envelope(algorithm) => format!("{}, v2", algorithm.name()),
+            Self::Unknown => "unknown".to_string(),
+        }
+    }
+}
+
+/// How every blob in the current format starts, to find the others in SQL
+pub fn current_prefix() -> String {
+    format!("{}{:02x}", ENVELOPE_PREFIX, Algorithm::CURRENT.id())
+}
+
+/// Whether a blob was sealed with associated data, rather than in the
+/// unversioned format written before it existed
+pub fn is_bound(blob: &str) -> bool {
+    Format::of(blob) != Format::Legacy
+}
+
+/// Encrypt a string bound to `aad`, which decryption must be given again
+pub fn encrypt_string_bound(key: &[u8], plaintext: &str, aad: &[u8]) -> CryptoResult<EncryptedBlob> {
+    seal(key, plaintext.as_bytes(), aad)
+}
+
+/// Decrypt a string sealed by `encrypt_string_bound` with the same `aad`.
+/// Unversioned blobs are refused.
+pub fn decrypt_string_bound(key: &[u8], ciphertext: &str, aad: &[u8]) -> CryptoResult<String> {
+    if !is_bound(ciphertext) {
+        return Err(CryptoError::DecryptionFailed("Ciphertext is not bound to a record".to_string()));
+    }
+    let bytes = open(key, ciphertext, aad)?;
+    String::from_utf8(bytes).map_err(|e| CryptoError::DecryptionFailed(e.to_string()))
+}
+
+/// Encrypt a string
+pub fn encrypt_string(key: &[u8], plaintext: &str) -> CryptoResult<EncryptedBlob> {
+    encrypt_bytes(key, plaintext.as_bytes())
+}
+
+/// Decrypt a string, in any format
+pub fn decrypt_string(key: &[u8], ciphertext: &EncryptedBlob) -> CryptoResult<String> {
+    let bytes = decrypt_bytes(key, ciphertext)?;
+    String::from_utf8(bytes).map_err(|e| CryptoError::DecryptionFailed(e.to_string()))
+}
+
+/// Encrypt bytes
+pub fn encrypt_bytes(key: &[u8], plaintext: &[u8]) -> CryptoResult<EncryptedBlob> {
+    seal(key, plaintext, &[])
+}
+
+/// Decrypt bytes, in any format
+pub fn decrypt_bytes(key: &[u8], ciphertext: &EncryptedBlob) -> CryptoResult<Vec<u8>> {
+    open(key, ciphertext, &[])
+}
+
+fn seal(key: &[u8], plaintext: &[u8], aad: &[u8]) -> CryptoResult<EncryptedBlob> {
+    check_key(key)?;
+    let mut envelope = vec![Algorithm::CURRENT.id()];
+    envelope.extend(Algorithm::CURRENT.seal(key, plaintext, aad)?);
+    Ok(format!("{}{}", ENVELOPE_PREFIX, hex::encode(envelope)))
+}
+
+fn open(key: &[u8], ciphertext: &str, aad: &[u8]) -> CryptoResult<Vec<u8>> {
+    check_key(key)?;
+    let format = Format::of(ciphertext);
+    let encoded = match format {
+        Format::Legacy => ciphertext,
+        Format::Bound => &ciphertext[BOUND_PREFIX.len()..],
+        // Past the prefix and the algorithm byte
+        Format::Envelope(_) => &ciphertext[ENVELOPE_PREFIX.len() + 2..],
+        Format::Unknown => {
+            return Err(CryptoError::DecryptionFailed("Unknown ciphertext format".to_string()));
+        }
+    };
+    let data = hex::decode(encoded).map_err(|e| CryptoError::DecryptionFailed(e.to_string()))?;
+    let algorithm = format.algorithm().unwrap_or(Algorithm::ChaCha20Poly1305);
+    algorithm.open(key, &data, aad)
+}
+
+fn check_key(key: &[u8]) -> CryptoResult<()> {
+    if key.len() != 32 {
+        return Err(CryptoError::InvalidKeyLength(key.len()));
+    }
+    Ok(())
+}
+
+/// Nonce followed by the ciphertext
+fn seal_with<C: Aead + KeyInit>(key: &[u8], nonce_size: usize, plaintext: &[u8], aad: &[u8]) -> CryptoResult<Vec<u8>> {
+    let cipher = C::new_from_slice(key).map_err(|e| CryptoError::EncryptionFailed(e.to_string()))?;
+
+    // Generate random nonce
+    let mut result = vec![0u8; nonce_size];
+    rand::thread_rng().fill_bytes(&mut result);
+
+    let ciphertext = cipher
+        .encrypt(Nonce::<C>::from_slice(&result), Payload { msg: plaintext, aad })
+        .map_err(|e| CryptoError::EncryptionFailed(e.to_string()))?;
+    result.extend(ciphertext);
+    Ok(result)
+}
+
+fn open_with<C: Aead + KeyInit>(key: &[u8], nonce_size: usize, data: &[u8], aad: &[u8]) -> CryptoResult<Vec<u8>> {
+    if data.len() < nonce_size {
+        return Err(CryptoError::DecryptionFailed(
+            "Ciphertext too short".to_string(),
+        ));
+    }
+
+    // Extract nonce and ciphertext
+    let (nonce_bytes, ciphertext_bytes) = data.split_at(nonce_size);
+
+    let cipher = C::new_from_slice(key).map_err(|e| CryptoError::DecryptionFailed(e.to_string()))?;
+
+    cipher
+        .decrypt(Nonce::<C>::from_slice(nonce_bytes), Payload { msg: ciphertext_bytes, aad })
+        .map_err(|e| CryptoError::DecryptionFailed(e.to_string()))
+}
+
+/// A blob in one of the older formats, for tests of reading and migrating them
+#[cfg(test)]
+pub(crate) fn seal_as(format: Format, key: &[u8], plaintext: &str, aad: &[u8]) -> EncryptedBlob {
+    let sealed = |algorithm: Algorithm| algorithm.seal(key, plaintext.as_bytes(), aad).unwrap();
+    match format {
+        Format::Legacy => hex::encode(sealed(Algorithm::ChaCha20Poly1305)),
+        Format::Bound => format!("{}{}", BOUND_PREFIX, hex::encode(sealed(Algorithm::ChaCha20Poly1305))),
+        Format::Envelope(algorithm) => {
+            let mut envelope = vec![algorithm.id()];
+            envelope.extend(sealed(algorithm));
+            format!("{}{}", ENVELOPE_PREFIX, hex::encode(envelope))
+        }
+        Format::Unknown => "v9:00".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key() -> [u8; 32] {
+        [0x42u8; 32]
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_string() {
+        let key = test_key();
+        let plaintext = "Hello, World!";
+
+        let encrypted = encrypt_string(&key, plaintext).unwrap();
+        let decrypted = decrypt_string(&key, &encrypted).unwrap();
+
+        assert_eq!(plaintext, decrypted);
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_bytes() {
+        let key = test_key();
+        let plaintext = b"Binary data \x00\x01\x02";
+
+        let encrypted = encrypt_bytes(&key, plaintext).unwrap();
+        let decrypted = decrypt_bytes(&key, &encrypted).unwrap();
+
+        assert_eq!(plaintext.as_slice(), decrypted.as_slice());
+    }
+
+    #[test]
+    fn test_different_nonces() {
+        let key = test_key();
+        let plaintext = "Same message";
+
+        let encrypted1 = encrypt_string(&key, plaintext).unwrap();
+        let encrypted2 = encrypt_string(&key, plaintext).unwrap();
+
+        // Same plaintext should produce different ciphertexts due to random nonces
+        assert_ne!(encrypted1, encrypted2);
+
+        // But both should decrypt to the same value
+        assert_eq!(decrypt_string(&key, &encrypted1).unwrap(), plaintext);
+        assert_eq!(decrypt_string(&key, &encrypted2).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_bound_to_associated_data() {
+        let key = test_key();
+        let encrypted = encrypt_string_bound(&key, "Secret", b"cred-1/secret").unwrap();
+        assert!(is_bound(&encrypted));
+        assert_eq!(decrypt_string_bound(&key, &encrypted, b"cred-1/secret").unwrap(), "Secret");
+        assert!(decrypt_string_bound(&key, &encrypted, b"cred-2/secret").is_err());
+        assert!(decrypt_string_bound(&key, &encrypted, b"cred-1/notes").is_err());
+
+        // An unbound blob is refused, and a bound one needs its data
+        let legacy = seal_as(Format::Legacy, &key, "Secret", b"");
+        assert!(!is_bound(&legacy));
+        assert!(decrypt_string_bound(&key, &legacy, b"").is_err());
+        assert!(decrypt_string(&key, &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_envelope_formats() {
+        let key = test_key();
+        let encrypted = encrypt_string(&key, "Secret").unwrap();
+        assert_eq!(Format::of(&encrypted), Format::Envelope(Algorithm::CURRENT));
+        assert!(Format::of(&encrypted).is_current());
+        assert!(encrypted.starts_with(&current_prefix()));
+
+        // Every older format still reads
+        let aad = b"cred-1/secret";
+        for format in [Format::Bound, Format::Envelope(Algorithm::ChaCha20Poly1305)] {
+            let blob = seal_as(format, &key, "Secret", aad);
+            assert_eq!(Format::of(&blob), format);
+            assert!(!format.is_current());
+            assert_eq!(decrypt_string_bound(&key, &blob, aad).unwrap(), "Secret");
+        }
+        let legacy = seal_as(Format::Legacy, &key, "Secret", b"");
+        assert_eq!(Format::of(&legacy), Format::Legacy);
+        assert_eq!(decrypt_string(&key, &legacy).unwrap(), "Secret");
+
+        let unknown = seal_as(Format::Unknown, &key, "Secret", b"");
+        assert_eq!(Format::of(&unknown), Format::Unknown);
+        assert_eq!(Format::of("v2:7f00"), Format::Unknown);
+        assert!(decrypt_string(&key, &unknown).is_err());
+    }
+
+    #[test]
+    fn test_wrong_key_fails() {
+        let key1 = [0x42u8; 32];
+        let key2 = [0x43u8; 32];
+        let plaintext = "Secret";
+
+        let encrypted = encrypt_string(&key1, plaintext).unwrap();
+        let result = decrypt_string(&key2, &encrypted);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_invalid_key_length() {
+        let short_key = [0u8; 16];
+        let result = encrypt_string(&short_key, "test");
+        assert!(matches!(result, Err(CryptoError::InvalidKeyLength(16))));
+    }
+
+    #[test]
+    fn test_tampered_ciphertext_fails() {
+        let key = test_key();
+        let plaintext = "Secret message";
+
+        let mut encrypted = encrypt_string(&key, plaintext).unwrap();
+
+        // Tamper with the ciphertext (flip a bit in the middle)
+        let mut bytes: Vec<u8> = hex::decode(&encrypted[ENVELOPE_PREFIX.len()..]).unwrap();
+        if bytes.len() > 1 + XNONCE_SIZE + 5 {
+            bytes[1 + XNONCE_SIZE + 5] ^= 0x01;
+        }
+        encrypted = format!("{}{}", ENVELOPE_PREFIX, hex::encode(bytes));
+
+        let result = decrypt_string(&key, &encrypted);
+        assert!(result.is_err());
+    }
+}
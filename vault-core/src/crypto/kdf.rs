@@ -0,0 +1,342 @@
+//! Key Derivation Function
+//!
+//! Argon2id password hashing for master key derivation. Random codes
+//! with enough entropy of their own, like emergency codes, use HKDF
+//! instead; the hash string records which of the two made it.
+
+use argon2::{
+    password_hash::{rand_core::OsRng, Ident, Output, PasswordHash, PasswordHasher, PasswordVerifier, Salt, SaltString},
+    Argon2, Params,
+};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use super::{CryptoError, CryptoResult, LockedBuffer};
+
+/// Master key (256 bits)
+///
+/// Memory-locked to prevent swapping to disk.
+#[derive(Clone)]
+pub struct MasterKey {
+    key: LockedBuffer<32>,
+}
+
+impl MasterKey {
+    /// Create from raw bytes
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self {
+            key: LockedBuffer::new(bytes),
+        }
+    }
+
+    /// Get key bytes
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.key
+    }
+}
+
+impl AsRef<[u8]> for MasterKey {
+    fn as_ref(&self) -> &[u8] {
+        self.key.as_ref()
+    }
+}
+
+// Debug impl that doesn't leak key material
+impl std::fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MasterKey").finish_non_exhaustive()
+    }
+}
+
+/// KDF parameters for Argon2id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory cost in KiB (default: 19456 = 19 MiB)
+    pub memory_cost: u32,
+    /// Time cost (iterations) (default: 2)
+    pub time_cost: u32,
+    /// Parallelism (default: 1)
+    pub parallelism: u32,
+    /// Output length in bytes (default: 32)
+    pub output_len: usize,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_cost: 19456, // 19 MiB - OWASP recommended minimum
+            time_cost: 2,
+            parallelism: 1,
+            output_len: 32,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Create params for testing (fast but insecure)
+    pub fn testing() -> Self {
+        Self {
+            memory_cost: 1024, // 1 MiB
+            time_cost: 1,
+            parallelism: 1,
+            output_len: 32,
+        }
+    }
+}
+
+/// Derive master key from password using Argon2id
+/// Returns (MasterKey, password_hash_string)
+pub fn derive_master_key(password: &[u8], params: &KdfParams) -> CryptoResult<(MasterKey, String)> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    let argon2_params = Params::new(
+        params.memory_cost,
+        params.time_cost,
+        params.parallelism,
+        Some(params.output_len),
+    )
+    .map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+
+    let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, argon2_params);
+
+    let password_hash = argon2
+        .hash_password(password, &salt)
+        .map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+
+    let master_key = key_from_hash(&password_hash)?;
+    Ok((master_key, password_hash.to_string()))
+}
+
+/// Verify password against stored hash and derive key
+pub fn verify_master_key(password: &[u8], password_hash: &str) -> CryptoResult<MasterKey> {
+    let parsed_hash = PasswordHash::new(password_hash)
+        .map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+    if parsed_hash.algorithm == CODE_KDF {
+        return verify_code_key(password, &parsed_hash);
+    }
+
+    // Verify the password
+    Argon2::default()
+        .verify_password(password, &parsed_hash)
+        .map_err(|_| CryptoError::InvalidPassword)?;
+
+    key_from_hash(&parsed_hash)
+}
+
+/// Algorithm identifier of hashes made by `derive_code_key`
+const CODE_KDF: Ident<'static> = Ident::new_unwrap("hkdf-sha256");
+
+/// Derive a key from a random code with HKDF-SHA256. Only for codes
+/// with at least 128 bits of entropy, which gain nothing from Argon2's
+/// cost; passwords go through `derive_master_key`.
+/// Returns (MasterKey, password_hash_string), the hash verifiable with
+/// `verify_master_key`
+pub fn derive_code_key(code: &[u8]) -> CryptoResult<(MasterKey, String)> {
+    let salt = SaltString::generate(&mut OsRng);
+    let (master_key, check) = code_key(code, salt.as_salt())?;
+    let password_hash = PasswordHash {
+        algorithm: CODE_KDF,
+        version: None,
+        params: Default::default(),
+        salt: Some(salt.as_salt()),
+        hash: Some(check),
+    };
+    Ok((master_key, password_hash.to_string()))
+}
+
+/// Check a code against a `derive_code_key` hash and derive its key
+fn verify_code_key(code: &[u8], password_hash: &PasswordHash) -> CryptoResult<MasterKey> {
+    let salt = password_hash
+        .salt
+        .ok_or_else(|| CryptoError::KeyDerivationFailed("No salt".to_string()))?;
+    let (master_key, check) = code_key(code, salt)?;
+    // Output's equality is constant-time
+    if password_hash.hash != Some(check) {
+        return Err(CryptoError::InvalidPassword);
+    }
+    Ok(master_key)
+}
+
+/// The key and the check value stored in place of a password hash, both
+/// expanded from the code under distinct labels
+fn code_key(code: &[u8], salt: Salt) -> CryptoResult<(MasterKey, Output)> {
+    let hkdf = Hkdf::<Sha256>::new(Some(salt.as_str().as_bytes()), code);
+    let mut key_bytes = [0u8; 32];
+    let mut check_bytes = [0u8; 32];
+    hkdf.expand(b"vault code key", &mut key_bytes)
+        .and_then(|()| hkdf.expand(b"vault code check", &mut check_bytes))
+        .map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+    let check = Output::new(&check_bytes).map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()));
+
+    let master_key = MasterKey::from_bytes(key_bytes);
+    key_bytes.zeroize();
+    Ok((master_key, check?))
+}
+
+/// The password hash without its output: algorithm, parameters and salt.
+/// Together with the password these reproduce the master key, while on
+/// their own they reveal nothing about it.
+pub fn kdf_params_string(password_hash: &str) -> CryptoResult<String> {
+    let mut parsed = PasswordHash::new(password_hash)
+        .map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+    parsed.hash = None;
+    Ok(parsed.to_string())
+}
+
+/// The algorithm and cost parameters of a password hash, e.g.
+/// `argon2id v19, m=65536, t=3, p=4`, for display
+pub fn describe_kdf(password_hash: &str) -> CryptoResult<String> {
+    let parsed = PasswordHash::new(password_hash)
+        .map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+    let mut parts = vec![match parsed.version {
+        Some(version) => format!("{} v{}", parsed.algorithm, version),
+        None => parsed.algorithm.to_string(),
+    }];
+    parts.extend(parsed.params.iter().map(|(name, value)| format!("{}={}", name, value)));
+    Ok(parts.join(", "))
+}
+
+/// Derive the master key again from parameters saved by `kdf_params_string`
+/// Returns (MasterKey, password_hash_string)
+pub fn rederive_master_key(password: &[u8], kdf_params: &str) -> CryptoResult<(MasterKey, String)> {
+    let parsed = PasswordHash::new(kdf_params)
+        .map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+    let salt = parsed
+        .salt
+        .ok_or_else(|| CryptoError::KeyDerivationFailed("No salt".to_string()))?;
+    let argon2_params = Params::try_from(&parsed)
+        .map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+
+    let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, argon2_params);
+    let password_hash = argon2
+        .hash_password(password, salt)
+        .map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+
+    let master_key = key_from_hash(&password_hash)?;
+    Ok((master_key, password_hash.to_string()))
+}
+
+/// Take the Argon2 output as the master key
+fn key_from_hash(password_hash: &PasswordHash) -> CryptoResult<MasterKey> {
+    let hash_output = password_hash
+        .hash
+        .ok_or_else(|| CryptoError::KeyDerivationFailed("No hash output".to_string()))?;
+
+    let hash_bytes = hash_output.as_bytes();
+    if hash_bytes.len() < 32 {
+        return Err(CryptoError::KeyDerivationFailed(
+            "Hash output too short".to_string(),
+        ));
+    }
+
+    let mut key_bytes = [0u8; 32];
+    key_bytes.copy_from_slice(&hash_bytes[..32]);
+
+    // MasterKey constructor handles mlock
+    let master_key = MasterKey::from_bytes(key_bytes);
+
+    // Zeroize the temporary buffer
+    key_bytes.zeroize();
+
+    Ok(master_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_master_key() {
+        let password = b"test_password_123";
+        let params = KdfParams::testing();
+
+        let (key, hash) = derive_master_key(password, &params).unwrap();
+
+        assert_eq!(key.as_bytes().len(), 32);
+        assert!(!hash.is_empty());
+        assert!(hash.starts_with("$argon2id$"));
+    }
+
+    #[test]
+    fn test_verify_master_key() {
+        let password = b"test_password_123";
+        let params = KdfParams::testing();
+
+        let (original_key, hash) = derive_master_key(password, &params).unwrap();
+        let verified_key = verify_master_key(password, &hash).unwrap();
+
+        assert_eq!(original_key.as_bytes(), verified_key.as_bytes());
+    }
+
+    #[test]
+    fn test_wrong_password_fails() {
+        let password = b"correct_password";
+        let wrong_password = b"wrong_password";
+        let params = KdfParams::testing();
+
+        let (_, hash) = derive_master_key(password, &params).unwrap();
+        let result = verify_master_key(wrong_password, &hash);
+
+        assert!(matches!(result, Err(CryptoError::InvalidPassword)));
+    }
+
+    #[test]
+    fn test_code_key() {
+        let code = b"7M3QKXR2D9VHT0B4WC8NJ5FYE";
+
+        let (key, hash) = derive_code_key(code).unwrap();
+        assert!(hash.starts_with("$hkdf-sha256$"));
+        assert_eq!(describe_kdf(&hash).unwrap(), "hkdf-sha256");
+        assert_eq!(verify_master_key(code, &hash).unwrap().as_bytes(), key.as_bytes());
+        assert!(matches!(verify_master_key(b"7M3QKXR2D9VHT0B4WC8NJ5FYF", &hash), Err(CryptoError::InvalidPassword)));
+
+        let (other, _) = derive_code_key(code).unwrap();
+        assert_ne!(key.as_bytes(), other.as_bytes());
+    }
+
+    #[test]
+    fn test_different_salts_different_keys() {
+        let password = b"same_password";
+        let params = KdfParams::testing();
+
+        let (key1, _) = derive_master_key(password, &params).unwrap();
+        let (key2, _) = derive_master_key(password, &params).unwrap();
+
+        // Different salts should produce different keys
+        assert_ne!(key1.as_bytes(), key2.as_bytes());
+    }
+
+    #[test]
+    fn test_rederive_from_params() {
+        let password = b"test_password";
+        let params = KdfParams::testing();
+
+        let (key, hash) = derive_master_key(password, &params).unwrap();
+        let kdf_params = kdf_params_string(&hash).unwrap();
+        assert!(hash.starts_with(&kdf_params));
+        assert!(kdf_params.len() < hash.len());
+
+        let (rederived, rehash) = rederive_master_key(password, &kdf_params).unwrap();
+        assert_eq!(key.as_bytes(), rederived.as_bytes());
+        assert_eq!(hash, rehash);
+
+        let (wrong, _) = rederive_master_key(b"wrong_password", &kdf_params).unwrap();
+        assert_ne!(key.as_bytes(), wrong.as_bytes());
+    }
+
+    #[test]
+    fn test_deterministic_verification() {
+        let password = b"test_password";
+        let params = KdfParams::testing();
+
+        let (key1, hash) = derive_master_key(password, &params).unwrap();
+        let key2 = verify_master_key(password, &hash).unwrap();
+        let key3 = verify_master_key(password, &hash).unwrap();
+
+        // Verification should always produce the same key
+        assert_eq!(key1.as_bytes(), key2.as_bytes());
+        assert_eq!(key2.as_bytes(), key3.as_bytes());
+    }
+}
@@ -102,6 +102,11 @@ impl KeyHierarchy {
         &self.dek
     }
 
+    /// See [`DataEncryptionKey::require_bound`]
+    pub fn require_bound(&mut self) {
+        self.dek.require_bound();
+    }
+
     /// Get reference to master key
     pub fn master_key(&self) -> &MasterKey {
         &self.master_key
@@ -116,6 +121,11 @@ impl KeyHierarchy {
     pub fn derive_audit_key(&self) -> CryptoResult<DerivedKey> {
         derive_key(self.dek.as_bytes(), "audit", "log")
     }
+
+    /// Derive a key for the vault fingerprint's HMAC
+    pub fn derive_fingerprint_key(&self) -> CryptoResult<DerivedKey> {
+        derive_key(self.dek.as_bytes(), "fingerprint", "vault")
+    }
 }
 
 /// Derive a credential key directly (convenience function)
@@ -2,14 +2,20 @@
 //!
 //! Provides secure encryption, key derivation, and password generation.
 
+pub mod cert;
 pub mod dek;
 pub mod encryption;
 pub mod kdf;
 pub mod key_hierarchy;
 pub mod password_gen;
+pub mod ssh;
+pub mod strength;
 pub mod totp;
 
+use std::alloc::{self, Layout};
 use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use thiserror::Error;
 use zeroize::Zeroize;
 
@@ -33,15 +39,45 @@ pub enum CryptoError {
 
     #[error("TOTP generation failed: {0}")]
     TotpFailed(String),
+
+    #[error("Invalid SSH key: {0}")]
+    InvalidKey(String),
+
+    #[error("Invalid certificate: {0}")]
+    InvalidCertificate(String),
 }
 
 pub type CryptoResult<T> = Result<T, CryptoError>;
 
+static BUFFERS_LOCKED: AtomicUsize = AtomicUsize::new(0);
+static BUFFERS_NOT_LOCKED: AtomicUsize = AtomicUsize::new(0);
+static BUFFERS_NOT_DUMPED: AtomicUsize = AtomicUsize::new(0);
+
+/// How key buffers created so far fared, for `:securityinfo`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryLockStats {
+    pub locked: usize,
+    pub not_locked: usize,
+    /// Excluded from core dumps with `MADV_DONTDUMP`
+    pub not_dumped: usize,
+}
+
+pub fn memory_lock_stats() -> MemoryLockStats {
+    MemoryLockStats {
+        locked: BUFFERS_LOCKED.load(Ordering::Relaxed),
+        not_locked: BUFFERS_NOT_LOCKED.load(Ordering::Relaxed),
+        not_dumped: BUFFERS_NOT_DUMPED.load(Ordering::Relaxed),
+    }
+}
+
 /// A buffer that is locked in memory to prevent swapping to disk.
 ///
 /// Uses `mlock()` on Unix and `VirtualLock()` on Windows to advise the OS
-/// not to swap these pages. The buffer is automatically zeroed before
-/// being unlocked on drop.
+/// not to swap these pages, and on Linux `madvise(MADV_DONTDUMP)` to keep
+/// them out of core dumps. The bytes live in whole pages of their own on
+/// the heap, so they never move once locked and locking or unlocking one
+/// buffer does not touch another's pages. The buffer is automatically
+/// zeroed before being unlocked on drop.
 ///
 /// # Security Notes
 /// - `mlock` may fail due to resource limits (`RLIMIT_MEMLOCK`) - we handle
@@ -49,19 +85,32 @@ pub type CryptoResult<T> = Result<T, CryptoError>;
 /// - The buffer is always zeroized on drop, regardless of lock status
 /// - Clone creates a new locked buffer (both copies are locked)
 pub struct LockedBuffer<const N: usize> {
-    data: [u8; N],
+    data: NonNull<[u8; N]>,
     #[allow(dead_code)]
     locked: bool,
 }
 
+// SAFETY: the buffer owns its allocation outright, like a Box
+unsafe impl<const N: usize> Send for LockedBuffer<N> {}
+// SAFETY: shared access only reads through `&self`
+unsafe impl<const N: usize> Sync for LockedBuffer<N> {}
+
 impl<const N: usize> LockedBuffer<N> {
-    /// Create a new locked buffer with the given data
-    pub fn new(data: [u8; N]) -> Self {
+    /// Create a new locked buffer with the given data. The pages are
+    /// locked before the data is copied in, and the copy passed by value
+    /// is zeroed.
+    pub fn new(mut data: [u8; N]) -> Self {
+        let layout = Self::layout();
+        // SAFETY: the layout is at least one page long
+        let ptr = unsafe { alloc::alloc_zeroed(layout) } as *mut [u8; N];
+        let Some(ptr) = NonNull::new(ptr) else { alloc::handle_alloc_error(layout) };
         let mut buf = Self {
-            data,
+            data: ptr,
             locked: false,
         };
         buf.try_lock();
+        *buf = data;
+        data.zeroize();
         buf
     }
 
@@ -71,9 +120,21 @@ impl<const N: usize> LockedBuffer<N> {
         Self::new([0u8; N])
     }
 
+    /// Whole pages, aligned to a page, holding `N` bytes
+    fn layout() -> Layout {
+        let page = page_size();
+        Layout::from_size_align(N.max(1).div_ceil(page) * page, page).expect("page size is a power of two")
+    }
+
     /// Attempt to lock the memory region
     fn try_lock(&mut self) {
-        self.locked = Self::mlock_impl(self.data.as_ptr(), N);
+        let (ptr, len) = (self.data.as_ptr() as *const u8, Self::layout().size());
+        self.locked = Self::mlock_impl(ptr, len);
+        let counter = if self.locked { &BUFFERS_LOCKED } else { &BUFFERS_NOT_LOCKED };
+        counter.fetch_add(1, Ordering::Relaxed);
+        if Self::dontdump_impl(ptr, len) {
+            BUFFERS_NOT_DUMPED.fetch_add(1, Ordering::Relaxed);
+        }
         #[cfg(debug_assertions)]
         if !self.locked {
             eprintln!("Warning: mlock() failed for {} byte buffer - sensitive data may be swapped to disk", N);
@@ -83,7 +144,7 @@ impl<const N: usize> LockedBuffer<N> {
     /// Unlock the memory region
     fn unlock(&mut self) {
         if self.locked {
-            Self::munlock_impl(self.data.as_ptr(), N);
+            Self::munlock_impl(self.data.as_ptr() as *const u8, Self::layout().size());
             self.locked = false;
         }
     }
@@ -107,6 +168,19 @@ impl<const N: usize> LockedBuffer<N> {
         false
     }
 
+    /// Exclude the pages holding the buffer from core dumps
+    #[cfg(target_os = "linux")]
+    fn dontdump_impl(ptr: *const u8, len: usize) -> bool {
+        // SAFETY: ptr is page aligned and the range covers whole pages
+        // of valid memory
+        unsafe { libc::madvise(ptr as *mut libc::c_void, len, libc::MADV_DONTDUMP) == 0 }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn dontdump_impl(_ptr: *const u8, _len: usize) -> bool {
+        false
+    }
+
     /// Platform-specific munlock implementation
     #[cfg(unix)]
     fn munlock_impl(ptr: *const u8, len: usize) {
@@ -136,10 +210,22 @@ impl<const N: usize> LockedBuffer<N> {
     }
 }
 
+#[cfg(unix)]
+fn page_size() -> usize {
+    // SAFETY: sysconf has no preconditions
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    usize::try_from(page).ok().filter(|p| p.is_power_of_two()).unwrap_or(4096)
+}
+
+#[cfg(not(unix))]
+fn page_size() -> usize {
+    4096
+}
+
 impl<const N: usize> Clone for LockedBuffer<N> {
     fn clone(&self) -> Self {
         // Create a new locked buffer with copied data
-        Self::new(self.data)
+        Self::new(**self)
     }
 }
 
@@ -147,34 +233,38 @@ impl<const N: usize> Deref for LockedBuffer<N> {
     type Target = [u8; N];
 
     fn deref(&self) -> &Self::Target {
-        &self.data
+        // SAFETY: the allocation is owned by self, initialized and aligned
+        unsafe { self.data.as_ref() }
     }
 }
 
 impl<const N: usize> DerefMut for LockedBuffer<N> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.data
+        // SAFETY: as in deref, and `&mut self` makes the access unique
+        unsafe { self.data.as_mut() }
     }
 }
 
 impl<const N: usize> AsRef<[u8]> for LockedBuffer<N> {
     fn as_ref(&self) -> &[u8] {
-        &self.data
+        &**self
     }
 }
 
 impl<const N: usize> Zeroize for LockedBuffer<N> {
     fn zeroize(&mut self) {
-        self.data.zeroize();
+        (**self).zeroize();
     }
 }
 
 impl<const N: usize> Drop for LockedBuffer<N> {
     fn drop(&mut self) {
         // Zeroize while memory is still locked
-        self.data.zeroize();
+        self.zeroize();
         // Then unlock
         self.unlock();
+        // SAFETY: allocated in `new` with this layout and not freed since
+        unsafe { alloc::dealloc(self.data.as_ptr() as *mut u8, Self::layout()) };
     }
 }
 
@@ -189,11 +279,19 @@ impl<const N: usize> std::fmt::Debug for LockedBuffer<N> {
 }
 
 // Re-exports
+pub use cert::Certificate;
 pub use dek::DataEncryptionKey;
-pub use encryption::{decrypt_bytes, decrypt_string, encrypt_bytes, encrypt_string};
-pub use kdf::{derive_master_key, verify_master_key, KdfParams, MasterKey};
+pub use encryption::{
+    current_prefix, decrypt_bytes, decrypt_string, decrypt_string_bound, encrypt_bytes, encrypt_string, encrypt_string_bound,
+    is_bound, Algorithm, Format,
+};
+pub use kdf::{derive_code_key, derive_master_key, describe_kdf, kdf_params_string, rederive_master_key, verify_master_key, KdfParams, MasterKey};
 pub use key_hierarchy::{DerivedKey, KeyHierarchy};
-pub use password_gen::{generate_password, password_strength, strength_label, PasswordPolicy};
+pub use password_gen::{
+    generate_diceware, generate_password, memorable_word, password_strength, strength_label, PassphrasePolicy,
+    PasswordPolicy,
+};
+pub use ssh::SshPublicKey;
 pub use totp::{generate_totp, time_remaining, TotpSecret};
 
 #[cfg(test)]
@@ -221,6 +319,18 @@ mod tests {
         assert_eq!(*buf, [0u8; 32]);
     }
 
+    #[test]
+    fn test_locked_buffer_owns_its_pages() {
+        let buf = LockedBuffer::new([0x42u8; 32]);
+        let addr = buf.as_ptr() as usize;
+        assert_eq!(addr % page_size(), 0);
+
+        // Moving the buffer leaves the locked bytes where they were
+        let moved = Some(buf);
+        assert_eq!(moved.as_ref().unwrap().as_ptr() as usize, addr);
+        assert_eq!(**moved.as_ref().unwrap(), [0x42u8; 32]);
+    }
+
     #[test]
     fn test_locked_buffer_deref_mut() {
         let mut buf = LockedBuffer::new([0u8; 32]);
@@ -4,9 +4,11 @@
 
 use rand::{seq::SliceRandom, Rng};
 use rand::prelude::IteratorRandom; // provides .choose() for iterators
+use serde::{Deserialize, Serialize};
 
 /// Password generation policy
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PasswordPolicy {
     pub length: usize,
     /// Include uppercase letters
@@ -161,24 +163,92 @@ pub fn generate_password(policy: &PasswordPolicy) -> String {
     password.into_iter().collect()
 }
 
-/// Generate a passphrase from wordlist
-pub fn generate_passphrase(word_count: usize, separator: &str) -> String {
-    // Simple wordlist - in production, use a proper wordlist like EFF's
-    const WORDS: &[&str] = &[
-        "apple", "banana", "cherry", "dragon", "eagle", "forest", "garden", "harbor",
-        "island", "jungle", "knight", "lemon", "mountain", "noble", "ocean", "planet",
-        "quantum", "river", "sunset", "thunder", "umbrella", "valley", "winter", "yellow",
-        "zebra", "anchor", "bridge", "castle", "delta", "ember", "falcon", "glacier",
-        "horizon", "ivory", "jasper", "karma", "lotus", "marble", "nebula", "orbit",
-        "phoenix", "quartz", "radar", "safari", "temple", "ultra", "vertex", "whisper",
-    ];
+/// EFF long wordlist, one word per line
+const EFF_WORDLIST: &str = include_str!("wordlists/eff_large_wordlist.txt");
+
+fn eff_words() -> impl Iterator<Item = &'static str> {
+    EFF_WORDLIST.lines().filter(|w| !w.is_empty())
+}
 
+/// Diceware passphrase policy
+#[derive(Debug, Clone)]
+pub struct PassphrasePolicy {
+    pub words: usize,
+    pub separator: String,
+    /// Capitalize the first letter of each word
+    pub capitalize: bool,
+    /// Append a random digit to one randomly chosen word
+    pub include_digit: bool,
+}
+
+impl Default for PassphrasePolicy {
+    fn default() -> Self {
+        Self {
+            words: 6,
+            separator: "-".to_string(),
+            capitalize: false,
+            include_digit: false,
+        }
+    }
+}
+
+impl PassphrasePolicy {
+    pub fn with_words(words: usize) -> Self {
+        Self { words, ..Self::default() }
+    }
+
+    /// Entropy in bits, from the wordlist size and digit injection
+    pub fn entropy_bits(&self) -> f64 {
+        let per_word = (eff_words().count() as f64).log2();
+        let mut bits = per_word * self.words as f64;
+        if self.include_digit && self.words > 0 {
+            bits += 10f64.log2() + (self.words as f64).log2();
+        }
+        bits
+    }
+}
+
+/// Generate a diceware passphrase from the EFF long wordlist
+pub fn generate_diceware(policy: &PassphrasePolicy) -> String {
     let mut rng = rand::thread_rng();
-    let words: Vec<&str> = (0..word_count)
-        .map(|_| WORDS[rng.gen_range(0..WORDS.len())])
+    let wordlist: Vec<&str> = eff_words().collect();
+
+    let mut words: Vec<String> = (0..policy.words)
+        .map(|_| wordlist[rng.gen_range(0..wordlist.len())])
+        .map(|w| if policy.capitalize { capitalize(w) } else { w.to_string() })
         .collect();
 
-    words.join(separator)
+    if policy.include_digit && !words.is_empty() {
+        let idx = rng.gen_range(0..words.len());
+        let digit = char::from(b'0' + rng.gen_range(0..10u8));
+        words[idx].push(digit);
+    }
+
+    words.join(&policy.separator)
+}
+
+/// A random word from the EFF long wordlist no longer than `max_len`,
+/// for names that should be easy to remember rather than hard to guess
+pub fn memorable_word(max_len: usize) -> Option<&'static str> {
+    let short: Vec<&str> = eff_words().filter(|w| w.len() <= max_len).collect();
+    short.choose(&mut rand::thread_rng()).copied()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Generate a passphrase from the EFF long wordlist
+pub fn generate_passphrase(word_count: usize, separator: &str) -> String {
+    generate_diceware(&PassphrasePolicy {
+        words: word_count,
+        separator: separator.to_string(),
+        ..PassphrasePolicy::default()
+    })
 }
 
 /// Calculate password strength (0-100)
@@ -265,12 +335,43 @@ mod tests {
 
     #[test]
     fn test_generate_passphrase() {
-        let passphrase = generate_passphrase(4, "-");
-        let words: Vec<&str> = passphrase.split('-').collect();
+        // The EFF list contains hyphenated words such as "yo-yo"
+        let passphrase = generate_passphrase(4, " ");
+        let words: Vec<&str> = passphrase.split(' ').collect();
 
         assert_eq!(words.len(), 4);
     }
 
+    #[test]
+    fn test_eff_wordlist() {
+        let words: Vec<&str> = eff_words().collect();
+        assert!(words.len() > 7000);
+        assert!(words.windows(2).all(|w| w[0] < w[1]));
+        assert!(words.iter().all(|w| w.chars().all(|c| c.is_ascii_lowercase() || c == '-')));
+    }
+
+    #[test]
+    fn test_generate_diceware_options() {
+        let policy = PassphrasePolicy {
+            words: 5,
+            separator: ".".to_string(),
+            capitalize: true,
+            include_digit: true,
+        };
+        let passphrase = generate_diceware(&policy);
+        let words: Vec<&str> = passphrase.split('.').collect();
+
+        assert_eq!(words.len(), 5);
+        assert!(words.iter().all(|w| w.starts_with(|c: char| c.is_ascii_uppercase())));
+        assert_eq!(passphrase.chars().filter(|c| c.is_ascii_digit()).count(), 1);
+    }
+
+    #[test]
+    fn test_passphrase_entropy() {
+        let bits = PassphrasePolicy::with_words(6).entropy_bits();
+        assert!(bits > 75.0 && bits < 80.0);
+    }
+
     #[test]
     fn test_password_strength() {
         assert!(password_strength("abc") < 30);
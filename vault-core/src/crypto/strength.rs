@@ -0,0 +1,139 @@
+//! Master password strength
+//!
+//! A rough entropy estimate and a small list of passwords too common to
+//! protect a vault, checked when a master password is chosen.
+
+use thiserror::Error;
+
+/// Shortest master password accepted, whatever its entropy
+pub const MIN_MASTER_LENGTH: usize = 8;
+
+/// Entropy a master password needs unless the config says otherwise
+pub const DEFAULT_MIN_ENTROPY: f64 = 50.0;
+
+/// Well-known passwords, one per line in lowercase
+const COMMON_PASSWORDS: &str = include_str!("wordlists/common_passwords.txt");
+
+/// Why a master password was refused
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum Weakness {
+    #[error("Password must be at least {MIN_MASTER_LENGTH} characters")]
+    TooShort,
+
+    #[error("Password is too common")]
+    Common,
+
+    #[error("Password is too guessable ({bits:.0} of {required:.0} bits)")]
+    LowEntropy { bits: f64, required: f64 },
+}
+
+/// Estimated entropy in bits: the length times the bits of the character
+/// classes used. Repeated and sequential characters, like `aaaa` or `1234`,
+/// count for a quarter.
+pub fn estimate_entropy(password: &str) -> f64 {
+    let chars: Vec<char> = password.chars().collect();
+    if chars.is_empty() {
+        return 0.0;
+    }
+
+    let mut pool = 0u32;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    if chars.iter().any(|c| c.is_ascii() && !c.is_ascii_alphanumeric()) {
+        pool += 33;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        pool += 100;
+    }
+
+    let effective: f64 = chars
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| {
+            let predictable = i > 0 && (c as i64 - chars[i - 1] as i64).abs() <= 1;
+            if predictable { 0.25 } else { 1.0 }
+        })
+        .sum();
+    effective * f64::from(pool).log2()
+}
+
+/// Whether the password is, or is a decorated form of, a well-known one:
+/// `P@ssw0rd!` and `dragon2024` count as `password` and `dragon`
+pub fn is_common_password(password: &str) -> bool {
+    let lower = password.trim().to_lowercase();
+    let base = lower.trim_end_matches(|c: char| !c.is_alphabetic());
+    let undecorated: String = base
+        .chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | '!' => 'i',
+            '3' => 'e',
+            '4' | '@' => 'a',
+            '5' | '$' => 's',
+            '7' => 't',
+            c => c,
+        })
+        .collect();
+
+    COMMON_PASSWORDS
+        .lines()
+        .any(|common| common == lower || (!undecorated.is_empty() && common == undecorated))
+}
+
+/// Check a new master password against the length, dictionary and entropy rules
+pub fn check_master_password(password: &str, min_entropy: f64) -> Result<(), Weakness> {
+    if password.chars().count() < MIN_MASTER_LENGTH {
+        return Err(Weakness::TooShort);
+    }
+    if is_common_password(password) {
+        return Err(Weakness::Common);
+    }
+    let bits = estimate_entropy(password);
+    if bits < min_entropy {
+        return Err(Weakness::LowEntropy { bits, required: min_entropy });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_entropy() {
+        assert_eq!(estimate_entropy(""), 0.0);
+        assert!((estimate_entropy("akqmzxrt") - 8.0 * 26f64.log2()).abs() < 1e-9);
+        assert!(estimate_entropy("abcdefghijkl") < estimate_entropy("akqmzxrt"));
+        assert!(estimate_entropy("aaaaaaaaaaaa") < estimate_entropy("akqmzxrt"));
+        assert!(estimate_entropy("correct horse battery staple") > 100.0);
+        assert!(estimate_entropy("Tr0ub4dor&3") > estimate_entropy("troubador"));
+    }
+
+    #[test]
+    fn test_common_passwords() {
+        assert!(is_common_password("password"));
+        assert!(is_common_password("P@ssw0rd!"));
+        assert!(is_common_password("Dragon2024"));
+        assert!(is_common_password("12345678"));
+        assert!(!is_common_password("correct horse battery staple"));
+    }
+
+    #[test]
+    fn test_check_master_password() {
+        assert_eq!(check_master_password("short", DEFAULT_MIN_ENTROPY), Err(Weakness::TooShort));
+        assert_eq!(check_master_password("letmein123", DEFAULT_MIN_ENTROPY), Err(Weakness::Common));
+        assert!(matches!(
+            check_master_password("abcdefghij", DEFAULT_MIN_ENTROPY),
+            Err(Weakness::LowEntropy { .. })
+        ));
+        assert!(check_master_password("correct horse battery staple", DEFAULT_MIN_ENTROPY).is_ok());
+        assert!(check_master_password("abcdefghij", 0.0).is_ok());
+    }
+}
@@ -75,6 +75,23 @@ impl TotpSecret {
         })
     }
 
+    /// Read the secret of a TOTP credential: this struct as JSON, an
+    /// otpauth:// URI, or a bare base32 key, ignoring case, spaces and
+    /// dashes. Fails unless codes can be generated from it.
+    pub fn parse(input: &str, account: &str) -> CryptoResult<Self> {
+        let input = input.trim();
+        let secret = if let Ok(secret) = serde_json::from_str::<Self>(input) {
+            secret
+        } else if input.get(..10).is_some_and(|scheme| scheme.eq_ignore_ascii_case("otpauth://")) {
+            Self::from_uri(input)?
+        } else {
+            let key = input.chars().filter(|c| !matches!(c, ' ' | '-' | '=')).map(|c| c.to_ascii_uppercase()).collect();
+            Self::new(key, account.to_string(), "Vault".to_string())
+        };
+        secret.build_totp()?;
+        Ok(secret)
+    }
+
     /// Generate otpauth:// URI for QR code
     pub fn to_uri(&self) -> CryptoResult<String> {
         let totp = self.build_totp()?;
@@ -189,6 +206,20 @@ mod tests {
         assert_eq!(secret.period, 30);
     }
 
+    #[test]
+    fn test_parse() {
+        let key = "HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ";
+        assert_eq!(TotpSecret::parse("hxdm vjec jjws rb3h wizr 4ifu gftm xboz", "me").unwrap().secret, key);
+        let uri = format!("OTPAUTH://totp/ACME:john@example.com?secret={}&issuer=ACME", key);
+        assert_eq!(TotpSecret::parse(&uri, "me").unwrap().account, "john@example.com");
+        let json = serde_json::to_string(&TotpSecret::new(key.to_string(), "me".to_string(), "X".to_string())).unwrap();
+        assert_eq!(TotpSecret::parse(&json, "other").unwrap().account, "me");
+
+        assert!(TotpSecret::parse("not base32!", "me").is_err());
+        assert!(TotpSecret::parse("JBSWY3DP", "me").is_err()); // too short to use
+        assert!(TotpSecret::parse("otpauth://hotp/x?secret=JBSWY3DP", "me").is_err());
+    }
+
     #[test]
     fn test_generate_secret() {
         let secret1 = generate_secret();
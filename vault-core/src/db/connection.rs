@@ -3,10 +3,14 @@
 //! Handles SQLite database connections and configuration.
 
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use rusqlite::{Connection, OpenFlags};
 
-use super::{schema::init_schema, DbResult};
+use super::{
+    schema::{check_schema_version, init_schema_with_backup},
+    DbResult,
+};
 
 /// Database configuration
 #[derive(Debug, Clone)]
@@ -17,6 +21,11 @@ pub struct DatabaseConfig {
     pub wal_mode: bool,
     /// Enable foreign keys
     pub foreign_keys: bool,
+    /// Open without write access; the schema is not created or upgraded
+    pub read_only: bool,
+    /// How long a statement waits on another process holding the lock,
+    /// e.g. the CLI writing while the TUI is open
+    pub busy_timeout: Duration,
 }
 
 impl Default for DatabaseConfig {
@@ -25,6 +34,8 @@ impl Default for DatabaseConfig {
             path: default_db_path(),
             wal_mode: true,
             foreign_keys: true,
+            read_only: false,
+            busy_timeout: Duration::from_secs(5),
         }
     }
 }
@@ -35,10 +46,15 @@ impl DatabaseConfig {
         Self {
             path: PathBuf::from(":memory:"),
             wal_mode: false,
-            foreign_keys: true,
+            ..Default::default()
         }
     }
 
+    /// Path of the database file, `None` for an in-memory database
+    fn file_path(&self) -> Option<&Path> {
+        (self.path.to_str() != Some(":memory:")).then_some(self.path.as_path())
+    }
+
     /// Create config for a specific path
     pub fn with_path(path: impl Into<PathBuf>) -> Self {
         Self {
@@ -68,7 +84,12 @@ impl Database {
         ensure_parent_dir(&config)?;
         let conn = open_connection(&config)?;
         configure_connection(&conn, &config)?;
-        init_schema(&conn)?;
+        match config.read_only {
+            true => {
+                check_schema_version(&conn)?;
+            }
+            false => init_schema_with_backup(&conn, config.file_path())?,
+        }
         Ok(Self { conn, config })
     }
 
@@ -92,6 +113,11 @@ impl Database {
         &mut self.conn
     }
 
+    /// Whether the connection refuses writes
+    pub fn is_read_only(&self) -> bool {
+        self.config.read_only
+    }
+
     /// Get database path
     pub fn path(&self) -> &Path {
         &self.config.path
@@ -119,6 +145,26 @@ impl Database {
         Ok(std::fs::metadata(&self.config.path)?.len())
     }
 
+    /// Start a transaction that the following statements on this
+    /// connection join, for writes made across several calls. It takes the
+    /// write lock straight away, so a concurrent writer waits instead of
+    /// failing halfway. End it with [`Database::commit`] or
+    /// [`Database::rollback`].
+    pub fn begin(&self) -> DbResult<()> {
+        self.conn.execute_batch("BEGIN IMMEDIATE")?;
+        Ok(())
+    }
+
+    pub fn commit(&self) -> DbResult<()> {
+        self.conn.execute_batch("COMMIT")?;
+        Ok(())
+    }
+
+    pub fn rollback(&self) -> DbResult<()> {
+        self.conn.execute_batch("ROLLBACK")?;
+        Ok(())
+    }
+
     /// Execute a function within a transaction
     pub fn transaction<T, F>(&mut self, f: F) -> DbResult<T>
     where
@@ -158,9 +204,11 @@ fn open_connection(config: &DatabaseConfig) -> DbResult<Connection> {
     if config.path.to_str() == Some(":memory:") {
         return Ok(Connection::open_in_memory()?);
     }
-    let flags = OpenFlags::SQLITE_OPEN_READ_WRITE
-        | OpenFlags::SQLITE_OPEN_CREATE
-        | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    let flags = if config.read_only {
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX
+    } else {
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_NO_MUTEX
+    };
     Ok(Connection::open_with_flags(&config.path, flags)?)
 }
 
@@ -168,10 +216,12 @@ fn configure_connection(conn: &Connection, config: &DatabaseConfig) -> DbResult<
     if config.foreign_keys {
         conn.execute_batch("PRAGMA foreign_keys = ON;")?;
     }
-    if config.wal_mode && config.path.to_str() != Some(":memory:") {
+    if config.wal_mode && !config.read_only && config.path.to_str() != Some(":memory:") {
         conn.execute_batch("PRAGMA journal_mode = WAL;")?;
     }
-    conn.busy_timeout(std::time::Duration::from_secs(5))?;
+    conn.busy_timeout(config.busy_timeout)?;
+    // Keyless until the vault is unlocked
+    super::fields::install(conn, None, false)?;
     Ok(())
 }
 
@@ -193,6 +243,20 @@ mod tests {
         assert_eq!(count_test_credentials(&db), 1);
     }
 
+    #[test]
+    fn test_begin_and_rollback() {
+        let db = Database::open_in_memory().unwrap();
+        db.begin().unwrap();
+        insert_test_credential(db.conn()).unwrap();
+        db.rollback().unwrap();
+        assert_eq!(count_test_credentials(&db), 0);
+
+        db.begin().unwrap();
+        insert_test_credential(db.conn()).unwrap();
+        db.commit().unwrap();
+        assert_eq!(count_test_credentials(&db), 1);
+    }
+
     fn insert_test_credential(conn: &Connection) -> DbResult<i32> {
         conn.execute(
             "INSERT INTO credentials (id, name, credential_type, encrypted_secret, created_at, updated_at) 
@@ -208,6 +272,17 @@ mod tests {
             .unwrap()
     }
 
+    #[test]
+    fn test_read_only_refuses_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.db");
+        drop(Database::open(DatabaseConfig::with_path(&path)).unwrap());
+
+        let db = Database::open(DatabaseConfig { read_only: true, ..DatabaseConfig::with_path(&path) }).unwrap();
+        assert!(db.is_read_only());
+        assert!(insert_test_credential(db.conn()).is_err());
+    }
+
     #[test]
     fn test_foreign_keys_enabled() {
         let db = Database::open_in_memory().unwrap();
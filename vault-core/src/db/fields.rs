@@ -0,0 +1,170 @@
+//! Sealed Fields
+//!
+//! Usernames and URLs are stored in plaintext unless the vault is set to
+//! seal them, for when which services you use is itself worth hiding.
+//! Sealing happens inside SQL, through two functions every credential
+//! query goes through: `vault_seal(id, field, value)` on the way in and
+//! `vault_open(id, field, value)` on the way out. Every connection starts
+//! with them passing values through; [`install`] gives them the data key
+//! once the vault is unlocked. Filters such as `user:` then match the
+//! opened value, whatever is stored.
+//!
+//! A sealed value is [`SEALED_PREFIX`] followed by the field encrypted
+//! with the credential id and field name as associated data, so it cannot
+//! be moved to another credential or field unnoticed: one that does not
+//! open reads as NULL, and [`unopenable`] names it for the integrity check.
+
+use rusqlite::functions::{Context, FunctionFlags};
+use rusqlite::Connection;
+
+use crate::crypto::{decrypt_string_bound, encrypt_string_bound, DataEncryptionKey};
+
+use super::DbResult;
+
+/// Marks a sealed username or URL
+pub const SEALED_PREFIX: &str = "sealed:";
+
+pub fn is_sealed(value: &str) -> bool {
+    value.starts_with(SEALED_PREFIX)
+}
+
+fn aad(id: &str, field: &str) -> Vec<u8> {
+    format!("{}/{}", id, field).into_bytes()
+}
+
+/// Record id, field name and value of a call
+fn args(ctx: &Context) -> rusqlite::Result<(String, String, Option<String>)> {
+    let id: Option<String> = ctx.get(0)?;
+    Ok((id.unwrap_or_default(), ctx.get(1)?, ctx.get(2)?))
+}
+
+/// Define `vault_seal` and `vault_open` on a connection. Without a key
+/// both pass values through, and sealed values stay sealed; with one,
+/// `vault_open` opens sealed values and `vault_seal` seals plaintext when
+/// `seal` is set. Values already sealed are never sealed twice.
+pub fn install(conn: &Connection, key: Option<&DataEncryptionKey>, seal: bool) -> DbResult<()> {
+    let flags = FunctionFlags::SQLITE_UTF8;
+    let sealing_key = key.filter(|_| seal).cloned();
+    conn.create_scalar_function("vault_seal", 3, flags, move |ctx| {
+        let (id, field, value) = args(ctx)?;
+        let Some(value) = value else { return Ok(None) };
+        let Some(key) = &sealing_key else { return Ok(Some(value)) };
+        let value = match value {
+            value if value.is_empty() || is_sealed(&value) => value,
+            value => {
+                let sealed = encrypt_string_bound(key.as_ref(), &value, &aad(&id, &field))
+                    .map_err(|e| rusqlite::Error::UserFunctionError(e.to_string().into()))?;
+                format!("{}{}", SEALED_PREFIX, sealed)
+            }
+        };
+        Ok(Some(value))
+    })?;
+    // Not deterministic: what it returns depends on the key installed
+    let opening_key = key.cloned();
+    conn.create_scalar_function("vault_open", 3, flags, move |ctx| {
+        let (id, field, value) = args(ctx)?;
+        let Some(value) = value else { return Ok(None) };
+        let (Some(key), Some(sealed)) = (&opening_key, value.strip_prefix(SEALED_PREFIX)) else { return Ok(Some(value)) };
+        // Moved or tampered with; NULL rather than failing every query the row is in
+        Ok(decrypt_string_bound(key.as_ref(), sealed, &aad(&id, &field)).ok())
+    })?;
+    Ok(())
+}
+
+/// Seal every stored username and URL, or open them all again, to match
+/// the functions installed on the connection. Returns how many
+/// credentials changed. Usernames in the audit log follow along; their
+/// HMACs cover the plaintext, so the chain still verifies.
+pub fn reseal_all(conn: &Connection, seal: bool) -> DbResult<usize> {
+    let (credentials, audit) = match seal {
+        true => (
+            "UPDATE credentials SET username = vault_seal(id, 'username', username), url = vault_seal(id, 'url', url)
+             WHERE (username != '' AND username NOT LIKE 'sealed:%') OR (url != '' AND url NOT LIKE 'sealed:%')",
+            "UPDATE audit_log SET username = vault_seal(credential_id, 'audit username', username)
+             WHERE username != '' AND username NOT LIKE 'sealed:%'",
+        ),
+        false => (
+            "UPDATE credentials SET username = vault_open(id, 'username', username), url = vault_open(id, 'url', url)
+             WHERE username LIKE 'sealed:%' OR url LIKE 'sealed:%'",
+            "UPDATE audit_log SET username = vault_open(credential_id, 'audit username', username)
+             WHERE username LIKE 'sealed:%'",
+        ),
+    };
+    let changed = conn.execute(credentials, [])?;
+    conn.execute(audit, [])?;
+    Ok(changed)
+}
+
+/// Names of credentials whose sealed username or URL does not open with
+/// the installed key
+pub fn unopenable(conn: &Connection) -> DbResult<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM credentials
+         WHERE (username LIKE 'sealed:%' AND vault_open(id, 'username', username) IS NULL)
+            OR (url LIKE 'sealed:%' AND vault_open(id, 'url', url) IS NULL)
+         ORDER BY name",
+    )?;
+    let names = stmt.query_map([], |row| row.get(0))?.collect::<Result<_, _>>()?;
+    Ok(names)
+}
+
+/// How many credentials have a username or URL in the other form than
+/// `seal` asks for
+pub fn count_unmatched(conn: &Connection, seal: bool) -> DbResult<usize> {
+    let sql = match seal {
+        true => {
+            "SELECT COUNT(*) FROM credentials
+             WHERE (username != '' AND username NOT LIKE 'sealed:%') OR (url != '' AND url NOT LIKE 'sealed:%')"
+        }
+        false => "SELECT COUNT(*) FROM credentials WHERE username LIKE 'sealed:%' OR url LIKE 'sealed:%'",
+    };
+    let count: i64 = conn.query_row(sql, [], |row| row.get(0))?;
+    Ok(count as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{self, Credential, CredentialType, Database};
+
+    #[test]
+    fn test_sealed_fields() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let dek = DataEncryptionKey::from_bytes([0x42u8; 32]);
+        let mut cred = Credential::new("GitHub".to_string(), CredentialType::Password, "blob".to_string());
+        cred.username = Some("alice".to_string());
+        cred.url = Some("https://github.com".to_string());
+        db::create_credential(conn, &cred).unwrap();
+
+        install(conn, Some(&dek), true).unwrap();
+        assert_eq!(count_unmatched(conn, true).unwrap(), 1);
+        assert_eq!(reseal_all(conn, true).unwrap(), 1);
+        assert_eq!(count_unmatched(conn, true).unwrap(), 0);
+
+        let stored: String = conn.query_row("SELECT username FROM credentials", [], |row| row.get(0)).unwrap();
+        assert!(is_sealed(&stored) && !stored.contains("alice"));
+        let read = db::get_credential(conn, &cred.id).unwrap();
+        assert_eq!(read.username.as_deref(), Some("alice"));
+        assert_eq!(read.url.as_deref(), Some("https://github.com"));
+        let query = db::SearchQuery::parse("user:ali").unwrap();
+        assert_eq!(db::search_credentials_filtered(conn, &query).unwrap().len(), 1);
+
+        // Moved to another credential it does not open, and is named
+        let other = Credential::new("GitLab".to_string(), CredentialType::Password, "blob".to_string());
+        db::create_credential(conn, &other).unwrap();
+        conn.execute("UPDATE credentials SET username = ?1 WHERE id = ?2", [&stored, &other.id]).unwrap();
+        assert_eq!(db::get_credential(conn, &other.id).unwrap().username, None);
+        assert_eq!(unopenable(conn).unwrap(), vec!["GitLab".to_string()]);
+        db::delete_credential(conn, &other.id).unwrap();
+
+        // Without the key the sealed value is all there is
+        install(conn, None, false).unwrap();
+        assert_eq!(db::get_credential(conn, &cred.id).unwrap().username, Some(stored));
+
+        install(conn, Some(&dek), false).unwrap();
+        assert_eq!(reseal_all(conn, false).unwrap(), 1);
+        let stored: String = conn.query_row("SELECT username FROM credentials", [], |row| row.get(0)).unwrap();
+        assert_eq!(stored, "alice");
+    }
+}
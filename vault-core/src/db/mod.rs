@@ -3,9 +3,11 @@
 //! SQLite database layer with FTS5 full-text search.
 
 pub mod connection;
+pub(crate) mod fields;
 pub mod models;
-pub mod queries;
+pub(crate) mod queries;
 pub mod schema;
+pub mod search_query;
 
 use thiserror::Error;
 
@@ -20,11 +22,16 @@ pub enum DbError {
 
     #[error("Migration failed: {0}")]
     MigrationFailed(String),
+
+    #[error("Vault uses schema version {found}, but this build only supports up to {supported}; upgrade the app")]
+    NewerSchema { found: i32, supported: i32 },
 }
 
 pub type DbResult<T> = Result<T, DbError>;
 
 // Re-exports
 pub use connection::{Database, DatabaseConfig};
-pub use models::{AuditAction, AuditLog, Credential, CredentialType};
-pub use queries::*;
+pub use models::{AccessStats, AuditAction, AuditLog, Credential, CredentialType, KeySlot, SecretHistoryEntry, SyncState};
+pub(crate) use queries::*;
+pub use schema::{create_fts, drop_fts, has_fts};
+pub use search_query::{AuditQuery, SearchQuery, SortOrder};
@@ -0,0 +1,456 @@
+//! Database Models
+//!
+//! Data structures for credentials and audit logs.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Credential type enum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialType {
+    Password,
+    ApiKey,
+    SshKey,
+    Certificate,
+    Totp,
+    Note,
+    Database,
+    Custom,
+}
+
+impl CredentialType {
+    pub fn all() -> &'static [Self] {
+        &[
+            Self::Password,
+            Self::ApiKey,
+            Self::SshKey,
+            Self::Certificate,
+            Self::Totp,
+            Self::Note,
+            Self::Database,
+            Self::Custom,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Password => "password",
+            Self::ApiKey => "api_key",
+            Self::SshKey => "ssh_key",
+            Self::Certificate => "certificate",
+            Self::Totp => "totp",
+            Self::Note => "note",
+            Self::Database => "database",
+            Self::Custom => "custom",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "password" => Self::Password,
+            "api_key" => Self::ApiKey,
+            "ssh_key" => Self::SshKey,
+            "certificate" => Self::Certificate,
+            "totp" => Self::Totp,
+            "note" => Self::Note,
+            "database" => Self::Database,
+            _ => Self::Custom,
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Self::Password => "󰌋",
+            Self::ApiKey => "󰯄",
+            Self::SshKey => "󰣀",
+            Self::Certificate => "󰄤",
+            Self::Totp => "󰪥",
+            Self::Note => "󰎞",
+            Self::Database => "󰆼",
+            Self::Custom => "󰘓",
+        }
+    }
+
+    /// `icon` for terminals without a Nerd Font
+    pub fn ascii_icon(&self) -> &'static str {
+        match self {
+            Self::Password => "P",
+            Self::ApiKey => "A",
+            Self::SshKey => "S",
+            Self::Certificate => "C",
+            Self::Totp => "T",
+            Self::Note => "N",
+            Self::Database => "D",
+            Self::Custom => "*",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Password => "Password",
+            Self::ApiKey => "API Key",
+            Self::SshKey => "SSH Key",
+            Self::Certificate => "Certificate",
+            Self::Totp => "TOTP",
+            Self::Note => "Note",
+            Self::Database => "Database",
+            Self::Custom => "Custom",
+        }
+    }
+
+    /// Fields this type adds to the common ones, kept in
+    /// `Credential::attributes` under their keys. A custom type's fields
+    /// come from its definition instead, see `CustomType`.
+    pub fn attributes(&self) -> Vec<Attribute> {
+        match self {
+            Self::Database => vec![
+                Attribute::new("host", "Host", AttributeKind::Text),
+                Attribute::new("port", "Port", AttributeKind::Port),
+                Attribute::new("database", "Database", AttributeKind::Text),
+            ],
+            Self::ApiKey => vec![
+                Attribute::new("issued_at", "Issued", AttributeKind::Date),
+                Attribute::new(EXPIRES_AT, "Expires", AttributeKind::Date),
+                Attribute::new("scope", "Scope", AttributeKind::Text),
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    /// The fields of a credential of this type, taken from `custom` for
+    /// a user-defined type
+    pub fn attributes_with(&self, custom: Option<&CustomType>) -> Vec<Attribute> {
+        match (self, custom) {
+            (Self::Custom, Some(custom)) => custom.fields.clone(),
+            _ => self.attributes(),
+        }
+    }
+}
+
+/// Attribute naming the user-defined type of a Custom credential
+pub const CUSTOM_TYPE: &str = "custom_type";
+
+/// Attribute holding the date an API key stops working
+pub const EXPIRES_AT: &str = "expires_at";
+
+/// Attribute naming the backend a credential's secret is kept in; the
+/// secret stored in the vault is then the backend's reference to it
+pub const SECRET_BACKEND: &str = "backend";
+
+/// How date attributes are written
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// A date attribute's value, or `None` if it is not a date
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, DATE_FORMAT).ok()
+}
+
+/// A field specific to one credential type
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attribute {
+    pub key: String,
+    pub label: String,
+    #[serde(default)]
+    pub kind: AttributeKind,
+}
+
+impl Attribute {
+    pub fn new(key: &str, label: &str, kind: AttributeKind) -> Self {
+        Self { key: key.to_string(), label: label.to_string(), kind }
+    }
+}
+
+/// What values an attribute accepts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttributeKind {
+    #[default]
+    Text,
+    /// A TCP port, 1 to 65535
+    Port,
+    /// A calendar date in `DATE_FORMAT`
+    Date,
+}
+
+impl AttributeKind {
+    /// Why `value` is not acceptable, if it is not
+    pub fn check(&self, value: &str) -> Option<&'static str> {
+        match self {
+            Self::Text => None,
+            Self::Port => match value.parse::<u16>() {
+                Ok(port) if port > 0 => None,
+                _ => Some("Not a port number (1-65535)"),
+            },
+            Self::Date => match parse_date(value) {
+                Some(_) => None,
+                None => Some("Not a date (YYYY-MM-DD)"),
+            },
+        }
+    }
+}
+
+/// A kind of Custom credential defined in the config, with its own
+/// fields, e.g. a "Wi-Fi" type with SSID and security fields
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomType {
+    pub name: String,
+    /// Shown in place of the Custom icon
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub fields: Vec<Attribute>,
+    /// What `yy` copies: `secret` (the default), `username` or a field key
+    #[serde(default)]
+    pub copy: Option<String>,
+}
+
+impl CustomType {
+    /// The definition a Custom credential's `custom_type` attribute names
+    pub fn find<'a>(types: &'a [CustomType], attributes: &BTreeMap<String, String>) -> Option<&'a CustomType> {
+        let name = attributes.get(CUSTOM_TYPE)?;
+        types.iter().find(|t| &t.name == name)
+    }
+}
+
+/// Credential model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credential {
+    pub id: String,
+    pub name: String,
+    pub credential_type: CredentialType,
+    pub username: Option<String>,
+    pub encrypted_secret: String,
+    pub encrypted_notes: Option<String>,
+    pub url: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>,
+    pub accessed_at: Option<DateTime<Local>>,
+    /// Name of the configured password policy used to generate the secret
+    pub password_policy: Option<String>,
+    /// Environment variable `vault exec` exports the secret as
+    pub env_var: Option<String>,
+    /// OpenSSH public key line of an SSH key credential
+    pub public_key: Option<String>,
+    /// Values of the type's attributes, e.g. a database's host and port
+    pub attributes: BTreeMap<String, String>,
+    /// Short name to look the credential up by, e.g. `gh`; unique
+    /// ignoring case
+    pub alias: Option<String>,
+}
+
+impl Credential {
+    /// Create a new credential with generated ID
+    pub fn new(
+        name: String,
+        credential_type: CredentialType,
+        encrypted_secret: String,
+    ) -> Self {
+        let now = Local::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            credential_type,
+            username: None,
+            encrypted_secret,
+            encrypted_notes: None,
+            url: None,
+            tags: Vec::new(),
+            created_at: now,
+            updated_at: now,
+            accessed_at: None,
+            password_policy: None,
+            env_var: None,
+            public_key: None,
+            attributes: BTreeMap::new(),
+            alias: None,
+        }
+    }
+
+    /// When the credential stops working, from its `expires_at` attribute
+    pub fn expires_on(&self) -> Option<NaiveDate> {
+        parse_date(self.attributes.get(EXPIRES_AT)?)
+    }
+}
+
+/// Audit action types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Read,
+    /// Fields decrypted to show beside the list, without being opened
+    Preview,
+    Update,
+    Delete,
+    Copy,
+    Export,
+    Import,
+    Unlock,
+    Lock,
+    FailedUnlock,
+}
+
+impl AuditAction {
+    pub fn all() -> &'static [Self] {
+        &[
+            Self::Create,
+            Self::Read,
+            Self::Preview,
+            Self::Update,
+            Self::Delete,
+            Self::Copy,
+            Self::Export,
+            Self::Import,
+            Self::Unlock,
+            Self::Lock,
+            Self::FailedUnlock,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Read => "read",
+            Self::Preview => "preview",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Copy => "copy",
+            Self::Export => "export",
+            Self::Import => "import",
+            Self::Unlock => "unlock",
+            Self::Lock => "lock",
+            Self::FailedUnlock => "failed_unlock",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "create" => Self::Create,
+            "read" => Self::Read,
+            "preview" => Self::Preview,
+            "update" => Self::Update,
+            "delete" => Self::Delete,
+            "copy" => Self::Copy,
+            "export" => Self::Export,
+            "import" => Self::Import,
+            "unlock" => Self::Unlock,
+            "lock" => Self::Lock,
+            "failed_unlock" => Self::FailedUnlock,
+            _ => Self::Read,
+        }
+    }
+}
+
+/// Audit log entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLog {
+    pub id: i64,
+    pub timestamp: DateTime<Local>,
+    pub action: AuditAction,
+    pub credential_id: Option<String>,
+    pub credential_name: Option<String>,
+    pub username: Option<String>,
+    pub details: Option<String>,
+    pub hmac: String,
+}
+
+impl AuditLog {
+    /// Create a new audit log entry (ID assigned by database)
+    pub fn new(
+        action: AuditAction,
+        credential_id: Option<String>,
+        credential_name: Option<String>,
+        username: Option<String>,
+        details: Option<String>,
+        hmac: String,
+    ) -> Self {
+        Self {
+            id: 0,
+            timestamp: Local::now(),
+            action,
+            credential_id,
+            credential_name,
+            username,
+            details,
+            hmac,
+        }
+    }
+}
+
+/// An extra master password that unwraps the vault's DEK
+#[derive(Debug, Clone)]
+pub struct KeySlot {
+    pub label: String,
+    /// Argon2 PHC string, carrying the slot's own salt
+    pub password_hash: String,
+    pub wrapped_dek: String,
+    pub created_at: DateTime<Local>,
+}
+
+/// A secret a rotation replaced, kept encrypted
+#[derive(Debug, Clone)]
+pub struct SecretHistoryEntry {
+    pub credential_id: String,
+    pub encrypted_secret: String,
+    pub replaced_at: DateTime<Local>,
+}
+
+/// How often a credential was viewed and copied, from the audit log
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessStats {
+    pub reads: usize,
+    pub copies: usize,
+    /// Latest read, copy or `accessed_at` touch
+    pub last_accessed: Option<DateTime<Local>>,
+}
+
+/// What a remote looked like at the last successful push or pull
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncState {
+    pub remote: String,
+    /// SHA-256 of the vault file on the remote
+    pub remote_hash: String,
+    /// Fingerprint of the local credentials at the time
+    pub local_fingerprint: String,
+    pub synced_at: DateTime<Local>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credential_type_roundtrip() {
+        let types = [
+            CredentialType::Password,
+            CredentialType::ApiKey,
+            CredentialType::SshKey,
+            CredentialType::Certificate,
+            CredentialType::Totp,
+            CredentialType::Note,
+            CredentialType::Database,
+            CredentialType::Custom,
+        ];
+
+        for ct in types {
+            assert_eq!(CredentialType::from_str(ct.as_str()), ct);
+        }
+    }
+
+    #[test]
+    fn test_credential_new() {
+        let cred = Credential::new(
+            "Test".to_string(),
+            CredentialType::Password,
+            "encrypted".to_string(),
+        );
+
+        assert!(!cred.id.is_empty());
+        assert_eq!(cred.name, "Test");
+        assert_eq!(cred.credential_type, CredentialType::Password);
+    }
+}
@@ -0,0 +1,1149 @@
+//! Database Queries
+//!
+//! Parameterized queries for CRUD operations on credentials.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use super::{
+    models::{AccessStats, AuditAction, AuditLog, Credential, CredentialType, KeySlot, SecretHistoryEntry, SyncState},
+    search_query::{AuditQuery, SearchQuery, SortOrder},
+    DbError, DbResult,
+};
+
+// ============================================================================
+// Credential Queries
+// ============================================================================
+
+/// Create a new credential
+pub fn create_credential(conn: &Connection, credential: &Credential) -> DbResult<()> {
+    let tags_json = serde_json::to_string(&credential.tags).unwrap_or_else(|_| "[]".to_string());
+
+    conn.execute(
+        r#"
+        INSERT INTO credentials (id, name, credential_type, username, encrypted_secret, encrypted_notes, url, tags, created_at, updated_at, accessed_at, password_policy, env_var, public_key, attributes, alias)
+        VALUES (?1, ?2, ?3, vault_seal(?1, 'username', ?4), ?5, ?6, vault_seal(?1, 'url', ?7), ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
+        "#,
+        params![
+            credential.id,
+            credential.name,
+            credential.credential_type.as_str(),
+            credential.username,
+            credential.encrypted_secret,
+            credential.encrypted_notes,
+            credential.url,
+            tags_json,
+            credential.created_at.to_rfc3339(),
+            credential.updated_at.to_rfc3339(),
+            credential.accessed_at.map(|dt| dt.to_rfc3339()),
+            credential.password_policy,
+            credential.env_var,
+            credential.public_key,
+            attributes_json(&credential.attributes),
+            credential.alias,
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Get a credential by ID
+pub fn get_credential(conn: &Connection, id: &str) -> DbResult<Credential> {
+    conn.query_row(
+        r#"
+        SELECT id, name, credential_type, vault_open(id, 'username', username), encrypted_secret, encrypted_notes, vault_open(id, 'url', url), tags, created_at, updated_at, accessed_at, password_policy, env_var, public_key, attributes, alias
+        FROM credentials
+        WHERE id = ?1
+        "#,
+        [id],
+        row_to_credential,
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => DbError::NotFound(format!("Credential: {}", id)),
+        _ => e.into(),
+    })
+}
+
+/// Get all credentials
+pub fn get_all_credentials(conn: &Connection) -> DbResult<Vec<Credential>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT id, name, credential_type, vault_open(id, 'username', username), encrypted_secret, encrypted_notes, vault_open(id, 'url', url), tags, created_at, updated_at, accessed_at, password_policy, env_var, public_key, attributes, alias
+        FROM credentials
+        ORDER BY name
+        "#,
+    )?;
+
+    let credentials = stmt
+        .query_map([], row_to_credential)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(credentials)
+}
+
+/// Id and last update of every credential, to tell what changed since an
+/// index was built
+pub fn credential_stamps(conn: &Connection) -> DbResult<Vec<(String, DateTime<Local>)>> {
+    let mut stmt = conn.prepare("SELECT id, updated_at FROM credentials")?;
+    let stamps = stmt
+        .query_map([], |row| Ok((row.get(0)?, parse_datetime(row.get::<_, String>(1)?))))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(stamps)
+}
+
+/// Number of credentials in the vault
+pub fn count_credentials(conn: &Connection) -> DbResult<usize> {
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM credentials", [], |row| row.get(0))?;
+    Ok(count as usize)
+}
+
+/// Number of credentials of each type, most common first
+pub fn count_credentials_by_type(conn: &Connection) -> DbResult<Vec<(String, usize)>> {
+    let mut stmt = conn.prepare(
+        "SELECT credential_type, COUNT(*) FROM credentials GROUP BY credential_type ORDER BY COUNT(*) DESC, credential_type",
+    )?;
+    let counts = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize)))?
+        .collect::<Result<_, _>>()?;
+    Ok(counts)
+}
+
+/// Number of entries in the audit log
+pub fn count_audit_logs(conn: &Connection) -> DbResult<usize> {
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM audit_log", [], |row| row.get(0))?;
+    Ok(count as usize)
+}
+
+/// Up to `limit` credentials starting at row `offset` in `order`, so the
+/// list only has to hold the rows around its viewport
+pub fn get_credentials_page(conn: &Connection, order: SortOrder, offset: usize, limit: usize) -> DbResult<Vec<Credential>> {
+    let query = format!(
+        r#"
+        SELECT id, name, credential_type, vault_open(id, 'username', username), encrypted_secret, encrypted_notes, vault_open(id, 'url', url), tags, created_at, updated_at, accessed_at, password_policy, env_var, public_key, attributes, alias
+        FROM credentials
+        ORDER BY {}
+        LIMIT ?1 OFFSET ?2
+        "#,
+        order.order_by()
+    );
+    let mut stmt = conn.prepare(&query)?;
+
+    let credentials = stmt
+        .query_map(params![limit as i64, offset as i64], row_to_credential)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(credentials)
+}
+
+/// Row of a credential in the full list sorted by `order`
+pub fn credential_position(conn: &Connection, order: SortOrder, id: &str) -> DbResult<Option<usize>> {
+    let query = format!(
+        "SELECT pos FROM (SELECT id, ROW_NUMBER() OVER (ORDER BY {}) - 1 AS pos FROM credentials) WHERE id = ?1",
+        order.order_by()
+    );
+    let pos: Option<i64> = conn.query_row(&query, [id], |row| row.get(0)).optional()?;
+    Ok(pos.map(|p| p as usize))
+}
+
+/// Get credentials by tags (AND logic - must have all tags)
+pub fn get_credentials_by_tag(conn: &Connection, tags: &[String]) -> DbResult<Vec<Credential>> {
+    if tags.is_empty() {
+        return get_all_credentials(conn);
+    }
+
+    // Build query with multiple LIKE conditions (AND logic)
+    let conditions: Vec<String> = tags
+        .iter()
+        .enumerate()
+        .map(|(i, _)| format!("tags LIKE ?{} ESCAPE '\\'", i + 1))
+        .collect();
+    
+    let query = format!(
+        r#"
+        SELECT id, name, credential_type, vault_open(id, 'username', username), encrypted_secret, encrypted_notes, vault_open(id, 'url', url), tags, created_at, updated_at, accessed_at, password_policy, env_var, public_key, attributes, alias
+        FROM credentials
+        WHERE {}
+        ORDER BY name
+        "#,
+        conditions.join(" AND ")
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    
+    let patterns: Vec<String> = tags.iter().map(|t| like_contains(&format!("\"{}\"", t))).collect();
+    let params: Vec<&dyn rusqlite::ToSql> = patterns.iter().map(|p| p as &dyn rusqlite::ToSql).collect();
+    
+    let credentials = stmt
+        .query_map(params.as_slice(), row_to_credential)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(credentials)
+}
+
+/// Get all unique tags with counts
+pub fn get_all_tags_with_counts(conn: &Connection) -> DbResult<Vec<(String, usize)>> {
+    use std::collections::HashMap;
+    
+    let mut stmt = conn.prepare("SELECT tags FROM credentials")?;
+    let mut tag_counts: HashMap<String, usize> = HashMap::new();
+    
+    for tags_json in stmt.query_map([], |row| row.get::<_, String>(0))?.filter_map(|r| r.ok()) {
+        let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
+        for tag in tags {
+            *tag_counts.entry(tag).or_insert(0) += 1;
+        }
+    }
+    
+    let mut tags: Vec<_> = tag_counts.into_iter().collect();
+    tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    
+    Ok(tags)
+}
+
+/// Search credentials using FTS5
+pub fn search_credentials(conn: &Connection, query: &str) -> DbResult<Vec<Credential>> {
+    let Some(fts_query) = fts_prefix_query(query) else {
+        return get_all_credentials(conn);
+    };
+
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT c.id, c.name, c.credential_type, vault_open(c.id, 'username', c.username), c.encrypted_secret, c.encrypted_notes, vault_open(c.id, 'url', c.url), c.tags, c.created_at, c.updated_at, c.accessed_at, c.password_policy, c.env_var, c.public_key, c.attributes, c.alias
+        FROM credentials c
+        INNER JOIN credentials_fts fts ON c.rowid = fts.rowid
+        WHERE credentials_fts MATCH ?1
+        ORDER BY rank
+        "#,
+    )?;
+
+    let credentials = stmt
+        .query_map([fts_query], row_to_credential)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(credentials)
+}
+
+/// Search credentials with a structured query: FTS5 match on the free
+/// text, combined with tag/type/user/date filters
+pub fn search_credentials_filtered(conn: &Connection, query: &SearchQuery) -> DbResult<Vec<Credential>> {
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    let fts_query = fts_prefix_query(&query.text);
+    if let Some(fts) = &fts_query {
+        params.push(Box::new(fts.clone()));
+        conditions.push(format!("credentials_fts MATCH ?{}", params.len()));
+    }
+
+    // Whole tags only, compared as case-insensitively as LIKE would
+    for tag in &query.tags {
+        params.push(Box::new(tag.clone()));
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM json_each(c.tags) WHERE value = ?{} COLLATE NOCASE)",
+            params.len()
+        ));
+    }
+
+    if !query.types.is_empty() {
+        let placeholders: Vec<String> = query
+            .types
+            .iter()
+            .map(|t| {
+                params.push(Box::new(t.as_str()));
+                format!("?{}", params.len())
+            })
+            .collect();
+        conditions.push(format!("c.credential_type IN ({})", placeholders.join(", ")));
+    }
+
+    if let Some(user) = &query.user {
+        params.push(Box::new(like_contains(user)));
+        conditions.push(format!("vault_open(c.id, 'username', c.username) LIKE ?{} ESCAPE '\\'", params.len()));
+    }
+
+    for (column, filter) in [("created_at", &query.created), ("updated_at", &query.updated)] {
+        let Some(filter) = filter else { continue };
+        params.push(Box::new(filter.date.format("%Y-%m-%d").to_string()));
+        conditions.push(format!("date(c.{}) {} ?{}", column, filter.op.as_sql(), params.len()));
+    }
+
+    // Creation counts as use, so new credentials are not reported as unused
+    if let Some(days) = query.unused {
+        let cutoff = Local::now().date_naive() - chrono::Duration::days(i64::from(days));
+        params.push(Box::new(cutoff.format("%Y-%m-%d").to_string()));
+        conditions.push(format!(
+            "substr(MAX(c.created_at, COALESCE(c.accessed_at, ''), COALESCE({}, '')), 1, 10) < ?{}",
+            LAST_ACCESS_SQL,
+            params.len()
+        ));
+    }
+
+    let join = match fts_query {
+        Some(_) => "INNER JOIN credentials_fts fts ON c.rowid = fts.rowid",
+        None => "",
+    };
+    let where_clause = match conditions.is_empty() {
+        true => String::new(),
+        false => format!("WHERE {}", conditions.join(" AND ")),
+    };
+    let order = match fts_query {
+        Some(_) => "rank",
+        None => "c.name",
+    };
+
+    let sql = format!(
+        r#"
+        SELECT c.id, c.name, c.credential_type, vault_open(c.id, 'username', c.username), c.encrypted_secret, c.encrypted_notes, vault_open(c.id, 'url', c.url), c.tags, c.created_at, c.updated_at, c.accessed_at, c.password_policy, c.env_var, c.public_key, c.attributes, c.alias
+        FROM credentials c
+        {}
+        {}
+        ORDER BY {}
+        "#,
+        join, where_clause, order
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+
+    let credentials = stmt
+        .query_map(param_refs.as_slice(), row_to_credential)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(credentials)
+}
+
+/// Latest read or copy of credential `c` in the audit log
+const LAST_ACCESS_SQL: &str =
+    "(SELECT MAX(a.timestamp) FROM audit_log a WHERE a.credential_id = c.id AND a.action IN ('read', 'copy'))";
+
+/// A LIKE pattern, used with `ESCAPE '\'`, matching values that contain
+/// `text` with its `%`, `_` and `\` taken literally
+fn like_contains(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Escape special FTS5 characters and use prefix matching for better UX
+fn fts_prefix_query(text: &str) -> Option<String> {
+    let escaped = text.replace('"', "\"\"").replace(['*', ':'], "");
+    let escaped = escaped.trim();
+    (!escaped.is_empty()).then(|| format!("\"{}\"*", escaped))
+}
+
+/// Update a credential
+pub fn update_credential(conn: &Connection, credential: &Credential) -> DbResult<()> {
+    let tags_json = serde_json::to_string(&credential.tags).unwrap_or_else(|_| "[]".to_string());
+
+    let rows = conn.execute(
+        r#"
+        UPDATE credentials
+        SET name = ?2, credential_type = ?3, username = vault_seal(?1, 'username', ?4), encrypted_secret = ?5, encrypted_notes = ?6, url = vault_seal(?1, 'url', ?7), tags = ?8, updated_at = ?9, password_policy = ?10, env_var = ?11, public_key = ?12, attributes = ?13, alias = ?14
+        WHERE id = ?1
+        "#,
+        params![
+            credential.id,
+            credential.name,
+            credential.credential_type.as_str(),
+            credential.username,
+            credential.encrypted_secret,
+            credential.encrypted_notes,
+            credential.url,
+            tags_json,
+            Local::now().to_rfc3339(),
+            credential.password_policy,
+            credential.env_var,
+            credential.public_key,
+            attributes_json(&credential.attributes),
+            credential.alias,
+        ],
+    )?;
+
+    if rows == 0 {
+        return Err(DbError::NotFound(format!("Credential: {}", credential.id)));
+    }
+
+    Ok(())
+}
+
+/// Set the environment variable a credential is exported as, leaving
+/// `updated_at` alone
+pub fn set_credential_env_var(conn: &Connection, id: &str, env_var: Option<&str>) -> DbResult<()> {
+    let rows = conn.execute("UPDATE credentials SET env_var = ?2 WHERE id = ?1", params![id, env_var])?;
+    if rows == 0 {
+        return Err(DbError::NotFound(format!("Credential: {}", id)));
+    }
+    Ok(())
+}
+
+/// Set or clear a credential's alias. The unique index refuses one
+/// another credential already has.
+pub fn set_credential_alias(conn: &Connection, id: &str, alias: Option<&str>) -> DbResult<()> {
+    let rows = conn.execute(
+        "UPDATE credentials SET alias = ?2, updated_at = ?3 WHERE id = ?1",
+        params![id, alias, Local::now().to_rfc3339()],
+    )?;
+    if rows == 0 {
+        return Err(DbError::NotFound(format!("Credential: {}", id)));
+    }
+    Ok(())
+}
+
+/// The credential with this alias, ignoring case
+pub fn get_credential_by_alias(conn: &Connection, alias: &str) -> DbResult<Option<Credential>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT id, name, credential_type, vault_open(id, 'username', username), encrypted_secret, encrypted_notes, vault_open(id, 'url', url), tags, created_at, updated_at, accessed_at, password_policy, env_var, public_key, attributes, alias
+        FROM credentials WHERE alias = ?1 COLLATE NOCASE
+        "#,
+    )?;
+    Ok(stmt.query_row([alias], row_to_credential).optional()?)
+}
+
+/// Set the public key kept beside an SSH key, leaving `updated_at` alone
+pub fn set_credential_public_key(conn: &Connection, id: &str, public_key: Option<&str>) -> DbResult<()> {
+    let rows = conn.execute("UPDATE credentials SET public_key = ?2 WHERE id = ?1", params![id, public_key])?;
+    if rows == 0 {
+        return Err(DbError::NotFound(format!("Credential: {}", id)));
+    }
+    Ok(())
+}
+
+/// Update credential access time
+pub fn touch_credential(conn: &Connection, id: &str) -> DbResult<()> {
+    conn.execute(
+        "UPDATE credentials SET accessed_at = ?2 WHERE id = ?1",
+        params![id, Local::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// Read and copy counts for a credential, from the audit log
+pub fn get_access_stats(conn: &Connection, id: &str) -> DbResult<AccessStats> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT action, COUNT(*), MAX(timestamp)
+        FROM audit_log
+        WHERE credential_id = ?1 AND action IN ('read', 'copy')
+        GROUP BY action
+        "#,
+    )?;
+    let rows = stmt.query_map([id], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?))
+    })?;
+
+    let mut stats = AccessStats::default();
+    for row in rows {
+        let (action, count, latest) = row?;
+        match AuditAction::from_str(&action) {
+            AuditAction::Copy => stats.copies = count as usize,
+            _ => stats.reads = count as usize,
+        }
+        stats.last_accessed = stats.last_accessed.max(Some(parse_datetime(latest)));
+    }
+
+    let touched: Option<String> =
+        conn.query_row("SELECT accessed_at FROM credentials WHERE id = ?1", [id], |row| row.get(0)).unwrap_or(None);
+    stats.last_accessed = stats.last_accessed.max(touched.map(parse_datetime));
+    Ok(stats)
+}
+
+/// Delete a credential
+pub fn delete_credential(conn: &Connection, id: &str) -> DbResult<()> {
+    let rows = conn.execute("DELETE FROM credentials WHERE id = ?1", [id])?;
+
+    if rows == 0 {
+        return Err(DbError::NotFound(format!("Credential: {}", id)));
+    }
+
+    Ok(())
+}
+
+fn row_to_credential(row: &Row) -> rusqlite::Result<Credential> {
+    let tags_json: String = row.get(7)?;
+    let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
+
+    let accessed_at: Option<String> = row.get(10)?;
+
+    Ok(Credential {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        credential_type: CredentialType::from_str(&row.get::<_, String>(2)?),
+        username: row.get(3)?,
+        encrypted_secret: row.get(4)?,
+        encrypted_notes: row.get(5)?,
+        url: row.get(6)?,
+        tags,
+        created_at: parse_datetime(row.get::<_, String>(8)?),
+        updated_at: parse_datetime(row.get::<_, String>(9)?),
+        accessed_at: accessed_at.map(parse_datetime),
+        password_policy: row.get(11)?,
+        env_var: row.get(12)?,
+        public_key: row.get(13)?,
+        attributes: row
+            .get::<_, Option<String>>(14)?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        alias: row.get(15)?,
+    })
+}
+
+/// Attributes as stored: a JSON object, or NULL when there are none
+fn attributes_json(attributes: &BTreeMap<String, String>) -> Option<String> {
+    if attributes.is_empty() {
+        return None;
+    }
+    serde_json::to_string(attributes).ok()
+}
+
+// ============================================================================
+// Audit Log Queries
+// ============================================================================
+
+/// Create an audit log entry
+pub fn create_audit_log(conn: &Connection, log: &AuditLog) -> DbResult<i64> {
+    conn.execute(
+        r#"
+        INSERT INTO audit_log (timestamp, action, credential_id, credential_name, username, details, hmac)
+        VALUES (?1, ?2, ?3, ?4, vault_seal(?3, 'audit username', ?5), ?6, ?7)
+        "#,
+        params![
+            log.timestamp.to_rfc3339(),
+            log.action.as_str(),
+            log.credential_id,
+            log.credential_name,
+            log.username,
+            log.details,
+            log.hmac,
+        ],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Search audit logs, newest first, with every filter bound as a parameter
+pub fn search_audit_logs(conn: &Connection, query: &AuditQuery, limit: usize) -> DbResult<Vec<AuditLog>> {
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    if !query.actions.is_empty() {
+        let placeholders: Vec<String> = query
+            .actions
+            .iter()
+            .map(|a| {
+                params.push(Box::new(a.as_str()));
+                format!("?{}", params.len())
+            })
+            .collect();
+        conditions.push(format!("action IN ({})", placeholders.join(", ")));
+    }
+
+    if let Some(name) = &query.credential {
+        params.push(Box::new(like_contains(name)));
+        conditions.push(format!("credential_name LIKE ?{} ESCAPE '\\'", params.len()));
+    }
+
+    // Timestamps are stored in local time, so the first ten characters are
+    // the local date; SQLite's date() would shift them to UTC
+    for (op, date) in [(">=", &query.since), ("<=", &query.until)] {
+        let Some(date) = date else { continue };
+        params.push(Box::new(date.format("%Y-%m-%d").to_string()));
+        conditions.push(format!("substr(timestamp, 1, 10) {} ?{}", op, params.len()));
+    }
+
+    let text = query.text.trim();
+    if !text.is_empty() {
+        params.push(Box::new(like_contains(text)));
+        let n = params.len();
+        conditions.push(format!(
+            "(credential_name LIKE ?{n} ESCAPE '\\' OR vault_open(credential_id, 'audit username', username) LIKE ?{n} ESCAPE '\\' \
+             OR details LIKE ?{n} ESCAPE '\\')"
+        ));
+    }
+
+    let where_clause = match conditions.is_empty() {
+        true => String::new(),
+        false => format!("WHERE {}", conditions.join(" AND ")),
+    };
+    params.push(Box::new(limit as i64));
+
+    let sql = format!(
+        r#"
+        SELECT id, timestamp, action, credential_id, credential_name, vault_open(credential_id, 'audit username', username), details, hmac
+        FROM audit_log
+        {}
+        ORDER BY timestamp DESC
+        LIMIT ?{}
+        "#,
+        where_clause,
+        params.len()
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+
+    let logs = stmt
+        .query_map(param_refs.as_slice(), row_to_audit_log)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(logs)
+}
+
+/// Get audit logs for a credential
+pub fn get_credential_audit_logs(conn: &Connection, credential_id: &str) -> DbResult<Vec<AuditLog>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT id, timestamp, action, credential_id, credential_name, vault_open(credential_id, 'audit username', username), details, hmac
+        FROM audit_log
+        WHERE credential_id = ?1
+        ORDER BY timestamp DESC
+        "#,
+    )?;
+
+    let logs = stmt
+        .query_map([credential_id], row_to_audit_log)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(logs)
+}
+
+/// Get every audit log, oldest first
+pub fn get_all_audit_logs(conn: &Connection) -> DbResult<Vec<AuditLog>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT id, timestamp, action, credential_id, credential_name, vault_open(credential_id, 'audit username', username), details, hmac
+        FROM audit_log
+        ORDER BY id ASC
+        "#,
+    )?;
+
+    let logs = stmt
+        .query_map([], row_to_audit_log)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(logs)
+}
+
+fn row_to_audit_log(row: &Row) -> rusqlite::Result<AuditLog> {
+    Ok(AuditLog {
+        id: row.get(0)?,
+        timestamp: parse_datetime(row.get::<_, String>(1)?),
+        action: AuditAction::from_str(&row.get::<_, String>(2)?),
+        credential_id: row.get(3)?,
+        credential_name: row.get(4)?,
+        username: row.get(5)?,
+        details: row.get(6)?,
+        hmac: row.get(7)?,
+    })
+}
+
+// ============================================================================
+// Sync State Queries
+// ============================================================================
+
+pub fn get_sync_state(conn: &Connection, remote: &str) -> DbResult<Option<SyncState>> {
+    let result = conn.query_row(
+        "SELECT remote, remote_hash, local_fingerprint, synced_at FROM sync_state WHERE remote = ?1",
+        [remote],
+        |row| {
+            Ok(SyncState {
+                remote: row.get(0)?,
+                remote_hash: row.get(1)?,
+                local_fingerprint: row.get(2)?,
+                synced_at: parse_datetime(row.get(3)?),
+            })
+        },
+    );
+    match result {
+        Ok(state) => Ok(Some(state)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save_sync_state(conn: &Connection, state: &SyncState) -> DbResult<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO sync_state (remote, remote_hash, local_fingerprint, synced_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![state.remote, state.remote_hash, state.local_fingerprint, state.synced_at.to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+// ============================================================================
+// Key Slot Queries
+// ============================================================================
+
+pub fn get_key_slots(conn: &Connection) -> DbResult<Vec<KeySlot>> {
+    let mut stmt = conn.prepare("SELECT label, password_hash, wrapped_dek, created_at FROM key_slots ORDER BY created_at")?;
+    let slots = stmt
+        .query_map([], |row| {
+            Ok(KeySlot {
+                label: row.get(0)?,
+                password_hash: row.get(1)?,
+                wrapped_dek: row.get(2)?,
+                created_at: parse_datetime(row.get::<_, String>(3)?),
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(slots)
+}
+
+/// Insert or replace the slot with this label
+pub fn save_key_slot(conn: &Connection, slot: &KeySlot) -> DbResult<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO key_slots (label, password_hash, wrapped_dek, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![slot.label, slot.password_hash, slot.wrapped_dek, slot.created_at.to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+pub fn delete_key_slot(conn: &Connection, label: &str) -> DbResult<()> {
+    let rows = conn.execute("DELETE FROM key_slots WHERE label = ?1", [label])?;
+    if rows == 0 {
+        return Err(DbError::NotFound(format!("Key slot '{}'", label)));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Secret History Queries
+// ============================================================================
+
+pub fn add_secret_history(conn: &Connection, entry: &SecretHistoryEntry) -> DbResult<()> {
+    conn.execute(
+        "INSERT INTO secret_history (credential_id, encrypted_secret, replaced_at) VALUES (?1, ?2, ?3)",
+        params![entry.credential_id, entry.encrypted_secret, entry.replaced_at.to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// A credential's replaced secrets, most recently replaced first
+pub fn get_secret_history(conn: &Connection, credential_id: &str) -> DbResult<Vec<SecretHistoryEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT credential_id, encrypted_secret, replaced_at FROM secret_history
+         WHERE credential_id = ?1 ORDER BY id DESC",
+    )?;
+    let entries = stmt
+        .query_map([credential_id], |row| {
+            Ok(SecretHistoryEntry {
+                credential_id: row.get(0)?,
+                encrypted_secret: row.get(1)?,
+                replaced_at: parse_datetime(row.get::<_, String>(2)?),
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(entries)
+}
+
+/// Every archived secret with its row id, for re-encrypting in place
+pub fn get_all_secret_history(conn: &Connection) -> DbResult<Vec<(i64, SecretHistoryEntry)>> {
+    let mut stmt = conn.prepare("SELECT id, credential_id, encrypted_secret, replaced_at FROM secret_history")?;
+    let entries = stmt.query_map([], row_to_history_row)?.filter_map(|r| r.ok()).collect();
+    Ok(entries)
+}
+
+/// Up to `limit` archived secrets not starting with `prefix`
+pub fn get_secret_history_without_prefix(conn: &Connection, prefix: &str, limit: usize) -> DbResult<Vec<(i64, SecretHistoryEntry)>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, credential_id, encrypted_secret, replaced_at FROM secret_history
+         WHERE substr(encrypted_secret, 1, length(?1)) != ?1 LIMIT ?2",
+    )?;
+    let entries = stmt
+        .query_map(params![prefix, limit as i64], row_to_history_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(entries)
+}
+
+fn row_to_history_row(row: &Row) -> rusqlite::Result<(i64, SecretHistoryEntry)> {
+    let entry = SecretHistoryEntry {
+        credential_id: row.get(1)?,
+        encrypted_secret: row.get(2)?,
+        replaced_at: parse_datetime(row.get::<_, String>(3)?),
+    };
+    Ok((row.get(0)?, entry))
+}
+
+pub fn set_history_secret(conn: &Connection, row_id: i64, encrypted_secret: &str) -> DbResult<()> {
+    conn.execute("UPDATE secret_history SET encrypted_secret = ?1 WHERE id = ?2", params![encrypted_secret, row_id])?;
+    Ok(())
+}
+
+// ============================================================================
+// Maintenance Queries
+// ============================================================================
+
+/// Replace a credential's ciphertexts with the same values sealed anew.
+/// Not an edit, so `updated_at` is left alone.
+pub fn set_encrypted_fields(conn: &Connection, id: &str, encrypted_secret: &str, encrypted_notes: Option<&str>) -> DbResult<()> {
+    conn.execute(
+        "UPDATE credentials SET encrypted_secret = ?1, encrypted_notes = ?2 WHERE id = ?3",
+        params![encrypted_secret, encrypted_notes, id],
+    )?;
+    Ok(())
+}
+
+/// Up to `limit` credentials whose secret or notes do not start with `prefix`
+pub fn get_credentials_without_prefix(conn: &Connection, prefix: &str, limit: usize) -> DbResult<Vec<Credential>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT id, name, credential_type, vault_open(id, 'username', username), encrypted_secret, encrypted_notes, vault_open(id, 'url', url), tags, created_at, updated_at, accessed_at, password_policy, env_var, public_key, attributes, alias
+        FROM credentials
+        WHERE substr(encrypted_secret, 1, length(?1)) != ?1
+           OR substr(encrypted_notes, 1, length(?1)) != ?1
+        LIMIT ?2
+        "#,
+    )?;
+    let credentials = stmt
+        .query_map(params![prefix, limit as i64], row_to_credential)?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(credentials)
+}
+
+/// Secrets, notes and archived secrets not starting with `prefix`
+pub fn count_ciphertexts_without_prefix(conn: &Connection, prefix: &str) -> DbResult<usize> {
+    let count: i64 = conn.query_row(
+        r#"
+        SELECT (SELECT COUNT(*) FROM credentials WHERE substr(encrypted_secret, 1, length(?1)) != ?1)
+             + (SELECT COUNT(*) FROM credentials WHERE substr(encrypted_notes, 1, length(?1)) != ?1)
+             + (SELECT COUNT(*) FROM secret_history WHERE substr(encrypted_secret, 1, length(?1)) != ?1)
+        "#,
+        [prefix],
+        |row| row.get(0),
+    )?;
+    Ok(count as usize)
+}
+
+/// Compare the FTS index against the credentials table
+pub fn fts_integrity_check(conn: &Connection) -> DbResult<bool> {
+    let result = conn.execute(
+        "INSERT INTO credentials_fts(credentials_fts, rank) VALUES ('integrity-check', 1)",
+        [],
+    );
+    match result {
+        Ok(_) => Ok(true),
+        Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ErrorCode::DatabaseCorrupt => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Rebuild the FTS index from the credentials table
+pub fn rebuild_fts(conn: &Connection) -> DbResult<()> {
+    conn.execute("INSERT INTO credentials_fts(credentials_fts) VALUES ('rebuild')", [])?;
+    Ok(())
+}
+
+pub fn get_metadata(conn: &Connection, key: &str) -> DbResult<Option<String>> {
+    let result = conn.query_row("SELECT value FROM metadata WHERE key = ?1", [key], |row| row.get(0));
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn set_metadata(conn: &Connection, key: &str, value: &str) -> DbResult<()> {
+    conn.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)", [key, value])?;
+    Ok(())
+}
+
+pub fn get_metadata_keys(conn: &Connection) -> DbResult<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT key FROM metadata ORDER BY key")?;
+    let keys = stmt
+        .query_map([], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(keys)
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+fn parse_datetime(s: String) -> DateTime<Local> {
+    DateTime::parse_from_rfc3339(&s)
+        .map(|dt| dt.with_timezone(&Local))
+        .unwrap_or_else(|_| Local::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[test]
+    fn test_credential_crud() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+
+        let mut cred = Credential::new(
+            "GitHub Token".to_string(),
+            CredentialType::ApiKey,
+            "encrypted_secret".to_string(),
+        );
+        cred.username = Some("user".to_string());
+        cred.tags = vec!["dev".to_string(), "api".to_string()];
+
+        create_credential(conn, &cred).unwrap();
+
+        let fetched = get_credential(conn, &cred.id).unwrap();
+        assert_eq!(fetched.name, "GitHub Token");
+        assert_eq!(fetched.tags.len(), 2);
+
+        delete_credential(conn, &cred.id).unwrap();
+        assert!(get_credential(conn, &cred.id).is_err());
+    }
+
+    #[test]
+    fn test_fts_search() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+
+        let cred1 = Credential::new(
+            "AWS Production".to_string(),
+            CredentialType::ApiKey,
+            "enc".to_string(),
+        );
+        let cred2 = Credential::new(
+            "AWS Staging".to_string(),
+            CredentialType::ApiKey,
+            "enc".to_string(),
+        );
+        let cred3 = Credential::new(
+            "GitHub Token".to_string(),
+            CredentialType::ApiKey,
+            "enc".to_string(),
+        );
+
+        create_credential(conn, &cred1).unwrap();
+        create_credential(conn, &cred2).unwrap();
+        create_credential(conn, &cred3).unwrap();
+
+        let results = search_credentials(conn, "AWS").unwrap();
+        assert_eq!(results.len(), 2);
+
+        let results = search_credentials(conn, "GitHub").unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn test_filtered_search() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+
+        let mut cred1 = Credential::new("AWS Production".to_string(), CredentialType::ApiKey, "enc".to_string());
+        cred1.tags = vec!["work".to_string()];
+        cred1.username = Some("alice".to_string());
+        let mut cred2 = Credential::new("AWS Personal".to_string(), CredentialType::Password, "enc".to_string());
+        cred2.tags = vec!["home".to_string()];
+        let cred3 = Credential::new("GitHub".to_string(), CredentialType::ApiKey, "enc".to_string());
+
+        create_credential(conn, &cred1).unwrap();
+        create_credential(conn, &cred2).unwrap();
+        create_credential(conn, &cred3).unwrap();
+
+        let search = |q: &str| search_credentials_filtered(conn, &SearchQuery::parse(q).unwrap()).unwrap();
+
+        assert_eq!(search("type:apikey").len(), 2);
+        assert_eq!(search("tag:work aws").len(), 1);
+        assert_eq!(search("aws").len(), 2);
+        assert_eq!(search("user:ali").len(), 1);
+        assert_eq!(search("user:a_ice").len(), 0);
+        assert_eq!(search("user:%").len(), 0);
+        assert_eq!(search("type:password tag:work").len(), 0);
+        assert_eq!(search("updated:>2000-01-01").len(), 3);
+        assert_eq!(search("updated:<2000-01-01").len(), 0);
+        // Wildcards and quotes in a tag are matched literally
+        assert_eq!(search("tag:WORK").len(), 1);
+        assert_eq!(search("tag:w_rk").len(), 0);
+        assert_eq!(search("tag:%").len(), 0);
+        assert_eq!(search("tag:wor").len(), 0);
+    }
+
+    #[test]
+    fn test_credential_pages() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+
+        let mut all = Vec::new();
+        for (i, kind) in [CredentialType::Password, CredentialType::ApiKey, CredentialType::Note].iter().cycle().take(10).enumerate() {
+            let mut cred = Credential::new(format!("cred{:02}", i), *kind, "enc".to_string());
+            cred.tags = vec![format!("t{}", i % 2)];
+            create_credential(conn, &cred).unwrap();
+            all.push(cred);
+        }
+        assert_eq!(count_credentials(conn).unwrap(), 10);
+
+        for order in SortOrder::ALL {
+            let mut expected = get_all_credentials(conn).unwrap();
+            order.apply(&mut expected);
+            let paged: Vec<String> = (0..4)
+                .flat_map(|page| get_credentials_page(conn, order, page * 3, 3).unwrap())
+                .map(|c| c.id)
+                .collect();
+            let expected_ids: Vec<String> = expected.iter().map(|c| c.id.clone()).collect();
+            assert_eq!(paged, expected_ids, "{:?}", order);
+            assert_eq!(credential_position(conn, order, &expected[7].id).unwrap(), Some(7));
+        }
+        assert_eq!(credential_position(conn, SortOrder::Name, "missing").unwrap(), None);
+        assert_eq!(get_all_tags_with_counts(conn).unwrap(), vec![("t0".to_string(), 5), ("t1".to_string(), 5)]);
+    }
+
+    #[test]
+    fn test_access_stats_and_unused() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+
+        let mut old = Credential::new("Old".to_string(), CredentialType::Password, "enc".to_string());
+        old.created_at = Local::now() - chrono::Duration::days(800);
+        let mut used = Credential::new("Used".to_string(), CredentialType::Password, "enc".to_string());
+        used.created_at = old.created_at;
+        let fresh = Credential::new("Fresh".to_string(), CredentialType::Password, "enc".to_string());
+        for cred in [&old, &used, &fresh] {
+            create_credential(conn, cred).unwrap();
+        }
+
+        let log = |action, id: &str| {
+            let log = AuditLog::new(action, Some(id.to_string()), None, None, None, String::new());
+            create_audit_log(conn, &log).unwrap();
+        };
+        log(AuditAction::Read, &used.id);
+        log(AuditAction::Read, &used.id);
+        log(AuditAction::Copy, &used.id);
+        log(AuditAction::Update, &used.id);
+
+        let stats = get_access_stats(conn, &used.id).unwrap();
+        assert_eq!((stats.reads, stats.copies), (2, 1));
+        assert!(stats.last_accessed.is_some());
+        assert_eq!(get_access_stats(conn, &old.id).unwrap(), AccessStats::default());
+        touch_credential(conn, &old.id).unwrap();
+        assert!(get_access_stats(conn, &old.id).unwrap().last_accessed.is_some());
+
+        let unused = search_credentials_filtered(conn, &SearchQuery::unused_for(365)).unwrap();
+        assert!(unused.is_empty());
+        conn.execute("UPDATE credentials SET accessed_at = NULL", []).unwrap();
+        let unused = search_credentials_filtered(conn, &SearchQuery::unused_for(365)).unwrap();
+        assert_eq!(unused.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["Old"]);
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+
+        let log = AuditLog::new(
+            AuditAction::Create,
+            Some("cred-1".to_string()),
+            Some("user_foo".to_string()),
+            Some("bar123".to_string()),
+            Some("Created credential".to_string()),
+            "hmac_value".to_string(),
+        );
+
+        let id = create_audit_log(conn, &log).unwrap();
+        assert!(id > 0);
+
+        let recent = search_audit_logs(conn, &AuditQuery::default(), 10).unwrap();
+        assert!(!recent.is_empty());
+    }
+
+    #[test]
+    fn test_search_audit_logs() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let add = |action, name: &str, details: &str| {
+            let log = AuditLog::new(action, None, Some(name.to_string()), None, Some(details.to_string()), String::new());
+            create_audit_log(conn, &log).unwrap();
+        };
+        add(AuditAction::Create, "GitHub", "Created");
+        add(AuditAction::Copy, "GitHub", "Secret");
+        add(AuditAction::Copy, "AWS", "Username");
+
+        let search = |q: &str| search_audit_logs(conn, &AuditQuery::parse(q).unwrap(), 500).unwrap();
+
+        assert_eq!(search("").len(), 3);
+        assert_eq!(search("action:copy").len(), 2);
+        assert_eq!(search("action:copy name:git").len(), 1);
+        assert_eq!(search("name:%").len(), 0);
+        assert_eq!(search("git_ub").len(), 0);
+        assert_eq!(search("\\").len(), 0);
+        assert_eq!(search("secret").len(), 1);
+        assert_eq!(search("since:2000-01-01").len(), 3);
+        assert_eq!(search("until:2000-01-01").len(), 0);
+        assert_eq!(search_audit_logs(conn, &AuditQuery::default(), 2).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_fts_integrity_and_rebuild() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        create_credential(conn, &Credential::new("GitHub".to_string(), CredentialType::Password, "x".to_string())).unwrap();
+        assert!(fts_integrity_check(conn).unwrap());
+
+        conn.execute(
+            "INSERT INTO credentials_fts(rowid, name, username, url, tags) VALUES (999, 'ghost', '', '', '[]')",
+            [],
+        )
+        .unwrap();
+        assert!(!fts_integrity_check(conn).unwrap());
+
+        rebuild_fts(conn).unwrap();
+        assert!(fts_integrity_check(conn).unwrap());
+    }
+
+    #[test]
+    fn test_sync_state_roundtrip() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        assert_eq!(get_sync_state(conn, "host:vault.db").unwrap(), None);
+
+        let mut state = SyncState {
+            remote: "host:vault.db".to_string(),
+            remote_hash: "abc".to_string(),
+            local_fingerprint: "def".to_string(),
+            synced_at: parse_datetime(Local::now().to_rfc3339()),
+        };
+        save_sync_state(conn, &state).unwrap();
+        state.remote_hash = "123".to_string();
+        save_sync_state(conn, &state).unwrap();
+
+        assert_eq!(get_sync_state(conn, "host:vault.db").unwrap(), Some(state));
+    }
+
+    #[test]
+    fn test_key_slots() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let mut slot = KeySlot {
+            label: "team".to_string(),
+            password_hash: "hash".to_string(),
+            wrapped_dek: "dek".to_string(),
+            created_at: Local::now(),
+        };
+        save_key_slot(conn, &slot).unwrap();
+        slot.password_hash = "rehashed".to_string();
+        save_key_slot(conn, &slot).unwrap();
+
+        let slots = get_key_slots(conn).unwrap();
+        assert_eq!(slots.len(), 1);
+        assert_eq!(slots[0].password_hash, "rehashed");
+
+        delete_key_slot(conn, "team").unwrap();
+        assert!(get_key_slots(conn).unwrap().is_empty());
+        assert!(matches!(delete_key_slot(conn, "team"), Err(DbError::NotFound(_))));
+    }
+}
@@ -0,0 +1,611 @@
+//! Database Schema
+//!
+//! SQLite schema with FTS5 for full-text search.
+
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, DatabaseName};
+
+use super::{DbError, DbResult};
+
+/// Current schema version
+pub const SCHEMA_VERSION: i32 = 10;
+
+/// Initialize the database schema
+pub fn init_schema(conn: &Connection) -> DbResult<()> {
+    init_schema_with_backup(conn, None)
+}
+
+/// Initialize the database schema. Before migrating an existing vault, a
+/// copy of it is written next to `vault_path` as `<file>.v<old version>.bak`.
+pub fn init_schema_with_backup(conn: &Connection, vault_path: Option<&Path>) -> DbResult<()> {
+    // Check if schema exists
+    let has_schema: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type='table' AND name='metadata'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+
+    if !has_schema {
+        create_schema(conn)?;
+    }
+
+    migrate(conn, vault_path)
+}
+
+/// A step from the previous schema version to `version`
+struct Migration {
+    version: i32,
+    apply: fn(&Connection) -> DbResult<()>,
+}
+
+/// Every migration, oldest first. The last one's version is
+/// [`SCHEMA_VERSION`].
+const MIGRATIONS: &[Migration] = &[
+    Migration { version: 3, apply: add_password_policy },
+    Migration { version: 4, apply: add_sync_state },
+    Migration { version: 5, apply: add_key_slots },
+    Migration { version: 6, apply: add_env_var },
+    Migration { version: 7, apply: add_public_key },
+    Migration { version: 8, apply: add_attributes },
+    Migration { version: 9, apply: add_secret_history },
+    Migration { version: 10, apply: add_alias },
+];
+
+fn add_password_policy(conn: &Connection) -> DbResult<()> {
+    conn.execute_batch("ALTER TABLE credentials ADD COLUMN password_policy TEXT;")?;
+    Ok(())
+}
+
+fn add_sync_state(conn: &Connection) -> DbResult<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS sync_state (
+            remote TEXT PRIMARY KEY,
+            remote_hash TEXT NOT NULL,
+            local_fingerprint TEXT NOT NULL,
+            synced_at TEXT NOT NULL
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+fn add_key_slots(conn: &Connection) -> DbResult<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS key_slots (
+            label TEXT PRIMARY KEY,
+            password_hash TEXT NOT NULL,
+            wrapped_dek TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+fn add_secret_history(conn: &Connection) -> DbResult<()> {
+    conn.execute_batch(SECRET_HISTORY_SCHEMA)?;
+    Ok(())
+}
+
+/// Secrets replaced by a rotation, still encrypted with the DEK
+const SECRET_HISTORY_SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS secret_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    credential_id TEXT NOT NULL REFERENCES credentials(id) ON DELETE CASCADE,
+    encrypted_secret TEXT NOT NULL,
+    replaced_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_secret_history_credential ON secret_history(credential_id);
+"#;
+
+fn add_env_var(conn: &Connection) -> DbResult<()> {
+    if !has_column(conn, "credentials", "env_var")? {
+        conn.execute_batch("ALTER TABLE credentials ADD COLUMN env_var TEXT;")?;
+    }
+    Ok(())
+}
+
+fn add_public_key(conn: &Connection) -> DbResult<()> {
+    if !has_column(conn, "credentials", "public_key")? {
+        conn.execute_batch("ALTER TABLE credentials ADD COLUMN public_key TEXT;")?;
+    }
+    Ok(())
+}
+
+fn add_attributes(conn: &Connection) -> DbResult<()> {
+    if !has_column(conn, "credentials", "attributes")? {
+        conn.execute_batch("ALTER TABLE credentials ADD COLUMN attributes TEXT;")?;
+    }
+    Ok(())
+}
+
+/// The alias column, and the search index rebuilt to cover it
+fn add_alias(conn: &Connection) -> DbResult<()> {
+    if !has_column(conn, "credentials", "alias")? {
+        conn.execute_batch("ALTER TABLE credentials ADD COLUMN alias TEXT;")?;
+    }
+    conn.execute_batch(ALIAS_INDEX)?;
+    if has_fts(conn)? {
+        conn.execute_batch(DROP_FTS)?;
+        create_fts(conn)?;
+    }
+    Ok(())
+}
+
+/// Aliases are unique ignoring case; NULLs do not collide
+const ALIAS_INDEX: &str =
+    "CREATE UNIQUE INDEX IF NOT EXISTS idx_credentials_alias ON credentials(alias COLLATE NOCASE);";
+
+/// Refuse vaults written by a newer build, which this one could damage
+pub fn check_schema_version(conn: &Connection) -> DbResult<i32> {
+    let version = get_schema_version(conn)?;
+    if version > SCHEMA_VERSION {
+        return Err(DbError::NewerSchema { found: version, supported: SCHEMA_VERSION });
+    }
+    Ok(version)
+}
+
+/// Bring an existing vault up to the current schema version, one
+/// migration per transaction
+fn migrate(conn: &Connection, vault_path: Option<&Path>) -> DbResult<()> {
+    let version = check_schema_version(conn)?;
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > version).collect();
+    if pending.is_empty() {
+        // Vaults from before user_version tracking only need it stamped
+        if user_version(conn)? != version {
+            set_schema_version(conn, version)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = vault_path {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(format!(".v{}.bak", version));
+        conn.backup(DatabaseName::Main, PathBuf::from(backup), None)
+            .map_err(|e| DbError::MigrationFailed(format!("backup before migrating: {}", e)))?;
+    }
+
+    for migration in pending {
+        let tx = conn.unchecked_transaction()?;
+        (migration.apply)(&tx)
+            .and_then(|()| set_schema_version(&tx, migration.version))
+            .map_err(|e| DbError::MigrationFailed(format!("to version {}: {}", migration.version, e)))?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Record the schema version in `user_version`, and in the metadata table
+/// where older builds look for it
+fn set_schema_version(conn: &Connection, version: i32) -> DbResult<()> {
+    conn.execute_batch(&format!("PRAGMA user_version = {};", version))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', ?1)",
+        [version.to_string()],
+    )?;
+    Ok(())
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> DbResult<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(1))?.collect::<Result<Vec<_>, _>>()?;
+    Ok(names.iter().any(|name| name == column))
+}
+
+/// Full-text index over the plaintext credential columns, kept in sync by
+/// triggers
+const FTS_SCHEMA: &str = r#"
+-- FTS5 virtual table for full-text search
+CREATE VIRTUAL TABLE IF NOT EXISTS credentials_fts USING fts5(
+    name,
+    username,
+    url,
+    tags,
+    alias,
+    content='credentials',
+    content_rowid='rowid'
+);
+
+-- Triggers to keep FTS index in sync
+CREATE TRIGGER IF NOT EXISTS credentials_ai AFTER INSERT ON credentials BEGIN
+    INSERT INTO credentials_fts(rowid, name, username, url, tags, alias)
+    VALUES (new.rowid, new.name, new.username, new.url, new.tags, new.alias);
+END;
+
+CREATE TRIGGER IF NOT EXISTS credentials_ad AFTER DELETE ON credentials BEGIN
+    INSERT INTO credentials_fts(credentials_fts, rowid, name, username, url, tags, alias)
+    VALUES ('delete', old.rowid, old.name, old.username, old.url, old.tags, old.alias);
+END;
+
+CREATE TRIGGER IF NOT EXISTS credentials_au AFTER UPDATE ON credentials BEGIN
+    INSERT INTO credentials_fts(credentials_fts, rowid, name, username, url, tags, alias)
+    VALUES ('delete', old.rowid, old.name, old.username, old.url, old.tags, old.alias);
+    INSERT INTO credentials_fts(rowid, name, username, url, tags, alias)
+    VALUES (new.rowid, new.name, new.username, new.url, new.tags, new.alias);
+END;
+"#;
+
+/// Create the full schema
+fn create_schema(conn: &Connection) -> DbResult<()> {
+    conn.execute_batch(
+        r#"
+        -- Metadata table for vault configuration
+        CREATE TABLE IF NOT EXISTS metadata (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        -- Credentials table
+        CREATE TABLE IF NOT EXISTS credentials (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            credential_type TEXT NOT NULL,
+            username TEXT,
+            encrypted_secret TEXT NOT NULL,
+            encrypted_notes TEXT,
+            url TEXT,
+            tags TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            accessed_at TEXT,
+            password_policy TEXT,
+            env_var TEXT,
+            public_key TEXT,
+            attributes TEXT,
+            alias TEXT
+        );
+
+        -- Audit log table
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp TEXT NOT NULL,
+            action TEXT NOT NULL,
+            credential_id TEXT,
+            credential_name TEXT,
+            username TEXT,
+            details TEXT,
+            hmac TEXT NOT NULL
+        );
+
+        -- Last successful sync per remote, for conflict detection
+        CREATE TABLE IF NOT EXISTS sync_state (
+            remote TEXT PRIMARY KEY,
+            remote_hash TEXT NOT NULL,
+            local_fingerprint TEXT NOT NULL,
+            synced_at TEXT NOT NULL
+        );
+
+        -- Additional passwords that unwrap the same DEK; the primary one
+        -- lives in metadata
+        CREATE TABLE IF NOT EXISTS key_slots (
+            label TEXT PRIMARY KEY,
+            password_hash TEXT NOT NULL,
+            wrapped_dek TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+
+        -- Indexes for common queries
+        CREATE INDEX IF NOT EXISTS idx_credentials_type ON credentials(credential_type);
+        CREATE INDEX IF NOT EXISTS idx_credentials_updated ON credentials(updated_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_audit_timestamp ON audit_log(timestamp DESC);
+
+        "#,
+    )?;
+    conn.execute_batch(SECRET_HISTORY_SCHEMA)?;
+    conn.execute_batch(ALIAS_INDEX)?;
+    conn.execute_batch(FTS_SCHEMA)?;
+    set_schema_version(conn, SCHEMA_VERSION)?;
+
+    Ok(())
+}
+
+/// Whether the vault keeps its search index on disk
+pub fn has_fts(conn: &Connection) -> DbResult<bool> {
+    let exists = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type='table' AND name='credentials_fts'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(exists)
+}
+
+/// Put the on-disk search index back and fill it from the credentials
+pub fn create_fts(conn: &Connection) -> DbResult<()> {
+    conn.execute_batch(FTS_SCHEMA)?;
+    conn.execute("INSERT INTO credentials_fts(credentials_fts) VALUES ('rebuild')", [])?;
+    Ok(())
+}
+
+/// Remove the on-disk search index. The vacuum rewrites the file so the
+/// freed index pages do not linger in it.
+pub fn drop_fts(conn: &Connection) -> DbResult<()> {
+    conn.execute_batch(DROP_FTS)?;
+    conn.execute_batch("VACUUM;")?;
+    Ok(())
+}
+
+const DROP_FTS: &str = r#"
+DROP TRIGGER IF EXISTS credentials_ai;
+DROP TRIGGER IF EXISTS credentials_ad;
+DROP TRIGGER IF EXISTS credentials_au;
+DROP TABLE IF EXISTS credentials_fts;
+"#;
+
+fn user_version(conn: &Connection) -> DbResult<i32> {
+    Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+}
+
+/// Get current schema version
+pub fn get_schema_version(conn: &Connection) -> DbResult<i32> {
+    let user_version = user_version(conn)?;
+    if user_version > 0 {
+        return Ok(user_version);
+    }
+
+    // Vaults from before user_version tracking kept it in metadata only
+    let version: String = conn
+        .query_row(
+            "SELECT value FROM metadata WHERE key = 'schema_version'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or_else(|_| "0".to_string());
+
+    Ok(version.parse().unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+
+        // Verify tables exist
+        let tables: Vec<String> = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type='table' ORDER BY name")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .filter_map(|r| r.ok())
+            .collect();
+
+        assert!(tables.contains(&"credentials".to_string()));
+        assert!(tables.contains(&"audit_log".to_string()));
+        assert!(tables.contains(&"metadata".to_string()));
+    }
+
+    #[test]
+    fn test_schema_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+
+        let version = get_schema_version(&conn).unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_upgrade_from_v2() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "ALTER TABLE credentials DROP COLUMN password_policy;
+             UPDATE metadata SET value = '2' WHERE key = 'schema_version';
+             PRAGMA user_version = 2;",
+        )
+        .unwrap();
+
+        init_schema(&conn).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        conn.prepare("SELECT password_policy FROM credentials").unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_from_v3() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "DROP TABLE sync_state;
+             UPDATE metadata SET value = '3' WHERE key = 'schema_version';
+             PRAGMA user_version = 3;",
+        )
+        .unwrap();
+
+        init_schema(&conn).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        conn.prepare("SELECT remote, remote_hash FROM sync_state").unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_from_v4() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "DROP TABLE key_slots;
+             UPDATE metadata SET value = '4' WHERE key = 'schema_version';
+             PRAGMA user_version = 4;",
+        )
+        .unwrap();
+
+        init_schema(&conn).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        conn.prepare("SELECT label, password_hash, wrapped_dek FROM key_slots").unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_from_v5() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "ALTER TABLE credentials DROP COLUMN env_var;
+             UPDATE metadata SET value = '5' WHERE key = 'schema_version';
+             PRAGMA user_version = 5;",
+        )
+        .unwrap();
+
+        init_schema(&conn).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        conn.prepare("SELECT env_var FROM credentials").unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_from_v6() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "ALTER TABLE credentials DROP COLUMN public_key;
+             UPDATE metadata SET value = '6' WHERE key = 'schema_version';
+             PRAGMA user_version = 6;",
+        )
+        .unwrap();
+
+        init_schema(&conn).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        conn.prepare("SELECT public_key FROM credentials").unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_from_v7() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "ALTER TABLE credentials DROP COLUMN attributes;
+             UPDATE metadata SET value = '7' WHERE key = 'schema_version';
+             PRAGMA user_version = 7;",
+        )
+        .unwrap();
+
+        init_schema(&conn).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        conn.prepare("SELECT attributes FROM credentials").unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_from_v8() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "DROP TABLE secret_history;
+             UPDATE metadata SET value = '8' WHERE key = 'schema_version';
+             PRAGMA user_version = 8;",
+        )
+        .unwrap();
+
+        init_schema(&conn).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        conn.prepare("SELECT encrypted_secret, replaced_at FROM secret_history").unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_from_v9() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_fts_credential(&conn);
+        conn.execute_batch(DROP_FTS).unwrap();
+        conn.execute_batch(
+            "DROP INDEX idx_credentials_alias;
+             ALTER TABLE credentials DROP COLUMN alias;
+             PRAGMA user_version = 9;",
+        )
+        .unwrap();
+        // The search index as it was before aliases
+        let old_fts = FTS_SCHEMA.replace("    alias,\n", "").replace(", alias)", ")").replace(", new.alias", "").replace(", old.alias", "");
+        conn.execute_batch(&old_fts).unwrap();
+        conn.execute("INSERT INTO credentials_fts(credentials_fts) VALUES ('rebuild')", []).unwrap();
+
+        init_schema(&conn).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        conn.execute("UPDATE credentials SET alias = 'gh'", []).unwrap();
+        assert!(fts_search_found(&conn, "gh"));
+        assert!(fts_search_found(&conn, "GitHub"));
+    }
+
+    #[test]
+    fn test_migrations_end_at_schema_version() {
+        let versions: Vec<i32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        assert!(versions.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(versions.last(), Some(&SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn test_version_from_metadata_is_stamped() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute_batch("PRAGMA user_version = 0;").unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+
+        init_schema(&conn).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_refuses_newer_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute_batch(&format!("PRAGMA user_version = {};", SCHEMA_VERSION + 1)).unwrap();
+        assert!(matches!(init_schema(&conn), Err(DbError::NewerSchema { .. })));
+    }
+
+    #[test]
+    fn test_backup_before_migrating() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.db");
+        let conn = Connection::open(&path).unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "ALTER TABLE credentials DROP COLUMN env_var;
+             PRAGMA user_version = 5;",
+        )
+        .unwrap();
+
+        init_schema_with_backup(&conn, Some(&path)).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let backup = Connection::open(dir.path().join("vault.db.v5.bak")).unwrap();
+        assert_eq!(get_schema_version(&backup).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_fts_index() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_fts_credential(&conn);
+        assert!(fts_search_found(&conn, "GitHub"));
+    }
+
+    #[test]
+    fn test_drop_and_create_fts() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        insert_test_fts_credential(&conn);
+
+        drop_fts(&conn).unwrap();
+        assert!(!has_fts(&conn).unwrap());
+        // Writes keep working without the triggers
+        conn.execute("UPDATE credentials SET name = 'GitHub Classic'", []).unwrap();
+
+        create_fts(&conn).unwrap();
+        assert!(has_fts(&conn).unwrap());
+        assert!(fts_search_found(&conn, "Classic"));
+    }
+
+    fn insert_test_fts_credential(conn: &Connection) {
+        conn.execute(
+            r#"INSERT INTO credentials (id, name, credential_type, encrypted_secret, created_at, updated_at)
+            VALUES ('test-1', 'GitHub Token', 'api_key', 'encrypted', datetime('now'), datetime('now'))"#,
+            [],
+        )
+        .unwrap();
+    }
+
+    fn fts_search_found(conn: &Connection, query: &str) -> bool {
+        let sql = "SELECT COUNT(*) > 0 FROM credentials_fts WHERE credentials_fts MATCH ?1";
+        conn.query_row(sql, [query], |row| row.get(0)).unwrap()
+    }
+}
@@ -0,0 +1,471 @@
+//! Search Query Language
+//!
+//! Parses structured searches such as
+//! `tag:work type:apikey user:alice updated:>2024-01-01 unused:365 github`
+//! into filters applied alongside the FTS5 match, and audit log searches
+//! such as `action:copy name:github since:2024-01-01 until:2024-02-01`.
+
+use std::fmt;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::models::{AuditAction, Credential, CredentialType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateOp {
+    Before,
+    OnOrBefore,
+    On,
+    OnOrAfter,
+    After,
+}
+
+impl DateOp {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Before => "<",
+            Self::OnOrBefore => "<=",
+            Self::On => "=",
+            Self::OnOrAfter => ">=",
+            Self::After => ">",
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Self::On => "",
+            _ => self.as_sql(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateFilter {
+    pub op: DateOp,
+    pub date: NaiveDate,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// Free text matched against the FTS index
+    pub text: String,
+    pub tags: Vec<String>,
+    pub types: Vec<CredentialType>,
+    pub user: Option<String>,
+    pub created: Option<DateFilter>,
+    pub updated: Option<DateFilter>,
+    /// Days since the credential was last created, viewed or copied
+    pub unused: Option<u32>,
+}
+
+impl SearchQuery {
+    /// Parse a query string. Unknown `key:value` tokens are treated as text.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut query = Self::default();
+        let mut words: Vec<&str> = Vec::new();
+
+        for token in input.split_whitespace() {
+            let Some((key, value)) = token.split_once(':') else {
+                words.push(token);
+                continue;
+            };
+            if value.is_empty() {
+                return Err(format!("Missing value for '{}:'", key));
+            }
+
+            match key {
+                "tag" | "t" => query.tags.push(value.to_string()),
+                "type" => query.types.push(parse_type(value)?),
+                "user" | "u" => query.user = Some(value.to_string()),
+                "created" => query.created = Some(parse_date_filter(value)?),
+                "updated" => query.updated = Some(parse_date_filter(value)?),
+                "unused" => query.unused = Some(parse_days(value)?),
+                _ => words.push(token),
+            }
+        }
+
+        query.text = words.join(" ");
+        Ok(query)
+    }
+
+    pub fn with_tags(tags: &[String]) -> Self {
+        Self { tags: tags.to_vec(), ..Self::default() }
+    }
+
+    pub fn unused_for(days: u32) -> Self {
+        Self { unused: Some(days), ..Self::default() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.trim().is_empty() && !self.has_filters()
+    }
+
+    /// The free text, if any
+    pub fn free_text(&self) -> Option<&str> {
+        Some(self.text.trim()).filter(|t| !t.is_empty())
+    }
+
+    pub fn has_filters(&self) -> bool {
+        !self.tags.is_empty()
+            || !self.types.is_empty()
+            || self.user.is_some()
+            || self.created.is_some()
+            || self.updated.is_some()
+            || self.unused.is_some()
+    }
+
+    /// Short labels for each active filter, shown in the status line
+    pub fn chips(&self) -> Vec<String> {
+        let mut chips: Vec<String> = Vec::new();
+        chips.extend(self.tags.iter().map(|t| format!("tag:{}", t)));
+        chips.extend(self.types.iter().map(|t| format!("type:{}", t.as_str())));
+        chips.extend(self.user.iter().map(|u| format!("user:{}", u)));
+        chips.extend(self.created.iter().map(|d| format!("created:{}", format_date_filter(d))));
+        chips.extend(self.updated.iter().map(|d| format!("updated:{}", format_date_filter(d))));
+        chips.extend(self.unused.iter().map(|days| format!("unused:{}", days)));
+        if !self.text.trim().is_empty() {
+            chips.push(format!("\"{}\"", self.text.trim()));
+        }
+        chips
+    }
+}
+
+/// Renders the query back into the syntax accepted by `parse`
+impl fmt::Display for SearchQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tokens = self.chips();
+        if !self.text.trim().is_empty() {
+            tokens.pop();
+            tokens.push(self.text.trim().to_string());
+        }
+        write!(f, "{}", tokens.join(" "))
+    }
+}
+
+/// Filters for the audit log screen
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditQuery {
+    /// Free text matched against credential name, username and details
+    pub text: String,
+    pub actions: Vec<AuditAction>,
+    /// Credential name, matched as a substring
+    pub credential: Option<String>,
+    /// First day to include
+    pub since: Option<NaiveDate>,
+    /// Last day to include
+    pub until: Option<NaiveDate>,
+}
+
+impl AuditQuery {
+    /// Parse a query string. Unknown `key:value` tokens are treated as text.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut query = Self::default();
+        let mut words: Vec<&str> = Vec::new();
+
+        for token in input.split_whitespace() {
+            let Some((key, value)) = token.split_once(':') else {
+                words.push(token);
+                continue;
+            };
+            if value.is_empty() {
+                return Err(format!("Missing value for '{}:'", key));
+            }
+
+            match key {
+                "action" | "a" => query.actions.push(parse_action(value)?),
+                "name" | "n" => query.credential = Some(value.to_string()),
+                "since" => query.since = Some(parse_date(value)?),
+                "until" => query.until = Some(parse_date(value)?),
+                _ => words.push(token),
+            }
+        }
+
+        query.text = words.join(" ");
+        Ok(query)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.trim().is_empty()
+            && self.actions.is_empty()
+            && self.credential.is_none()
+            && self.since.is_none()
+            && self.until.is_none()
+    }
+
+    /// Show only the action after the current one, cycling back to all
+    /// actions after the last
+    pub fn cycle_action(&mut self) {
+        let all = AuditAction::all();
+        let next = match self.actions.as_slice() {
+            [] => all.first(),
+            [current] => all.iter().skip_while(|a| *a != current).nth(1),
+            _ => None,
+        };
+        self.actions = next.copied().into_iter().collect();
+    }
+
+    /// Short labels for each active filter, shown in the logs title
+    pub fn chips(&self) -> Vec<String> {
+        let mut chips: Vec<String> = Vec::new();
+        chips.extend(self.actions.iter().map(|a| format!("action:{}", a.as_str())));
+        chips.extend(self.credential.iter().map(|n| format!("name:{}", n)));
+        chips.extend(self.since.iter().map(|d| format!("since:{}", d.format("%Y-%m-%d"))));
+        chips.extend(self.until.iter().map(|d| format!("until:{}", d.format("%Y-%m-%d"))));
+        if !self.text.trim().is_empty() {
+            chips.push(format!("\"{}\"", self.text.trim()));
+        }
+        chips
+    }
+}
+
+/// Renders the query back into the syntax accepted by `parse`
+impl fmt::Display for AuditQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tokens = self.chips();
+        if !self.text.trim().is_empty() {
+            tokens.pop();
+            tokens.push(self.text.trim().to_string());
+        }
+        write!(f, "{}", tokens.join(" "))
+    }
+}
+
+fn parse_action(value: &str) -> Result<AuditAction, String> {
+    let wanted = value.to_lowercase().replace('-', "_");
+    let wanted = if wanted == "failed" { "failed_unlock".to_string() } else { wanted };
+    AuditAction::all()
+        .iter()
+        .copied()
+        .find(|a| a.as_str() == wanted)
+        .ok_or_else(|| format!("Unknown action '{}'", value))
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", value))
+}
+
+fn parse_days(value: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| format!("Invalid day count '{}'", value))
+}
+
+fn parse_type(value: &str) -> Result<CredentialType, String> {
+    let wanted = normalize_type_name(value);
+    CredentialType::all()
+        .iter()
+        .copied()
+        .find(|t| normalize_type_name(t.as_str()) == wanted || normalize_type_name(t.display_name()) == wanted)
+        .ok_or_else(|| format!("Unknown type '{}'", value))
+}
+
+fn normalize_type_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn parse_date_filter(value: &str) -> Result<DateFilter, String> {
+    let (op, date) = split_date_op(value);
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", date))?;
+    Ok(DateFilter { op, date })
+}
+
+fn split_date_op(value: &str) -> (DateOp, &str) {
+    let ops = [
+        (">=", DateOp::OnOrAfter),
+        ("<=", DateOp::OnOrBefore),
+        (">", DateOp::After),
+        ("<", DateOp::Before),
+        ("=", DateOp::On),
+    ];
+    ops.iter()
+        .find_map(|(prefix, op)| value.strip_prefix(prefix).map(|rest| (*op, rest)))
+        .unwrap_or((DateOp::On, value))
+}
+
+fn format_date_filter(filter: &DateFilter) -> String {
+    format!("{}{}", filter.op.symbol(), filter.date.format("%Y-%m-%d"))
+}
+
+/// Order of the credential list. `Name` keeps the query's own order, which
+/// is by name, or by relevance when searching text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Name,
+    /// Most recently updated first
+    Updated,
+    /// Most recently created first
+    Created,
+    /// By type, then name
+    Type,
+}
+
+impl SortOrder {
+    pub const ALL: [Self; 4] = [Self::Name, Self::Updated, Self::Created, Self::Type];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Updated => "updated",
+            Self::Created => "created",
+            Self::Type => "type",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|order| order.as_str().eq_ignore_ascii_case(s.trim()))
+    }
+
+    /// SQL ORDER BY matching [`Self::apply`] on a name-ordered list, with
+    /// the id as a final tie-break so pages never overlap
+    pub fn order_by(&self) -> String {
+        match self {
+            Self::Name => "name, id".to_string(),
+            Self::Updated => "updated_at DESC, name, id".to_string(),
+            Self::Created => "created_at DESC, name, id".to_string(),
+            Self::Type => {
+                let mut types = CredentialType::all().to_vec();
+                types.sort_by_key(|t| t.display_name());
+                let ranks: String = types
+                    .iter()
+                    .enumerate()
+                    .map(|(rank, t)| format!(" WHEN '{}' THEN {}", t.as_str(), rank))
+                    .collect();
+                format!("CASE credential_type{} ELSE {} END, name, id", ranks, types.len())
+            }
+        }
+    }
+
+    pub fn apply(&self, credentials: &mut [Credential]) {
+        match self {
+            Self::Name => {}
+            Self::Updated => credentials.sort_by_key(|c| std::cmp::Reverse(c.updated_at)),
+            Self::Created => credentials.sort_by_key(|c| std::cmp::Reverse(c.created_at)),
+            Self::Type => credentials.sort_by_key(|c| c.credential_type.display_name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_order() {
+        let mut older = Credential::new("b".into(), CredentialType::Password, String::new());
+        older.updated_at -= chrono::Duration::days(1);
+        let newer = Credential::new("a".into(), CredentialType::ApiKey, String::new());
+        let mut credentials = vec![older, newer];
+
+        SortOrder::Updated.apply(&mut credentials);
+        assert_eq!(credentials[0].name, "a");
+        SortOrder::Name.apply(&mut credentials);
+        assert_eq!(credentials[0].name, "a");
+        SortOrder::Type.apply(&mut credentials);
+        assert_eq!(credentials[0].credential_type, CredentialType::ApiKey);
+
+        assert_eq!(SortOrder::parse("Updated"), Some(SortOrder::Updated));
+        assert_eq!(SortOrder::parse("size"), None);
+    }
+
+    #[test]
+    fn test_parse_full_query() {
+        let q = SearchQuery::parse("tag:work type:apikey user:alice updated:>2024-01-01 foo bar").unwrap();
+        assert_eq!(q.tags, vec!["work"]);
+        assert_eq!(q.types, vec![CredentialType::ApiKey]);
+        assert_eq!(q.user.as_deref(), Some("alice"));
+        assert_eq!(
+            q.updated,
+            Some(DateFilter { op: DateOp::After, date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() })
+        );
+        assert_eq!(q.text, "foo bar");
+        assert_eq!(SearchQuery::parse("unused:365").unwrap(), SearchQuery::unused_for(365));
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let q = SearchQuery::parse("github").unwrap();
+        assert_eq!(q.text, "github");
+        assert!(!q.has_filters());
+    }
+
+    #[test]
+    fn test_type_aliases() {
+        for alias in ["apikey", "api_key", "api-key", "API Key"] {
+            assert_eq!(parse_type(alias).unwrap(), CredentialType::ApiKey);
+        }
+        assert!(parse_type("bogus").is_err());
+    }
+
+    #[test]
+    fn test_date_ops() {
+        assert_eq!(parse_date_filter(">=2024-02-01").unwrap().op, DateOp::OnOrAfter);
+        assert_eq!(parse_date_filter("<2024-02-01").unwrap().op, DateOp::Before);
+        assert_eq!(parse_date_filter("2024-02-01").unwrap().op, DateOp::On);
+        assert!(parse_date_filter(">yesterday").is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(SearchQuery::parse("tag:").is_err());
+        assert!(SearchQuery::parse("updated:>2024-13-01").is_err());
+        assert!(SearchQuery::parse("unused:year").is_err());
+    }
+
+    #[test]
+    fn test_unknown_key_is_text() {
+        let q = SearchQuery::parse("https://example.com").unwrap();
+        assert_eq!(q.text, "https://example.com");
+    }
+
+    #[test]
+    fn test_parse_audit_query() {
+        let q = AuditQuery::parse("action:copy a:failed name:github since:2024-01-01 until:2024-02-01 ssh").unwrap();
+        assert_eq!(q.actions, vec![AuditAction::Copy, AuditAction::FailedUnlock]);
+        assert_eq!(q.credential.as_deref(), Some("github"));
+        assert_eq!(q.since, NaiveDate::from_ymd_opt(2024, 1, 1));
+        assert_eq!(q.until, NaiveDate::from_ymd_opt(2024, 2, 1));
+        assert_eq!(q.text, "ssh");
+        assert!(AuditQuery::parse("action:bogus").is_err());
+        assert!(AuditQuery::parse("since:yesterday").is_err());
+    }
+
+    #[test]
+    fn test_cycle_audit_action() {
+        let mut q = AuditQuery::default();
+        q.cycle_action();
+        assert_eq!(q.actions, vec![AuditAction::Create]);
+        q.cycle_action();
+        assert_eq!(q.actions, vec![AuditAction::Read]);
+
+        q.actions = vec![AuditAction::FailedUnlock];
+        q.cycle_action();
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn test_audit_query_roundtrip() {
+        let input = "action:copy name:github since:2024-01-01 ssh key";
+        let q = AuditQuery::parse(input).unwrap();
+        assert_eq!(q.to_string(), input);
+        assert_eq!(AuditQuery::parse(&q.to_string()).unwrap(), q);
+    }
+
+    #[test]
+    fn test_search_query_roundtrip() {
+        let input = "tag:work type:api_key user:alice updated:>2024-01-01 unused:30 foo bar";
+        let q = SearchQuery::parse(input).unwrap();
+        assert_eq!(q.to_string(), input);
+        assert_eq!(SearchQuery::parse(&q.to_string()).unwrap(), q);
+    }
+
+    #[test]
+    fn test_chips() {
+        let q = SearchQuery::parse("tag:work updated:>=2024-01-01 foo").unwrap();
+        assert_eq!(q.chips(), vec!["tag:work", "updated:>=2024-01-01", "\"foo\""]);
+    }
+}
@@ -0,0 +1,44 @@
+//! Vault Core
+//!
+//! Encryption, storage and the vault operations built on them, without
+//! the terminal UI. The `vault` binary is built on this crate; GUI
+//! frontends, backup tools and scripts can use it to work on vault files
+//! directly instead of shelling out.
+//!
+//! - [`vault`]: [`vault::Vault`] opens, unlocks and locks a vault file, and
+//!   the submodules work on an unlocked one: [`vault::credential`] creates,
+//!   reads and edits credentials, [`vault::search`] queries them,
+//!   [`vault::backup`], [`vault::sync`] and [`vault::fsck`] look after the file.
+//! - [`crypto`]: key derivation, the key hierarchy and sealing of blobs.
+//! - [`db`]: the connection, schema and models underneath. Its queries
+//!   are private; reads and writes go through [`vault`].
+//!
+//! ```
+//! use vault_core::db::CredentialType;
+//! use vault_core::vault::credential::{self, NewCredential};
+//! use vault_core::vault::{Vault, VaultConfig};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let dir = tempfile::tempdir()?;
+//! let mut vault = Vault::new(VaultConfig::with_path(dir.path().join("vault.db")));
+//! vault.initialize("correct horse battery staple")?;
+//!
+//! let conn = vault.db()?.conn();
+//! let new = NewCredential {
+//!     username: Some("octocat".to_string()),
+//!     ..NewCredential::new("GitHub".to_string(), CredentialType::Password, "hunter2")
+//! };
+//! let cred = credential::create_credential(conn, vault.dek()?, new)?;
+//! let decrypted = credential::decrypt_fields(vault.dek()?, &cred)?;
+//! assert_eq!(decrypted.username.as_deref(), Some("octocat"));
+//! vault.lock();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The crate follows semantic versioning. While it is at 0.x, a minor
+//! release may change the API; a patch release does not.
+
+pub mod crypto;
+pub mod db;
+pub mod vault;
@@ -0,0 +1,122 @@
+//! Aliases
+//!
+//! A short name a credential can be looked up by, e.g. `gh` for GitHub,
+//! in search and wherever the command line takes a credential name.
+//! Aliases are unique ignoring case and may not be another credential's
+//! name, so a lookup never has two answers. Instead of picking one, a
+//! credential can be given a short word from the passphrase wordlist.
+
+use crate::crypto::memorable_word;
+use crate::db::{self, Credential};
+
+use super::{VaultError, VaultResult};
+
+pub const MAX_LEN: usize = 32;
+
+/// Longest word a memorable alias is picked from
+const MEMORABLE_LEN: usize = 6;
+
+/// Draws before a digit is appended to get a free alias
+const MEMORABLE_TRIES: usize = 20;
+
+/// Why `alias` cannot be used, if it cannot: it has to be typed on a
+/// command line, so only letters, digits, `-`, `_` and `.`
+pub fn check(alias: &str) -> Option<String> {
+    if alias.is_empty() || alias.len() > MAX_LEN {
+        return Some(format!("An alias is 1 to {} characters", MAX_LEN));
+    }
+    if !alias.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+        return Some(format!("'{}' may only contain letters, digits, '-', '_' and '.'", alias));
+    }
+    None
+}
+
+/// The credential with this alias, ignoring case
+pub fn find(conn: &rusqlite::Connection, alias: &str) -> VaultResult<Option<Credential>> {
+    Ok(db::get_credential_by_alias(conn, alias)?)
+}
+
+/// The credential other than `id` that `alias` would collide with, by
+/// its alias or its name
+pub fn collision(conn: &rusqlite::Connection, id: &str, alias: &str) -> VaultResult<Option<Credential>> {
+    if let Some(owner) = db::get_credential_by_alias(conn, alias)?
+        && owner.id != id
+    {
+        return Ok(Some(owner));
+    }
+    Ok(db::get_all_credentials(conn)?
+        .into_iter()
+        .find(|c| c.id != id && c.name.eq_ignore_ascii_case(alias)))
+}
+
+/// Set or clear a credential's alias, refusing one that is taken
+pub fn set_alias(conn: &rusqlite::Connection, cred: &mut Credential, alias: Option<String>) -> VaultResult<()> {
+    if let Some(alias) = &alias {
+        if let Some(problem) = check(alias) {
+            return Err(VaultError::OperationFailed(problem));
+        }
+        if let Some(other) = collision(conn, &cred.id, alias)? {
+            return Err(VaultError::OperationFailed(format!("'{}' is already used by {}", alias, other.name)));
+        }
+    }
+    db::set_credential_alias(conn, &cred.id, alias.as_deref())?;
+    cred.alias = alias;
+    Ok(())
+}
+
+/// A short wordlist word no credential uses as an alias or name
+pub fn suggest(conn: &rusqlite::Connection) -> VaultResult<String> {
+    let all = db::get_all_credentials(conn)?;
+    let taken = |word: &str| {
+        all.iter().any(|c| c.name.eq_ignore_ascii_case(word) || c.alias.as_deref().is_some_and(|a| a.eq_ignore_ascii_case(word)))
+    };
+    let word = || memorable_word(MEMORABLE_LEN).unwrap_or("entry");
+    if let Some(free) = (0..MEMORABLE_TRIES).map(|_| word()).find(|w| !taken(w)) {
+        return Ok(free.to_string());
+    }
+    let base = word();
+    let free = (2..).map(|n| format!("{}{}", base, n)).find(|w| !taken(w));
+    Ok(free.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::DataEncryptionKey;
+    use crate::db::{CredentialType, Database, DatabaseConfig};
+    use crate::vault::credential;
+
+    fn add(conn: &rusqlite::Connection, name: &str) -> Credential {
+        let dek = DataEncryptionKey::from_bytes([0x42u8; 32]);
+        let new = credential::NewCredential::new(name.to_string(), CredentialType::Password, "s");
+        credential::create_credential(conn, &dek, new).unwrap()
+    }
+
+    #[test]
+    fn test_alias_lookup_and_collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(DatabaseConfig::with_path(dir.path().join("vault.db"))).unwrap();
+        let conn = db.conn();
+        let mut github = add(conn, "GitHub");
+        let mut gitlab = add(conn, "GitLab");
+
+        set_alias(conn, &mut github, Some("gh".to_string())).unwrap();
+        assert_eq!(db::get_credential_by_alias(conn, "GH").unwrap().unwrap().id, github.id);
+        assert!(db::search_credentials(conn, "gh").unwrap().iter().any(|c| c.id == github.id));
+
+        // Taken as an alias or as a name, and not typeable
+        assert!(set_alias(conn, &mut gitlab, Some("GH".to_string())).is_err());
+        assert!(set_alias(conn, &mut gitlab, Some("github".to_string())).is_err());
+        assert!(set_alias(conn, &mut gitlab, Some("git lab".to_string())).is_err());
+        assert_eq!(gitlab.alias, None);
+
+        // Setting its own alias again is fine, clearing frees it
+        set_alias(conn, &mut github, Some("GH".to_string())).unwrap();
+        set_alias(conn, &mut github, None).unwrap();
+        set_alias(conn, &mut gitlab, Some("gh".to_string())).unwrap();
+
+        let word = suggest(conn).unwrap();
+        assert!(check(&word).is_none());
+        assert!(collision(conn, "", &word).unwrap().is_none());
+    }
+}
@@ -0,0 +1,536 @@
+//! Audit Trail
+//!
+//! Hash-chained audit logging for tamper detection. Each entry's HMAC
+//! covers its time, its fields and the previous entry's digest, so
+//! editing, deleting or reordering rows breaks the chain. Entries written before the chain existed keep
+//! their per-row HMAC and are sealed by an anchor the chain starts from.
+//! The newest entry is recorded too, under a MAC of its own, so cutting
+//! rows off the end is caught even when the record is rewritten to match.
+
+use std::fmt;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::crypto::DerivedKey;
+use crate::db::{self, AuditAction, AuditLog, AuditQuery};
+
+use super::VaultResult;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// `<first chained id>:<digest over the legacy entries>`
+const ANCHOR_KEY: &str = "audit_chain_anchor";
+/// `<id>:<digest>:<mac>` of the newest chained entry, to catch a
+/// truncated tail
+const HEAD_KEY: &str = "audit_chain_head";
+
+/// Create an audit log entry chained to the previous one
+pub fn log_action(
+    conn: &rusqlite::Connection,
+    audit_key: &DerivedKey,
+    action: AuditAction,
+    credential_id: Option<&str>,
+    credential_name: Option<&str>,
+    username: Option<&str>,
+    details: Option<&str>,
+) -> VaultResult<i64> {
+    let log = AuditLog::new(
+        action,
+        credential_id.map(|s| s.to_string()),
+        credential_name.map(|s| s.to_string()),
+        username.map(|s| s.to_string()),
+        details.map(|s| s.to_string()),
+        String::new(),
+    );
+    in_write(conn, || {
+        let prev = chain_tip(conn, audit_key)?;
+        append(conn, audit_key, &prev, log)
+    })
+}
+
+/// Write `log` as the entry after `prev` and record it as the newest
+fn append(conn: &rusqlite::Connection, audit_key: &DerivedKey, prev: &str, mut log: AuditLog) -> VaultResult<i64> {
+    log.hmac = chained_hmac(audit_key, prev, &log);
+    let id = db::create_audit_log(conn, &log)?;
+    let head = link(id, &log.hmac);
+    db::set_metadata(conn, HEAD_KEY, &format!("{}:{}", head, compute_hmac(audit_key.as_bytes(), &format!("head:{}", head))))?;
+    Ok(id)
+}
+
+/// Run `f` as one write. Alone it takes the write lock up front, so
+/// another process writing the same file cannot chain from the same tip;
+/// inside the caller's transaction it is a savepoint.
+fn in_write<T>(conn: &rusqlite::Connection, f: impl FnOnce() -> VaultResult<T>) -> VaultResult<T> {
+    let (begin, commit, rollback) = match conn.is_autocommit() {
+        true => ("BEGIN IMMEDIATE", "COMMIT", "ROLLBACK"),
+        false => ("SAVEPOINT audit_entry", "RELEASE audit_entry", "ROLLBACK TO audit_entry; RELEASE audit_entry"),
+    };
+    conn.execute_batch(begin)?;
+    let result = f();
+    match &result {
+        Ok(_) => conn.execute_batch(commit)?,
+        Err(_) => {
+            let _ = conn.execute_batch(rollback);
+        }
+    }
+    result
+}
+
+/// Seal the entries written before the chain existed. Runs once, on the
+/// first entry logged after upgrading; returns the anchor digest.
+fn migrate_to_chain(conn: &rusqlite::Connection, audit_key: &DerivedKey) -> VaultResult<String> {
+    if let Some((_, digest)) = read_link(conn, ANCHOR_KEY)? {
+        return Ok(digest);
+    }
+    let legacy = db::get_all_audit_logs(conn)?;
+    let start = legacy.last().map_or(1, |log| log.id + 1);
+    let digest = anchor_digest(audit_key, start, &legacy);
+    db::set_metadata(conn, ANCHOR_KEY, &format!("{}:{}", start, digest))?;
+    Ok(digest)
+}
+
+/// Where the chain breaks, by position in the log (1 = oldest)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainBreak {
+    pub position: usize,
+    pub id: Option<i64>,
+    pub kind: BreakKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakKind {
+    /// A pre-chain entry fails its own HMAC
+    Modified,
+    /// The entry does not follow from its predecessor: it or an earlier
+    /// entry was edited, deleted or moved
+    Broken,
+    /// Pre-chain entries were added, removed or reordered
+    AnchorMismatch,
+    /// The log no longer ends with the newest entry written
+    Truncated,
+    /// The record of the newest entry was not written with the audit key
+    ForgedHead,
+}
+
+impl fmt::Display for ChainBreak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self.kind {
+            BreakKind::Modified => "modified",
+            BreakKind::Broken => "chain broken",
+            BreakKind::AnchorMismatch => "pre-chain entries altered",
+            BreakKind::Truncated => "newest recorded entry missing",
+            BreakKind::ForgedHead => "record of the newest entry forged",
+        };
+        match self.id {
+            Some(id) => write!(f, "{} at entry {} (id {})", what, self.position, id),
+            None => write!(f, "{} after entry {}", what, self.position.saturating_sub(1)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ChainReport {
+    pub total: usize,
+    /// Entries written before the chain was introduced
+    pub legacy: usize,
+    pub breaks: Vec<ChainBreak>,
+}
+
+impl ChainReport {
+    pub fn is_intact(&self) -> bool {
+        self.breaks.is_empty()
+    }
+}
+
+/// Walk the whole log, oldest first, checking every link
+pub fn verify_chain(conn: &rusqlite::Connection, audit_key: &DerivedKey) -> VaultResult<ChainReport> {
+    let logs = db::get_all_audit_logs(conn)?;
+    let mut report = ChainReport { total: logs.len(), ..ChainReport::default() };
+
+    let Some((start, anchor)) = read_link(conn, ANCHOR_KEY)? else {
+        report.legacy = logs.len();
+        report.breaks = verify_legacy(audit_key, &logs);
+        return Ok(report);
+    };
+
+    let split = logs.iter().position(|log| log.id >= start).unwrap_or(logs.len());
+    let (legacy, chained) = logs.split_at(split);
+    report.legacy = legacy.len();
+    report.breaks = verify_legacy(audit_key, legacy);
+    if anchor_digest(audit_key, start, legacy) != anchor {
+        report.breaks.push(ChainBreak { position: 1, id: legacy.first().map(|l| l.id), kind: BreakKind::AnchorMismatch });
+    }
+
+    let mut prev = anchor;
+    for (i, log) in chained.iter().enumerate() {
+        if chained_hmac(audit_key, &prev, log) != log.hmac {
+            let position = split + i + 1;
+            report.breaks.push(ChainBreak { position, id: Some(log.id), kind: BreakKind::Broken });
+        }
+        prev = log.hmac.clone();
+    }
+
+    let tail = chained.last().map(|log| link(log.id, &log.hmac));
+    let kind = match read_head(conn, audit_key)? {
+        Head::Signed(head) if tail.as_ref() == Some(&head) => None,
+        Head::Signed(_) => Some(BreakKind::Truncated),
+        Head::Missing if chained.is_empty() => None,
+        Head::Missing => Some(BreakKind::Truncated),
+        Head::Forged(_) => Some(BreakKind::ForgedHead),
+    };
+    if let Some(kind) = kind {
+        report.breaks.push(ChainBreak { position: logs.len() + 1, id: None, kind });
+    }
+
+    Ok(report)
+}
+
+/// Get the most recent audit logs matching a query
+pub fn search_logs(conn: &rusqlite::Connection, query: &AuditQuery, limit: usize) -> VaultResult<Vec<AuditLog>> {
+    Ok(db::search_audit_logs(conn, query, limit)?)
+}
+
+/// Get every audit log, oldest first
+pub fn get_all_logs(conn: &rusqlite::Connection) -> VaultResult<Vec<AuditLog>> {
+    Ok(db::get_all_audit_logs(conn)?)
+}
+
+/// Get audit logs for a specific credential
+pub fn get_credential_logs(conn: &rusqlite::Connection, credential_id: &str) -> VaultResult<Vec<AuditLog>> {
+    Ok(db::get_credential_audit_logs(conn, credential_id)?)
+}
+
+/// Digest the next entry chains from: the newest entry, or the anchor
+/// when nothing has been chained yet
+fn chain_tip(conn: &rusqlite::Connection, audit_key: &DerivedKey) -> VaultResult<String> {
+    match read_head(conn, audit_key)? {
+        Head::Signed(head) => Ok(digest_of(&head).to_string()),
+        // Chaining on from a record that was not ours would hide the rows
+        // it was rewritten to cut, so the next entry is left unlinked
+        Head::Forged(head) => Ok(format!("forged:{}", head)),
+        Head::Missing => migrate_to_chain(conn, audit_key),
+    }
+}
+
+enum Head {
+    /// `<id>:<digest>`, under a valid MAC
+    Signed(String),
+    /// Not written with the audit key
+    Forged(String),
+    Missing,
+}
+
+fn read_head(conn: &rusqlite::Connection, audit_key: &DerivedKey) -> VaultResult<Head> {
+    let Some(value) = db::get_metadata(conn, HEAD_KEY)? else { return Ok(Head::Missing) };
+    let head = match value.rsplit_once(':') {
+        Some((head, mac)) if head.contains(':') && compute_hmac(audit_key.as_bytes(), &format!("head:{}", head)) == mac => {
+            Head::Signed(head.to_string())
+        }
+        _ => Head::Forged(value),
+    };
+    Ok(head)
+}
+
+fn link(id: i64, digest: &str) -> String {
+    format!("{}:{}", id, digest)
+}
+
+fn digest_of(link: &str) -> &str {
+    link.split_once(':').map_or(link, |(_, digest)| digest)
+}
+
+fn read_link(conn: &rusqlite::Connection, key: &str) -> VaultResult<Option<(i64, String)>> {
+    let link = db::get_metadata(conn, key)?.and_then(|value| {
+        let (id, digest) = value.split_once(':')?;
+        Some((id.parse().ok()?, digest.to_string()))
+    });
+    Ok(link)
+}
+
+fn verify_legacy(audit_key: &DerivedKey, logs: &[AuditLog]) -> Vec<ChainBreak> {
+    logs.iter()
+        .enumerate()
+        .filter(|(_, log)| compute_hmac(audit_key.as_bytes(), &entry_message(log)) != log.hmac)
+        .map(|(i, log)| ChainBreak { position: i + 1, id: Some(log.id), kind: BreakKind::Modified })
+        .collect()
+}
+
+fn anchor_digest(audit_key: &DerivedKey, start: i64, legacy: &[AuditLog]) -> String {
+    let mut message = format!("anchor:{}", start);
+    for log in legacy {
+        message.push_str(&format!("|{}:{}", log.id, log.hmac));
+    }
+    compute_hmac(audit_key.as_bytes(), &message)
+}
+
+/// The HMAC of a chained entry, over the previous digest and every
+/// field shown, each prefixed with its length so no two entries sign alike
+fn chained_hmac(audit_key: &DerivedKey, prev: &str, log: &AuditLog) -> String {
+    let timestamp = log.timestamp.with_timezone(&Utc).to_rfc3339();
+    let fields = [
+        Some(prev),
+        Some(timestamp.as_str()),
+        Some(log.action.as_str()),
+        log.credential_id.as_deref(),
+        log.credential_name.as_deref(),
+        log.username.as_deref(),
+        log.details.as_deref(),
+    ];
+    let message: Vec<String> = fields
+        .iter()
+        .map(|field| match field {
+            Some(value) => format!("{}:{}", value.len(), value),
+            None => "-".to_string(),
+        })
+        .collect();
+    compute_hmac(audit_key.as_bytes(), &message.concat())
+}
+
+/// The signed fields of a pre-chain entry, kept to verify those
+fn entry_message(log: &AuditLog) -> String {
+    format!(
+        "{}:{}:{}:{}:{}",
+        log.action.as_str(),
+        log.credential_id.as_deref().unwrap_or(""),
+        log.credential_name.as_deref().unwrap_or(""),
+        log.username.as_deref().unwrap_or(""),
+        log.details.as_deref().unwrap_or(""),
+    )
+}
+
+fn compute_hmac(key: &[u8], message: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(key)
+        .expect("HMAC can take key of any size");
+    mac.update(message.as_bytes());
+    let result = mac.finalize();
+    hex::encode(result.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{CryptoResult, MasterKey};
+    use crate::crypto::key_hierarchy::KeyHierarchy;
+    use crate::db::Database;
+
+    fn test_audit_key() -> CryptoResult<DerivedKey> {
+        let master = MasterKey::from_bytes([0x42u8; 32]);
+        let hierarchy = KeyHierarchy::new(master)?;
+        hierarchy.derive_audit_key()
+    }
+
+    #[test]
+    fn test_log_action() -> CryptoResult<()> {
+        let db = Database::open_in_memory().unwrap();
+        let key = test_audit_key()?;
+
+        let id = log_action(
+            db.conn(),
+            &key,
+            AuditAction::Create,
+            Some("cred-123"),
+            Some("GitHub Token"),
+            Some("user@example.com"),
+            Some("Created new credential"),
+        )
+        .unwrap();
+
+        assert!(id > 0);
+
+        let logs = search_logs(db.conn(), &AuditQuery::default(), 10).unwrap();
+        assert!(!logs.is_empty());
+        assert_eq!(logs[0].credential_name.as_deref(), Some("GitHub Token"));
+        assert_eq!(logs[0].username.as_deref(), Some("user@example.com"));
+
+        Ok(())
+    }
+
+    fn log(conn: &rusqlite::Connection, key: &DerivedKey, action: AuditAction, name: &str) -> i64 {
+        log_action(conn, key, action, None, Some(name), None, None).unwrap()
+    }
+
+    fn break_kinds(report: &ChainReport) -> Vec<(usize, BreakKind)> {
+        report.breaks.iter().map(|b| (b.position, b.kind)).collect()
+    }
+
+    #[test]
+    fn test_verify_chain() -> CryptoResult<()> {
+        let db = Database::open_in_memory().unwrap();
+        let key = test_audit_key()?;
+
+        log(db.conn(), &key, AuditAction::Unlock, "");
+        log(db.conn(), &key, AuditAction::Read, "AWS Key");
+        log(db.conn(), &key, AuditAction::Lock, "");
+
+        let report = verify_chain(db.conn(), &key).unwrap();
+        assert!(report.is_intact());
+        assert_eq!((report.total, report.legacy), (3, 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tampered_log_fails_verification() -> CryptoResult<()> {
+        let db = Database::open_in_memory().unwrap();
+        let key = test_audit_key()?;
+
+        log(db.conn(), &key, AuditAction::Copy, "Secret Key");
+        let id = log(db.conn(), &key, AuditAction::Update, "Original Name");
+        log(db.conn(), &key, AuditAction::Lock, "");
+        db.conn().execute("UPDATE audit_log SET credential_name = 'Tampered' WHERE id = ?1", [id]).unwrap();
+
+        let report = verify_chain(db.conn(), &key).unwrap();
+        assert_eq!(break_kinds(&report), vec![(2, BreakKind::Broken)]);
+        assert_eq!(report.breaks[0].to_string(), format!("chain broken at entry 2 (id {})", id));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewritten_time_and_shifted_fields_fail_verification() -> CryptoResult<()> {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let key = test_audit_key()?;
+
+        let id = log_action(conn, &key, AuditAction::Read, Some("id"), Some("a:b"), Some(""), None).unwrap();
+        conn.execute("UPDATE audit_log SET timestamp = '2020-01-01T00:00:00+00:00' WHERE id = ?1", [id]).unwrap();
+        assert_eq!(break_kinds(&verify_chain(conn, &key).unwrap()), vec![(1, BreakKind::Broken)]);
+
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let id = log_action(conn, &key, AuditAction::Read, Some("id"), Some("a:b"), Some(""), None).unwrap();
+        conn.execute("UPDATE audit_log SET credential_name = 'a', username = vault_seal(credential_id, 'audit username', 'b:') WHERE id = ?1", [id]).unwrap();
+        assert_eq!(break_kinds(&verify_chain(conn, &key).unwrap()), vec![(1, BreakKind::Broken)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deleted_and_reordered_logs_fail_verification() -> CryptoResult<()> {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let key = test_audit_key()?;
+
+        let ids: Vec<i64> = ["a", "b", "c", "d"].iter().map(|n| log(conn, &key, AuditAction::Read, n)).collect();
+        conn.execute("DELETE FROM audit_log WHERE id = ?1", [ids[1]]).unwrap();
+        assert_eq!(break_kinds(&verify_chain(conn, &key).unwrap()), vec![(2, BreakKind::Broken)]);
+
+        // Swap the contents of the last two rows
+        conn.execute_batch(&format!(
+            "UPDATE audit_log SET id = -1 WHERE id = {c};
+             UPDATE audit_log SET id = {c} WHERE id = {d};
+             UPDATE audit_log SET id = {d} WHERE id = -1;",
+            c = ids[2],
+            d = ids[3],
+        ))
+        .unwrap();
+        let kinds = break_kinds(&verify_chain(conn, &key).unwrap());
+        assert!(kinds.contains(&(3, BreakKind::Broken)));
+        assert!(kinds.contains(&(4, BreakKind::Truncated)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_log_fails_verification() -> CryptoResult<()> {
+        let db = Database::open_in_memory().unwrap();
+        let key = test_audit_key()?;
+
+        log(db.conn(), &key, AuditAction::Unlock, "");
+        let last = log(db.conn(), &key, AuditAction::Delete, "Test");
+        db.conn().execute("DELETE FROM audit_log WHERE id = ?1", [last]).unwrap();
+
+        assert_eq!(break_kinds(&verify_chain(db.conn(), &key).unwrap()), vec![(2, BreakKind::Truncated)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewritten_head_fails_verification() -> CryptoResult<()> {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let key = test_audit_key()?;
+
+        log(conn, &key, AuditAction::Unlock, "");
+        let kept = log(conn, &key, AuditAction::Read, "AWS Key");
+        let cut = log(conn, &key, AuditAction::Export, "");
+        conn.execute("DELETE FROM audit_log WHERE id = ?1", [cut]).unwrap();
+        let hmac: String = conn.query_row("SELECT hmac FROM audit_log WHERE id = ?1", [kept], |row| row.get(0)).unwrap();
+        db::set_metadata(conn, HEAD_KEY, &format!("{}:{}", kept, hmac)).unwrap();
+        assert_eq!(break_kinds(&verify_chain(conn, &key).unwrap()), vec![(3, BreakKind::ForgedHead)]);
+
+        // Writing on does not paper over it
+        log(conn, &key, AuditAction::Lock, "");
+        assert_eq!(break_kinds(&verify_chain(conn, &key).unwrap()), vec![(3, BreakKind::Broken)]);
+
+        // Nor does dropping the record altogether
+        conn.execute("DELETE FROM metadata WHERE key = ?1", [HEAD_KEY]).unwrap();
+        assert!(break_kinds(&verify_chain(conn, &key).unwrap()).contains(&(4, BreakKind::Truncated)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_action_joins_transaction() -> CryptoResult<()> {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let key = test_audit_key()?;
+
+        log(conn, &key, AuditAction::Unlock, "");
+        let tx = conn.unchecked_transaction().unwrap();
+        log(&tx, &key, AuditAction::Delete, "Test");
+        drop(tx);
+        assert_eq!(search_logs(conn, &AuditQuery::default(), 10).unwrap().len(), 1);
+        assert!(verify_chain(conn, &key).unwrap().is_intact());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_key_fails_verification() -> CryptoResult<()> {
+        let db = Database::open_in_memory().unwrap();
+        let key1 = test_audit_key()?;
+
+        let master2 = MasterKey::from_bytes([0x43u8; 32]);
+        let hierarchy2 = KeyHierarchy::new(master2).unwrap();
+        let key2 = hierarchy2.derive_audit_key()?;
+
+        log(db.conn(), &key1, AuditAction::Delete, "Test");
+
+        assert!(!verify_chain(db.conn(), &key2).unwrap().is_intact());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_logs_are_sealed() -> CryptoResult<()> {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let key = test_audit_key()?;
+
+        // Entries as written before the chain: each HMAC covers only its own row
+        let mut legacy_ids = Vec::new();
+        for name in ["GitHub", "AWS"] {
+            let mut entry = AuditLog::new(AuditAction::Read, None, Some(name.to_string()), None, None, String::new());
+            entry.hmac = compute_hmac(key.as_bytes(), &entry_message(&entry));
+            legacy_ids.push(db::create_audit_log(conn, &entry).unwrap());
+        }
+        assert!(verify_chain(conn, &key).unwrap().is_intact());
+
+        log(conn, &key, AuditAction::Unlock, "");
+        let report = verify_chain(conn, &key).unwrap();
+        assert!(report.is_intact());
+        assert_eq!((report.total, report.legacy), (3, 2));
+
+        conn.execute("UPDATE audit_log SET credential_name = 'GitLab' WHERE id = ?1", [legacy_ids[0]]).unwrap();
+        assert_eq!(break_kinds(&verify_chain(conn, &key).unwrap()), vec![(1, BreakKind::Modified)]);
+
+        conn.execute("DELETE FROM audit_log WHERE id = ?1", [legacy_ids[1]]).unwrap();
+        let kinds = break_kinds(&verify_chain(conn, &key).unwrap());
+        assert!(kinds.contains(&(1, BreakKind::AnchorMismatch)));
+
+        Ok(())
+    }
+}
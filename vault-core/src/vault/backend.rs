@@ -0,0 +1,189 @@
+//! Secret Backends
+//!
+//! A credential can leave its secret in another store, such as `pass`, an
+//! HSM or an organisation's secrets server, and keep only its name,
+//! username, URL and tags here to be browsed and searched. Its `backend`
+//! attribute names the store, and its secret, encrypted like any other,
+//! is the store's reference to the real one. Resolving the credential
+//! asks the store's driver for the secret; callers audit each fetch.
+
+use std::collections::BTreeMap;
+use std::process::Command;
+
+use secrecy::{ExposeSecret, SecretString};
+use zeroize::Zeroizing;
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::models::SECRET_BACKEND;
+use crate::db::Credential;
+
+use super::credential::{decrypt_credential, DecryptedCredential};
+use super::{VaultError, VaultResult};
+
+/// Fetches secrets kept outside the vault
+pub trait SecretBackend {
+    /// What credentials name in their `backend` attribute
+    fn name(&self) -> &str;
+
+    /// The secret `reference` points to
+    fn fetch(&self, reference: &str) -> VaultResult<SecretString>;
+}
+
+/// Runs a command with `{}` in its arguments replaced by the reference,
+/// taking the first line it prints, e.g. `pass show {}` or
+/// `vault kv get -field=password {}`
+pub struct CommandBackend {
+    name: String,
+    command: Vec<String>,
+}
+
+impl CommandBackend {
+    pub fn new(name: impl Into<String>, command: Vec<String>) -> Self {
+        Self { name: name.into(), command }
+    }
+}
+
+impl SecretBackend for CommandBackend {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn fetch(&self, reference: &str) -> VaultResult<SecretString> {
+        let (program, args) = self
+            .command
+            .split_first()
+            .ok_or_else(|| VaultError::OperationFailed(format!("Backend '{}' has no command", self.name)))?;
+        let output = Command::new(program)
+            .args(args.iter().map(|arg| arg.replace("{}", reference)))
+            .output()
+            .map_err(|e| VaultError::OperationFailed(format!("Failed to run {}: {}", program, e)))?;
+        let stdout = Zeroizing::new(output.stdout);
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(VaultError::OperationFailed(format!("{} failed: {}", program, stderr.trim())));
+        }
+        let text = std::str::from_utf8(&stdout)
+            .map_err(|_| VaultError::OperationFailed(format!("{} printed a secret that is not UTF-8", program)))?;
+        match text.lines().next() {
+            Some(line) if !line.is_empty() => Ok(SecretString::from(line.to_string())),
+            _ => Err(VaultError::OperationFailed(format!("{} printed no secret for '{}'", program, reference))),
+        }
+    }
+}
+
+/// The configured backends, by name
+#[derive(Default)]
+pub struct Backends {
+    drivers: BTreeMap<String, Box<dyn SecretBackend + Send + Sync>>,
+}
+
+impl Backends {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A [`CommandBackend`] for each named command
+    pub fn from_commands(commands: &BTreeMap<String, Vec<String>>) -> Self {
+        let mut backends = Self::new();
+        for (name, command) in commands {
+            backends.register(Box::new(CommandBackend::new(name, command.clone())));
+        }
+        backends
+    }
+
+    pub fn register(&mut self, backend: Box<dyn SecretBackend + Send + Sync>) {
+        self.drivers.insert(backend.name().to_string(), backend);
+    }
+
+    /// Replace a credential's stored reference with the secret its backend
+    /// holds. Returns the backend's name, or `None` for a credential whose
+    /// secret is in the vault.
+    pub fn resolve(&self, cred: &mut DecryptedCredential) -> VaultResult<Option<String>> {
+        let Some(name) = cred.attributes.get(SECRET_BACKEND) else { return Ok(None) };
+        let backend = self
+            .drivers
+            .get(name)
+            .ok_or_else(|| VaultError::OperationFailed(format!("'{}' is kept in backend '{}', which is not configured", cred.name, name)))?;
+        let reference = cred.secret.as_ref().map(|s| s.expose_secret().trim().to_string()).unwrap_or_default();
+        if reference.is_empty() {
+            return Err(VaultError::OperationFailed(format!("'{}' has no reference into '{}'", cred.name, name)));
+        }
+        cred.secret = Some(backend.fetch(&reference)?);
+        Ok(Some(name.clone()))
+    }
+}
+
+/// Decrypt a credential with its secret fetched from its backend, and
+/// the backend's name for the audit entry
+pub fn decrypt_resolved(
+    conn: &rusqlite::Connection,
+    dek: &DataEncryptionKey,
+    backends: &Backends,
+    cred: &Credential,
+) -> VaultResult<(DecryptedCredential, Option<String>)> {
+    let mut decrypted = decrypt_credential(conn, dek, cred, true)?;
+    let backend = backends.resolve(&mut decrypted)?;
+    Ok((decrypted, backend))
+}
+
+/// Audit details noting where the secret was fetched from
+pub fn audit_details(details: &str, backend: Option<&str>) -> String {
+    match backend {
+        Some(backend) => format!("{} (fetched from {})", details, backend),
+        None => details.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::CredentialType;
+
+    struct Fixed;
+
+    impl SecretBackend for Fixed {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn fetch(&self, reference: &str) -> VaultResult<SecretString> {
+            Ok(SecretString::from(format!("secret of {}", reference)))
+        }
+    }
+
+    fn decrypted(secret: &str, backend: Option<&str>) -> DecryptedCredential {
+        let mut cred = Credential::new("GitHub".to_string(), CredentialType::Password, String::new());
+        if let Some(backend) = backend {
+            cred.attributes.insert(SECRET_BACKEND.to_string(), backend.to_string());
+        }
+        DecryptedCredential::from_credential(&cred, Some(secret.to_string()), None)
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut backends = Backends::new();
+        backends.register(Box::new(Fixed));
+
+        let mut local = decrypted("hunter2", None);
+        assert_eq!(backends.resolve(&mut local).unwrap(), None);
+        assert_eq!(local.secret.unwrap().expose_secret(), "hunter2");
+
+        let mut remote = decrypted("web/github", Some("fixed"));
+        assert_eq!(backends.resolve(&mut remote).unwrap().as_deref(), Some("fixed"));
+        assert_eq!(remote.secret.unwrap().expose_secret(), "secret of web/github");
+
+        let mut unknown = decrypted("web/github", Some("hsm"));
+        assert!(backends.resolve(&mut unknown).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_backend() {
+        let command = ["sh", "-c", "printf 'pw-%s\\nuser: me\\n' \"$0\"", "{}"];
+        let backend = CommandBackend::new("sh", command.iter().map(|s| s.to_string()).collect());
+        assert_eq!(backend.fetch("web/github").unwrap().expose_secret(), "pw-web/github");
+
+        let failing = CommandBackend::new("sh", vec!["sh".into(), "-c".into(), "echo gone >&2; exit 1".into()]);
+        assert!(failing.fetch("x").unwrap_err().to_string().contains("gone"));
+    }
+}
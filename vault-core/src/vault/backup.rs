@@ -0,0 +1,305 @@
+//! Encrypted Backups
+//!
+//! Timestamped snapshots of the vault database, encrypted with the DEK.
+//! The DEK survives password changes, so any backup can be restored from
+//! the running session; the restored file keeps the master password that
+//! was current when the backup was taken. A backup can also be opened
+//! read-only next to the live vault, to copy a single old secret out of
+//! it without restoring the whole file.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use rusqlite::DatabaseName;
+
+use crate::crypto::{decrypt_bytes, encrypt_bytes, DataEncryptionKey};
+use crate::db::{self, Database, DatabaseConfig};
+
+use super::{VaultError, VaultResult};
+
+const PREFIX: &str = "vault-";
+const EXTENSION: &str = ".bak";
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3f";
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+/// Directory under the backup directory that opened backups are
+/// decrypted into
+const OPEN_DIR: &str = ".open";
+
+#[derive(Debug, Clone)]
+pub struct BackupEntry {
+    pub path: PathBuf,
+    pub created: DateTime<Local>,
+    /// What triggered the backup, e.g. "delete" or "scheduled"
+    pub reason: String,
+    pub size: u64,
+}
+
+/// Snapshot the database, encrypt it and write it to `dir`
+pub fn create_backup(
+    conn: &rusqlite::Connection,
+    dek: &DataEncryptionKey,
+    dir: &Path,
+    reason: &str,
+) -> VaultResult<PathBuf> {
+    let path = dir.join(backup_file_name(Local::now(), reason));
+    export_backup(conn, dek, &path)?;
+    Ok(path)
+}
+
+/// Snapshot the database and write it, encrypted, to `path`
+pub fn export_backup(conn: &rusqlite::Connection, dek: &DataEncryptionKey, path: &Path) -> VaultResult<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(dir).map_err(io_error)?;
+    let snapshot = snapshot_bytes(conn, dir)?;
+    let encrypted = encrypt_bytes(dek.as_ref(), &snapshot).map_err(|e| VaultError::CryptoError(e.to_string()))?;
+    write_private(path, encrypted.as_bytes())
+}
+
+/// All backups in `dir`, newest first
+pub fn list_backups(dir: &Path) -> VaultResult<Vec<BackupEntry>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut entries: Vec<BackupEntry> = std::fs::read_dir(dir)
+        .map_err(io_error)?
+        .filter_map(Result::ok)
+        .filter_map(|entry| parse_entry(&entry.path()))
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.created));
+    Ok(entries)
+}
+
+/// Delete all but the newest `keep` backups, returning how many were removed
+pub fn prune_backups(dir: &Path, keep: usize) -> VaultResult<usize> {
+    let stale = list_backups(dir)?.into_iter().skip(keep).collect::<Vec<_>>();
+    for entry in &stale {
+        std::fs::remove_file(&entry.path).map_err(io_error)?;
+    }
+    Ok(stale.len())
+}
+
+/// Decrypt a backup, checking that it holds a database
+pub fn read_backup(dek: &DataEncryptionKey, backup: &Path) -> VaultResult<Vec<u8>> {
+    let contents = std::fs::read_to_string(backup).map_err(io_error)?;
+    let snapshot = decrypt_bytes(dek.as_ref(), &contents).map_err(|e| VaultError::CryptoError(e.to_string()))?;
+    if !snapshot.starts_with(SQLITE_HEADER) {
+        return Err(VaultError::OperationFailed("Backup is not a vault database".into()));
+    }
+    Ok(snapshot)
+}
+
+/// A backup decrypted to a private file and opened read-only. The file
+/// is deleted when this is dropped.
+pub struct OpenedBackup {
+    db: Option<Database>,
+    dir: PathBuf,
+    /// The backup it was decrypted from
+    pub source: PathBuf,
+}
+
+impl OpenedBackup {
+    pub fn db(&self) -> &Database {
+        self.db.as_ref().expect("database is open until drop")
+    }
+}
+
+impl Drop for OpenedBackup {
+    fn drop(&mut self) {
+        self.db = None;
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Decrypt a backup or export into a private directory under `dir` and
+/// open it read-only. Backups from an older schema are migrated first,
+/// on the decrypted copy.
+pub fn open_backup(dek: &DataEncryptionKey, backup: &Path, dir: &Path) -> VaultResult<OpenedBackup> {
+    let snapshot = read_backup(dek, backup)?;
+    let staging = dir.join(OPEN_DIR).join(std::process::id().to_string());
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::create_dir_all(&staging).map_err(io_error)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staging, std::fs::Permissions::from_mode(0o700)).map_err(io_error)?;
+    }
+    let mut opened = OpenedBackup { db: None, dir: staging, source: backup.to_path_buf() };
+
+    let path = opened.dir.join("vault.db");
+    write_private(&path, &snapshot)?;
+    drop(Database::open(DatabaseConfig::with_path(&path))?);
+    let db = Database::open(DatabaseConfig { read_only: true, ..DatabaseConfig::with_path(&path) })?;
+    db::fields::install(db.conn(), Some(dek), false)?;
+    opened.db = Some(db);
+    Ok(opened)
+}
+
+/// Swap a decrypted snapshot in for the vault file. The vault must be
+/// locked so no connection holds the old file open.
+pub fn replace_vault_file(snapshot: &[u8], vault_path: &Path) -> VaultResult<()> {
+    let staging = sibling_path(vault_path, ".restore");
+    write_private(&staging, snapshot)?;
+    std::fs::rename(&staging, vault_path).map_err(io_error)?;
+
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(sibling_path(vault_path, suffix));
+    }
+    Ok(())
+}
+
+/// Copy the live database through SQLite's backup API so the snapshot
+/// is consistent even with a WAL in use
+pub(super) fn snapshot_bytes(conn: &rusqlite::Connection, dir: &Path) -> VaultResult<Vec<u8>> {
+    let staging = dir.join(".snapshot");
+    let result = conn
+        .backup(DatabaseName::Main, &staging, None)
+        .map_err(VaultError::from)
+        .and_then(|_| std::fs::read(&staging).map_err(io_error));
+    let _ = std::fs::remove_file(&staging);
+    result
+}
+
+fn backup_file_name(created: DateTime<Local>, reason: &str) -> String {
+    format!("{}{}-{}{}", PREFIX, created.format(TIMESTAMP_FORMAT), reason, EXTENSION)
+}
+
+fn parse_entry(path: &Path) -> Option<BackupEntry> {
+    let name = path.file_name()?.to_str()?;
+    let stem = name.strip_prefix(PREFIX)?.strip_suffix(EXTENSION)?;
+    let (timestamp, reason) = stem.split_once('-')?;
+    let naive = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+    let created = Local.from_local_datetime(&naive).single()?;
+    let size = std::fs::metadata(path).ok()?.len();
+    Some(BackupEntry { path: path.to_path_buf(), created, reason: reason.to_string(), size })
+}
+
+pub(super) fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+pub(super) fn write_private(path: &Path, contents: &[u8]) -> VaultResult<()> {
+    std::fs::write(path, contents).map_err(io_error)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).map_err(io_error)?;
+    }
+    Ok(())
+}
+
+pub(super) fn io_error(e: std::io::Error) -> VaultError {
+    VaultError::IoError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{CredentialType, Database, DatabaseConfig};
+    use crate::vault::credential;
+
+    fn test_dek() -> DataEncryptionKey {
+        DataEncryptionKey::from_bytes([0x42u8; 32])
+    }
+
+    fn add_credential(db: &Database, name: &str) {
+        credential::create_credential(
+            db.conn(),
+            &test_dek(),
+            credential::NewCredential::new(name.to_string(), CredentialType::Password, "secret"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault_path = dir.path().join("vault.db");
+        let backups = dir.path().join("backups");
+
+        let db = Database::open(DatabaseConfig::with_path(&vault_path)).unwrap();
+        add_credential(&db, "GitHub");
+        let backup = create_backup(db.conn(), &test_dek(), &backups, "delete").unwrap();
+        add_credential(&db, "GitLab");
+        drop(db);
+
+        let raw = std::fs::read(&backup).unwrap();
+        assert!(!raw.windows(6).any(|w| w == b"GitHub"));
+
+        let snapshot = read_backup(&test_dek(), &backup).unwrap();
+        replace_vault_file(&snapshot, &vault_path).unwrap();
+        let db = Database::open(DatabaseConfig::with_path(&vault_path)).unwrap();
+        let names: Vec<_> = crate::db::get_all_credentials(db.conn()).unwrap().into_iter().map(|c| c.name).collect();
+        assert_eq!(names, vec!["GitHub"]);
+    }
+
+    #[test]
+    fn test_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open_in_memory().unwrap();
+        add_credential(&db, "GitHub");
+        let path = dir.path().join("exports").join("vault.export");
+        export_backup(db.conn(), &test_dek(), &path).unwrap();
+
+        assert!(read_backup(&test_dek(), &path).unwrap().starts_with(SQLITE_HEADER));
+        assert!(list_backups(&dir.path().join("exports")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_open_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(DatabaseConfig::with_path(dir.path().join("vault.db"))).unwrap();
+        add_credential(&db, "GitHub");
+        let backup = create_backup(db.conn(), &test_dek(), dir.path(), "manual").unwrap();
+        add_credential(&db, "GitLab");
+
+        let opened = open_backup(&test_dek(), &backup, dir.path()).unwrap();
+        let names: Vec<_> = crate::db::get_all_credentials(opened.db().conn()).unwrap().into_iter().map(|c| c.name).collect();
+        assert_eq!(names, vec!["GitHub"]);
+        assert!(opened.db().is_read_only());
+        let write = credential::create_credential(
+            opened.db().conn(),
+            &test_dek(),
+            credential::NewCredential::new("GitLab".to_string(), CredentialType::Password, "s"),
+        );
+        assert!(matches!(write, Err(VaultError::ReadOnly)));
+        assert_eq!(opened.source, backup);
+
+        let staging = opened.dir.clone();
+        drop(opened);
+        assert!(!staging.exists());
+        assert_eq!(list_backups(dir.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_restore_rejects_wrong_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open_in_memory().unwrap();
+        let backup = create_backup(db.conn(), &test_dek(), dir.path(), "manual").unwrap();
+
+        let other = DataEncryptionKey::from_bytes([0x07u8; 32]);
+        assert!(read_backup(&other, &backup).is_err());
+    }
+
+    #[test]
+    fn test_list_and_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = Local.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+        for (i, reason) in ["scheduled", "delete", "passwd"].iter().enumerate() {
+            let created = base + chrono::Duration::minutes(i as i64);
+            std::fs::write(dir.path().join(backup_file_name(created, reason)), "x").unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let entries = list_backups(dir.path()).unwrap();
+        let reasons: Vec<_> = entries.iter().map(|e| e.reason.as_str()).collect();
+        assert_eq!(reasons, vec!["passwd", "delete", "scheduled"]);
+
+        assert_eq!(prune_backups(dir.path(), 2).unwrap(), 1);
+        assert_eq!(list_backups(dir.path()).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,130 @@
+//! Benchmark Workloads
+//!
+//! The operations whose speed users notice: deriving the master key,
+//! encrypting and decrypting every secret, searching, and reloading the
+//! list. The criterion benchmarks and `vault bench` both run these, so a
+//! number from a user's machine compares with one from CI.
+
+use std::time::{Duration, Instant};
+
+use crate::crypto::{self, encryption::EncryptedBlob, DataEncryptionKey, KdfParams};
+use crate::db::{self, CredentialType, Database, SortOrder};
+
+use super::{credential, search, VaultError, VaultResult};
+
+/// Vault size the credential workloads are measured at by default
+pub const DEFAULT_COUNT: usize = 10_000;
+
+/// Rows the list loads around the cursor, as the TUI does on refresh
+pub const PAGE_SIZE: usize = 512;
+
+const PASSWORD: &[u8] = b"correct horse battery staple";
+
+const SERVICES: &[&str] = &[
+    "github", "gitlab", "aws", "gmail", "bank", "netflix", "slack", "jira", "docker", "npm", "stripe", "azure",
+];
+
+const TAGS: &[&str] = &["work", "personal", "finance", "dev", "shared"];
+
+/// Argon2 settings worth comparing: the default and two stronger ones
+pub fn kdf_presets() -> Vec<(&'static str, KdfParams)> {
+    let params = |memory_cost, time_cost, parallelism| KdfParams { memory_cost, time_cost, parallelism, output_len: 32 };
+    vec![
+        ("19 MiB, t=2 (default)", KdfParams::default()),
+        ("64 MiB, t=3", params(65536, 3, 1)),
+        ("256 MiB, t=4, p=4", params(262144, 4, 4)),
+    ]
+}
+
+pub fn derive_key(params: &KdfParams) -> VaultResult<()> {
+    crypto::derive_master_key(PASSWORD, params).map_err(|e| VaultError::CryptoError(e.to_string()))?;
+    Ok(())
+}
+
+/// An in-memory vault filled with generated credentials
+pub struct Fixture {
+    pub db: Database,
+    pub dek: DataEncryptionKey,
+    pub secrets: Vec<String>,
+}
+
+impl Fixture {
+    pub fn new(count: usize) -> VaultResult<Self> {
+        let db = Database::open_in_memory()?;
+        let dek = DataEncryptionKey::generate();
+        let secrets: Vec<String> = (0..count).map(|i| format!("s3cret-{:08x}-{}", i.wrapping_mul(2_654_435_761), i)).collect();
+
+        let tx = db.conn().unchecked_transaction()?;
+        for (i, secret) in secrets.iter().enumerate() {
+            let service = SERVICES[i % SERVICES.len()];
+            credential::create_credential(
+                &tx,
+                &dek,
+                credential::NewCredential {
+                    username: Some(format!("user{}@example.com", i)),
+                    url: Some(format!("https://{}.example.com", service)),
+                    tags: vec![TAGS[i % TAGS.len()].to_string()],
+                    ..credential::NewCredential::new(format!("{} {}", service, i), CredentialType::Password, secret)
+                },
+            )?;
+        }
+        tx.commit()?;
+        Ok(Self { db, dek, secrets })
+    }
+
+    pub fn encrypt_all(&self) -> VaultResult<Vec<EncryptedBlob>> {
+        self.secrets
+            .iter()
+            .map(|s| crypto::encrypt_string(self.dek.as_ref(), s))
+            .collect::<Result<_, _>>()
+            .map_err(|e| VaultError::CryptoError(e.to_string()))
+    }
+
+    /// Load every credential and decrypt its fields
+    pub fn decrypt_all(&self) -> VaultResult<usize> {
+        let credentials = credential::list_credentials(self.db.conn())?;
+        for cred in &credentials {
+            credential::decrypt_fields(&self.dek, cred)?;
+        }
+        Ok(credentials.len())
+    }
+
+    /// Full-text search, returning the number of matches
+    pub fn search(&self, query: &str) -> VaultResult<usize> {
+        Ok(search::search(self.db.conn(), query)?.total)
+    }
+
+    /// What the list does on refresh: count, then load the first page
+    pub fn refresh(&self) -> VaultResult<usize> {
+        let total = db::count_credentials(self.db.conn())?;
+        db::get_credentials_page(self.db.conn(), SortOrder::default(), 0, PAGE_SIZE)?;
+        Ok(total)
+    }
+}
+
+/// The fastest of `runs` runs, which is the least disturbed by whatever
+/// else the machine is doing
+pub fn best_of<T>(runs: usize, mut f: impl FnMut() -> VaultResult<T>) -> VaultResult<Duration> {
+    let mut best = Duration::MAX;
+    for _ in 0..runs.max(1) {
+        let start = Instant::now();
+        f()?;
+        best = best.min(start.elapsed());
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_workloads() {
+        let fixture = Fixture::new(50).unwrap();
+        assert_eq!(fixture.encrypt_all().unwrap().len(), 50);
+        assert_eq!(fixture.decrypt_all().unwrap(), 50);
+        assert_eq!(fixture.refresh().unwrap(), 50);
+        assert!(fixture.search("github").unwrap() > 0);
+        derive_key(&KdfParams::testing()).unwrap();
+    }
+}
@@ -0,0 +1,204 @@
+//! Offline Breach Check
+//!
+//! A Bloom filter of the SHA-1 hashes in the Have I Been Pwned password
+//! corpus, built once with `vault breach-index build` and then queried
+//! on disk, so breached passwords are found on a machine with no network
+//! access. The corpus's hashes are already uniformly distributed, so the
+//! filter's bit positions are taken straight from them. A match can be a
+//! false positive, at the rate the filter was built for; a miss is certain.
+//!
+//! File layout: magic, bit count (u64), hash count (u32), entry count
+//! (u64), all little-endian, then the bits.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use secrecy::ExposeSecret;
+use sha1::{Digest, Sha1};
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::{self, Credential, CredentialType};
+
+use super::backup::io_error;
+use super::credential::decrypt_fields;
+use super::{VaultError, VaultResult};
+
+const MAGIC: &[u8; 8] = b"VBLOOM1\0";
+const HEADER_LEN: u64 = 8 + 8 + 4 + 8;
+
+/// False positive rate a filter is built for unless told otherwise:
+/// about 14 bits per entry
+pub const DEFAULT_FP_RATE: f64 = 0.001;
+
+/// A built filter, read from disk as it is queried
+pub struct BreachIndex {
+    file: File,
+    bits: u64,
+    hashes: u32,
+    /// Hashes the filter was built from
+    pub entries: u64,
+}
+
+/// Bit count and hash count for `entries` at `fp_rate`
+fn dimensions(entries: u64, fp_rate: f64) -> (u64, u32) {
+    let ln2 = std::f64::consts::LN_2;
+    let bits = (-(entries.max(1) as f64) * fp_rate.ln() / (ln2 * ln2)).ceil().max(64.0) as u64;
+    let hashes = ((bits as f64 / entries.max(1) as f64) * ln2).round().clamp(1.0, 32.0) as u32;
+    (bits, hashes)
+}
+
+/// Bits set for a hash, by double hashing on two halves of the digest
+fn positions(digest: &[u8; 20], bits: u64, hashes: u32) -> impl Iterator<Item = u64> {
+    let h1 = u64::from_le_bytes(digest[0..8].try_into().unwrap_or_default());
+    let h2 = u64::from_le_bytes(digest[8..16].try_into().unwrap_or_default()) | 1;
+    (0..hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bits)
+}
+
+/// The hash on a line of the corpus, `HASH:COUNT` or just `HASH`
+fn parse_line(line: &str) -> Option<[u8; 20]> {
+    let hash = line.split(':').next()?.trim();
+    let mut digest = [0u8; 20];
+    hex::decode_to_slice(hash, &mut digest).ok()?;
+    Some(digest)
+}
+
+fn for_each_hash(dump: &Path, mut f: impl FnMut([u8; 20])) -> VaultResult<()> {
+    let reader = BufReader::new(File::open(dump).map_err(io_error)?);
+    for line in reader.lines() {
+        if let Some(digest) = parse_line(&line.map_err(io_error)?) {
+            f(digest);
+        }
+    }
+    Ok(())
+}
+
+/// Build a filter at `out` from a SHA-1 corpus download, reading it twice:
+/// once to size the filter, once to fill it. Returns the hashes added.
+pub fn build(dump: &Path, out: &Path, fp_rate: f64) -> VaultResult<u64> {
+    if !(fp_rate > 0.0 && fp_rate < 1.0) {
+        return Err(VaultError::OperationFailed("The false positive rate must be between 0 and 1".to_string()));
+    }
+    let mut entries = 0u64;
+    for_each_hash(dump, |_| entries += 1)?;
+    if entries == 0 {
+        return Err(VaultError::OperationFailed(format!("No SHA-1 hashes in {}", dump.display())));
+    }
+
+    let (bits, hashes) = dimensions(entries, fp_rate);
+    let len = usize::try_from(bits.div_ceil(8))
+        .map_err(|_| VaultError::OperationFailed("Filter too large for this machine".to_string()))?;
+    let mut filter = vec![0u8; len];
+    for_each_hash(dump, |digest| {
+        for bit in positions(&digest, bits, hashes) {
+            filter[(bit / 8) as usize] |= 1 << (bit % 8);
+        }
+    })?;
+
+    let mut writer = BufWriter::new(File::create(out).map_err(io_error)?);
+    writer.write_all(MAGIC).map_err(io_error)?;
+    writer.write_all(&bits.to_le_bytes()).map_err(io_error)?;
+    writer.write_all(&hashes.to_le_bytes()).map_err(io_error)?;
+    writer.write_all(&entries.to_le_bytes()).map_err(io_error)?;
+    writer.write_all(&filter).map_err(io_error)?;
+    writer.flush().map_err(io_error)?;
+    Ok(entries)
+}
+
+impl BreachIndex {
+    pub fn open(path: &Path) -> VaultResult<Self> {
+        let mut file = File::open(path).map_err(io_error)?;
+        let mut header = [0u8; HEADER_LEN as usize];
+        let not_index = || VaultError::OperationFailed(format!("{} is not a breach index", path.display()));
+        file.read_exact(&mut header).map_err(|_| not_index())?;
+        if &header[0..8] != MAGIC {
+            return Err(not_index());
+        }
+        let bits = u64::from_le_bytes(header[8..16].try_into().unwrap_or_default());
+        let hashes = u32::from_le_bytes(header[16..20].try_into().unwrap_or_default());
+        let entries = u64::from_le_bytes(header[20..28].try_into().unwrap_or_default());
+        let len = file.metadata().map_err(io_error)?.len();
+        if bits == 0 || hashes == 0 || len < HEADER_LEN + bits.div_ceil(8) {
+            return Err(not_index());
+        }
+        Ok(Self { file, bits, hashes, entries })
+    }
+
+    fn contains(&self, digest: &[u8; 20]) -> VaultResult<bool> {
+        let mut file = &self.file;
+        for bit in positions(digest, self.bits, self.hashes) {
+            let mut byte = [0u8; 1];
+            file.seek(SeekFrom::Start(HEADER_LEN + bit / 8)).map_err(io_error)?;
+            file.read_exact(&mut byte).map_err(io_error)?;
+            if byte[0] & (1 << (bit % 8)) == 0 {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Whether the password is probably in the corpus
+    pub fn is_breached(&self, password: &str) -> VaultResult<bool> {
+        let digest: [u8; 20] = Sha1::digest(password.as_bytes()).into();
+        self.contains(&digest)
+    }
+}
+
+/// Whether a credential's secret is a password someone could have reused
+pub fn is_checked(credential_type: CredentialType) -> bool {
+    matches!(credential_type, CredentialType::Password | CredentialType::Database)
+}
+
+/// Password and database credentials whose secret is in the index, and
+/// how many were checked
+pub fn check_vault(
+    conn: &rusqlite::Connection,
+    dek: &DataEncryptionKey,
+    index: &BreachIndex,
+) -> VaultResult<(Vec<Credential>, usize)> {
+    let candidates: Vec<Credential> =
+        db::get_all_credentials(conn)?.into_iter().filter(|c| is_checked(c.credential_type)).collect();
+    let checked = candidates.len();
+    let mut breached = Vec::new();
+    for cred in candidates {
+        let decrypted = decrypt_fields(dek, &cred)?;
+        let secret = decrypted.secret.as_ref().map(|s| s.expose_secret()).unwrap_or_default();
+        if !secret.is_empty() && index.is_breached(secret)? {
+            breached.push(cred);
+        }
+    }
+    breached.sort_by_key(|c| c.name.to_lowercase());
+    Ok((breached, checked))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sha1_hex(password: &str) -> String {
+        hex::encode_upper(Sha1::digest(password.as_bytes()))
+    }
+
+    #[test]
+    fn test_build_and_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let dump = dir.path().join("pwned-passwords-sha1.txt");
+        let mut lines: Vec<String> = (0..2000).map(|i| format!("{}:{}", sha1_hex(&format!("leaked{}", i)), i + 1)).collect();
+        lines.push(sha1_hex("password"));
+        lines.push("not a hash".to_string());
+        std::fs::write(&dump, lines.join("\r\n")).unwrap();
+
+        let out = dir.path().join("breach.bloom");
+        assert_eq!(build(&dump, &out, 0.01).unwrap(), 2001);
+        let index = BreachIndex::open(&out).unwrap();
+        assert_eq!(index.entries, 2001);
+        assert!(index.is_breached("password").unwrap());
+        assert!((0..2000).all(|i| index.is_breached(&format!("leaked{}", i)).unwrap()));
+
+        let false_positives = (0..2000).filter(|i| index.is_breached(&format!("fresh{}", i)).unwrap()).count();
+        assert!(false_positives < 60, "{} false positives", false_positives);
+
+        assert!(BreachIndex::open(&dump).is_err());
+        assert!(build(&dump, &out, 1.5).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+//! Compaction
+//!
+//! SQLite leaves deleted rows in free pages until they are reused, so a
+//! deleted credential's ciphertext, name and username can linger in the
+//! file. Compacting overwrites freed content, rebuilds the file without
+//! the free pages and checks the result.
+
+use rusqlite::Connection;
+
+use crate::db;
+
+use super::VaultResult;
+
+/// Deletions since the last compaction, kept across sessions
+const DELETES_KEY: &str = "deletes_since_compact";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactReport {
+    pub size_before: u64,
+    pub size_after: u64,
+    /// `PRAGMA integrity_check` passed afterwards
+    pub intact: bool,
+}
+
+impl CompactReport {
+    pub fn reclaimed(&self) -> u64 {
+        self.size_before.saturating_sub(self.size_after)
+    }
+}
+
+fn database_size(conn: &Connection) -> VaultResult<u64> {
+    let pages: u64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let page_size: u64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    Ok(pages * page_size)
+}
+
+/// Turn on secure_delete, vacuum, flush the WAL and run an integrity check
+pub fn compact(conn: &Connection) -> VaultResult<CompactReport> {
+    let size_before = database_size(conn)?;
+    conn.execute_batch("PRAGMA secure_delete = ON; VACUUM;")?;
+    // The WAL still holds the old pages until it is checkpointed away
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    let check: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    db::set_metadata(conn, DELETES_KEY, "0")?;
+    Ok(CompactReport { size_before, size_after: database_size(conn)?, intact: check == "ok" })
+}
+
+/// Count a deletion, returning how many there have been since the last
+/// compaction
+pub fn record_delete(conn: &Connection) -> VaultResult<u32> {
+    let deletes = db::get_metadata(conn, DELETES_KEY)?.and_then(|v| v.parse::<u32>().ok()).unwrap_or(0) + 1;
+    db::set_metadata(conn, DELETES_KEY, &deletes.to_string())?;
+    Ok(deletes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::DataEncryptionKey;
+    use crate::db::{CredentialType, Database, DatabaseConfig};
+    use crate::vault::credential::{create_credential, delete_credential, NewCredential};
+
+    #[test]
+    fn test_compact_erases_deleted_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.db");
+        let db = Database::open(DatabaseConfig::with_path(&path)).unwrap();
+        let conn = db.conn();
+        let dek = DataEncryptionKey::from_bytes([0x42u8; 32]);
+        let marker = "lingering-username-marker";
+
+        let mut ids = Vec::new();
+        for i in 0..50 {
+            let name = format!("cred{}", i);
+            let new = NewCredential {
+                username: Some(marker.into()),
+                ..NewCredential::new(name, CredentialType::Password, "secret")
+            };
+            let cred = create_credential(conn, &dek, new).unwrap();
+            ids.push(cred.id);
+        }
+        for id in &ids {
+            delete_credential(conn, id).unwrap();
+            record_delete(conn).unwrap();
+        }
+        assert_eq!(record_delete(conn).unwrap(), 51);
+
+        let report = compact(conn).unwrap();
+        assert!(report.intact);
+        assert!(report.reclaimed() > 0);
+        let raw = std::fs::read(&path).unwrap();
+        assert!(!raw.windows(marker.len()).any(|w| w == marker.as_bytes()));
+        assert_eq!(record_delete(conn).unwrap(), 1);
+    }
+}
@@ -0,0 +1,107 @@
+//! Crypto Info
+//!
+//! Which envelope version and algorithm every blob in the vault is sealed
+//! with, per credential and per key wrap, and how many the migrator still
+//! has to re-encrypt.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::crypto::Format;
+use crate::db;
+
+use super::credential;
+use super::VaultResult;
+
+/// The formats one credential's secret, notes and archived secrets, or one
+/// wrapped copy of the key, are stored in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CryptoRecord {
+    pub name: String,
+    /// A wrapped data encryption key rather than a credential
+    pub key: bool,
+    pub formats: BTreeSet<Format>,
+}
+
+impl CryptoRecord {
+    pub fn is_current(&self) -> bool {
+        self.formats.iter().all(|format| format.is_current())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CryptoInfo {
+    pub records: Vec<CryptoRecord>,
+    /// Credential blobs per format
+    pub totals: BTreeMap<Format, usize>,
+    /// Credential blobs left for the migrator
+    pub outdated: usize,
+}
+
+pub fn crypto_info(conn: &rusqlite::Connection) -> VaultResult<CryptoInfo> {
+    let mut history: HashMap<String, Vec<String>> = HashMap::new();
+    for (_, entry) in db::get_all_secret_history(conn)? {
+        history.entry(entry.credential_id).or_default().push(entry.encrypted_secret);
+    }
+
+    let mut info = CryptoInfo { outdated: credential::count_outdated(conn)?, ..CryptoInfo::default() };
+    for cred in db::get_all_credentials(conn)? {
+        let archived = history.remove(&cred.id).unwrap_or_default();
+        let blobs = std::iter::once(&cred.encrypted_secret).chain(&cred.encrypted_notes).chain(&archived);
+        let formats: Vec<Format> = blobs.map(|blob| Format::of(blob)).collect();
+        for format in &formats {
+            *info.totals.entry(*format).or_default() += 1;
+        }
+        info.records.push(CryptoRecord { name: cred.name, key: false, formats: formats.into_iter().collect() });
+    }
+
+    let primary = db::get_metadata(conn, "wrapped_dek")?.map(|blob| ("primary".to_string(), blob));
+    let slots = db::get_key_slots(conn)?.into_iter().map(|slot| (slot.label, slot.wrapped_dek));
+    for (label, blob) in primary.into_iter().chain(slots) {
+        let formats = BTreeSet::from([Format::of(&blob)]);
+        info.records.push(CryptoRecord { name: format!("key slot {}", label), key: true, formats });
+    }
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::ExposeSecret;
+
+    use super::*;
+    use crate::crypto::encryption::{self, Algorithm};
+    use crate::crypto::DataEncryptionKey;
+    use crate::db::{CredentialType, Database};
+
+    #[test]
+    fn test_reports_and_migrates_formats() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let dek = DataEncryptionKey::from_bytes([0x42u8; 32]);
+        let cred = credential::create_credential(
+            conn,
+            &dek,
+            credential::NewCredential {
+                notes: Some("notes"),
+                ..credential::NewCredential::new("GitHub".to_string(), CredentialType::Password, "secret")
+            },
+        )
+        .unwrap();
+        let old = encryption::seal_as(Format::Bound, dek.as_ref(), "secret", format!("{}/secret", cred.id).as_bytes());
+        let notes = cred.encrypted_notes.as_deref();
+        db::set_encrypted_fields(conn, &cred.id, &old, notes).unwrap();
+
+        let info = crypto_info(conn).unwrap();
+        assert_eq!(info.outdated, 1);
+        assert_eq!(info.records[0].formats, BTreeSet::from([Format::Bound, Format::Envelope(Algorithm::CURRENT)]));
+        assert!(!info.records[0].is_current());
+
+        assert_eq!(credential::migrate_ciphertexts(conn, &dek, 10).unwrap(), 1);
+        assert_eq!(credential::migrate_ciphertexts(conn, &dek, 10).unwrap(), 0);
+        let info = crypto_info(conn).unwrap();
+        assert_eq!(info.outdated, 0);
+        assert!(info.records[0].is_current());
+        let stored = credential::get_credential(conn, &cred.id).unwrap();
+        let decrypted = credential::decrypt_fields(&dek, &stored).unwrap();
+        assert_eq!(decrypted.notes.as_ref().map(|s| s.expose_secret()), Some("notes"));
+    }
+}
@@ -0,0 +1,185 @@
+//! CSV Export
+//!
+//! Writes chosen fields of every credential as CSV, for inventories and
+//! for moving to another password manager. Cells are quoted as RFC 4180
+//! describes, so commas, quotes and line breaks in a value survive.
+//! Secrets and notes are only written when asked for by name.
+
+use std::path::Path;
+
+use secrecy::ExposeSecret;
+use zeroize::Zeroizing;
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::{self, Credential};
+
+use super::backup::write_private;
+use super::credential::{decrypt_fields, DecryptedCredential};
+use super::{VaultError, VaultResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvField {
+    Name,
+    Type,
+    Username,
+    Url,
+    Tags,
+    Alias,
+    Created,
+    Updated,
+    Secret,
+    Notes,
+}
+
+impl CsvField {
+    /// Columns written when none are chosen
+    pub const DEFAULT: [Self; 4] = [Self::Name, Self::Username, Self::Url, Self::Tags];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "name" => Some(Self::Name),
+            "type" => Some(Self::Type),
+            "username" | "user" => Some(Self::Username),
+            "url" => Some(Self::Url),
+            "tags" => Some(Self::Tags),
+            "alias" => Some(Self::Alias),
+            "created" => Some(Self::Created),
+            "updated" => Some(Self::Updated),
+            "secret" | "password" => Some(Self::Secret),
+            "notes" => Some(Self::Notes),
+            _ => None,
+        }
+    }
+
+    /// Column header
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Type => "type",
+            Self::Username => "username",
+            Self::Url => "url",
+            Self::Tags => "tags",
+            Self::Alias => "alias",
+            Self::Created => "created",
+            Self::Updated => "updated",
+            Self::Secret => "secret",
+            Self::Notes => "notes",
+        }
+    }
+
+    /// Stored encrypted, so only exported on request
+    pub fn is_secret(self) -> bool {
+        matches!(self, Self::Secret | Self::Notes)
+    }
+
+    fn value(self, cred: &Credential, decrypted: Option<&DecryptedCredential>) -> String {
+        let expose = |value: Option<&secrecy::SecretString>| value.map(|v| v.expose_secret().to_string());
+        match self {
+            Self::Name => cred.name.clone(),
+            Self::Type => cred.credential_type.as_str().to_string(),
+            Self::Username => cred.username.clone().unwrap_or_default(),
+            Self::Url => cred.url.clone().unwrap_or_default(),
+            Self::Tags => cred.tags.join(","),
+            Self::Alias => cred.alias.clone().unwrap_or_default(),
+            Self::Created => cred.created_at.to_rfc3339(),
+            Self::Updated => cred.updated_at.to_rfc3339(),
+            Self::Secret => decrypted.and_then(|d| expose(d.secret.as_ref())).unwrap_or_default(),
+            Self::Notes => decrypted.and_then(|d| expose(d.notes.as_ref())).unwrap_or_default(),
+        }
+    }
+}
+
+/// Parse a comma separated field list such as `name,username,url`
+pub fn parse_fields(list: &str) -> Result<Vec<CsvField>, String> {
+    let fields: Vec<CsvField> = list
+        .split(',')
+        .filter(|f| !f.trim().is_empty())
+        .map(|f| CsvField::parse(f).ok_or_else(|| format!("unknown field '{}'", f.trim())))
+        .collect::<Result<_, _>>()?;
+    if fields.is_empty() {
+        return Err("no fields given".to_string());
+    }
+    Ok(fields)
+}
+
+/// Quote a cell when it holds a separator, a quote or a line break,
+/// doubling any quotes inside
+fn quote(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+fn push_row(out: &mut String, cells: impl Iterator<Item = String>) {
+    let cells: Vec<Zeroizing<String>> = cells.map(|c| Zeroizing::new(quote(&c))).collect();
+    for (i, cell) in cells.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(cell);
+    }
+    out.push_str("\r\n");
+}
+
+/// Every credential as CSV with a header row, in name order
+pub fn to_csv(conn: &rusqlite::Connection, dek: &DataEncryptionKey, fields: &[CsvField]) -> VaultResult<Zeroizing<String>> {
+    if fields.is_empty() {
+        return Err(VaultError::OperationFailed("No fields to export".to_string()));
+    }
+    let needs_secrets = fields.iter().any(|f| f.is_secret());
+    let mut credentials = db::get_all_credentials(conn)?;
+    credentials.sort_by_key(|c| c.name.to_lowercase());
+
+    let mut out = Zeroizing::new(String::new());
+    push_row(&mut out, fields.iter().map(|f| f.label().to_string()));
+    for cred in &credentials {
+        let decrypted = if needs_secrets { Some(decrypt_fields(dek, cred)?) } else { None };
+        push_row(&mut out, fields.iter().map(|f| f.value(cred, decrypted.as_ref())));
+    }
+    Ok(out)
+}
+
+/// Write the CSV to `path`, readable only by the owner, and return how
+/// many credentials it holds
+pub fn export_csv(conn: &rusqlite::Connection, dek: &DataEncryptionKey, fields: &[CsvField], path: &Path) -> VaultResult<usize> {
+    let csv = to_csv(conn, dek, fields)?;
+    write_private(path, csv.as_bytes())?;
+    Ok(db::count_credentials(conn)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{CredentialType, Database};
+    use crate::vault::credential::{create_credential, NewCredential};
+
+    #[test]
+    fn test_csv_quoting_and_fields() {
+        let db = Database::open_in_memory().unwrap();
+        let dek = DataEncryptionKey::from_bytes([0x42u8; 32]);
+        create_credential(
+            db.conn(),
+            &dek,
+            NewCredential {
+                username: Some("me".to_string()),
+                tags: vec!["money".to_string(), "home".to_string()],
+                notes: Some("line one\nline two"),
+                ..NewCredential::new("Bank, \"main\"".to_string(), CredentialType::Password, "p,w\"d")
+            },
+        )
+        .unwrap();
+
+        let fields = parse_fields("name,username,tags").unwrap();
+        let csv = to_csv(db.conn(), &dek, &fields).unwrap();
+        assert_eq!(csv.as_str(), "name,username,tags\r\n\"Bank, \"\"main\"\"\",me,\"money,home\"\r\n");
+        assert!(!csv.contains("p,w"));
+
+        let csv = to_csv(db.conn(), &dek, &parse_fields("secret, notes").unwrap()).unwrap();
+        assert_eq!(csv.as_str(), "secret,notes\r\n\"p,w\"\"d\",\"line one\nline two\"\r\n");
+
+        assert!(parse_fields("name,pin").is_err());
+        assert!(parse_fields(",").is_err());
+    }
+}
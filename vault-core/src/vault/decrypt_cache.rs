@@ -0,0 +1,198 @@
+//! Decrypted credential cache
+//!
+//! Keeps the most recently used credentials decrypted for previewing,
+//! every field but the secret, keyed by id and `updated_at` so an edited
+//! credential never matches its old entry. A worker thread with its own
+//! copy of the DEK decrypts the rows around the cursor ahead of time, so
+//! moving through a large vault rarely has to wait on decryption. Secrets
+//! are never cached; they are decrypted when a credential is opened.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use chrono::{DateTime, Local};
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::Credential;
+
+use super::credential::{decrypt_preview, DecryptedCredential};
+
+/// Decrypted credentials kept before the least recently used are dropped
+pub const CACHE_CAPACITY: usize = 256;
+
+type CacheKey = (String, DateTime<Local>);
+
+struct Entry {
+    credential: DecryptedCredential,
+    last_used: u64,
+}
+
+struct Worker {
+    requests: Sender<Vec<Credential>>,
+    results: Receiver<DecryptedCredential>,
+}
+
+impl Worker {
+    fn spawn(dek: DataEncryptionKey) -> Self {
+        let (requests, jobs) = mpsc::channel::<Vec<Credential>>();
+        let (done, results) = mpsc::channel();
+        thread::spawn(move || {
+            // Ends when the cache drops its sender, taking the DEK copy with it
+            for batch in jobs {
+                for cred in &batch {
+                    let Ok(decrypted) = decrypt_preview(&dek, cred) else { continue };
+                    if done.send(decrypted).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Self { requests, results }
+    }
+}
+
+#[derive(Default)]
+pub struct DecryptCache {
+    entries: HashMap<CacheKey, Entry>,
+    /// Sent to the worker and not back yet
+    in_flight: HashSet<CacheKey>,
+    clock: u64,
+    worker: Option<Worker>,
+}
+
+impl DecryptCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn key(cred: &Credential) -> CacheKey {
+        (cred.id.clone(), cred.updated_at)
+    }
+
+    /// The decrypted form of `cred`, if it is cached and still current
+    pub fn get(&mut self, cred: &Credential) -> Option<DecryptedCredential> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(&Self::key(cred))?;
+        entry.last_used = self.clock;
+        Some(entry.credential.clone())
+    }
+
+    pub fn insert(&mut self, credential: DecryptedCredential) {
+        let key = (credential.id.clone(), credential.updated_at);
+        self.in_flight.remove(&key);
+        // Older versions of the same credential can never be hit again
+        self.entries.retain(|(id, _), _| *id != credential.id);
+        self.clock += 1;
+        self.entries.insert(key, Entry { credential, last_used: self.clock });
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.entries.len() > CACHE_CAPACITY {
+            let Some(oldest) = self.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone()) else {
+                return;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    /// Drop a deleted credential
+    pub fn remove(&mut self, id: &str) {
+        self.entries.retain(|(cached, _), _| cached != id);
+    }
+
+    /// Queue the credentials that are not cached yet for the worker,
+    /// starting it with `dek` if it is not running
+    pub fn prefetch<'a>(&mut self, dek: &DataEncryptionKey, creds: impl IntoIterator<Item = &'a Credential>) {
+        let batch: Vec<Credential> = creds
+            .into_iter()
+            .filter(|c| {
+                let key = Self::key(c);
+                !self.entries.contains_key(&key) && !self.in_flight.contains(&key)
+            })
+            .cloned()
+            .collect();
+        if batch.is_empty() {
+            return;
+        }
+        let worker = self.worker.get_or_insert_with(|| Worker::spawn(dek.clone()));
+        self.in_flight.extend(batch.iter().map(Self::key));
+        if worker.requests.send(batch).is_err() {
+            self.worker = None;
+            self.in_flight.clear();
+        }
+    }
+
+    /// Move whatever the worker has finished into the cache
+    pub fn receive(&mut self) {
+        let Some(worker) = &self.worker else { return };
+        let done: Vec<DecryptedCredential> = worker.results.try_iter().collect();
+        for credential in done {
+            self.insert(credential);
+        }
+    }
+
+    /// Forget everything and stop the worker, e.g. when the vault locks
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.in_flight.clear();
+        self.worker = None;
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::db::{CredentialType, Database};
+    use crate::vault::credential::{create_credential, NewCredential};
+
+    fn test_dek() -> DataEncryptionKey {
+        DataEncryptionKey::from_bytes([0x42u8; 32])
+    }
+
+    fn add(conn: &rusqlite::Connection, name: &str) -> Credential {
+        create_credential(conn, &test_dek(), NewCredential::new(name.to_string(), CredentialType::Password, "secret"))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_prefetch_and_eviction() {
+        let db = Database::open_in_memory().unwrap();
+        let creds: Vec<Credential> = (0..CACHE_CAPACITY + 10).map(|i| add(db.conn(), &format!("cred{}", i))).collect();
+
+        let mut cache = DecryptCache::new();
+        assert!(cache.get(&creds[0]).is_none());
+        cache.prefetch(&test_dek(), &creds[..3]);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while cache.len() < 3 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+            cache.receive();
+        }
+        let cached = cache.get(&creds[1]).unwrap();
+        assert_eq!(cached.name, "cred1");
+        assert!(cached.secret.is_none());
+
+        let mut edited = creds[1].clone();
+        edited.updated_at += chrono::Duration::seconds(1);
+        assert!(cache.get(&edited).is_none());
+
+        for cred in &creds {
+            cache.insert(decrypt_preview(&test_dek(), cred).unwrap());
+        }
+        assert_eq!(cache.len(), CACHE_CAPACITY);
+        assert!(cache.get(&creds[0]).is_none());
+        cache.remove(&creds[CACHE_CAPACITY].id);
+        assert!(cache.get(&creds[CACHE_CAPACITY]).is_none());
+
+        cache.clear();
+        assert_eq!(cache.len(), 0);
+    }
+}
@@ -0,0 +1,206 @@
+//! Vault Diff
+//!
+//! Compares two copies of a vault, such as a backup and the live file or
+//! the two ends of a sync, credential by credential. Credentials are
+//! matched by id. Secrets and notes are only decrypted when their
+//! ciphertexts differ, which tells a changed secret from one that was
+//! merely re-encrypted.
+
+use chrono::{DateTime, Local};
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::{self, Credential};
+
+use super::credential::decrypt_fields;
+use super::timeline::SECRET_FIELD;
+use super::VaultResult;
+
+/// Reported when only the encryption of the secret or notes differs
+pub const REENCRYPTED: &str = "re-encrypted";
+
+/// Reported when nothing but the update time differs
+pub const TIMESTAMP: &str = "updated time";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffKind {
+    /// Only in the right vault
+    Added,
+    /// Only in the left vault
+    Removed,
+    /// In both, with these fields differing
+    Changed(Vec<&'static str>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffEntry {
+    pub name: String,
+    pub kind: DiffKind,
+    pub left_updated: Option<DateTime<Local>>,
+    pub right_updated: Option<DateTime<Local>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultDiff {
+    /// In name order
+    pub entries: Vec<DiffEntry>,
+    pub unchanged: usize,
+}
+
+impl VaultDiff {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn count(&self, kind: fn(&DiffKind) -> bool) -> usize {
+        self.entries.iter().filter(|e| kind(&e.kind)).count()
+    }
+
+    /// e.g. "2 added, 1 removed, 3 changed, 40 unchanged"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} added, {} removed, {} changed, {} unchanged",
+            self.count(|k| *k == DiffKind::Added),
+            self.count(|k| *k == DiffKind::Removed),
+            self.count(|k| matches!(k, DiffKind::Changed(_))),
+            self.unchanged
+        )
+    }
+}
+
+/// One side of the comparison: an unlocked vault's connection and key
+pub type Side<'a> = (&'a rusqlite::Connection, &'a DataEncryptionKey);
+
+fn ciphertext_hash(cred: &Credential) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(cred.encrypted_secret.as_bytes());
+    hasher.update([0]);
+    hasher.update(cred.encrypted_notes.as_deref().unwrap_or_default().as_bytes());
+    hasher.finalize().into()
+}
+
+/// Fields that differ between two copies of a credential
+fn changed_fields(left: &Credential, left_key: &DataEncryptionKey, right: &Credential, right_key: &DataEncryptionKey) -> VaultResult<Vec<&'static str>> {
+    let mut fields: Vec<&'static str> = [
+        ("name", left.name != right.name),
+        ("type", left.credential_type != right.credential_type),
+        ("username", left.username != right.username),
+        ("url", left.url != right.url),
+        ("tags", left.tags != right.tags),
+        ("alias", left.alias != right.alias),
+        ("env var", left.env_var != right.env_var),
+        ("attributes", left.attributes != right.attributes),
+        ("policy", left.password_policy != right.password_policy),
+        ("public key", left.public_key != right.public_key),
+    ]
+    .into_iter()
+    .filter_map(|(field, changed)| changed.then_some(field))
+    .collect();
+
+    if ciphertext_hash(left) != ciphertext_hash(right) {
+        let (before, after) = (decrypt_fields(left_key, left)?, decrypt_fields(right_key, right)?);
+        let expose = |s: Option<&secrecy::SecretString>| s.map(|s| s.expose_secret().to_string());
+        let secret = expose(before.secret.as_ref()) != expose(after.secret.as_ref());
+        let notes = expose(before.notes.as_ref()) != expose(after.notes.as_ref());
+        fields.extend([(SECRET_FIELD, secret), ("notes", notes)].into_iter().filter_map(|(f, changed)| changed.then_some(f)));
+        if !secret && !notes {
+            fields.push(REENCRYPTED);
+        }
+    }
+    if fields.is_empty() && left.updated_at != right.updated_at {
+        fields.push(TIMESTAMP);
+    }
+    Ok(fields)
+}
+
+/// What changed going from `left` to `right`
+pub fn diff(left: Side, right: Side) -> VaultResult<VaultDiff> {
+    let left_creds = db::get_all_credentials(left.0)?;
+    let mut right_creds = db::get_all_credentials(right.0)?;
+    let mut result = VaultDiff::default();
+
+    for before in left_creds {
+        let Some(i) = right_creds.iter().position(|c| c.id == before.id) else {
+            result.entries.push(DiffEntry {
+                name: before.name.clone(),
+                kind: DiffKind::Removed,
+                left_updated: Some(before.updated_at),
+                right_updated: None,
+            });
+            continue;
+        };
+        let after = right_creds.swap_remove(i);
+        let fields = changed_fields(&before, left.1, &after, right.1)?;
+        if fields.is_empty() {
+            result.unchanged += 1;
+            continue;
+        }
+        result.entries.push(DiffEntry {
+            name: after.name.clone(),
+            kind: DiffKind::Changed(fields),
+            left_updated: Some(before.updated_at),
+            right_updated: Some(after.updated_at),
+        });
+    }
+    result.entries.extend(right_creds.into_iter().map(|added| DiffEntry {
+        name: added.name.clone(),
+        kind: DiffKind::Added,
+        left_updated: None,
+        right_updated: Some(added.updated_at),
+    }));
+    result.entries.sort_by_key(|e| e.name.to_lowercase());
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{CredentialType, Database};
+    use crate::vault::credential;
+
+    #[test]
+    fn test_diff_copies() {
+        let dek = DataEncryptionKey::from_bytes([0x42u8; 32]);
+        let add = |conn: &rusqlite::Connection, name: &str, secret: &str| {
+            let new = credential::NewCredential::new(name.to_string(), CredentialType::Password, secret);
+            credential::create_credential(conn, &dek, new).unwrap()
+        };
+        let left = Database::open_in_memory().unwrap();
+        let right = Database::open_in_memory().unwrap();
+        let copy = |cred: &Credential| db::create_credential(right.conn(), cred).unwrap();
+
+        let kept = add(left.conn(), "Kept", "same");
+        copy(&kept);
+        let removed = add(left.conn(), "Removed", "gone");
+        let mut renamed = add(left.conn(), "Mail", "s3cret");
+        renamed.name = "Email".to_string();
+        renamed.username = Some("me".to_string());
+        copy(&renamed);
+        let mut rotated = add(left.conn(), "Bank", "old");
+        copy(&rotated);
+        credential::update_credential(right.conn(), &dek, &mut rotated, Some("new"), None).unwrap();
+        let mut resealed = add(left.conn(), "Wifi", "hunter2");
+        copy(&resealed);
+        credential::update_credential(right.conn(), &dek, &mut resealed, Some("hunter2"), None).unwrap();
+        add(right.conn(), "Added", "new one");
+
+        let result = diff((left.conn(), &dek), (right.conn(), &dek)).unwrap();
+        assert_eq!(result.summary(), "1 added, 1 removed, 3 changed, 1 unchanged");
+        let kinds: Vec<(&str, &DiffKind)> = result.entries.iter().map(|e| (e.name.as_str(), &e.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("Added", &DiffKind::Added),
+                ("Bank", &DiffKind::Changed(vec![SECRET_FIELD])),
+                ("Email", &DiffKind::Changed(vec!["name", "username"])),
+                ("Removed", &DiffKind::Removed),
+                ("Wifi", &DiffKind::Changed(vec![REENCRYPTED])),
+            ]
+        );
+        assert_eq!(result.entries[3].left_updated, Some(removed.updated_at));
+        assert_eq!(result.entries[3].right_updated, None);
+
+        assert!(diff((left.conn(), &dek), (left.conn(), &dek)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+//! Duplicates
+//!
+//! Finds credentials that probably describe the same account: a new entry
+//! close to an existing one, and pairs already in the vault with the same
+//! login or the same secret.
+
+use std::collections::{BTreeMap, HashMap};
+
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::{self, Credential};
+
+use super::credential::decrypt_credential;
+use super::search::UrlParts;
+use super::VaultResult;
+
+/// Names at least this long may differ by one edit and still match
+const FUZZY_NAME_MIN_LEN: usize = 5;
+
+/// Two credentials that look like the same account
+#[derive(Debug, Clone)]
+pub struct DuplicatePair {
+    pub first: Credential,
+    pub second: Credential,
+    /// Same username on the same site
+    pub same_login: bool,
+    pub same_secret: bool,
+}
+
+impl DuplicatePair {
+    pub fn reason(&self) -> &'static str {
+        match (self.same_login, self.same_secret) {
+            (true, true) => "same login and secret",
+            (true, false) => "same login",
+            _ => "same secret",
+        }
+    }
+}
+
+/// Existing credentials a new one with these fields would likely
+/// duplicate: a near-identical name, or the same username on the same site
+pub fn similar_to(
+    conn: &rusqlite::Connection,
+    name: &str,
+    username: Option<&str>,
+    url: Option<&str>,
+    exclude_id: Option<&str>,
+) -> VaultResult<Vec<Credential>> {
+    let name = normalize_name(name);
+    let login = login_key(username, url);
+    Ok(db::get_all_credentials(conn)?
+        .into_iter()
+        .filter(|c| Some(c.id.as_str()) != exclude_id)
+        .filter(|c| {
+            names_close(&name, &normalize_name(&c.name))
+                || (login.is_some() && login == login_key(c.username.as_deref(), c.url.as_deref()))
+        })
+        .collect())
+}
+
+/// Pairs of credentials sharing a login or a secret, ordered by name. A
+/// group of three or more is reported as neighbouring pairs rather than
+/// every combination, so one reused password does not flood the report.
+pub fn find_duplicates(conn: &rusqlite::Connection, dek: &DataEncryptionKey) -> VaultResult<Vec<DuplicatePair>> {
+    let credentials = db::get_all_credentials(conn)?;
+
+    let mut logins: BTreeMap<(String, String), Vec<usize>> = BTreeMap::new();
+    let mut secrets: HashMap<[u8; 32], Vec<usize>> = HashMap::new();
+    for (i, cred) in credentials.iter().enumerate() {
+        if let Some(key) = login_key(cred.username.as_deref(), cred.url.as_deref()) {
+            logins.entry(key).or_default().push(i);
+        }
+        let decrypted = decrypt_credential(conn, dek, cred, false)?;
+        if let Some(secret) = decrypted.secret.as_ref().map(|s| s.expose_secret())
+            && !secret.is_empty()
+        {
+            secrets.entry(Sha256::digest(secret.as_bytes()).into()).or_default().push(i);
+        }
+    }
+
+    let mut pairs: BTreeMap<(usize, usize), (bool, bool)> = BTreeMap::new();
+    for group in logins.values() {
+        for pair in group.windows(2) {
+            pairs.entry((pair[0], pair[1])).or_default().0 = true;
+        }
+    }
+    for group in secrets.values() {
+        for pair in group.windows(2) {
+            pairs.entry((pair[0], pair[1])).or_default().1 = true;
+        }
+    }
+
+    // Credentials come sorted by name, so the pair keys already are
+    Ok(pairs
+        .into_iter()
+        .map(|((a, b), (same_login, same_secret))| DuplicatePair {
+            first: credentials[a].clone(),
+            second: credentials[b].clone(),
+            same_login,
+            same_secret,
+        })
+        .collect())
+}
+
+/// Lowercase letters and digits only, so "GitHub " and "git-hub" agree
+fn normalize_name(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+fn names_close(a: &str, b: &str) -> bool {
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+    a == b || (a.chars().count().min(b.chars().count()) >= FUZZY_NAME_MIN_LEN && edit_distance(a, b) <= 1)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Lowercase username and registrable domain, when both are present
+fn login_key(username: Option<&str>, url: Option<&str>) -> Option<(String, String)> {
+    let username = username.map(str::trim).filter(|u| !u.is_empty())?;
+    let site = UrlParts::parse(url?)?;
+    Some((username.to_lowercase(), site.registrable_domain().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{CredentialType, Database};
+    use crate::vault::credential::{create_credential, NewCredential};
+
+    fn test_dek() -> DataEncryptionKey {
+        DataEncryptionKey::from_bytes([0x42u8; 32])
+    }
+
+    fn add(conn: &rusqlite::Connection, name: &str, username: Option<&str>, url: Option<&str>, secret: &str) -> Credential {
+        create_credential(
+            conn,
+            &test_dek(),
+            NewCredential {
+                username: username.map(str::to_string),
+                url: url.map(str::to_string),
+                ..NewCredential::new(name.to_string(), CredentialType::Password, secret)
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_names_close() {
+        assert!(names_close(&normalize_name("GitHub"), &normalize_name("git-hub ")));
+        assert!(names_close("gitlab", "gitlav"));
+        assert!(!names_close("aws", "gcp"));
+        assert!(!names_close("mail", "main"));
+        assert!(!names_close("github", "githubwork"));
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_similar_to() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let github = add(conn, "GitHub", Some("octocat"), Some("https://github.com"), "a");
+        add(conn, "Bank", Some("me"), Some("https://bank.example"), "b");
+
+        let names = |found: Vec<Credential>| found.into_iter().map(|c| c.name).collect::<Vec<_>>();
+        assert_eq!(names(similar_to(conn, "github", None, None, None).unwrap()), ["GitHub"]);
+        assert_eq!(names(similar_to(conn, "Work", Some("OctoCat"), Some("gist.github.com"), None).unwrap()), ["GitHub"]);
+        assert!(similar_to(conn, "Work", Some("someone"), Some("github.com"), None).unwrap().is_empty());
+        assert!(similar_to(conn, "GitHub", None, None, Some(&github.id)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        add(conn, "GitHub", Some("octocat"), Some("https://github.com"), "hunter2");
+        add(conn, "GitHub (imported)", Some("octocat"), Some("github.com/login"), "hunter2");
+        add(conn, "Forum", Some("me"), Some("forum.example"), "hunter2");
+        add(conn, "Bank", Some("me"), Some("bank.example"), "unique");
+        add(conn, "Empty A", None, None, "");
+        add(conn, "Empty B", None, None, "");
+
+        let pairs = find_duplicates(conn, &test_dek()).unwrap();
+        let summary: Vec<(&str, &str, &str)> =
+            pairs.iter().map(|p| (p.first.name.as_str(), p.second.name.as_str(), p.reason())).collect();
+        assert_eq!(
+            summary,
+            [("Forum", "GitHub", "same secret"), ("GitHub", "GitHub (imported)", "same login and secret")]
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Emergency Codes
+//!
+//! Single-use unlock codes for a trusted person to open the vault if its
+//! owner cannot. Each code is a key slot labelled `emergency-N` wrapping
+//! the DEK under a key derived from a random code with HKDF, which is
+//! shown once when generated and never stored. Unlocking with one deletes its slot
+//! before the vault opens, records the event in the audit log and leaves
+//! a notice the owner is shown on their next unlock.
+
+use chrono::{DateTime, Local};
+use rand::Rng;
+
+/// Reserved label prefix of the slots holding emergency codes
+pub const EMERGENCY_SLOT_PREFIX: &str = "emergency-";
+/// How many codes `vault emergency-codes` prints unless told otherwise
+pub const DEFAULT_CODES: usize = 5;
+/// Metadata key listing used codes the owner has not been shown yet
+pub(crate) const EMERGENCY_UNLOCKS_KEY: &str = "emergency_unlocks";
+
+/// Crockford's base32: no I, L, O or U to misread
+const ALPHABET: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const GROUPS: usize = 5;
+const GROUP_LEN: usize = 5;
+
+pub fn is_emergency_slot(label: &str) -> bool {
+    label.starts_with(EMERGENCY_SLOT_PREFIX)
+}
+
+/// A random code of 125 bits, e.g. `7KQ2M-XV0TB-...`
+pub fn generate_code() -> String {
+    let mut rng = rand::thread_rng();
+    let groups: Vec<String> = (0..GROUPS)
+        .map(|_| (0..GROUP_LEN).map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char).collect())
+        .collect();
+    groups.join("-")
+}
+
+/// The code as generated, whatever case, spacing or dashes it was typed
+/// with
+pub fn normalize_code(input: &str) -> String {
+    let chars: Vec<char> = input
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    chars.chunks(GROUP_LEN).map(|group| group.iter().collect::<String>()).collect::<Vec<_>>().join("-")
+}
+
+/// Whether normalized input has the shape of a code, so a mistyped
+/// password is never tried against the emergency slots
+pub fn is_code(normalized: &str) -> bool {
+    normalized.len() == GROUPS * GROUP_LEN + GROUPS - 1
+        && normalized.bytes().all(|b| b == b'-' || ALPHABET.contains(&b))
+}
+
+/// A code that was used, until its owner is shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmergencyUnlock {
+    pub label: String,
+    pub at: DateTime<Local>,
+}
+
+impl EmergencyUnlock {
+    pub(crate) fn parse_all(value: &str) -> Vec<Self> {
+        value
+            .lines()
+            .filter_map(|line| {
+                let (label, at) = line.split_once(' ')?;
+                let at = DateTime::parse_from_rfc3339(at).ok()?.with_timezone(&Local);
+                Some(Self { label: label.to_string(), at })
+            })
+            .collect()
+    }
+
+    pub(crate) fn to_line(&self) -> String {
+        format!("{} {}", self.label, self.at.to_rfc3339())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes() {
+        let code = generate_code();
+        assert_eq!(code.len(), GROUPS * GROUP_LEN + GROUPS - 1);
+        assert_eq!(normalize_code(&code), code);
+        assert_eq!(normalize_code(&code.to_lowercase().replace('-', " ")), code);
+        assert_ne!(generate_code(), code);
+        assert!(is_code(&code));
+        assert!(!is_code(&normalize_code("correct horse battery staple")));
+        assert!(!is_code(&normalize_code(&code[..code.len() - 1])));
+
+        let used = EmergencyUnlock { label: "emergency-2".to_string(), at: Local::now() };
+        let parsed = EmergencyUnlock::parse_all(&format!("{}\ngarbage", used.to_line()));
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].label, "emergency-2");
+    }
+}
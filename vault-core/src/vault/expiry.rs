@@ -0,0 +1,65 @@
+//! Expiry Dates
+//!
+//! Time left before an API key's `expires_at` date, worded the same way
+//! as certificate expiry.
+
+use chrono::NaiveDate;
+
+use crate::crypto::cert::EXPIRY_WARNING_DAYS;
+
+/// Whole days from `today` to `expires`, negative once it has passed
+pub fn days_left(expires: NaiveDate, today: NaiveDate) -> i64 {
+    (expires - today).num_days()
+}
+
+/// Expired, or within `EXPIRY_WARNING_DAYS` of it. A key is treated as
+/// expired on its expiry date.
+pub fn expires_soon(expires: NaiveDate, today: NaiveDate) -> bool {
+    days_left(expires, today) < EXPIRY_WARNING_DAYS
+}
+
+/// The date and how far off it is, e.g. `2030-01-01 (in 90 days)`
+pub fn summary(expires: NaiveDate, today: NaiveDate) -> String {
+    let date = expires.format("%Y-%m-%d");
+    match days_left(expires, today) {
+        days if days < 0 => format!("{} (expired {} days ago)", date, -days),
+        0 => format!("{} (today)", date),
+        1 => format!("{} (in 1 day)", date),
+        days => format!("{} (in {} days)", date, days),
+    }
+}
+
+/// Compact form for the list, e.g. `12d` or `expired`
+pub fn short(expires: NaiveDate, today: NaiveDate) -> String {
+    match days_left(expires, today) {
+        days if days <= 0 => "expired".to_string(),
+        days => format!("{}d", days),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_summary() {
+        let expires = date("2030-01-01");
+        assert_eq!(summary(expires, date("2029-12-30")), "2030-01-01 (in 2 days)");
+        assert_eq!(summary(expires, date("2030-01-01")), "2030-01-01 (today)");
+        assert_eq!(summary(expires, date("2030-01-04")), "2030-01-01 (expired 3 days ago)");
+        assert_eq!(short(expires, date("2029-12-20")), "12d");
+        assert_eq!(short(expires, date("2030-01-01")), "expired");
+    }
+
+    #[test]
+    fn test_expires_soon() {
+        let expires = date("2030-01-01");
+        assert!(!expires_soon(expires, date("2029-11-01")));
+        assert!(expires_soon(expires, date("2029-12-15")));
+        assert!(expires_soon(expires, date("2031-01-01")));
+    }
+}
@@ -0,0 +1,162 @@
+//! Vault Fingerprint
+//!
+//! A short code naming one vault file, shown on the unlock screen before
+//! the password is typed and by `:info`. It hashes the wrapped DEK and
+//! the creation time as they were when the fingerprint was made, and is
+//! kept in the metadata, so changing the password does not change it.
+//!
+//! Anyone can copy the code into another file, so it goes with an HMAC
+//! under a key derived from the DEK. Once unlocked, a fingerprint that
+//! was not made with this vault's key is reported.
+//!
+//! The HMAC also records whether every ciphertext in the vault is bound
+//! to its credential, so that cannot be undone by editing the file.
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::DerivedKey;
+use crate::db;
+
+use super::VaultResult;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const FINGERPRINT_KEY: &str = "fingerprint";
+const FINGERPRINT_MAC_KEY: &str = "fingerprint_mac";
+/// When the vault was created, RFC 3339
+pub const CREATED_AT_KEY: &str = "created_at";
+
+/// Bytes of the hash shown, as groups of four hex digits
+const FINGERPRINT_LEN: usize = 8;
+
+/// `1A2B-3C4D-5E6F-7A8B`
+pub fn compute(wrapped_dek: &str, created_at: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(b"vault-fingerprint\0")
+        .chain_update(wrapped_dek.as_bytes())
+        .chain_update(b"\0")
+        .chain_update(created_at.as_bytes())
+        .finalize();
+    let hex = hex::encode_upper(&digest[..FINGERPRINT_LEN]);
+    hex.as_bytes().chunks(4).map(|group| String::from_utf8_lossy(group).into_owned()).collect::<Vec<_>>().join("-")
+}
+
+/// The stored fingerprint, or for a vault from before fingerprints the one
+/// it gets on its next read-write unlock. None until the vault exists.
+pub fn read(conn: &rusqlite::Connection) -> VaultResult<Option<String>> {
+    if let Some(stored) = db::get_metadata(conn, FINGERPRINT_KEY)? {
+        return Ok(Some(stored));
+    }
+    let Some(wrapped_dek) = db::get_metadata(conn, "wrapped_dek")? else { return Ok(None) };
+    let created_at = db::get_metadata(conn, CREATED_AT_KEY)?.unwrap_or_default();
+    Ok(Some(compute(&wrapped_dek, &created_at)))
+}
+
+/// Store the fingerprint and its HMAC if the vault has none yet
+pub fn establish(conn: &rusqlite::Connection, key: &DerivedKey) -> VaultResult<()> {
+    if db::get_metadata(conn, FINGERPRINT_KEY)?.is_some() {
+        return Ok(());
+    }
+    let Some(fingerprint) = read(conn)? else { return Ok(()) };
+    db::set_metadata(conn, FINGERPRINT_KEY, &fingerprint)?;
+    db::set_metadata(conn, FINGERPRINT_MAC_KEY, &mac(key, &fingerprint, false))?;
+    Ok(())
+}
+
+/// Record that every ciphertext is bound; call [`establish`] first
+pub fn mark_bound(conn: &rusqlite::Connection, key: &DerivedKey) -> VaultResult<()> {
+    let Some(fingerprint) = db::get_metadata(conn, FINGERPRINT_KEY)? else { return Ok(()) };
+    db::set_metadata(conn, FINGERPRINT_MAC_KEY, &mac(key, &fingerprint, true))?;
+    Ok(())
+}
+
+/// Whether the stored fingerprint was made with this vault's key. A vault
+/// with none stored yet passes.
+pub fn verify(conn: &rusqlite::Connection, key: &DerivedKey) -> VaultResult<bool> {
+    Ok(stored_mac_matches(conn, key, false)? || stored_mac_matches(conn, key, true)?)
+}
+
+/// Whether this vault's key recorded every ciphertext as bound
+pub fn is_bound(conn: &rusqlite::Connection, key: &DerivedKey) -> VaultResult<bool> {
+    Ok(db::get_metadata(conn, FINGERPRINT_KEY)?.is_some() && stored_mac_matches(conn, key, true)?)
+}
+
+fn stored_mac_matches(conn: &rusqlite::Connection, key: &DerivedKey, bound: bool) -> VaultResult<bool> {
+    let Some(fingerprint) = db::get_metadata(conn, FINGERPRINT_KEY)? else { return Ok(true) };
+    let stored = db::get_metadata(conn, FINGERPRINT_MAC_KEY)?.unwrap_or_default();
+    let Ok(stored) = hex::decode(stored) else { return Ok(false) };
+    Ok(hmac(key, &fingerprint, bound).verify_slice(&stored).is_ok())
+}
+
+fn hmac(key: &DerivedKey, fingerprint: &str, bound: bool) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(fingerprint.as_bytes());
+    if bound {
+        mac.update(b"\0ciphertexts_bound");
+    }
+    mac
+}
+
+fn mac(key: &DerivedKey, fingerprint: &str, bound: bool) -> String {
+    hex::encode(hmac(key, fingerprint, bound).finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::key_hierarchy::KeyHierarchy;
+    use crate::crypto::MasterKey;
+    use crate::db::Database;
+
+    fn setup(wrapped_dek: &str) -> Database {
+        let db = Database::open_in_memory().unwrap();
+        db::set_metadata(db.conn(), "wrapped_dek", wrapped_dek).unwrap();
+        db::set_metadata(db.conn(), CREATED_AT_KEY, "2026-01-01T00:00:00+00:00").unwrap();
+        db
+    }
+
+    #[test]
+    fn test_stable_across_rewrap() {
+        let keys = KeyHierarchy::new(MasterKey::from_bytes([0x42u8; 32])).unwrap();
+        let key = keys.derive_fingerprint_key().unwrap();
+        let db = setup(keys.wrapped_dek());
+        let fingerprint = read(db.conn()).unwrap().unwrap();
+        assert_eq!(fingerprint.len(), 19);
+        assert_eq!(fingerprint, compute(keys.wrapped_dek(), "2026-01-01T00:00:00+00:00"));
+
+        establish(db.conn(), &key).unwrap();
+        db::set_metadata(db.conn(), "wrapped_dek", "rewrapped").unwrap();
+        assert_eq!(read(db.conn()).unwrap().unwrap(), fingerprint);
+        assert!(verify(db.conn(), &key).unwrap());
+    }
+
+    #[test]
+    fn test_copied_fingerprint_detected() {
+        let ours = KeyHierarchy::new(MasterKey::from_bytes([0x42u8; 32])).unwrap();
+        let theirs = KeyHierarchy::new(MasterKey::from_bytes([0x43u8; 32])).unwrap();
+        let db = setup(ours.wrapped_dek());
+        establish(db.conn(), &ours.derive_fingerprint_key().unwrap()).unwrap();
+
+        assert!(!verify(db.conn(), &theirs.derive_fingerprint_key().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_bound_needs_the_key() {
+        let ours = KeyHierarchy::new(MasterKey::from_bytes([0x42u8; 32])).unwrap();
+        let theirs = KeyHierarchy::new(MasterKey::from_bytes([0x43u8; 32])).unwrap();
+        let key = ours.derive_fingerprint_key().unwrap();
+        let db = setup(ours.wrapped_dek());
+        establish(db.conn(), &key).unwrap();
+        assert!(!is_bound(db.conn(), &key).unwrap());
+
+        mark_bound(db.conn(), &key).unwrap();
+        assert!(is_bound(db.conn(), &key).unwrap());
+        assert!(verify(db.conn(), &key).unwrap());
+
+        // Rewriting the record without the key does not clear or forge it
+        mark_bound(db.conn(), &theirs.derive_fingerprint_key().unwrap()).unwrap();
+        assert!(!is_bound(db.conn(), &key).unwrap());
+        assert!(!verify(db.conn(), &key).unwrap());
+    }
+}
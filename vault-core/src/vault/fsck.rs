@@ -0,0 +1,331 @@
+//! Integrity Check
+//!
+//! Verifies that every credential decrypts, every ciphertext is bound
+//! to its credential and every sealed field opens, the FTS index matches the
+//! credentials table, required metadata is present and the audit log's hash chain is
+//! unbroken. The FTS index is the only thing that can be
+//! repaired without the user's help, by rebuilding it. Certificates and
+//! API keys that have expired or are about to are reported as warnings.
+
+use chrono::{Local, Utc};
+use secrecy::ExposeSecret;
+
+use crate::crypto::{Certificate, DataEncryptionKey, DerivedKey};
+use crate::db::{self, schema::SCHEMA_VERSION, CredentialType};
+
+use super::audit::{verify_chain, ChainBreak};
+use super::credential::{self, decrypt_credential};
+use super::expiry;
+use super::VaultResult;
+
+/// Metadata keys a usable vault cannot do without
+pub const REQUIRED_METADATA: [&str; 3] = ["schema_version", "password_hash", "wrapped_dek"];
+
+#[derive(Debug, Default)]
+pub struct FsckReport {
+    pub credentials: usize,
+    /// Names of credentials whose secret or notes fail to decrypt
+    pub undecryptable: Vec<String>,
+    /// Names of credentials whose sealed username or URL does not open
+    pub unopenable: Vec<String>,
+    /// Ciphertexts in the format from before they were bound to their credential
+    pub unbound: usize,
+    /// Whether the vault was upgraded, so an unbound ciphertext was put there since
+    pub bound: bool,
+    pub missing_metadata: Vec<&'static str>,
+    pub schema_version: i32,
+    pub fts_consistent: bool,
+    pub fts_repaired: bool,
+    pub audit_entries: usize,
+    pub audit_breaks: Vec<ChainBreak>,
+    /// Certificate credentials past or near their expiry, with the date
+    pub expiring_certificates: Vec<String>,
+    /// API keys past or near their `expires_at` date, with the date
+    pub expiring_api_keys: Vec<String>,
+}
+
+impl FsckReport {
+    /// Problems that are still present after any repairs
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.undecryptable.is_empty() {
+            problems.push(format!(
+                "{} credential(s) fail to decrypt: {}",
+                self.undecryptable.len(),
+                self.undecryptable.join(", ")
+            ));
+        }
+        if !self.unopenable.is_empty() {
+            problems.push(format!(
+                "{} credential(s) have a sealed username or URL that does not open: {}",
+                self.unopenable.len(),
+                self.unopenable.join(", ")
+            ));
+        }
+        if self.bound && self.unbound > 0 {
+            problems.push(format!("{} ciphertext(s) not bound to their credential", self.unbound));
+        }
+        if !self.missing_metadata.is_empty() {
+            problems.push(format!("missing metadata: {}", self.missing_metadata.join(", ")));
+        }
+        if self.schema_version != SCHEMA_VERSION {
+            problems.push(format!("schema version {} (expected {})", self.schema_version, SCHEMA_VERSION));
+        }
+        if !self.fts_consistent && !self.fts_repaired {
+            problems.push("search index has orphaned or missing entries (repair to rebuild)".to_string());
+        }
+        for audit_break in &self.audit_breaks {
+            problems.push(format!("audit log {}", audit_break));
+        }
+        problems
+    }
+
+    /// Things worth attention that are not damage to the vault
+    pub fn warnings(&self) -> Vec<String> {
+        let certificates = self.expiring_certificates.iter().map(|cert| format!("certificate {}", cert));
+        let api_keys = self.expiring_api_keys.iter().map(|key| format!("API key {}", key));
+        let unbound = (!self.bound && self.unbound > 0)
+            .then(|| format!("{} ciphertext(s) in the old format, upgraded at the next read-write unlock", self.unbound));
+        certificates.chain(api_keys).chain(unbound).collect()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.problems().is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        let repaired = if self.fts_repaired { ", search index rebuilt" } else { "" };
+        format!(
+            "{} credentials, {} audit entries checked{}",
+            self.credentials, self.audit_entries, repaired
+        )
+    }
+}
+
+/// Run every check, rebuilding the FTS index when `repair` is set
+pub fn check_vault(
+    conn: &rusqlite::Connection,
+    dek: &DataEncryptionKey,
+    audit_key: &DerivedKey,
+    repair: bool,
+) -> VaultResult<FsckReport> {
+    let mut report = FsckReport::default();
+
+    check_credentials(conn, dek, &mut report)?;
+    check_metadata(conn, &mut report)?;
+    check_fts(conn, repair, &mut report)?;
+    check_audit(conn, audit_key, &mut report)?;
+
+    Ok(report)
+}
+
+fn check_credentials(conn: &rusqlite::Connection, dek: &DataEncryptionKey, report: &mut FsckReport) -> VaultResult<()> {
+    let credentials = db::get_all_credentials(conn)?;
+    report.credentials = credentials.len();
+    report.unbound = credential::count_unbound(conn)?;
+    report.bound = dek.requires_bound();
+    report.unopenable = db::fields::unopenable(conn)?;
+    let now = Utc::now();
+    let today = Local::now().date_naive();
+    for cred in &credentials {
+        let Ok(decrypted) = decrypt_credential(conn, dek, cred, false) else {
+            report.undecryptable.push(cred.name.clone());
+            continue;
+        };
+        if let Some(expires) = cred.expires_on()
+            && expiry::expires_soon(expires, today)
+        {
+            report.expiring_api_keys.push(format!("{} expires {}", cred.name, expiry::summary(expires, today)));
+        }
+        if cred.credential_type != CredentialType::Certificate {
+            continue;
+        }
+        let Some(secret) = &decrypted.secret else { continue };
+        if let Ok(cert) = Certificate::from_pem(secret.expose_secret())
+            && cert.expires_soon(now)
+        {
+            report.expiring_certificates.push(format!("{} expires {}", cred.name, cert.expiry_summary(now)));
+        }
+    }
+    Ok(())
+}
+
+fn check_metadata(conn: &rusqlite::Connection, report: &mut FsckReport) -> VaultResult<()> {
+    let keys = db::get_metadata_keys(conn)?;
+    report.missing_metadata = REQUIRED_METADATA
+        .into_iter()
+        .filter(|required| !keys.iter().any(|k| k == required))
+        .collect();
+    report.schema_version = db::schema::get_schema_version(conn)?;
+    Ok(())
+}
+
+fn check_fts(conn: &rusqlite::Connection, repair: bool, report: &mut FsckReport) -> VaultResult<()> {
+    // With the index kept in memory there is nothing on disk to check
+    if !db::has_fts(conn)? {
+        report.fts_consistent = true;
+        return Ok(());
+    }
+    report.fts_consistent = db::fts_integrity_check(conn)?;
+    if !report.fts_consistent && repair {
+        db::rebuild_fts(conn)?;
+        report.fts_repaired = db::fts_integrity_check(conn)?;
+    }
+    Ok(())
+}
+
+fn check_audit(conn: &rusqlite::Connection, audit_key: &DerivedKey, report: &mut FsckReport) -> VaultResult<()> {
+    let chain = verify_chain(conn, audit_key)?;
+    report.audit_entries = chain.total;
+    report.audit_breaks = chain.breaks;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::key_hierarchy::KeyHierarchy;
+    use crate::crypto::encryption::{self, Format};
+    use crate::crypto::MasterKey;
+    use crate::db::{AuditAction, CredentialType, Database};
+    use crate::vault::{audit, credential};
+
+    fn setup() -> (Database, KeyHierarchy) {
+        let db = Database::open_in_memory().unwrap();
+        let keys = KeyHierarchy::new(MasterKey::from_bytes([0x42u8; 32])).unwrap();
+        for (key, value) in [("password_hash", "hash"), ("wrapped_dek", keys.wrapped_dek())] {
+            db.conn()
+                .execute("INSERT INTO metadata (key, value) VALUES (?1, ?2)", [key, value])
+                .unwrap();
+        }
+        (db, keys)
+    }
+
+    fn add_credential(db: &Database, keys: &KeyHierarchy, name: &str) {
+        credential::create_credential(
+            db.conn(),
+            keys.dek(),
+            credential::NewCredential {
+                notes: Some("notes"),
+                ..credential::NewCredential::new(name.to_string(), CredentialType::Password, "secret")
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_clean_vault() {
+        let (db, keys) = setup();
+        let audit_key = keys.derive_audit_key().unwrap();
+        add_credential(&db, &keys, "GitHub");
+        audit::log_action(db.conn(), &audit_key, AuditAction::Create, None, Some("GitHub"), None, None).unwrap();
+
+        let report = check_vault(db.conn(), keys.dek(), &audit_key, false).unwrap();
+        assert!(report.is_clean(), "{:?}", report.problems());
+        assert_eq!(report.credentials, 1);
+        assert_eq!(report.audit_entries, 1);
+    }
+
+    #[test]
+    fn test_detects_problems() {
+        let (db, keys) = setup();
+        let audit_key = keys.derive_audit_key().unwrap();
+        add_credential(&db, &keys, "GitHub");
+        audit::log_action(db.conn(), &audit_key, AuditAction::Create, None, Some("GitHub"), None, None).unwrap();
+
+        let conn = db.conn();
+        conn.execute("UPDATE credentials SET encrypted_secret = 'garbage'", []).unwrap();
+        conn.execute("UPDATE audit_log SET details = 'edited'", []).unwrap();
+        conn.execute("DELETE FROM metadata WHERE key = 'wrapped_dek'", []).unwrap();
+
+        let report = check_vault(conn, keys.dek(), &audit_key, false).unwrap();
+        assert_eq!(report.undecryptable, vec!["GitHub"]);
+        assert_eq!(report.audit_breaks.len(), 1);
+        assert_eq!(report.missing_metadata, vec!["wrapped_dek"]);
+    }
+
+    #[test]
+    fn test_flags_unbound_ciphertexts() {
+        let (db, mut keys) = setup();
+        let audit_key = keys.derive_audit_key().unwrap();
+        add_credential(&db, &keys, "GitHub");
+        let legacy = encryption::seal_as(Format::Legacy, keys.dek().as_ref(), "secret", b"");
+        db.conn().execute("UPDATE credentials SET encrypted_secret = ?1", [&legacy]).unwrap();
+
+        let report = check_vault(db.conn(), keys.dek(), &audit_key, false).unwrap();
+        assert_eq!(report.unbound, 1);
+        assert!(report.is_clean());
+        assert_eq!(report.warnings().len(), 1);
+
+        keys.require_bound();
+        let report = check_vault(db.conn(), keys.dek(), &audit_key, false).unwrap();
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn test_warns_about_expiring_api_keys() {
+        let (db, keys) = setup();
+        let audit_key = keys.derive_audit_key().unwrap();
+        let mut cred = credential::create_credential(
+            db.conn(),
+            keys.dek(),
+            credential::NewCredential::new("Stripe".to_string(), CredentialType::ApiKey, "sk_live"),
+        )
+        .unwrap();
+        let attributes = [("expires_at".to_string(), "2020-01-01".to_string())].into();
+        credential::set_attributes(db.conn(), &mut cred, attributes).unwrap();
+
+        let report = check_vault(db.conn(), keys.dek(), &audit_key, false).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.warnings().len(), 1);
+        assert!(report.warnings()[0].starts_with("API key Stripe expires 2020-01-01 (expired"));
+    }
+
+    #[cfg(feature = "x509")]
+    #[test]
+    fn test_warns_about_expired_certificates() {
+        const EXPIRED: &str = "-----BEGIN CERTIFICATE-----
+MIIBiTCCAS+gAwIBAgIUcCxO0bHhGgOsfR3ZyYrX/w+72nYwCgYIKoZIzj0EAwIw
+GjEYMBYGA1UEAwwPb2xkLmV4YW1wbGUuY29tMB4XDTIwMDEwMTAwMDAwMFoXDTIx
+MDEwMTAwMDAwMFowGjEYMBYGA1UEAwwPb2xkLmV4YW1wbGUuY29tMFkwEwYHKoZI
+zj0CAQYIKoZIzj0DAQcDQgAE4w/oCbDGcfUOGV192SjT8YXnuqNV0UGDRj3+v2sh
+WvvRHqqjCZ3viYhARs81oZxcYxrLyALawqQJOKT8ua6KyaNTMFEwHQYDVR0OBBYE
+FAx/77Q2bU46xg7PtjJSoBoQdwQrMB8GA1UdIwQYMBaAFAx/77Q2bU46xg7PtjJS
+oBoQdwQrMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIhAPQV0c9h
+x4YVrZTojSJYCx4R632iRA3ua05czvSMNUN/AiAkn6qGO8S8BM64A9JZaRt6LP+J
+tPwJQ0gUeBHkAWVDzg==
+-----END CERTIFICATE-----
+";
+        let (db, keys) = setup();
+        let audit_key = keys.derive_audit_key().unwrap();
+        credential::create_credential(
+            db.conn(),
+            keys.dek(),
+            credential::NewCredential::new("old.example.com".to_string(), CredentialType::Certificate, EXPIRED),
+        )
+        .unwrap();
+
+        let report = check_vault(db.conn(), keys.dek(), &audit_key, false).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.warnings().len(), 1);
+        assert!(report.warnings()[0].starts_with("certificate old.example.com expires 2021-01-01 (expired"));
+    }
+
+    #[test]
+    fn test_repairs_fts() {
+        let (db, keys) = setup();
+        let audit_key = keys.derive_audit_key().unwrap();
+        add_credential(&db, &keys, "GitHub");
+        db.conn()
+            .execute("INSERT INTO credentials_fts(rowid, name, username, url, tags) VALUES (999, 'ghost', '', '', '[]')", [])
+            .unwrap();
+
+        let report = check_vault(db.conn(), keys.dek(), &audit_key, false).unwrap();
+        assert!(!report.fts_consistent);
+        assert!(!report.is_clean());
+
+        let report = check_vault(db.conn(), keys.dek(), &audit_key, true).unwrap();
+        assert!(report.fts_repaired);
+        assert!(report.is_clean());
+    }
+}
@@ -0,0 +1,74 @@
+//! Background key derivation
+//!
+//! Argon2 takes long enough to freeze the terminal, so the password dialogs
+//! hand it to a worker thread and poll for the result while they redraw.
+//! Dropping the task cancels it: the worker still finishes, but nobody is
+//! listening for its result.
+
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{VaultError, VaultResult};
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const SPINNER_FRAME: Duration = Duration::from_millis(100);
+
+pub struct KdfTask<T> {
+    receiver: Receiver<VaultResult<T>>,
+    started: Instant,
+}
+
+impl<T: Send + 'static> KdfTask<T> {
+    pub fn spawn(work: impl FnOnce() -> VaultResult<T> + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(work());
+        });
+        Self { receiver, started: Instant::now() }
+    }
+}
+
+impl<T> KdfTask<T> {
+    /// The result once the worker is done, `None` while it is still busy
+    pub fn poll(&self) -> Option<VaultResult<T>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                Some(Err(VaultError::CryptoError("key derivation stopped unexpectedly".to_string())))
+            }
+        }
+    }
+
+    pub fn spinner(&self) -> char {
+        let frame = self.started.elapsed().as_millis() / SPINNER_FRAME.as_millis();
+        SPINNER[frame as usize % SPINNER.len()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait<T>(task: &KdfTask<T>) -> VaultResult<T> {
+        loop {
+            if let Some(result) = task.poll() {
+                return result;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_kdf_task() {
+        let task = KdfTask::spawn(|| Ok(42));
+        assert_eq!(wait(&task).unwrap(), 42);
+
+        let failed = KdfTask::<()>::spawn(|| Err(VaultError::InvalidPassword));
+        assert!(matches!(wait(&failed), Err(VaultError::InvalidPassword)));
+
+        let panicked = KdfTask::<()>::spawn(|| panic!("worker died"));
+        assert!(matches!(wait(&panicked), Err(VaultError::CryptoError(_))));
+    }
+}
@@ -0,0 +1,1362 @@
+//! Vault Manager
+//!
+//! Core vault state, including storage, key management, and lock/unlock flow.
+//!
+//! Uses a wrapped DEK (Data Encryption Key) model so password changes do not
+//! require re-encrypting stored data.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::crypto::{
+    derive_code_key, derive_master_key, verify_master_key, DataEncryptionKey, DerivedKey, KdfParams, KeyHierarchy, MasterKey,
+};
+use crate::db::{self, AuditAction, Database, DatabaseConfig, KeySlot};
+
+use super::audit;
+use super::backup::OpenedBackup;
+use super::emergency::{self, EmergencyUnlock, EMERGENCY_UNLOCKS_KEY};
+use super::{compact, credential, fingerprint};
+use super::relocate;
+use super::search_index::SearchIndexMode;
+use super::{VaultError, VaultResult};
+
+/// Consecutive failures allowed before unlock attempts are delayed
+const FREE_UNLOCK_ATTEMPTS: u32 = 2;
+/// Upper bound on the delay between unlock attempts
+const MAX_UNLOCK_DELAY: Duration = Duration::from_secs(300);
+/// Label of the slot kept in the metadata table, created with the vault
+pub const PRIMARY_SLOT: &str = "primary";
+/// Set once the secret history is sealed under its own field
+const HISTORY_BOUND_KEY: &str = "history_bound";
+/// Metadata key of the state the frontend saves between sessions
+const SESSION_KEY: &str = "last_session";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VaultState {
+    Uninitialized,
+    Locked,
+    Unlocked,
+}
+
+#[derive(Debug, Clone)]
+pub struct VaultConfig {
+    pub path: PathBuf,
+    pub auto_lock_timeout: Duration,
+    /// Unlock without write access, e.g. to inspect a backup
+    pub read_only: bool,
+    /// Destroy the wrapped DEK after this many consecutive failed unlocks; 0 never
+    pub wipe_after_failures: u32,
+    /// Whether the FTS index is kept in the vault file
+    pub search_index: SearchIndexMode,
+    /// Store usernames and URLs encrypted with the data key. The on-disk
+    /// search index would hold them in plaintext, so it is dropped and
+    /// searches use the in-memory one.
+    pub encrypt_metadata: bool,
+}
+
+/// Result of recording a failed unlock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailedUnlock {
+    /// Consecutive failures, including this one
+    pub attempts: u32,
+    /// How long until the next attempt is accepted
+    pub retry_after: Duration,
+    /// The wrapped DEK was destroyed; the vault can no longer be opened
+    pub wiped: bool,
+}
+
+impl Default for VaultConfig {
+    fn default() -> Self {
+        let path = dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("credlock")
+            .join("vault.db");
+
+        Self {
+            path,
+            auto_lock_timeout: Duration::from_secs(300),
+            read_only: false,
+            wipe_after_failures: 0,
+            search_index: SearchIndexMode::default(),
+            encrypt_metadata: false,
+        }
+    }
+}
+
+impl VaultConfig {
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+}
+
+pub struct Vault {
+    config: VaultConfig,
+    db: Option<Database>,
+    key_hierarchy: Option<KeyHierarchy>,
+    password_hash: Option<String>,
+    /// Key slot the vault was unlocked with, `None` for the primary one
+    active_slot: Option<String>,
+    /// Backup shown in place of the vault's own database
+    inspected: Option<OpenedBackup>,
+    last_activity: Instant,
+}
+
+impl Vault {
+    pub fn new(config: VaultConfig) -> Self {
+        Self {
+            config,
+            db: None,
+            key_hierarchy: None,
+            password_hash: None,
+            active_slot: None,
+            inspected: None,
+            last_activity: Instant::now(),
+        }
+    }
+
+    pub fn with_default_config() -> Self {
+        Self::new(VaultConfig::default())
+    }
+
+    pub fn state(&self) -> VaultState {
+        if self.key_hierarchy.is_some() {
+            return VaultState::Unlocked;
+        }
+        if self.config.path.exists() {
+            return VaultState::Locked;
+        }
+        VaultState::Uninitialized
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.state() == VaultState::Unlocked
+    }
+
+    /// Opened read-only, or showing a backup
+    pub fn is_read_only(&self) -> bool {
+        self.config.read_only || self.inspected.is_some()
+    }
+
+    pub fn initialize(&mut self, password: &str) -> VaultResult<()> {
+        self.ensure_can_initialize()?;
+        let (master_key, password_hash) = Self::derive_key(password)?;
+        self.initialize_with_key(master_key, password_hash)
+    }
+
+    /// Create the vault from a key already derived with [`Vault::derive_key`]
+    pub fn initialize_with_key(&mut self, master_key: MasterKey, password_hash: String) -> VaultResult<()> {
+        self.ensure_can_initialize()?;
+        self.create_parent_directory()?;
+        let mut key_hierarchy = self.create_key_hierarchy(master_key)?;
+        let db = self.open_database()?;
+        self.seal_fields(db.conn(), key_hierarchy.dek())?;
+        self.sync_search_index(db.conn())?;
+
+        let tx = db.conn().unchecked_transaction()?;
+        Self::store_password_hash(&tx, &password_hash)?;
+        Self::store_wrapped_dek(&tx, key_hierarchy.wrapped_dek())?;
+        Self::set_metadata_value(&tx, fingerprint::CREATED_AT_KEY, &chrono::Local::now().to_rfc3339())?;
+        let fingerprint_key = Self::fingerprint_key(&key_hierarchy)?;
+        fingerprint::establish(&tx, &fingerprint_key)?;
+        fingerprint::mark_bound(&tx, &fingerprint_key)?;
+        tx.commit()?;
+        key_hierarchy.require_bound();
+
+        self.db = Some(db);
+        self.key_hierarchy = Some(key_hierarchy);
+        self.password_hash = Some(password_hash);
+        self.update_activity();
+
+        Ok(())
+    }
+
+    pub fn unlock(&mut self, password: &str) -> VaultResult<()> {
+        self.unlock_without_index(password)?;
+        self.apply_search_index()
+    }
+
+    /// Unlock, leaving the on-disk search index as it is. Building or
+    /// dropping it can take seconds on a large vault, so the TUI does it
+    /// with `apply_search_index` once the list is on screen; searches in
+    /// the meantime fall back to the in-memory index.
+    pub fn unlock_without_index(&mut self, password: &str) -> VaultResult<()> {
+        if !self.config.path.exists() {
+            return Err(VaultError::NotFound);
+        }
+
+        let db = self.open_database()?;
+        Self::ensure_not_wiped(db.conn())?;
+        Self::ensure_unlock_allowed(db.conn())?;
+
+        let (slot, stored_hash, master_key, wrapped_dek) = Self::open_key_slot(db.conn(), password)?;
+        let mut key_hierarchy = Self::reconstruct_key_hierarchy(master_key, wrapped_dek)?;
+        if let Some(label) = slot.as_deref().filter(|l| emergency::is_emergency_slot(l)) {
+            self.spend_emergency_code(label, &key_hierarchy)?;
+        }
+        if Self::get_metadata_value(db.conn(), "consecutive_failed_unlocks").is_some() {
+            let _ = self.open_writable_database().and_then(|db| Self::reset_unlock_throttle(db.conn()));
+        }
+        let fingerprint_key = Self::fingerprint_key(&key_hierarchy)?;
+        if !self.config.read_only {
+            fingerprint::establish(db.conn(), &fingerprint_key)?;
+            Self::bind_ciphertexts(db.conn(), key_hierarchy.dek(), &fingerprint_key)?;
+        }
+        if Self::ciphertexts_bound(db.conn(), &fingerprint_key)? {
+            key_hierarchy.require_bound();
+        }
+        self.seal_fields(db.conn(), key_hierarchy.dek())?;
+
+        self.db = Some(db);
+        self.key_hierarchy = Some(key_hierarchy);
+        self.password_hash = Some(stored_hash);
+        self.active_slot = slot;
+        self.update_activity();
+
+        Ok(())
+    }
+
+    pub fn lock(&mut self) {
+        self.inspected = None;
+        self.db = None;
+        self.key_hierarchy = None;
+        self.password_hash = None;
+        self.active_slot = None;
+    }
+
+    pub fn should_auto_lock(&self) -> bool {
+        self.is_unlocked() && self.last_activity.elapsed() > self.config.auto_lock_timeout
+    }
+
+    /// Time left before auto-lock kicks in, `None` while locked
+    pub fn time_until_auto_lock(&self) -> Option<Duration> {
+        self.is_unlocked()
+            .then(|| self.config.auto_lock_timeout.saturating_sub(self.last_activity.elapsed()))
+    }
+
+    /// Time since the last recorded activity
+    pub fn idle_time(&self) -> Duration {
+        self.last_activity.elapsed()
+    }
+
+    pub fn update_activity(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    /// The vault's database, or the backup being inspected
+    pub fn db(&self) -> VaultResult<&Database> {
+        let live = self.live_db()?;
+        Ok(self.inspected.as_ref().map_or(live, OpenedBackup::db))
+    }
+
+    /// The vault's own database, even while a backup is inspected
+    pub fn live_db(&self) -> VaultResult<&Database> {
+        self.db.as_ref().ok_or(VaultError::Locked)
+    }
+
+    /// Show a backup opened with [`open_backup`](super::backup::open_backup)
+    /// in place of the vault: reads go to it and every change is refused
+    /// until [`Vault::close_inspected`] or the vault locks
+    pub fn inspect(&mut self, backup: OpenedBackup) -> VaultResult<()> {
+        self.db.as_ref().ok_or(VaultError::Locked)?;
+        self.inspected = Some(backup);
+        Ok(())
+    }
+
+    /// Go back to the vault's own database; returns whether a backup was shown
+    pub fn close_inspected(&mut self) -> bool {
+        self.inspected.take().is_some()
+    }
+
+    /// The backup file being inspected, if any
+    pub fn inspected(&self) -> Option<&Path> {
+        self.inspected.as_ref().map(|b| b.source.as_path())
+    }
+
+    pub fn keys(&self) -> VaultResult<&KeyHierarchy> {
+        self.key_hierarchy.as_ref().ok_or(VaultError::Locked)
+    }
+
+    pub fn dek(&self) -> VaultResult<&DataEncryptionKey> {
+        Ok(self.keys()?.dek())
+    }
+
+    pub fn master_key(&self) -> VaultResult<&MasterKey> {
+        Ok(self.keys()?.master_key())
+    }
+
+    pub fn verify_password(&self, password: &str) -> VaultResult<()> {
+        let hash = self.password_hash.as_ref().ok_or(VaultError::Locked)?;
+        verify_master_key(password.as_bytes(), hash).map_err(|_| VaultError::InvalidPassword)?;
+        Ok(())
+    }
+
+    /// [`Vault::verify_password`] detached from the vault, so the slow
+    /// check can run on another thread
+    pub fn password_verifier(&self) -> VaultResult<impl FnOnce(&str) -> VaultResult<()> + Send + 'static> {
+        let hash = self.password_hash.clone().ok_or(VaultError::Locked)?;
+        Ok(move |password: &str| Self::verify_password_and_get_key(password, &hash).map(drop))
+    }
+
+    /// Derive a new master key and its hash with a fresh salt. This is the
+    /// slow part of creating a vault, changing a password or adding a key
+    /// slot, and needs no vault, so it can run on another thread.
+    pub fn derive_key(password: &str) -> VaultResult<(MasterKey, String)> {
+        derive_master_key(password.as_bytes(), &KdfParams::default())
+            .map_err(|e| VaultError::CryptoError(e.to_string()))
+    }
+
+    pub fn config(&self) -> &VaultConfig {
+        &self.config
+    }
+
+    pub fn set_auto_lock_timeout(&mut self, timeout: Duration) {
+        self.config.auto_lock_timeout = timeout;
+    }
+
+    /// Move the vault file to `path` and carry on from there. The old file
+    /// is deleted only once the new one is open; returns whether deleting
+    /// it worked.
+    pub fn relocate(&mut self, path: &Path) -> VaultResult<bool> {
+        if self.is_read_only() {
+            return Err(VaultError::ReadOnly);
+        }
+        relocate::copy_verified(self.db()?.conn(), path)?;
+
+        let old_path = std::mem::replace(&mut self.config.path, path.to_path_buf());
+        let db = match self.open_database() {
+            Ok(db) => db,
+            Err(e) => {
+                self.config.path = old_path;
+                let _ = relocate::remove_vault_file(path);
+                return Err(e);
+            }
+        };
+        self.seal_fields(db.conn(), self.dek()?)?;
+        self.sync_search_index(db.conn())?;
+        // Closing the old connection checkpoints its WAL before the delete
+        self.db = Some(db);
+        Ok(relocate::remove_vault_file(&old_path).is_ok())
+    }
+
+    pub fn change_password(&mut self, old_password: &str, new_password: &str) -> VaultResult<()> {
+        if self.is_read_only() {
+            return Err(VaultError::ReadOnly);
+        }
+        self.ensure_not_emergency("change the password")?;
+        self.verify_password(old_password)?;
+        let (new_master_key, new_hash) = Self::derive_key(new_password)?;
+        self.change_password_with_key(new_master_key, new_hash)
+    }
+
+    /// Switch the active slot to a key derived with [`Vault::derive_key`].
+    /// The caller is responsible for having checked the current password.
+    pub fn change_password_with_key(&mut self, new_master_key: MasterKey, new_hash: String) -> VaultResult<()> {
+        if self.is_read_only() {
+            return Err(VaultError::ReadOnly);
+        }
+        self.ensure_not_emergency("change the password")?;
+        let new_wrapped_dek = self.rewrap_dek(new_master_key)?;
+
+        let db = self.db.as_ref().ok_or(VaultError::Locked)?;
+        match &self.active_slot {
+            Some(label) => {
+                let mut slot = db::get_key_slots(db.conn())?
+                    .into_iter()
+                    .find(|s| &s.label == label)
+                    .ok_or(VaultError::NotFound)?;
+                slot.password_hash = new_hash.clone();
+                slot.wrapped_dek = new_wrapped_dek;
+                db::save_key_slot(db.conn(), &slot)?;
+            }
+            None => {
+                // A hash without its matching wrapped DEK would lock everyone out
+                let tx = db.conn().unchecked_transaction()?;
+                Self::store_password_hash(&tx, &new_hash)?;
+                Self::store_wrapped_dek(&tx, &new_wrapped_dek)?;
+                tx.commit()?;
+            }
+        }
+
+        self.password_hash = Some(new_hash);
+        self.update_activity();
+
+        Ok(())
+    }
+
+    /// Label of the slot the vault was unlocked with
+    pub fn active_slot(&self) -> &str {
+        self.active_slot.as_deref().unwrap_or(PRIMARY_SLOT)
+    }
+
+    /// All slot labels, the primary one first
+    pub fn key_slots(&self) -> VaultResult<Vec<String>> {
+        let db = self.db()?;
+        let mut labels = vec![PRIMARY_SLOT.to_string()];
+        labels.extend(db::get_key_slots(db.conn())?.into_iter().map(|s| s.label));
+        Ok(labels)
+    }
+
+    /// Let another password unlock the same DEK. The slot gets its own
+    /// Argon2 salt, so the passwords share nothing but the data key.
+    pub fn add_key_slot(&mut self, label: &str, password: &str) -> VaultResult<()> {
+        if self.is_read_only() {
+            return Err(VaultError::ReadOnly);
+        }
+        self.ensure_not_emergency("add key slots")?;
+        self.ensure_slot_available(label)?;
+        let (master_key, password_hash) = Self::derive_key(password)?;
+        self.add_key_slot_with_key(label, master_key, password_hash)
+    }
+
+    /// [`Vault::add_key_slot`] with a key derived with [`Vault::derive_key`]
+    pub fn add_key_slot_with_key(&mut self, label: &str, master_key: MasterKey, password_hash: String) -> VaultResult<()> {
+        if self.is_read_only() {
+            return Err(VaultError::ReadOnly);
+        }
+        self.ensure_not_emergency("add key slots")?;
+        self.ensure_slot_available(label)?;
+        let wrapped_dek = self.dek()?.wrap(&master_key).map_err(|e| VaultError::CryptoError(e.to_string()))?;
+        let slot = KeySlot { label: label.to_string(), password_hash, wrapped_dek, created_at: chrono::Local::now() };
+        let db = self.db()?;
+        let tx = db.conn().unchecked_transaction()?;
+        db::save_key_slot(&tx, &slot)?;
+        self.log_key_slot_change(&tx, &format!("Key slot '{}' added", label))?;
+        tx.commit()?;
+        self.update_activity();
+        Ok(())
+    }
+
+    /// Revoke a slot's password. The primary slot and the one in use stay.
+    /// Its wrapped DEK is overwritten before the row goes, so no copy the
+    /// old password opens is left in the file.
+    pub fn remove_key_slot(&mut self, label: &str) -> VaultResult<()> {
+        if self.is_read_only() {
+            return Err(VaultError::ReadOnly);
+        }
+        self.ensure_not_emergency("remove key slots")?;
+        if label == PRIMARY_SLOT {
+            return Err(VaultError::OperationFailed("The primary key slot cannot be removed".to_string()));
+        }
+        if label == self.active_slot() {
+            return Err(VaultError::OperationFailed("Cannot remove the key slot in use".to_string()));
+        }
+        let conn = self.db()?.conn();
+        conn.execute_batch("PRAGMA secure_delete = ON")?;
+        let tx = conn.unchecked_transaction()?;
+        Self::shred_key_slot(&tx, label)?;
+        self.log_key_slot_change(&tx, &format!("Key slot '{}' removed", label))?;
+        tx.commit()?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        self.update_activity();
+        Ok(())
+    }
+
+    /// Replace any unused emergency codes with `count` new ones. Returns
+    /// each slot's label and code; the codes are not stored anywhere.
+    pub fn create_emergency_codes(&mut self, count: usize) -> VaultResult<Vec<(String, String)>> {
+        if self.is_read_only() {
+            return Err(VaultError::ReadOnly);
+        }
+        self.ensure_not_emergency("issue new emergency codes")?;
+        let dek = self.dek()?;
+        let mut slots = Vec::with_capacity(count);
+        let mut codes = Vec::with_capacity(count);
+        for n in 1..=count {
+            let code = emergency::generate_code();
+            let (master_key, password_hash) =
+                derive_code_key(code.as_bytes()).map_err(|e| VaultError::CryptoError(e.to_string()))?;
+            let wrapped_dek = dek.wrap(&master_key).map_err(|e| VaultError::CryptoError(e.to_string()))?;
+            let label = format!("{}{}", emergency::EMERGENCY_SLOT_PREFIX, n);
+            slots.push(KeySlot { label: label.clone(), password_hash, wrapped_dek, created_at: chrono::Local::now() });
+            codes.push((label, code));
+        }
+
+        let conn = self.db()?.conn();
+        conn.execute_batch("PRAGMA secure_delete = ON")?;
+        let tx = conn.unchecked_transaction()?;
+        for old in db::get_key_slots(&tx)?.into_iter().filter(|s| emergency::is_emergency_slot(&s.label)) {
+            Self::shred_key_slot(&tx, &old.label)?;
+        }
+        for slot in &slots {
+            db::save_key_slot(&tx, slot)?;
+        }
+        self.log_key_slot_change(&tx, &format!("{} emergency codes issued, replacing any unused", count))?;
+        tx.commit()?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        self.update_activity();
+        Ok(codes)
+    }
+
+    /// Emergency codes used since the owner last unlocked, clearing them.
+    /// Kept for later while a code or a read-only session has the vault
+    /// open, so they reach someone who can act on them.
+    pub fn take_emergency_unlocks(&self) -> VaultResult<Vec<EmergencyUnlock>> {
+        let db = self.db.as_ref().ok_or(VaultError::Locked)?;
+        if db.is_read_only() || emergency::is_emergency_slot(self.active_slot()) {
+            return Ok(Vec::new());
+        }
+        let Some(value) = Self::get_metadata_value(db.conn(), EMERGENCY_UNLOCKS_KEY) else {
+            return Ok(Vec::new());
+        };
+        db.conn().execute("DELETE FROM metadata WHERE key = ?1", [EMERGENCY_UNLOCKS_KEY])?;
+        Ok(EmergencyUnlock::parse_all(&value))
+    }
+
+    /// Where the frontend left off, as it saved it with
+    /// [`Vault::save_session`]
+    pub fn saved_session(&self) -> VaultResult<Option<String>> {
+        Ok(Self::get_metadata_value(self.db()?.conn(), SESSION_KEY))
+    }
+
+    /// Keep the frontend's state for the next unlock. Stored as given, so
+    /// the caller seals anything it would not leave in plaintext.
+    pub fn save_session(&self, session: &str) -> VaultResult<()> {
+        Self::set_metadata_value(self.db()?.conn(), SESSION_KEY, session)
+    }
+
+    /// Count a failed unlock, delay the next attempt and, once the
+    /// configured limit is reached, destroy the wrapped DEK. Recorded even
+    /// in read-only mode so it cannot be used to guess without limits.
+    pub fn record_failed_unlock(&self) -> VaultResult<FailedUnlock> {
+        if !self.config.path.exists() {
+            return Err(VaultError::NotFound);
+        }
+        let db = self.open_writable_database()?;
+        let tx = db.conn().unchecked_transaction()?;
+
+        Self::increment_failed_unlock_counter(&tx)?;
+        Self::update_failed_unlock_timestamp(&tx)?;
+
+        let attempts = Self::increment_consecutive_failures(&tx)?;
+        let wipe_after = self.config.wipe_after_failures;
+        if wipe_after > 0 && attempts >= wipe_after {
+            // The wipe checkpoints the WAL, which cannot happen mid-transaction
+            tx.commit()?;
+            Self::wipe_wrapped_dek(db.conn())?;
+            return Ok(FailedUnlock { attempts, retry_after: Duration::ZERO, wiped: true });
+        }
+
+        let retry_after = unlock_delay(attempts);
+        let not_before = chrono::Local::now().timestamp() + retry_after.as_secs() as i64;
+        Self::set_metadata_value(&tx, "unlock_not_before", &not_before.to_string())?;
+        tx.commit()?;
+
+        Ok(FailedUnlock { attempts, retry_after, wiped: false })
+    }
+
+    pub fn take_pending_failed_attempts(&self) -> VaultResult<Option<(u32, String)>> {
+        let db = self.db.as_ref().ok_or(VaultError::Locked)?;
+
+        let count = Self::get_metadata_value(db.conn(), "pending_failed_unlocks");
+        let timestamp = Self::get_metadata_value(db.conn(), "last_failed_unlock_at");
+
+        // Left in place so the next writable unlock still reports them
+        if db.is_read_only() {
+            return Ok(None);
+        }
+        Self::clear_failed_attempt_metadata(db.conn())?;
+
+        Self::parse_failed_attempts(count, timestamp)
+    }
+}
+
+impl Vault {
+    fn create_parent_directory(&self) -> VaultResult<()> {
+        let Some(parent) = self.config.path.parent() else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(parent).map_err(|e| VaultError::IoError(e.to_string()))
+    }
+
+    fn ensure_can_initialize(&self) -> VaultResult<()> {
+        if self.config.path.exists() {
+            return Err(VaultError::AlreadyExists);
+        }
+        if self.is_read_only() {
+            return Err(VaultError::ReadOnly);
+        }
+        Ok(())
+    }
+
+    /// A session opened with an emergency code gets the vault once; it
+    /// cannot leave itself, or anyone else, a way back in
+    fn ensure_not_emergency(&self, what: &str) -> VaultResult<()> {
+        match emergency::is_emergency_slot(self.active_slot()) {
+            true => Err(VaultError::OperationFailed(format!("A vault opened with an emergency code cannot {}", what))),
+            false => Ok(()),
+        }
+    }
+
+    fn ensure_slot_available(&self, label: &str) -> VaultResult<()> {
+        validate_slot_label(label)?;
+        if self.key_slots()?.iter().any(|l| l == label) {
+            return Err(VaultError::OperationFailed(format!("Key slot '{}' already exists", label)));
+        }
+        Ok(())
+    }
+
+    fn create_key_hierarchy(&self, master_key: MasterKey) -> VaultResult<KeyHierarchy> {
+        KeyHierarchy::new(master_key).map_err(|e| VaultError::CryptoError(e.to_string()))
+    }
+
+    fn open_database(&self) -> VaultResult<Database> {
+        let db_config = DatabaseConfig {
+            read_only: self.config.read_only,
+            ..DatabaseConfig::with_path(&self.config.path)
+        };
+        Database::open(db_config).map_err(Into::into)
+    }
+
+    /// Connection for bookkeeping that must be written even when the vault
+    /// itself is opened read-only
+    fn open_writable_database(&self) -> VaultResult<Database> {
+        Database::open(DatabaseConfig::with_path(&self.config.path)).map_err(Into::into)
+    }
+
+    /// Revoke the emergency code just used, even when opening read-only,
+    /// and leave word of it in the audit log and for the owner. The vault
+    /// stays locked if any of it fails, so a code never opens it twice.
+    fn spend_emergency_code(&self, label: &str, keys: &KeyHierarchy) -> VaultResult<()> {
+        let db = self.open_writable_database()?;
+        let audit_key = keys.derive_audit_key().map_err(|e| VaultError::CryptoError(e.to_string()))?;
+        let used = EmergencyUnlock { label: label.to_string(), at: chrono::Local::now() };
+        let mut pending = Self::get_metadata_value(db.conn(), EMERGENCY_UNLOCKS_KEY).unwrap_or_default();
+        pending.push_str(&used.to_line());
+        pending.push('\n');
+
+        db.conn().execute_batch("PRAGMA secure_delete = ON")?;
+        let tx = db.conn().unchecked_transaction()?;
+        Self::shred_key_slot(&tx, label)?;
+        Self::set_metadata_value(&tx, EMERGENCY_UNLOCKS_KEY, &pending)?;
+        let details = format!("EMERGENCY unlock with single-use code {}, now revoked", label);
+        audit::log_action(&tx, &audit_key, AuditAction::Unlock, None, None, None, Some(&details))?;
+        tx.commit()?;
+        db.conn().query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    fn verify_password_and_get_key(password: &str, stored_hash: &str) -> VaultResult<MasterKey> {
+        verify_master_key(password.as_bytes(), stored_hash)
+            .map_err(|_| VaultError::InvalidPassword)
+    }
+
+    /// Try the primary slot, then each extra one, returning the matching
+    /// slot's label (`None` for primary), hash, master key and wrapped DEK.
+    /// Emergency slots are only tried with input shaped like a code, and
+    /// derive their key with HKDF, so they add no Argon2 runs to a
+    /// mistyped password.
+    fn open_key_slot(
+        conn: &rusqlite::Connection,
+        password: &str,
+    ) -> VaultResult<(Option<String>, String, MasterKey, String)> {
+        let stored_hash = Self::load_password_hash(conn)?;
+        if let Ok(master_key) = Self::verify_password_and_get_key(password, &stored_hash) {
+            return Ok((None, stored_hash, master_key, Self::load_wrapped_dek(conn)?));
+        }
+        let code = emergency::normalize_code(password);
+        for slot in db::get_key_slots(conn)? {
+            let password = match emergency::is_emergency_slot(&slot.label) {
+                true if !emergency::is_code(&code) => continue,
+                true => code.as_str(),
+                false => password,
+            };
+            if let Ok(master_key) = Self::verify_password_and_get_key(password, &slot.password_hash) {
+                return Ok((Some(slot.label), slot.password_hash, master_key, slot.wrapped_dek));
+            }
+        }
+        Err(VaultError::InvalidPassword)
+    }
+
+    fn reconstruct_key_hierarchy(
+        master_key: MasterKey,
+        wrapped_dek: String,
+    ) -> VaultResult<KeyHierarchy> {
+        KeyHierarchy::from_wrapped_dek(master_key, wrapped_dek)
+            .map_err(|e| VaultError::CryptoError(e.to_string()))
+    }
+
+    fn rewrap_dek(&mut self, new_master_key: MasterKey) -> VaultResult<String> {
+        let key_hierarchy = self.key_hierarchy.as_mut().ok_or(VaultError::Locked)?;
+        key_hierarchy
+            .change_master_key(new_master_key)
+            .map_err(|e| VaultError::CryptoError(e.to_string()))
+    }
+
+    fn store_password_hash(conn: &rusqlite::Connection, hash: &str) -> VaultResult<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('password_hash', ?1)",
+            [hash],
+        )?;
+        Ok(())
+    }
+
+    fn load_password_hash(conn: &rusqlite::Connection) -> VaultResult<String> {
+        conn.query_row(
+            "SELECT value FROM metadata WHERE key = 'password_hash'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|_| VaultError::NotFound)
+    }
+
+    fn store_wrapped_dek(conn: &rusqlite::Connection, wrapped_dek: &str) -> VaultResult<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('wrapped_dek', ?1)",
+            [wrapped_dek],
+        )?;
+        Ok(())
+    }
+
+    fn load_wrapped_dek(conn: &rusqlite::Connection) -> VaultResult<String> {
+        conn.query_row(
+            "SELECT value FROM metadata WHERE key = 'wrapped_dek'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|_| VaultError::NotFound)
+    }
+
+    fn increment_failed_unlock_counter(conn: &rusqlite::Connection) -> VaultResult<()> {
+        conn.execute(
+            r#"
+            INSERT INTO metadata (key, value) VALUES ('pending_failed_unlocks', '1')
+            ON CONFLICT(key) DO UPDATE SET value = CAST(CAST(value AS INTEGER) + 1 AS TEXT)
+            "#,
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn update_failed_unlock_timestamp(conn: &rusqlite::Connection) -> VaultResult<()> {
+        let now = chrono::Local::now().format("%d-%b-%Y at %H:%M").to_string();
+        conn.execute(
+            r#"
+            INSERT INTO metadata (key, value) VALUES ('last_failed_unlock_at', ?1)
+            ON CONFLICT(key) DO UPDATE SET value = ?1
+            "#,
+            [&now],
+        )?;
+        Ok(())
+    }
+
+    fn increment_consecutive_failures(conn: &rusqlite::Connection) -> VaultResult<u32> {
+        let attempts: u32 = Self::get_metadata_value(conn, "consecutive_failed_unlocks")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0)
+            + 1;
+        Self::set_metadata_value(conn, "consecutive_failed_unlocks", &attempts.to_string())?;
+        Ok(attempts)
+    }
+
+    fn reset_unlock_throttle(conn: &rusqlite::Connection) -> VaultResult<()> {
+        conn.execute(
+            "DELETE FROM metadata WHERE key IN ('consecutive_failed_unlocks', 'unlock_not_before')",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn ensure_unlock_allowed(conn: &rusqlite::Connection) -> VaultResult<()> {
+        let Some(not_before) = Self::get_metadata_value(conn, "unlock_not_before").and_then(|v| v.parse::<i64>().ok())
+        else {
+            return Ok(());
+        };
+        let remaining = not_before - chrono::Local::now().timestamp();
+        if remaining > 0 {
+            return Err(VaultError::Throttled(remaining as u64));
+        }
+        Ok(())
+    }
+
+    /// Create or drop the on-disk search index to match the configured mode.
+    /// A read-only vault keeps whatever it has.
+    pub fn apply_search_index(&self) -> VaultResult<()> {
+        self.sync_search_index(self.db()?.conn())
+    }
+
+    fn sync_search_index(&self, conn: &rusqlite::Connection) -> VaultResult<()> {
+        if self.is_read_only() {
+            return Ok(());
+        }
+        let on_disk = db::has_fts(conn)?;
+        let mode = match self.config.encrypt_metadata {
+            true => SearchIndexMode::Memory,
+            false => self.config.search_index,
+        };
+        match mode {
+            SearchIndexMode::Disk if !on_disk => db::create_fts(conn)?,
+            SearchIndexMode::Memory if on_disk => db::drop_fts(conn)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Give the connection the data key for sealed usernames and URLs and,
+    /// unless read-only, bring the stored ones in line with
+    /// `encrypt_metadata`
+    fn seal_fields(&self, conn: &rusqlite::Connection, dek: &DataEncryptionKey) -> VaultResult<()> {
+        let seal = self.config.encrypt_metadata;
+        db::fields::install(conn, Some(dek), seal)?;
+        if !self.is_read_only() && db::fields::count_unmatched(conn, seal)? > 0 {
+            let tx = conn.unchecked_transaction()?;
+            db::fields::reseal_all(&tx, seal)?;
+            tx.commit()?;
+            if seal {
+                // The plaintext lives on in the search index, freed pages
+                // and the WAL until they are dropped and overwritten
+                if db::has_fts(conn)? {
+                    db::drop_fts(conn)?;
+                }
+                compact::compact(conn)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn ensure_not_wiped(conn: &rusqlite::Connection) -> VaultResult<()> {
+        match Self::get_metadata_value(conn, "wiped_at") {
+            Some(_) => Err(VaultError::Wiped),
+            None => Ok(()),
+        }
+    }
+
+    /// Overwrite a slot's wrapped DEK in place, then delete it. Callers turn
+    /// secure_delete on first and checkpoint after committing, as
+    /// `wipe_wrapped_dek` does.
+    fn shred_key_slot(conn: &rusqlite::Connection, label: &str) -> VaultResult<()> {
+        conn.execute(
+            "UPDATE key_slots SET wrapped_dek = replace(hex(zeroblob(length(wrapped_dek))), '00', '0') WHERE label = ?1",
+            [label],
+        )?;
+        db::delete_key_slot(conn, label)?;
+        Ok(())
+    }
+
+    /// Record a change to who can unlock the vault in the audit log
+    fn log_key_slot_change(&self, conn: &rusqlite::Connection, details: &str) -> VaultResult<()> {
+        let audit_key = self.keys()?.derive_audit_key().map_err(|e| VaultError::CryptoError(e.to_string()))?;
+        audit::log_action(conn, &audit_key, AuditAction::Update, None, None, None, Some(details))?;
+        Ok(())
+    }
+
+    /// Overwrite every wrapped DEK in place and delete them with
+    /// secure_delete on, then checkpoint so no copy survives in the WAL
+    fn wipe_wrapped_dek(conn: &rusqlite::Connection) -> VaultResult<()> {
+        conn.execute_batch("PRAGMA secure_delete = ON")?;
+        if let Some(wrapped_dek) = Self::get_metadata_value(conn, "wrapped_dek") {
+            Self::set_metadata_value(conn, "wrapped_dek", &"0".repeat(wrapped_dek.len()))?;
+        }
+        conn.execute("DELETE FROM metadata WHERE key = 'wrapped_dek'", [])?;
+        conn.execute("UPDATE key_slots SET wrapped_dek = replace(hex(zeroblob(length(wrapped_dek))), '00', '0')", [])?;
+        conn.execute("DELETE FROM key_slots", [])?;
+        Self::set_metadata_value(conn, "wiped_at", &chrono::Local::now().to_rfc3339())?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    /// Key for the HMAC that ties the stored fingerprint to this vault
+    fn fingerprint_key(key_hierarchy: &KeyHierarchy) -> VaultResult<DerivedKey> {
+        key_hierarchy.derive_fingerprint_key().map_err(|e| VaultError::CryptoError(e.to_string()))
+    }
+
+    /// The code naming this vault file, readable while locked. None if the
+    /// vault does not exist yet.
+    pub fn fingerprint(&self) -> VaultResult<Option<String>> {
+        if let Some(db) = &self.db {
+            return fingerprint::read(db.conn());
+        }
+        if !self.config.path.exists() {
+            return Ok(None);
+        }
+        fingerprint::read(self.open_database()?.conn())
+    }
+
+    /// Whether the stored fingerprint was made with this vault's key; a
+    /// file whose fingerprint was copied from another vault fails
+    pub fn fingerprint_matches(&self) -> VaultResult<bool> {
+        fingerprint::verify(self.db()?.conn(), &Self::fingerprint_key(self.keys()?)?)
+    }
+
+    /// Upgrade ciphertexts written before they were bound to their
+    /// credential, and archived secrets bound as the current one. Each is
+    /// done once; vaults created since start out bound.
+    fn bind_ciphertexts(conn: &rusqlite::Connection, dek: &DataEncryptionKey, fingerprint_key: &DerivedKey) -> VaultResult<()> {
+        if !Self::ciphertexts_bound(conn, fingerprint_key)? {
+            credential::bind_ciphertexts(conn, dek)?;
+            fingerprint::mark_bound(conn, fingerprint_key)?;
+        }
+        // Losing the flag only runs this again, which leaves sealed entries be
+        if Self::get_metadata_value(conn, HISTORY_BOUND_KEY).is_none() {
+            credential::bind_history(conn, dek)?;
+            Self::set_metadata_value(conn, HISTORY_BOUND_KEY, "1")?;
+        }
+        Ok(())
+    }
+
+    /// Whether blobs from before binding are refused: the vault recorded
+    /// under its key that none are left, or its record was not made with
+    /// that key and so cannot be trusted to say otherwise
+    fn ciphertexts_bound(conn: &rusqlite::Connection, fingerprint_key: &DerivedKey) -> VaultResult<bool> {
+        Ok(fingerprint::is_bound(conn, fingerprint_key)? || !fingerprint::verify(conn, fingerprint_key)?)
+    }
+
+    fn set_metadata_value(conn: &rusqlite::Connection, key: &str, value: &str) -> VaultResult<()> {
+        conn.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)", [key, value])?;
+        Ok(())
+    }
+
+    fn get_metadata_value(conn: &rusqlite::Connection, key: &str) -> Option<String> {
+        conn.query_row(
+            "SELECT value FROM metadata WHERE key = ?1",
+            [key],
+            |row| row.get(0),
+        )
+        .ok()
+    }
+
+    fn clear_failed_attempt_metadata(conn: &rusqlite::Connection) -> VaultResult<()> {
+        conn.execute(
+            "DELETE FROM metadata WHERE key IN ('pending_failed_unlocks', 'last_failed_unlock_at')",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn parse_failed_attempts(
+        count: Option<String>,
+        timestamp: Option<String>,
+    ) -> VaultResult<Option<(u32, String)>> {
+        let Some(c) = count else {
+            return Ok(None);
+        };
+        let Some(t) = timestamp else {
+            return Ok(None);
+        };
+
+        let n: u32 = c.parse().unwrap_or(0);
+        if n == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some((n, t)))
+    }
+}
+
+fn validate_slot_label(label: &str) -> VaultResult<()> {
+    if label.is_empty() || label.chars().any(char::is_whitespace) {
+        return Err(VaultError::OperationFailed("Key slot labels must be a single word".to_string()));
+    }
+    if label == PRIMARY_SLOT {
+        return Err(VaultError::OperationFailed(format!("'{}' is reserved", PRIMARY_SLOT)));
+    }
+    if emergency::is_emergency_slot(label) {
+        return Err(VaultError::OperationFailed(format!("'{}' labels are reserved for emergency codes", emergency::EMERGENCY_SLOT_PREFIX)));
+    }
+    Ok(())
+}
+
+/// No delay for the first few failures, then doubling from one second
+fn unlock_delay(attempts: u32) -> Duration {
+    let Some(excess) = attempts.checked_sub(FREE_UNLOCK_ATTEMPTS + 1) else {
+        return Duration::ZERO;
+    };
+    let secs = 1u64.checked_shl(excess).unwrap_or(u64::MAX);
+    Duration::from_secs(secs).min(MAX_UNLOCK_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::backup;
+    use tempfile::TempDir;
+
+    fn temp_vault() -> (TempDir, VaultConfig) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("test_vault.db");
+        let config = VaultConfig::with_path(path);
+        (dir, config)
+    }
+
+    fn create_initialized_vault(config: VaultConfig, password: &str) -> Vault {
+        let mut vault = Vault::new(config);
+        vault.initialize(password).unwrap();
+        vault
+    }
+
+    #[test]
+    fn test_vault_lifecycle() {
+        let (_dir, config) = temp_vault();
+        let mut vault = Vault::new(config);
+
+        assert_eq!(vault.state(), VaultState::Uninitialized);
+
+        vault.initialize("test_password").unwrap();
+        assert_eq!(vault.state(), VaultState::Unlocked);
+        assert!(vault.dek().is_ok());
+
+        vault.lock();
+        assert_eq!(vault.state(), VaultState::Locked);
+
+        vault.unlock("test_password").unwrap();
+        assert_eq!(vault.state(), VaultState::Unlocked);
+    }
+
+    #[test]
+    fn test_search_index_applied_after_unlock() {
+        let (_dir, config) = temp_vault();
+        let path = config.path.clone();
+        create_initialized_vault(config, "password").lock();
+
+        let config = VaultConfig { search_index: SearchIndexMode::Memory, ..VaultConfig::with_path(path) };
+        let mut vault = Vault::new(config);
+        vault.unlock_without_index("password").unwrap();
+        assert!(db::has_fts(vault.db().unwrap().conn()).unwrap());
+        vault.apply_search_index().unwrap();
+        assert!(!db::has_fts(vault.db().unwrap().conn()).unwrap());
+    }
+
+    #[test]
+    fn test_encrypt_metadata() {
+        let (_dir, config) = temp_vault();
+        let path = config.path.clone();
+        // Without an on-disk index to drop, and the vacuum that goes with it
+        let mut vault = create_initialized_vault(VaultConfig { search_index: SearchIndexMode::Memory, ..config }, "password");
+        let dek = vault.dek().unwrap().clone();
+        let conn = vault.db().unwrap().conn();
+        let username = Some("alice".to_string());
+        let new = credential::NewCredential {
+            username,
+            ..credential::NewCredential::new("GitHub".into(), db::CredentialType::Password, "s3cret")
+        };
+        credential::create_credential(conn, &dek, new).unwrap();
+        vault.lock();
+
+        let stored = |vault: &Vault| -> String {
+            vault.db().unwrap().conn().query_row("SELECT username FROM credentials", [], |row| row.get(0)).unwrap()
+        };
+        let mut vault = Vault::new(VaultConfig { encrypt_metadata: true, ..VaultConfig::with_path(&path) });
+        vault.unlock("password").unwrap();
+        assert!(db::fields::is_sealed(&stored(&vault)));
+        assert!(!db::has_fts(vault.db().unwrap().conn()).unwrap());
+        let read = db::get_all_credentials(vault.db().unwrap().conn()).unwrap();
+        assert_eq!(read[0].username.as_deref(), Some("alice"));
+        // No copy of the plaintext is left behind in the file or its WAL
+        for file in [path.clone(), path.with_extension("db-wal")] {
+            let bytes = std::fs::read(&file).unwrap_or_default();
+            assert!(!bytes.windows(5).any(|w| w == b"alice"), "{} still holds the username", file.display());
+        }
+        vault.lock();
+
+        // Turning it off opens them again on the next unlock
+        let mut vault = Vault::new(VaultConfig::with_path(&path));
+        vault.unlock("password").unwrap();
+        assert_eq!(stored(&vault), "alice");
+    }
+
+    #[test]
+    fn test_unbound_blob_refused_once_bound() {
+        use crate::crypto::encryption::{self, Format};
+
+        let (_dir, config) = temp_vault();
+        let path = config.path.clone();
+        let mut vault = create_initialized_vault(config, "password");
+        let dek = vault.dek().unwrap().clone();
+        let conn = vault.db().unwrap().conn();
+        let new = credential::NewCredential::new("GitHub".into(), db::CredentialType::Password, "s3cret");
+        let cred = credential::create_credential(conn, &dek, new).unwrap();
+        let legacy = encryption::seal_as(Format::Legacy, dek.as_ref(), "swapped", b"");
+        conn.execute("UPDATE credentials SET encrypted_secret = ?1", [&legacy]).unwrap();
+        vault.lock();
+
+        // Unlocking neither reads the planted blob nor binds it in place
+        let mut vault = Vault::new(VaultConfig::with_path(&path));
+        vault.unlock("password").unwrap();
+        let conn = vault.db().unwrap().conn();
+        let stored = db::get_credential(conn, &cred.id).unwrap();
+        assert_eq!(stored.encrypted_secret, legacy);
+        assert!(credential::decrypt_credential(conn, vault.dek().unwrap(), &stored, false).is_err());
+        assert!(vault.fingerprint_matches().unwrap());
+    }
+
+    #[test]
+    fn test_relocate() {
+        let (dir, config) = temp_vault();
+        let old_path = config.path.clone();
+        let mut vault = create_initialized_vault(config, "password");
+        let new_path = dir.path().join("elsewhere").join("vault.db");
+
+        assert!(vault.relocate(&new_path).unwrap());
+        assert!(!old_path.exists());
+        assert_eq!(vault.state(), VaultState::Unlocked);
+        vault.lock();
+        vault.unlock("password").unwrap();
+        assert_eq!(vault.config().path, new_path);
+    }
+
+    #[test]
+    fn test_inspect_backup() {
+        let (dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config, "password");
+        let backup = backup::create_backup(vault.db().unwrap().conn(), vault.dek().unwrap(), dir.path(), "manual").unwrap();
+        let count = |vault: &Vault| db::get_all_credentials(vault.db().unwrap().conn()).unwrap().len();
+        credential::create_credential(
+            vault.db().unwrap().conn(),
+            vault.dek().unwrap(),
+            credential::NewCredential::new("GitHub".to_string(), db::CredentialType::Password, "s"),
+        )
+        .unwrap();
+
+        let opened = backup::open_backup(vault.dek().unwrap(), &backup, dir.path()).unwrap();
+        vault.inspect(opened).unwrap();
+        assert_eq!(vault.inspected(), Some(backup.as_path()));
+        assert!(vault.is_read_only());
+        assert_eq!(count(&vault), 0);
+        assert!(matches!(vault.change_password("password", "other"), Err(VaultError::ReadOnly)));
+
+        assert!(vault.close_inspected());
+        assert!(!vault.is_read_only());
+        assert_eq!(count(&vault), 1);
+
+        vault.inspect(backup::open_backup(vault.dek().unwrap(), &backup, dir.path()).unwrap()).unwrap();
+        vault.lock();
+        assert_eq!(vault.inspected(), None);
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config, "correct_password");
+        vault.lock();
+
+        let result = vault.unlock("wrong_password");
+        assert!(matches!(result, Err(VaultError::InvalidPassword)));
+    }
+
+    #[test]
+    fn test_change_password() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config, "old_password");
+
+        let dek_before = vault.dek().unwrap().as_bytes().clone();
+
+        vault.change_password("old_password", "new_password").unwrap();
+
+        let dek_after = vault.dek().unwrap().as_bytes();
+        assert_eq!(&dek_before, dek_after);
+
+        vault.lock();
+        assert!(vault.unlock("old_password").is_err());
+
+        vault.unlock("new_password").unwrap();
+        assert!(vault.is_unlocked());
+        assert_eq!(&dek_before, vault.dek().unwrap().as_bytes());
+    }
+
+    #[test]
+    fn test_detached_key_work() {
+        let (_dir, config) = temp_vault();
+        let mut vault = Vault::new(config);
+        let (key, hash) = Vault::derive_key("first_password").unwrap();
+        vault.initialize_with_key(key, hash).unwrap();
+
+        let verify = vault.password_verifier().unwrap();
+        assert!(std::thread::spawn(move || verify("first_password")).join().unwrap().is_ok());
+        let verify = vault.password_verifier().unwrap();
+        assert!(matches!(verify("wrong"), Err(VaultError::InvalidPassword)));
+
+        let (key, hash) = Vault::derive_key("second_password").unwrap();
+        vault.change_password_with_key(key, hash).unwrap();
+        vault.lock();
+        assert!(vault.password_verifier().is_err());
+        vault.unlock("second_password").unwrap();
+    }
+
+    #[test]
+    fn test_credentials_accessible_after_password_change() {
+        use crate::crypto::{decrypt_string, encrypt_string};
+
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config, "password1");
+
+        let secret = "my_secret_data";
+        let encrypted = encrypt_string(vault.dek().unwrap().as_ref(), secret).unwrap();
+
+        vault.change_password("password1", "password2").unwrap();
+
+        let decrypted = decrypt_string(vault.dek().unwrap().as_ref(), &encrypted).unwrap();
+        assert_eq!(secret, decrypted);
+
+        vault.lock();
+        vault.unlock("password2").unwrap();
+
+        let decrypted = decrypt_string(vault.dek().unwrap().as_ref(), &encrypted).unwrap();
+        assert_eq!(secret, decrypted);
+    }
+
+    fn get_wrapped_dek(conn: &rusqlite::Connection) -> String {
+        conn.query_row(
+            "SELECT value FROM metadata WHERE key = 'wrapped_dek'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_unlock_delay() {
+        assert_eq!(unlock_delay(1), Duration::ZERO);
+        assert_eq!(unlock_delay(2), Duration::ZERO);
+        assert_eq!(unlock_delay(3), Duration::from_secs(1));
+        assert_eq!(unlock_delay(5), Duration::from_secs(4));
+        assert_eq!(unlock_delay(200), MAX_UNLOCK_DELAY);
+    }
+
+    #[test]
+    fn test_failed_unlocks_are_throttled() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config, "password");
+        vault.lock();
+
+        for _ in 0..FREE_UNLOCK_ATTEMPTS {
+            assert_eq!(vault.record_failed_unlock().unwrap().retry_after, Duration::ZERO);
+        }
+        let failure = vault.record_failed_unlock().unwrap();
+        assert_eq!((failure.attempts, failure.retry_after), (3, Duration::from_secs(1)));
+        assert!(matches!(vault.unlock("password"), Err(VaultError::Throttled(_))));
+
+        // Pretend the delay has passed
+        let db = vault.open_writable_database().unwrap();
+        Vault::set_metadata_value(db.conn(), "unlock_not_before", "0").unwrap();
+        drop(db);
+
+        vault.unlock("password").unwrap();
+        let conn = vault.db().unwrap().conn();
+        assert!(Vault::get_metadata_value(conn, "consecutive_failed_unlocks").is_none());
+        assert!(Vault::get_metadata_value(conn, "pending_failed_unlocks").is_some());
+    }
+
+    #[test]
+    fn test_wipe_after_failures() {
+        let (_dir, config) = temp_vault();
+        let path = config.path.clone();
+        let config = VaultConfig { wipe_after_failures: 2, ..config };
+        let mut vault = create_initialized_vault(config, "password");
+        vault.add_key_slot("spare", "spare_password").unwrap();
+        let wrapped_dek = get_wrapped_dek(vault.db().unwrap().conn());
+        let slot_dek = db::get_key_slots(vault.db().unwrap().conn()).unwrap()[0].wrapped_dek.clone();
+        vault.lock();
+
+        assert!(!vault.record_failed_unlock().unwrap().wiped);
+        assert!(vault.record_failed_unlock().unwrap().wiped);
+        assert!(matches!(vault.unlock("password"), Err(VaultError::Wiped)));
+
+        let raw = std::fs::read(&path).unwrap();
+        assert!(!raw.windows(wrapped_dek.len()).any(|w| w == wrapped_dek.as_bytes()));
+        assert!(!raw.windows(slot_dek.len()).any(|w| w == slot_dek.as_bytes()));
+    }
+
+    #[test]
+    fn test_key_slots() {
+        let (_dir, config) = temp_vault();
+        let path = config.path.clone();
+        let mut vault = create_initialized_vault(config, "owner");
+        let dek = *vault.dek().unwrap().as_bytes();
+        vault.add_key_slot("alice", "alice_password").unwrap();
+        assert!(vault.add_key_slot("alice", "other").is_err());
+        assert!(vault.add_key_slot(PRIMARY_SLOT, "other").is_err());
+        vault.lock();
+
+        vault.unlock("alice_password").unwrap();
+        assert_eq!(vault.active_slot(), "alice");
+        assert_eq!(&dek, vault.dek().unwrap().as_bytes());
+        assert!(vault.remove_key_slot("alice").is_err());
+
+        vault.change_password("alice_password", "alice_new").unwrap();
+        vault.lock();
+        assert!(vault.unlock("alice_password").is_err());
+        vault.unlock("alice_new").unwrap();
+        vault.lock();
+
+        vault.unlock("owner").unwrap();
+        assert_eq!(vault.key_slots().unwrap(), vec![PRIMARY_SLOT, "alice"]);
+        let conn = vault.db().unwrap().conn();
+        let wrapped_dek = db::get_key_slots(conn).unwrap().pop().unwrap().wrapped_dek;
+        vault.remove_key_slot("alice").unwrap();
+        let logs = db::get_all_audit_logs(vault.db().unwrap().conn()).unwrap();
+        for change in ["Key slot 'alice' added", "Key slot 'alice' removed"] {
+            assert!(logs.iter().any(|log| log.details.as_deref() == Some(change)), "no audit entry: {}", change);
+        }
+        // The removed slot's wrapped DEK is gone from the file and its WAL
+        for file in [path.clone(), path.with_extension("db-wal")] {
+            let bytes = std::fs::read(&file).unwrap_or_default();
+            assert!(!bytes.windows(wrapped_dek.len()).any(|w| w == wrapped_dek.as_bytes()), "{} still holds it", file.display());
+        }
+        vault.lock();
+        assert!(matches!(vault.unlock("alice_new"), Err(VaultError::InvalidPassword)));
+    }
+
+    #[test]
+    fn test_emergency_codes() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config, "owner");
+        let codes = vault.create_emergency_codes(2).unwrap();
+        assert_eq!(vault.key_slots().unwrap(), vec![PRIMARY_SLOT, "emergency-1", "emergency-2"]);
+        let logs = db::get_all_audit_logs(vault.db().unwrap().conn()).unwrap();
+        assert!(logs.iter().any(|log| log.details.as_deref() == Some("2 emergency codes issued, replacing any unused")));
+        assert!(vault.add_key_slot("emergency-3", "other").is_err());
+        let slots = db::get_key_slots(vault.db().unwrap().conn()).unwrap();
+        assert!(slots.iter().all(|slot| slot.password_hash.starts_with("$hkdf-sha256$")));
+        vault.lock();
+
+        // Typed loosely, the code still works, but only once
+        let (label, code) = &codes[1];
+        vault.unlock(&code.to_lowercase().replace('-', " ")).unwrap();
+        assert_eq!(vault.active_slot(), label);
+        assert!(vault.take_emergency_unlocks().unwrap().is_empty());
+        assert!(vault.create_emergency_codes(1).is_err());
+        let logs = db::get_all_audit_logs(vault.db().unwrap().conn()).unwrap();
+        assert!(logs.iter().any(|log| log.details.as_deref().is_some_and(|d| d.starts_with("EMERGENCY"))));
+        vault.lock();
+        assert!(matches!(vault.unlock(code), Err(VaultError::InvalidPassword)));
+
+        vault.unlock("owner").unwrap();
+        assert_eq!(vault.key_slots().unwrap(), vec![PRIMARY_SLOT, "emergency-1"]);
+        let used = vault.take_emergency_unlocks().unwrap();
+        assert_eq!(used.len(), 1);
+        assert_eq!(&used[0].label, label);
+        assert!(vault.take_emergency_unlocks().unwrap().is_empty());
+
+        // A new set revokes the old one
+        vault.create_emergency_codes(1).unwrap();
+        vault.lock();
+        assert!(vault.unlock(&codes[0].1).is_err());
+    }
+
+    #[test]
+    fn test_emergency_session_cannot_add_slot() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config, "owner");
+        let codes = vault.create_emergency_codes(2).unwrap();
+        vault.lock();
+
+        vault.unlock(&codes[0].1).unwrap();
+        assert!(vault.add_key_slot("backdoor", "permanent").is_err());
+        assert!(vault.remove_key_slot("emergency-2").is_err());
+        assert!(vault.change_password(&codes[0].1, "permanent").is_err());
+        assert_eq!(vault.key_slots().unwrap(), vec![PRIMARY_SLOT, "emergency-2"]);
+        vault.lock();
+        assert!(vault.unlock("permanent").is_err());
+    }
+
+    #[test]
+    fn test_wrapped_dek_stored() {
+        let (_dir, config) = temp_vault();
+        let vault = create_initialized_vault(config, "password");
+        let wrapped_dek = get_wrapped_dek(vault.db().unwrap().conn());
+        assert!(!wrapped_dek.is_empty());
+    }
+}
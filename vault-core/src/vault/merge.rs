@@ -0,0 +1,323 @@
+//! Merge
+//!
+//! Combines two credentials into one. Single-valued fields are picked from
+//! either side, tags are unioned and notes concatenated; the left
+//! credential survives and the right one is deleted.
+
+use secrecy::ExposeSecret;
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::{self, Credential};
+
+use super::credential::{update_credential, DecryptedCredential};
+use super::{VaultError, VaultResult};
+
+/// Line between the two credentials' notes
+pub const NOTES_SEPARATOR: &str = "\n---\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    pub fn other(self) -> Self {
+        match self {
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeField {
+    Name,
+    Type,
+    Username,
+    Url,
+    Secret,
+    Tags,
+    Notes,
+}
+
+impl MergeField {
+    pub const ALL: [Self; 7] = [Self::Name, Self::Type, Self::Username, Self::Url, Self::Secret, Self::Tags, Self::Notes];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Name => "Name",
+            Self::Type => "Type",
+            Self::Username => "Username",
+            Self::Url => "URL",
+            Self::Secret => "Secret",
+            Self::Tags => "Tags",
+            Self::Notes => "Notes",
+        }
+    }
+
+    fn audit_name(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Type => "type",
+            Self::Username => "username",
+            Self::Url => "url",
+            Self::Secret => super::timeline::SECRET_FIELD,
+            Self::Tags => "tags",
+            Self::Notes => "notes",
+        }
+    }
+}
+
+/// Which side each single-valued field comes from
+#[derive(Clone)]
+pub struct MergePlan {
+    pub left: DecryptedCredential,
+    pub right: DecryptedCredential,
+    name: Side,
+    credential_type: Side,
+    username: Side,
+    url: Side,
+    secret: Side,
+}
+
+impl MergePlan {
+    /// Fields default to the left side unless it is empty there; the secret
+    /// defaults to whichever side was updated last
+    pub fn new(left: DecryptedCredential, right: DecryptedCredential) -> Self {
+        let filled = |l: &Option<String>| match l.as_deref().is_some_and(|v| !v.trim().is_empty()) {
+            true => Side::Left,
+            false => Side::Right,
+        };
+        let secret = match right.updated_at > left.updated_at {
+            true => Side::Right,
+            false => Side::Left,
+        };
+        Self {
+            username: filled(&left.username),
+            url: filled(&left.url),
+            name: Side::Left,
+            credential_type: Side::Left,
+            secret,
+            left,
+            right,
+        }
+    }
+
+    fn get(&self, side: Side) -> &DecryptedCredential {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+        }
+    }
+
+    /// The chosen side of a field, or `None` for the fields both sides feed
+    pub fn side(&self, field: MergeField) -> Option<Side> {
+        match field {
+            MergeField::Name => Some(self.name),
+            MergeField::Type => Some(self.credential_type),
+            MergeField::Username => Some(self.username),
+            MergeField::Url => Some(self.url),
+            MergeField::Secret => Some(self.secret),
+            MergeField::Tags | MergeField::Notes => None,
+        }
+    }
+
+    pub fn toggle(&mut self, field: MergeField) {
+        let side = match field {
+            MergeField::Name => &mut self.name,
+            MergeField::Type => &mut self.credential_type,
+            MergeField::Username => &mut self.username,
+            MergeField::Url => &mut self.url,
+            MergeField::Secret => &mut self.secret,
+            MergeField::Tags | MergeField::Notes => return,
+        };
+        *side = side.other();
+    }
+
+    pub fn name(&self) -> &str {
+        &self.get(self.name).name
+    }
+
+    /// Left tags followed by any right tags not already present
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags = self.left.tags.clone();
+        for tag in &self.right.tags {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        tags
+    }
+
+    /// Both sides' notes, once when they are identical
+    pub fn notes(&self) -> Option<String> {
+        let notes = |c: &DecryptedCredential| {
+            c.notes.as_ref().map(|n| n.expose_secret().trim().to_string()).filter(|n| !n.is_empty())
+        };
+        match (notes(&self.left), notes(&self.right)) {
+            (Some(l), Some(r)) if l == r => Some(l),
+            (Some(l), Some(r)) => Some(format!("{}{}{}", l, NOTES_SEPARATOR, r)),
+            (l, r) => l.or(r),
+        }
+    }
+
+    /// A field's value on one side, with the secret masked
+    pub fn display(&self, field: MergeField, side: Side) -> String {
+        let cred = self.get(side);
+        match field {
+            MergeField::Name => cred.name.clone(),
+            MergeField::Type => cred.credential_type.display_name().to_string(),
+            MergeField::Username => cred.username.clone().unwrap_or_default(),
+            MergeField::Url => cred.url.clone().unwrap_or_default(),
+            MergeField::Secret => format!("•••••••• ({})", cred.updated_at.format("%d-%b-%Y")),
+            MergeField::Tags => self.tags().join(", "),
+            MergeField::Notes => self.notes().unwrap_or_default().replace('\n', " "),
+        }
+    }
+
+    /// Names of the fields the survivor gains from the right side, as
+    /// recorded in update events
+    pub fn changed_fields(&self) -> Vec<&'static str> {
+        let mut fields: Vec<&'static str> = MergeField::ALL
+            .into_iter()
+            .filter(|f| self.side(*f) == Some(Side::Right) && self.raw(*f, Side::Left) != self.raw(*f, Side::Right))
+            .map(MergeField::audit_name)
+            .collect();
+        if self.tags() != self.left.tags {
+            fields.push(MergeField::Tags.audit_name());
+        }
+        let left_notes = self.left.notes.as_ref().map(|n| n.expose_secret().trim().to_string());
+        if self.notes() != left_notes.filter(|n| !n.is_empty()) {
+            fields.push(MergeField::Notes.audit_name());
+        }
+        fields
+    }
+
+    fn raw(&self, field: MergeField, side: Side) -> Option<String> {
+        let cred = self.get(side);
+        match field {
+            MergeField::Type => Some(cred.credential_type.as_str().to_string()),
+            MergeField::Secret => cred.secret.as_ref().map(|s| s.expose_secret().to_string()),
+            MergeField::Username => cred.username.clone(),
+            MergeField::Url => cred.url.clone(),
+            _ => Some(self.display(field, side)),
+        }
+    }
+}
+
+/// Write the merged fields to the left credential and delete the right one.
+/// Returns the updated survivor.
+pub fn apply(conn: &rusqlite::Connection, dek: &DataEncryptionKey, plan: &MergePlan) -> VaultResult<Credential> {
+    if plan.left.id == plan.right.id {
+        return Err(VaultError::OperationFailed("cannot merge a credential with itself".to_string()));
+    }
+
+    let secret_side = plan.get(plan.secret);
+    let secret = secret_side.secret.as_ref().map(|s| s.expose_secret().to_string()).unwrap_or_default();
+    let mut cred = db::get_credential(conn, &plan.left.id)?;
+    cred.name = plan.name().to_string();
+    cred.credential_type = plan.get(plan.credential_type).credential_type;
+    cred.username = plan.get(plan.username).username.clone();
+    cred.url = plan.get(plan.url).url.clone();
+    cred.tags = plan.tags();
+    cred.password_policy = secret_side.password_policy.clone();
+    cred.env_var = plan.left.env_var.clone().or_else(|| plan.right.env_var.clone());
+    cred.alias = plan.left.alias.clone().or_else(|| plan.right.alias.clone());
+    cred.public_key = secret_side.public_key.clone();
+    // Attributes describe where the secret is used, so they follow it
+    cred.attributes = secret_side.attributes.clone();
+
+    // The right one goes first so its alias is free to move over
+    let tx = conn.unchecked_transaction()?;
+    super::credential::delete_credential(&tx, &plan.right.id)?;
+    update_credential(&tx, dek, &mut cred, Some(&secret), plan.notes().as_deref())?;
+    tx.commit()?;
+    Ok(cred)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{CredentialType, Database};
+    use crate::vault::credential::{create_credential, decrypt_credential, NewCredential};
+
+    fn test_dek() -> DataEncryptionKey {
+        DataEncryptionKey::from_bytes([0x42u8; 32])
+    }
+
+    fn add(
+        conn: &rusqlite::Connection,
+        name: &str,
+        username: Option<&str>,
+        secret: &str,
+        tags: &[&str],
+        notes: Option<&str>,
+    ) -> DecryptedCredential {
+        let cred = create_credential(
+            conn,
+            &test_dek(),
+            NewCredential {
+                username: username.map(str::to_string),
+                url: Some("https://github.com".to_string()),
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                notes,
+                ..NewCredential::new(name.to_string(), CredentialType::Password, secret)
+            },
+        )
+        .unwrap();
+        decrypt_credential(conn, &test_dek(), &cred, false).unwrap()
+    }
+
+    #[test]
+    fn test_plan_defaults() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let old = add(conn, "GitHub", None, "old", &["dev", "work"], Some("recovery codes"));
+        let mut new = add(conn, "GitHub (imported)", Some("octocat"), "new", &["work", "imported"], Some("2fa on"));
+        new.updated_at = old.updated_at + chrono::Duration::seconds(1);
+
+        let mut plan = MergePlan::new(old, new);
+        assert_eq!(plan.name(), "GitHub");
+        assert_eq!(plan.side(MergeField::Username), Some(Side::Right));
+        assert_eq!(plan.side(MergeField::Secret), Some(Side::Right));
+        assert_eq!(plan.tags(), ["dev", "work", "imported"]);
+        assert_eq!(plan.notes().unwrap(), format!("recovery codes{}2fa on", NOTES_SEPARATOR));
+        assert_eq!(plan.changed_fields(), ["username", "password", "tags", "notes"]);
+
+        plan.toggle(MergeField::Name);
+        assert_eq!(plan.name(), "GitHub (imported)");
+        plan.toggle(MergeField::Tags);
+        assert_eq!(plan.side(MergeField::Tags), None);
+    }
+
+    #[test]
+    fn test_apply() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let left = add(conn, "GitHub", Some("octocat"), "left", &["dev"], Some("same"));
+        let right = add(conn, "Github", None, "right", &["work"], Some("same"));
+        let right_id = right.id.clone();
+
+        let mut plan = MergePlan::new(left, right);
+        plan.toggle(MergeField::Secret);
+        let merged = apply(conn, &test_dek(), &plan).unwrap();
+
+        let all = db::get_all_credentials(conn).unwrap();
+        assert_eq!(all.len(), 1);
+        assert!(db::get_credential(conn, &right_id).is_err());
+        let decrypted = decrypt_credential(conn, &test_dek(), &merged, false).unwrap();
+        assert_eq!(decrypted.name, "GitHub");
+        assert_eq!(decrypted.username.as_deref(), Some("octocat"));
+        assert_eq!(decrypted.tags, ["dev", "work"]);
+        assert_eq!(decrypted.notes.as_ref().map(|n| n.expose_secret().to_string()).as_deref(), Some("same"));
+        let expected = match plan.side(MergeField::Secret) {
+            Some(Side::Left) => "left",
+            _ => "right",
+        };
+        assert_eq!(decrypted.secret.as_ref().map(|s| s.expose_secret().to_string()).as_deref(), Some(expected));
+
+        let same = MergePlan::new(decrypted.clone(), decrypted);
+        assert!(apply(conn, &test_dek(), &same).is_err());
+    }
+}
@@ -2,10 +2,40 @@
 //!
 //! Secure credential storage with encryption and key management.
 
+pub mod alias;
 pub mod audit;
+pub mod backend;
+pub mod backup;
+/// Workloads for `vault bench` and the criterion benchmarks; not part
+/// of the stable API
+#[doc(hidden)]
+pub mod bench;
+pub mod breach;
+pub mod compact;
+pub mod connection;
 pub mod credential;
+pub mod csv_export;
+pub mod cryptoinfo;
+pub mod decrypt_cache;
+pub mod diff;
+pub mod dupes;
+pub mod emergency;
+pub mod expiry;
+mod fingerprint;
+pub mod fsck;
+pub mod kdf_task;
 pub mod manager;
+pub mod merge;
+pub mod otp_backup;
+pub mod recovery;
+mod relocate;
+pub mod runner;
 pub mod search;
+pub mod search_index;
+pub mod stats;
+pub mod sync;
+pub mod template;
+pub mod timeline;
 
 use thiserror::Error;
 
@@ -24,6 +54,15 @@ pub enum VaultError {
     #[error("Invalid password")]
     InvalidPassword,
 
+    #[error("Vault is open read-only")]
+    ReadOnly,
+
+    #[error("Too many failed attempts, try again in {0}s")]
+    Throttled(u64),
+
+    #[error("Vault key was destroyed after too many failed unlock attempts")]
+    Wiped,
+
     #[error("Database error: {0}")]
     DatabaseError(#[from] crate::db::DbError),
 
@@ -33,6 +72,9 @@ pub enum VaultError {
     #[error("IO error: {0}")]
     IoError(String),
 
+    #[error("Sync conflict: {0}")]
+    SyncConflict(String),
+
     #[error("Operation failed: {0}")]
     OperationFailed(String),
 }
@@ -0,0 +1,401 @@
+//! TOTP Backups
+//!
+//! Moves two-factor accounts between the vault and the Aegis and andOTP
+//! authenticator apps through their plain JSON backups. Each account
+//! becomes a TOTP credential holding an otpauth:// URI, so digits, period
+//! and algorithm survive the round trip.
+//!
+//! Both apps can also encrypt their backups. Those are refused with a
+//! message asking for a plain export instead; the ciphers they use are
+//! not available here.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use secrecy::ExposeSecret;
+use serde_json::{json, Value};
+use zeroize::Zeroizing;
+
+use crate::crypto::totp::{TotpAlgorithm, TotpSecret};
+use crate::crypto::DataEncryptionKey;
+use crate::db::{self, Credential, CredentialType};
+
+use super::backup::write_private;
+use super::credential::{create_credential, decrypt_fields, NewCredential};
+use super::{VaultError, VaultResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtpFormat {
+    Aegis,
+    AndOtp,
+}
+
+impl OtpFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "aegis" => Some(Self::Aegis),
+            "andotp" => Some(Self::AndOtp),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Aegis => "Aegis",
+            Self::AndOtp => "andOTP",
+        }
+    }
+}
+
+/// One account read from a backup
+#[derive(Debug, Clone)]
+pub struct OtpEntry {
+    pub secret: TotpSecret,
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+}
+
+/// What an import did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OtpImport {
+    pub format: OtpFormat,
+    pub imported: usize,
+    /// Accounts whose key is already in the vault
+    pub duplicates: usize,
+    /// HOTP, Steam and other entries that are not TOTP
+    pub unsupported: usize,
+}
+
+/// Read a backup in either format, telling them apart by shape. Entries
+/// other than TOTP are counted but not returned.
+pub fn parse_backup(json: &str) -> VaultResult<(OtpFormat, Vec<OtpEntry>, usize)> {
+    let value: Value = serde_json::from_str(json).map_err(|_| {
+        VaultError::OperationFailed("not a plain JSON backup; encrypted andOTP backups are not supported".to_string())
+    })?;
+    match value {
+        Value::Array(entries) => {
+            let (entries, unsupported) = parse_entries(&entries, parse_andotp_entry)?;
+            Ok((OtpFormat::AndOtp, entries, unsupported))
+        }
+        Value::Object(_) if value.get("db").is_some_and(Value::is_string) => Err(VaultError::OperationFailed(
+            "encrypted Aegis backups are not supported; export a plain one".to_string(),
+        )),
+        Value::Object(_) => {
+            let entries = value["db"]["entries"].as_array().ok_or_else(|| invalid("Aegis backup has no entries"))?;
+            let (entries, unsupported) = parse_entries(entries, parse_aegis_entry)?;
+            Ok((OtpFormat::Aegis, entries, unsupported))
+        }
+        _ => Err(invalid("not an Aegis or andOTP backup")),
+    }
+}
+
+/// Parse with `parse_entry`, which returns `None` for entries that are not TOTP
+fn parse_entries(
+    values: &[Value],
+    parse_entry: fn(&Value) -> VaultResult<Option<OtpEntry>>,
+) -> VaultResult<(Vec<OtpEntry>, usize)> {
+    let mut entries = Vec::new();
+    let mut unsupported = 0;
+    for value in values {
+        match parse_entry(value)? {
+            Some(entry) => entries.push(entry),
+            None => unsupported += 1,
+        }
+    }
+    Ok((entries, unsupported))
+}
+
+fn parse_aegis_entry(value: &Value) -> VaultResult<Option<OtpEntry>> {
+    if !str_field(value, "type").eq_ignore_ascii_case("totp") {
+        return Ok(None);
+    }
+    let info = &value["info"];
+    let secret = totp_secret(
+        str_field(info, "secret"),
+        str_field(value, "name"),
+        str_field(value, "issuer"),
+        info["digits"].as_u64(),
+        info["period"].as_u64(),
+        str_field(info, "algo"),
+    )?;
+    let note = Some(str_field(value, "note")).filter(|n| !n.is_empty()).map(str::to_string);
+    Ok(Some(OtpEntry { secret, tags: Vec::new(), note }))
+}
+
+fn parse_andotp_entry(value: &Value) -> VaultResult<Option<OtpEntry>> {
+    if !str_field(value, "type").eq_ignore_ascii_case("totp") {
+        return Ok(None);
+    }
+    // Older andOTP versions kept the issuer in the label as "Issuer:account"
+    let label = str_field(value, "label");
+    let (issuer, account) = match (str_field(value, "issuer"), label.split_once(':')) {
+        ("", Some((issuer, account))) => (issuer.trim(), account.trim()),
+        (issuer, _) => (issuer, label),
+    };
+    let secret = totp_secret(
+        str_field(value, "secret"),
+        account,
+        issuer,
+        value["digits"].as_u64(),
+        value["period"].as_u64(),
+        str_field(value, "algorithm"),
+    )?;
+    let tags = value["tags"]
+        .as_array()
+        .map(|tags| tags.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default();
+    Ok(Some(OtpEntry { secret, tags, note: None }))
+}
+
+fn totp_secret(
+    key: &str,
+    account: &str,
+    issuer: &str,
+    digits: Option<u64>,
+    period: Option<u64>,
+    algorithm: &str,
+) -> VaultResult<TotpSecret> {
+    let mut secret = TotpSecret::new(normalize_key(key), account.to_string(), issuer.to_string());
+    secret.digits = digits.unwrap_or(6) as usize;
+    secret.period = period.unwrap_or(30);
+    secret.algorithm = match algorithm.to_ascii_uppercase().as_str() {
+        "" | "SHA1" => TotpAlgorithm::SHA1,
+        "SHA256" => TotpAlgorithm::SHA256,
+        "SHA512" => TotpAlgorithm::SHA512,
+        other => return Err(invalid(&format!("{}: unsupported algorithm {}", entry_name(&secret), other))),
+    };
+    // Fails on a bad key or parameters, before anything is written
+    secret.to_uri().map_err(|e| invalid(&format!("{}: {}", entry_name(&secret), e)))?;
+    Ok(secret)
+}
+
+/// Create a TOTP credential for each account in the backup whose key is
+/// not in the vault yet
+pub fn import(conn: &rusqlite::Connection, dek: &DataEncryptionKey, json: &str) -> VaultResult<OtpImport> {
+    let (format, entries, unsupported) = parse_backup(json)?;
+    let mut known: HashSet<String> = totp_credentials(conn, dek)?
+        .into_iter()
+        .map(|(_, secret)| normalize_key(&secret.secret))
+        .collect();
+
+    // Joins the caller's transaction when there is one
+    let tx = if conn.is_autocommit() { Some(conn.unchecked_transaction()?) } else { None };
+    let mut report = OtpImport { format, imported: 0, duplicates: 0, unsupported };
+    for entry in entries {
+        if !known.insert(normalize_key(&entry.secret.secret)) {
+            report.duplicates += 1;
+            continue;
+        }
+        let uri = entry.secret.to_uri().map_err(|e| VaultError::CryptoError(e.to_string()))?;
+        let username = Some(entry.secret.account.clone()).filter(|a| !a.is_empty());
+        create_credential(
+            conn,
+            dek,
+            NewCredential {
+                username,
+                tags: entry.tags,
+                notes: entry.note.as_deref(),
+                ..NewCredential::new(entry_name(&entry.secret), CredentialType::Totp, &uri)
+            },
+        )?;
+        report.imported += 1;
+    }
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    Ok(report)
+}
+
+/// Every TOTP credential in the vault as a backup in `format`
+pub fn export(conn: &rusqlite::Connection, dek: &DataEncryptionKey, format: OtpFormat) -> VaultResult<String> {
+    to_json(&totp_credentials(conn, dek)?, format)
+}
+
+/// Write the export to `path`, readable only by the owner, and return
+/// how many accounts it holds
+pub fn export_file(conn: &rusqlite::Connection, dek: &DataEncryptionKey, format: OtpFormat, path: &Path) -> VaultResult<usize> {
+    let credentials = totp_credentials(conn, dek)?;
+    let json = Zeroizing::new(to_json(&credentials, format)?);
+    write_private(path, json.as_bytes())?;
+    Ok(credentials.len())
+}
+
+fn to_json(credentials: &[(Credential, TotpSecret)], format: OtpFormat) -> VaultResult<String> {
+    let backup = match format {
+        OtpFormat::Aegis => {
+            let entries: Vec<Value> = credentials.iter().map(|(cred, secret)| aegis_entry(cred, secret)).collect();
+            json!({
+                "version": 1,
+                "header": { "slots": null, "params": null },
+                "db": { "version": 2, "entries": entries },
+            })
+        }
+        OtpFormat::AndOtp => credentials.iter().map(|(cred, secret)| andotp_entry(cred, secret)).collect(),
+    };
+    serde_json::to_string_pretty(&backup).map_err(|e| VaultError::OperationFailed(e.to_string()))
+}
+
+fn aegis_entry(cred: &Credential, secret: &TotpSecret) -> Value {
+    json!({
+        "type": "totp",
+        "uuid": cred.id,
+        "name": account(cred, secret),
+        "issuer": cred.name,
+        "note": "",
+        "icon": null,
+        "info": {
+            "secret": secret.secret,
+            "algo": algorithm_name(secret.algorithm),
+            "digits": secret.digits,
+            "period": secret.period,
+        },
+    })
+}
+
+fn andotp_entry(cred: &Credential, secret: &TotpSecret) -> Value {
+    json!({
+        "secret": secret.secret,
+        "issuer": cred.name,
+        "label": account(cred, secret),
+        "digits": secret.digits,
+        "type": "TOTP",
+        "algorithm": algorithm_name(secret.algorithm),
+        "period": secret.period,
+        "tags": cred.tags,
+    })
+}
+
+/// TOTP credentials with their parsed secrets, skipping any whose secret
+/// cannot generate codes
+fn totp_credentials(conn: &rusqlite::Connection, dek: &DataEncryptionKey) -> VaultResult<Vec<(Credential, TotpSecret)>> {
+    let mut found = Vec::new();
+    for cred in db::get_all_credentials(conn)? {
+        if cred.credential_type != CredentialType::Totp {
+            continue;
+        }
+        let decrypted = decrypt_fields(dek, &cred)?;
+        let Some(secret) = decrypted.secret.as_ref() else { continue };
+        let account = cred.username.clone().unwrap_or_default();
+        if let Ok(secret) = TotpSecret::parse(secret.expose_secret(), &account) {
+            found.push((cred, secret));
+        }
+    }
+    Ok(found)
+}
+
+fn account(cred: &Credential, secret: &TotpSecret) -> String {
+    cred.username.clone().unwrap_or_else(|| secret.account.clone())
+}
+
+/// Credential name for an account: the issuer, else the account itself
+fn entry_name(secret: &TotpSecret) -> String {
+    if secret.issuer.is_empty() { secret.account.clone() } else { secret.issuer.clone() }
+}
+
+fn algorithm_name(algorithm: TotpAlgorithm) -> &'static str {
+    match algorithm {
+        TotpAlgorithm::SHA1 => "SHA1",
+        TotpAlgorithm::SHA256 => "SHA256",
+        TotpAlgorithm::SHA512 => "SHA512",
+    }
+}
+
+fn normalize_key(key: &str) -> String {
+    key.chars().filter(|c| !matches!(c, ' ' | '-' | '=')).map(|c| c.to_ascii_uppercase()).collect()
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value[key].as_str().unwrap_or("")
+}
+
+fn invalid(msg: &str) -> VaultError {
+    VaultError::OperationFailed(format!("Invalid OTP backup: {}", msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Database, DatabaseConfig};
+
+    const KEY: &str = "HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ";
+
+    fn test_db() -> (tempfile::TempDir, Database, DataEncryptionKey) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(DatabaseConfig::with_path(dir.path().join("vault.db"))).unwrap();
+        (dir, db, DataEncryptionKey::from_bytes([0x42u8; 32]))
+    }
+
+    #[test]
+    fn test_import_andotp_and_export_aegis() {
+        let (_dir, db, dek) = test_db();
+        let backup = json!([
+            { "secret": KEY, "issuer": "GitHub", "label": "octocat", "digits": 8, "type": "TOTP",
+              "algorithm": "SHA256", "period": 60, "tags": ["work"] },
+            { "secret": "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ", "label": "Example:me@example.com", "type": "TOTP" },
+            { "secret": KEY, "label": "counter", "type": "HOTP", "counter": 3 },
+        ])
+        .to_string();
+
+        let report = import(db.conn(), &dek, &backup).unwrap();
+        assert_eq!(report, OtpImport { format: OtpFormat::AndOtp, imported: 2, duplicates: 0, unsupported: 1 });
+        assert_eq!(import(db.conn(), &dek, &backup).unwrap().duplicates, 2);
+
+        let creds = db::get_all_credentials(db.conn()).unwrap();
+        let github = creds.iter().find(|c| c.name == "GitHub").unwrap();
+        assert_eq!(github.credential_type, CredentialType::Totp);
+        assert_eq!(github.username.as_deref(), Some("octocat"));
+        assert_eq!(github.tags, vec!["work".to_string()]);
+        assert!(creds.iter().any(|c| c.name == "Example" && c.username.as_deref() == Some("me@example.com")));
+
+        let exported = export(db.conn(), &dek, OtpFormat::Aegis).unwrap();
+        let (format, entries, _) = parse_backup(&exported).unwrap();
+        assert_eq!(format, OtpFormat::Aegis);
+        let github = entries.iter().find(|e| e.secret.issuer == "GitHub").unwrap();
+        assert_eq!(github.secret.secret, KEY);
+        assert_eq!((github.secret.digits, github.secret.period), (8, 60));
+        assert!(matches!(github.secret.algorithm, TotpAlgorithm::SHA256));
+    }
+
+    #[test]
+    fn test_import_aegis_and_export_andotp() {
+        let (_dir, db, dek) = test_db();
+        let backup = json!({
+            "version": 1,
+            "header": { "slots": null, "params": null },
+            "db": { "version": 2, "entries": [
+                { "type": "totp", "uuid": "x", "name": "me@example.com", "issuer": "Mail", "note": "recovery in drawer",
+                  "info": { "secret": KEY, "algo": "SHA1", "digits": 6, "period": 30 } },
+                { "type": "steam", "name": "gaben", "issuer": "Steam", "info": { "secret": KEY } },
+            ] },
+        })
+        .to_string();
+
+        let report = import(db.conn(), &dek, &backup).unwrap();
+        assert_eq!((report.format, report.imported, report.unsupported), (OtpFormat::Aegis, 1, 1));
+        let cred = db::get_all_credentials(db.conn()).unwrap().remove(0);
+        let notes = decrypt_fields(&dek, &cred).unwrap().notes.unwrap();
+        assert_eq!(notes.expose_secret(), "recovery in drawer");
+
+        let exported: Value = serde_json::from_str(&export(db.conn(), &dek, OtpFormat::AndOtp).unwrap()).unwrap();
+        assert_eq!(exported[0]["issuer"], "Mail");
+        assert_eq!(exported[0]["label"], "me@example.com");
+        assert_eq!(exported[0]["secret"], KEY);
+    }
+
+    #[test]
+    fn test_refuses_encrypted_and_invalid_backups() {
+        let (_dir, db, dek) = test_db();
+        let aegis = json!({ "version": 1, "header": { "slots": [], "params": {} }, "db": "bm9wZQ==" }).to_string();
+        assert!(import(db.conn(), &dek, &aegis).unwrap_err().to_string().contains("encrypted Aegis"));
+        assert!(import(db.conn(), &dek, "\u{1}\u{2}binary").unwrap_err().to_string().contains("encrypted andOTP"));
+
+        // A bad key anywhere leaves the vault untouched
+        let backup = json!([
+            { "secret": KEY, "label": "good", "type": "TOTP" },
+            { "secret": "not base32!", "label": "bad", "type": "TOTP" },
+        ])
+        .to_string();
+        assert!(import(db.conn(), &dek, &backup).is_err());
+        assert!(db::get_all_credentials(db.conn()).unwrap().is_empty());
+    }
+}
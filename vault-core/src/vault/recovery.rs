@@ -0,0 +1,224 @@
+//! Recovery Kit
+//!
+//! A printable copy of what is needed, besides the master password, to
+//! rebuild the vault's key metadata: the wrapped DEK, the Argon2 salt and
+//! parameters, and a fingerprint identifying the key. The Argon2 output is
+//! left out, so the kit alone cannot unwrap anything.
+
+use std::path::Path;
+
+use qrcode::render::unicode::Dense1x2;
+use qrcode::{EcLevel, QrCode};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::{kdf_params_string, rederive_master_key, DataEncryptionKey};
+use crate::db;
+
+use super::credential;
+use super::{Vault, VaultError, VaultResult};
+
+const KIT_VERSION: &str = "vaultkit1";
+const BEGIN_MARKER: &str = "-----BEGIN VAULT RECOVERY KIT-----";
+const END_MARKER: &str = "-----END VAULT RECOVERY KIT-----";
+const LINE_WIDTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryKit {
+    /// Argon2 algorithm, parameters and salt, without the hash output
+    pub kdf_params: String,
+    pub wrapped_dek: String,
+    pub fingerprint: String,
+}
+
+impl RecoveryKit {
+    /// Kit for the primary key slot of an unlocked vault
+    pub fn export(vault: &Vault) -> VaultResult<Self> {
+        let conn = vault.db()?.conn();
+        let password_hash = db::get_metadata(conn, "password_hash")?.ok_or(VaultError::NotFound)?;
+        let wrapped_dek = db::get_metadata(conn, "wrapped_dek")?.ok_or(VaultError::NotFound)?;
+        let kdf_params = kdf_params_string(&password_hash).map_err(|e| VaultError::CryptoError(e.to_string()))?;
+
+        Ok(Self { kdf_params, fingerprint: fingerprint(&wrapped_dek), wrapped_dek })
+    }
+
+    /// Base58Check encoding of the kit, safe to type back in by hand
+    pub fn encode(&self) -> String {
+        let payload = [KIT_VERSION, &self.kdf_params, &self.wrapped_dek, &self.fingerprint].join("\n");
+        bs58::encode(payload).with_check().into_string()
+    }
+
+    /// Decode a kit, ignoring whitespace and line breaks
+    pub fn decode(code: &str) -> VaultResult<Self> {
+        let code: String = code.split_whitespace().collect();
+        let bytes = bs58::decode(code)
+            .with_check(None)
+            .into_vec()
+            .map_err(|e| invalid_kit(&e.to_string()))?;
+        let payload = String::from_utf8(bytes).map_err(|_| invalid_kit("not text"))?;
+
+        let fields: Vec<&str> = payload.split('\n').collect();
+        let [version, kdf_params, wrapped_dek, fingerprint] = fields[..] else {
+            return Err(invalid_kit("wrong number of fields"));
+        };
+        if version != KIT_VERSION {
+            return Err(invalid_kit(&format!("unsupported version '{}'", version)));
+        }
+        Ok(Self {
+            kdf_params: kdf_params.to_string(),
+            wrapped_dek: wrapped_dek.to_string(),
+            fingerprint: fingerprint.to_string(),
+        })
+    }
+
+    /// Read a kit as printed by `render`, or a bare code
+    pub fn parse(text: &str) -> VaultResult<Self> {
+        let code = match (text.find(BEGIN_MARKER), text.find(END_MARKER)) {
+            (Some(begin), Some(end)) if begin < end => &text[begin + BEGIN_MARKER.len()..end],
+            _ => text,
+        };
+        Self::decode(code)
+    }
+
+    pub fn qr_code(&self) -> VaultResult<String> {
+        let code = QrCode::with_error_correction_level(self.encode(), EcLevel::M)
+            .map_err(|e| VaultError::OperationFailed(e.to_string()))?;
+        Ok(code.render::<Dense1x2>().quiet_zone(true).build())
+    }
+
+    /// The full printable page
+    pub fn render(&self, vault_path: &Path) -> VaultResult<String> {
+        let encoded = self.encode();
+        let lines: Vec<&str> = encoded
+            .as_bytes()
+            .chunks(LINE_WIDTH)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+            .collect();
+
+        Ok(format!(
+            "VAULT RECOVERY KIT\n\n\
+             Vault:       {}\n\
+             Created:     {}\n\
+             Fingerprint: {}\n\n\
+             {}\n\
+             {}\n{}\n{}\n\n\
+             Restore with `vault recover <this file> [VAULT_PATH]` and the master\n\
+             password that was current when this kit was made.\n",
+            vault_path.display(),
+            chrono::Local::now().format("%Y-%m-%d %H:%M"),
+            self.fingerprint,
+            self.qr_code()?,
+            BEGIN_MARKER,
+            lines.join("\n"),
+            END_MARKER,
+        ))
+    }
+}
+
+/// Short identifier of a wrapped DEK, e.g. `1A2B-3C4D-5E6F-7081`
+pub fn fingerprint(wrapped_dek: &str) -> String {
+    let digest = hex::encode_upper(&Sha256::digest(wrapped_dek.as_bytes())[..8]);
+    digest
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Rebuild the primary key slot from a kit. The password must be the one
+/// the kit was made with, and the recovered DEK must decrypt the vault's
+/// credentials, so a kit from another vault is refused. Also lifts a
+/// failed-unlock wipe or throttle.
+pub fn recover(conn: &rusqlite::Connection, kit: &RecoveryKit, password: &str) -> VaultResult<()> {
+    if fingerprint(&kit.wrapped_dek) != kit.fingerprint {
+        return Err(invalid_kit("fingerprint does not match its key"));
+    }
+
+    let (master_key, password_hash) = rederive_master_key(password.as_bytes(), &kit.kdf_params)
+        .map_err(|e| VaultError::CryptoError(e.to_string()))?;
+    let dek = DataEncryptionKey::unwrap(&kit.wrapped_dek, &master_key).map_err(|_| VaultError::InvalidPassword)?;
+    ensure_dek_matches(conn, &dek)?;
+
+    db::set_metadata(conn, "password_hash", &password_hash)?;
+    db::set_metadata(conn, "wrapped_dek", &kit.wrapped_dek)?;
+    conn.execute(
+        "DELETE FROM metadata WHERE key IN ('wiped_at', 'consecutive_failed_unlocks', 'unlock_not_before')",
+        [],
+    )?;
+    Ok(())
+}
+
+fn ensure_dek_matches(conn: &rusqlite::Connection, dek: &DataEncryptionKey) -> VaultResult<()> {
+    let Some(cred) = db::get_all_credentials(conn)?.into_iter().next() else {
+        return Ok(());
+    };
+    credential::decrypt_fields(dek, &cred)
+        .map_err(|_| VaultError::OperationFailed("Recovery kit belongs to a different vault".to_string()))?;
+    Ok(())
+}
+
+fn invalid_kit(reason: &str) -> VaultError {
+    VaultError::OperationFailed(format!("Invalid recovery kit: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::CredentialType;
+    use crate::vault::{credential, VaultConfig};
+    use tempfile::TempDir;
+
+    fn vault_with_credential(dir: &TempDir, name: &str) -> Vault {
+        let mut vault = Vault::new(VaultConfig::with_path(dir.path().join(format!("{}.db", name))));
+        vault.initialize("password").unwrap();
+        credential::create_credential(
+            vault.db().unwrap().conn(),
+            vault.dek().unwrap(),
+            credential::NewCredential::new(name.to_string(), CredentialType::Password, "secret"),
+        )
+        .unwrap();
+        vault
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let vault = vault_with_credential(&dir, "a");
+        let kit = RecoveryKit::export(&vault).unwrap();
+        assert!(!kit.kdf_params.is_empty());
+
+        let printed = kit.render(&vault.config().path).unwrap();
+        assert!(printed.contains(&kit.fingerprint));
+        assert_eq!(RecoveryKit::parse(&printed).unwrap(), kit);
+
+        let mut typo = kit.encode();
+        typo.replace_range(10..11, if &typo[10..11] == "2" { "3" } else { "2" });
+        assert!(RecoveryKit::decode(&typo).is_err());
+    }
+
+    #[test]
+    fn test_recover_corrupted_metadata() {
+        let dir = TempDir::new().unwrap();
+        let mut vault = vault_with_credential(&dir, "a");
+        let kit = RecoveryKit::export(&vault).unwrap();
+
+        let conn = vault.db().unwrap().conn();
+        conn.execute("DELETE FROM metadata WHERE key IN ('password_hash', 'wrapped_dek')", []).unwrap();
+        assert!(matches!(recover(conn, &kit, "wrong"), Err(VaultError::InvalidPassword)));
+        recover(conn, &kit, "password").unwrap();
+
+        vault.lock();
+        vault.unlock("password").unwrap();
+    }
+
+    #[test]
+    fn test_recover_refuses_other_vault() {
+        let dir = TempDir::new().unwrap();
+        let kit = RecoveryKit::export(&vault_with_credential(&dir, "a")).unwrap();
+        let other = vault_with_credential(&dir, "b");
+        let conn = other.db().unwrap().conn();
+
+        assert!(matches!(recover(conn, &kit, "password"), Err(VaultError::OperationFailed(_))));
+        assert_ne!(db::get_metadata(conn, "wrapped_dek").unwrap(), Some(kit.wrapped_dek));
+    }
+}
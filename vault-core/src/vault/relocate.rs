@@ -0,0 +1,113 @@
+//! Moving the vault file
+//!
+//! `:vaultmove` copies the open database through SQLite's backup API,
+//! syncs the copy to disk and checks it against the original before the
+//! session switches over to it. Until then any failure deletes the copy
+//! and leaves the vault where it was; the old file is removed last.
+
+use std::fs::File;
+use std::path::Path;
+
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+
+use super::backup::{io_error, sibling_path};
+use super::{VaultError, VaultResult};
+
+/// Write a verified copy of the database to `dest`, which must not exist
+pub fn copy_verified(conn: &Connection, dest: &Path) -> VaultResult<()> {
+    if dest.exists() {
+        return Err(VaultError::OperationFailed(format!("{} already exists", dest.display())));
+    }
+    let dir = match dest.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(dir).map_err(io_error)?;
+
+    let staging = sibling_path(dest, ".moving");
+    let result = write_copy(conn, &staging).and_then(|_| {
+        std::fs::rename(&staging, dest).map_err(io_error)?;
+        sync_dir(dir)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&staging);
+    }
+    result
+}
+
+fn write_copy(conn: &Connection, staging: &Path) -> VaultResult<()> {
+    conn.backup(DatabaseName::Main, staging, None)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(staging, std::fs::Permissions::from_mode(0o600)).map_err(io_error)?;
+    }
+    File::open(staging).and_then(|file| file.sync_all()).map_err(io_error)?;
+    verify_copy(conn, staging)
+}
+
+/// The copy passes an integrity check and holds the same tables with the
+/// same number of rows
+fn verify_copy(original: &Connection, copy: &Path) -> VaultResult<()> {
+    let copy = Connection::open_with_flags(copy, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let check: String = copy.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    if check != "ok" {
+        return Err(VaultError::OperationFailed(format!("copy failed the integrity check: {}", check)));
+    }
+    if row_counts(original)? != row_counts(&copy)? {
+        return Err(VaultError::OperationFailed("copy does not match the vault".into()));
+    }
+    Ok(())
+}
+
+fn row_counts(conn: &Connection) -> VaultResult<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")?;
+    let tables = stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+    tables
+        .into_iter()
+        .map(|table| {
+            let sql = format!("SELECT count(*) FROM \"{}\"", table.replace('"', "\"\""));
+            let count = conn.query_row(&sql, [], |row| row.get(0))?;
+            Ok((table, count))
+        })
+        .collect()
+}
+
+/// Make the rename itself durable
+fn sync_dir(dir: &Path) -> VaultResult<()> {
+    #[cfg(unix)]
+    File::open(dir).and_then(|dir| dir.sync_all()).map_err(io_error)?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Delete a vault file with its WAL and shared-memory files
+pub fn remove_vault_file(path: &Path) -> VaultResult<()> {
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(sibling_path(path, suffix));
+    }
+    std::fs::remove_file(path).map_err(io_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Database, DatabaseConfig};
+
+    #[test]
+    fn test_copy_verified() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(DatabaseConfig::with_path(dir.path().join("vault.db"))).unwrap();
+        crate::db::set_metadata(db.conn(), "marker", "kept").unwrap();
+
+        let dest = dir.path().join("moved").join("vault.db");
+        copy_verified(db.conn(), &dest).unwrap();
+        let moved = Database::open(DatabaseConfig::with_path(&dest)).unwrap();
+        assert_eq!(crate::db::get_metadata(moved.conn(), "marker").unwrap().as_deref(), Some("kept"));
+        assert!(!sibling_path(&dest, ".moving").exists());
+
+        // Never overwrites
+        assert!(copy_verified(db.conn(), &dest).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! Environment Runner
+//!
+//! Resolves credentials into environment variables for `vault exec` and
+//! starts the command with them. Secrets only ever live in the child's
+//! environment; nothing is written to disk.
+
+use std::process::Command;
+
+use secrecy::SecretString;
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::{self, Credential};
+
+use super::backend::{decrypt_resolved, Backends};
+use super::{VaultError, VaultResult};
+
+/// Which credentials to export. A credential is picked when it has an
+/// environment variable and either carries every tag or is named.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    pub tags: Vec<String>,
+    pub names: Vec<String>,
+}
+
+impl Selection {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.names.is_empty()
+    }
+
+    fn matches(&self, cred: &Credential) -> bool {
+        let tagged = !self.tags.is_empty() && self.tags.iter().all(|t| cred.tags.contains(t));
+        tagged || self.names.contains(&cred.name)
+    }
+}
+
+pub struct EnvVar {
+    pub name: String,
+    pub value: SecretString,
+    pub credential: Credential,
+    /// Backend the value was fetched from, if it is not kept in the vault
+    pub backend: Option<String>,
+}
+
+/// POSIX-style name: a letter or underscore, then letters, digits or underscores
+pub fn is_valid_env_var(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Decrypt the selected credentials into environment variables. Fails
+/// when a named credential has no variable or two share one.
+pub fn resolve_env(
+    conn: &rusqlite::Connection,
+    dek: &DataEncryptionKey,
+    backends: &Backends,
+    selection: &Selection,
+) -> VaultResult<Vec<EnvVar>> {
+    let selected: Vec<Credential> = db::get_all_credentials(conn)?
+        .into_iter()
+        .filter(|c| selection.matches(c))
+        .collect();
+
+    for name in &selection.names {
+        match selected.iter().find(|c| &c.name == name) {
+            None => return Err(VaultError::OperationFailed(format!("No credential named '{}'", name))),
+            Some(c) if c.env_var.is_none() => {
+                return Err(VaultError::OperationFailed(format!("'{}' has no environment variable set", name)));
+            }
+            Some(_) => {}
+        }
+    }
+
+    let mut vars: Vec<EnvVar> = Vec::new();
+    for cred in selected {
+        let Some(name) = cred.env_var.clone() else { continue };
+        if let Some(other) = vars.iter().find(|v| v.name == name) {
+            return Err(VaultError::OperationFailed(format!(
+                "'{}' and '{}' both export {}",
+                other.credential.name, cred.name, name
+            )));
+        }
+        let (decrypted, backend) = decrypt_resolved(conn, dek, backends, &cred)?;
+        let value = decrypted.secret.unwrap_or_else(|| SecretString::from(String::new()));
+        vars.push(EnvVar { name, value, credential: cred, backend });
+    }
+    Ok(vars)
+}
+
+/// Run `argv` with the variables added to the inherited environment.
+/// On Unix the current process is replaced and this only returns on
+/// failure; elsewhere the child's exit code is returned.
+pub fn exec<'a>(argv: &[String], vars: impl IntoIterator<Item = (&'a str, &'a str)>) -> VaultResult<i32> {
+    let (program, args) = argv
+        .split_first()
+        .ok_or_else(|| VaultError::OperationFailed("No command given".to_string()))?;
+    let mut command = Command::new(program);
+    command.args(args);
+    command.envs(vars);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let err = command.exec();
+        Err(VaultError::IoError(format!("{}: {}", program, err)))
+    }
+    #[cfg(not(unix))]
+    {
+        let status = command.status().map_err(|e| VaultError::IoError(format!("{}: {}", program, e)))?;
+        Ok(status.code().unwrap_or(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::ExposeSecret;
+    use crate::db::{CredentialType, Database};
+    use crate::vault::credential::{create_credential, set_env_var, NewCredential};
+
+    fn add(conn: &rusqlite::Connection, dek: &DataEncryptionKey, name: &str, tags: &[&str], env_var: Option<&str>) {
+        let mut cred = create_credential(
+            conn,
+            dek,
+            NewCredential {
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                ..NewCredential::new(name.to_string(), CredentialType::Password, &format!("{}-secret", name))
+            },
+        )
+        .unwrap();
+        set_env_var(conn, &mut cred, env_var.map(str::to_string)).unwrap();
+    }
+
+    #[test]
+    fn test_env_var_names() {
+        assert!(is_valid_env_var("DATABASE_URL"));
+        assert!(is_valid_env_var("_private1"));
+        assert!(!is_valid_env_var("1PASSWORD"));
+        assert!(!is_valid_env_var("API-KEY"));
+        assert!(!is_valid_env_var(""));
+    }
+
+    #[test]
+    fn test_resolve_env() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let dek = DataEncryptionKey::from_bytes([0x42u8; 32]);
+        add(conn, &dek, "prod-db", &["prod"], Some("DB_PASSWORD"));
+        add(conn, &dek, "prod-api", &["prod", "api"], Some("API_KEY"));
+        add(conn, &dek, "prod-notes", &["prod"], None);
+        add(conn, &dek, "dev-db", &["dev"], Some("DB_PASSWORD"));
+
+        let selection = Selection { tags: vec!["prod".to_string()], names: vec![] };
+        let vars = resolve_env(conn, &dek, &Backends::new(), &selection).unwrap();
+        let pairs: Vec<(&str, &str)> = vars.iter().map(|v| (v.name.as_str(), v.value.expose_secret())).collect();
+        assert_eq!(pairs, vec![("API_KEY", "prod-api-secret"), ("DB_PASSWORD", "prod-db-secret")]);
+
+        let clash = Selection { tags: vec!["prod".to_string()], names: vec!["dev-db".to_string()] };
+        assert!(resolve_env(conn, &dek, &Backends::new(), &clash).is_err());
+
+        let unmapped = Selection { tags: vec![], names: vec!["prod-notes".to_string()] };
+        assert!(resolve_env(conn, &dek, &Backends::new(), &unmapped).is_err());
+    }
+
+    #[test]
+    fn test_invalid_env_var_refused() {
+        let db = Database::open_in_memory().unwrap();
+        let dek = DataEncryptionKey::from_bytes([0x42u8; 32]);
+        let mut cred = create_credential(
+            db.conn(),
+            &dek,
+            NewCredential::new("x".to_string(), CredentialType::Password, "s"),
+        )
+        .unwrap();
+        assert!(set_env_var(db.conn(), &mut cred, Some("NOT VALID".to_string())).is_err());
+    }
+}
@@ -0,0 +1,470 @@
+//! Search Operations
+//!
+//! Fast search and filtering of credentials.
+
+use crate::db::{self, Credential, CredentialType, SearchQuery, SortOrder};
+
+use super::VaultResult;
+
+#[derive(Debug, Clone)]
+pub struct SearchResults {
+    pub credentials: Vec<Credential>,
+    pub total: usize,
+    pub query: Option<String>,
+}
+
+impl SearchResults {
+    pub fn new(credentials: Vec<Credential>, query: Option<String>) -> Self {
+        let total = credentials.len();
+        Self {
+            credentials,
+            total,
+            query,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.credentials.is_empty()
+    }
+}
+
+pub fn search(conn: &rusqlite::Connection, query: &str) -> VaultResult<SearchResults> {
+    let trimmed = query.trim();
+    if trimmed.is_empty() {
+        let credentials = db::get_all_credentials(conn)?;
+        return Ok(SearchResults::new(credentials, None));
+    }
+
+    let credentials = db::search_credentials(conn, trimmed)?;
+    Ok(SearchResults::new(credentials, Some(trimmed.to_string())))
+}
+
+pub fn search_by_tag(conn: &rusqlite::Connection, tag: &str) -> VaultResult<SearchResults> {
+    let credentials = db::get_credentials_by_tag(conn, &[tag.to_string()])?;
+    Ok(SearchResults::new(credentials, Some(format!("tag:{}", tag))))
+}
+
+pub fn search_by_type(
+    conn: &rusqlite::Connection,
+    cred_type: CredentialType,
+) -> VaultResult<SearchResults> {
+    let all = db::get_all_credentials(conn)?;
+    let filtered: Vec<_> = all
+        .into_iter()
+        .filter(|c| c.credential_type == cred_type)
+        .collect();
+
+    Ok(SearchResults::new(
+        filtered,
+        Some(format!("type:{}", cred_type.as_str())),
+    ))
+}
+
+fn fetch_initial_credentials(
+    conn: &rusqlite::Connection,
+    query: Option<&str>,
+) -> VaultResult<Vec<Credential>> {
+    let Some(q) = query else {
+        return db::get_all_credentials(conn).map_err(Into::into);
+    };
+
+    let trimmed = q.trim();
+    if trimmed.is_empty() {
+        return db::get_all_credentials(conn).map_err(Into::into);
+    }
+
+    db::search_credentials(conn, trimmed).map_err(Into::into)
+}
+
+fn filter_by_tag(credentials: &mut Vec<Credential>, tag: &str) {
+    let tag_lower = tag.to_lowercase();
+    credentials.retain(|c| credential_has_tag(c, &tag_lower));
+}
+
+fn credential_has_tag(cred: &Credential, tag_lower: &str) -> bool {
+    cred.tags.iter().any(|ct| ct.to_lowercase().contains(tag_lower))
+}
+
+fn filter_by_type(credentials: &mut Vec<Credential>, cred_type: CredentialType) {
+    credentials.retain(|c| c.credential_type == cred_type);
+}
+
+fn build_query_string(
+    query: Option<&str>,
+    tag: Option<&str>,
+    cred_type: Option<CredentialType>,
+) -> Option<String> {
+    let mut parts = Vec::new();
+
+    if let Some(q) = query {
+        let trimmed = q.trim();
+        if !trimmed.is_empty() {
+            parts.push(trimmed.to_string());
+        }
+    }
+
+    if let Some(t) = tag {
+        parts.push(format!("tag:{}", t));
+    }
+
+    if let Some(ct) = cred_type {
+        parts.push(format!("type:{}", ct.as_str()));
+    }
+
+    if parts.is_empty() {
+        return None;
+    }
+
+    Some(parts.join(" "))
+}
+
+pub fn search_combined(
+    conn: &rusqlite::Connection,
+    query: Option<&str>,
+    tag: Option<&str>,
+    cred_type: Option<CredentialType>,
+) -> VaultResult<SearchResults> {
+    let mut credentials = fetch_initial_credentials(conn, query)?;
+
+    if let Some(t) = tag {
+        filter_by_tag(&mut credentials, t);
+    }
+
+    if let Some(ct) = cred_type {
+        filter_by_type(&mut credentials, ct);
+    }
+
+    let query_string = build_query_string(query, tag, cred_type);
+    Ok(SearchResults::new(credentials, query_string))
+}
+
+fn compare_by_access_time(a: &Credential, b: &Credential) -> std::cmp::Ordering {
+    let a_time = a.accessed_at.unwrap_or(a.updated_at);
+    let b_time = b.accessed_at.unwrap_or(b.updated_at);
+    b_time.cmp(&a_time)
+}
+
+pub fn get_recent(conn: &rusqlite::Connection, limit: usize) -> VaultResult<SearchResults> {
+    let mut all = db::get_all_credentials(conn)?;
+    all.sort_by(compare_by_access_time);
+    all.truncate(limit);
+    Ok(SearchResults::new(all, Some("recent".to_string())))
+}
+
+pub fn get_all_tags(conn: &rusqlite::Connection) -> VaultResult<Vec<String>> {
+    let all = db::get_all_credentials(conn)?;
+    let mut tags: Vec<String> = all.into_iter().flat_map(|c| c.tags).collect();
+    tags.sort();
+    tags.dedup();
+    Ok(tags)
+}
+
+/// Every tag with how many credentials carry it
+pub fn get_tags_with_counts(conn: &rusqlite::Connection) -> VaultResult<Vec<(String, usize)>> {
+    Ok(db::get_all_tags_with_counts(conn)?)
+}
+
+/// Credentials matching a parsed query, its filters included
+pub fn search_filtered(conn: &rusqlite::Connection, query: &SearchQuery) -> VaultResult<Vec<Credential>> {
+    Ok(db::search_credentials_filtered(conn, query)?)
+}
+
+pub fn count_credentials(conn: &rusqlite::Connection) -> VaultResult<usize> {
+    Ok(db::count_credentials(conn)?)
+}
+
+/// Up to `limit` credentials from `offset` on, in `order`, for a list
+/// that loads a page at a time
+pub fn get_page(conn: &rusqlite::Connection, order: SortOrder, offset: usize, limit: usize) -> VaultResult<Vec<Credential>> {
+    Ok(db::get_credentials_page(conn, order, offset, limit)?)
+}
+
+/// Where credential `id` falls in the whole list in `order`
+pub fn position(conn: &rusqlite::Connection, order: SortOrder, id: &str) -> VaultResult<Option<usize>> {
+    Ok(db::credential_position(conn, order, id)?)
+}
+
+/// Multi-label public suffixes, so `example.co.uk` rather than `co.uk` is
+/// treated as the site. Not the full Public Suffix List: any other host
+/// falls back to its last label as the suffix.
+const MULTI_LABEL_SUFFIXES: &[&str] = &[
+    "co.uk", "org.uk", "ac.uk", "gov.uk", "me.uk", "ltd.uk", "plc.uk", "net.uk",
+    "com.au", "net.au", "org.au", "edu.au", "gov.au",
+    "co.nz", "org.nz", "co.jp", "ne.jp", "or.jp", "ac.jp", "co.kr", "or.kr",
+    "co.in", "net.in", "org.in", "co.za", "org.za", "co.il", "co.id", "co.th",
+    "com.br", "net.br", "org.br", "com.cn", "net.cn", "org.cn", "com.hk", "com.tw",
+    "com.sg", "com.my", "com.mx", "com.ar", "com.tr", "com.ua", "com.pl", "com.es",
+    "github.io", "gitlab.io", "herokuapp.com", "netlify.app", "vercel.app", "pages.dev",
+    "workers.dev", "web.app", "firebaseapp.com", "appspot.com", "azurewebsites.net",
+    "cloudfront.net", "blogspot.com", "s3.amazonaws.com",
+];
+
+/// Host, port and path of a URL, normalized for matching
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlParts {
+    /// Lowercase, without `www.` or a trailing dot
+    pub host: String,
+    /// Explicit, non-default port
+    pub port: Option<u16>,
+    pub path: String,
+}
+
+impl UrlParts {
+    /// Parse a full URL or a bare `host[:port][/path]`
+    pub fn parse(url: &str) -> Option<Self> {
+        let url = url.trim();
+        let (scheme, rest) = match url.split_once("://") {
+            Some((scheme, rest)) => (Some(scheme.to_ascii_lowercase()), rest),
+            None => (None, url),
+        };
+        let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        let (authority, path) = rest.split_at(end);
+        let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !host.ends_with(':') && !port.contains(']') => (host, Some(port.parse::<u16>().ok()?)),
+            _ => (authority, None),
+        };
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let host = host.strip_prefix("www.").map(str::to_string).unwrap_or(host);
+        if host.is_empty() || host.contains(char::is_whitespace) {
+            return None;
+        }
+
+        let default_port = match scheme.as_deref() {
+            Some("http") => Some(80),
+            Some("https") | None => Some(443),
+            _ => None,
+        };
+        let port = port.filter(|p| Some(*p) != default_port);
+        let path = path.split(['?', '#']).next().unwrap_or_default().trim_end_matches('/').to_string();
+        Some(Self { host, port, path })
+    }
+
+    fn is_ip(&self) -> bool {
+        self.host.starts_with('[') || self.host.parse::<std::net::Ipv4Addr>().is_ok()
+    }
+
+    /// eTLD+1, e.g. `example.co.uk` for `login.example.co.uk`
+    pub fn registrable_domain(&self) -> &str {
+        if self.is_ip() {
+            return &self.host;
+        }
+        let suffix_labels = MULTI_LABEL_SUFFIXES
+            .iter()
+            .filter(|suffix| self.host.ends_with(&format!(".{}", suffix)))
+            .map(|suffix| suffix.split('.').count())
+            .max()
+            .unwrap_or(1);
+        let labels: Vec<&str> = self.host.split('.').collect();
+        if labels.len() <= suffix_labels {
+            return &self.host;
+        }
+        let keep = suffix_labels + 1;
+        let skip: usize = labels[..labels.len() - keep].iter().map(|l| l.len() + 1).sum();
+        &self.host[skip..]
+    }
+}
+
+/// How closely a credential's URL matches a page, worst to best
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UrlMatchKind {
+    /// Another host on the same site, e.g. `api.github.com` for `gist.github.com`
+    SameSite,
+    /// The credential's host is a more specific subdomain of the page's
+    Subdomain,
+    /// The credential's host contains the page's, e.g. `github.com` for `gist.github.com`
+    Parent,
+    Exact,
+}
+
+#[derive(Debug, Clone)]
+pub struct UrlMatch {
+    pub credential: Credential,
+    pub kind: UrlMatchKind,
+    /// Labels between the two hosts, or 1 for the same host when only one
+    /// URL gives a port; fewer is closer
+    pub distance: usize,
+    /// Length of the credential's path when it is a prefix of the page's
+    pub path_depth: usize,
+}
+
+/// Credentials whose URL belongs to the same site as `url`, best match
+/// first. Ports must agree when both URLs give one.
+pub fn match_url(conn: &rusqlite::Connection, url: &str) -> VaultResult<Vec<UrlMatch>> {
+    let Some(page) = UrlParts::parse(url) else {
+        return Ok(Vec::new());
+    };
+    let mut matches: Vec<UrlMatch> = db::get_all_credentials(conn)?
+        .into_iter()
+        .filter_map(|cred| {
+            let stored = UrlParts::parse(cred.url.as_deref()?)?;
+            let (kind, distance) = compare_hosts(&stored, &page)?;
+            let path_depth = match page.path.starts_with(&stored.path) {
+                true => stored.path.len(),
+                false => 0,
+            };
+            Some(UrlMatch { credential: cred, kind, distance, path_depth })
+        })
+        .collect();
+    matches.sort_by(|a, b| {
+        b.kind
+            .cmp(&a.kind)
+            .then(a.distance.cmp(&b.distance))
+            .then(b.path_depth.cmp(&a.path_depth))
+            .then_with(|| a.credential.name.cmp(&b.credential.name))
+    });
+    Ok(matches)
+}
+
+fn compare_hosts(stored: &UrlParts, page: &UrlParts) -> Option<(UrlMatchKind, usize)> {
+    if let (Some(a), Some(b)) = (stored.port, page.port)
+        && a != b
+    {
+        return None;
+    }
+    if stored.host == page.host {
+        return Some((UrlMatchKind::Exact, usize::from(stored.port != page.port)));
+    }
+    if stored.is_ip() || page.is_ip() || stored.registrable_domain() != page.registrable_domain() {
+        return None;
+    }
+
+    let labels = |host: &str| host.split('.').count();
+    let (stored_labels, page_labels) = (labels(&stored.host), labels(&page.host));
+    if page.host.ends_with(&format!(".{}", stored.host)) {
+        return Some((UrlMatchKind::Parent, page_labels - stored_labels));
+    }
+    if stored.host.ends_with(&format!(".{}", page.host)) {
+        return Some((UrlMatchKind::Subdomain, stored_labels - page_labels));
+    }
+    let site_labels = labels(stored.registrable_domain());
+    Some((UrlMatchKind::SameSite, stored_labels + page_labels - 2 * site_labels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{encrypt_string, MasterKey};
+    use crate::db::Database;
+
+    fn test_key() -> MasterKey {
+        MasterKey::from_bytes([0x42u8; 32])
+    }
+
+    fn create_test_credential(name: &str, ctype: CredentialType, tags: Vec<&str>) -> Credential {
+        let key = test_key();
+        let blob = encrypt_string(key.as_ref(), "secret").unwrap();
+        let mut cred = Credential::new(name.to_string(), ctype, blob);
+        cred.tags = tags.into_iter().map(|s| s.to_string()).collect();
+        cred
+    }
+
+    fn setup_test_data(conn: &rusqlite::Connection) {
+        let creds = vec![
+            ("AWS Prod", CredentialType::ApiKey, vec!["cloud", "prod"]),
+            ("AWS Staging", CredentialType::ApiKey, vec!["cloud", "staging"]),
+            ("GitHub Token", CredentialType::ApiKey, vec!["dev"]),
+            ("Gmail", CredentialType::Password, vec!["personal"]),
+        ];
+
+        for (name, ctype, tags) in creds {
+            let cred = create_test_credential(name, ctype, tags);
+            db::create_credential(conn, &cred).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        let db = Database::open_in_memory().unwrap();
+        setup_test_data(db.conn());
+
+        let results = search(db.conn(), "AWS").unwrap();
+        assert_eq!(results.total, 2);
+
+        let results = search(db.conn(), "GitHub").unwrap();
+        assert_eq!(results.total, 1);
+
+        let results = search(db.conn(), "").unwrap();
+        assert_eq!(results.total, 4);
+    }
+
+    #[test]
+    fn test_search_by_type() {
+        let db = Database::open_in_memory().unwrap();
+        setup_test_data(db.conn());
+
+        let results = search_by_type(db.conn(), CredentialType::ApiKey).unwrap();
+        assert_eq!(results.total, 3);
+
+        let results = search_by_type(db.conn(), CredentialType::Password).unwrap();
+        assert_eq!(results.total, 1);
+    }
+
+    #[test]
+    fn test_combined_search() {
+        let db = Database::open_in_memory().unwrap();
+        setup_test_data(db.conn());
+
+        let results = search_combined(db.conn(), Some("AWS"), Some("prod"), None).unwrap();
+        assert_eq!(results.total, 1);
+        assert_eq!(results.credentials[0].name, "AWS Prod");
+    }
+
+    #[test]
+    fn test_url_parts() {
+        let parts = UrlParts::parse("https://user@WWW.GitHub.com:443/login?next=/").unwrap();
+        assert_eq!(parts, UrlParts { host: "github.com".to_string(), port: None, path: "/login".to_string() });
+        assert_eq!(UrlParts::parse("localhost:8080").unwrap().port, Some(8080));
+        assert_eq!(UrlParts::parse("http://[::1]:3000/").unwrap().host, "[::1]");
+        assert!(UrlParts::parse("https://host:notaport").is_none());
+
+        let domain = |url: &str| UrlParts::parse(url).unwrap().registrable_domain().to_string();
+        assert_eq!(domain("https://login.bank.co.uk"), "bank.co.uk");
+        assert_eq!(domain("gist.github.com"), "github.com");
+        assert_eq!(domain("me.github.io"), "me.github.io");
+        assert_eq!(domain("co.uk"), "co.uk");
+        assert_eq!(domain("10.0.0.1"), "10.0.0.1");
+    }
+
+    #[test]
+    fn test_match_url() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let urls = [
+            ("GitHub", "https://github.com"),
+            ("GitHub Enterprise", "github.com:8443"),
+            ("Gist", "https://gist.github.com/me"),
+            ("GitHub API", "api.github.com"),
+            ("Other Pages", "https://someone.github.io"),
+            ("Bank", "https://bank.co.uk"),
+            ("Other Bank", "https://other.co.uk"),
+        ];
+        for (name, url) in urls {
+            let mut cred = create_test_credential(name, CredentialType::Password, vec![]);
+            cred.url = Some(url.to_string());
+            db::create_credential(conn, &cred).unwrap();
+        }
+
+        let names = |url: &str| -> Vec<String> {
+            match_url(conn, url).unwrap().into_iter().map(|m| m.credential.name).collect()
+        };
+        assert_eq!(names("https://gist.github.com/me/abc"), vec!["Gist", "GitHub", "GitHub Enterprise", "GitHub API"]);
+        assert_eq!(names("https://github.com/login"), vec!["GitHub", "GitHub Enterprise", "Gist", "GitHub API"]);
+        assert_eq!(names("https://github.com:8443/"), vec!["GitHub Enterprise", "GitHub", "Gist", "GitHub API"]);
+        assert_eq!(names("https://me.github.io"), Vec::<String>::new());
+        assert_eq!(names("https://www.bank.co.uk/login"), vec!["Bank"]);
+        assert!(names("not a url").is_empty());
+    }
+
+    #[test]
+    fn test_get_all_tags() {
+        let db = Database::open_in_memory().unwrap();
+        setup_test_data(db.conn());
+
+        let tags = get_all_tags(db.conn()).unwrap();
+        assert!(tags.contains(&"cloud".to_string()));
+        assert!(tags.contains(&"prod".to_string()));
+        assert!(tags.contains(&"dev".to_string()));
+    }
+}
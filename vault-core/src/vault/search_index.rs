@@ -0,0 +1,184 @@
+//! In-memory search index
+//!
+//! The FTS5 table keeps searchable words on disk next to the encrypted
+//! data. With `search_index` set to `memory` the vault drops that table and
+//! searches this index instead, built from the decrypted credentials after
+//! unlock and brought up to date before each search, so nothing searchable
+//! outlives the session.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::{self, Credential};
+
+use super::credential::decrypt_notes;
+use super::VaultResult;
+
+/// Where the search index lives
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchIndexMode {
+    /// FTS5 table in the vault file
+    #[default]
+    Disk,
+    /// Built after unlock and never written
+    Memory,
+}
+
+/// Field weights, in the order the fields are indexed: name, alias,
+/// username, url, tags, notes
+const FIELD_WEIGHTS: [u32; 6] = [8, 8, 4, 2, 2, 1];
+
+struct Entry {
+    updated_at: DateTime<Local>,
+    /// Lowercased words of each field
+    fields: Zeroizing<Vec<Vec<String>>>,
+}
+
+#[derive(Default)]
+pub struct MemoryIndex {
+    entries: HashMap<String, Entry>,
+}
+
+/// Lowercased words, split the way FTS5's default tokenizer does
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Whether `query` appears as a run of words in `words`, the last one
+/// allowed to be a prefix, like an FTS5 `"phrase"*` query
+fn phrase_matches(words: &[String], query: &[String]) -> bool {
+    let Some((last, rest)) = query.split_last() else { return false };
+    words.windows(query.len()).any(|window| {
+        window[..rest.len()] == *rest && window[rest.len()].starts_with(last.as_str())
+    })
+}
+
+impl MemoryIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Re-index the credentials added or changed since the last call and
+    /// forget deleted ones
+    pub fn sync(&mut self, conn: &rusqlite::Connection, dek: &DataEncryptionKey) -> VaultResult<()> {
+        let stamps = db::credential_stamps(conn)?;
+        let current: HashSet<&str> = stamps.iter().map(|(id, _)| id.as_str()).collect();
+        self.entries.retain(|id, _| current.contains(id.as_str()));
+
+        let stale: HashSet<&str> = stamps
+            .iter()
+            .filter(|(id, updated_at)| self.entries.get(id).is_none_or(|e| e.updated_at != *updated_at))
+            .map(|(id, _)| id.as_str())
+            .collect();
+        if stale.is_empty() {
+            return Ok(());
+        }
+        for cred in db::get_all_credentials(conn)?.iter().filter(|c| stale.contains(c.id.as_str())) {
+            self.insert(dek, cred)?;
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, dek: &DataEncryptionKey, cred: &Credential) -> VaultResult<()> {
+        let notes = Zeroizing::new(decrypt_notes(dek, &cred.id, cred.encrypted_notes.as_ref())?.unwrap_or_default());
+        let fields = vec![
+            tokenize(&cred.name),
+            tokenize(cred.alias.as_deref().unwrap_or_default()),
+            tokenize(cred.username.as_deref().unwrap_or_default()),
+            tokenize(cred.url.as_deref().unwrap_or_default()),
+            tokenize(&cred.tags.join(" ")),
+            tokenize(&notes),
+        ];
+        let entry = Entry { updated_at: cred.updated_at, fields: Zeroizing::new(fields) };
+        self.entries.insert(cred.id.clone(), entry);
+        Ok(())
+    }
+
+    fn score(&self, id: &str, query: &[String]) -> u32 {
+        let Some(entry) = self.entries.get(id) else { return 0 };
+        entry
+            .fields
+            .iter()
+            .zip(FIELD_WEIGHTS)
+            .filter(|(words, _)| phrase_matches(words, query))
+            .map(|(_, weight)| weight)
+            .sum()
+    }
+
+    /// The credentials matching `text`, best matches first. Ties keep
+    /// their order, so an already sorted list stays sorted within a score.
+    pub fn search(&self, text: &str, credentials: Vec<Credential>) -> Vec<Credential> {
+        let query = tokenize(text);
+        if query.is_empty() {
+            return credentials;
+        }
+        let mut scored: Vec<(u32, Credential)> = credentials
+            .into_iter()
+            .map(|c| (self.score(&c.id, &query), c))
+            .filter(|(score, _)| *score > 0)
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, c)| c).collect()
+    }
+
+    /// Forget everything, e.g. when the vault locks
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{CredentialType, Database};
+    use crate::vault::credential::{create_credential, update_credential, NewCredential};
+
+    fn test_dek() -> DataEncryptionKey {
+        DataEncryptionKey::from_bytes([0x42u8; 32])
+    }
+
+    fn add(conn: &rusqlite::Connection, name: &str, notes: Option<&str>) -> Credential {
+        let new = NewCredential { notes, ..NewCredential::new(name.to_string(), CredentialType::Password, "secret") };
+        create_credential(conn, &test_dek(), new).unwrap()
+    }
+
+    fn names(results: &[Credential]) -> Vec<&str> {
+        results.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_memory_search() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        add(conn, "AWS Production", None);
+        add(conn, "Backup box", Some("rotate the aws keys monthly"));
+        let mut github = add(conn, "GitHub", None);
+
+        let mut index = MemoryIndex::new();
+        index.sync(conn, &test_dek()).unwrap();
+        let search = |index: &MemoryIndex, q: &str| {
+            let all = db::get_all_credentials(conn).unwrap();
+            names(&index.search(q, all)).join(",")
+        };
+        assert_eq!(search(&index, "aws"), "AWS Production,Backup box");
+        assert_eq!(search(&index, "aws prod"), "AWS Production");
+        assert_eq!(search(&index, "keys month"), "Backup box");
+        assert_eq!(search(&index, "prod aws"), "");
+
+        github.name = "GitLab".to_string();
+        github.updated_at += chrono::Duration::seconds(1);
+        update_credential(conn, &test_dek(), &mut github, None, None).unwrap();
+        db::delete_credential(conn, &db::get_all_credentials(conn).unwrap()[0].id).unwrap();
+        index.sync(conn, &test_dek()).unwrap();
+        assert_eq!(search(&index, "gitl"), "GitLab");
+        assert_eq!(search(&index, "production"), "");
+    }
+}
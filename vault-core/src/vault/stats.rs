@@ -0,0 +1,87 @@
+//! Vault Statistics
+//!
+//! What the `:info` screen reports about an open vault: how many
+//! credentials of each type it holds, its tags, audit log and key slots,
+//! the key derivation parameters of its password and the formats its
+//! credentials are encrypted in. Read straight from the database, so
+//! checking them needs no sqlite3.
+
+use std::collections::BTreeMap;
+
+use crate::crypto::{describe_kdf, Format};
+use crate::db::{self, AccessStats};
+
+use super::cryptoinfo::crypto_info;
+use super::VaultResult;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultStats {
+    pub credentials: usize,
+    /// Credentials per stored type, most common first
+    pub by_type: Vec<(String, usize)>,
+    pub tags: usize,
+    pub audit_entries: usize,
+    /// Extra master keys besides the primary one
+    pub key_slots: usize,
+    pub schema_version: i32,
+    /// Algorithm and cost of the primary password's key derivation
+    pub kdf: Option<String>,
+    /// Credential blobs per encryption format
+    pub formats: BTreeMap<Format, usize>,
+}
+
+pub fn vault_stats(conn: &rusqlite::Connection) -> VaultResult<VaultStats> {
+    let kdf = db::get_metadata(conn, "password_hash")?.and_then(|hash| describe_kdf(&hash).ok());
+    Ok(VaultStats {
+        credentials: db::count_credentials(conn)?,
+        by_type: db::count_credentials_by_type(conn)?,
+        tags: db::get_all_tags_with_counts(conn)?.len(),
+        audit_entries: db::count_audit_logs(conn)?,
+        key_slots: db::get_key_slots(conn)?.len(),
+        schema_version: db::schema::get_schema_version(conn)?,
+        kdf,
+        formats: crypto_info(conn)?.totals,
+    })
+}
+
+/// How often credential `id` was read and copied, from the audit log
+pub fn access_stats(conn: &rusqlite::Connection, id: &str) -> VaultResult<AccessStats> {
+    Ok(db::get_access_stats(conn, id)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{derive_master_key, DataEncryptionKey, KdfParams};
+    use crate::db::{CredentialType, Database};
+    use crate::vault::credential::{create_credential, NewCredential};
+
+    #[test]
+    fn test_vault_stats() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let dek = DataEncryptionKey::from_bytes([0x42u8; 32]);
+        let add = |name: &str, kind: CredentialType, tags: &[&str]| {
+            let tags = tags.iter().map(|t| t.to_string()).collect();
+            let new = NewCredential { tags, ..NewCredential::new(name.to_string(), kind, "s3cret") };
+            create_credential(conn, &dek, new).unwrap();
+        };
+        add("GitHub", CredentialType::Password, &["work", "code"]);
+        add("Bank", CredentialType::Password, &["home"]);
+        add("Stripe", CredentialType::ApiKey, &["work"]);
+
+        let (_, hash) = derive_master_key(b"password", &KdfParams::testing()).unwrap();
+        conn.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES ('password_hash', ?1)", [&hash]).unwrap();
+
+        let stats = vault_stats(conn).unwrap();
+        assert_eq!(stats.credentials, 3);
+        assert_eq!(stats.by_type, [("password".to_string(), 2), ("api_key".to_string(), 1)]);
+        assert_eq!(stats.tags, 3);
+        assert_eq!(stats.key_slots, 0);
+        assert_eq!(stats.schema_version, db::schema::SCHEMA_VERSION);
+        assert_eq!(stats.formats.values().sum::<usize>(), 3);
+        let kdf = stats.kdf.unwrap();
+        assert!(kdf.starts_with("argon2id v19, m="), "{}", kdf);
+        assert!(!kdf.contains('$'));
+    }
+}
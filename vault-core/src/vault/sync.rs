@@ -0,0 +1,372 @@
+//! Remote Sync
+//!
+//! Pushes and pulls the vault file to a remote host over SSH by shelling
+//! out to `scp`. The vault is copied as-is, so secrets stay encrypted in
+//! transit and at rest on the remote. Each side remembers what the remote
+//! and the local credentials looked like at the last sync; a push or pull
+//! that would overwrite changes made since then is refused unless forced.
+
+use std::path::Path;
+use std::process::Command;
+
+use chrono::Local;
+use rusqlite::{Connection, OpenFlags};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::{self, SyncState};
+
+use super::backup::{snapshot_bytes, write_private};
+use super::credential::decrypt_credential;
+use super::{VaultError, VaultResult};
+
+/// Moves the vault file between this machine and a remote
+pub trait Transport {
+    /// Identifies the remote in the sync state table
+    fn remote(&self) -> &str;
+
+    /// Copy the remote vault to `dest`, returning false if there is none yet
+    fn download(&self, dest: &Path) -> VaultResult<bool>;
+
+    fn upload(&self, src: &Path) -> VaultResult<()>;
+}
+
+/// `scp` in batch mode, so key-based authentication is required
+pub struct ScpTransport {
+    remote: String,
+}
+
+impl ScpTransport {
+    /// `remote` is an scp target such as `me@desktop:.local/share/vault/vault.db`.
+    /// One starting with `-` is refused, as scp would read it as an option.
+    pub fn new(remote: impl Into<String>) -> VaultResult<Self> {
+        let remote = remote.into();
+        if remote.starts_with('-') {
+            return Err(VaultError::OperationFailed(format!("Invalid sync remote '{}'", remote)));
+        }
+        Ok(Self { remote })
+    }
+
+    fn scp(&self, from: &str, to: &str) -> VaultResult<std::process::Output> {
+        Command::new("scp")
+            .args(["-q", "-B", "--", from, to])
+            .output()
+            .map_err(|e| VaultError::OperationFailed(format!("Failed to run scp: {}", e)))
+    }
+}
+
+impl Transport for ScpTransport {
+    fn remote(&self) -> &str {
+        &self.remote
+    }
+
+    fn download(&self, dest: &Path) -> VaultResult<bool> {
+        let output = self.scp(&self.remote, &dest.to_string_lossy())?;
+        if output.status.success() {
+            return Ok(true);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("No such file") {
+            return Ok(false);
+        }
+        Err(VaultError::OperationFailed(format!("scp failed: {}", stderr.trim())))
+    }
+
+    fn upload(&self, src: &Path) -> VaultResult<()> {
+        let output = self.scp(&src.to_string_lossy(), &self.remote)?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(VaultError::OperationFailed(format!("scp failed: {}", stderr.trim())))
+    }
+}
+
+#[derive(Debug)]
+pub enum SyncOutcome {
+    UpToDate,
+    Pushed,
+    /// The remote vault, ready to replace the local file once it is locked
+    Pulled(Vec<u8>),
+}
+
+/// Upload the local vault, refusing if the remote changed since the last sync
+pub fn push(conn: &Connection, transport: &dyn Transport, work_dir: &Path, force: bool) -> VaultResult<SyncOutcome> {
+    let state = db::get_sync_state(conn, transport.remote())?;
+    let remote_hash = fetch_remote_hash(transport, work_dir)?;
+    let fingerprint = content_fingerprint(conn)?;
+
+    let remote_unchanged = remote_hash.as_ref() == state.as_ref().map(|s| &s.remote_hash);
+    if remote_hash.is_some() && !remote_unchanged && !force {
+        return Err(VaultError::SyncConflict(
+            "remote changed since the last sync; pull first or use :sync! push".into(),
+        ));
+    }
+    if remote_unchanged && state.as_ref().is_some_and(|s| s.local_fingerprint == fingerprint) {
+        return Ok(SyncOutcome::UpToDate);
+    }
+
+    let snapshot = snapshot_bytes(conn, work_dir)?;
+    let staging = work_dir.join(".sync-push");
+    write_private(&staging, &snapshot)?;
+    let uploaded = transport.upload(&staging);
+    let _ = std::fs::remove_file(&staging);
+    uploaded?;
+
+    record_sync(conn, transport.remote(), sha256_hex(&snapshot), fingerprint)?;
+    Ok(SyncOutcome::Pushed)
+}
+
+/// Download the remote vault, refusing if both sides changed since the last sync
+pub fn pull(
+    conn: &Connection,
+    dek: &DataEncryptionKey,
+    transport: &dyn Transport,
+    work_dir: &Path,
+    force: bool,
+) -> VaultResult<SyncOutcome> {
+    let staging = work_dir.join(".sync-pull");
+    let result = pull_to(conn, dek, transport, &staging, force);
+    let _ = std::fs::remove_file(&staging);
+    result
+}
+
+fn pull_to(
+    conn: &Connection,
+    dek: &DataEncryptionKey,
+    transport: &dyn Transport,
+    staging: &Path,
+    force: bool,
+) -> VaultResult<SyncOutcome> {
+    if !transport.download(staging)? {
+        return Err(VaultError::OperationFailed("Nothing to pull: the remote has no vault".into()));
+    }
+    let remote_hash = sha256_hex(&read(staging)?);
+
+    let state = db::get_sync_state(conn, transport.remote())?;
+    if state.as_ref().is_some_and(|s| s.remote_hash == remote_hash) {
+        return Ok(SyncOutcome::UpToDate);
+    }
+
+    if local_changed(conn, state.as_ref())? && !force {
+        return Err(VaultError::SyncConflict(
+            "both this vault and the remote changed since the last sync; use :sync! pull to take the remote".into(),
+        ));
+    }
+
+    prepare_pulled(staging, dek, transport.remote(), remote_hash)?;
+    Ok(SyncOutcome::Pulled(read(staging)?))
+}
+
+/// Check the pulled vault belongs to this DEK and stamp it with the sync
+/// state, so the next sync from the pulled copy starts from this point
+fn prepare_pulled(path: &Path, dek: &DataEncryptionKey, remote: &str, remote_hash: String) -> VaultResult<()> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+    db::schema::init_schema(&conn)?;
+    db::fields::install(&conn, Some(dek), false)?;
+
+    let credentials = db::get_all_credentials(&conn)?;
+    if let Some(cred) = credentials.first()
+        && decrypt_credential(&conn, dek, cred, false).is_err()
+    {
+        return Err(VaultError::OperationFailed("The remote vault is encrypted with a different key".into()));
+    }
+
+    let fingerprint = content_fingerprint(&conn)?;
+    record_sync(&conn, remote, remote_hash, fingerprint)?;
+    conn.execute_batch("PRAGMA journal_mode = DELETE;")?;
+    Ok(())
+}
+
+fn local_changed(conn: &Connection, state: Option<&SyncState>) -> VaultResult<bool> {
+    match state {
+        Some(state) => Ok(state.local_fingerprint != content_fingerprint(conn)?),
+        None => Ok(!db::get_all_credentials(conn)?.is_empty()),
+    }
+}
+
+fn fetch_remote_hash(transport: &dyn Transport, work_dir: &Path) -> VaultResult<Option<String>> {
+    let staging = work_dir.join(".sync-remote");
+    let result = match transport.download(&staging) {
+        Ok(true) => read(&staging).map(|bytes| Some(sha256_hex(&bytes))),
+        Ok(false) => Ok(None),
+        Err(e) => Err(e),
+    };
+    let _ = std::fs::remove_file(&staging);
+    result
+}
+
+fn record_sync(conn: &Connection, remote: &str, remote_hash: String, local_fingerprint: String) -> VaultResult<()> {
+    let state = SyncState {
+        remote: remote.to_string(),
+        remote_hash,
+        local_fingerprint,
+        synced_at: Local::now(),
+    };
+    Ok(db::save_sync_state(conn, &state)?)
+}
+
+/// Hash of every credential's id and last modification plus the password
+/// hashes, which changes whenever credentials or any master password do
+fn content_fingerprint(conn: &Connection) -> VaultResult<String> {
+    let mut credentials = db::get_all_credentials(conn)?;
+    credentials.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut hasher = Sha256::new();
+    for cred in &credentials {
+        hasher.update(format!("{}|{}\n", cred.id, cred.updated_at.to_rfc3339()));
+    }
+    let password_hash: Option<String> = conn
+        .query_row("SELECT value FROM metadata WHERE key = 'password_hash'", [], |row| row.get(0))
+        .ok();
+    hasher.update(password_hash.unwrap_or_default());
+    for slot in db::get_key_slots(conn)? {
+        hasher.update(format!("\n{}|{}", slot.label, slot.password_hash));
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn read(path: &Path) -> VaultResult<Vec<u8>> {
+    std::fs::read(path).map_err(|e| VaultError::IoError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::db::{CredentialType, Database, DatabaseConfig};
+    use crate::vault::credential;
+
+    /// Stands in for the remote host with a plain file
+    struct FileTransport {
+        path: PathBuf,
+    }
+
+    impl Transport for FileTransport {
+        fn remote(&self) -> &str {
+            "test:vault.db"
+        }
+
+        fn download(&self, dest: &Path) -> VaultResult<bool> {
+            if !self.path.exists() {
+                return Ok(false);
+            }
+            std::fs::copy(&self.path, dest).map_err(|e| VaultError::IoError(e.to_string()))?;
+            Ok(true)
+        }
+
+        fn upload(&self, src: &Path) -> VaultResult<()> {
+            std::fs::copy(src, &self.path).map_err(|e| VaultError::IoError(e.to_string()))?;
+            Ok(())
+        }
+    }
+
+    fn test_dek() -> DataEncryptionKey {
+        DataEncryptionKey::from_bytes([0x42u8; 32])
+    }
+
+    fn add_credential(db: &Database, name: &str) {
+        credential::create_credential(
+            db.conn(),
+            &test_dek(),
+            credential::NewCredential::new(name.to_string(), CredentialType::Password, "secret"),
+        )
+        .unwrap();
+    }
+
+    fn open(path: &Path) -> Database {
+        Database::open(DatabaseConfig::with_path(path)).unwrap()
+    }
+
+    fn names(db: &Database) -> Vec<String> {
+        let mut names: Vec<_> = db::get_all_credentials(db.conn()).unwrap().into_iter().map(|c| c.name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_push_then_pull_on_other_machine() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = FileTransport { path: dir.path().join("remote.db") };
+
+        let laptop = open(&dir.path().join("laptop.db"));
+        add_credential(&laptop, "GitHub");
+        assert!(matches!(push(laptop.conn(), &remote, dir.path(), false).unwrap(), SyncOutcome::Pushed));
+        assert!(matches!(push(laptop.conn(), &remote, dir.path(), false).unwrap(), SyncOutcome::UpToDate));
+
+        let desktop_path = dir.path().join("desktop.db");
+        let desktop = open(&desktop_path);
+        let SyncOutcome::Pulled(bytes) = pull(desktop.conn(), &test_dek(), &remote, dir.path(), false).unwrap() else {
+            panic!("expected a pull");
+        };
+        drop(desktop);
+        crate::vault::backup::replace_vault_file(&bytes, &desktop_path).unwrap();
+
+        let desktop = open(&desktop_path);
+        assert_eq!(names(&desktop), vec!["GitHub"]);
+        assert!(matches!(pull(desktop.conn(), &test_dek(), &remote, dir.path(), false).unwrap(), SyncOutcome::UpToDate));
+    }
+
+    #[test]
+    fn test_push_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = FileTransport { path: dir.path().join("remote.db") };
+
+        let laptop = open(&dir.path().join("laptop.db"));
+        add_credential(&laptop, "GitHub");
+        push(laptop.conn(), &remote, dir.path(), false).unwrap();
+
+        let desktop = open(&dir.path().join("desktop.db"));
+        add_credential(&desktop, "GitLab");
+        let err = push(desktop.conn(), &remote, dir.path(), false).unwrap_err();
+        assert!(matches!(err, VaultError::SyncConflict(_)));
+        assert!(matches!(push(desktop.conn(), &remote, dir.path(), true).unwrap(), SyncOutcome::Pushed));
+    }
+
+    #[test]
+    fn test_pull_conflict_when_both_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = FileTransport { path: dir.path().join("remote.db") };
+
+        let laptop = open(&dir.path().join("laptop.db"));
+        add_credential(&laptop, "GitHub");
+        push(laptop.conn(), &remote, dir.path(), false).unwrap();
+        add_credential(&laptop, "Local only");
+
+        let other = open(&dir.path().join("other.db"));
+        add_credential(&other, "Remote only");
+        push(other.conn(), &remote, dir.path(), true).unwrap();
+
+        let err = pull(laptop.conn(), &test_dek(), &remote, dir.path(), false).unwrap_err();
+        assert!(matches!(err, VaultError::SyncConflict(_)));
+        assert!(matches!(
+            pull(laptop.conn(), &test_dek(), &remote, dir.path(), true).unwrap(),
+            SyncOutcome::Pulled(_)
+        ));
+    }
+
+    #[test]
+    fn test_pull_rejects_other_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = FileTransport { path: dir.path().join("remote.db") };
+
+        let laptop = open(&dir.path().join("laptop.db"));
+        add_credential(&laptop, "GitHub");
+        push(laptop.conn(), &remote, dir.path(), false).unwrap();
+
+        let desktop = open(&dir.path().join("desktop.db"));
+        let other_dek = DataEncryptionKey::from_bytes([0x07u8; 32]);
+        assert!(pull(desktop.conn(), &other_dek, &remote, dir.path(), false).is_err());
+    }
+
+    #[test]
+    fn test_scp_remote_cannot_be_an_option() {
+        assert!(ScpTransport::new("-oProxyCommand=sh:x").is_err());
+        assert_eq!(ScpTransport::new("me@desktop:vault.db").unwrap().remote(), "me@desktop:vault.db");
+    }
+}
@@ -0,0 +1,122 @@
+//! Templates
+//!
+//! Fills `{{ vault "name" "field" }}` placeholders in config files with
+//! decrypted credential fields. The field is optional and defaults to the
+//! secret.
+
+use super::credential::Field;
+use super::{VaultError, VaultResult};
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    pub name: String,
+    pub field: Field,
+}
+
+/// Replace every placeholder with what `lookup` returns for it. Errors
+/// name the line of the offending placeholder.
+pub fn render(
+    template: &str,
+    mut lookup: impl FnMut(&Placeholder) -> VaultResult<String>,
+) -> VaultResult<String> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(OPEN) {
+        output.push_str(&rest[..start]);
+        let line = line_number(template, rest, start);
+        let after_open = &rest[start + OPEN.len()..];
+        let end = after_open
+            .find(CLOSE)
+            .ok_or_else(|| template_error(line, "unclosed '{{'"))?;
+
+        let placeholder = parse_placeholder(&after_open[..end]).map_err(|reason| template_error(line, &reason))?;
+        let value = lookup(&placeholder).map_err(|e| template_error(line, &e.to_string()))?;
+        output.push_str(&value);
+        rest = &after_open[end + CLOSE.len()..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn parse_placeholder(inner: &str) -> Result<Placeholder, String> {
+    let inner = inner.trim();
+    let Some(args) = inner.strip_prefix("vault") else {
+        return Err(format!("unknown placeholder '{{{{ {} }}}}'", inner));
+    };
+
+    let strings = quoted_strings(args)?;
+    let (name, field) = match strings.as_slice() {
+        [name] => (name.clone(), Field::Secret),
+        [name, field] => {
+            let field = Field::parse(field).ok_or_else(|| format!("unknown field '{}'", field))?;
+            (name.clone(), field)
+        }
+        _ => return Err("expected {{ vault \"name\" \"field\" }}".to_string()),
+    };
+    Ok(Placeholder { name, field })
+}
+
+fn quoted_strings(args: &str) -> Result<Vec<String>, String> {
+    let mut strings = Vec::new();
+    let mut rest = args.trim_start();
+    while !rest.is_empty() {
+        let body = rest.strip_prefix('"').ok_or_else(|| format!("expected a quoted string at '{}'", rest))?;
+        let end = body.find('"').ok_or("unterminated string")?;
+        strings.push(body[..end].to_string());
+        rest = body[end + 1..].trim_start();
+    }
+    Ok(strings)
+}
+
+fn line_number(template: &str, rest: &str, offset: usize) -> usize {
+    let position = template.len() - rest.len() + offset;
+    template[..position].matches('\n').count() + 1
+}
+
+fn template_error(line: usize, reason: &str) -> VaultError {
+    VaultError::OperationFailed(format!("template line {}: {}", line, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let template = "user={{ vault \"db\" \"username\" }}\npass={{vault \"db\"}}\nplain {not} a placeholder\n";
+        let rendered = render(template, |p| Ok(format!("{}.{}", p.name, p.field.label()))).unwrap();
+        assert_eq!(rendered, "user=db.username\npass=db.secret\nplain {not} a placeholder\n");
+    }
+
+    #[test]
+    fn test_quoted_names() {
+        let mut found = Vec::new();
+        render("{{ vault \"a b\" \"url\" }} {{ vault \"c\" }}", |p| {
+            found.push(p.clone());
+            Ok(String::new())
+        })
+        .unwrap();
+        assert_eq!(
+            found,
+            vec![
+                Placeholder { name: "a b".to_string(), field: Field::Url },
+                Placeholder { name: "c".to_string(), field: Field::Secret },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_name_the_line() {
+        let err = |template: &str| render(template, |_| Ok(String::new())).unwrap_err().to_string();
+        assert!(err("ok\n{{ vault \"db\" \"colour\" }}").contains("line 2: unknown field 'colour'"));
+        assert!(err("{{ env \"HOME\" }}").contains("line 1: unknown placeholder"));
+        assert!(err("a\nb\n{{ vault \"db\"").contains("line 3: unclosed"));
+        assert!(err("{{ vault db }}").contains("expected a quoted string"));
+
+        let missing = render("{{ vault \"gone\" }}", |_| Err(VaultError::NotFound)).unwrap_err();
+        assert!(missing.to_string().contains("line 1"));
+    }
+}
@@ -0,0 +1,195 @@
+//! Credential Timeline
+//!
+//! Chronological provenance for a single credential, built from its audit
+//! events. Update events carry the list of changed fields, so edits and
+//! password rotations show up as history entries.
+
+use chrono::{DateTime, Local};
+
+use crate::db::{AuditAction, AuditLog};
+
+use super::VaultResult;
+
+/// Prefix of the details recorded on update events
+pub const CHANGED_PREFIX: &str = "Changed: ";
+
+/// Prefix of the details recorded when another credential is merged in
+pub const MERGED_PREFIX: &str = "Merged: ";
+
+/// Field name recorded when the secret changes
+pub const SECRET_FIELD: &str = "password";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineEntry {
+    /// Time of the latest event folded into this entry
+    pub timestamp: DateTime<Local>,
+    pub action: AuditAction,
+    pub summary: String,
+    pub count: usize,
+}
+
+impl TimelineEntry {
+    pub fn label(&self) -> String {
+        match self.count {
+            1 => self.summary.clone(),
+            n => format!("{} {}×", self.summary, n),
+        }
+    }
+}
+
+/// Load the timeline for a credential, oldest event first
+pub fn credential_timeline(conn: &rusqlite::Connection, credential_id: &str) -> VaultResult<Vec<TimelineEntry>> {
+    let logs = super::audit::get_credential_logs(conn, credential_id)?;
+    Ok(build_timeline(logs))
+}
+
+/// Format the details for an update event
+pub fn changed_fields_details(fields: &[&str]) -> Option<String> {
+    (!fields.is_empty()).then(|| format!("{}{}", CHANGED_PREFIX, fields.join(", ")))
+}
+
+/// Format the details for a merge, naming the absorbed credential and the
+/// fields it contributed
+pub fn merged_details(from: &str, fields: &[&str]) -> String {
+    match changed_fields_details(fields) {
+        Some(changed) => format!("{}{}; {}", MERGED_PREFIX, from, changed),
+        None => format!("{}{}", MERGED_PREFIX, from),
+    }
+}
+
+/// Merge audit events into timeline entries. Consecutive reads and copies
+/// of the same kind collapse into one entry with a count.
+pub fn build_timeline(mut logs: Vec<AuditLog>) -> Vec<TimelineEntry> {
+    logs.sort_by_key(|log| log.timestamp);
+
+    let mut entries: Vec<TimelineEntry> = Vec::new();
+    for log in logs {
+        let summary = describe(&log);
+
+        if let Some(last) = entries.last_mut()
+            && is_repeatable(log.action)
+            && last.action == log.action
+            && last.summary == summary
+        {
+            last.count += 1;
+            last.timestamp = log.timestamp;
+            continue;
+        }
+
+        entries.push(TimelineEntry { timestamp: log.timestamp, action: log.action, summary, count: 1 });
+    }
+    entries
+}
+
+fn is_repeatable(action: AuditAction) -> bool {
+    matches!(action, AuditAction::Read | AuditAction::Copy)
+}
+
+fn describe(log: &AuditLog) -> String {
+    match log.action {
+        AuditAction::Create => "Created".to_string(),
+        AuditAction::Update => describe_update(log.details.as_deref()),
+        AuditAction::Delete => "Deleted".to_string(),
+        AuditAction::Read => "Viewed".to_string(),
+        AuditAction::Copy => describe_copy(log.details.as_deref()),
+        AuditAction::Export => "Exported".to_string(),
+        AuditAction::Import => "Imported".to_string(),
+        _ => log.action.as_str().to_string(),
+    }
+}
+
+fn describe_update(details: Option<&str>) -> String {
+    if let Some(merged) = details.and_then(|d| d.strip_prefix(MERGED_PREFIX)) {
+        return match merged.split_once("; ") {
+            Some((from, changed)) => format!("Merged with {} ({})", from, describe_update(Some(changed)).to_lowercase()),
+            None => format!("Merged with {}", merged),
+        };
+    }
+    let Some(fields) = details.and_then(|d| d.strip_prefix(CHANGED_PREFIX)) else {
+        return "Edited".to_string();
+    };
+
+    let (secret, others): (Vec<&str>, Vec<&str>) = fields.split(", ").partition(|f| *f == SECRET_FIELD);
+
+    match (secret.is_empty(), others.is_empty()) {
+        (false, true) => "Password rotated".to_string(),
+        (false, false) => format!("Password rotated, edited {}", others.join(", ")),
+        (true, _) => format!("Edited {}", others.join(", ")),
+    }
+}
+
+fn describe_copy(details: Option<&str>) -> String {
+    match details {
+        Some(what) => format!("Copied {}", what.to_lowercase()),
+        None => "Copied".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn log(action: AuditAction, details: Option<&str>, minutes: i64) -> AuditLog {
+        let base = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().with_timezone(&Local);
+        AuditLog {
+            id: minutes,
+            timestamp: base + Duration::minutes(minutes),
+            action,
+            credential_id: Some("c1".to_string()),
+            credential_name: Some("GitHub".to_string()),
+            username: None,
+            details: details.map(String::from),
+            hmac: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_chronological_order() {
+        let timeline = build_timeline(vec![
+            log(AuditAction::Update, None, 5),
+            log(AuditAction::Create, None, 0),
+        ]);
+        assert_eq!(timeline[0].summary, "Created");
+        assert_eq!(timeline[1].summary, "Edited");
+    }
+
+    #[test]
+    fn test_collapses_repeated_copies() {
+        let timeline = build_timeline(vec![
+            log(AuditAction::Copy, Some("Secret"), 1),
+            log(AuditAction::Copy, Some("Secret"), 2),
+            log(AuditAction::Copy, Some("Secret"), 3),
+            log(AuditAction::Copy, Some("Username"), 4),
+        ]);
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(timeline[0].label(), "Copied secret 3×");
+        assert_eq!(timeline[0].timestamp, log(AuditAction::Copy, None, 3).timestamp);
+        assert_eq!(timeline[1].label(), "Copied username");
+    }
+
+    #[test]
+    fn test_updates_not_collapsed() {
+        let timeline = build_timeline(vec![
+            log(AuditAction::Update, None, 1),
+            log(AuditAction::Update, None, 2),
+        ]);
+        assert_eq!(timeline.len(), 2);
+    }
+
+    #[test]
+    fn test_describe_update_fields() {
+        assert_eq!(describe_update(Some("Changed: password")), "Password rotated");
+        assert_eq!(describe_update(Some("Changed: url, tags")), "Edited url, tags");
+        assert_eq!(describe_update(Some("Changed: name, password")), "Password rotated, edited name");
+        assert_eq!(describe_update(None), "Edited");
+        assert_eq!(describe_update(Some(&merged_details("Github", &["tags"]))), "Merged with Github (edited tags)");
+        assert_eq!(describe_update(Some(&merged_details("Github", &[]))), "Merged with Github");
+    }
+
+    #[test]
+    fn test_changed_fields_details() {
+        assert_eq!(changed_fields_details(&["url", "password"]).as_deref(), Some("Changed: url, password"));
+        assert_eq!(changed_fields_details(&[]), None);
+    }
+}